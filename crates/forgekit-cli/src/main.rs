@@ -1,9 +1,74 @@
 //! ForgeKit CLI - Command line interface for building .mox applications
 
 use anyhow::Result;
-use clap::{Parser, Subcommand};
-use forgekit_core::{package_manager::PackageManager, templates::TemplateType, ForgeKit};
-use std::path::PathBuf;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use forgekit_core::{
+    affected,
+    analytics::{self, AnalyticsCollector},
+    artifacts::{self, ArtifactDiff},
+    audit::DependencyAuditor,
+    audit_log::{self, AuditEntry, AuditLog},
+    batch::{BatchCommand, BatchOptions},
+    build_log::BuildLog,
+    builder::{BuildInfo, BuildOptions},
+    cancel::CancellationToken,
+    cicd::{CICDGenerator, CiOptions, CiProvider},
+    command_policy::{self, Approval, CommandPolicy, Decision},
+    config::{
+        AuditSink, CompilerCacheMode, GlobalConfig, ProjectConfig, ProjectKind, Strictness,
+        ToolchainConfig,
+    },
+    crash, credentials,
+    dedup::Deduplicator,
+    dev_env,
+    diagnostics::{self, CheckOptions, Diagnostic, Severity},
+    dry_run::DryRun,
+    env_manager::EnvManager,
+    error::ForgeKitError,
+    generate::HookPoint,
+    git_hooks::GitHooks,
+    installer::{self, InstallOptions},
+    lint::Linter,
+    lock::LockOptions,
+    logs::{LogFilter, LogLevel, LogRouter},
+    output::OutputFormat,
+    package_diff::{self, ChangeKind, PackageDiff},
+    package_manager::{InstallProgress, InstallProgressCallback, PackageManager},
+    permissions::Permission,
+    platform,
+    plugin_store::PluginStore,
+    policy::{self, Policy, PolicyStage, PolicyViolation, POLICY_FILE},
+    ports,
+    project::{self, InitOptions, License, Vcs},
+    redact::Redactor,
+    registry::{
+        mock::{self, MockRegistry},
+        DownloadProgress, ProgressCallback, RegistryClient, RegistryConfig,
+    },
+    registry_server::{self, RegistryServer, RegistryServerConfig},
+    runner::{RestartPolicy, RunEvent, RunOptions, Runner},
+    scripts::{self, ScriptFile, SyncStatus},
+    semver_check,
+    store::PackageStore,
+    symbols,
+    telemetry::{TelemetryEvent, TelemetryStore},
+    templates::TemplateType,
+    testing::{TestKind, TestRunner},
+    toolchain,
+    update_pr::DependencyUpdater,
+    upload::{self, UploadOptions, UploadProgress, UploadProgressCallback},
+    watch::{BuildWatcher, WatchOptions, WatchStatus},
+    workspace::Workspace,
+    ForgeKit,
+};
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+mod output;
+mod tui;
+
+use output::{say, Output};
 
 #[derive(Parser)]
 #[command(name = "forgekit")]
@@ -12,6 +77,185 @@ use std::path::PathBuf;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Output format (text, json, markdown)
+    #[arg(long, global = true, default_value = "text")]
+    format: OutputFormat,
+    /// Do not lock the project against concurrent forgekit invocations
+    #[arg(long, global = true)]
+    no_lock: bool,
+    /// Approval token allowing a command `[command_policy]` restricts
+    /// (defaults to FORGEKIT_APPROVAL)
+    #[arg(long, global = true)]
+    approval: Option<String>,
+}
+
+/// Filtering of app log lines
+#[derive(clap::Args)]
+struct LogArgs {
+    /// Only show lines of this level or above (trace, debug, info, warn, error)
+    #[arg(long)]
+    level: Option<LogLevel>,
+    /// Highlight matches of a regex
+    #[arg(long)]
+    highlight: Option<regex::Regex>,
+    /// Show only these fields of JSON log lines
+    #[arg(long = "field", value_delimiter = ',')]
+    fields: Vec<String>,
+}
+
+impl LogArgs {
+    fn filter(self) -> LogFilter {
+        LogFilter {
+            level: self.level,
+            highlight: self.highlight,
+            fields: self.fields,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum CrashCommands {
+    /// List crash reports of the app, newest first
+    List {
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+    /// Print a crash report as a symbolicated stack trace
+    Symbolicate {
+        /// Report file (defaults to the newest report)
+        report: Option<PathBuf>,
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+    /// Add the crash handler to an existing project
+    Install {
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum SymbolsCommands {
+    /// Upload the debug symbols of a release to the symbol server
+    Upload {
+        /// Release version (defaults to the project version)
+        #[arg(long)]
+        version: Option<String>,
+        /// Symbol server URL (defaults to [build.symbols] server)
+        #[arg(long)]
+        server: Option<String>,
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum PackageCommands {
+    /// Compare two packages entry by entry
+    Diff {
+        /// Older package
+        old: PathBuf,
+        /// Newer package
+        new: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum ArtifactsCommands {
+    /// List the packages kept in the artifact history
+    List {
+        /// Only list this profile (defaults to all profiles)
+        #[arg(long)]
+        profile: Option<String>,
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+    /// Compare two artifacts, each given by id or version
+    Diff {
+        /// Older artifact
+        from: String,
+        /// Newer artifact
+        to: String,
+        /// Profile to look in (defaults to all profiles)
+        #[arg(long)]
+        profile: Option<String>,
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+    /// Remove old artifacts
+    Clean {
+        /// Newest artifacts kept per profile
+        #[arg(long, default_value_t = 0)]
+        keep: usize,
+        /// Only clean this profile (defaults to all profiles)
+        #[arg(long)]
+        profile: Option<String>,
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+    /// Copy an earlier artifact back to the package output path
+    Restore {
+        /// Artifact id or version; the newest artifact of a version is used
+        artifact: String,
+        /// Profile to look in (defaults to all profiles)
+        #[arg(long)]
+        profile: Option<String>,
+        /// Where to copy the package (defaults to where `forgekit package` writes it)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum TelemetryCommands {
+    /// Show whether telemetry is enabled and how many events are stored
+    Status,
+    /// Opt in to anonymous usage statistics
+    Enable {
+        /// Endpoint collected events are uploaded to
+        #[arg(long)]
+        endpoint: Option<String>,
+    },
+    /// Opt out and delete the collected events
+    Disable,
+    /// Show the collected statistics
+    Show,
+    /// Upload the collected events to the configured endpoint
+    Upload,
+}
+
+#[derive(Subcommand)]
+enum RegistryCommands {
+    /// Serve a registry directory over HTTP for clients to publish to and
+    /// download from
+    Serve {
+        /// Directory holding the index and the package archives
+        #[arg(long, default_value = "registry")]
+        dir: PathBuf,
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1")]
+        bind: std::net::IpAddr,
+        /// Port to listen on
+        #[arg(long, default_value_t = registry_server::DEFAULT_PORT)]
+        port: u16,
+        /// Registry to mirror packages from, e.g. https://github.com
+        #[arg(long)]
+        upstream: Option<String>,
+        /// Token publishers must send (defaults to $FORGEKIT_REGISTRY_TOKEN);
+        /// publishing is disabled without one
+        #[arg(long)]
+        token: Option<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -27,7 +271,35 @@ enum CacheCommands {
         /// Path to the project (defaults to current directory)
         #[arg(short, long)]
         path: Option<PathBuf>,
+        /// Break the size down by project, profile, target and toolchain
+        #[arg(long)]
+        by_namespace: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Print forgekit.toml with extended layers and environment overrides applied
+    Resolve {
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+        /// Environment profile whose `[env.<name>]` overrides are applied
+        #[arg(long)]
+        env: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum SchemaCommands {
+    /// Print the JSON Schema of forgekit.toml
+    Dump {
+        /// Write the schema to this file instead of printing it
+        #[arg(short, long)]
+        output: Option<PathBuf>,
     },
+    /// Write the schema to the ForgeKit config directory for editors to use
+    Install,
 }
 
 #[derive(Subcommand)]
@@ -51,455 +323,4487 @@ enum EnvCommands {
         #[arg(short, long)]
         path: Option<PathBuf>,
     },
+    /// Write a reproducible development environment pinning the toolchain,
+    /// system packages and ForgeKit
+    #[command(group(clap::ArgGroup::new("kind").required(true).multiple(true)))]
+    Scaffold {
+        /// Write a Nix flake (flake.nix)
+        #[arg(long, group = "kind")]
+        nix: bool,
+        /// Write a VS Code devcontainer (.devcontainer/devcontainer.json)
+        #[arg(long, group = "kind")]
+        devcontainer: bool,
+        /// Replace existing files
+        #[arg(long)]
+        force: bool,
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
 }
 
 #[derive(Subcommand)]
-enum Commands {
-    /// Create a new .mox application
-    New {
-        /// Name of the new project
+enum ApprovalCommands {
+    /// Generate a key for signing approval tokens and print the public key
+    /// to add to `[command_policy] approval_keys`
+    Keygen {
+        /// File to write the private key to
+        key: PathBuf,
+    },
+    /// Issue a token allowing a restricted command
+    Issue {
+        /// Command to allow, like `publish` or `secret *`
+        command: String,
+        /// Environment the command may run in (any when omitted)
+        #[arg(long)]
+        env: Option<String>,
+        /// How long the token is valid, like 30m or 1h
+        #[arg(long, default_value = "1h")]
+        expires_in: String,
+        /// Private key to sign with
+        #[arg(long)]
+        key: PathBuf,
+        /// Who approves (defaults to the current user)
+        #[arg(long)]
+        approver: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum AuditLogCommands {
+    /// Show recorded commands, oldest first
+    Show {
+        /// Only show commands run within this age, like 7d, 12h or 2w
+        #[arg(long)]
+        since: Option<String>,
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum SecretCommands {
+    /// Store a secret that env files refer to as `secret:<name>`
+    Set {
+        /// Secret name
         name: String,
-        /// Path where to create the project (defaults to current directory)
+        /// Secret value (prompted for when omitted)
+        value: Option<String>,
+    },
+    /// Remove a stored secret
+    Delete {
+        /// Secret name
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum TemplateCommands {
+    /// Merge fixes from the current revision of the project's template
+    Update {
+        /// Path to the project (defaults to current directory)
         #[arg(short, long)]
         path: Option<PathBuf>,
-        /// Template type to use
-        #[arg(short, long, default_value = "basic")]
-        template: String,
+        /// Show what would change without writing anything
+        #[arg(long)]
+        dry_run: bool,
     },
-    /// Build the current project
-    Build {
+}
+
+#[derive(Subcommand)]
+enum BuildsCommands {
+    /// List recorded builds, newest first
+    List {
         /// Path to the project (defaults to current directory)
         #[arg(short, long)]
         path: Option<PathBuf>,
     },
-    /// Package the project into a .mox file
-    Package {
+    /// Show the record and full log of a build
+    Show {
+        /// Build identifier, from `forgekit builds list`
+        id: String,
         /// Path to the project (defaults to current directory)
         #[arg(short, long)]
         path: Option<PathBuf>,
     },
-    /// Build and package the project
-    BuildPackage {
+}
+
+#[derive(Subcommand)]
+enum FeedCommands {
+    /// Add the project's .mox to the signed update feed devices poll
+    Generate {
         /// Path to the project (defaults to current directory)
         #[arg(short, long)]
         path: Option<PathBuf>,
+        /// Channel to publish to (defaults to the one the package was built for)
+        #[arg(long)]
+        channel: Option<String>,
+        /// Download URL of the package (defaults to next to [ota] feed_url)
+        #[arg(long)]
+        url: Option<String>,
+        /// Feed file to update (defaults to feed.json next to the .mox)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
     },
-    /// Run the project locally (for testing)
-    Run {
+    /// Create the key update feeds are signed with
+    Keygen {
         /// Path to the project (defaults to current directory)
         #[arg(short, long)]
         path: Option<PathBuf>,
     },
-    /// Add a dependency to the project
-    Add {
-        /// Package name to add
-        package: String,
-        /// Version to install
-        #[arg(short, long, default_value = "*")]
-        version: String,
+}
+
+#[derive(Subcommand)]
+enum GenerateCommands {
+    /// Add a subcommand to a CLI tool and dispatch to it from main.rs
+    Command {
+        /// Name of the command
+        name: String,
         /// Path to the project (defaults to current directory)
         #[arg(short, long)]
         path: Option<PathBuf>,
+        /// Show what would be written without writing anything
+        #[arg(long)]
+        dry_run: bool,
     },
-    /// Remove a dependency from the project
-    Remove {
-        /// Package name to remove
-        package: String,
+    /// Add a SQL migration
+    Migration {
+        /// Name of the migration
+        name: String,
         /// Path to the project (defaults to current directory)
         #[arg(short, long)]
         path: Option<PathBuf>,
+        /// Show what would be written without writing anything
+        #[arg(long)]
+        dry_run: bool,
     },
-    /// Update project dependencies
-    Update {
+    /// Add a plugin hook run at a build step
+    PluginHook {
+        /// Name of the hook
+        name: String,
+        /// Build step the hook runs at (pre-build, post-build, package)
+        #[arg(long, default_value = "pre-build")]
+        on: HookPoint,
         /// Path to the project (defaults to current directory)
         #[arg(short, long)]
         path: Option<PathBuf>,
+        /// Show what would be written without writing anything
+        #[arg(long)]
+        dry_run: bool,
     },
-    /// Search for available packages
+    /// Add a UI layout and the view module loading it
+    UiView {
+        /// Name of the view
+        name: String,
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+        /// Show what would be written without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum StoreCommands {
+    /// Remove packages no longer used by any project
+    Gc,
+    /// Show the global package store location
+    Path,
+}
+
+/// Where `forgekit plugin` installs plugins
+#[derive(clap::Args)]
+struct PluginScopeArgs {
+    /// Use the plugins installed for every project instead of the project's
+    #[arg(short, long, conflicts_with = "path")]
+    global: bool,
+    /// Path to the project (defaults to current directory)
+    #[arg(short, long)]
+    path: Option<PathBuf>,
+}
+
+impl PluginScopeArgs {
+    fn store(self) -> Result<PluginStore> {
+        if self.global {
+            return Ok(PluginStore::global()?);
+        }
+        let project_path = match self.path {
+            Some(p) => p,
+            None => std::env::current_dir()?,
+        };
+        Ok(PluginStore::for_project(&project_path)?)
+    }
+}
+
+#[derive(Subcommand)]
+enum PluginCommands {
+    /// Search the registry for plugins
     Search {
-        /// Search query
+        /// Text to find in plugin names and descriptions
+        #[arg(default_value = "")]
         query: String,
     },
-    /// List available templates
-    Templates,
-    /// Validate the current project
-    Validate {
+    /// Install a plugin from the registry
+    Install {
+        /// Plugin name
+        name: String,
+        /// Version to install (defaults to the newest this ForgeKit can use)
+        #[arg(long)]
+        version: Option<String>,
+        #[command(flatten)]
+        scope: PluginScopeArgs,
+    },
+    /// List installed plugins
+    List {
+        #[command(flatten)]
+        scope: PluginScopeArgs,
+    },
+    /// Load an installed plugin again
+    Enable {
+        /// Plugin name
+        name: String,
+        #[command(flatten)]
+        scope: PluginScopeArgs,
+    },
+    /// Keep an installed plugin from being loaded
+    Disable {
+        /// Plugin name
+        name: String,
+        #[command(flatten)]
+        scope: PluginScopeArgs,
+    },
+    /// Update installed plugins to the newest versions this ForgeKit can use
+    Update {
+        /// Plugin to update (defaults to all)
+        name: Option<String>,
+        #[command(flatten)]
+        scope: PluginScopeArgs,
+    },
+    /// Remove an installed plugin
+    Uninstall {
+        /// Plugin name
+        name: String,
+        #[command(flatten)]
+        scope: PluginScopeArgs,
+    },
+}
+
+#[derive(Subcommand)]
+enum HooksCommands {
+    /// Install git hooks configured in [git_hooks]
+    Install {
         /// Path to the project (defaults to current directory)
         #[arg(short, long)]
         path: Option<PathBuf>,
+        /// Replace existing hooks not written by ForgeKit
+        #[arg(long)]
+        force: bool,
     },
-    /// Manage environment variables
-    Env {
-        #[command(subcommand)]
-        command: EnvCommands,
+    /// Remove git hooks written by ForgeKit
+    Uninstall {
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
     },
-    /// Run project tests
-    Test {
+    /// Run the stages of a git hook (called by the installed hooks)
+    Run {
+        /// Hook name (pre-commit, pre-push)
+        hook: String,
         /// Path to the project (defaults to current directory)
         #[arg(short, long)]
         path: Option<PathBuf>,
-        /// Generate coverage report
-        #[arg(long)]
-        coverage: bool,
-        /// Output format (text, json)
-        #[arg(short, long, default_value = "text")]
-        format: String,
     },
-    /// Generate test scaffolding
-    TestGenerate {
-        /// Name of the test
-        name: String,
+}
+
+#[derive(Subcommand)]
+enum ToolchainCommands {
+    /// Check the toolchain, its components and sysroot
+    Status {
         /// Path to the project (defaults to current directory)
         #[arg(short, long)]
         path: Option<PathBuf>,
     },
-    /// Manage build cache
-    Cache {
-        #[command(subcommand)]
-        command: CacheCommands,
+    /// Install the toolchain and components with rustup
+    Install {
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+    /// Write the ledokoz target spec into the project
+    Spec {
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+    /// Pin the project's Rust toolchain and write rust-toolchain.toml
+    Pin {
+        /// Toolchain to pin, like `1.78` or `nightly-2024-05-01` (defaults to the current pin)
+        channel: Option<String>,
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
     },
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    // Initialize logging
-    tracing_subscriber::fmt::init();
-
-    let cli = Cli::parse();
-
-    match cli.command {
-        Commands::New {
-            name,
-            path,
-            template,
-        } => {
-            let project_path = path.unwrap_or_else(|| PathBuf::from(&name));
-            let forgekit = ForgeKit::new();
-
-            // Parse template type
-            let template_type = match template.as_str() {
-                "basic" => TemplateType::Basic,
-                "gui" => TemplateType::Gui,
-                "cli" => TemplateType::Cli,
-                "service" => TemplateType::Service,
-                "plugin" => TemplateType::Plugin,
-                _ => {
-                    eprintln!("Unknown template: {}. Using basic template.", template);
-                    TemplateType::Basic
-                }
-            };
+#[derive(Subcommand)]
+enum DaemonCommands {
+    /// Run the daemon in the foreground until it is stopped
+    Start {
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+    /// Stop a running daemon
+    Stop {
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+    /// Check whether a daemon is running
+    Status {
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+    /// Send a JSON-RPC request to a running daemon
+    Call {
+        /// Method name, e.g. project/diagnostics
+        method: String,
+        /// Method parameters as JSON
+        #[arg(long)]
+        params: Option<String>,
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Create a new .mox application
+    New {
+        /// Name of the new project
+        name: String,
+        /// Path where to create the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+        /// Template type to use
+        #[arg(short, long, default_value = "basic")]
+        template: String,
+        /// License written to LICENSE (mit, bsd-3-clause, isc, unlicense)
+        #[arg(long)]
+        license: Option<License>,
+        /// Version control to set up (git, none)
+        #[arg(long, default_value = "git")]
+        vcs: Vcs,
+        /// Ask for each option and preview the files before creating anything
+        #[arg(short, long)]
+        interactive: bool,
+    },
+    /// Rename the project, updating manifests, scripts and CI files
+    Rename {
+        /// New name of the project
+        new_name: String,
+        /// Show what would change without touching disk
+        #[arg(long)]
+        dry_run: bool,
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+    /// Build the current project
+    Build {
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+        /// Compiler cache (auto, sccache, builtin, off); defaults to the global config
+        #[arg(long)]
+        compiler_cache: Option<CompilerCacheMode>,
+        /// Rebuild whenever project files change
+        #[arg(long)]
+        watch: bool,
+        /// Package the project after each successful build (with --watch)
+        #[arg(long, requires = "watch")]
+        package: bool,
+        /// Environment profile passed to the build (loads .env.<name>)
+        #[arg(long)]
+        env: Option<String>,
+        /// Build for a host-compatible triple instead of ledokoz, for local testing
+        #[arg(long)]
+        host: bool,
+        /// Run a recorded build again with the same flags and environment
+        #[arg(long, value_name = "ID", conflicts_with = "watch")]
+        replay: Option<String>,
+    },
+    /// Inspect the logs of past builds
+    Builds {
+        #[command(subcommand)]
+        command: BuildsCommands,
+    },
+    /// Package the project into a .mox file (or .moxlib for libraries)
+    #[command(args_conflicts_with_subcommands = true)]
+    Package {
+        #[command(subcommand)]
+        command: Option<PackageCommands>,
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+        /// Only bundle these locales, e.g. `fr,de` (defaults to all)
+        #[arg(long, value_delimiter = ',')]
+        locales: Vec<String>,
+        /// Update channel to build for (defaults to the one in [ota])
+        #[arg(long)]
+        channel: Option<String>,
+        /// Show what would change without touching disk or network
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Install a .mox into a directory the way the device installer would
+    Install {
+        /// Package to install
+        package: PathBuf,
+        /// Directory standing in for the device root
+        #[arg(long)]
+        prefix: PathBuf,
+        /// Permissions to accept, e.g. `network,camera` (defaults to all requested)
+        #[arg(long, value_delimiter = ',')]
+        grant: Option<Vec<Permission>>,
+        /// Skip the package's pre_install and post_install hooks
+        #[arg(long)]
+        no_hooks: bool,
+    },
+    /// Remove an app installed with `forgekit install`
+    Uninstall {
+        /// Name of the installed app
+        name: String,
+        /// Directory the app was installed into
+        #[arg(long)]
+        prefix: PathBuf,
+        /// Also delete the app data directory
+        #[arg(long)]
+        purge: bool,
+    },
+    /// Package a library and publish it to the local registry index
+    Publish {
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+        /// Upload to the registry server logged in to instead
+        #[arg(long)]
+        remote: bool,
+        /// Refuse to publish breaking API changes without a major version bump
+        #[arg(long)]
+        check_semver: bool,
+        /// MiB sent per request when uploading
+        #[arg(long, default_value_t = upload::DEFAULT_CHUNK_SIZE / 1024 / 1024)]
+        chunk_size: u64,
+        /// Largest upload rate in KiB/s
+        #[arg(long)]
+        limit_rate: Option<u64>,
+    },
+    /// Build and package the project
+    BuildPackage {
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+    /// Run the project locally (for testing)
+    Run {
+        /// Restart policy (never, on-failure, always)
+        #[arg(long, default_value = "never")]
+        restart: RestartPolicy,
+        /// Maximum number of restarts
+        #[arg(long, default_value_t = 5)]
+        max_retries: u32,
+        /// Shell command that succeeds once the app is ready
+        #[arg(long)]
+        health_cmd: Option<String>,
+        /// Move services whose port is taken to the next free port
+        #[arg(long)]
+        auto_ports: bool,
+        /// Environment profile to build and run with (loads .env.<name>)
+        #[arg(long)]
+        env: Option<String>,
+        /// Build for a host-compatible triple instead of ledokoz
+        #[arg(long)]
+        host: bool,
+        #[command(flatten)]
+        logs: LogArgs,
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+    /// Serve assets and docs over HTTP and recompile UI layouts as they change
+    Dev {
+        /// Port to listen on
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+        /// Move to the next free port when the port is taken
+        #[arg(long)]
+        auto_ports: bool,
+        /// Address to listen on (0.0.0.0 to allow other devices on the LAN)
+        #[arg(long, default_value = "127.0.0.1")]
+        bind: std::net::IpAddr,
+        /// Host name to advertise in the URL and certificate
+        #[arg(long)]
+        host: Option<String>,
+        /// Serve HTTPS with a self-signed development certificate
+        #[arg(long)]
+        https: bool,
+        /// Serve and rebuild every ForgeKit project of the workspace at the
+        /// path, each on its own port starting at --port
+        #[arg(long)]
+        workspace: bool,
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+    /// Show the logs of the app started with `forgekit run`
+    Logs {
+        /// Keep printing lines as the running app writes them
+        #[arg(short, long)]
+        follow: bool,
+        #[command(flatten)]
+        logs: LogArgs,
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+    /// Add a dependency to the project
+    Add {
+        /// Package name to add
+        package: String,
+        /// Version to install
+        #[arg(short, long, default_value = "*")]
+        version: String,
+        /// Show what would change without touching disk or network
+        #[arg(long)]
+        dry_run: bool,
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+    /// Remove a dependency from the project
+    Remove {
+        /// Package name to remove
+        package: String,
+        /// Show what would change without touching disk or network
+        #[arg(long)]
+        dry_run: bool,
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+    /// Update project dependencies
+    Update {
+        /// Show what would change without touching disk or network
+        #[arg(long)]
+        dry_run: bool,
+        /// Open a pull request per outdated dependency instead of updating
+        /// in place
+        #[arg(long, conflicts_with = "dry_run")]
+        create_pr: bool,
+        /// With --create-pr, bump all outdated dependencies in one pull request
+        #[arg(long, requires = "create_pr")]
+        group: bool,
+        /// With --create-pr, git remote to push branches to
+        #[arg(long, default_value = "origin", requires = "create_pr")]
+        remote: String,
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+    /// Vendor all locked dependencies for offline builds
+    Vendor {
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+    /// Report duplicate dependency versions across workspace members
+    Dedup {
+        /// Path to the workspace root (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+        /// Rewrite member forgekit.toml files to the suggested versions
+        #[arg(long)]
+        apply: bool,
+    },
+    /// Run build, test, validate or package over many projects concurrently
+    Batch {
+        /// Command to run (build, test, validate, package)
+        command: BatchCommand,
+        /// Project roots
+        #[arg(long, num_args = 1.., required = true)]
+        projects: Vec<PathBuf>,
+        /// Maximum number of projects processed at the same time
+        #[arg(short, long)]
+        jobs: Option<usize>,
+        /// Cargo target directory shared by all projects (defaults to target/batch)
+        #[arg(long)]
+        target_dir: Option<PathBuf>,
+    },
+    /// List workspace members affected by changes since a git ref
+    Affected {
+        /// Git ref to compare the working tree against, e.g. origin/main
+        #[arg(long)]
+        since: String,
+        /// Path to the workspace root (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+        /// Also run a command for the affected members (build, test, validate, package)
+        #[arg(long)]
+        run: Option<BatchCommand>,
+        /// Maximum number of members processed at the same time
+        #[arg(short, long)]
+        jobs: Option<usize>,
+        /// Cargo target directory shared by the members
+        #[arg(long)]
+        target_dir: Option<PathBuf>,
+    },
+    /// Search for available packages
+    Search {
+        /// Search query
+        query: String,
+    },
+    /// Find, install and manage plugins from the registry
+    Plugin {
+        #[command(subcommand)]
+        command: PluginCommands,
+    },
+    /// Show detailed information about a package
+    Info {
+        /// Package name
+        package: String,
+        /// Print the full README instead of a preview
+        #[arg(long)]
+        readme: bool,
+    },
+    /// List available templates
+    Templates,
+    /// Keep a project in sync with the template it was generated from
+    Template {
+        #[command(subcommand)]
+        command: TemplateCommands,
+    },
+    /// Add a command, migration, plugin hook or UI view to the project
+    Generate {
+        #[command(subcommand)]
+        command: GenerateCommands,
+    },
+    /// Validate the current project
+    Validate {
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+        /// Treat unknown forgekit.toml keys as errors instead of warnings
+        #[arg(long)]
+        strict: bool,
+        /// Fix what can be fixed, like missing license headers, before validating
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Generate launcher icons, entry and splash from the [appmeta] icon
+    Appmeta {
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+    /// Bundle the store listing next to the .mox
+    Appstore {
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+        /// Only validate the listing, without writing the bundle
+        #[arg(long)]
+        check: bool,
+    },
+    /// Manage the over-the-air update feed
+    Feed {
+        #[command(subcommand)]
+        command: FeedCommands,
+    },
+    /// Audit dependencies for known vulnerabilities
+    Audit {
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+        /// Also count unsafe code and FFI in the project and each dependency
+        #[arg(long = "unsafe")]
+        unsafe_code: bool,
+    },
+    /// Issue approval tokens for commands `[command_policy]` restricts
+    Approval {
+        #[command(subcommand)]
+        command: ApprovalCommands,
+    },
+    /// Inspect the audit trail of commands that publish, install or change
+    /// credentials
+    AuditLog {
+        #[command(subcommand)]
+        command: AuditLogCommands,
+    },
+    /// Check dependencies against the supply-chain policy in forgekit-policy.toml
+    Policy {
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+        /// Also apply the rules for release builds
+        #[arg(long)]
+        release: bool,
+    },
+    /// List TODO, FIXME and HACK markers with their age from git blame, and
+    /// misspelled words in comments
+    Todos {
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+    /// Grade project health from A to F with recommendations
+    Health {
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+    /// Report doc coverage of the public API and its changes since the last release
+    Api {
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+        /// Git revision of the previous release (defaults to the latest tag)
+        #[arg(long)]
+        against: Option<String>,
+        /// List every undocumented public item
+        #[arg(long)]
+        undocumented: bool,
+    },
+    /// List dependencies with newer versions available
+    Outdated {
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+    /// Run clippy, rustfmt and project validation
+    Lint {
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+        /// Apply clippy suggestions and format files
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Run lint, validation, the dependency audit and tests in one report
+    Check {
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+        /// Skip the dependency audit
+        #[arg(long)]
+        no_audit: bool,
+        /// Skip the test suite
+        #[arg(long)]
+        no_tests: bool,
+        /// Also write the report as SARIF to this file
+        #[arg(long)]
+        sarif: Option<PathBuf>,
+        /// Also write the report as a GitLab Code Quality report to this file
+        #[arg(long)]
+        code_quality: Option<PathBuf>,
+    },
+    /// Generate a CI pipeline (github, gitlab or jenkins)
+    Ci {
+        /// CI system to generate the pipeline for
+        #[arg(default_value = "github")]
+        provider: CiProvider,
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+        /// Run `forgekit check` in the pipeline and upload its report (SARIF,
+        /// or Code Quality on GitLab)
+        #[arg(long)]
+        sarif: bool,
+    },
+    /// Generate a justfile or Makefile with the project's [scripts] and
+    /// build, test and package shortcuts
+    SyncScripts {
+        /// Write a justfile
+        #[arg(long)]
+        just: bool,
+        /// Write a Makefile
+        #[arg(long)]
+        make: bool,
+        /// Only report whether the generated files are up to date
+        #[arg(long)]
+        check: bool,
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+    /// Open the interactive dashboard
+    Ui {
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+    /// Manage git hooks
+    Hooks {
+        #[command(subcommand)]
+        command: HooksCommands,
+    },
+    /// Keep project state warm and serve JSON-RPC requests over a unix socket
+    Daemon {
+        #[command(subcommand)]
+        command: DaemonCommands,
+    },
+    /// Inspect the project configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+    /// Export the forgekit.toml schema for editor completion and validation
+    Schema {
+        #[command(subcommand)]
+        command: SchemaCommands,
+    },
+    /// Manage environment variables
+    Env {
+        #[command(subcommand)]
+        command: EnvCommands,
+    },
+    /// Manage secrets in the credential store
+    Secret {
+        #[command(subcommand)]
+        command: SecretCommands,
+    },
+    /// Run project tests
+    Test {
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+        /// Generate coverage report
+        #[arg(long)]
+        coverage: bool,
+        /// Rerun each failed test up to N times (default: `[test] retries`)
+        #[arg(long, value_name = "N")]
+        retries: Option<u32>,
+        /// Add tests that passed only on retry to `[test] quarantine`
+        #[arg(long)]
+        quarantine_flaky: bool,
+        /// Output format, like the global `--format`
+        #[arg(short = 'f', value_name = "FORMAT")]
+        format: Option<OutputFormat>,
+    },
+    /// Show how often tests were flaky in recent runs
+    Flaky {
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+    /// Generate test scaffolding
+    TestGenerate {
+        /// Name of the test
+        name: String,
+        /// Kind of test: unit, property (proptest) or async (tokio)
+        #[arg(short, long, default_value = "unit")]
+        kind: TestKind,
+        /// Path to the project (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+    /// Manage the toolchain building the ledokoz target
+    Toolchain {
+        #[command(subcommand)]
+        command: ToolchainCommands,
+    },
+    /// Manage build cache
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommands,
+    },
+    /// Manage the global package store
+    Store {
+        #[command(subcommand)]
+        command: StoreCommands,
+    },
+    /// Free space: unlocked store packages, stale cache namespaces, old artifacts
+    Gc {
+        /// List what would be removed and how much space it takes
+        #[arg(long)]
+        dry_run: bool,
+        /// Days a build-cache namespace is kept after its last write
+        #[arg(long, default_value_t = forgekit_core::gc::DEFAULT_CACHE_MAX_AGE_DAYS)]
+        max_age: u64,
+        /// Also consider this project (repeatable; the current one is included)
+        #[arg(long = "project")]
+        projects: Vec<PathBuf>,
+    },
+    /// Inspect crash reports of the built app
+    Crash {
+        #[command(subcommand)]
+        command: CrashCommands,
+    },
+    /// Inspect, compare and restore earlier packages
+    Artifacts {
+        #[command(subcommand)]
+        command: ArtifactsCommands,
+    },
+    /// Manage debug symbols split from release builds
+    Symbols {
+        #[command(subcommand)]
+        command: SymbolsCommands,
+    },
+    /// Manage opt-in anonymous usage statistics
+    Telemetry {
+        #[command(subcommand)]
+        command: TelemetryCommands,
+    },
+    /// Update forgekit itself to the latest release
+    Upgrade {
+        /// Only report whether a newer release exists
+        #[arg(long)]
+        check: bool,
+        /// Follow this release channel from now on (stable or nightly)
+        #[arg(long)]
+        channel: Option<forgekit_core::config::UpdateChannel>,
+        /// Install the channel's latest release even if it is not newer
+        #[arg(long)]
+        force: bool,
+    },
+    /// Log in to a package registry
+    Login {
+        /// Registry name
+        #[arg(default_value = forgekit_core::registry::DEFAULT_REGISTRY)]
+        registry: String,
+        /// Access token (prompted for when omitted)
+        #[arg(long)]
+        token: Option<String>,
+        /// Registry base URL
+        #[arg(long)]
+        url: Option<String>,
+        /// Number of days until the token expires
+        #[arg(long)]
+        expires_in_days: Option<i64>,
+    },
+    /// Remove stored credentials for a package registry
+    Logout {
+        /// Registry name
+        #[arg(default_value = forgekit_core::registry::DEFAULT_REGISTRY)]
+        registry: String,
+    },
+    /// Host a package registry
+    Registry {
+        #[command(subcommand)]
+        command: RegistryCommands,
+    },
+    /// Search, add and publish packages against a local mock registry
+    Demo,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches)?;
+
+    // Initialize logging on stderr so stdout stays parseable with --format json
+    // (the dashboard owns the terminal, so it logs to its own pane)
+    if !matches!(cli.command, Commands::Ui { .. }) {
+        tracing_subscriber::fmt()
+            .with_writer(std::io::stderr)
+            .init();
+    }
+
+    let command = command_name(&matches);
+    let audit_path = command_path(&matches);
+    let mut audit_args: Vec<String> = std::env::args().skip(1).collect();
+    // A secret given on the command line is positional, so mask it by value
+    if let Commands::Secret {
+        command: SecretCommands::Set {
+            value: Some(value), ..
+        },
+    } = &cli.command
+    {
+        for arg in audit_args.iter_mut().filter(|arg| *arg == value) {
+            *arg = forgekit_core::redact::MASK.to_string();
+        }
+    }
+    // `forgekit test -f json` predates the global `--format`
+    let format = match &cli.command {
+        Commands::Test {
+            format: Some(format),
+            ..
+        } => *format,
+        _ => cli.format,
+    };
+    let mut out = Output::new(format, &command);
+    let started = std::time::Instant::now();
+    let cancel = cancel_on_ctrl_c();
+    let lock = if cli.no_lock {
+        LockOptions::disabled()
+    } else {
+        LockOptions::default()
+    };
+    let approval = cli
+        .approval
+        .clone()
+        .or_else(|| std::env::var(command_policy::APPROVAL_VAR).ok());
+    let allowed = authorize(
+        &command,
+        command_environment(&matches).as_deref(),
+        approval.as_deref(),
+    );
+    let result = tokio::select! {
+        biased;
+        result = async {
+            allowed?;
+            run(cli.command, &mut out, &cancel, &lock).await
+        } => result,
+        // Commands that do not watch the token are dropped, which kills
+        // their child processes
+        _ = async {
+            cancel.cancelled().await;
+            tokio::time::sleep(CANCEL_GRACE_PERIOD).await;
+        } => Err(ForgeKitError::Cancelled("interrupted".to_string()).into()),
+    };
+
+    let error_code = result
+        .as_ref()
+        .err()
+        .and_then(|e| e.downcast_ref::<ForgeKitError>())
+        .map(|e| e.code().to_string());
+    let cache_hit_rate = out.cache_hit_rate();
+    let exit_code = out.finish(result);
+
+    if audit_log::is_audited(&command) {
+        record_audit(
+            &command,
+            &audit_args,
+            &audit_path,
+            exit_code,
+            error_code.clone(),
+        );
+    }
+    let mut event = TelemetryEvent::new(&command, started.elapsed(), exit_code);
+    event.error_code = error_code;
+    event.cache_hit_rate = cache_hit_rate;
+    record_telemetry(&event);
+    std::process::exit(exit_code);
+}
+
+/// Store a telemetry event if the user opted in; failures are only logged
+fn record_telemetry(event: &TelemetryEvent) {
+    let result = GlobalConfig::load(GlobalConfig::default_path()).and_then(|config| {
+        TelemetryStore::new(&TelemetryStore::default_path()).record(&config.telemetry, event)
+    });
+    if let Err(e) = result {
+        tracing::debug!("Failed to record telemetry: {}", e);
+    }
+}
+
+/// Apply `[command_policy]` to a command about to run
+///
+/// A restricted command runs with a valid approval token, or once the user
+/// confirms at a terminal if the rule only asks for confirmation.
+fn authorize(command: &str, environment: Option<&str>, approval: Option<&str>) -> Result<()> {
+    let policy = CommandPolicy::load()?;
+    let environment = policy.environment(environment);
+    let (reason, confirm) = match policy.check(command, environment.as_deref()) {
+        Decision::Allow => return Ok(()),
+        Decision::Confirm(reason) => (reason, true),
+        Decision::Deny(reason) => (reason, false),
+    };
+
+    if let Some(token) = approval {
+        let approval = policy.verify_approval(token, command, environment.as_deref())?;
+        eprintln!(
+            "🔓 {}; approved by {} until {}",
+            reason, approval.approver, approval.expires_at
+        );
+        return Ok(());
+    }
+    if confirm && std::io::stdin().is_terminal() {
+        eprint!("⚠️  {}. Continue? [y/N] ", reason);
+        std::io::stderr().flush()?;
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+        if matches!(answer.trim(), "y" | "Y" | "yes") {
+            return Ok(());
+        }
+    }
+    Err(ForgeKitError::CommandDenied(format!(
+        "{} (pass an approval token with --approval)",
+        reason
+    ))
+    .into())
+}
+
+/// Append a finished command to the project's audit log; failures are
+/// reported but do not change the exit code
+fn record_audit(
+    command: &str,
+    args: &[String],
+    project_path: &Path,
+    exit_code: i32,
+    error_code: Option<String>,
+) {
+    let result = AuditLog::for_project(project_path).and_then(|log| {
+        let redactor = Redactor::for_project(project_path)?;
+        let mut entry = AuditEntry::new(command, args, exit_code, &redactor);
+        entry.error_code = error_code;
+        log.record(&entry)
+    });
+    if let Err(e) = result {
+        tracing::warn!("Failed to record the command in the audit log: {}", e);
+    }
+}
+
+/// Time an interrupted command gets to clean up before it is dropped
+const CANCEL_GRACE_PERIOD: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Cancellation token triggered by Ctrl+C
+fn cancel_on_ctrl_c() -> CancellationToken {
+    let cancel = CancellationToken::new();
+    let trigger = cancel.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            trigger.cancel();
+        }
+    });
+    cancel
+}
+
+/// `[build.toolchain]` of the project with its `[toolchain]` pin applied,
+/// and the pin; the defaults outside a project
+fn toolchain_config(project_path: &Path) -> Result<(ToolchainConfig, Option<String>)> {
+    let config_path = project_path.join("forgekit.toml");
+    if !config_path.exists() {
+        return Ok((ToolchainConfig::default(), None));
+    }
+    let config = ProjectConfig::load(config_path)?;
+    Ok((
+        config.build.toolchain.with_pin(&config.toolchain),
+        config.toolchain.channel,
+    ))
+}
+
+fn print_toolchain_status(out: &mut Output, status: &toolchain::ToolchainStatus) {
+    let installed = if status.installed {
+        "installed"
+    } else {
+        "not installed"
+    };
+    say!(out, "🧰 Toolchain {}: {}", status.channel, installed);
+    if !status.missing_components.is_empty() {
+        say!(
+            out,
+            "   Missing components: {}",
+            status.missing_components.join(", ")
+        );
+    }
+    if let Some(sysroot) = &status.sysroot {
+        say!(out, "   Sysroot: {}", sysroot.display());
+    }
+    if let Some(rust_src) = &status.rust_src {
+        say!(out, "   Standard library sources: {}", rust_src.display());
+    }
+    if let Some(host) = &status.host {
+        say!(out, "   Host triple: {}", host);
+    }
+}
+
+/// Build options that stop the build when `cancel` is triggered
+fn cancellable_build(cancel: &CancellationToken) -> BuildOptions {
+    BuildOptions {
+        cancel: cancel.clone(),
+        ..Default::default()
+    }
+}
+
+#[cfg(unix)]
+async fn daemon(command: DaemonCommands, out: &mut Output) -> Result<()> {
+    use forgekit_core::daemon::{Daemon, DaemonClient};
+
+    match command {
+        DaemonCommands::Start { path } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+
+            say!(out, "🚀 Starting daemon for {:?}", project_path);
+            Daemon::new(&project_path)?.serve().await?;
+        }
+        DaemonCommands::Stop { path } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+
+            let mut client = DaemonClient::connect(&project_path).await?;
+            client.call("shutdown", serde_json::Value::Null).await?;
+            say!(out, "✅ Daemon stopped");
+        }
+        DaemonCommands::Status { path } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+
+            match DaemonClient::connect(&project_path).await {
+                Ok(mut client) => {
+                    let result = client.call("ping", serde_json::Value::Null).await?;
+                    say!(out, "✅ Daemon running (pid {})", result["pid"]);
+                    out.data(result)?;
+                }
+                Err(_) => {
+                    say!(out, "❌ No daemon running");
+                    out.fail();
+                }
+            }
+        }
+        DaemonCommands::Call {
+            method,
+            params,
+            path,
+        } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+            let params = match params {
+                Some(params) => serde_json::from_str(&params)?,
+                None => serde_json::Value::Null,
+            };
+
+            let mut client = DaemonClient::connect(&project_path).await?;
+            let result = client.call(&method, params).await?;
+            say!(out, "{}", serde_json::to_string_pretty(&result)?);
+            out.data(result)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+async fn daemon(_command: DaemonCommands, _out: &mut Output) -> Result<()> {
+    anyhow::bail!(
+        "forgekit daemon requires unix domain sockets and is not supported on this platform"
+    )
+}
+
+/// Arguments of the invoked subcommand
+fn leaf_matches(matches: &clap::ArgMatches) -> &clap::ArgMatches {
+    let mut current = matches;
+    while let Some((_, sub)) = current.subcommand() {
+        current = sub;
+    }
+    current
+}
+
+/// Environment the invoked subcommand targets with `--env`, if any
+fn command_environment(matches: &clap::ArgMatches) -> Option<String> {
+    let matches = leaf_matches(matches);
+    ["env", "environment"]
+        .iter()
+        .find_map(|name| matches.try_get_one::<String>(name).ok().flatten())
+        .cloned()
+}
+
+/// Project the invoked subcommand works on: its `--path`, or the current
+/// directory
+fn command_path(matches: &clap::ArgMatches) -> PathBuf {
+    leaf_matches(matches)
+        .try_get_one::<PathBuf>("path")
+        .ok()
+        .flatten()
+        .cloned()
+        .or_else(|| std::env::current_dir().ok())
+        .unwrap_or_default()
+}
+
+/// Full name of the invoked subcommand, e.g. `cache stats`
+fn command_name(matches: &clap::ArgMatches) -> String {
+    let mut names = Vec::new();
+    let mut current = matches;
+    while let Some((name, sub)) = current.subcommand() {
+        names.push(name);
+        current = sub;
+    }
+    names.join(" ")
+}
+
+async fn run(
+    command: Commands,
+    out: &mut Output,
+    cancel: &CancellationToken,
+    lock: &LockOptions,
+) -> Result<()> {
+    match command {
+        Commands::New {
+            name,
+            path,
+            template,
+            license,
+            vcs,
+            interactive,
+        } => {
+            let project_path = path.unwrap_or_else(|| PathBuf::from(&name));
+            let forgekit = ForgeKit::builder().with_lock(lock.clone()).build()?;
+
+            let mut template = template;
+            let mut options = InitOptions {
+                template: Some(parse_template(&template)),
+                license,
+                vcs,
+                authors: Vec::new(),
+            };
+            if interactive {
+                template = ask(
+                    "Template (basic, gui, cli, service, plugin, library)",
+                    &template,
+                )?;
+                options.template = Some(parse_template(&template));
+                let license = ask(
+                    "License (mit, bsd-3-clause, isc, unlicense, none)",
+                    options.license.map_or("none", |l| l.spdx()),
+                )?;
+                options.license = match license.as_str() {
+                    "none" => None,
+                    license => Some(license.parse().map_err(anyhow::Error::msg)?),
+                };
+                options.vcs = ask("Version control (git, none)", options.vcs.as_str())?
+                    .parse()
+                    .map_err(anyhow::Error::msg)?;
+                let authors = ask("Authors", &project::detect_authors().await.join(", "))?;
+                options.authors = authors
+                    .split(',')
+                    .map(|author| author.trim().to_string())
+                    .filter(|author| !author.is_empty())
+                    .collect();
+
+                let files = project::preview(&name, &options).await?;
+                say!(out, "📋 {} will contain:", project_path.display());
+                print_tree(out, &files);
+                let answer = ask("Create the project? (y/n)", "y")?;
+                if !matches!(answer.to_ascii_lowercase().as_str(), "y" | "yes") {
+                    say!(out, "❎ Nothing was created");
+                    return Ok(());
+                }
+            }
+
+            forgekit
+                .init_project_with_options(&name, &project_path, &options)
+                .await?;
+            say!(
+                out,
+                "✅ Created new {} project '{}' at {:?}",
+                template,
+                name,
+                project_path
+            );
+            say!(out, "📁 Navigate to the project directory:");
+            say!(out, "   cd {}", project_path.display());
+            say!(out, "🔨 Build your project:");
+            say!(out, "   forgekit build");
+        }
+        Commands::Rename {
+            new_name,
+            dry_run,
+            path,
+        } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+
+            let dry_run = DryRun::new(dry_run);
+            let forgekit = ForgeKit::builder().with_lock(lock.clone()).build()?;
+            let files = forgekit
+                .rename_project(&project_path, &new_name, &dry_run)
+                .await?;
+            if dry_run.is_enabled() {
+                return print_plan(out, &dry_run);
+            }
+            for file in &files {
+                let shown = file.strip_prefix(&project_path).unwrap_or(file);
+                say!(out, "  ✏️  {}", shown.display());
+            }
+            say!(out, "✅ Renamed project to '{}'", new_name);
+            out.data(serde_json::json!({ "name": new_name, "files": files }))?;
+        }
+        Commands::Build {
+            path,
+            compiler_cache,
+            watch,
+            package,
+            env,
+            host,
+            replay,
+        } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+            if let Some(id) = replay {
+                let original = BuildLog::load(&project_path, &id)?;
+                let log = BuildLog::replay(&project_path, &id, cancel).await?;
+                print_build_log(out, &log);
+                if log.success != original.success || log.diagnostics != original.diagnostics {
+                    say!(
+                        out,
+                        "⚠️  Replay differs from build {}: it {} with {} error(s) and {} warning(s)",
+                        original.id,
+                        if original.success { "succeeded" } else { "failed" },
+                        original.errors,
+                        original.warnings
+                    );
+                } else {
+                    say!(out, "✅ Replay matches build {}", original.id);
+                }
+                out.data(&log)?;
+                if !log.success {
+                    out.fail();
+                }
+                return Ok(());
+            }
+            let forgekit = ForgeKit::builder()
+                .with_lock(lock.clone())
+                .with_installed_plugins(&project_path)
+                .build()?;
+            let mut cache_config = forgekit.global_config().compiler_cache.clone();
+            if let Some(mode) = compiler_cache {
+                cache_config.mode = mode;
+            }
+            let options = BuildOptions {
+                compiler_cache: Some(cache_config),
+                cancel: cancel.clone(),
+                environment: env,
+                host,
+                ..Default::default()
+            };
+
+            if watch {
+                let options = WatchOptions {
+                    build: options,
+                    package,
+                    ..Default::default()
+                };
+                say!(out, "👀 Watching {:?} (press Ctrl+C to stop)", project_path);
+
+                let text = out.is_text();
+                let mut statuses = Vec::new();
+                BuildWatcher::new(&project_path, options)
+                    .run(|status| {
+                        if text {
+                            print_watch_status(status);
+                        }
+                        statuses.push(status.clone());
+                    })
+                    .await?;
+
+                if text {
+                    eprintln!();
+                }
+                if statuses.last().is_some_and(|status| !status.success) {
+                    out.fail();
+                }
+                out.data(serde_json::json!({
+                    "project_path": project_path,
+                    "builds": statuses,
+                }))?;
+                return Ok(());
+            }
+
+            let summary = forgekit
+                .build_project_with_options(&project_path, &options)
+                .await?;
+            say!(
+                out,
+                "✅ Build completed successfully in {:.1}s",
+                summary.duration.as_secs_f64()
+            );
+            if !summary.sandbox_violations.is_empty() {
+                say!(
+                    out,
+                    "🛡️  Build sandbox blocked {} access(es):",
+                    summary.sandbox_violations.len()
+                );
+                for violation in &summary.sandbox_violations {
+                    say!(out, "   {}", violation);
+                }
+            }
+            if let Some(stats) = &summary.compiler_cache {
+                out.set_cache_hit_rate(stats.hit_rate());
+                say!(
+                    out,
+                    "🗄️  Compiler cache ({}): {} hits, {} misses ({:.1}% hit rate)",
+                    stats.backend,
+                    stats.hits,
+                    stats.misses,
+                    stats.hit_rate() * 100.0
+                );
+            }
+            if !summary.profile.is_empty() {
+                say!(out, "⚙️  Release profile:");
+                for setting in &summary.profile {
+                    say!(
+                        out,
+                        "   {} = {}: {}",
+                        setting.key,
+                        setting.value,
+                        setting.impact
+                    );
+                }
+            }
+            out.data(serde_json::json!({
+                "project_path": project_path,
+                "summary": summary,
+            }))?;
+        }
+        Commands::Package {
+            command: Some(PackageCommands::Diff { old, new }),
+            ..
+        } => {
+            let diff = package_diff::diff(&old, &new)?;
+            print_package_diff(out, &diff);
+            out.data(&diff)?;
+        }
+        Commands::Package {
+            command: None,
+            path,
+            locales,
+            channel,
+            dry_run,
+        } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+            let forgekit = ForgeKit::builder()
+                .with_lock(lock.clone())
+                .with_installed_plugins(&project_path)
+                .build()?;
+
+            let dry_run = DryRun::new(dry_run);
+            let options = forgekit_core::packager::PackageOptions {
+                locales,
+                cancel: cancel.clone(),
+                dry_run: dry_run.clone(),
+                channel,
+                ..Default::default()
+            };
+            let package_path = forgekit
+                .package_project_with_options(&project_path, &options)
+                .await?;
+            if dry_run.is_enabled() {
+                return print_plan(out, &dry_run);
+            }
+            say!(out, "✅ Package created at {:?}", package_path);
+            out.data(serde_json::json!({ "package_path": package_path }))?;
+        }
+        Commands::Install {
+            package,
+            prefix,
+            grant,
+            no_hooks,
+        } => {
+            let options = InstallOptions {
+                granted: grant,
+                run_hooks: !no_hooks,
+            };
+            let app = installer::install(&package, &prefix, &options).await?;
+            say!(
+                out,
+                "✅ Installed {} {} to {}",
+                app.name,
+                app.version,
+                app.app_dir.display()
+            );
+            if !app.permissions.is_empty() {
+                let granted: Vec<String> = app.permissions.iter().map(|p| p.to_string()).collect();
+                say!(out, "🔐 Granted: {}", granted.join(", "));
+            }
+            out.data(&app)?;
+        }
+        Commands::Uninstall {
+            name,
+            prefix,
+            purge,
+        } => {
+            let app = installer::uninstall(&prefix, &name, purge)?;
+            say!(out, "✅ Uninstalled {} {}", app.name, app.version);
+            if !purge {
+                say!(out, "📁 Data kept at {}", app.data_dir.display());
+            }
+            out.data(&app)?;
+        }
+        Commands::Publish {
+            path,
+            remote,
+            check_semver,
+            chunk_size,
+            limit_rate,
+        } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+            let config = ProjectConfig::load(project_path.join("forgekit.toml"))?;
+            if config.kind != ProjectKind::Library {
+                anyhow::bail!("only library projects (kind = \"library\") can be published");
+            }
+
+            let forgekit = ForgeKit::builder().with_lock(lock.clone()).build()?;
+            let package_path = forgekit
+                .package_project_with_cancel(&project_path, cancel)
+                .await?;
+            let registry = forgekit.registry();
+            let semver = if check_semver {
+                let check = semver_check::check(registry, &package_path, remote).await?;
+                match &check.previous {
+                    None => say!(out, "ℹ️  First release, nothing to check semver against"),
+                    Some(previous) if check.passed() => say!(
+                        out,
+                        "✅ Public API of v{} is compatible with v{} ({} change(s))",
+                        check.version,
+                        previous,
+                        check.diff.changes.len()
+                    ),
+                    Some(previous) => {
+                        say!(
+                            out,
+                            "❌ v{} breaks the public API of v{}:",
+                            check.version,
+                            previous
+                        );
+                        for change in check.diff.breaking() {
+                            say!(out, "   - {}", change);
+                        }
+                        anyhow::bail!(
+                            "refusing to publish breaking API changes without a {} version bump",
+                            check.required
+                        );
+                    }
+                }
+                Some(check)
+            } else {
+                None
+            };
+            let (info, destination) = if remote {
+                let options = UploadOptions {
+                    chunk_size: chunk_size.max(1) * 1024 * 1024,
+                    rate_limit: limit_rate.map(|kib| kib * 1024),
+                };
+                let info = registry
+                    .publish_remote_with(&package_path, &options, Some(upload_progress_bar()))
+                    .await?;
+                (info, registry.config().base_url.clone())
+            } else {
+                let info = registry.publish_library(&package_path)?;
+                (info, "the local registry".to_string())
+            };
+            say!(
+                out,
+                "✅ Published {} v{} to {}",
+                config.name,
+                info.version,
+                destination
+            );
+            out.data(serde_json::json!({
+                "package_path": package_path,
+                "version": info.version,
+                "checksum": info.checksum,
+                "semver": semver,
+            }))?;
+        }
+        Commands::BuildPackage { path } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+            let forgekit = ForgeKit::builder()
+                .with_lock(lock.clone())
+                .with_installed_plugins(&project_path)
+                .build()?;
+
+            // Build first
+            forgekit
+                .build_project_with_options(&project_path, &cancellable_build(cancel))
+                .await?;
+            say!(out, "✅ Build completed");
+
+            // Then package
+            let package_path = forgekit
+                .package_project_with_cancel(&project_path, cancel)
+                .await?;
+            say!(out, "✅ Package created at {:?}", package_path);
+            out.data(serde_json::json!({ "package_path": package_path }))?;
+        }
+        Commands::Run {
+            restart,
+            max_retries,
+            health_cmd,
+            auto_ports,
+            env: environment,
+            host,
+            logs,
+            path,
+        } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+            let forgekit = ForgeKit::builder()
+                .with_lock(lock.clone())
+                .with_installed_plugins(&project_path)
+                .build()?;
+
+            // Build first
+            let build_options = BuildOptions {
+                environment: environment.clone(),
+                host,
+                ..cancellable_build(cancel)
+            };
+            forgekit
+                .build_project_with_options(&project_path, &build_options)
+                .await?;
+            say!(out, "✅ Build completed");
+
+            // Run the binary
+            let config =
+                forgekit_core::config::ProjectConfig::load(project_path.join("forgekit.toml"))?;
+            let build_info = BuildInfo::load(&project_path)?;
+            let target = build_info
+                .as_ref()
+                .map_or(platform::TARGET, |info| info.target());
+            let target_dir = build_info.as_ref().map_or_else(
+                || project_path.join("target"),
+                |info| info.target_dir(&project_path),
+            );
+            let binary_path = platform::app_binary(&target_dir, target, &config.name);
+
+            let mut env: Vec<(String, String)> = match &environment {
+                Some(environment) => {
+                    let profile = EnvManager::load_profile(
+                        environment,
+                        &project_path,
+                        &*credentials::default_store()?,
+                    )?;
+                    say!(out, "🌍 Using environment '{}'", environment);
+                    profile.all().clone().into_iter().collect()
+                }
+                None => Vec::new(),
+            };
+
+            // Check service ports before starting instead of failing mid-start
+            let checks = ports::check(&config, &[]);
+            env.extend(ports::resolve(&checks, auto_ports)?);
+            for check in checks.iter().filter(|check| !check.available) {
+                say!(
+                    out,
+                    "🔀 Port {} of '{}' is in use, using {} ({})",
+                    check.port,
+                    check.service,
+                    check.assigned(),
+                    check.env
+                );
+            }
+            env.push((
+                crash::CRASH_DIR_ENV.to_string(),
+                crash::project_crash_dir(&project_path)
+                    .to_string_lossy()
+                    .to_string(),
+            ));
+
+            say!(out, "🏃 Running application...");
+            let options = RunOptions {
+                restart,
+                max_retries,
+                health_cmd,
+                env,
+                cancel: cancel.clone(),
+                ..Default::default()
+            };
+            let mut router = LogRouter::new(&project_path, logs.filter())?
+                .with_color(out.is_text() && std::io::stdout().is_terminal())
+                .with_stdout_to_stderr(!out.is_text());
+            let report = Runner::new(binary_path, options)
+                .run(&mut router, |event| match event {
+                    RunEvent::Started { attempt } if *attempt > 1 => {
+                        say!(out, "🔁 Restarted application (attempt {})", attempt)
+                    }
+                    RunEvent::Started { .. } => {}
+                    RunEvent::Ready { after, .. } => {
+                        say!(
+                            out,
+                            "🟢 Application ready after {:.1}s",
+                            after.as_secs_f64()
+                        )
+                    }
+                    RunEvent::Exited { code, signal, .. } => match (code, signal) {
+                        (Some(0), _) => say!(out, "✅ Application exited successfully"),
+                        (_, Some(signal)) => {
+                            say!(out, "⚠️  Application killed by signal {}", signal)
+                        }
+                        (code, _) => say!(
+                            out,
+                            "⚠️  Application exited with code: {}",
+                            code.unwrap_or(-1)
+                        ),
+                    },
+                    RunEvent::Restarting { delay, .. } => {
+                        say!(out, "⏳ Restarting in {:.1}s", delay.as_secs_f64())
+                    }
+                })
+                .await?;
+
+            if report.gave_up {
+                say!(
+                    out,
+                    "❌ Giving up after {} restart(s)",
+                    report.attempts.len().saturating_sub(1)
+                );
+            }
+            if !report.success()
+                && crash::list(&project_path, &config.name)?
+                    .first()
+                    .is_some_and(|report| {
+                        report
+                            .path
+                            .starts_with(crash::project_crash_dir(&project_path))
+                    })
+            {
+                say!(
+                    out,
+                    "💥 Crash report written; run `forgekit crash symbolicate`"
+                );
+            }
+            out.data(&report)?;
+        }
+        Commands::Dev {
+            port,
+            auto_ports,
+            bind,
+            host,
+            https,
+            workspace,
+            path,
+        } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+            let config = forgekit_core::dev_server::DevServerConfig {
+                port,
+                auto_ports,
+                bind,
+                host,
+                https,
+                ..Default::default()
+            };
+            if workspace {
+                use forgekit_core::dev_server::Reload;
+                use forgekit_core::dev_workspace::{DevEvent, WorkspaceDevServer};
+
+                say!(
+                    out,
+                    "👀 Serving the workspace {:?} (Ctrl+C to stop)",
+                    project_path
+                );
+                let public = !bind.is_loopback();
+                WorkspaceDevServer::new(&project_path, config, cancellable_build(cancel))
+                    .run(cancel, |event| match event {
+                        DevEvent::Listening { member, url } => {
+                            say!(out, "🚀 {} on {}", member, url);
+                            if public {
+                                if let Some(code) = forgekit_core::dev_server::qr_code(url) {
+                                    say!(out, "{}", code);
+                                }
+                            }
+                        }
+                        DevEvent::Built { member, status } => {
+                            say!(out, "🔨 {}: {}", member, status.status_line());
+                            if let Some(message) = &status.message {
+                                say!(out, "   {}", message);
+                            }
+                        }
+                        DevEvent::Reloaded {
+                            member,
+                            reload: Reload::Assets(paths),
+                        } => {
+                            say!(out, "♻️  {} swapped {}", member, paths.join(", "));
+                        }
+                        DevEvent::Reloaded { member, reload } => {
+                            say!(out, "🔄 {} reloaded ({})", member, reload);
+                        }
+                    })
+                    .await?;
+                return Ok(());
+            }
+            let server = forgekit_core::dev_server::DevServer::new(config);
+            server
+                .run(&project_path, cancel, |url| {
+                    say!(out, "🚀 Development server on {}", url);
+                    say!(
+                        out,
+                        "   Serving /assets, /ui, /docs and /rustdoc; Ctrl+C to stop"
+                    );
+                    if https {
+                        say!(out, "   Self-signed certificate: accept it once per device");
+                    }
+                    if server.is_public() {
+                        if let Some(code) = forgekit_core::dev_server::qr_code(url) {
+                            say!(out, "{}", code);
+                        }
+                    }
+                })
+                .await?;
+        }
+        Commands::Logs { follow, logs, path } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+
+            let filter = logs.filter();
+            let text_output = out.is_text();
+            let color = text_output && std::io::stdout().is_terminal();
+            let mut shown = Vec::new();
+            forgekit_core::logs::read(&project_path, follow, cancel, |line| {
+                if !filter.matches(&line) {
+                    return;
+                }
+                let text = filter.render(&line, color);
+                if follow && text_output {
+                    println!("{}", text);
+                } else if follow {
+                    // Keep stdout for the JSON result
+                    eprintln!("{}", text);
+                } else {
+                    shown.push(text);
+                }
+            })
+            .await?;
+            for line in &shown {
+                say!(out, "{}", line);
+            }
+            out.data(serde_json::json!({ "lines": shown }))?;
+        }
+        Commands::Add {
+            package,
+            version,
+            dry_run,
+            path,
+        } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+
+            let dry_run = DryRun::new(dry_run);
+            let package_manager = PackageManager::new(project_path.clone())?
+                .with_lock(lock.clone())
+                .with_progress(download_progress_bar())
+                .with_dry_run(dry_run.clone());
+            package_manager.add_dependency(&package, &version).await?;
+            if dry_run.is_enabled() {
+                return print_plan(out, &dry_run);
+            }
+            say!(out, "✅ Added dependency: {} v{}", package, version);
+        }
+        Commands::Remove {
+            package,
+            dry_run,
+            path,
+        } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+
+            let dry_run = DryRun::new(dry_run);
+            let package_manager = PackageManager::new(project_path.clone())?
+                .with_lock(lock.clone())
+                .with_dry_run(dry_run.clone());
+            package_manager.remove_dependency(&package).await?;
+            if dry_run.is_enabled() {
+                return print_plan(out, &dry_run);
+            }
+            say!(out, "✅ Removed dependency: {}", package);
+        }
+        Commands::Update {
+            dry_run,
+            create_pr,
+            group,
+            remote,
+            path,
+        } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+
+            if create_pr {
+                let pull_requests = DependencyUpdater::new(project_path)?
+                    .with_remote(&remote)
+                    .with_group(group)
+                    .with_lock(lock.clone())
+                    .create_pull_requests()
+                    .await?;
+                for pull_request in &pull_requests {
+                    let checks = if pull_request.checks.passed() {
+                        "checks passed"
+                    } else {
+                        "checks failed"
+                    };
+                    say!(
+                        out,
+                        "🔀 {} ({}): {}",
+                        pull_request.branch.title,
+                        checks,
+                        pull_request.url
+                    );
+                }
+                if pull_requests.is_empty() {
+                    say!(out, "✅ All dependencies are up to date");
+                }
+                out.data(&pull_requests)?;
+                return Ok(());
+            }
+
+            let dry_run = DryRun::new(dry_run);
+            let package_manager = PackageManager::new(project_path.clone())?
+                .with_lock(lock.clone())
+                .with_install_progress(install_progress_bar())
+                .with_dry_run(dry_run.clone());
+            package_manager.update_dependencies().await?;
+            if dry_run.is_enabled() {
+                return print_plan(out, &dry_run);
+            }
+            say!(out, "✅ Dependencies updated");
+        }
+        Commands::Vendor { path } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+
+            let package_manager =
+                PackageManager::new(project_path.clone())?.with_lock(lock.clone());
+            let report = package_manager.vendor().await?;
+            say!(
+                out,
+                "✅ Vendored {} package(s) ({} checksum(s) verified)",
+                report.packages,
+                report.verified
+            );
+            say!(
+                out,
+                "🔧 Cargo configured to build ForgeKit packages from vendor/: {:?}",
+                report.cargo_config
+            );
+        }
+        Commands::Dedup { path, apply } => {
+            let workspace_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+
+            let workspace = Workspace::load(&workspace_path)?;
+            let report = Deduplicator::analyze(&workspace);
+
+            if report.duplicates.is_empty() {
+                say!(
+                    out,
+                    "✅ No duplicate dependencies across {} member(s)",
+                    workspace.members.len()
+                );
+                return Ok(());
+            }
+
+            say!(out, "📦 Duplicate dependencies:");
+            for duplicate in &report.duplicates {
+                say!(
+                    out,
+                    "  {} -> {}",
+                    duplicate.name,
+                    duplicate.suggested_version
+                );
+                for (version, members) in &duplicate.versions {
+                    say!(out, "    {} used by {}", version, members.join(", "));
+                }
+            }
+            say!(
+                out,
+                "⚠️  Estimated cost: ~{}s build time, ~{} KB binary size",
+                report.total_build_secs(),
+                report.total_size_kb()
+            );
+
+            if apply {
+                let applied = Deduplicator::apply(&workspace, &report)?;
+                say!(out, "✅ Applied {} edit(s)", applied);
+            } else {
+                say!(out, "🔧 Suggested edits:");
+                for edit in &report.edits {
+                    say!(
+                        out,
+                        "  {}: {} {} -> {}",
+                        edit.config_path.display(),
+                        edit.dependency,
+                        edit.from,
+                        edit.to
+                    );
+                }
+                say!(out, "Run with --apply to rewrite forgekit.toml files");
+            }
+        }
+        Commands::Batch {
+            command,
+            projects,
+            jobs,
+            target_dir,
+        } => {
+            let mut options = BatchOptions::new();
+            if let Some(jobs) = jobs {
+                options.jobs = jobs;
+            }
+            options.target_dir = Some(match target_dir {
+                Some(dir) => dir,
+                None => std::env::current_dir()?.join("target").join("batch"),
+            });
+
+            say!(
+                out,
+                "🔨 Running {} for {} project(s) ({} at a time)",
+                command,
+                projects.len(),
+                options.jobs
+            );
+            let report = ForgeKit::builder()
+                .with_lock(lock.clone())
+                .build()?
+                .batch(&projects, command, &options)
+                .await?;
+
+            for row in report.table() {
+                say!(out, "{}", row);
+            }
+            say!(
+                out,
+                "{} succeeded, {} failed",
+                report.succeeded(),
+                report.failed()
+            );
+            out.data(&report)?;
+            if !report.all_passed() {
+                out.fail();
+            }
+        }
+        Commands::Affected {
+            since,
+            path,
+            run,
+            jobs,
+            target_dir,
+        } => {
+            let workspace_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+
+            let report = affected::detect(&workspace_path, &since).await?;
+            say!(
+                out,
+                "🔍 {} file(s) changed since {}, {} member(s) affected",
+                report.changed_files.len(),
+                since,
+                report.members.len()
+            );
+            for member in &report.members {
+                say!(out, "  📦 {} ({})", member.name, member.reason);
+            }
+
+            let Some(command) = run.filter(|_| !report.members.is_empty()) else {
+                return out.data(&report);
+            };
+            let mut options = BatchOptions::new();
+            if let Some(jobs) = jobs {
+                options.jobs = jobs;
+            }
+            options.target_dir = target_dir;
+            let batch = ForgeKit::builder()
+                .with_lock(lock.clone())
+                .build()?
+                .batch(&report.paths(), command, &options)
+                .await?;
+            for row in batch.table() {
+                say!(out, "{}", row);
+            }
+            say!(
+                out,
+                "{} succeeded, {} failed",
+                batch.succeeded(),
+                batch.failed()
+            );
+            out.data(serde_json::json!({ "affected": report, "batch": batch }))?;
+            if !batch.all_passed() {
+                out.fail();
+            }
+        }
+        Commands::Search { query } => {
+            let current_dir = std::env::current_dir()?;
+            let package_manager = PackageManager::new(current_dir)?;
+            let results = package_manager.search_packages(&query).await?;
+
+            if results.is_empty() {
+                say!(out, "No packages found matching '{}'", query);
+            } else {
+                say!(out, "Found {} packages:", results.len());
+                for result in results {
+                    say!(out, "  {}", result);
+                }
+            }
+        }
+        Commands::Plugin { command } => match command {
+            PluginCommands::Search { query } => {
+                let results = PluginStore::global()?.search(&query).await?;
+                if results.is_empty() {
+                    say!(out, "No plugins found matching '{}'", query);
+                } else {
+                    say!(out, "Found {} plugins:", results.len());
+                    for result in &results {
+                        let compatibility = if result.compatible {
+                            String::new()
+                        } else {
+                            format!(" (needs plugin API {})", result.api_version)
+                        };
+                        say!(
+                            out,
+                            "  {} v{}{} - {}",
+                            result.name,
+                            result.version,
+                            compatibility,
+                            result.description.as_deref().unwrap_or("")
+                        );
+                    }
+                }
+                out.data(&results)?;
+            }
+            PluginCommands::Install {
+                name,
+                version,
+                scope,
+            } => {
+                let store = scope.store()?;
+                let plugin = store.install(&name, version.as_deref()).await?;
+                say!(
+                    out,
+                    "✅ Installed {} v{} into {}",
+                    plugin.name,
+                    plugin.version,
+                    store.dir().display()
+                );
+                if !plugin.enabled {
+                    say!(
+                        out,
+                        "   It stays disabled; run `forgekit plugin enable {}`",
+                        name
+                    );
+                }
+                out.data(&plugin)?;
+            }
+            PluginCommands::List { scope } => {
+                let plugins = scope.store()?.installed()?;
+                if plugins.is_empty() {
+                    say!(out, "No plugins installed");
+                }
+                for plugin in &plugins {
+                    let state = if !plugin.is_compatible() {
+                        format!("incompatible, plugin API {}", plugin.api_version)
+                    } else if plugin.enabled {
+                        "enabled".to_string()
+                    } else {
+                        "disabled".to_string()
+                    };
+                    say!(out, "  {} v{} ({})", plugin.name, plugin.version, state);
+                }
+                out.data(&plugins)?;
+            }
+            PluginCommands::Enable { name, scope } => {
+                let plugin = scope.store()?.set_enabled(&name, true)?;
+                say!(out, "✅ Enabled {}", name);
+                out.data(&plugin)?;
+            }
+            PluginCommands::Disable { name, scope } => {
+                let plugin = scope.store()?.set_enabled(&name, false)?;
+                say!(out, "✅ Disabled {}", name);
+                out.data(&plugin)?;
+            }
+            PluginCommands::Update { name, scope } => {
+                let updates = scope.store()?.update(name.as_deref()).await?;
+                if updates.is_empty() {
+                    say!(out, "✅ All plugins are up to date");
+                }
+                for update in &updates {
+                    say!(
+                        out,
+                        "⬆️  {} v{} -> v{}",
+                        update.name,
+                        update.from,
+                        update.to
+                    );
+                }
+                out.data(&updates)?;
+            }
+            PluginCommands::Uninstall { name, scope } => {
+                scope.store()?.uninstall(&name)?;
+                say!(out, "✅ Removed {}", name);
+            }
+        },
+        Commands::Info { package, readme } => {
+            let client = forgekit_core::registry::RegistryClient::from_login(
+                forgekit_core::registry::RegistryConfig::default(),
+            )?;
+            let details = client.get_package_details(&package).await?;
+            let metadata = &details.metadata;
+
+            say!(out, "{} v{}", metadata.name, metadata.version);
+            say!(out, "  {}", metadata.description);
+            say!(out, "");
+            say!(out, "  License:    {}", metadata.license);
+            say!(out, "  Repository: {}", metadata.repository);
+            say!(out, "  Owners:     {}", details.owners.join(", "));
+            say!(out, "  Downloads:  {}", metadata.downloads);
+            if !metadata.keywords.is_empty() {
+                say!(out, "  Keywords:   {}", metadata.keywords.join(", "));
+            }
+
+            say!(out, "");
+            say!(out, "Versions:");
+            for version in &details.versions {
+                say!(out, "  {:<12} {}", version.version, version.published);
+            }
+
+            if let Some(text) = &details.readme {
+                say!(out, "");
+                say!(out, "README:");
+                let lines: Vec<&str> = text.lines().collect();
+                let shown = if readme {
+                    lines.len()
+                } else {
+                    lines.len().min(20)
+                };
+                for line in &lines[..shown] {
+                    say!(out, "  {}", line);
+                }
+                if shown < lines.len() {
+                    say!(
+                        out,
+                        "  ... (use --readme to show all {} lines)",
+                        lines.len()
+                    );
+                }
+            }
+        }
+        Commands::Templates => {
+            say!(out, "Available templates:");
+            say!(out, "  basic    - Basic application template");
+            say!(out, "  gui      - Graphical user interface application");
+            say!(out, "  cli      - Command-line interface tool");
+            say!(out, "  service  - Background service/daemon");
+            say!(out, "  plugin   - ForgeKit plugin library");
+            say!(out, "  library  - Reusable library packaged as .moxlib");
+        }
+        Commands::Template { command } => match command {
+            TemplateCommands::Update { path, dry_run } => {
+                use forgekit_core::template_update::{self, FileOutcome};
+
+                let project_path = match path {
+                    Some(p) => p,
+                    None => std::env::current_dir()?,
+                };
+                let dry_run = DryRun::new(dry_run);
+                let update = template_update::update(&project_path, &dry_run).await?;
+
+                for file in &update.files {
+                    let (icon, what) = match &file.outcome {
+                        FileOutcome::Added => ("➕", "added".to_string()),
+                        FileOutcome::Updated => ("✏️ ", "updated".to_string()),
+                        FileOutcome::Merged => ("🔀", "merged with your changes".to_string()),
+                        FileOutcome::Conflict { conflicts } => {
+                            ("⚠️ ", format!("{} conflict(s) to resolve", conflicts))
+                        }
+                        FileOutcome::Removed => ("➖", "removed".to_string()),
+                        FileOutcome::Kept => ("⏭️ ", "kept your version".to_string()),
+                    };
+                    say!(out, "  {} {}: {}", icon, file.path.display(), what);
+                }
+                let verb = if dry_run.is_enabled() {
+                    "Would update"
+                } else {
+                    "Updated"
+                };
+                if update.files.is_empty() && update.from == update.to {
+                    say!(
+                        out,
+                        "✅ Project is up to date with template '{}' v{}",
+                        update.template,
+                        update.to
+                    );
+                } else if update.conflicts() > 0 {
+                    say!(
+                        out,
+                        "⚠️  {} template '{}' v{} → v{}; resolve the conflict markers in {} file(s)",
+                        verb,
+                        update.template,
+                        update.from,
+                        update.to,
+                        update.conflicts()
+                    );
+                } else {
+                    say!(
+                        out,
+                        "✅ {} template '{}' v{} → v{}",
+                        verb,
+                        update.template,
+                        update.from,
+                        update.to
+                    );
+                }
+                out.data(&update)?;
+            }
+        },
+        Commands::Generate { command } => {
+            use forgekit_core::generate::{generate, Generator};
+
+            let (generator, name, path, dry_run) = match command {
+                GenerateCommands::Command {
+                    name,
+                    path,
+                    dry_run,
+                } => (Generator::Command, name, path, dry_run),
+                GenerateCommands::Migration {
+                    name,
+                    path,
+                    dry_run,
+                } => (Generator::Migration, name, path, dry_run),
+                GenerateCommands::PluginHook {
+                    name,
+                    on,
+                    path,
+                    dry_run,
+                } => (Generator::PluginHook(on), name, path, dry_run),
+                GenerateCommands::UiView {
+                    name,
+                    path,
+                    dry_run,
+                } => (Generator::UiView, name, path, dry_run),
+            };
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+            let dry_run = DryRun::new(dry_run);
+            let generated = generate(&project_path, generator, &name, &dry_run)?;
+
+            for file in &generated.created {
+                say!(out, "  ➕ {}", file.display());
+            }
+            for file in &generated.updated {
+                say!(out, "  ✏️  {}", file.display());
+            }
+            if dry_run.is_enabled() {
+                say!(out, "Would generate {} '{}'", generator, name);
+            } else {
+                say!(out, "✅ Generated {} '{}'", generator, name);
+            }
+            out.data(&generated)?;
+        }
+        Commands::Validate { path, strict, fix } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+
+            if fix {
+                let fixed = forgekit_core::validator::ProjectValidator::fix(&project_path)?;
+                for file in &fixed {
+                    say!(
+                        out,
+                        "🔧 Added license header to {}",
+                        file.strip_prefix(&project_path).unwrap_or(file).display()
+                    );
+                }
+            }
+
+            let strictness = if strict {
+                Strictness::Deny
+            } else {
+                Strictness::Warn
+            };
+            let report = forgekit_core::validator::ProjectValidator::validate_project_with(
+                &project_path,
+                strictness,
+            )
+            .await?;
+
+            if report.errors.is_empty() && report.warnings.is_empty() {
+                say!(out, "✅ Project validation passed");
+            } else {
+                if !report.errors.is_empty() {
+                    say!(out, "❌ Validation errors:");
+                    for error in &report.errors {
+                        say!(out, "   - {}", error);
+                    }
+                }
+                if !report.warnings.is_empty() {
+                    say!(out, "⚠️  Validation warnings:");
+                    for warning in &report.warnings {
+                        say!(out, "   - {}", warning);
+                    }
+                }
+            }
+
+            out.data(&report)?;
+            if !report.is_valid {
+                out.fail();
+            }
+        }
+        Commands::Appmeta { path } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+
+            let config = ProjectConfig::load(project_path.join("forgekit.toml"))?;
+            let files = forgekit_core::appmeta::AppMeta::generate(&project_path, &config)?;
+            say!(out, "🖼️  Generated {} app metadata file(s):", files.len());
+            for file in &files {
+                say!(
+                    out,
+                    "   - {}",
+                    file.strip_prefix(&project_path).unwrap_or(file).display()
+                );
+            }
+            out.data(&files)?;
+        }
+        Commands::Appstore { path, check } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+
+            let config = ProjectConfig::load(project_path.join("forgekit.toml"))?;
+            if check {
+                let problems = forgekit_core::appstore::AppStore::validate(&project_path, &config);
+                if problems.is_empty() {
+                    say!(out, "✅ Store listing is valid");
+                } else {
+                    say!(out, "❌ Store listing has {} problem(s):", problems.len());
+                    for problem in &problems {
+                        say!(out, "   - {}", problem);
+                    }
+                }
+                out.data(&problems)?;
+                if !problems.is_empty() {
+                    anyhow::bail!("Store listing is invalid");
+                }
+                return Ok(());
+            }
+            let bundle = forgekit_core::appstore::AppStore::bundle(&project_path, &config)?;
+            say!(
+                out,
+                "🛍️  Store listing bundle created: {}",
+                bundle.display()
+            );
+            out.data(&bundle)?;
+        }
+        Commands::Builds { command } => match command {
+            BuildsCommands::List { path } => {
+                let project_path = match path {
+                    Some(p) => p,
+                    None => std::env::current_dir()?,
+                };
+
+                let builds = BuildLog::list(&project_path)?;
+                if builds.is_empty() {
+                    say!(out, "No recorded builds");
+                }
+                for build in &builds {
+                    let replay = match &build.replay_of {
+                        Some(id) => format!(" (replay of {})", id),
+                        None => String::new(),
+                    };
+                    say!(
+                        out,
+                        "{} {} {:.1}s, {} error(s), {} warning(s){}",
+                        if build.success { "✅" } else { "❌" },
+                        build.id,
+                        build.duration_ms as f64 / 1000.0,
+                        build.errors,
+                        build.warnings,
+                        replay
+                    );
+                }
+                out.data(&builds)?;
+            }
+            BuildsCommands::Show { id, path } => {
+                let project_path = match path {
+                    Some(p) => p,
+                    None => std::env::current_dir()?,
+                };
+
+                let log = BuildLog::load(&project_path, &id)?;
+                print_build_log(out, &log);
+                say!(out, "");
+                say!(
+                    out,
+                    "{}",
+                    std::fs::read_to_string(log.log_path(&project_path))?.trim_end()
+                );
+                out.data(&log)?;
+            }
+        },
+        Commands::Feed {
+            command: FeedCommands::Keygen { path },
+        } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+
+            let config = ProjectConfig::load(project_path.join("forgekit.toml"))?;
+            let public_key = forgekit_core::ota::generate_key(&project_path, &config)?;
+            say!(out, "🔑 Update feed signing key created");
+            say!(out, "   Public key: {}", public_key);
+            out.data(serde_json::json!({ "public_key": public_key }))?;
+        }
+        Commands::Feed {
+            command:
+                FeedCommands::Generate {
+                    path,
+                    channel,
+                    url,
+                    output,
+                },
+        } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+
+            let config = ProjectConfig::load(project_path.join("forgekit.toml"))?;
+            let options = forgekit_core::ota::FeedOptions {
+                channel,
+                url,
+                output,
+            };
+            let (feed_path, signed) =
+                forgekit_core::ota::generate_feed(&project_path, &config, &options)?;
+            say!(out, "📡 Update feed written to {}", feed_path.display());
+            for (channel, entry) in &signed.feed.channels {
+                say!(out, "   - {}: {} ({})", channel, entry.version, entry.url);
+            }
+            out.data(&signed)?;
+        }
+        Commands::Policy { path, release } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+
+            if Policy::load(&project_path)?.is_none() {
+                say!(out, "ℹ️  No {} in {}", POLICY_FILE, project_path.display());
+                out.data(Vec::<PolicyViolation>::new())?;
+                return Ok(());
+            }
+            let config = ProjectConfig::load(project_path.join("forgekit.toml"))?;
+            let registry = RegistryClient::from_login(RegistryConfig::default())?;
+            let stage = if release {
+                PolicyStage::Release
+            } else {
+                PolicyStage::Resolution
+            };
+            let violations = policy::check(&project_path, &config, &registry, stage)?;
+            if violations.is_empty() {
+                say!(out, "✅ Dependencies follow {}", POLICY_FILE);
+            } else {
+                say!(out, "❌ Policy violations:");
+                for violation in &violations {
+                    say!(out, "   - {}", violation);
+                }
+                out.fail();
+            }
+            out.data(&violations)?;
+        }
+        Commands::Approval { command } => match command {
+            ApprovalCommands::Keygen { key } => {
+                let public_key = command_policy::generate_key(&key)?;
+                say!(out, "🔑 Wrote approval signing key to {}", key.display());
+                say!(
+                    out,
+                    "   Trust it with [command_policy] approval_keys = [\"{}\"]",
+                    public_key
+                );
+                out.data(serde_json::json!({ "public_key": public_key }))?;
+            }
+            ApprovalCommands::Issue {
+                command,
+                env,
+                expires_in,
+                key,
+                approver,
+            } => {
+                let approver = approver.unwrap_or_else(audit_log::current_user);
+                let approval = Approval::new(&command, env.as_deref(), &approver, &expires_in)?;
+                let token = approval.sign(&command_policy::load_key(&key)?)?;
+                say!(out, "{}", token);
+                out.data(serde_json::json!({ "token": token, "approval": approval }))?;
+            }
+        },
+        Commands::AuditLog {
+            command: AuditLogCommands::Show { since, path },
+        } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+
+            let log = AuditLog::for_project(&project_path)?;
+            if log.sink() != AuditSink::File {
+                anyhow::bail!(
+                    "The audit log of this project goes to {:?}, not to a file",
+                    log.sink()
+                );
+            }
+            let entries = match since {
+                Some(since) => log.entries_since(audit_log::cutoff(&since)?)?,
+                None => log.entries()?,
+            };
+            if entries.is_empty() {
+                say!(out, "No commands recorded in {}", log.path().display());
+            }
+            for entry in &entries {
+                let outcome = match &entry.error_code {
+                    _ if entry.succeeded() => "✅".to_string(),
+                    Some(code) => format!("❌ {}", code),
+                    None => format!("❌ exit {}", entry.exit_code),
+                };
+                say!(
+                    out,
+                    "{}  {:<12} {}  {}",
+                    entry.timestamp,
+                    entry.user,
+                    entry.args.join(" "),
+                    outcome
+                );
+            }
+            out.data(&entries)?;
+        }
+        Commands::Audit { path, unsafe_code } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+
+            let report = DependencyAuditor::audit_dependencies(&project_path).await?;
+            if report.vulnerabilities.is_empty() {
+                say!(out, "✅ No known vulnerabilities found");
+            } else {
+                say!(out, "❌ Vulnerabilities:");
+                for vulnerability in &report.vulnerabilities {
+                    say!(
+                        out,
+                        "   - {} {} ({:?}): {}",
+                        vulnerability.package,
+                        vulnerability.version,
+                        vulnerability.severity,
+                        vulnerability.description
+                    );
+                }
+                out.fail();
+            }
+            if !unsafe_code {
+                out.data(&report)?;
+                return Ok(());
+            }
+
+            let unsafe_report = AnalyticsCollector::unsafe_report(&project_path).await?;
+            say!(out, "☢️  Unsafe code and FFI:");
+            for krate in &unsafe_report.crates {
+                let counts = &krate.counts;
+                if !krate.is_project && counts.total() == 0 {
+                    continue;
+                }
+                let origin = if krate.is_project { " (project)" } else { "" };
+                if krate.forbids_unsafe {
+                    say!(
+                        out,
+                        "   {} {}{}: forbids unsafe code",
+                        krate.name,
+                        krate.version,
+                        origin
+                    );
+                    continue;
+                }
+                say!(
+                    out,
+                    "   {} {}{}: {} block(s), {} fn(s), {} impl(s), {} trait(s), {} FFI import(s), {} FFI export(s)",
+                    krate.name,
+                    krate.version,
+                    origin,
+                    counts.blocks,
+                    counts.functions,
+                    counts.impls,
+                    counts.traits,
+                    counts.ffi_imports,
+                    counts.ffi_exports
+                );
+                if krate.unparsed_files > 0 {
+                    say!(
+                        out,
+                        "      {} file(s) could not be parsed",
+                        krate.unparsed_files
+                    );
+                }
+            }
+            say!(
+                out,
+                "   {} of {} dependencies contain unsafe code",
+                unsafe_report.unsafe_dependencies().count(),
+                unsafe_report
+                    .crates
+                    .iter()
+                    .filter(|c| !c.is_project)
+                    .count()
+            );
+            out.data(serde_json::json!({
+                "audit": report,
+                "unsafe": unsafe_report,
+            }))?;
+        }
+        Commands::Api {
+            path,
+            against,
+            undocumented,
+        } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+
+            let report = AnalyticsCollector::api_report(&project_path, against.as_deref()).await?;
+            let surface = &report.surface;
+            say!(
+                out,
+                "📚 Documentation coverage: {:.1}% ({} public item(s), {} undocumented)",
+                report.coverage,
+                surface.items.len(),
+                surface.undocumented().count()
+            );
+            if undocumented {
+                for item in surface.undocumented() {
+                    say!(
+                        out,
+                        "   - {} {} ({})",
+                        item.kind,
+                        item.path,
+                        item.file.display()
+                    );
+                }
+            }
+
+            match (&report.previous_release, &report.diff) {
+                (Some(release), Some(diff)) => {
+                    if diff.changes.is_empty() {
+                        say!(out, "✅ Public API unchanged since {}", release);
+                    } else {
+                        say!(out, "🔎 Public API changes since {}:", release);
+                        for change in &diff.changes {
+                            let icon = if change.is_breaking() { "❌" } else { "➕" };
+                            say!(out, "   {} {}", icon, change);
+                        }
+                    }
+                    if let Some(bump) = report.suggested_bump {
+                        say!(out, "💡 Suggested version bump: {}", bump);
+                    }
+                }
+                _ => say!(
+                    out,
+                    "ℹ️  No previous release tag; pass --against to compare the API"
+                ),
+            }
+            out.data(&report)?;
+        }
+        Commands::Health { path } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+
+            let report = AnalyticsCollector::health_report(&project_path).await?;
+            if out.is_markdown() {
+                say!(out, "{}", report.to_markdown());
+            } else {
+                say!(
+                    out,
+                    "🩺 Project health: {} ({}/100)",
+                    report.grade,
+                    report.score
+                );
+                say!(
+                    out,
+                    "   Validation: {} error(s), {} warning(s)",
+                    report.validation_errors,
+                    report.validation_warnings
+                );
+                let vulns = &report.vulnerabilities;
+                say!(
+                    out,
+                    "   Vulnerabilities: {} critical, {} high, {} medium, {} low",
+                    vulns.critical,
+                    vulns.high,
+                    vulns.medium,
+                    vulns.low
+                );
+                say!(out, "   Outdated dependencies: {}", report.outdated);
+                match report.test_coverage {
+                    Some(coverage) => say!(out, "   Test coverage: {:.1}%", coverage),
+                    None => say!(out, "   Test coverage: not measured"),
+                }
+                if let Some(trend) = report.build_trend {
+                    say!(out, "   Build time trend: {:+.0}%", trend * 100.0);
+                }
+                if report.flaky_tests > 0 {
+                    say!(out, "   Flaky tests: {}", report.flaky_tests);
+                }
+                if report.todos > 0 {
+                    say!(
+                        out,
+                        "   TODO markers: {} ({} stale)",
+                        report.todos,
+                        report.stale_todos
+                    );
+                }
+                if report.typos > 0 {
+                    say!(out, "   Misspelled words: {}", report.typos);
+                }
+                if !report.recommendations.is_empty() {
+                    say!(out, "💡 Recommendations:");
+                    for recommendation in &report.recommendations {
+                        say!(
+                            out,
+                            "   [{:?}] {}",
+                            recommendation.priority,
+                            recommendation.message
+                        );
+                    }
+                }
+            }
+            out.data(&report)?;
+        }
+        Commands::Todos { path } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
 
-            forgekit
-                .init_project_with_template(&name, &project_path, template_type)
-                .await?;
-            println!(
-                "✅ Created new {} project '{}' at {:?}",
-                template, name, project_path
+            let report = AnalyticsCollector::todo_report(&project_path).await?;
+            if report.markers.is_empty() {
+                say!(out, "✅ No TODO, FIXME or HACK markers");
+            } else {
+                for marker in &report.markers {
+                    let age = match (marker.age_days, &marker.author) {
+                        (Some(days), Some(author)) => format!(" ({} days, {})", days, author),
+                        _ => String::new(),
+                    };
+                    say!(
+                        out,
+                        "   {}:{}: {} {}{}",
+                        marker.file.display(),
+                        marker.line,
+                        marker.kind,
+                        marker.text,
+                        age
+                    );
+                }
+                let counts: Vec<String> = report
+                    .counts
+                    .iter()
+                    .map(|(kind, count)| format!("{} {}", count, kind))
+                    .collect();
+                say!(
+                    out,
+                    "📝 {} marker(s): {}; {} stale",
+                    report.markers.len(),
+                    counts.join(", "),
+                    report.stale
+                );
+            }
+            if !report.typos.is_empty() {
+                say!(out, "🔤 Misspelled words in comments:");
+                for typo in &report.typos {
+                    say!(
+                        out,
+                        "   {}:{}: {} -> {}",
+                        typo.file.display(),
+                        typo.line,
+                        typo.word,
+                        typo.suggestion
+                    );
+                }
+            }
+            out.data(&report)?;
+        }
+        Commands::Outdated { path } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+
+            let updates = DependencyAuditor::check_for_updates(&project_path).await?;
+            if updates.is_empty() {
+                say!(out, "✅ All dependencies are up to date");
+            } else {
+                say!(out, "📦 Updates available:");
+                for update in &updates {
+                    say!(
+                        out,
+                        "   {} {} -> {}",
+                        update.package,
+                        update.current_version,
+                        update.suggested_version
+                    );
+                }
+            }
+            out.data(&updates)?;
+        }
+        Commands::Lint { path, fix } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+
+            let fail_on = Linter::fail_on(&project_path)?;
+            let report = Linter::run(&project_path, fix).await?;
+
+            for diagnostic in &report.diagnostics {
+                print_diagnostic(out, diagnostic);
+            }
+            for reason in &report.skipped {
+                say!(out, "⏭️  Skipped {}", reason);
+            }
+
+            say!(
+                out,
+                "{} error(s), {} warning(s)",
+                report.count(Severity::Error),
+                report.count(Severity::Warning)
+            );
+            out.data(&report.diagnostics)?;
+            if report.fails(fail_on) {
+                out.fail();
+            } else {
+                say!(out, "✅ Lint passed");
+            }
+        }
+        Commands::Check {
+            path,
+            no_audit,
+            no_tests,
+            sarif,
+            code_quality,
+        } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+
+            let fail_on = Linter::fail_on(&project_path)?;
+            let options = CheckOptions {
+                lint: true,
+                audit: !no_audit,
+                tests: !no_tests,
+            };
+            let report = diagnostics::run_checks(&project_path, &options).await?;
+
+            for diagnostic in &report.diagnostics {
+                print_diagnostic(out, diagnostic);
+            }
+            if let Some(sarif) = sarif {
+                std::fs::write(
+                    &sarif,
+                    serde_json::to_string_pretty(&report.to_sarif(&project_path))?,
+                )?;
+                say!(out, "📄 SARIF report written to {}", sarif.display());
+            }
+            if let Some(code_quality) = code_quality {
+                std::fs::write(
+                    &code_quality,
+                    serde_json::to_string_pretty(&report.to_code_quality(&project_path))?,
+                )?;
+                say!(
+                    out,
+                    "📄 Code Quality report written to {}",
+                    code_quality.display()
+                );
+            }
+
+            say!(
+                out,
+                "{} error(s), {} warning(s), {} note(s)",
+                report.count(Severity::Error),
+                report.count(Severity::Warning),
+                report.count(Severity::Note)
             );
-            println!("📁 Navigate to the project directory:");
-            println!("   cd {}", project_path.display());
-            println!("🔨 Build your project:");
-            println!("   forgekit build");
+            out.data(&report)?;
+            if report.fails(fail_on) {
+                out.fail();
+            } else {
+                say!(out, "✅ All checks passed");
+            }
         }
-        Commands::Build { path } => {
+        Commands::Ci {
+            provider,
+            path,
+            sarif,
+        } => {
             let project_path = match path {
                 Some(p) => p,
                 None => std::env::current_dir()?,
             };
-            let forgekit = ForgeKit::new();
 
-            forgekit.build_project(&project_path).await?;
-            println!("✅ Build completed successfully");
+            let file =
+                CICDGenerator::generate(&project_path, provider, &CiOptions { sarif }).await?;
+            say!(out, "✅ Generated {}", file.display());
+            if sarif {
+                say!(
+                    out,
+                    "   The pipeline uploads {} from `forgekit check`",
+                    match provider {
+                        CiProvider::GitLab => diagnostics::CODE_QUALITY_FILE,
+                        _ => diagnostics::SARIF_FILE,
+                    }
+                );
+            }
         }
-        Commands::Package { path } => {
+        Commands::SyncScripts {
+            just,
+            make,
+            check,
+            path,
+        } => {
             let project_path = match path {
                 Some(p) => p,
                 None => std::env::current_dir()?,
             };
-            let forgekit = ForgeKit::new();
 
-            let package_path = forgekit.package_project(&project_path).await?;
-            println!("✅ Package created at {:?}", package_path);
+            let mut kinds = Vec::new();
+            if just {
+                kinds.push(ScriptFile::Justfile);
+            }
+            if make {
+                kinds.push(ScriptFile::Makefile);
+            }
+            let synced = scripts::sync(&project_path, &kinds, check)?;
+            for file in &synced {
+                match file.status {
+                    SyncStatus::Written => say!(out, "✅ Generated {}", file.path.display()),
+                    SyncStatus::Unchanged => say!(out, "✅ {} is up to date", file.path.display()),
+                    SyncStatus::Stale => say!(
+                        out,
+                        "❌ {} is out of date; run `forgekit sync-scripts`",
+                        file.path.display()
+                    ),
+                }
+            }
+            out.data(&synced)?;
+            if synced.iter().any(|file| file.status == SyncStatus::Stale) {
+                anyhow::bail!("Generated task runner files are out of date");
+            }
         }
-        Commands::BuildPackage { path } => {
+        Commands::Ui { path } => {
             let project_path = match path {
                 Some(p) => p,
                 None => std::env::current_dir()?,
             };
-            let forgekit = ForgeKit::new();
 
-            // Build first
-            forgekit.build_project(&project_path).await?;
-            println!("✅ Build completed");
+            tui::run(&project_path).await?;
+        }
+        Commands::Hooks { command } => match command {
+            HooksCommands::Install { path, force } => {
+                let project_path = match path {
+                    Some(p) => p,
+                    None => std::env::current_dir()?,
+                };
 
-            // Then package
-            let package_path = forgekit.package_project(&project_path).await?;
-            println!("✅ Package created at {:?}", package_path);
+                let report = GitHooks::install(&project_path, force).await?;
+                for hook in &report.installed {
+                    say!(out, "✅ Installed {} hook", hook);
+                }
+                for hook in &report.removed {
+                    say!(out, "🔧 Removed {} hook (no stages configured)", hook);
+                }
+                for hook in &report.skipped {
+                    say!(out,
+                        "⚠️  Existing {} hook was not written by ForgeKit; use --force to replace it",
+                        hook
+                    );
+                }
+                if report.installed.is_empty() && report.removed.is_empty() {
+                    say!(out, "No git hooks configured in [git_hooks]");
+                }
+            }
+            HooksCommands::Uninstall { path } => {
+                let project_path = match path {
+                    Some(p) => p,
+                    None => std::env::current_dir()?,
+                };
+
+                let removed = GitHooks::uninstall(&project_path).await?;
+                say!(out, "✅ Removed {} hook(s)", removed.len());
+            }
+            HooksCommands::Run { hook, path } => {
+                let project_path = match path {
+                    Some(p) => p,
+                    None => std::env::current_dir()?,
+                };
+
+                let failed = GitHooks::run(&project_path, &hook).await?;
+                out.data(serde_json::json!({ "hook": hook, "failed": failed }))?;
+                if !failed.is_empty() {
+                    say!(out, "❌ {} failed: {}", hook, failed.join(", "));
+                    say!(out, "   Set FORGEKIT_SKIP={} to bypass", failed.join(","));
+                    out.fail();
+                }
+            }
+        },
+        Commands::Daemon { command } => daemon(command, out).await?,
+        Commands::Config { command } => match command {
+            ConfigCommands::Resolve { path, env } => {
+                let project_path = match path {
+                    Some(p) => p,
+                    None => std::env::current_dir()?,
+                };
+
+                let mut config = ProjectConfig::load(project_path.join("forgekit.toml"))?
+                    .resolve(env.as_deref())?;
+                // Only the effective values; the layers they came from are already merged
+                config.extends = None;
+                config.include.clear();
+                config.env.clear();
+                say!(out, "{}", toml::to_string_pretty(&config)?.trim_end());
+                out.data(&config)?;
+            }
+        },
+        Commands::Schema { command } => match command {
+            SchemaCommands::Dump { output } => {
+                let schema = forgekit_core::schema::project_schema();
+                let contents = serde_json::to_string_pretty(&schema)?;
+                match output {
+                    Some(output) => {
+                        std::fs::write(&output, contents)?;
+                        say!(out, "✅ Wrote forgekit.toml schema to {}", output.display());
+                    }
+                    None => say!(out, "{}", contents),
+                }
+                out.data(&schema)?;
+            }
+            SchemaCommands::Install => {
+                let path = forgekit_core::schema::install()?;
+                say!(
+                    out,
+                    "✅ Installed forgekit.toml schema at {}",
+                    path.display()
+                );
+                say!(
+                    out,
+                    "   Add `#:schema {}` as the first line of forgekit.toml, or point a taplo rule for `**/forgekit.toml` at it",
+                    path.display()
+                );
+                out.data(serde_json::json!({ "schema_path": path }))?;
+            }
+        },
+        Commands::Env { command } => match command {
+            EnvCommands::Set { key, value, file } => {
+                let env_file = file.unwrap_or_else(|| PathBuf::from(".env"));
+                let mut manager =
+                    forgekit_core::env_manager::EnvManager::load_from_file(&env_file)?;
+                manager.set(key.clone(), value.clone());
+                manager.save_to_file(&env_file)?;
+                say!(out, "✅ Set {}={}", key, value);
+            }
+            EnvCommands::List { environment, path } => {
+                let project_path = match path {
+                    Some(p) => p,
+                    None => std::env::current_dir()?,
+                };
+
+                let manager = if let Some(env) = environment {
+                    forgekit_core::env_manager::EnvManager::load_for_environment(
+                        &env,
+                        &project_path,
+                    )?
+                } else {
+                    forgekit_core::env_manager::EnvManager::load_from_file(
+                        &project_path.join(".env"),
+                    )?
+                };
+
+                if manager.all().is_empty() {
+                    say!(out, "No environment variables set");
+                } else {
+                    let redactor = Redactor::for_project(&project_path)?;
+                    say!(out, "Environment variables:");
+                    for (key, value) in manager.all() {
+                        say!(out, "  {}={}", key, redactor.display_value(key, value));
+                    }
+                }
+            }
+            EnvCommands::Scaffold {
+                nix,
+                devcontainer,
+                force,
+                path,
+            } => {
+                let project_path = match path {
+                    Some(p) => p,
+                    None => std::env::current_dir()?,
+                };
+
+                let mut files = Vec::new();
+                if nix {
+                    files.push(dev_env::write_flake(&project_path, force)?);
+                }
+                if devcontainer {
+                    files.push(dev_env::write_devcontainer(&project_path, force)?);
+                }
+                for file in &files {
+                    say!(out, "✅ Generated {}", file.display());
+                }
+                if nix {
+                    say!(out, "   Enter the environment with `nix develop`");
+                }
+                out.data(serde_json::json!({ "files": files }))?;
+            }
+        },
+
+        Commands::Secret { command } => {
+            let store = credentials::default_store()?;
+            match command {
+                SecretCommands::Set { name, value } => {
+                    let value = match value {
+                        Some(value) => value,
+                        None => {
+                            eprint!("🔑 Value of secret '{}': ", name);
+                            std::io::stderr().flush()?;
+                            let mut line = String::new();
+                            std::io::stdin().read_line(&mut line)?;
+                            line.trim().to_string()
+                        }
+                    };
+                    if value.is_empty() {
+                        anyhow::bail!("No value provided");
+                    }
+                    store.set(&name, &value)?;
+                    say!(
+                        out,
+                        "✅ Stored secret '{}' in the {} credential store",
+                        name,
+                        store.backend()
+                    );
+                }
+                SecretCommands::Delete { name } => {
+                    if store.delete(&name)? {
+                        say!(out, "✅ Removed secret '{}'", name);
+                    } else {
+                        say!(out, "No secret named '{}'", name);
+                    }
+                }
+            }
+        }
+
+        Commands::Test {
+            path,
+            coverage,
+            retries,
+            quarantine_flaky,
+            ..
+        } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+
+            let config = ProjectConfig::load(project_path.join("forgekit.toml"))
+                .map(|config| config.test)
+                .unwrap_or_default();
+            let report = TestRunner::run_tests_with_retries(
+                &project_path,
+                &BuildOptions::default(),
+                retries.unwrap_or(config.retries),
+                &config.quarantine,
+            )
+            .await?;
+            let coverage_report = if coverage {
+                Some(TestRunner::generate_coverage_report(&project_path).await?)
+            } else {
+                None
+            };
+
+            say!(out, "Test Results:");
+            say!(out, "  Total: {}", report.total);
+            say!(out, "  Passed: {}", report.passed);
+            say!(out, "  Failed: {}", report.failed);
+            for test in &report.failed_tests {
+                say!(out, "    ❌ {}", test);
+            }
+            if !report.flaky.is_empty() {
+                say!(out, "  Flaky (passed on retry): {}", report.flaky.len());
+                for test in &report.flaky {
+                    say!(out, "    🔁 {}", test);
+                }
+            }
+            if !report.quarantined.is_empty() {
+                say!(out, "  Quarantined failures: {}", report.quarantined.len());
+                for test in &report.quarantined {
+                    say!(out, "    🧪 {}", test);
+                }
+            }
+            if let Some(coverage_report) = &coverage_report {
+                say!(out, "\nCoverage:");
+                say!(out, "  {:.2}%", coverage_report.coverage_percentage);
+                say!(
+                    out,
+                    "  Lines: {}/{}",
+                    coverage_report.lines_covered,
+                    coverage_report.total_lines
+                );
+            }
+
+            if quarantine_flaky && !report.flaky.is_empty() {
+                let added = TestRunner::quarantine(&project_path, &report.flaky)?;
+                if !added.is_empty() {
+                    say!(
+                        out,
+                        "\n🧪 Quarantined {} flaky test(s) in forgekit.toml",
+                        added.len()
+                    );
+                }
+            }
+
+            out.data(serde_json::json!({
+                "tests": {
+                    "total": report.total,
+                    "passed": report.passed,
+                    "failed": report.failed,
+                    "failed_tests": report.failed_tests,
+                    "flaky": report.flaky,
+                    "quarantined": report.quarantined,
+                },
+                "coverage": coverage_report.as_ref().map(|c| serde_json::json!({
+                    "percentage": c.coverage_percentage,
+                    "lines_covered": c.lines_covered,
+                    "total_lines": c.total_lines,
+                })),
+            }))?;
+
+            if report.failed > 0 {
+                say!(out, "\n❌ Some tests failed");
+                out.fail();
+            } else {
+                say!(out, "\n✅ All tests passed");
+            }
+        }
+        Commands::Flaky { path } => {
+            let project_path = match path {
+                Some(p) => p,
+                None => std::env::current_dir()?,
+            };
+
+            let rates = analytics::flake_rates(&project_path);
+            if rates.is_empty() {
+                say!(out, "✅ No flaky tests in the recorded test runs");
+            } else {
+                say!(out, "🔁 Flaky tests in the last {} run(s):", rates[0].runs);
+                for rate in &rates {
+                    say!(
+                        out,
+                        "   {:>5.1}%  {} ({} run(s))",
+                        rate.rate * 100.0,
+                        rate.test,
+                        rate.flaky_runs
+                    );
+                }
+            }
+            out.data(&rates)?;
         }
-        Commands::Run { path } => {
+        Commands::TestGenerate { name, kind, path } => {
             let project_path = match path {
                 Some(p) => p,
                 None => std::env::current_dir()?,
             };
-            let forgekit = ForgeKit::new();
 
-            // Build first
-            forgekit.build_project(&project_path).await?;
-            println!("✅ Build completed");
+            let scaffold = forgekit_core::testing::TestRunner::generate_test_scaffold_with(
+                &name,
+                &project_path,
+                kind,
+            )
+            .await?;
+            say!(
+                out,
+                "✅ Generated {} test scaffold at {:?}",
+                kind.as_str(),
+                scaffold.file
+            );
+            if let Some(dependency) = &scaffold.added_dependency {
+                say!(out, "📦 Added {} to [dev-dependencies]", dependency);
+            }
+            if !scaffold.enabled_features.is_empty() {
+                say!(
+                    out,
+                    "📦 Enabled features: {}",
+                    scaffold.enabled_features.join(", ")
+                );
+            }
+        }
+        Commands::Toolchain { command } => match command {
+            ToolchainCommands::Status { path } => {
+                let project_path = match path {
+                    Some(p) => p,
+                    None => std::env::current_dir()?,
+                };
+
+                let (config, pinned) = toolchain_config(&project_path)?;
+                let status = toolchain::status(&config).await;
+                print_toolchain_status(out, &status);
+                if let Some(channel) = pinned {
+                    say!(out, "📌 Pinned to {} in forgekit.toml", channel);
+                }
+                if !status.is_ready() {
+                    say!(out, "💡 Run `forgekit toolchain install` to set it up");
+                    out.fail();
+                }
+                out.data(&status)?;
+            }
+            ToolchainCommands::Install { path } => {
+                let project_path = match path {
+                    Some(p) => p,
+                    None => std::env::current_dir()?,
+                };
+
+                let (config, _) = toolchain_config(&project_path)?;
+                say!(
+                    out,
+                    "📥 Installing {} with {}",
+                    config.channel,
+                    toolchain::REQUIRED_COMPONENTS.join(", ")
+                );
+                let status = toolchain::install(&config).await?;
+                print_toolchain_status(out, &status);
+                say!(out, "✅ Toolchain ready to build the ledokoz target");
+                out.data(&status)?;
+            }
+            ToolchainCommands::Spec { path } => {
+                let project_path = match path {
+                    Some(p) => p,
+                    None => std::env::current_dir()?,
+                };
+
+                let spec = toolchain::write_spec(&project_path)?;
+                say!(out, "✅ Wrote the ledokoz target spec to {:?}", spec);
+                out.data(serde_json::json!({ "spec": spec }))?;
+            }
+            ToolchainCommands::Pin { channel, path } => {
+                let project_path = match path {
+                    Some(p) => p,
+                    None => std::env::current_dir()?,
+                };
+
+                let channel = toolchain::pin(&project_path, channel.as_deref())?;
+                say!(
+                    out,
+                    "📌 Pinned Rust {} and wrote {}",
+                    channel,
+                    toolchain::RUST_TOOLCHAIN_FILE
+                );
+                out.data(serde_json::json!({
+                    "channel": channel,
+                    "file": project_path.join(toolchain::RUST_TOOLCHAIN_FILE),
+                }))?;
+            }
+        },
+        Commands::Cache { command } => match command {
+            CacheCommands::Clear { path } => {
+                let project_path = match path {
+                    Some(p) => p,
+                    None => std::env::current_dir()?,
+                };
+
+                let cache_dir = project_path.join(".forgekit").join("cache");
+                let mut cache =
+                    forgekit_core::cache::BuildCache::new(cache_dir)?.with_lock(lock.clone());
+                cache.clear().await?;
+                say!(out, "✅ Cache cleared");
+            }
+            CacheCommands::Stats { path, by_namespace } => {
+                let project_path = match path {
+                    Some(p) => p,
+                    None => std::env::current_dir()?,
+                };
+
+                let cache_dir = project_path.join(".forgekit").join("cache");
+                let mut cache =
+                    forgekit_core::cache::BuildCache::new(cache_dir)?.with_lock(lock.clone());
+                cache.load().await?;
 
-            // Run the binary
-            let config =
-                forgekit_core::config::ProjectConfig::load(project_path.join("forgekit.toml"))?;
-            let binary_path = project_path
-                .join("target")
-                .join("ledokoz")
-                .join("release")
-                .join(&config.name);
+                let stats = cache.stats();
+                say!(out, "Cache Statistics:");
+                say!(out, "  Items: {}", stats.item_count);
+                say!(out, "  Size: {} bytes", stats.total_size);
+                say!(out, "  Hits: {}", stats.hits);
+                say!(out, "  Misses: {}", stats.misses);
+                say!(out, "  Hit Rate: {:.2}%", stats.hit_rate * 100.0);
 
-            println!("🏃 Running application...");
-            let status = tokio::process::Command::new(binary_path).status().await?;
+                if by_namespace {
+                    let namespaces = cache.stats_by_namespace();
+                    say!(out, "");
+                    say!(out, "By namespace:");
+                    for namespace in &namespaces {
+                        let name = if namespace.namespace.is_empty() {
+                            "(no namespace)"
+                        } else {
+                            &namespace.namespace
+                        };
+                        say!(
+                            out,
+                            "  {}: {} item(s), {} bytes",
+                            name,
+                            namespace.item_count,
+                            namespace.total_size
+                        );
+                    }
+                    out.data(serde_json::json!({
+                        "stats": stats,
+                        "namespaces": namespaces,
+                    }))?;
+                }
+            }
+        },
+        Commands::Store { command } => {
+            let store = forgekit_core::store::PackageStore::new(
+                forgekit_core::store::PackageStore::default_location(),
+            )?;
 
-            if status.success() {
-                println!("✅ Application exited successfully");
-            } else {
-                println!(
-                    "⚠️  Application exited with code: {}",
-                    status.code().unwrap_or(-1)
-                );
+            match command {
+                StoreCommands::Gc => {
+                    let report = store.gc()?;
+                    say!(
+                        out,
+                        "✅ Removed {} unused package(s), freed {} bytes ({} still in use)",
+                        report.removed,
+                        report.freed_bytes,
+                        report.kept
+                    );
+                }
+                StoreCommands::Path => {
+                    say!(out, "{}", store.root().display());
+                }
             }
         }
-        Commands::Add {
-            package,
-            version,
-            path,
+        Commands::Gc {
+            dry_run,
+            max_age,
+            mut projects,
         } => {
-            let project_path = match path {
-                Some(p) => p,
-                None => std::env::current_dir()?,
-            };
-
-            let package_manager = PackageManager::new(project_path.clone())?;
-            package_manager.add_dependency(&package, &version).await?;
-            println!("✅ Added dependency: {} v{}", package, version);
-        }
-        Commands::Remove { package, path } => {
-            let project_path = match path {
-                Some(p) => p,
-                None => std::env::current_dir()?,
-            };
+            use forgekit_core::gc::{collect_garbage, GcOptions};
 
-            let package_manager = PackageManager::new(project_path.clone())?;
-            package_manager.remove_dependency(&package).await?;
-            println!("✅ Removed dependency: {}", package);
-        }
-        Commands::Update { path } => {
-            let project_path = match path {
-                Some(p) => p,
-                None => std::env::current_dir()?,
+            let current = std::env::current_dir()?;
+            if current.join("forgekit.toml").is_file() {
+                projects.push(current);
+            }
+            let store = forgekit_core::store::PackageStore::new(
+                forgekit_core::store::PackageStore::default_location(),
+            )?;
+            let options = GcOptions {
+                projects,
+                cache_max_age: std::time::Duration::from_secs(max_age * 24 * 60 * 60),
+                lock: lock.clone(),
             };
+            let dry_run = DryRun::new(dry_run);
+            let report = collect_garbage(&store, &options, &dry_run).await?;
 
-            let package_manager = PackageManager::new(project_path.clone())?;
-            package_manager.update_dependencies().await?;
-            println!("✅ Dependencies updated");
-        }
-        Commands::Search { query } => {
-            let current_dir = std::env::current_dir()?;
-            let package_manager = PackageManager::new(current_dir)?;
-            let results = package_manager.search_packages(&query).await?;
-
-            if results.is_empty() {
-                println!("No packages found matching '{}'", query);
+            let verb = if dry_run.is_enabled() {
+                "Would free"
             } else {
-                println!("Found {} packages:", results.len());
-                for result in results {
-                    println!("  {}", result);
+                "Freed"
+            };
+            if dry_run.is_enabled() {
+                for item in &report.items {
+                    say!(
+                        out,
+                        "📝 Would remove {} {} ({} bytes)",
+                        item.category,
+                        item.path.display(),
+                        item.size
+                    );
                 }
             }
+            for total in report.totals() {
+                say!(
+                    out,
+                    "  {}: {} item(s), {} bytes",
+                    total.category,
+                    total.count,
+                    total.size
+                );
+            }
+            say!(
+                out,
+                "🧹 {} {} bytes across {} project(s)",
+                verb,
+                report.total_size(),
+                report.projects.len()
+            );
+            out.data(serde_json::json!({
+                "dry_run": dry_run.is_enabled(),
+                "projects": report.projects,
+                "items": report.items,
+                "totals": report.totals(),
+            }))?;
         }
-        Commands::Templates => {
-            println!("Available templates:");
-            println!("  basic    - Basic application template");
-            println!("  gui      - Graphical user interface application");
-            println!("  cli      - Command-line interface tool");
-            println!("  service  - Background service/daemon");
-            println!("  plugin   - ForgeKit plugin library");
-        }
-        Commands::Validate { path } => {
-            let project_path = match path {
-                Some(p) => p,
-                None => std::env::current_dir()?,
-            };
-
-            let report =
-                forgekit_core::validator::ProjectValidator::validate_project(&project_path).await?;
+        Commands::Crash { command } => match command {
+            CrashCommands::List { path } => {
+                let project_path = match path {
+                    Some(p) => p,
+                    None => std::env::current_dir()?,
+                };
 
-            if report.errors.is_empty() && report.warnings.is_empty() {
-                println!("✅ Project validation passed");
-            } else {
-                if !report.errors.is_empty() {
-                    println!("❌ Validation errors:");
-                    for error in &report.errors {
-                        println!("   - {}", error);
+                let config = ProjectConfig::load(project_path.join("forgekit.toml"))?;
+                let reports = crash::list(&project_path, &config.name)?;
+                if reports.is_empty() {
+                    say!(out, "✅ No crash reports for {}", config.name);
+                } else {
+                    say!(out, "💥 {} crash report(s):", reports.len());
+                    for report in &reports {
+                        say!(
+                            out,
+                            "   {} v{} {}: {}",
+                            report.crashed_at(),
+                            report.version,
+                            report.path.display(),
+                            report.message
+                        );
                     }
                 }
-                if !report.warnings.is_empty() {
-                    println!("⚠️  Validation warnings:");
-                    for warning in &report.warnings {
-                        println!("   - {}", warning);
+                out.data(&reports)?;
+            }
+            CrashCommands::Symbolicate { report, path } => {
+                let project_path = match path {
+                    Some(p) => p,
+                    None => std::env::current_dir()?,
+                };
+
+                let report = match report {
+                    Some(report) => {
+                        let mut report = crash::CrashReport::load(&report)?;
+                        report.redact(&Redactor::for_project(&project_path)?);
+                        report
+                    }
+                    None => {
+                        let config = ProjectConfig::load(project_path.join("forgekit.toml"))?;
+                        crash::list(&project_path, &config.name)?
+                            .into_iter()
+                            .next()
+                            .ok_or_else(|| anyhow::anyhow!("No crash reports found"))?
                     }
+                };
+                let report = crash::symbolicate(&project_path, &report).await?;
+                say!(out, "{}", report.stack_trace());
+                out.data(&report)?;
+            }
+            CrashCommands::Install { path } => {
+                let project_path = match path {
+                    Some(p) => p,
+                    None => std::env::current_dir()?,
+                };
+
+                if crash::install_handler(&project_path)? {
+                    say!(out, "✅ Wrote src/{}", crash::HANDLER_FILE);
+                    say!(
+                        out,
+                        "   Add `mod forgekit_crash;` and call `forgekit_crash::install();` first in main"
+                    );
+                } else {
+                    say!(out, "✅ Crash handler is up to date");
                 }
             }
+        },
+        Commands::Artifacts { command } => match command {
+            ArtifactsCommands::List { profile, path } => {
+                let project_path = match path {
+                    Some(p) => p,
+                    None => std::env::current_dir()?,
+                };
 
-            if !report.is_valid {
-                std::process::exit(1);
+                let artifacts = artifacts::list(&project_path, profile.as_deref())?;
+                if artifacts.is_empty() {
+                    say!(out, "📭 No artifacts recorded yet");
+                }
+                for artifact in &artifacts {
+                    let commit = artifact.git_sha.as_deref().unwrap_or("-");
+                    say!(
+                        out,
+                        "📦 {:<28} {:<10} {:>10} bytes  {:.10}  {}",
+                        artifact.id,
+                        artifact.profile,
+                        artifact.size,
+                        commit,
+                        artifact.created_at
+                    );
+                }
+                out.data(&artifacts)?;
             }
-        }
-        Commands::Env { command } => match command {
-            EnvCommands::Set { key, value, file } => {
-                let env_file = file.unwrap_or_else(|| PathBuf::from(".env"));
-                let mut manager =
-                    forgekit_core::env_manager::EnvManager::load_from_file(&env_file)?;
-                manager.set(key.clone(), value.clone());
-                manager.save_to_file(&env_file)?;
-                println!("✅ Set {}={}", key, value);
+            ArtifactsCommands::Diff {
+                from,
+                to,
+                profile,
+                path,
+            } => {
+                let project_path = match path {
+                    Some(p) => p,
+                    None => std::env::current_dir()?,
+                };
+
+                let diff = ArtifactDiff::new(
+                    artifacts::find(&project_path, profile.as_deref(), &from)?,
+                    artifacts::find(&project_path, profile.as_deref(), &to)?,
+                );
+                say!(out, "📦 {} → {}", diff.from.id, diff.to.id);
+                say!(
+                    out,
+                    "  Size: {} → {} bytes ({:+})",
+                    diff.from.size,
+                    diff.to.size,
+                    diff.size_delta
+                );
+                say!(
+                    out,
+                    "  Commit: {} → {}",
+                    diff.from.git_sha.as_deref().unwrap_or("-"),
+                    diff.to.git_sha.as_deref().unwrap_or("-")
+                );
+                if diff.identical {
+                    say!(out, "  Contents are identical");
+                    return out.data(&diff);
+                }
+                let contents = package_diff::diff(
+                    &diff.from.path(&project_path),
+                    &diff.to.path(&project_path),
+                )?;
+                print_package_diff(out, &contents);
+                out.data(serde_json::json!({ "artifacts": diff, "contents": contents }))?;
             }
-            EnvCommands::List { environment, path } => {
+            ArtifactsCommands::Clean {
+                keep,
+                profile,
+                path,
+            } => {
                 let project_path = match path {
                     Some(p) => p,
                     None => std::env::current_dir()?,
                 };
 
-                let manager = if let Some(env) = environment {
-                    forgekit_core::env_manager::EnvManager::load_for_environment(
-                        &env,
-                        &project_path,
-                    )?
-                } else {
-                    forgekit_core::env_manager::EnvManager::load_from_file(
-                        &project_path.join(".env"),
-                    )?
+                let removed = artifacts::clean(&project_path, profile.as_deref(), keep)?;
+                say!(out, "🧹 Removed {} artifact(s)", removed.len());
+                out.data(&removed)?;
+            }
+            ArtifactsCommands::Restore {
+                artifact,
+                profile,
+                output,
+                path,
+            } => {
+                let project_path = match path {
+                    Some(p) => p,
+                    None => std::env::current_dir()?,
                 };
 
-                if manager.all().is_empty() {
-                    println!("No environment variables set");
-                } else {
-                    println!("Environment variables:");
-                    for (key, value) in manager.all() {
-                        println!("  {}={}", key, value);
+                let artifact = artifacts::find(&project_path, profile.as_deref(), &artifact)?;
+                let output = match output {
+                    Some(output) => output,
+                    None => {
+                        let config = ProjectConfig::load(project_path.join("forgekit.toml"))?;
+                        forgekit_core::packager::output_path(&project_path, &config)
                     }
+                };
+                if let Some(dir) = output.parent() {
+                    std::fs::create_dir_all(dir)?;
                 }
+                std::fs::copy(artifact.path(&project_path), &output)?;
+                say!(out, "✅ Restored {} to {}", artifact.id, output.display());
+                out.data(serde_json::json!({ "artifact": artifact, "output": output }))?;
             }
         },
+        Commands::Symbols { command } => match command {
+            SymbolsCommands::Upload {
+                version,
+                server,
+                path,
+            } => {
+                let project_path = match path {
+                    Some(p) => p,
+                    None => std::env::current_dir()?,
+                };
 
-        Commands::Test {
-            path,
-            coverage,
-            format,
+                let config = ProjectConfig::load(project_path.join("forgekit.toml"))?;
+                let version = version.unwrap_or_else(|| config.version.clone());
+                let server = server
+                    .or(config.build.symbols.server.clone())
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "No symbol server configured; pass --server or set [build.symbols] server"
+                        )
+                    })?;
+
+                let uploaded =
+                    symbols::upload(&project_path, &config.name, &version, &server).await?;
+                say!(
+                    out,
+                    "✅ Uploaded {} symbol file(s) for {} {} to {}",
+                    uploaded,
+                    config.name,
+                    version,
+                    server
+                );
+                out.data(serde_json::json!({
+                    "version": version,
+                    "server": server,
+                    "uploaded": uploaded,
+                }))?;
+            }
+        },
+        Commands::Upgrade {
+            check,
+            channel,
+            force,
         } => {
-            let project_path = match path {
-                Some(p) => p,
-                None => std::env::current_dir()?,
-            };
+            use forgekit_core::self_update::{Updater, CURRENT_VERSION};
 
-            if coverage {
-                let (test_report, coverage_report) =
-                    forgekit_core::testing::TestRunner::run_tests_with_coverage(&project_path)
-                        .await?;
+            let config_path = GlobalConfig::default_path();
+            let mut config = GlobalConfig::load(&config_path)?;
+            if let Some(channel) = channel {
+                if channel != config.update.channel {
+                    config.update.channel = channel;
+                    config.save(&config_path)?;
+                    say!(out, "📡 Following the {} channel", channel);
+                }
+            }
 
-                if format == "json" {
-                    let json = serde_json::json!({
-                        "tests": {
-                            "total": test_report.total,
-                            "passed": test_report.passed,
-                            "failed": test_report.failed,
-                        },
-                        "coverage": {
-                            "percentage": coverage_report.coverage_percentage,
-                            "lines_covered": coverage_report.lines_covered,
-                            "total_lines": coverage_report.total_lines,
-                        }
-                    });
-                    println!("{}", serde_json::to_string_pretty(&json)?);
-                } else {
-                    println!("Test Results:");
-                    println!("  Total: {}", test_report.total);
-                    println!("  Passed: {}", test_report.passed);
-                    println!("  Failed: {}", test_report.failed);
-                    println!("\nCoverage:");
-                    println!("  {:.2}%", coverage_report.coverage_percentage);
-                    println!(
-                        "  Lines: {}/{}",
-                        coverage_report.lines_covered, coverage_report.total_lines
+            let updater = Updater::new(&config.update)?;
+            let release = updater.latest().await?;
+            let install = release.is_newer() || (force && release.version != CURRENT_VERSION);
+            out.data(serde_json::json!({
+                "current": CURRENT_VERSION,
+                "channel": updater.channel(),
+                "latest": release,
+                "update_available": release.is_newer(),
+            }))?;
+            if !install {
+                say!(
+                    out,
+                    "✅ forgekit {} is up to date ({} channel)",
+                    CURRENT_VERSION,
+                    updater.channel()
+                );
+            } else if check {
+                say!(
+                    out,
+                    "⬆️  forgekit {} is available ({} channel, running {}); run `forgekit upgrade`",
+                    release.version,
+                    updater.channel(),
+                    CURRENT_VERSION
+                );
+            } else {
+                say!(out, "⬇️  Downloading forgekit {}...", release.version);
+                let exe = std::env::current_exe()?.canonicalize()?;
+                updater.install(&release, &exe).await?;
+                say!(
+                    out,
+                    "✅ Upgraded forgekit {} → {}",
+                    CURRENT_VERSION,
+                    release.version
+                );
+                if let Some(notes) = &release.notes {
+                    say!(out, "{}", notes);
+                }
+            }
+        }
+        Commands::Telemetry { command } => {
+            let config_path = GlobalConfig::default_path();
+            let mut config = GlobalConfig::load(&config_path)?;
+            let store = TelemetryStore::new(&TelemetryStore::default_path());
+
+            match command {
+                TelemetryCommands::Status => {
+                    let events = store.events()?.len();
+                    let state = if config.telemetry.enabled {
+                        "enabled"
+                    } else {
+                        "disabled"
+                    };
+                    say!(out, "📊 Telemetry is {}", state);
+                    say!(
+                        out,
+                        "   {} event(s) stored in {}",
+                        events,
+                        store.path().display()
                     );
+                    if let Some(endpoint) = &config.telemetry.endpoint {
+                        say!(out, "   Upload endpoint: {}", endpoint);
+                    }
+                    out.data(serde_json::json!({
+                        "enabled": config.telemetry.enabled,
+                        "endpoint": config.telemetry.endpoint,
+                        "events": events,
+                    }))?;
                 }
-            } else {
-                let report = forgekit_core::testing::TestRunner::run_tests(&project_path).await?;
-
-                if format == "json" {
-                    let json = serde_json::json!({
-                        "total": report.total,
-                        "passed": report.passed,
-                        "failed": report.failed,
-                    });
-                    println!("{}", serde_json::to_string_pretty(&json)?);
-                } else {
-                    println!("Test Results:");
-                    println!("  Total: {}", report.total);
-                    println!("  Passed: {}", report.passed);
-                    println!("  Failed: {}", report.failed);
-
-                    if report.failed > 0 {
-                        println!("\n❌ Some tests failed");
-                        std::process::exit(1);
+                TelemetryCommands::Enable { endpoint } => {
+                    config.telemetry.enabled = true;
+                    if endpoint.is_some() {
+                        config.telemetry.endpoint = endpoint;
+                    }
+                    config.save(&config_path)?;
+                    say!(
+                        out,
+                        "✅ Telemetry enabled; command names, durations, exit codes and cache hit rates are stored locally"
+                    );
+                }
+                TelemetryCommands::Disable => {
+                    config.telemetry.enabled = false;
+                    config.save(&config_path)?;
+                    store.clear()?;
+                    say!(out, "✅ Telemetry disabled and collected events deleted");
+                }
+                TelemetryCommands::Show => {
+                    let summary = store.summary()?;
+                    if summary.events == 0 {
+                        say!(out, "📊 No telemetry events collected");
                     } else {
-                        println!("\n✅ All tests passed");
+                        say!(out, "📊 {} event(s) collected:", summary.events);
+                        for stats in &summary.commands {
+                            let cache = stats
+                                .avg_cache_hit_rate
+                                .map(|rate| format!(", {:.0}% cache hits", rate * 100.0))
+                                .unwrap_or_default();
+                            say!(
+                                out,
+                                "   {:<16} {} run(s), {} failed, avg {}ms{}",
+                                stats.command,
+                                stats.runs,
+                                stats.failures,
+                                stats.avg_duration_ms,
+                                cache
+                            );
+                            for (code, count) in &stats.error_codes {
+                                say!(out, "      {} x{}", code, count);
+                            }
+                        }
                     }
+                    out.data(&summary)?;
+                }
+                TelemetryCommands::Upload => {
+                    let sent = store.upload(&config.telemetry).await?;
+                    say!(out, "✅ Uploaded {} telemetry event(s)", sent);
+                    out.data(serde_json::json!({ "uploaded": sent }))?;
                 }
             }
         }
-        Commands::TestGenerate { name, path } => {
-            let project_path = match path {
-                Some(p) => p,
-                None => std::env::current_dir()?,
+        Commands::Login {
+            registry,
+            token,
+            url,
+            expires_in_days,
+        } => {
+            let token = match token {
+                Some(token) => token,
+                None => {
+                    eprint!("🔑 Paste your token for '{}': ", registry);
+                    std::io::stderr().flush()?;
+                    let mut line = String::new();
+                    std::io::stdin().read_line(&mut line)?;
+                    line.trim().to_string()
+                }
             };
+            if token.is_empty() {
+                anyhow::bail!("No token provided");
+            }
 
-            let test_file =
-                forgekit_core::testing::TestRunner::generate_test_scaffold(&name, &project_path)
-                    .await?;
-            println!("✅ Generated test scaffold at {:?}", test_file);
+            let url = match url {
+                Some(url) => url,
+                None => forgekit_core::config::GlobalConfig::load(
+                    forgekit_core::config::GlobalConfig::default_path(),
+                )?
+                .registries
+                .get(&registry)
+                .map(|entry| entry.url.clone())
+                .unwrap_or_else(|| forgekit_core::registry::RegistryConfig::default().base_url),
+            };
+            forgekit_core::registry::login(&registry, &url, &token, expires_in_days)?;
+            say!(out, "✅ Logged in to '{}' ({})", registry, url);
         }
-        Commands::Cache { command } => match command {
-            CacheCommands::Clear { path } => {
-                let project_path = match path {
-                    Some(p) => p,
-                    None => std::env::current_dir()?,
+        Commands::Logout { registry } => {
+            if forgekit_core::registry::logout(&registry)? {
+                say!(out, "✅ Logged out of '{}'", registry);
+            } else {
+                say!(out, "No credentials stored for '{}'", registry);
+            }
+        }
+        Commands::Registry { command } => match command {
+            RegistryCommands::Serve {
+                dir,
+                bind,
+                port,
+                upstream,
+                token,
+            } => {
+                std::fs::create_dir_all(&dir)?;
+                let token = token.or_else(|| std::env::var(registry_server::TOKEN_ENV).ok());
+                let config = RegistryServerConfig {
+                    root: dir,
+                    addr: (bind, port).into(),
+                    upstream,
+                    token,
                 };
+                say!(
+                    out,
+                    "📦 Serving {} on http://{}",
+                    config.root.display(),
+                    config.addr
+                );
+                say!(
+                    out,
+                    "   Point clients at it with `forgekit login --url http://{}`",
+                    config.addr
+                );
+                RegistryServer::new(config)?.serve(cancel).await?;
+            }
+        },
+        Commands::Demo => {
+            let registry = MockRegistry::start().await?;
+            let scratch = tempfile::TempDir::new()?;
+            let client = registry.client(&scratch.path().join("client"))?;
+            say!(
+                out,
+                "🧪 Started a mock registry on {} with {} packages",
+                registry.url(),
+                mock::MOCK_PACKAGES.len()
+            );
+            say!(out, "   Nothing here touches GitHub or your own projects");
 
-                let cache_dir = project_path.join(".forgekit").join("cache");
-                let mut cache = forgekit_core::cache::BuildCache::new(cache_dir)?;
-                cache.clear().await?;
-                println!("✅ Cache cleared");
+            say!(out, "");
+            say!(out, "$ forgekit search forgekit");
+            for package in client.search_packages("forgekit").await? {
+                say!(out, "  {} v{}", package.name, package.version);
             }
-            CacheCommands::Stats { path } => {
-                let project_path = match path {
-                    Some(p) => p,
-                    None => std::env::current_dir()?,
-                };
 
-                let cache_dir = project_path.join(".forgekit").join("cache");
-                let mut cache = forgekit_core::cache::BuildCache::new(cache_dir)?;
-                cache.load_from_disk()?;
+            say!(out, "");
+            say!(out, "$ forgekit add forgekit-http --version 0.2.0");
+            let project_path = scratch.path().join("demo-app");
+            std::fs::create_dir_all(&project_path)?;
+            ProjectConfig {
+                name: "demo-app".to_string(),
+                ..Default::default()
+            }
+            .save(project_path.join("forgekit.toml"))?;
+            let package_manager = PackageManager::new(project_path.clone())?
+                .with_registry_client(client.clone())
+                .with_store(PackageStore::new(scratch.path().join("store"))?)
+                .with_lock(lock.clone())
+                .with_progress(download_progress_bar());
+            package_manager
+                .add_dependency("forgekit-http", "0.2.0")
+                .await?;
+            say!(out, "✅ Added dependency: forgekit-http v0.2.0");
 
-                let stats = cache.stats();
-                println!("Cache Statistics:");
-                println!("  Items: {}", stats.item_count);
-                println!("  Size: {} bytes", stats.total_size);
-                println!("  Hits: {}", stats.hits);
-                println!("  Misses: {}", stats.misses);
-                println!("  Hit Rate: {:.2}%", stats.hit_rate * 100.0);
+            say!(out, "");
+            say!(out, "$ forgekit publish --remote");
+            let archive = mock::library(scratch.path(), "demo-lib", "0.1.0").await?;
+            let info = client.publish_remote(&archive).await?;
+            say!(
+                out,
+                "📤 Published demo-lib v{} to {}",
+                info.version,
+                registry.url()
+            );
+            for package in client.search_packages("demo").await? {
+                say!(out, "  {} v{}", package.name, package.version);
             }
-        },
+
+            say!(out, "");
+            say!(
+                out,
+                "Host a registry of your own with `forgekit registry serve`"
+            );
+        }
     }
 
     Ok(())
 }
+
+/// Build a progress callback that renders a download progress bar on stderr
+fn download_progress_bar() -> ProgressCallback {
+    Arc::new(|progress: &DownloadProgress| {
+        let line = match progress.total {
+            Some(total) if total > 0 => {
+                format_progress(&progress.package, progress.downloaded, total)
+            }
+            _ => format!("📦 {} {} KiB", progress.package, progress.downloaded / 1024),
+        };
+        print_progress(&line, progress.finished);
+    })
+}
+
+/// Build a progress callback that renders an upload progress bar on stderr
+fn upload_progress_bar() -> UploadProgressCallback {
+    Arc::new(|progress: &UploadProgress| {
+        let line = format_progress(&progress.package, progress.uploaded, progress.total);
+        print_progress(&line, progress.finished);
+    })
+}
+
+/// Build a progress callback that renders aggregate install progress on stderr
+fn install_progress_bar() -> InstallProgressCallback {
+    Arc::new(|progress: &InstallProgress| {
+        let label = format!("{}/{} packages", progress.completed, progress.total);
+        let line = format_progress(&label, progress.completed as u64, progress.total as u64);
+        print_progress(&line, progress.completed == progress.total);
+    })
+}
+
+/// Format a progress bar line
+fn format_progress(label: &str, done: u64, total: u64) -> String {
+    const WIDTH: u64 = 30;
+
+    let done = done.min(total);
+    let filled = (done * WIDTH / total.max(1)) as usize;
+    format!(
+        "📦 {} [{}{}] {}%",
+        label,
+        "#".repeat(filled),
+        "-".repeat(WIDTH as usize - filled),
+        done * 100 / total.max(1)
+    )
+}
+
+/// Template named on the command line, falling back to the basic one
+fn parse_template(template: &str) -> TemplateType {
+    template.parse().unwrap_or_else(|_| {
+        eprintln!("Unknown template: {}. Using basic template.", template);
+        TemplateType::Basic
+    })
+}
+
+/// Ask a question on stderr, returning `default` for an empty answer
+fn ask(question: &str, default: &str) -> Result<String> {
+    eprint!("❓ {} [{}]: ", question, default);
+    std::io::stderr().flush()?;
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line)?;
+    let answer = line.trim();
+    Ok(if answer.is_empty() {
+        default.to_string()
+    } else {
+        answer.to_string()
+    })
+}
+
+/// Print files as an indented tree, directories first seen on their first file
+fn print_tree(out: &mut Output, files: &[PathBuf]) {
+    let mut shown = std::collections::HashSet::new();
+    for file in files {
+        let components: Vec<_> = file.components().collect();
+        let mut prefix = PathBuf::new();
+        for (depth, component) in components.iter().enumerate() {
+            prefix.push(component);
+            if shown.insert(prefix.clone()) {
+                let suffix = if depth + 1 < components.len() {
+                    "/"
+                } else {
+                    ""
+                };
+                say!(
+                    out,
+                    "   {}{}{}",
+                    "  ".repeat(depth),
+                    component.as_os_str().to_string_lossy(),
+                    suffix
+                );
+            }
+        }
+    }
+}
+
+/// Print the actions recorded by a dry run
+fn print_plan(out: &mut Output, dry_run: &DryRun) -> Result<()> {
+    let plan = dry_run.plan();
+    if plan.actions.is_empty() {
+        say!(out, "📝 Nothing to do");
+    }
+    for action in &plan.actions {
+        say!(out, "📝 Would {}", action);
+    }
+    out.data(plan)
+}
+
+/// Print the record of a build: outcome, command, environment and diagnostics
+fn print_diagnostic(out: &mut Output, diagnostic: &Diagnostic) {
+    let icon = match diagnostic.severity {
+        Severity::Error => "❌",
+        Severity::Warning => "⚠️ ",
+        Severity::Note => "ℹ️ ",
+    };
+    let location = match (&diagnostic.file, diagnostic.line, diagnostic.column) {
+        (Some(file), Some(line), Some(column)) => {
+            format!("{}:{}:{}: ", file.display(), line, column)
+        }
+        (Some(file), Some(line), None) => format!("{}:{}: ", file.display(), line),
+        (Some(file), None, _) => format!("{}: ", file.display()),
+        _ => String::new(),
+    };
+    let code = diagnostic
+        .code
+        .as_ref()
+        .map(|c| format!(" [{}]", c))
+        .unwrap_or_default();
+    say!(
+        out,
+        "{} {}{}{} ({})",
+        icon,
+        location,
+        diagnostic.message,
+        code,
+        diagnostic.source
+    );
+    if let Some(fix) = &diagnostic.fix {
+        say!(out, "   💡 {}", fix);
+    }
+}
+
+fn print_build_log(out: &mut Output, log: &BuildLog) {
+    say!(
+        out,
+        "{} Build {} {} in {:.1}s ({} error(s), {} warning(s))",
+        if log.success { "✅" } else { "❌" },
+        log.id,
+        if log.success { "succeeded" } else { "failed" },
+        log.duration_ms as f64 / 1000.0,
+        log.errors,
+        log.warnings
+    );
+    let invocation = &log.invocation;
+    say!(
+        out,
+        "   Command: {} {}",
+        invocation.program,
+        invocation.args.join(" ")
+    );
+    if let Some(environment) = &invocation.environment {
+        say!(
+            out,
+            "   Environment profile: {} ({} variable(s))",
+            environment,
+            invocation.profile_vars.len()
+        );
+    }
+    say!(out, "   Environment: {} variable(s)", invocation.env.len());
+    for diagnostic in &log.diagnostics {
+        say!(out, "   {}", diagnostic);
+    }
+}
+
+/// Print what changed between two packages, biggest size growth first
+fn print_package_diff(out: &mut Output, diff: &PackageDiff) {
+    say!(
+        out,
+        "📦 Package size: {} → {} bytes ({:+})",
+        diff.old_size,
+        diff.new_size,
+        diff.size_delta()
+    );
+    if let Some(delta) = diff.binary_delta() {
+        say!(out, "  Binary: {:+} bytes", delta);
+    }
+    if diff.is_empty() {
+        say!(out, "  No entries changed");
+        return;
+    }
+    say!(
+        out,
+        "  {} added, {} removed, {} modified, {} unchanged",
+        diff.of_kind(ChangeKind::Added).count(),
+        diff.of_kind(ChangeKind::Removed).count(),
+        diff.of_kind(ChangeKind::Modified).count(),
+        diff.unchanged
+    );
+    for entry in &diff.entries {
+        say!(
+            out,
+            "  {:<8} {} ({:+} bytes)",
+            entry.kind,
+            entry.path,
+            entry.size_delta()
+        );
+    }
+    for change in &diff.manifest_changes {
+        say!(
+            out,
+            "  ⚙️  {} {}: {} → {}",
+            change.file,
+            change.key,
+            change.old.as_deref().unwrap_or("(unset)"),
+            change.new.as_deref().unwrap_or("(unset)")
+        );
+    }
+    let growth = diff.largest_growth(3);
+    if !growth.is_empty() {
+        say!(out, "📈 Largest growth:");
+        for entry in growth {
+            say!(out, "  {} ({:+} bytes)", entry.path, entry.size_delta());
+        }
+    }
+}
+
+/// Print the outcome of a watch build and redraw the status line on stderr
+fn print_watch_status(status: &WatchStatus) {
+    let mut stderr = std::io::stderr();
+    // Clear the previous status line before printing above it
+    let _ = write!(stderr, "\r\x1b[2K");
+    if let Some(changed) = status.changed.first() {
+        let _ = writeln!(
+            stderr,
+            "🔄 {} changed file(s), e.g. {}",
+            status.changed.len(),
+            changed.display()
+        );
+    }
+    if let Some(message) = &status.message {
+        let _ = writeln!(stderr, "❌ {}", message.trim_end());
+    }
+
+    let icon = if status.success { "✅" } else { "❌" };
+    let _ = write!(stderr, "{} {}", icon, status.status_line());
+    let _ = stderr.flush();
+}
+
+/// Redraw the current progress line on stderr
+fn print_progress(line: &str, finished: bool) {
+    let mut stderr = std::io::stderr();
+    let _ = write!(stderr, "\r{}", line);
+    if finished {
+        let _ = writeln!(stderr);
+    }
+    let _ = stderr.flush();
+}
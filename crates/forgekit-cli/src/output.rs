@@ -0,0 +1,90 @@
+//! Text and JSON output for CLI commands
+
+use forgekit_core::output::{CommandOutput, OutputFormat};
+use serde::Serialize;
+
+/// Print a message in text mode, or collect it into the JSON envelope
+macro_rules! say {
+    ($out:expr, $($arg:tt)*) => {
+        $out.say(format!($($arg)*))
+    };
+}
+pub(crate) use say;
+
+/// Output sink for a single command invocation
+pub struct Output {
+    format: OutputFormat,
+    envelope: CommandOutput,
+    cache_hit_rate: Option<f64>,
+}
+
+impl Output {
+    /// Create the output for a command
+    pub fn new(format: OutputFormat, command: &str) -> Self {
+        Self {
+            format,
+            envelope: CommandOutput::new(command),
+            cache_hit_rate: None,
+        }
+    }
+
+    /// Print a human readable line
+    pub fn say(&mut self, line: String) {
+        match self.format {
+            OutputFormat::Text | OutputFormat::Markdown => println!("{}", line),
+            OutputFormat::Json => self.envelope.messages.push(line),
+        }
+    }
+
+    /// Whether a Markdown report was requested
+    pub fn is_markdown(&self) -> bool {
+        matches!(self.format, OutputFormat::Markdown)
+    }
+
+    /// Whether output is meant for a person rather than a program
+    pub fn is_text(&self) -> bool {
+        matches!(self.format, OutputFormat::Text)
+    }
+
+    /// Set the structured result of the command
+    pub fn data<T: Serialize>(&mut self, data: T) -> anyhow::Result<()> {
+        self.envelope.data = serde_json::to_value(data)?;
+        Ok(())
+    }
+
+    /// Report the compiler cache hit rate of a build for telemetry
+    pub fn set_cache_hit_rate(&mut self, rate: f64) {
+        self.cache_hit_rate = Some(rate);
+    }
+
+    /// Compiler cache hit rate reported by the command
+    pub fn cache_hit_rate(&self) -> Option<f64> {
+        self.cache_hit_rate
+    }
+
+    /// Mark the command as having reported failures
+    pub fn fail(&mut self) {
+        self.envelope.fail();
+    }
+
+    /// Print the final result and return the process exit code
+    pub fn finish(mut self, result: anyhow::Result<()>) -> i32 {
+        if let Err(e) = &result {
+            self.envelope.set_error(format!("{:#}", e));
+        }
+
+        match self.format {
+            OutputFormat::Json => match serde_json::to_string_pretty(&self.envelope) {
+                Ok(json) => println!("{}", json),
+                Err(e) => eprintln!("Error: {}", e),
+            },
+            OutputFormat::Text | OutputFormat::Markdown => {
+                if let Err(e) = result {
+                    eprintln!("Error: {:?}", e);
+                }
+            }
+        }
+
+        self.envelope.exit_code
+    }
+}
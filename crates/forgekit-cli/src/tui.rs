@@ -0,0 +1,163 @@
+//! Interactive terminal dashboard for `forgekit ui`
+
+use forgekit_core::dashboard::{Dashboard, DashboardAction, TaskStatus};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::path::Path;
+use std::time::Duration;
+
+/// How often the dashboard polls for input and dev-server changes
+const TICK: Duration = Duration::from_millis(250);
+
+/// Run the dashboard until the user quits
+pub async fn run(project_path: &Path) -> anyhow::Result<()> {
+    let mut dashboard = Dashboard::load(project_path)?;
+    dashboard.log("Press b to build, t to test, p to package, r to refresh, q to quit");
+
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &mut dashboard).await;
+    ratatui::restore();
+    result
+}
+
+async fn event_loop(
+    terminal: &mut DefaultTerminal,
+    dashboard: &mut Dashboard,
+) -> anyhow::Result<()> {
+    loop {
+        terminal.draw(|frame| draw(frame, dashboard))?;
+
+        if !event::poll(TICK)? {
+            dashboard.poll_dev_server();
+            continue;
+        }
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        let action = match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Char('b') => DashboardAction::Build,
+            KeyCode::Char('t') => DashboardAction::Test,
+            KeyCode::Char('p') => DashboardAction::Package,
+            KeyCode::Char('r') => DashboardAction::Refresh,
+            _ => continue,
+        };
+
+        // Show the running state before blocking on the task
+        dashboard.start(action);
+        terminal.draw(|frame| draw(frame, dashboard))?;
+        dashboard.run(action).await;
+    }
+}
+
+fn draw(frame: &mut Frame, dashboard: &Dashboard) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Length(8),
+            Constraint::Min(5),
+            Constraint::Length(1),
+        ])
+        .split(frame.area());
+
+    let title = Paragraph::new(Line::from(vec![
+        Span::styled("ForgeKit ", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(format!(
+            "{} ({})",
+            dashboard.project_name,
+            dashboard.project_path.display()
+        )),
+    ]))
+    .block(Block::default().borders(Borders::ALL));
+    frame.render_widget(title, rows[0]);
+
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(34),
+            Constraint::Percentage(33),
+            Constraint::Percentage(33),
+        ])
+        .split(rows[1]);
+    draw_status(frame, dashboard, columns[0]);
+    draw_cache(frame, dashboard, columns[1]);
+    draw_dependencies(frame, dashboard, columns[2]);
+
+    let visible = rows[2].height.saturating_sub(2) as usize;
+    let logs: Vec<ListItem> = dashboard
+        .logs
+        .iter()
+        .skip(dashboard.logs.len().saturating_sub(visible))
+        .map(|line| ListItem::new(line.as_str()))
+        .collect();
+    frame.render_widget(
+        List::new(logs).block(Block::default().title("Logs").borders(Borders::ALL)),
+        rows[2],
+    );
+
+    frame.render_widget(
+        Paragraph::new("[b]uild  [t]est  [p]ackage  [r]efresh  [q]uit"),
+        rows[3],
+    );
+}
+
+fn draw_status(frame: &mut Frame, dashboard: &Dashboard, area: Rect) {
+    let lines = vec![
+        status_line("Build", &dashboard.build),
+        status_line("Tests", &dashboard.tests),
+        status_line("Package", &dashboard.package),
+    ];
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::default().title("Status").borders(Borders::ALL)),
+        area,
+    );
+}
+
+fn draw_cache(frame: &mut Frame, dashboard: &Dashboard, area: Rect) {
+    let cache = &dashboard.cache;
+    let lines = vec![
+        Line::from(format!("Items: {}", cache.item_count)),
+        Line::from(format!("Size: {} bytes", cache.total_size)),
+        Line::from(format!("Hit rate: {:.2}%", cache.hit_rate * 100.0)),
+    ];
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::default().title("Cache").borders(Borders::ALL)),
+        area,
+    );
+}
+
+fn draw_dependencies(frame: &mut Frame, dashboard: &Dashboard, area: Rect) {
+    let items: Vec<ListItem> = dashboard
+        .dependencies
+        .iter()
+        .map(|dep| ListItem::new(format!("{} {}", dep.name, dep.version)))
+        .collect();
+    frame.render_widget(
+        List::new(items).block(Block::default().title("Dependencies").borders(Borders::ALL)),
+        area,
+    );
+}
+
+fn status_line<'a>(label: &'a str, status: &'a TaskStatus) -> Line<'a> {
+    let (text, color) = match status {
+        TaskStatus::Idle => ("idle".to_string(), Color::DarkGray),
+        TaskStatus::Running => ("running...".to_string(), Color::Yellow),
+        TaskStatus::Succeeded(summary) => (summary.clone(), Color::Green),
+        TaskStatus::Failed(error) => (error.clone(), Color::Red),
+    };
+
+    Line::from(vec![
+        Span::raw(format!("{:<8}", label)),
+        Span::styled(text, Style::default().fg(color)),
+    ])
+}
@@ -0,0 +1,77 @@
+//! Dev-server responsiveness while a large build cache is loaded
+//!
+//! A task standing in for the dev server wakes up every millisecond on a
+//! single-threaded runtime and records how late each wake-up is while the
+//! cache is loaded, once with the blocking `load_from_disk` and once with
+//! the async `load`. Run with `cargo bench -p forgekit-core --bench cache_load`.
+
+use forgekit_core::cache::BuildCache;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const ENTRIES: usize = 4000;
+const ENTRY_SIZE: usize = 16 * 1024;
+const TICK: Duration = Duration::from_millis(1);
+
+/// Time `load` took and how late a 1ms ticker got at worst meanwhile
+fn measure<F: Future<Output = ()>>(load: impl FnOnce() -> F) -> (Duration, Duration) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        let done = Arc::new(AtomicBool::new(false));
+        let stop = done.clone();
+        let ticker = tokio::spawn(async move {
+            let mut worst = Duration::ZERO;
+            while !stop.load(Ordering::Relaxed) {
+                let expected = Instant::now() + TICK;
+                tokio::time::sleep(TICK).await;
+                worst = worst.max(Instant::now().saturating_duration_since(expected));
+            }
+            worst
+        });
+        // Let the ticker start before loading
+        tokio::time::sleep(TICK * 5).await;
+
+        let started = Instant::now();
+        load().await;
+        let elapsed = started.elapsed();
+        done.store(true, Ordering::Relaxed);
+        (elapsed, ticker.await.unwrap())
+    })
+}
+
+fn main() {
+    let dir = tempfile::TempDir::new().unwrap();
+    for i in 0..ENTRIES {
+        std::fs::write(
+            dir.path().join(format!("entry_{}.cache", i)),
+            vec![7u8; ENTRY_SIZE],
+        )
+        .unwrap();
+    }
+    let size_mb = ENTRIES * ENTRY_SIZE / (1024 * 1024);
+    println!("Loading {} entries ({} MB)", ENTRIES, size_mb);
+
+    let path = dir.path().to_path_buf();
+    let (elapsed, worst) = measure(|| async {
+        let mut cache = BuildCache::new(path.clone()).unwrap();
+        cache.load_from_disk().unwrap();
+    });
+    println!(
+        "load_from_disk: {:>8.1?} to load, ticker up to {:>8.1?} late",
+        elapsed, worst
+    );
+
+    let (elapsed, worst) = measure(|| async {
+        let mut cache = BuildCache::new(path.clone()).unwrap();
+        cache.load().await.unwrap();
+    });
+    println!(
+        "load:           {:>8.1?} to load, ticker up to {:>8.1?} late",
+        elapsed, worst
+    );
+}
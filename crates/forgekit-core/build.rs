@@ -0,0 +1,20 @@
+//! Records the rustc version and target forgekit-core is compiled with,
+//! which plugin libraries must match since their boundary uses the Rust ABI
+
+use std::process::Command;
+
+fn main() {
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let version = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_else(|| "rustc (unknown version)".to_string());
+    println!("cargo:rustc-env=FORGEKIT_RUSTC_VERSION={}", version);
+    let target = std::env::var("TARGET").unwrap_or_default();
+    println!("cargo:rustc-env=FORGEKIT_TARGET={}", target);
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=RUSTC");
+}
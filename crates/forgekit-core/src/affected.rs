@@ -0,0 +1,291 @@
+//! Affected members module
+//!
+//! This module works out which members of a workspace a change touches.
+//! Files changed since a git ref are mapped to the member containing them,
+//! then the set grows to every member depending on an affected one, through
+//! a Cargo path dependency or a forgekit.toml dependency naming another
+//! member. Changes to workspace-wide files such as the root Cargo.toml or
+//! Cargo.lock affect every member.
+
+use crate::error::ForgeKitError;
+use crate::workspace::{Workspace, WorkspaceMember};
+use serde::Serialize;
+use std::collections::{BTreeSet, VecDeque};
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+use tokio::process::Command;
+
+/// Files at the workspace root whose changes affect every member
+const WORKSPACE_FILES: &[&str] = &[
+    "Cargo.toml",
+    "Cargo.lock",
+    "forgekit.toml",
+    "rust-toolchain",
+    "rust-toolchain.toml",
+    ".cargo",
+];
+
+/// Cargo manifest sections that can hold path dependencies
+const DEPENDENCY_TABLES: &[&str] = &["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// Why a member is affected
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", content = "cause", rename_all = "snake_case")]
+pub enum AffectedReason {
+    /// A file inside the member changed
+    Changed,
+    /// The member depends on another affected member
+    DependsOn(String),
+    /// A workspace-wide file changed
+    Workspace(PathBuf),
+}
+
+impl fmt::Display for AffectedReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Changed => write!(f, "changed"),
+            Self::DependsOn(member) => write!(f, "depends on {}", member),
+            Self::Workspace(file) => write!(f, "{} changed", file.display()),
+        }
+    }
+}
+
+/// A member that needs rebuilding
+#[derive(Debug, Clone, Serialize)]
+pub struct AffectedMember {
+    /// Project name from forgekit.toml
+    pub name: String,
+    /// Path to the member directory
+    pub path: PathBuf,
+    /// First reason found for the member being affected
+    pub reason: AffectedReason,
+}
+
+/// Members affected by the changes since a git ref
+#[derive(Debug, Clone, Serialize)]
+pub struct AffectedReport {
+    /// Git ref the working tree was compared against
+    pub since: String,
+    /// Changed files, relative to the workspace root
+    pub changed_files: Vec<PathBuf>,
+    /// Affected members, in workspace order
+    pub members: Vec<AffectedMember>,
+}
+
+impl AffectedReport {
+    /// Directories of the affected members
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.members.iter().map(|m| m.path.clone()).collect()
+    }
+}
+
+/// Find the workspace members affected by changes since `since`
+pub async fn detect(root: &Path, since: &str) -> Result<AffectedReport, ForgeKitError> {
+    let workspace = Workspace::load(root)?;
+    let changed_files = changed_files(root, since).await?;
+    let members = affected(&workspace, &changed_files)?;
+    Ok(AffectedReport {
+        since: since.to_string(),
+        changed_files,
+        members,
+    })
+}
+
+/// Files changed since `since`, including uncommitted and untracked ones
+///
+/// Paths are relative to `root`; files outside it are left out.
+pub async fn changed_files(root: &Path, since: &str) -> Result<Vec<PathBuf>, ForgeKitError> {
+    let diff = git(root, &["diff", "--name-only", "--relative", since, "--"]).await?;
+    let untracked = git(root, &["ls-files", "--others", "--exclude-standard"]).await?;
+
+    let files: BTreeSet<PathBuf> = diff
+        .lines()
+        .chain(untracked.lines())
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect();
+    Ok(files.into_iter().collect())
+}
+
+/// Members containing a changed file, plus every member depending on one
+pub fn affected(
+    workspace: &Workspace,
+    changed_files: &[PathBuf],
+) -> Result<Vec<AffectedMember>, ForgeKitError> {
+    let members = &workspace.members;
+    let mut reasons: Vec<Option<AffectedReason>> = vec![None; members.len()];
+
+    if let Some(file) = changed_files.iter().find(|f| is_workspace_file(f)) {
+        reasons.fill(Some(AffectedReason::Workspace(file.clone())));
+    }
+
+    let relative: Vec<PathBuf> = members
+        .iter()
+        .map(|m| normalize(m.path.strip_prefix(&workspace.root).unwrap_or(&m.path)))
+        .collect();
+    for file in changed_files {
+        let file = normalize(file);
+        if let Some(index) = relative.iter().position(|dir| file.starts_with(dir)) {
+            reasons[index].get_or_insert(AffectedReason::Changed);
+        }
+    }
+
+    // Walk from affected members to the members depending on them
+    let dependencies = members
+        .iter()
+        .map(|member| member_dependencies(workspace, member))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut queue: VecDeque<usize> = (0..members.len())
+        .filter(|&i| reasons[i].is_some())
+        .collect();
+    while let Some(index) = queue.pop_front() {
+        for (dependent, deps) in dependencies.iter().enumerate() {
+            if reasons[dependent].is_none() && deps.contains(&index) {
+                reasons[dependent] = Some(AffectedReason::DependsOn(members[index].name.clone()));
+                queue.push_back(dependent);
+            }
+        }
+    }
+
+    Ok(members
+        .iter()
+        .zip(reasons)
+        .filter_map(|(member, reason)| {
+            reason.map(|reason| AffectedMember {
+                name: member.name.clone(),
+                path: member.path.clone(),
+                reason,
+            })
+        })
+        .collect())
+}
+
+/// Indices of the workspace members `member` depends on
+fn member_dependencies(
+    workspace: &Workspace,
+    member: &WorkspaceMember,
+) -> Result<BTreeSet<usize>, ForgeKitError> {
+    let mut paths = Vec::new();
+    let cargo_toml = member.path.join("Cargo.toml");
+    if cargo_toml.exists() {
+        let manifest: toml::Value = toml::from_str(&std::fs::read_to_string(&cargo_toml)?)?;
+        for table in DEPENDENCY_TABLES.iter().filter_map(|t| manifest.get(t)) {
+            let entries = table.as_table().into_iter().flat_map(|t| t.values());
+            for path in entries.filter_map(|dep| dep.get("path")?.as_str()) {
+                paths.push(normalize(&member.path.join(path)));
+            }
+        }
+    }
+
+    Ok(workspace
+        .members
+        .iter()
+        .enumerate()
+        .filter(|(_, other)| other.path != member.path)
+        .filter(|(_, other)| {
+            paths.contains(&normalize(&other.path))
+                || member
+                    .config
+                    .dependencies
+                    .iter()
+                    .any(|d| d.name == other.name)
+        })
+        .map(|(index, _)| index)
+        .collect())
+}
+
+fn is_workspace_file(file: &Path) -> bool {
+    file.components()
+        .next()
+        .is_some_and(|first| WORKSPACE_FILES.iter().any(|f| first.as_os_str() == *f))
+}
+
+/// Resolve `.` and `..` without touching the filesystem
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+async fn git(root: &Path, args: &[&str]) -> Result<String, ForgeKitError> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(root)
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(ForgeKitError::InvalidConfig(format!(
+            "git {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ProjectConfig;
+    use tempfile::TempDir;
+
+    fn write_member(root: &Path, name: &str, path_deps: &[&str]) {
+        let path = root.join(name);
+        std::fs::create_dir_all(&path).unwrap();
+        let deps: String = path_deps
+            .iter()
+            .map(|dep| format!("{dep} = {{ path = \"../{dep}\" }}\n"))
+            .collect();
+        std::fs::write(
+            path.join("Cargo.toml"),
+            format!("[package]\nname = \"{name}\"\n\n[dependencies]\n{deps}"),
+        )
+        .unwrap();
+        let config = ProjectConfig {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        config.save(path.join("forgekit.toml")).unwrap();
+    }
+
+    #[test]
+    fn test_affected_follows_dependents() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::write(
+            root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"core\", \"app\", \"tool\"]\n",
+        )
+        .unwrap();
+        write_member(root, "core", &[]);
+        write_member(root, "app", &["core"]);
+        write_member(root, "tool", &[]);
+        let workspace = Workspace::load(root).unwrap();
+
+        let members = affected(&workspace, &[PathBuf::from("core/src/lib.rs")]).unwrap();
+        let names: Vec<&str> = members.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, ["core", "app"]);
+        assert_eq!(
+            members[1].reason,
+            AffectedReason::DependsOn("core".to_string())
+        );
+
+        assert!(affected(&workspace, &[PathBuf::from("README.md")])
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            affected(&workspace, &[PathBuf::from("Cargo.lock")])
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+}
@@ -1,10 +1,112 @@
 //! Project analytics module
 //!
-//! This module provides project metrics and analytics.
+//! This module provides project metrics and analytics, including a health
+//! report that grades a project from A to F by combining validation, audit,
+//! outdated dependencies, test coverage and the trend of recent build times.
+//! Test runs are recorded too, so tests that only pass on retry show up as
+//! flake rates over time. Coverage is measured with cargo-llvm-cov or
+//! cargo-tarpaulin when one is installed; otherwise the report uses the last
+//! recorded measurement, and leaves coverage out when there is none.
+//!
+//! For security audits, [`AnalyticsCollector::unsafe_report`] counts the
+//! unsafe code and FFI of the project and of every crate it depends on,
+//! parsing their sources the way cargo-geiger does.
+//!
+//! For libraries, [`AnalyticsCollector::api_report`] measures documentation
+//! coverage of the public API and diffs it against the previous release to
+//! suggest the version bump.
+//!
+//! [`AnalyticsCollector::todo_report`] collects the TODO, FIXME and HACK
+//! markers left in comments and ages them with `git blame`, so teams can
+//! track that debt from one release to the next.
 
+use crate::api_surface::{self, ApiDiff, ApiSurface};
+use crate::atomic;
+use crate::audit::{DependencyAuditor, SeveritySummary};
+use crate::config::ProjectConfig;
 use crate::error::ForgeKitError;
-use std::path::Path;
+use crate::testing::TestReport;
+use crate::toolchain;
+use crate::validator::ProjectValidator;
+use crate::version_manager::{is_version, BumpType, VersionManager};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
 use std::time::Duration;
+use syn::visit::{self, Visit};
+
+/// Number of build times kept in the build history
+const BUILD_HISTORY_LIMIT: usize = 50;
+
+/// Number of test runs kept in the test history
+const TEST_HISTORY_LIMIT: usize = 50;
+
+/// Builds compared on each side of the build time trend
+const TREND_WINDOW: usize = 5;
+
+/// Test coverage below which the health score is reduced
+const COVERAGE_TARGET: f64 = 80.0;
+
+/// Cargo subcommands measuring coverage, in order of preference, with the
+/// arguments printing their summary
+const COVERAGE_TOOLS: &[(&str, &[&str])] = &[
+    ("llvm-cov", &["--summary-only", "--json"]),
+    ("tarpaulin", &["--skip-clean"]),
+];
+
+/// Stack size of the thread parsing crate sources
+const SCAN_STACK_SIZE: usize = 64 * 1024 * 1024;
+
+/// Age in days after which a TODO marker counts as stale
+const STALE_MARKER_DAYS: i64 = 180;
+
+/// Directories scanned for TODO markers
+const MARKER_DIRS: &[&str] = &["src", "tests", "benches", "examples"];
+
+/// Common misspellings in comments and their corrections, sorted
+const MISSPELLINGS: &[(&str, &str)] = &[
+    ("accomodate", "accommodate"),
+    ("acheive", "achieve"),
+    ("adress", "address"),
+    ("aquire", "acquire"),
+    ("arguement", "argument"),
+    ("begining", "beginning"),
+    ("beleive", "believe"),
+    ("calender", "calendar"),
+    ("compatability", "compatibility"),
+    ("continous", "continuous"),
+    ("definately", "definitely"),
+    ("dependancy", "dependency"),
+    ("enviroment", "environment"),
+    ("existant", "existent"),
+    ("funtion", "function"),
+    ("guarentee", "guarantee"),
+    ("independant", "independent"),
+    ("initalize", "initialize"),
+    ("lenght", "length"),
+    ("neccessary", "necessary"),
+    ("occured", "occurred"),
+    ("occurence", "occurrence"),
+    ("paramter", "parameter"),
+    ("persistant", "persistent"),
+    ("posible", "possible"),
+    ("recieve", "receive"),
+    ("recieved", "received"),
+    ("refered", "referred"),
+    ("retreive", "retrieve"),
+    ("seperate", "separate"),
+    ("succesful", "successful"),
+    ("sucess", "success"),
+    ("teh", "the"),
+    ("threshhold", "threshold"),
+    ("unkown", "unknown"),
+    ("untill", "until"),
+    ("wich", "which"),
+    ("writen", "written"),
+];
+
+/// Directories of a crate whose code is not compiled into it
+const NON_LIBRARY_DIRS: &[&str] = &["target", "tests", "benches", "examples"];
 
 /// Code metrics
 #[derive(Debug, Clone)]
@@ -52,7 +154,10 @@ impl AnalyticsCollector {
         }
 
         Ok(ProjectMetrics {
-            build_times: Vec::new(),
+            build_times: build_history(path)
+                .into_iter()
+                .map(|record| Duration::from_millis(record.duration_ms))
+                .collect(),
             dependency_count: 0,
             code_metrics: CodeMetrics {
                 lines_of_code,
@@ -71,6 +176,1176 @@ impl AnalyticsCollector {
             generated_at: chrono::Local::now().to_rfc3339(),
         })
     }
+
+    /// Grade the overall health of a project
+    pub async fn health_report(path: &Path) -> Result<HealthReport, ForgeKitError> {
+        let validation = ProjectValidator::validate_project(path).await?;
+        // Without a Cargo.toml there is nothing to audit; validation reports it
+        let vulnerabilities = DependencyAuditor::audit_dependencies(path)
+            .await
+            .map(|report| report.severity_summary)
+            .unwrap_or(SeveritySummary {
+                critical: 0,
+                high: 0,
+                medium: 0,
+                low: 0,
+            });
+        let outdated = DependencyAuditor::check_for_updates(path)
+            .await
+            .map(|updates| updates.len())
+            .unwrap_or(0);
+        let metrics = Self::collect_metrics(path).await?;
+        let todos = Self::todo_report(path).await?;
+
+        let mut report = HealthReport {
+            score: 100,
+            grade: 'A',
+            validation_errors: validation.errors.len(),
+            validation_warnings: validation.warnings.len(),
+            vulnerabilities,
+            outdated,
+            test_coverage: match measure_coverage(path).await {
+                Some(record) => Some(record.percent),
+                None => last_coverage(path).map(|record| record.percent),
+            },
+            build_trend: build_trend(&metrics.build_times),
+            flaky_tests: flake_rates(path).len(),
+            todos: todos.markers.len(),
+            stale_todos: todos.stale,
+            typos: todos.typos.len(),
+            recommendations: Vec::new(),
+            generated_at: chrono::Local::now().to_rfc3339(),
+        };
+        report.grade_findings();
+        Ok(report)
+    }
+
+    /// Collect the TODO, FIXME and HACK markers and the misspelled words in
+    /// the project's comments
+    ///
+    /// Markers are aged by when `git blame` says their line last changed;
+    /// outside a git repository, or on uncommitted lines, they have no age.
+    pub async fn todo_report(path: &Path) -> Result<TodoReport, ForgeKitError> {
+        let pattern = regex::Regex::new(
+            r"(?://|/\*|^\s*\*)[^\n]*?\b(TODO|FIXME|HACK)\b(?:\([^)]*\))?:?\s*(.*?)\s*(?:\*/)?$",
+        )
+        .expect("valid marker pattern");
+        let comment = regex::Regex::new(r"(?://|/\*|^\s*\*)(.*)$").expect("valid comment pattern");
+        let word = regex::Regex::new(r"[A-Za-z]+").expect("valid word pattern");
+        let now = chrono::Utc::now().timestamp();
+
+        let mut markers = Vec::new();
+        let mut typos = Vec::new();
+        for dir in MARKER_DIRS {
+            for entry in walkdir::WalkDir::new(path.join(dir))
+                .sort_by_file_name()
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.path().extension().is_some_and(|ext| ext == "rs"))
+            {
+                let Ok(content) = std::fs::read_to_string(entry.path()) else {
+                    continue;
+                };
+                let file = entry.path().strip_prefix(path).unwrap_or(entry.path());
+                for (i, line) in content.lines().enumerate() {
+                    let Some(captures) = comment.captures(line) else {
+                        continue;
+                    };
+                    for found in word.find_iter(&captures[1]) {
+                        let lower = found.as_str().to_lowercase();
+                        if let Ok(index) =
+                            MISSPELLINGS.binary_search_by_key(&lower.as_str(), |m| m.0)
+                        {
+                            typos.push(Typo {
+                                file: file.to_path_buf(),
+                                line: i + 1,
+                                word: found.as_str().to_string(),
+                                suggestion: MISSPELLINGS[index].1.to_string(),
+                            });
+                        }
+                    }
+                }
+                let mut found: Vec<Marker> = content
+                    .lines()
+                    .enumerate()
+                    .filter_map(|(i, line)| {
+                        let captures = pattern.captures(line)?;
+                        let kind = match &captures[1] {
+                            "TODO" => MarkerKind::Todo,
+                            "FIXME" => MarkerKind::Fixme,
+                            _ => MarkerKind::Hack,
+                        };
+                        Some(Marker {
+                            kind,
+                            file: file.to_path_buf(),
+                            line: i + 1,
+                            text: captures[2].to_string(),
+                            author: None,
+                            age_days: None,
+                        })
+                    })
+                    .collect();
+                if found.is_empty() {
+                    continue;
+                }
+
+                let blame = tokio::process::Command::new("git")
+                    .args(["blame", "--line-porcelain", "--"])
+                    .arg(file)
+                    .current_dir(path)
+                    .output()
+                    .await;
+                if let Some(output) = blame.ok().filter(|output| output.status.success()) {
+                    let lines = parse_blame(&String::from_utf8_lossy(&output.stdout));
+                    for marker in &mut found {
+                        if let Some((author, time)) = lines.get(&marker.line) {
+                            marker.author = Some(author.clone());
+                            marker.age_days = Some((now - time).max(0) / 86_400);
+                        }
+                    }
+                }
+                markers.extend(found);
+            }
+        }
+
+        let mut counts = BTreeMap::new();
+        for marker in &markers {
+            *counts.entry(marker.kind).or_default() += 1;
+        }
+        let stale = markers
+            .iter()
+            .filter(|marker| marker.age_days.is_some_and(|age| age > STALE_MARKER_DAYS))
+            .count();
+        Ok(TodoReport {
+            markers,
+            counts,
+            stale,
+            typos,
+            generated_at: chrono::Local::now().to_rfc3339(),
+        })
+    }
+
+    /// Count the unsafe code and FFI of the project and its dependencies
+    ///
+    /// The crates are the workspace members and everything they reach through
+    /// normal dependencies on the host platform, as resolved by
+    /// `cargo metadata`. Each crate's
+    /// sources are parsed, so unsafe code inside macro bodies is not counted.
+    pub async fn unsafe_report(path: &Path) -> Result<UnsafeReport, ForgeKitError> {
+        if !path.join("Cargo.toml").exists() {
+            return Err(ForgeKitError::ProjectNotFound(
+                "Cargo.toml not found".to_string(),
+            ));
+        }
+        let host = toolchain::host_triple().await?;
+        let output = tokio::process::Command::new("cargo")
+            .args(["metadata", "--format-version", "1", "--filter-platform"])
+            .arg(&host)
+            .current_dir(path)
+            .output()
+            .await?;
+        if !output.status.success() {
+            return Err(ForgeKitError::InvalidConfig(format!(
+                "cargo metadata failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        let metadata: CargoMetadata = serde_json::from_slice(&output.stdout)?;
+
+        let crates = tokio::task::spawn_blocking(move || scan_crates(&metadata))
+            .await
+            .map_err(std::io::Error::from)??;
+
+        Ok(UnsafeReport {
+            crates,
+            generated_at: chrono::Local::now().to_rfc3339(),
+        })
+    }
+
+    /// Report documentation coverage of the public API and its changes
+    ///
+    /// The API is compared with `against`, a git revision of the previous
+    /// release, defaulting to the most recent tag; without one only
+    /// coverage is reported.
+    pub async fn api_report(
+        path: &Path,
+        against: Option<&str>,
+    ) -> Result<ApiReport, ForgeKitError> {
+        let path = path.to_path_buf();
+        let against = against.map(str::to_string);
+        tokio::task::spawn_blocking(move || {
+            let surface = api_surface::scan(&path)?;
+            let previous_release = match against {
+                Some(revision) => Some(revision),
+                None => api_surface::previous_release(&path)?,
+            };
+            let diff = previous_release
+                .as_deref()
+                .map(|revision| {
+                    api_surface::scan_revision(&path, revision)
+                        .map(|previous| surface.diff(&previous))
+                })
+                .transpose()?;
+            // A revision like `HEAD~3` names no version; bump from the
+            // project's instead
+            let previous_version = previous_release.as_deref().and_then(|revision| {
+                if is_version(revision) {
+                    Some(revision.to_string())
+                } else {
+                    ProjectConfig::load(path.join("forgekit.toml"))
+                        .ok()
+                        .map(|config| config.version)
+                        .filter(|version| is_version(version))
+                }
+            });
+            let suggested_bump = previous_version
+                .as_deref()
+                .zip(diff.as_ref())
+                .map(|(version, diff)| VersionManager::suggest_bump(version, diff));
+
+            Ok(ApiReport {
+                coverage: surface.coverage(),
+                surface,
+                previous_release,
+                diff,
+                suggested_bump,
+                generated_at: chrono::Local::now().to_rfc3339(),
+            })
+        })
+        .await
+        .map_err(std::io::Error::from)?
+    }
+}
+
+/// Documentation coverage and public API changes of a library
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiReport {
+    /// Public items of the working tree
+    pub surface: ApiSurface,
+    /// Percentage of public items with a doc comment
+    pub coverage: f64,
+    /// Release the API was compared with, if the project has one
+    pub previous_release: Option<String>,
+    /// Changes since that release
+    pub diff: Option<ApiDiff>,
+    /// Smallest semver-compatible bump from that release
+    pub suggested_bump: Option<BumpType>,
+    /// When the report was generated (RFC 3339)
+    pub generated_at: String,
+}
+
+/// Unsafe code and FFI counted in a crate's sources
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct UnsafeCounts {
+    /// `unsafe` blocks
+    pub blocks: usize,
+    /// `unsafe fn` functions and methods
+    pub functions: usize,
+    /// `unsafe impl` items
+    pub impls: usize,
+    /// `unsafe trait` items
+    pub traits: usize,
+    /// Functions and statics declared in `extern` blocks
+    pub ffi_imports: usize,
+    /// Functions defined with a foreign ABI, callable from other languages
+    pub ffi_exports: usize,
+}
+
+impl UnsafeCounts {
+    /// Number of unsafe items, FFI included
+    pub fn total(&self) -> usize {
+        self.blocks
+            + self.functions
+            + self.impls
+            + self.traits
+            + self.ffi_imports
+            + self.ffi_exports
+    }
+}
+
+/// Unsafe code of one crate
+#[derive(Debug, Clone, Serialize)]
+pub struct CrateUnsafe {
+    /// Package name
+    pub name: String,
+    /// Package version
+    pub version: String,
+    /// Whether the crate belongs to the project rather than a dependency
+    pub is_project: bool,
+    /// Whether every target of the crate has `#![forbid(unsafe_code)]`
+    pub forbids_unsafe: bool,
+    /// What was counted
+    pub counts: UnsafeCounts,
+    /// Source files that could not be parsed and were not counted
+    pub unparsed_files: usize,
+}
+
+/// Unsafe code and FFI per crate, project crates first
+#[derive(Debug, Clone, Serialize)]
+pub struct UnsafeReport {
+    /// Crates of the project and its dependencies
+    pub crates: Vec<CrateUnsafe>,
+    /// When the report was generated (RFC 3339)
+    pub generated_at: String,
+}
+
+impl UnsafeReport {
+    /// Dependencies containing unsafe code or FFI
+    pub fn unsafe_dependencies(&self) -> impl Iterator<Item = &CrateUnsafe> {
+        self.crates
+            .iter()
+            .filter(|krate| !krate.is_project && krate.counts.total() > 0)
+    }
+}
+
+/// Output of `cargo metadata`, as far as the unsafe report needs it
+#[derive(Debug, Deserialize)]
+struct CargoMetadata {
+    packages: Vec<MetadataPackage>,
+    workspace_members: Vec<String>,
+    resolve: Option<MetadataResolve>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MetadataPackage {
+    id: String,
+    name: String,
+    version: String,
+    manifest_path: PathBuf,
+    targets: Vec<MetadataTarget>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MetadataTarget {
+    kind: Vec<String>,
+    src_path: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+struct MetadataResolve {
+    nodes: Vec<MetadataNode>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MetadataNode {
+    id: String,
+    deps: Vec<MetadataDep>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MetadataDep {
+    pkg: String,
+    dep_kinds: Vec<MetadataDepKind>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MetadataDepKind {
+    kind: Option<String>,
+}
+
+/// Scan the packages in the dependency closure, project crates first
+///
+/// Packages are shared out to one thread per core. Parsing recurses deeply on
+/// large generated sources, so the threads get more stack than usual.
+fn scan_crates(metadata: &CargoMetadata) -> Result<Vec<CrateUnsafe>, ForgeKitError> {
+    let members: HashSet<&str> = metadata
+        .workspace_members
+        .iter()
+        .map(String::as_str)
+        .collect();
+    let packages: HashMap<&str, &MetadataPackage> = metadata
+        .packages
+        .iter()
+        .map(|package| (package.id.as_str(), package))
+        .collect();
+    let closure: Vec<&MetadataPackage> = normal_dependency_closure(metadata)
+        .into_iter()
+        .filter_map(|id| packages.get(id.as_str()).copied())
+        .collect();
+
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = closure.len().div_ceil(threads).max(1);
+    let mut crates = std::thread::scope(|scope| {
+        let workers = closure
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let members = &members;
+                std::thread::Builder::new()
+                    .stack_size(SCAN_STACK_SIZE)
+                    .spawn_scoped(scope, move || {
+                        chunk
+                            .iter()
+                            .map(|package| {
+                                scan_crate(package, members.contains(package.id.as_str()))
+                            })
+                            .collect::<Vec<_>>()
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut crates = Vec::new();
+        for worker in workers {
+            crates.extend(
+                worker
+                    .join()
+                    .map_err(|_| std::io::Error::other("scanning for unsafe code panicked"))?,
+            );
+        }
+        Ok::<_, ForgeKitError>(crates)
+    })?;
+    crates.sort_by(|a, b| {
+        b.is_project
+            .cmp(&a.is_project)
+            .then_with(|| a.name.cmp(&b.name))
+            .then_with(|| a.version.cmp(&b.version))
+    });
+    Ok(crates)
+}
+
+/// Packages reached from the workspace members through normal dependencies
+fn normal_dependency_closure(metadata: &CargoMetadata) -> Vec<String> {
+    let nodes: HashMap<&str, &MetadataNode> = metadata
+        .resolve
+        .iter()
+        .flat_map(|resolve| &resolve.nodes)
+        .map(|node| (node.id.as_str(), node))
+        .collect();
+    let mut seen: HashSet<&str> = metadata
+        .workspace_members
+        .iter()
+        .map(String::as_str)
+        .collect();
+    let mut queue: VecDeque<&str> = seen.iter().copied().collect();
+    while let Some(id) = queue.pop_front() {
+        let deps = nodes
+            .get(id)
+            .map(|node| node.deps.as_slice())
+            .unwrap_or(&[]);
+        for dep in deps {
+            let normal = dep.dep_kinds.iter().any(|kind| kind.kind.is_none());
+            if normal && seen.insert(dep.pkg.as_str()) {
+                queue.push_back(dep.pkg.as_str());
+            }
+        }
+    }
+    seen.into_iter().map(str::to_string).collect()
+}
+
+/// Count the unsafe code in the sources of a package
+///
+/// Tests, benches, examples and nested packages are not part of the crate
+/// and are skipped.
+fn scan_crate(package: &MetadataPackage, is_project: bool) -> CrateUnsafe {
+    let dir = package.manifest_path.parent().unwrap_or(Path::new("."));
+    let mut visitor = UnsafeVisitor::default();
+    let mut unparsed_files = 0;
+    let files = walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() == 0
+                || !entry.file_type().is_dir()
+                || !(NON_LIBRARY_DIRS
+                    .iter()
+                    .any(|name| entry.file_name() == *name)
+                    || entry.path().join("Cargo.toml").exists())
+        })
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "rs"));
+    for entry in files {
+        match parse_file(entry.path()) {
+            Some(file) => visitor.visit_file(&file),
+            None => unparsed_files += 1,
+        }
+    }
+
+    let roots: Vec<&MetadataTarget> = package
+        .targets
+        .iter()
+        .filter(|target| {
+            target
+                .kind
+                .iter()
+                .any(|kind| kind.ends_with("lib") || kind == "bin" || kind == "proc-macro")
+        })
+        .collect();
+    let forbids_unsafe = !roots.is_empty()
+        && roots.iter().all(|target| {
+            parse_file(&target.src_path).is_some_and(|file| forbids_unsafe_code(&file.attrs))
+        });
+
+    CrateUnsafe {
+        name: package.name.clone(),
+        version: package.version.clone(),
+        is_project,
+        forbids_unsafe,
+        counts: visitor.counts,
+        unparsed_files,
+    }
+}
+
+fn parse_file(path: &Path) -> Option<syn::File> {
+    let source = std::fs::read_to_string(path).ok()?;
+    syn::parse_file(&source).ok()
+}
+
+/// Whether crate attributes contain `#![forbid(unsafe_code)]`
+fn forbids_unsafe_code(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        let mut forbids = false;
+        if attr.path().is_ident("forbid") {
+            let _ = attr.parse_nested_meta(|meta| {
+                forbids |= meta.path.is_ident("unsafe_code");
+                Ok(())
+            });
+        }
+        forbids
+    })
+}
+
+/// Counts unsafe code while walking a syntax tree
+#[derive(Default)]
+struct UnsafeVisitor {
+    counts: UnsafeCounts,
+}
+
+impl UnsafeVisitor {
+    fn signature(&mut self, sig: &syn::Signature) {
+        if sig.unsafety.is_some() {
+            self.counts.functions += 1;
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for UnsafeVisitor {
+    fn visit_expr_unsafe(&mut self, node: &'ast syn::ExprUnsafe) {
+        self.counts.blocks += 1;
+        visit::visit_expr_unsafe(self, node);
+    }
+
+    fn visit_item_fn(&mut self, node: &'ast syn::ItemFn) {
+        self.signature(&node.sig);
+        let foreign = node
+            .sig
+            .abi
+            .as_ref()
+            .is_some_and(|abi| abi.name.as_ref().is_none_or(|name| name.value() != "Rust"));
+        if foreign {
+            self.counts.ffi_exports += 1;
+        }
+        visit::visit_item_fn(self, node);
+    }
+
+    fn visit_impl_item_fn(&mut self, node: &'ast syn::ImplItemFn) {
+        self.signature(&node.sig);
+        visit::visit_impl_item_fn(self, node);
+    }
+
+    fn visit_trait_item_fn(&mut self, node: &'ast syn::TraitItemFn) {
+        self.signature(&node.sig);
+        visit::visit_trait_item_fn(self, node);
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast syn::ItemImpl) {
+        if node.unsafety.is_some() {
+            self.counts.impls += 1;
+        }
+        visit::visit_item_impl(self, node);
+    }
+
+    fn visit_item_trait(&mut self, node: &'ast syn::ItemTrait) {
+        if node.unsafety.is_some() {
+            self.counts.traits += 1;
+        }
+        visit::visit_item_trait(self, node);
+    }
+
+    fn visit_item_foreign_mod(&mut self, node: &'ast syn::ItemForeignMod) {
+        self.counts.ffi_imports += node
+            .items
+            .iter()
+            .filter(|item| matches!(item, syn::ForeignItem::Fn(_) | syn::ForeignItem::Static(_)))
+            .count();
+        visit::visit_item_foreign_mod(self, node);
+    }
+}
+
+/// A successful build recorded in the build history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildRecord {
+    /// When the build finished (RFC 3339)
+    pub finished_at: String,
+    /// Build duration in milliseconds
+    pub duration_ms: u64,
+}
+
+/// Path of a project's build history
+fn build_history_path(project_path: &Path) -> PathBuf {
+    project_path
+        .join("target")
+        .join("forgekit")
+        .join("build-history.json")
+}
+
+/// Recent successful builds of a project, oldest first
+pub fn build_history(project_path: &Path) -> Vec<BuildRecord> {
+    std::fs::read_to_string(build_history_path(project_path))
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+/// Append a successful build to the build history
+pub fn record_build(project_path: &Path, duration: Duration) -> Result<(), ForgeKitError> {
+    let mut history = build_history(project_path);
+    history.push(BuildRecord {
+        finished_at: chrono::Local::now().to_rfc3339(),
+        duration_ms: duration.as_millis() as u64,
+    });
+    let excess = history.len().saturating_sub(BUILD_HISTORY_LIMIT);
+    history.drain(..excess);
+
+    let path = build_history_path(project_path);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string(&history)?)?;
+    Ok(())
+}
+
+/// A test run recorded in the test history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestRunRecord {
+    /// When the run finished (RFC 3339)
+    pub finished_at: String,
+    /// Number of tests run
+    pub total: usize,
+    /// Tests still failing after the retries, quarantined ones included
+    pub failed: Vec<String>,
+    /// Tests that failed and then passed on retry
+    pub flaky: Vec<String>,
+}
+
+/// How often a test was flaky in the recorded runs
+#[derive(Debug, Clone, Serialize)]
+pub struct FlakeRate {
+    /// Test name as printed by `cargo test`
+    pub test: String,
+    /// Runs in which the test passed only on retry
+    pub flaky_runs: usize,
+    /// Runs in the test history
+    pub runs: usize,
+    /// `flaky_runs / runs`
+    pub rate: f64,
+}
+
+fn test_history_path(project_path: &Path) -> PathBuf {
+    project_path
+        .join("target")
+        .join("forgekit")
+        .join("test-history.json")
+}
+
+/// Recent test runs of a project, oldest first
+pub fn test_history(project_path: &Path) -> Vec<TestRunRecord> {
+    std::fs::read_to_string(test_history_path(project_path))
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+/// Append a test run to the test history
+pub fn record_test_run(project_path: &Path, report: &TestReport) -> Result<(), ForgeKitError> {
+    let mut history = test_history(project_path);
+    history.push(TestRunRecord {
+        finished_at: chrono::Local::now().to_rfc3339(),
+        total: report.total,
+        failed: report
+            .failed_tests
+            .iter()
+            .chain(&report.quarantined)
+            .cloned()
+            .collect(),
+        flaky: report.flaky.clone(),
+    });
+    let excess = history.len().saturating_sub(TEST_HISTORY_LIMIT);
+    history.drain(..excess);
+
+    // Written atomically, so an interrupted run cannot truncate the history
+    let path = test_history_path(project_path);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    atomic::write(path, serde_json::to_string(&history)?)?;
+    Ok(())
+}
+
+/// Line coverage measured by a coverage tool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoverageRecord {
+    /// When the measurement finished (RFC 3339)
+    pub finished_at: String,
+    /// Cargo subcommand that measured it, `llvm-cov` or `tarpaulin`
+    pub tool: String,
+    /// Percentage of lines covered by the tests
+    pub percent: f64,
+}
+
+fn coverage_path(project_path: &Path) -> PathBuf {
+    project_path
+        .join("target")
+        .join("forgekit")
+        .join("coverage.json")
+}
+
+/// Last recorded coverage measurement of a project
+pub fn last_coverage(project_path: &Path) -> Option<CoverageRecord> {
+    std::fs::read_to_string(coverage_path(project_path))
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+}
+
+/// Measure the line coverage of a project's tests and record it
+///
+/// Uses cargo-llvm-cov, or cargo-tarpaulin when only that is installed.
+/// Returns `None` when neither is installed, the project has no Cargo.toml
+/// or the measurement fails.
+pub async fn measure_coverage(project_path: &Path) -> Option<CoverageRecord> {
+    if !project_path.join("Cargo.toml").exists() {
+        return None;
+    }
+    for (tool, args) in COVERAGE_TOOLS {
+        let installed = tokio::process::Command::new("cargo")
+            .args([*tool, "--version"])
+            .output()
+            .await
+            .is_ok_and(|output| output.status.success());
+        if !installed {
+            continue;
+        }
+        let output = match tokio::process::Command::new("cargo")
+            .arg(tool)
+            .args(*args)
+            .current_dir(project_path)
+            .output()
+            .await
+        {
+            Ok(output) if output.status.success() => output,
+            Ok(output) => {
+                tracing::warn!(
+                    "cargo {} failed: {}",
+                    tool,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+                return None;
+            }
+            Err(e) => {
+                tracing::warn!("Failed to run cargo {}: {}", tool, e);
+                return None;
+            }
+        };
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let percent = match *tool {
+            "llvm-cov" => llvm_cov_percent(&stdout),
+            _ => tarpaulin_percent(&stdout),
+        };
+        let record = CoverageRecord {
+            finished_at: chrono::Local::now().to_rfc3339(),
+            tool: tool.to_string(),
+            percent: percent?,
+        };
+        if let Err(e) = record_coverage(project_path, &record) {
+            tracing::warn!("Failed to record coverage: {}", e);
+        }
+        return Some(record);
+    }
+    None
+}
+
+/// Save a coverage measurement as the project's last one
+fn record_coverage(project_path: &Path, record: &CoverageRecord) -> Result<(), ForgeKitError> {
+    let path = coverage_path(project_path);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    atomic::write(path, serde_json::to_string(record)?)?;
+    Ok(())
+}
+
+/// Line coverage from the JSON summary of `cargo llvm-cov`
+fn llvm_cov_percent(output: &str) -> Option<f64> {
+    let summary: serde_json::Value = serde_json::from_str(output).ok()?;
+    summary.pointer("/data/0/totals/lines/percent")?.as_f64()
+}
+
+/// Line coverage from the closing `NN.NN% coverage` line of `cargo tarpaulin`
+fn tarpaulin_percent(output: &str) -> Option<f64> {
+    output
+        .lines()
+        .rev()
+        .find_map(|line| line.split_once("% coverage"))
+        .and_then(|(percent, _)| percent.trim().parse().ok())
+}
+
+/// Tests that were flaky in the test history, most flaky first
+pub fn flake_rates(project_path: &Path) -> Vec<FlakeRate> {
+    let history = test_history(project_path);
+    let mut counts: std::collections::BTreeMap<&str, usize> = Default::default();
+    for test in history.iter().flat_map(|run| &run.flaky) {
+        *counts.entry(test).or_default() += 1;
+    }
+
+    let mut rates: Vec<FlakeRate> = counts
+        .into_iter()
+        .map(|(test, flaky_runs)| FlakeRate {
+            test: test.to_string(),
+            flaky_runs,
+            runs: history.len(),
+            rate: flaky_runs as f64 / history.len() as f64,
+        })
+        .collect();
+    rates.sort_by_key(|rate| std::cmp::Reverse(rate.flaky_runs));
+    rates
+}
+
+/// Kind of marker left in a comment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum MarkerKind {
+    /// Work still to do
+    Todo,
+    /// Known bug
+    Fixme,
+    /// Workaround to clean up
+    Hack,
+}
+
+impl std::fmt::Display for MarkerKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MarkerKind::Todo => write!(f, "TODO"),
+            MarkerKind::Fixme => write!(f, "FIXME"),
+            MarkerKind::Hack => write!(f, "HACK"),
+        }
+    }
+}
+
+/// A TODO, FIXME or HACK comment
+#[derive(Debug, Clone, Serialize)]
+pub struct Marker {
+    /// Kind of marker
+    pub kind: MarkerKind,
+    /// File, relative to the project
+    pub file: PathBuf,
+    /// Line number, starting at 1
+    pub line: usize,
+    /// Text following the marker
+    pub text: String,
+    /// Who last changed the line, from `git blame`
+    pub author: Option<String>,
+    /// Days since the line last changed
+    pub age_days: Option<i64>,
+}
+
+/// A commonly misspelled word in a comment
+#[derive(Debug, Clone, Serialize)]
+pub struct Typo {
+    /// File, relative to the project
+    pub file: PathBuf,
+    /// Line number, starting at 1
+    pub line: usize,
+    /// The word as written
+    pub word: String,
+    /// Correct spelling, in lowercase
+    pub suggestion: String,
+}
+
+/// TODO, FIXME and HACK markers and misspellings in the comments of a project
+#[derive(Debug, Clone, Serialize)]
+pub struct TodoReport {
+    /// Markers, by file and line
+    pub markers: Vec<Marker>,
+    /// Number of markers of each kind
+    pub counts: BTreeMap<MarkerKind, usize>,
+    /// Number of markers older than 180 days
+    pub stale: usize,
+    /// Misspelled words, by file and line
+    pub typos: Vec<Typo>,
+    /// When the report was generated
+    pub generated_at: String,
+}
+
+/// Author and time (Unix seconds) of each committed line of
+/// `git blame --line-porcelain` output, keyed by line number
+fn parse_blame(output: &str) -> HashMap<usize, (String, i64)> {
+    let mut lines = HashMap::new();
+    let (mut line, mut author, mut time, mut committed) = (0, String::new(), 0, false);
+    for entry in output.lines() {
+        if entry.starts_with('\t') {
+            if committed {
+                lines.insert(line, (author.clone(), time));
+            }
+        } else if let Some(value) = entry.strip_prefix("author ") {
+            author = value.to_string();
+        } else if let Some(value) = entry.strip_prefix("author-time ") {
+            time = value.parse().unwrap_or(0);
+        } else {
+            let mut fields = entry.split(' ');
+            let sha = fields.next().unwrap_or_default();
+            if sha.len() == 40 && sha.chars().all(|c| c.is_ascii_hexdigit()) {
+                committed = sha.chars().any(|c| c != '0');
+                line = fields.nth(1).and_then(|n| n.parse().ok()).unwrap_or(0);
+            }
+        }
+    }
+    lines
+}
+
+/// Relative change of the latest builds against the ones before them
+///
+/// `0.25` means recent builds take 25% longer. `None` until there is enough
+/// history to compare.
+fn build_trend(build_times: &[Duration]) -> Option<f64> {
+    if build_times.len() < TREND_WINDOW * 2 {
+        return None;
+    }
+    let mean = |times: &[Duration]| {
+        times.iter().map(Duration::as_secs_f64).sum::<f64>() / times.len() as f64
+    };
+    let recent = &build_times[build_times.len() - TREND_WINDOW..];
+    let previous = &build_times[build_times.len() - TREND_WINDOW * 2..][..TREND_WINDOW];
+    let baseline = mean(previous);
+    (baseline > 0.0).then(|| mean(recent) / baseline - 1.0)
+}
+
+/// Priority of a health recommendation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    /// Fix before shipping
+    High,
+    /// Fix soon
+    Medium,
+    /// Nice to have
+    Low,
+}
+
+/// Something to improve, ordered by priority in a health report
+#[derive(Debug, Clone, Serialize)]
+pub struct Recommendation {
+    /// How urgent the recommendation is
+    pub priority: Priority,
+    /// What to do
+    pub message: String,
+}
+
+/// Project health report card
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    /// Score from 0 to 100
+    pub score: u32,
+    /// Letter grade from A to F
+    pub grade: char,
+    /// Number of validation errors
+    pub validation_errors: usize,
+    /// Number of validation warnings
+    pub validation_warnings: usize,
+    /// Known vulnerabilities by severity
+    pub vulnerabilities: SeveritySummary,
+    /// Number of dependencies with newer versions
+    pub outdated: usize,
+    /// Test coverage percentage, when measured
+    pub test_coverage: Option<f64>,
+    /// Change of recent build times, e.g. `0.25` for 25% slower
+    pub build_trend: Option<f64>,
+    /// Number of tests that passed only on retry in recent runs
+    pub flaky_tests: usize,
+    /// Number of TODO, FIXME and HACK markers
+    pub todos: usize,
+    /// Number of those markers older than 180 days
+    pub stale_todos: usize,
+    /// Number of misspelled words in comments
+    pub typos: usize,
+    /// Recommendations, most urgent first
+    pub recommendations: Vec<Recommendation>,
+    /// When the report was generated
+    pub generated_at: String,
+}
+
+impl HealthReport {
+    /// Compute the score, grade and recommendations from the findings
+    fn grade_findings(&mut self) {
+        let mut penalty = 0.0;
+        let mut recommend = |priority, message: String, points: f64| {
+            penalty += points;
+            self.recommendations
+                .push(Recommendation { priority, message });
+        };
+
+        let vulns = &self.vulnerabilities;
+        if vulns.critical + vulns.high > 0 {
+            recommend(
+                Priority::High,
+                format!(
+                    "Update dependencies with {} critical and {} high severity vulnerabilities (`forgekit audit`)",
+                    vulns.critical, vulns.high
+                ),
+                (vulns.critical as f64 * 30.0 + vulns.high as f64 * 15.0).min(40.0),
+            );
+        }
+        if vulns.medium + vulns.low > 0 {
+            recommend(
+                Priority::Medium,
+                format!(
+                    "Review {} medium and {} low severity vulnerabilities",
+                    vulns.medium, vulns.low
+                ),
+                (vulns.medium as f64 * 5.0 + vulns.low as f64).min(10.0),
+            );
+        }
+        if self.validation_errors > 0 {
+            recommend(
+                Priority::High,
+                format!(
+                    "Fix {} validation error(s) (`forgekit validate`)",
+                    self.validation_errors
+                ),
+                (self.validation_errors as f64 * 15.0).min(30.0),
+            );
+        }
+        if self.validation_warnings > 0 {
+            recommend(
+                Priority::Low,
+                format!("Address {} validation warning(s)", self.validation_warnings),
+                (self.validation_warnings as f64 * 2.0).min(10.0),
+            );
+        }
+        if let Some(coverage) = self.test_coverage.filter(|c| *c < COVERAGE_TARGET) {
+            recommend(
+                Priority::Medium,
+                format!(
+                    "Raise test coverage from {:.0}% to {:.0}%",
+                    coverage, COVERAGE_TARGET
+                ),
+                ((COVERAGE_TARGET - coverage) / 4.0).min(20.0),
+            );
+        }
+        if self.outdated > 0 {
+            recommend(
+                Priority::Low,
+                format!(
+                    "Update {} outdated dependencies (`forgekit outdated`)",
+                    self.outdated
+                ),
+                (self.outdated as f64 * 2.0).min(10.0),
+            );
+        }
+        if let Some(trend) = self.build_trend.filter(|trend| *trend > 0.2) {
+            recommend(
+                Priority::Low,
+                format!(
+                    "Builds got {:.0}% slower recently; check new dependencies or enable a compiler cache",
+                    trend * 100.0
+                ),
+                5.0,
+            );
+        }
+
+        if self.flaky_tests > 0 {
+            recommend(
+                Priority::Medium,
+                format!(
+                    "Fix {} flaky test(s), or quarantine them in `[test]` (`forgekit flaky`)",
+                    self.flaky_tests
+                ),
+                (self.flaky_tests as f64 * 3.0).min(10.0),
+            );
+        }
+
+        if self.stale_todos > 0 {
+            recommend(
+                Priority::Low,
+                format!(
+                    "Resolve {} TODO/FIXME/HACK marker(s) older than {} days (`forgekit todos`)",
+                    self.stale_todos, STALE_MARKER_DAYS
+                ),
+                (self.stale_todos as f64).min(5.0),
+            );
+        }
+
+        if self.typos > 0 {
+            recommend(
+                Priority::Low,
+                format!(
+                    "Fix {} misspelled word(s) in comments (`forgekit todos`)",
+                    self.typos
+                ),
+                (self.typos as f64 * 0.5).min(3.0),
+            );
+        }
+
+        self.recommendations.sort_by_key(|r| r.priority);
+        self.score = (100.0 - penalty).clamp(0.0, 100.0).round() as u32;
+        self.grade = match self.score {
+            90.. => 'A',
+            80..=89 => 'B',
+            70..=79 => 'C',
+            60..=69 => 'D',
+            _ => 'F',
+        };
+    }
+
+    /// Render the report as Markdown, e.g. for a pull request comment
+    pub fn to_markdown(&self) -> String {
+        let vulns = &self.vulnerabilities;
+        let trend = match self.build_trend {
+            Some(trend) => format!("{:+.0}%", trend * 100.0),
+            None => "not enough builds".to_string(),
+        };
+        let coverage = match self.test_coverage {
+            Some(coverage) => format!("{:.1}%", coverage),
+            None => "not measured".to_string(),
+        };
+        let mut markdown = format!(
+            "## Project health: {} ({}/100)\n\n\
+             | Check | Result |\n\
+             | --- | --- |\n\
+             | Validation | {} error(s), {} warning(s) |\n\
+             | Vulnerabilities | {} critical, {} high, {} medium, {} low |\n\
+             | Outdated dependencies | {} |\n\
+             | Test coverage | {} |\n\
+             | Build time trend | {} |\n\
+             | Flaky tests | {} |\n\
+             | TODO markers | {} ({} stale) |\n\
+             | Spelling | {} typo(s) |\n",
+            self.grade,
+            self.score,
+            self.validation_errors,
+            self.validation_warnings,
+            vulns.critical,
+            vulns.high,
+            vulns.medium,
+            vulns.low,
+            self.outdated,
+            coverage,
+            trend,
+            self.flaky_tests,
+            self.todos,
+            self.stale_todos,
+            self.typos
+        );
+
+        if !self.recommendations.is_empty() {
+            markdown.push_str("\n### Recommendations\n\n");
+            for recommendation in &self.recommendations {
+                let priority = match recommendation.priority {
+                    Priority::High => "high",
+                    Priority::Medium => "medium",
+                    Priority::Low => "low",
+                };
+                markdown.push_str(&format!("- **{}**: {}\n", priority, recommendation.message));
+            }
+        }
+        markdown
+    }
 }
 
 #[cfg(test)]
@@ -84,4 +1359,219 @@ mod tests {
         let result = AnalyticsCollector::collect_metrics(temp_dir.path()).await;
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_health_grading() {
+        let mut report = HealthReport {
+            score: 0,
+            grade: 'F',
+            validation_errors: 0,
+            validation_warnings: 1,
+            vulnerabilities: SeveritySummary {
+                critical: 0,
+                high: 1,
+                medium: 0,
+                low: 0,
+            },
+            outdated: 0,
+            test_coverage: Some(60.0),
+            build_trend: None,
+            flaky_tests: 0,
+            todos: 0,
+            stale_todos: 0,
+            typos: 0,
+            recommendations: Vec::new(),
+            generated_at: String::new(),
+        };
+        report.grade_findings();
+
+        // 15 (high vulnerability) + 5 (coverage) + 2 (warning)
+        assert_eq!(report.score, 78);
+        assert_eq!(report.grade, 'C');
+        let priorities: Vec<Priority> = report.recommendations.iter().map(|r| r.priority).collect();
+        assert_eq!(
+            priorities,
+            [Priority::High, Priority::Medium, Priority::Low]
+        );
+        assert!(report
+            .to_markdown()
+            .starts_with("## Project health: C (78/100)"));
+    }
+
+    #[tokio::test]
+    async fn test_todo_report() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(
+            src.join("main.rs"),
+            "// TODO: split this up\nfn main() {\n    let todo = 1; // FIXME(ada): overflow\n}\n/* HACK until 2.0 */\n/// Recieve the value\nfn seperate() {}\n",
+        )
+        .unwrap();
+
+        let report = AnalyticsCollector::todo_report(temp_dir.path())
+            .await
+            .unwrap();
+        let found: Vec<(MarkerKind, usize, &str)> = report
+            .markers
+            .iter()
+            .map(|m| (m.kind, m.line, m.text.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (MarkerKind::Todo, 1, "split this up"),
+                (MarkerKind::Fixme, 3, "overflow"),
+                (MarkerKind::Hack, 5, "until 2.0"),
+            ]
+        );
+        assert_eq!(report.counts[&MarkerKind::Todo], 1);
+        let typos: Vec<(usize, &str, &str)> = report
+            .typos
+            .iter()
+            .map(|t| (t.line, t.word.as_str(), t.suggestion.as_str()))
+            .collect();
+        assert_eq!(typos, [(6, "Recieve", "receive")]);
+
+        let blame = format!(
+            "{0} 1 1 1\nauthor Ada\nauthor-time 1700000000\n\t// TODO\n{1} 2 2 1\nauthor Not Committed Yet\nauthor-time 1800000000\n\tx\n",
+            "a".repeat(40),
+            "0".repeat(40)
+        );
+        let lines = parse_blame(&blame);
+        assert_eq!(lines.get(&1), Some(&("Ada".to_string(), 1_700_000_000)));
+        assert!(!lines.contains_key(&2));
+    }
+
+    #[test]
+    fn test_flake_rates_from_test_history() {
+        let temp_dir = TempDir::new().unwrap();
+        for flaky in [vec!["a", "b"], vec!["a"], vec![], vec![]] {
+            let report = TestReport {
+                total: 3,
+                flaky: flaky.into_iter().map(String::from).collect(),
+                ..TestReport::new()
+            };
+            record_test_run(temp_dir.path(), &report).unwrap();
+        }
+
+        let rates = flake_rates(temp_dir.path());
+        let summary: Vec<(&str, usize, f64)> = rates
+            .iter()
+            .map(|r| (r.test.as_str(), r.flaky_runs, r.rate))
+            .collect();
+        assert_eq!(summary, [("a", 2, 0.5), ("b", 1, 0.25)]);
+    }
+
+    #[tokio::test]
+    async fn test_coverage_from_tool_output_or_last_record() {
+        assert_eq!(
+            llvm_cov_percent(
+                r#"{"data":[{"totals":{"lines":{"count":8,"covered":6,"percent":75.0}}}]}"#
+            ),
+            Some(75.0)
+        );
+        assert_eq!(
+            tarpaulin_percent("|| src/lib.rs: 6/8\n||\n62.50% coverage, 5/8 lines covered\n"),
+            Some(62.5)
+        );
+        assert_eq!(tarpaulin_percent("error: no tests"), None);
+
+        // Outside a Cargo project nothing is measured
+        let temp_dir = TempDir::new().unwrap();
+        assert!(measure_coverage(temp_dir.path()).await.is_none());
+        assert!(last_coverage(temp_dir.path()).is_none());
+        let record = CoverageRecord {
+            finished_at: chrono::Local::now().to_rfc3339(),
+            tool: "llvm-cov".to_string(),
+            percent: 81.5,
+        };
+        record_coverage(temp_dir.path(), &record).unwrap();
+        assert_eq!(last_coverage(temp_dir.path()).unwrap().percent, 81.5);
+    }
+
+    #[test]
+    fn test_build_history_trend() {
+        let temp_dir = TempDir::new().unwrap();
+        for ms in [100, 100, 100, 100, 100, 150, 150, 150, 150, 150] {
+            record_build(temp_dir.path(), Duration::from_millis(ms)).unwrap();
+        }
+
+        let history = build_history(temp_dir.path());
+        assert_eq!(history.len(), 10);
+        let times: Vec<Duration> = history
+            .iter()
+            .map(|r| Duration::from_millis(r.duration_ms))
+            .collect();
+        let trend = build_trend(&times).unwrap();
+        assert!((trend - 0.5).abs() < 1e-9);
+        assert_eq!(build_trend(&times[..9]), None);
+    }
+
+    #[tokio::test]
+    async fn test_unsafe_report_counts_project_and_dependencies() {
+        let temp_dir = TempDir::new().unwrap();
+        let write = |path: &str, contents: &str| {
+            let path = temp_dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        };
+        let manifest = |name: &str, deps: &str| {
+            format!(
+                "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n{}",
+                name, deps
+            )
+        };
+        write(
+            "app/Cargo.toml",
+            &manifest(
+                "app",
+                "[dependencies]\nsys = { path = \"../sys\" }\n\n[dev-dependencies]\nmock = { path = \"../mock\" }\n",
+            ),
+        );
+        write("app/src/main.rs", "#![forbid(unsafe_code)]\nfn main() {}\n");
+        write("app/tests/raw.rs", "fn f() { unsafe {} }\n");
+        write("sys/Cargo.toml", &manifest("sys", ""));
+        write(
+            "sys/src/lib.rs",
+            r#"
+extern "C" {
+    fn abs(x: i32) -> i32;
+    static errno: i32;
+}
+pub struct Handle;
+unsafe impl Send for Handle {}
+pub unsafe fn raw() -> i32 { unsafe { abs(-1) } }
+#[no_mangle]
+pub extern "C" fn exported() {}
+"#,
+        );
+        write("mock/Cargo.toml", &manifest("mock", ""));
+        write("mock/src/lib.rs", "pub fn f() { unsafe {} }\n");
+
+        let report = AnalyticsCollector::unsafe_report(&temp_dir.path().join("app"))
+            .await
+            .unwrap();
+        let names: Vec<_> = report.crates.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["app", "sys"]);
+
+        let app = &report.crates[0];
+        assert!(app.is_project && app.forbids_unsafe);
+        assert_eq!(app.counts.total(), 0);
+
+        let sys: Vec<_> = report.unsafe_dependencies().collect();
+        assert_eq!(sys.len(), 1);
+        assert!(!sys[0].forbids_unsafe);
+        assert_eq!(
+            sys[0].counts,
+            UnsafeCounts {
+                blocks: 1,
+                functions: 1,
+                impls: 1,
+                traits: 0,
+                ffi_imports: 2,
+                ffi_exports: 1,
+            }
+        );
+    }
 }
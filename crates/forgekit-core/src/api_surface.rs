@@ -0,0 +1,1077 @@
+//! Public API surface module
+//!
+//! This module parses a library's sources with `syn` and lists what it
+//! exports: the public items reachable from `src/lib.rs` through public
+//! modules, including re-exports and the public methods of inherent impls.
+//! From that list it computes documentation coverage, and diffing it against
+//! the surface of the previous release, read straight from the release's git
+//! tag, flags the changes that break dependents before the version is bumped.
+//!
+//! Declarations are compared after removing attributes, bodies and private
+//! fields, so editing docs or implementations never counts as a change.
+
+use crate::cancel::{self, CancellationToken};
+use crate::error::ForgeKitError;
+use quote::ToTokens;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Kind of a public item
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemKind {
+    Module,
+    Function,
+    Method,
+    Struct,
+    Enum,
+    Union,
+    Trait,
+    TypeAlias,
+    Constant,
+    Static,
+    Reexport,
+}
+
+impl fmt::Display for ItemKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            ItemKind::Module => "module",
+            ItemKind::Function => "function",
+            ItemKind::Method => "method",
+            ItemKind::Struct => "struct",
+            ItemKind::Enum => "enum",
+            ItemKind::Union => "union",
+            ItemKind::Trait => "trait",
+            ItemKind::TypeAlias => "type alias",
+            ItemKind::Constant => "constant",
+            ItemKind::Static => "static",
+            ItemKind::Reexport => "re-export",
+        };
+        write!(f, "{}", kind)
+    }
+}
+
+/// An item of the public API
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ApiItem {
+    /// Path of the item, like `crate::cache::BuildCache::get`
+    pub path: String,
+    /// Kind of item
+    pub kind: ItemKind,
+    /// Declaration without attributes, bodies or private fields
+    pub signature: String,
+    /// Whether the item has a doc comment
+    pub documented: bool,
+    /// Source file, relative to the project
+    pub file: PathBuf,
+}
+
+/// Public items of a library
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ApiSurface {
+    /// Items in declaration order
+    pub items: Vec<ApiItem>,
+}
+
+impl ApiSurface {
+    /// Items documentation coverage counts
+    ///
+    /// Re-exports are left out, their docs are the ones of the item they name.
+    fn documentable(&self) -> impl Iterator<Item = &ApiItem> {
+        self.items
+            .iter()
+            .filter(|item| item.kind != ItemKind::Reexport)
+    }
+
+    /// Percentage of items with a doc comment, 100 for an empty surface
+    pub fn coverage(&self) -> f64 {
+        let total = self.documentable().count();
+        if total == 0 {
+            return 100.0;
+        }
+        let documented = self.documentable().filter(|item| item.documented).count();
+        documented as f64 * 100.0 / total as f64
+    }
+
+    /// Public items without a doc comment
+    pub fn undocumented(&self) -> impl Iterator<Item = &ApiItem> {
+        self.documentable().filter(|item| !item.documented)
+    }
+
+    /// Changes from the `previous` surface to this one
+    pub fn diff(&self, previous: &ApiSurface) -> ApiDiff {
+        let before: BTreeMap<&str, &ApiItem> = previous
+            .items
+            .iter()
+            .map(|item| (item.path.as_str(), item))
+            .collect();
+        let after: BTreeMap<&str, &ApiItem> = self
+            .items
+            .iter()
+            .map(|item| (item.path.as_str(), item))
+            .collect();
+
+        let mut changes = Vec::new();
+        for (path, item) in &after {
+            match before.get(path) {
+                None => changes.push(ApiChange::Added {
+                    item: (*item).clone(),
+                }),
+                Some(old) if old.kind != item.kind || old.signature != item.signature => changes
+                    .push(ApiChange::Changed {
+                        before: (*old).clone(),
+                        after: (*item).clone(),
+                    }),
+                Some(_) => {}
+            }
+        }
+        for (path, item) in &before {
+            if !after.contains_key(path) {
+                changes.push(ApiChange::Removed {
+                    item: (*item).clone(),
+                });
+            }
+        }
+        changes.sort_by(|a, b| a.path().cmp(b.path()));
+        ApiDiff { changes }
+    }
+}
+
+/// A difference between two API surfaces
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum ApiChange {
+    /// A new public item
+    Added { item: ApiItem },
+    /// A public item that no longer exists
+    Removed { item: ApiItem },
+    /// A public item whose declaration changed
+    Changed { before: ApiItem, after: ApiItem },
+}
+
+impl ApiChange {
+    /// Path of the changed item
+    pub fn path(&self) -> &str {
+        match self {
+            ApiChange::Added { item } | ApiChange::Removed { item } => &item.path,
+            ApiChange::Changed { after, .. } => &after.path,
+        }
+    }
+
+    /// Whether code using the previous API may stop compiling
+    ///
+    /// Every changed declaration counts, even ones that only widen the API,
+    /// like a new enum variant or a new field of a struct without private
+    /// fields, which break exhaustive matches and struct literals.
+    pub fn is_breaking(&self) -> bool {
+        !matches!(self, ApiChange::Added { .. })
+    }
+}
+
+impl fmt::Display for ApiChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiChange::Added { item } => write!(f, "added {} {}", item.kind, item.path),
+            ApiChange::Removed { item } => write!(f, "removed {} {}", item.kind, item.path),
+            ApiChange::Changed { before, after } => write!(
+                f,
+                "changed {} {}: `{}` → `{}`",
+                after.kind, after.path, before.signature, after.signature
+            ),
+        }
+    }
+}
+
+/// Changes between two API surfaces
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ApiDiff {
+    /// Changes sorted by item path
+    pub changes: Vec<ApiChange>,
+}
+
+impl ApiDiff {
+    /// Changes that break code using the previous API
+    pub fn breaking(&self) -> impl Iterator<Item = &ApiChange> {
+        self.changes.iter().filter(|change| change.is_breaking())
+    }
+
+    /// Whether any change breaks code using the previous API
+    pub fn is_breaking(&self) -> bool {
+        self.breaking().next().is_some()
+    }
+
+    /// Whether anything was added
+    pub fn has_additions(&self) -> bool {
+        self.changes
+            .iter()
+            .any(|change| matches!(change, ApiChange::Added { .. }))
+    }
+}
+
+/// Public API of the library in the project's working tree
+///
+/// A project without `src/lib.rs` has an empty API.
+pub fn scan(project_path: &Path) -> Result<ApiSurface, ForgeKitError> {
+    scan_with(|file| std::fs::read_to_string(project_path.join(file)).ok())
+}
+
+/// Public API of the library at a git revision of the project, like a release tag
+pub fn scan_revision(project_path: &Path, revision: &str) -> Result<ApiSurface, ForgeKitError> {
+    let status = std::process::Command::new("git")
+        .args(["rev-parse", "--verify", "--quiet"])
+        .arg(format!("{}^{{commit}}", revision))
+        .current_dir(project_path)
+        .stdout(std::process::Stdio::null())
+        .status()?;
+    if !status.success() {
+        return Err(ForgeKitError::InvalidConfig(format!(
+            "'{}' is not a revision of the project's repository",
+            revision
+        )));
+    }
+
+    scan_with(|file| {
+        // git paths are relative to the repository root unless prefixed with ./
+        let spec = format!(
+            "{}:./{}",
+            revision,
+            file.to_string_lossy().replace('\\', "/")
+        );
+        let output = std::process::Command::new("git")
+            .args(["show", &spec])
+            .current_dir(project_path)
+            .output()
+            .ok()?;
+        if output.status.success() {
+            String::from_utf8(output.stdout).ok()
+        } else {
+            None
+        }
+    })
+}
+
+/// Most recent release tag reachable from the project's `HEAD`, if any
+pub fn previous_release(project_path: &Path) -> Result<Option<String>, ForgeKitError> {
+    let output = std::process::Command::new("git")
+        .args(["describe", "--tags", "--abbrev=0"])
+        .current_dir(project_path)
+        .output()?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let tag = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok((!tag.is_empty()).then_some(tag))
+}
+
+/// Public API of the library packaged in a .moxlib, from its sources
+pub fn scan_archive(archive: &Path) -> Result<ApiSurface, ForgeKitError> {
+    let mut zip = zip::ZipArchive::new(std::fs::File::open(archive)?)?;
+    let mut sources = HashMap::new();
+    for index in 0..zip.len() {
+        let mut file = zip.by_index(index)?;
+        if !file.name().starts_with("src/") || !file.name().ends_with(".rs") {
+            continue;
+        }
+        let name = PathBuf::from(file.name());
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        sources.insert(name, contents);
+    }
+    scan_with(|file| {
+        let name = file.to_string_lossy().replace('\\', "/");
+        sources.get(Path::new(&name)).cloned()
+    })
+}
+
+/// Generate rustdoc JSON of the library at `project_path`
+///
+/// The JSON output of rustdoc is unstable, so it is unlocked with
+/// `RUSTC_BOOTSTRAP` instead of requiring a nightly toolchain. The file is
+/// moved out of `target/doc` so it is not packaged with the HTML docs.
+pub async fn rustdoc_json(
+    project_path: &Path,
+    crate_name: &str,
+    cancel: &CancellationToken,
+) -> Result<PathBuf, ForgeKitError> {
+    let mut command = tokio::process::Command::new("cargo");
+    command
+        .args(["rustdoc", "--lib", "--", "-Z", "unstable-options"])
+        .args(["--output-format", "json"])
+        .env("RUSTC_BOOTSTRAP", "1")
+        .current_dir(project_path);
+    let output = cancel::output(&mut command, cancel, "rustdoc").await?;
+    if !output.status.success() {
+        return Err(ForgeKitError::BuildFailed(format!(
+            "cargo rustdoc failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    let target = project_path.join("target");
+    let api = target.join(format!("{}.api.json", crate_name));
+    std::fs::rename(
+        target.join("doc").join(format!("{}.json", crate_name)),
+        &api,
+    )?;
+    Ok(api)
+}
+
+/// Public API described by rustdoc JSON
+///
+/// Items are listed like [`scan`] does, but declarations are rustdoc's
+/// resolved ones, so a change of type behind an alias or re-export shows up
+/// too. Signatures are the item's JSON with ids, docs and impl lists removed,
+/// and so only comparable with other surfaces read from rustdoc JSON.
+pub fn from_rustdoc(json: &Value) -> Result<ApiSurface, ForgeKitError> {
+    let index = json["index"]
+        .as_object()
+        .ok_or_else(|| ForgeKitError::BuildFailed("rustdoc JSON has no item index".to_string()))?;
+    let root = id_key(&json["root"])
+        .and_then(|root| index.get(&root))
+        .ok_or_else(|| ForgeKitError::BuildFailed("rustdoc JSON has no root".to_string()))?;
+
+    let mut rustdoc = Rustdoc {
+        index,
+        items: Vec::new(),
+    };
+    rustdoc.module(root, "crate");
+    Ok(ApiSurface {
+        items: rustdoc.items,
+    })
+}
+
+/// Walks the module tree of rustdoc JSON
+struct Rustdoc<'a> {
+    index: &'a serde_json::Map<String, Value>,
+    items: Vec<ApiItem>,
+}
+
+impl<'a> Rustdoc<'a> {
+    fn get(&self, id: &Value) -> Option<&'a Value> {
+        id_key(id).and_then(|id| self.index.get(&id))
+    }
+
+    fn module(&mut self, module: &'a Value, path: &str) {
+        let ids = module["inner"]["module"]["items"]
+            .as_array()
+            .map_or(&[][..], Vec::as_slice);
+        for id in ids {
+            let Some(item) = self.get(id) else {
+                continue;
+            };
+            if item["visibility"] != "public" {
+                continue;
+            }
+            let Some((kind, inner)) = item["inner"]
+                .as_object()
+                .and_then(|inner| inner.iter().next())
+            else {
+                continue;
+            };
+            let name = item["name"].as_str().unwrap_or_default();
+            match kind.as_str() {
+                "module" => {
+                    self.push(path, name, ItemKind::Module, format!("mod {}", name), item);
+                    self.module(item, &format!("{}::{}", path, name));
+                }
+                "use" => {
+                    let source = inner["source"].as_str().unwrap_or_default();
+                    let name = if inner["is_glob"] == true {
+                        format!("{}::*", source)
+                    } else {
+                        inner["name"].as_str().unwrap_or(name).to_string()
+                    };
+                    self.push(
+                        path,
+                        &name,
+                        ItemKind::Reexport,
+                        format!("pub use {}", source),
+                        item,
+                    );
+                }
+                "trait" => {
+                    let mut declaration = inner.clone();
+                    let mut provided = Vec::new();
+                    if let Some(items) = declaration["items"].as_array_mut() {
+                        items.retain(|id| match self.get(id) {
+                            Some(item) if item["inner"]["function"]["has_body"] == true => {
+                                provided.push(item);
+                                false
+                            }
+                            _ => true,
+                        });
+                    }
+                    let signature = self.signature(&declaration);
+                    self.push(path, name, ItemKind::Trait, signature, item);
+                    let trait_path = format!("{}::{}", path, name);
+                    for method in provided {
+                        let signature = self.signature(&method["inner"]["function"]);
+                        let method_name = method["name"].as_str().unwrap_or_default();
+                        self.push(
+                            &trait_path,
+                            method_name,
+                            ItemKind::Method,
+                            signature,
+                            method,
+                        );
+                    }
+                }
+                kind => {
+                    let kind = match kind {
+                        "function" => ItemKind::Function,
+                        "struct" => ItemKind::Struct,
+                        "enum" => ItemKind::Enum,
+                        "union" => ItemKind::Union,
+                        "type_alias" => ItemKind::TypeAlias,
+                        "constant" => ItemKind::Constant,
+                        "static" => ItemKind::Static,
+                        _ => continue,
+                    };
+                    // The value of a constant is not part of its API
+                    let signature = match kind {
+                        ItemKind::Constant => self.signature(&inner["type"]),
+                        _ => self.signature(inner),
+                    };
+                    self.push(path, name, kind, signature, item);
+                    self.inherent_methods(inner, &format!("{}::{}", path, name));
+                }
+            }
+        }
+    }
+
+    /// Public methods and constants of the inherent impls of a type
+    fn inherent_methods(&mut self, inner: &'a Value, type_path: &str) {
+        let impls = inner["impls"].as_array().map_or(&[][..], Vec::as_slice);
+        for id in impls {
+            let Some(item) = self.get(id) else {
+                continue;
+            };
+            let block = &item["inner"]["impl"];
+            if !block["trait"].is_null() {
+                continue;
+            }
+            let ids = block["items"].as_array().map_or(&[][..], Vec::as_slice);
+            for id in ids {
+                let Some(member) = self.get(id) else {
+                    continue;
+                };
+                if member["visibility"] != "public" {
+                    continue;
+                }
+                let name = member["name"].as_str().unwrap_or_default();
+                let (kind, inner) = if member["inner"]["function"].is_object() {
+                    (ItemKind::Method, &member["inner"]["function"])
+                } else if member["inner"]["assoc_const"].is_object() {
+                    (ItemKind::Constant, &member["inner"]["assoc_const"]["type"])
+                } else {
+                    continue;
+                };
+                let signature = self.signature(inner);
+                self.push(type_path, name, kind, signature, member);
+            }
+        }
+    }
+
+    /// Normalized JSON of an item's declaration
+    fn signature(&self, inner: &Value) -> String {
+        self.normalize(inner, 0).to_string()
+    }
+
+    /// `value` without ids and impl lists, with the fields, variants and
+    /// trait items it references inlined
+    fn normalize(&self, value: &Value, depth: usize) -> Value {
+        match value {
+            Value::Object(object) => Value::Object(
+                object
+                    .iter()
+                    .filter(|(key, _)| !["id", "impls", "implementations"].contains(&key.as_str()))
+                    .map(|(key, value)| {
+                        let value = match (key.as_str(), value) {
+                            ("fields" | "variants" | "items" | "tuple", Value::Array(ids))
+                                if depth < RUSTDOC_DEPTH =>
+                            {
+                                Value::Array(ids.iter().map(|id| self.inline(id, depth)).collect())
+                            }
+                            _ => self.normalize(value, depth),
+                        };
+                        (key.clone(), value)
+                    })
+                    .collect(),
+            ),
+            Value::Array(values) => Value::Array(
+                values
+                    .iter()
+                    .map(|value| self.normalize(value, depth))
+                    .collect(),
+            ),
+            value => value.clone(),
+        }
+    }
+
+    /// Name and normalized declaration of a referenced item, `null` for a
+    /// stripped one
+    fn inline(&self, id: &Value, depth: usize) -> Value {
+        match self.get(id) {
+            Some(item) => serde_json::json!({
+                "name": item["name"],
+                "inner": self.normalize(&item["inner"], depth + 1),
+            }),
+            None => Value::Null,
+        }
+    }
+
+    fn push(&mut self, path: &str, name: &str, kind: ItemKind, signature: String, item: &Value) {
+        self.items.push(ApiItem {
+            path: format!("{}::{}", path, name),
+            kind,
+            signature,
+            documented: item["docs"].is_string(),
+            file: item["span"]["filename"]
+                .as_str()
+                .map(PathBuf::from)
+                .unwrap_or_default(),
+        });
+    }
+}
+
+/// Nesting of inlined items followed, bounding recursive types
+const RUSTDOC_DEPTH: usize = 8;
+
+/// Index key of a rustdoc id, a number in recent formats and a string before
+fn id_key(id: &Value) -> Option<String> {
+    match id {
+        Value::Number(id) => Some(id.to_string()),
+        Value::String(id) => Some(id.clone()),
+        _ => None,
+    }
+}
+
+/// Scan the library, reading sources by their path relative to the project
+fn scan_with(read: impl Fn(&Path) -> Option<String>) -> Result<ApiSurface, ForgeKitError> {
+    let root = Path::new("src").join("lib.rs");
+    let Some(source) = read(&root) else {
+        return Ok(ApiSurface::default());
+    };
+    let file = syn::parse_file(&source).map_err(|e| {
+        ForgeKitError::BuildFailed(format!("cannot parse {}: {}", root.display(), e))
+    })?;
+
+    let mut scanner = Scanner {
+        read,
+        items: Vec::new(),
+    };
+    scanner.module(&file.items, "crate", &root, Path::new("src"));
+    Ok(ApiSurface {
+        items: scanner.items,
+    })
+}
+
+/// Collects the public items of a module tree
+struct Scanner<F> {
+    read: F,
+    items: Vec<ApiItem>,
+}
+
+impl<F: Fn(&Path) -> Option<String>> Scanner<F> {
+    /// Collect the items of the module at `path`, declared in `file`, whose
+    /// child module files live in `dir`
+    fn module(&mut self, items: &[syn::Item], path: &str, file: &Path, dir: &Path) {
+        for item in items {
+            self.item(item, path, file, dir);
+        }
+        // Methods after the types, so only methods of public types are kept
+        for item in items {
+            if let syn::Item::Impl(item) = item {
+                self.inherent_impl(item, path, file);
+            }
+        }
+    }
+
+    fn item(&mut self, item: &syn::Item, path: &str, file: &Path, dir: &Path) {
+        use syn::Item;
+
+        match item {
+            Item::Fn(item) if exported(&item.vis, &item.attrs) => self.push(
+                path,
+                &item.sig.ident,
+                ItemKind::Function,
+                item.sig.to_token_stream().to_string(),
+                &item.attrs,
+                file,
+            ),
+            Item::Struct(item) if exported(&item.vis, &item.attrs) => {
+                let mut declaration = item.clone();
+                declaration.attrs.clear();
+                let private = public_fields(&mut declaration.fields);
+                self.push(
+                    path,
+                    &item.ident,
+                    ItemKind::Struct,
+                    with_private_fields(declaration.to_token_stream().to_string(), private),
+                    &item.attrs,
+                    file,
+                );
+            }
+            Item::Union(item) if exported(&item.vis, &item.attrs) => {
+                let mut declaration = item.clone();
+                declaration.attrs.clear();
+                let mut fields = syn::Fields::Named(declaration.fields.clone());
+                let private = public_fields(&mut fields);
+                if let syn::Fields::Named(fields) = fields {
+                    declaration.fields = fields;
+                }
+                self.push(
+                    path,
+                    &item.ident,
+                    ItemKind::Union,
+                    with_private_fields(declaration.to_token_stream().to_string(), private),
+                    &item.attrs,
+                    file,
+                );
+            }
+            Item::Enum(item) if exported(&item.vis, &item.attrs) => {
+                let mut declaration = item.clone();
+                declaration.attrs.clear();
+                for variant in &mut declaration.variants {
+                    variant.attrs.clear();
+                    for field in variant.fields.iter_mut() {
+                        field.attrs.clear();
+                    }
+                }
+                self.push(
+                    path,
+                    &item.ident,
+                    ItemKind::Enum,
+                    declaration.to_token_stream().to_string(),
+                    &item.attrs,
+                    file,
+                );
+            }
+            Item::Trait(item) if exported(&item.vis, &item.attrs) => self.trait_(item, path, file),
+            Item::Type(item) if exported(&item.vis, &item.attrs) => {
+                let mut declaration = item.clone();
+                declaration.attrs.clear();
+                self.push(
+                    path,
+                    &item.ident,
+                    ItemKind::TypeAlias,
+                    declaration.to_token_stream().to_string(),
+                    &item.attrs,
+                    file,
+                );
+            }
+            Item::Const(item) if exported(&item.vis, &item.attrs) => self.push(
+                path,
+                &item.ident,
+                ItemKind::Constant,
+                format!("const {} : {}", item.ident, item.ty.to_token_stream()),
+                &item.attrs,
+                file,
+            ),
+            Item::Static(item) if exported(&item.vis, &item.attrs) => self.push(
+                path,
+                &item.ident,
+                ItemKind::Static,
+                format!(
+                    "static {}{} : {}",
+                    if matches!(item.mutability, syn::StaticMutability::Mut(_)) {
+                        "mut "
+                    } else {
+                        ""
+                    },
+                    item.ident,
+                    item.ty.to_token_stream()
+                ),
+                &item.attrs,
+                file,
+            ),
+            Item::Use(item) if exported(&item.vis, &item.attrs) => {
+                let mut leaves = Vec::new();
+                use_leaves(&item.tree, String::new(), &mut leaves);
+                for (name, target) in leaves {
+                    self.items.push(ApiItem {
+                        path: format!("{}::{}", path, name),
+                        kind: ItemKind::Reexport,
+                        signature: format!("pub use {}", target),
+                        documented: documented(&item.attrs),
+                        file: file.to_path_buf(),
+                    });
+                }
+            }
+            Item::Mod(item) if exported(&item.vis, &item.attrs) => {
+                let index = self.items.len();
+                self.push(
+                    path,
+                    &item.ident,
+                    ItemKind::Module,
+                    format!("mod {}", item.ident),
+                    &item.attrs,
+                    file,
+                );
+                let module_path = format!("{}::{}", path, item.ident);
+                let module_dir = dir.join(item.ident.to_string());
+                match &item.content {
+                    Some((_, items)) => self.module(items, &module_path, file, &module_dir),
+                    None => {
+                        // `//!` docs at the top of the module's file
+                        if self.module_file(&item.ident.to_string(), &module_path, dir) {
+                            self.items[index].documented = true;
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Collect a module declared as `mod name;`, from `name.rs` or
+    /// `name/mod.rs`, returning whether the file documents the module
+    fn module_file(&mut self, name: &str, path: &str, dir: &Path) -> bool {
+        let module_dir = dir.join(name);
+        for file in [dir.join(format!("{}.rs", name)), module_dir.join("mod.rs")] {
+            let Some(source) = (self.read)(&file) else {
+                continue;
+            };
+            return match syn::parse_file(&source) {
+                Ok(parsed) => {
+                    self.module(&parsed.items, path, &file, &module_dir);
+                    documented(&parsed.attrs)
+                }
+                Err(e) => {
+                    tracing::warn!("Skipping {}: {}", file.display(), e);
+                    false
+                }
+            };
+        }
+        tracing::warn!("No source file for module {}", path);
+        false
+    }
+
+    /// A trait, whose declaration includes the items implementors must
+    /// provide, and its provided methods as items of their own
+    fn trait_(&mut self, item: &syn::ItemTrait, path: &str, file: &Path) {
+        let mut declaration = item.clone();
+        declaration.attrs.clear();
+        let mut provided = Vec::new();
+        declaration.items.retain_mut(|trait_item| match trait_item {
+            syn::TraitItem::Fn(method) if method.default.is_some() => {
+                provided.push(method.clone());
+                false
+            }
+            syn::TraitItem::Fn(method) => {
+                method.attrs.clear();
+                true
+            }
+            syn::TraitItem::Type(ty) => {
+                ty.attrs.clear();
+                true
+            }
+            syn::TraitItem::Const(constant) => {
+                constant.attrs.clear();
+                constant.default = None;
+                true
+            }
+            _ => true,
+        });
+        self.push(
+            path,
+            &item.ident,
+            ItemKind::Trait,
+            declaration.to_token_stream().to_string(),
+            &item.attrs,
+            file,
+        );
+
+        let trait_path = format!("{}::{}", path, item.ident);
+        for method in provided {
+            self.push(
+                &trait_path,
+                &method.sig.ident,
+                ItemKind::Method,
+                method.sig.to_token_stream().to_string(),
+                &method.attrs,
+                file,
+            );
+        }
+    }
+
+    /// Public methods and constants of an inherent impl of a public type
+    fn inherent_impl(&mut self, item: &syn::ItemImpl, path: &str, file: &Path) {
+        if item.trait_.is_some() {
+            return;
+        }
+        let syn::Type::Path(self_ty) = item.self_ty.as_ref() else {
+            return;
+        };
+        let Some(type_name) = self_ty.path.segments.last() else {
+            return;
+        };
+        let type_path = format!("{}::{}", path, type_name.ident);
+        if !self.items.iter().any(|item| item.path == type_path) {
+            return;
+        }
+
+        for impl_item in &item.items {
+            match impl_item {
+                syn::ImplItem::Fn(method) if exported(&method.vis, &method.attrs) => self.push(
+                    &type_path,
+                    &method.sig.ident,
+                    ItemKind::Method,
+                    method.sig.to_token_stream().to_string(),
+                    &method.attrs,
+                    file,
+                ),
+                syn::ImplItem::Const(constant) if exported(&constant.vis, &constant.attrs) => self
+                    .push(
+                        &type_path,
+                        &constant.ident,
+                        ItemKind::Constant,
+                        format!(
+                            "const {} : {}",
+                            constant.ident,
+                            constant.ty.to_token_stream()
+                        ),
+                        &constant.attrs,
+                        file,
+                    ),
+                _ => {}
+            }
+        }
+    }
+
+    fn push(
+        &mut self,
+        path: &str,
+        ident: &syn::Ident,
+        kind: ItemKind,
+        signature: String,
+        attrs: &[syn::Attribute],
+        file: &Path,
+    ) {
+        self.items.push(ApiItem {
+            path: format!("{}::{}", path, ident),
+            kind,
+            signature,
+            documented: documented(attrs),
+            file: file.to_path_buf(),
+        });
+    }
+}
+
+/// Whether an item is `pub` and not hidden from the docs
+fn exported(vis: &syn::Visibility, attrs: &[syn::Attribute]) -> bool {
+    matches!(vis, syn::Visibility::Public(_)) && !hidden(attrs)
+}
+
+/// Whether attributes contain `#[doc(hidden)]`
+fn hidden(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        let mut hidden = false;
+        if attr.path().is_ident("doc") {
+            let _ = attr.parse_nested_meta(|meta| {
+                hidden |= meta.path.is_ident("hidden");
+                Ok(())
+            });
+        }
+        hidden
+    })
+}
+
+/// Whether attributes contain a doc comment
+fn documented(attrs: &[syn::Attribute]) -> bool {
+    attrs
+        .iter()
+        .any(|attr| attr.path().is_ident("doc") && matches!(attr.meta, syn::Meta::NameValue(_)))
+}
+
+/// Keep only the public fields, without attributes, returning whether any
+/// private field was dropped
+fn public_fields(fields: &mut syn::Fields) -> bool {
+    let mut private = false;
+    let mut keep = |field: &syn::Field| {
+        let public = matches!(field.vis, syn::Visibility::Public(_));
+        private |= !public;
+        public.then(|| {
+            let mut field = field.clone();
+            field.attrs.clear();
+            field
+        })
+    };
+    match fields {
+        syn::Fields::Named(named) => {
+            named.named = named.named.iter().filter_map(&mut keep).collect();
+        }
+        syn::Fields::Unnamed(unnamed) => {
+            unnamed.unnamed = unnamed.unnamed.iter().filter_map(&mut keep).collect();
+        }
+        syn::Fields::Unit => {}
+    }
+    private
+}
+
+/// A declaration marked as having private fields, which can then be added
+/// or removed without breaking anyone
+fn with_private_fields(signature: String, private: bool) -> String {
+    if private {
+        format!("{} /* private fields */", signature)
+    } else {
+        signature
+    }
+}
+
+/// Name and target of each item a `pub use` re-exports
+fn use_leaves(tree: &syn::UseTree, prefix: String, leaves: &mut Vec<(String, String)>) {
+    let join = |name: &str| {
+        if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}::{}", prefix, name)
+        }
+    };
+    match tree {
+        syn::UseTree::Path(path) => use_leaves(&path.tree, join(&path.ident.to_string()), leaves),
+        syn::UseTree::Name(name) => {
+            leaves.push((name.ident.to_string(), join(&name.ident.to_string())))
+        }
+        syn::UseTree::Rename(rename) => leaves.push((
+            rename.rename.to_string(),
+            format!("{} as {}", join(&rename.ident.to_string()), rename.rename),
+        )),
+        syn::UseTree::Glob(_) => leaves.push((join("*"), join("*"))),
+        syn::UseTree::Group(group) => {
+            for tree in &group.items {
+                use_leaves(tree, prefix.clone(), leaves);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write(dir: &Path, file: &str, contents: &str) {
+        let path = dir.join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn test_scan_lists_public_items_and_coverage() {
+        let temp_dir = TempDir::new().unwrap();
+        write(
+            temp_dir.path(),
+            "src/lib.rs",
+            "//! Crate\n\npub mod cache;\npub mod gc;\nmod private;\npub use cache::Cache as Store;\n\n/// Documented\npub fn open() {}\n\n#[doc(hidden)]\npub fn internal() {}\n",
+        );
+        write(
+            temp_dir.path(),
+            "src/cache.rs",
+            "pub struct Cache {\n    pub dir: String,\n    len: usize,\n}\n\nimpl Cache {\n    /// Get\n    pub fn get(&self) {}\n    fn helper(&self) {}\n}\n",
+        );
+        write(
+            temp_dir.path(),
+            "src/gc.rs",
+            "//! Garbage collection\n\n/// Collect\npub fn collect() {}\n",
+        );
+        write(temp_dir.path(), "src/private.rs", "pub fn hidden() {}\n");
+
+        let surface = scan(temp_dir.path()).unwrap();
+        let paths: Vec<_> = surface.items.iter().map(|i| i.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "crate::cache",
+                "crate::cache::Cache",
+                "crate::cache::Cache::get",
+                "crate::gc",
+                "crate::gc::collect",
+                "crate::Store",
+                "crate::open",
+            ]
+        );
+        assert_eq!(
+            surface.items[1].signature,
+            "pub struct Cache { pub dir : String } /* private fields */"
+        );
+        assert_eq!(surface.coverage(), 400.0 / 6.0);
+        let undocumented: Vec<_> = surface.undocumented().map(|i| i.path.as_str()).collect();
+        assert_eq!(undocumented, vec!["crate::cache", "crate::cache::Cache"]);
+    }
+
+    #[test]
+    fn test_from_rustdoc_ignores_ids_and_docs() {
+        let rustdoc = |field_id: u64, docs: Option<&str>, field_type: &str| {
+            serde_json::json!({
+                "root": 0,
+                "index": {
+                    "0": {"name": "shared", "visibility": "public", "inner": {"module": {"items": [field_id + 1, 9]}}},
+                    (field_id + 1).to_string(): {"name": "Point", "visibility": "public", "docs": docs,
+                        "inner": {"struct": {"kind": {"plain": {"fields": [field_id], "has_stripped_fields": false}}, "impls": [field_id + 2]}}},
+                    field_id.to_string(): {"name": "x", "visibility": "public", "inner": {"struct_field": {"primitive": field_type}}},
+                    (field_id + 2).to_string(): {"name": null, "visibility": "default",
+                        "inner": {"impl": {"trait": null, "items": [field_id + 3]}}},
+                    (field_id + 3).to_string(): {"name": "norm", "visibility": "public",
+                        "inner": {"function": {"sig": {"inputs": [], "output": {"resolved_path": {"path": "Point", "id": field_id + 1}}}, "has_body": true}}},
+                    "9": {"name": "helper", "visibility": "crate", "inner": {"function": {}}},
+                },
+            })
+        };
+
+        let previous = from_rustdoc(&rustdoc(10, None, "u8")).unwrap();
+        let paths: Vec<_> = previous.items.iter().map(|i| i.path.as_str()).collect();
+        assert_eq!(paths, vec!["crate::Point", "crate::Point::norm"]);
+        assert!(!previous.items[0].documented);
+
+        let redocumented = from_rustdoc(&rustdoc(20, Some("A point"), "u8")).unwrap();
+        assert!(redocumented.diff(&previous).changes.is_empty());
+        assert_eq!(redocumented.coverage(), 50.0);
+
+        let retyped = from_rustdoc(&rustdoc(20, None, "u16")).unwrap();
+        let changed: Vec<_> = retyped
+            .diff(&previous)
+            .breaking()
+            .map(|c| c.path().to_string())
+            .collect();
+        assert_eq!(changed, vec!["crate::Point"]);
+    }
+
+    #[test]
+    fn test_diff_flags_breaking_changes_only() {
+        let temp_dir = TempDir::new().unwrap();
+        write(
+            temp_dir.path(),
+            "src/lib.rs",
+            "/// Old docs\npub fn open(path: &str) {}\npub fn close() {}\npub enum Mode { Read }\n",
+        );
+        let previous = scan(temp_dir.path()).unwrap();
+
+        write(
+            temp_dir.path(),
+            "src/lib.rs",
+            "/// New docs\npub fn open(path: &str) { todo!() }\npub fn close() {}\npub enum Mode { Read }\npub fn flush() {}\n",
+        );
+        let diff = scan(temp_dir.path()).unwrap().diff(&previous);
+        assert!(!diff.is_breaking());
+        assert!(diff.has_additions());
+
+        write(
+            temp_dir.path(),
+            "src/lib.rs",
+            "pub fn open(path: &Path) {}\npub fn close() {}\npub enum Mode { Read, Write }\n",
+        );
+        let diff = scan(temp_dir.path()).unwrap().diff(&previous);
+        let breaking: Vec<_> = diff.breaking().map(|c| c.path()).collect();
+        assert_eq!(breaking, vec!["crate::Mode", "crate::open"]);
+        assert_eq!(
+            diff.changes[1].to_string(),
+            "changed function crate::open: `fn open (path : & str)` → `fn open (path : & Path)`"
+        );
+    }
+}
@@ -0,0 +1,258 @@
+//! App metadata module
+//!
+//! This module generates the launcher metadata Ledokoz OS expects from a
+//! single source icon configured in `[appmeta]`: the icon in every required
+//! size, a launcher (desktop) entry and a splash screen. Generated files live
+//! in the project's `meta/` directory and are bundled into the .mox under the
+//! same path.
+
+use crate::config::ProjectConfig;
+use crate::error::ForgeKitError;
+use image::imageops::FilterType;
+use image::{Rgba, RgbaImage};
+use std::path::{Path, PathBuf};
+
+/// Directory holding generated metadata, in the project and in the .mox
+pub const APPMETA_DIR: &str = "meta";
+
+/// Icon sizes required by Ledokoz OS, in pixels
+pub const ICON_SIZES: &[u32] = &[32, 48, 64, 128, 256, 512];
+
+/// File name of the launcher entry
+pub const LAUNCHER_ENTRY: &str = "launcher.desktop";
+
+/// File name of the splash screen
+pub const SPLASH: &str = "splash.png";
+
+/// Splash screen size (portrait)
+const SPLASH_SIZE: (u32, u32) = (1080, 1920);
+
+/// Size of the icon drawn on the splash screen
+const SPLASH_ICON_SIZE: u32 = 256;
+
+/// Generates and checks launcher metadata
+pub struct AppMeta;
+
+impl AppMeta {
+    /// Generate icons, launcher entry and splash screen
+    ///
+    /// Returns the generated files.
+    pub fn generate(
+        project_path: &Path,
+        config: &ProjectConfig,
+    ) -> Result<Vec<PathBuf>, ForgeKitError> {
+        let icon_path =
+            config.appmeta.icon.as_ref().ok_or_else(|| {
+                ForgeKitError::InvalidConfig("[appmeta] icon is not set".to_string())
+            })?;
+        let icon = image::open(project_path.join(icon_path))
+            .map_err(|e| ForgeKitError::InvalidConfig(format!("Cannot read icon: {}", e)))?
+            .into_rgba8();
+
+        let largest = ICON_SIZES.iter().copied().max().unwrap_or(0);
+        if icon.width() != icon.height() || icon.width() < largest {
+            return Err(ForgeKitError::InvalidConfig(format!(
+                "Icon must be square and at least {0}x{0}, got {1}x{2}",
+                largest,
+                icon.width(),
+                icon.height()
+            )));
+        }
+
+        let meta_dir = project_path.join(APPMETA_DIR);
+        std::fs::create_dir_all(meta_dir.join("icons"))?;
+        let mut files = Vec::new();
+
+        for size in ICON_SIZES {
+            let path = icon_file(project_path, *size);
+            image::imageops::resize(&icon, *size, *size, FilterType::Lanczos3)
+                .save(&path)
+                .map_err(|e| ForgeKitError::InvalidConfig(e.to_string()))?;
+            files.push(path);
+        }
+
+        let background = parse_color(config.appmeta.splash_background.as_deref())?;
+        let mut splash = RgbaImage::from_pixel(SPLASH_SIZE.0, SPLASH_SIZE.1, background);
+        let splash_icon = image::imageops::resize(
+            &icon,
+            SPLASH_ICON_SIZE,
+            SPLASH_ICON_SIZE,
+            FilterType::Lanczos3,
+        );
+        image::imageops::overlay(
+            &mut splash,
+            &splash_icon,
+            i64::from((SPLASH_SIZE.0 - SPLASH_ICON_SIZE) / 2),
+            i64::from((SPLASH_SIZE.1 - SPLASH_ICON_SIZE) / 2),
+        );
+        let splash_path = meta_dir.join(SPLASH);
+        splash
+            .save(&splash_path)
+            .map_err(|e| ForgeKitError::InvalidConfig(e.to_string()))?;
+        files.push(splash_path);
+
+        let entry_path = meta_dir.join(LAUNCHER_ENTRY);
+        std::fs::write(&entry_path, launcher_entry(config))?;
+        files.push(entry_path);
+
+        Ok(files)
+    }
+
+    /// Problems with the generated metadata of a project
+    ///
+    /// Returns nothing when `[appmeta]` has no icon configured.
+    pub fn validate(project_path: &Path, config: &ProjectConfig) -> Vec<String> {
+        let Some(icon) = &config.appmeta.icon else {
+            return Vec::new();
+        };
+        let source = project_path.join(icon);
+        if !source.exists() {
+            return vec![format!("App icon '{}' not found", icon)];
+        }
+
+        let meta_dir = project_path.join(APPMETA_DIR);
+        let mut expected: Vec<PathBuf> = ICON_SIZES
+            .iter()
+            .map(|size| icon_file(project_path, *size))
+            .collect();
+        expected.push(meta_dir.join(SPLASH));
+        expected.push(meta_dir.join(LAUNCHER_ENTRY));
+
+        let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        let source_modified = modified(&source);
+        let mut problems = Vec::new();
+        for path in expected {
+            let name = path.strip_prefix(project_path).unwrap_or(&path).display();
+            match modified(&path) {
+                None => problems.push(format!(
+                    "Missing app metadata {}; run `forgekit appmeta`",
+                    name
+                )),
+                Some(generated) if source_modified.is_some_and(|s| s > generated) => {
+                    problems.push(format!(
+                        "App metadata {} is older than the icon; run `forgekit appmeta`",
+                        name
+                    ))
+                }
+                Some(_) => {}
+            }
+        }
+        problems
+    }
+}
+
+/// Path of the generated icon of a given size
+fn icon_file(project_path: &Path, size: u32) -> PathBuf {
+    project_path
+        .join(APPMETA_DIR)
+        .join("icons")
+        .join(format!("{0}x{0}.png", size))
+}
+
+/// Launcher entry in desktop-entry format
+fn launcher_entry(config: &ProjectConfig) -> String {
+    let name = config
+        .appmeta
+        .display_name
+        .as_deref()
+        .unwrap_or(&config.name);
+    let mut entry = format!(
+        "[Desktop Entry]\nType=Application\nName={}\nExec={}\nIcon={}/icons/512x512.png\nX-Ledokoz-Version={}\n",
+        name, config.name, APPMETA_DIR, config.version
+    );
+    if let Some(description) = &config.description {
+        entry.push_str(&format!("Comment={}\n", description));
+    }
+    if !config.appmeta.categories.is_empty() {
+        entry.push_str(&format!(
+            "Categories={};\n",
+            config.appmeta.categories.join(";")
+        ));
+    }
+    entry.push_str(&format!("X-Ledokoz-Splash={}/{}\n", APPMETA_DIR, SPLASH));
+    entry
+}
+
+/// Parse a `#rrggbb` color, defaulting to white
+fn parse_color(color: Option<&str>) -> Result<Rgba<u8>, ForgeKitError> {
+    let Some(color) = color else {
+        return Ok(Rgba([255, 255, 255, 255]));
+    };
+    let invalid = || {
+        ForgeKitError::InvalidConfig(format!(
+            "splash_background '{}' is not a #rrggbb color",
+            color
+        ))
+    };
+
+    let hex = color
+        .strip_prefix('#')
+        .filter(|h| h.len() == 6 && h.is_ascii())
+        .ok_or_else(invalid)?;
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+    Ok(Rgba([channel(0)?, channel(2)?, channel(4)?, 255]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppMetaConfig;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_generate_and_validate() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path();
+        RgbaImage::from_pixel(512, 512, Rgba([200, 0, 0, 255]))
+            .save(project.join("icon.png"))
+            .unwrap();
+
+        let config = ProjectConfig {
+            name: "notes".to_string(),
+            appmeta: AppMetaConfig {
+                icon: Some("icon.png".to_string()),
+                display_name: Some("Notes".to_string()),
+                categories: vec!["Office".to_string()],
+                splash_background: Some("#102030".to_string()),
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            AppMeta::validate(project, &config).len(),
+            ICON_SIZES.len() + 2
+        );
+
+        let files = AppMeta::generate(project, &config).unwrap();
+        assert_eq!(files.len(), ICON_SIZES.len() + 2);
+        assert!(AppMeta::validate(project, &config).is_empty());
+
+        let icon = image::open(icon_file(project, 48)).unwrap();
+        assert_eq!((icon.width(), icon.height()), (48, 48));
+        let splash = image::open(project.join(APPMETA_DIR).join(SPLASH))
+            .unwrap()
+            .into_rgba8();
+        assert_eq!(splash.get_pixel(0, 0), &Rgba([0x10, 0x20, 0x30, 255]));
+
+        let entry =
+            std::fs::read_to_string(project.join(APPMETA_DIR).join(LAUNCHER_ENTRY)).unwrap();
+        assert!(entry.contains("Name=Notes\n"));
+        assert!(entry.contains("Categories=Office;\n"));
+    }
+
+    #[test]
+    fn test_generate_rejects_small_icon() {
+        let temp_dir = TempDir::new().unwrap();
+        RgbaImage::new(64, 64)
+            .save(temp_dir.path().join("icon.png"))
+            .unwrap();
+        let config = ProjectConfig {
+            appmeta: AppMetaConfig {
+                icon: Some("icon.png".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(AppMeta::generate(temp_dir.path(), &config).is_err());
+        assert!(parse_color(Some("red")).is_err());
+    }
+}
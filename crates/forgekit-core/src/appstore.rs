@@ -0,0 +1,402 @@
+//! App store listing module
+//!
+//! This module builds the listing bundle the Ledokoz store expects next to a
+//! .mox: `listing.json` with the category, age rating, localized texts and
+//! privacy declarations, and the screenshots it references. Localized texts
+//! are read from the `appstore.title`, `appstore.short_description` and
+//! `appstore.description` keys of the project's locale files, and privacy
+//! declarations from the permissions table and the purposes in `[appstore]`.
+
+use crate::config::ProjectConfig;
+use crate::error::ForgeKitError;
+use crate::i18n;
+use crate::packager;
+use crate::permissions::{self, Permission};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zip::{write::FileOptions, ZipWriter};
+
+/// Version of the listing schema the store accepts
+pub const LISTING_SCHEMA: u32 = 1;
+
+/// Name of the listing inside the bundle
+pub const LISTING_FILE: &str = "listing.json";
+
+/// Directory holding screenshots inside the bundle
+pub const SCREENSHOTS_DIR: &str = "screenshots";
+
+/// Categories of the store
+pub const CATEGORIES: &[&str] = &[
+    "business",
+    "developer-tools",
+    "education",
+    "entertainment",
+    "finance",
+    "games",
+    "graphics",
+    "health",
+    "lifestyle",
+    "music",
+    "news",
+    "productivity",
+    "social",
+    "utilities",
+];
+
+/// Age ratings of the store
+pub const AGE_RATINGS: &[&str] = &["everyone", "7+", "12+", "16+", "18+"];
+
+/// Locale used when `[appstore]` sets none
+const DEFAULT_LOCALE: &str = "en";
+
+/// Longest short description the store shows, in characters
+const MAX_SHORT_DESCRIPTION: usize = 80;
+
+/// Longest description the store shows, in characters
+const MAX_DESCRIPTION: usize = 4000;
+
+/// Number of screenshots a listing must have
+const SCREENSHOT_COUNT: std::ops::RangeInclusive<usize> = 2..=8;
+
+/// Smallest side of a screenshot, in pixels
+const MIN_SCREENSHOT_SIDE: u32 = 320;
+
+/// Texts of a listing in one locale
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LocalizedText {
+    /// Title shown in the store
+    pub title: Option<String>,
+    /// One-line summary shown in search results
+    pub short_description: Option<String>,
+    /// Full description
+    pub description: Option<String>,
+}
+
+/// A screenshot of a listing
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Screenshot {
+    /// Path inside the bundle
+    pub path: String,
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// SHA-256 checksum of the file
+    pub sha256: String,
+}
+
+/// Why the app requests a permission
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrivacyDeclaration {
+    /// Requested permission
+    pub permission: Permission,
+    /// Purpose given in `[appstore.privacy]`
+    pub purpose: Option<String>,
+}
+
+/// Store listing of an app, serialized as `listing.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Listing {
+    /// Listing schema version
+    pub schema: u32,
+    /// App name
+    pub name: String,
+    /// App version
+    pub version: String,
+    /// Store category
+    pub category: Option<String>,
+    /// Age rating
+    pub age_rating: Option<String>,
+    /// Locale the store falls back to
+    pub default_locale: String,
+    /// Texts, keyed by locale
+    pub descriptions: BTreeMap<String, LocalizedText>,
+    /// Screenshots, in display order
+    pub screenshots: Vec<Screenshot>,
+    /// Declarations for every requested permission
+    pub privacy: Vec<PrivacyDeclaration>,
+    /// URL of the privacy policy
+    pub privacy_policy: Option<String>,
+    /// Notes of this release
+    pub release_notes: Option<String>,
+}
+
+/// Builds and checks store listings
+pub struct AppStore;
+
+impl AppStore {
+    /// Listing of a project as configured
+    ///
+    /// Screenshots that cannot be read are left out; `validate` reports them.
+    pub fn listing(project_path: &Path, config: &ProjectConfig) -> Result<Listing, ForgeKitError> {
+        let appstore = &config.appstore;
+        let default_locale = appstore
+            .default_locale
+            .clone()
+            .unwrap_or_else(|| DEFAULT_LOCALE.to_string());
+
+        let mut descriptions = BTreeMap::new();
+        for (locale, files) in i18n::discover_locales(project_path)? {
+            let strings = i18n::load_strings(&files)?;
+            let text = LocalizedText {
+                title: strings.get("appstore.title").cloned(),
+                short_description: strings.get("appstore.short_description").cloned(),
+                description: strings.get("appstore.description").cloned(),
+            };
+            if text != LocalizedText::default() {
+                descriptions.insert(locale, text);
+            }
+        }
+        let fallback = descriptions.entry(default_locale.clone()).or_default();
+        if fallback.title.is_none() {
+            fallback.title = Some(config.name.clone());
+        }
+        if fallback.description.is_none() {
+            fallback.description = config.description.clone();
+        }
+
+        let mut screenshots = Vec::new();
+        for (i, path) in appstore.screenshots.iter().enumerate() {
+            let source = project_path.join(path);
+            let Ok((width, height)) = image::image_dimensions(&source) else {
+                continue;
+            };
+            screenshots.push(Screenshot {
+                path: format!("{}/{}", SCREENSHOTS_DIR, screenshot_name(i, path)),
+                width,
+                height,
+                sha256: format!("{:x}", Sha256::digest(std::fs::read(&source)?)),
+            });
+        }
+
+        let privacy = permissions::requested(&config.permissions)
+            .into_iter()
+            .map(|permission| PrivacyDeclaration {
+                purpose: appstore.privacy.get(&permission.to_string()).cloned(),
+                permission,
+            })
+            .collect();
+
+        Ok(Listing {
+            schema: LISTING_SCHEMA,
+            name: config.name.clone(),
+            version: config.version.clone(),
+            category: appstore.category.clone(),
+            age_rating: appstore.age_rating.clone(),
+            default_locale,
+            descriptions,
+            screenshots,
+            privacy,
+            privacy_policy: appstore.privacy_policy.clone(),
+            release_notes: config.release_notes.clone(),
+        })
+    }
+
+    /// Problems that would make the store reject the listing of a project
+    pub fn validate(project_path: &Path, config: &ProjectConfig) -> Vec<String> {
+        let mut problems = Vec::new();
+        let appstore = &config.appstore;
+
+        match appstore.category.as_deref() {
+            None => problems.push("[appstore] category is not set".to_string()),
+            Some(category) if !CATEGORIES.contains(&category) => problems.push(format!(
+                "Unknown store category '{}', expected one of: {}",
+                category,
+                CATEGORIES.join(", ")
+            )),
+            Some(_) => {}
+        }
+        match appstore.age_rating.as_deref() {
+            None => problems.push("[appstore] age_rating is not set".to_string()),
+            Some(rating) if !AGE_RATINGS.contains(&rating) => problems.push(format!(
+                "Unknown age rating '{}', expected one of: {}",
+                rating,
+                AGE_RATINGS.join(", ")
+            )),
+            Some(_) => {}
+        }
+
+        if !SCREENSHOT_COUNT.contains(&appstore.screenshots.len()) {
+            problems.push(format!(
+                "The store needs {} to {} screenshots, got {}",
+                SCREENSHOT_COUNT.start(),
+                SCREENSHOT_COUNT.end(),
+                appstore.screenshots.len()
+            ));
+        }
+        for path in &appstore.screenshots {
+            if !path.to_ascii_lowercase().ends_with(".png") {
+                problems.push(format!("Screenshot '{}' is not a PNG", path));
+                continue;
+            }
+            match image::image_dimensions(project_path.join(path)) {
+                Err(e) => problems.push(format!("Cannot read screenshot '{}': {}", path, e)),
+                Ok((width, height)) if width.min(height) < MIN_SCREENSHOT_SIDE => {
+                    problems.push(format!(
+                        "Screenshot '{}' is {}x{}, sides must be at least {} pixels",
+                        path, width, height, MIN_SCREENSHOT_SIDE
+                    ))
+                }
+                Ok(_) => {}
+            }
+        }
+
+        for permission in permissions::requested(&config.permissions) {
+            if !appstore.privacy.contains_key(&permission.to_string()) {
+                problems.push(format!(
+                    "Permission '{0}' is requested but [appstore.privacy] gives no purpose for it",
+                    permission
+                ));
+            }
+        }
+
+        let listing = match Self::listing(project_path, config) {
+            Ok(listing) => listing,
+            Err(e) => {
+                problems.push(format!("Cannot read localized store texts: {}", e));
+                return problems;
+            }
+        };
+        if let Some(text) = listing.descriptions.get(&listing.default_locale) {
+            if text.description.is_none() {
+                problems.push(format!(
+                    "No description for the default locale '{}'",
+                    listing.default_locale
+                ));
+            }
+        }
+        for (locale, text) in &listing.descriptions {
+            let too_long = |field: &Option<String>, max: usize| {
+                field
+                    .as_ref()
+                    .is_some_and(|text| text.chars().count() > max)
+            };
+            if too_long(&text.short_description, MAX_SHORT_DESCRIPTION) {
+                problems.push(format!(
+                    "Short description for '{}' is longer than {} characters",
+                    locale, MAX_SHORT_DESCRIPTION
+                ));
+            }
+            if too_long(&text.description, MAX_DESCRIPTION) {
+                problems.push(format!(
+                    "Description for '{}' is longer than {} characters",
+                    locale, MAX_DESCRIPTION
+                ));
+            }
+        }
+        problems
+    }
+
+    /// Write the listing bundle next to the project's .mox
+    ///
+    /// Fails with the problems found by `validate` when there are any.
+    pub fn bundle(project_path: &Path, config: &ProjectConfig) -> Result<PathBuf, ForgeKitError> {
+        let problems = Self::validate(project_path, config);
+        if !problems.is_empty() {
+            return Err(ForgeKitError::PackagingFailed(format!(
+                "Store listing is invalid:\n  - {}",
+                problems.join("\n  - ")
+            )));
+        }
+
+        let listing = Self::listing(project_path, config)?;
+        let mox_path = packager::output_path(project_path, config);
+        let output_dir = mox_path.parent().unwrap_or(project_path);
+        std::fs::create_dir_all(output_dir)?;
+        let bundle_path = output_dir.join(format!("{}-appstore.zip", config.name));
+
+        let mut zip = ZipWriter::new(std::fs::File::create(&bundle_path)?);
+        let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        zip.start_file(LISTING_FILE, options)?;
+        zip.write_all(&serde_json::to_vec_pretty(&listing)?)?;
+        for (i, path) in config.appstore.screenshots.iter().enumerate() {
+            zip.start_file(
+                format!("{}/{}", SCREENSHOTS_DIR, screenshot_name(i, path)),
+                options,
+            )?;
+            zip.write_all(&std::fs::read(project_path.join(path))?)?;
+        }
+        zip.finish()?;
+
+        tracing::info!("Store listing bundle created at {:?}", bundle_path);
+        Ok(bundle_path)
+    }
+}
+
+/// Name of a screenshot in the bundle, numbered to keep the display order
+fn screenshot_name(index: usize, path: &str) -> String {
+    let file_name = Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    format!("{:02}-{}", index + 1, file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppStoreConfig;
+    use image::{Rgba, RgbaImage};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_validate_and_bundle() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path();
+        for name in ["home.png", "edit.png"] {
+            RgbaImage::from_pixel(540, 960, Rgba([0, 0, 0, 255]))
+                .save(project.join(name))
+                .unwrap();
+        }
+        std::fs::create_dir_all(project.join("locales")).unwrap();
+        std::fs::write(
+            project.join("locales").join("en.toml"),
+            "[appstore]\ntitle = \"Notes\"\nshort_description = \"Quick notes\"\ndescription = \"Take notes.\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            project.join("locales").join("fr.json"),
+            r#"{"appstore": {"title": "Notes", "description": "Prenez des notes."}}"#,
+        )
+        .unwrap();
+
+        let mut config = ProjectConfig {
+            name: "notes".to_string(),
+            appstore: AppStoreConfig {
+                category: Some("office".to_string()),
+                age_rating: Some("everyone".to_string()),
+                screenshots: vec!["home.png".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        config.permissions.network = true;
+        let problems = AppStore::validate(project, &config);
+        assert_eq!(problems.len(), 3, "{:?}", problems);
+        assert!(AppStore::bundle(project, &config).is_err());
+
+        config.appstore.category = Some("productivity".to_string());
+        config.appstore.screenshots.push("edit.png".to_string());
+        config
+            .appstore
+            .privacy
+            .insert("network".to_string(), "Sync notes".to_string());
+        assert!(AppStore::validate(project, &config).is_empty());
+
+        let bundle = AppStore::bundle(project, &config).unwrap();
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(bundle).unwrap()).unwrap();
+        assert!(archive.by_name("screenshots/02-edit.png").is_ok());
+        let listing: Listing =
+            serde_json::from_reader(archive.by_name(LISTING_FILE).unwrap()).unwrap();
+        assert_eq!(listing.descriptions.len(), 2);
+        assert_eq!(
+            listing.descriptions["fr"].description.as_deref(),
+            Some("Prenez des notes.")
+        );
+        assert_eq!(listing.screenshots[0].width, 540);
+        assert_eq!(listing.privacy[0].permission, Permission::Network);
+    }
+}
@@ -0,0 +1,315 @@
+//! Artifact history module
+//!
+//! This module keeps the last packages of a project under
+//! `.forgekit/artifacts/<profile>/`, so an earlier .mox can be inspected,
+//! compared or restored after newer builds replaced it. The profile is the
+//! environment the package was built for. Each profile directory has an
+//! `index.toml` listing its artifacts with the git commit, creation time,
+//! size and SHA-256 of the package; recording a new one removes the oldest
+//! beyond `[build.artifacts] keep`.
+
+use crate::atomic;
+use crate::error::ForgeKitError;
+use crate::store::hash_file;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// Profile of packages built without an environment
+pub const DEFAULT_PROFILE: &str = "default";
+
+const INDEX_FILE: &str = "index.toml";
+
+/// Directory holding the artifact history of a project
+pub fn artifacts_dir(project_path: &Path) -> PathBuf {
+    project_path.join(".forgekit").join("artifacts")
+}
+
+/// A package kept in the history
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Artifact {
+    /// Unique id, `<version>-<timestamp>`
+    pub id: String,
+    /// Project version the package was built from
+    pub version: String,
+    /// Profile the package belongs to
+    pub profile: String,
+    /// File name inside the profile directory
+    pub file: String,
+    /// Commit checked out when the package was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_sha: Option<String>,
+    /// When the package was recorded (RFC 3339)
+    pub created_at: String,
+    /// Package size in bytes
+    pub size: u64,
+    /// SHA-256 of the package
+    pub sha256: String,
+}
+
+impl Artifact {
+    /// Location of the stored package
+    pub fn path(&self, project_path: &Path) -> PathBuf {
+        artifacts_dir(project_path)
+            .join(&self.profile)
+            .join(&self.file)
+    }
+}
+
+/// How two artifacts differ
+#[derive(Debug, Clone, Serialize)]
+pub struct ArtifactDiff {
+    /// Older side of the comparison
+    pub from: Artifact,
+    /// Newer side of the comparison
+    pub to: Artifact,
+    /// Size change in bytes, negative when the package shrank
+    pub size_delta: i64,
+    /// Whether both packages have the same contents
+    pub identical: bool,
+}
+
+impl ArtifactDiff {
+    /// Compare two artifacts
+    pub fn new(from: Artifact, to: Artifact) -> Self {
+        Self {
+            size_delta: to.size as i64 - from.size as i64,
+            identical: from.sha256 == to.sha256,
+            from,
+            to,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Index {
+    #[serde(default, rename = "artifact")]
+    artifacts: Vec<Artifact>,
+}
+
+impl Index {
+    fn path(project_path: &Path, profile: &str) -> PathBuf {
+        artifacts_dir(project_path).join(profile).join(INDEX_FILE)
+    }
+
+    fn load(project_path: &Path, profile: &str) -> Result<Self, ForgeKitError> {
+        let path = Self::path(project_path, profile);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(toml::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    fn save(&self, project_path: &Path, profile: &str) -> Result<(), ForgeKitError> {
+        atomic::write(
+            Self::path(project_path, profile),
+            toml::to_string_pretty(self)?,
+        )
+    }
+
+    /// Drop all but the newest `keep` artifacts, returning the dropped ones
+    fn prune(&mut self, project_path: &Path, keep: usize) -> Result<Vec<Artifact>, ForgeKitError> {
+        let excess = self.artifacts.len().saturating_sub(keep);
+        let removed: Vec<Artifact> = self.artifacts.drain(..excess).collect();
+        for artifact in &removed {
+            let path = artifact.path(project_path);
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// Copy a freshly created package into the history of `profile`
+///
+/// Returns `None` when `keep` is 0, i.e. the history is disabled.
+pub async fn record(
+    project_path: &Path,
+    package: &Path,
+    version: &str,
+    profile: &str,
+    keep: usize,
+) -> Result<Option<Artifact>, ForgeKitError> {
+    if keep == 0 {
+        return Ok(None);
+    }
+
+    validate_profile(profile)?;
+    let dir = artifacts_dir(project_path).join(profile);
+    std::fs::create_dir_all(&dir)?;
+    let now = chrono::Utc::now();
+    let id = format!("{}-{}", version, now.format("%Y%m%d%H%M%S%3f"));
+    let stem = package.file_stem().unwrap_or_default().to_string_lossy();
+    let file = match package.extension() {
+        Some(ext) => format!("{}-{}.{}", stem, id, ext.to_string_lossy()),
+        None => format!("{}-{}", stem, id),
+    };
+    std::fs::copy(package, dir.join(&file))?;
+
+    let artifact = Artifact {
+        id,
+        version: version.to_string(),
+        profile: profile.to_string(),
+        file,
+        git_sha: git_head(project_path).await,
+        created_at: now.to_rfc3339(),
+        size: std::fs::metadata(package)?.len(),
+        sha256: hash_file(package)?,
+    };
+    let mut index = Index::load(project_path, profile)?;
+    index.artifacts.push(artifact.clone());
+    index.prune(project_path, keep)?;
+    index.save(project_path, profile)?;
+    Ok(Some(artifact))
+}
+
+/// Recorded artifacts, oldest first, of one profile or of all of them
+pub fn list(project_path: &Path, profile: Option<&str>) -> Result<Vec<Artifact>, ForgeKitError> {
+    let mut artifacts = Vec::new();
+    for profile in profiles(project_path, profile)? {
+        artifacts.extend(Index::load(project_path, &profile)?.artifacts);
+    }
+    artifacts.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+    Ok(artifacts)
+}
+
+/// Find an artifact by id, or the newest one of a version
+pub fn find(
+    project_path: &Path,
+    profile: Option<&str>,
+    selector: &str,
+) -> Result<Artifact, ForgeKitError> {
+    let artifacts = list(project_path, profile)?;
+    artifacts
+        .iter()
+        .find(|a| a.id == selector)
+        .or_else(|| artifacts.iter().rev().find(|a| a.version == selector))
+        .cloned()
+        .ok_or_else(|| {
+            ForgeKitError::PackagingFailed(format!(
+                "No artifact matches '{}'; run `forgekit artifacts list`",
+                selector
+            ))
+        })
+}
+
+/// Remove all but the newest `keep` artifacts of each profile
+///
+/// Returns the removed artifacts.
+pub fn clean(
+    project_path: &Path,
+    profile: Option<&str>,
+    keep: usize,
+) -> Result<Vec<Artifact>, ForgeKitError> {
+    let mut removed = Vec::new();
+    for profile in profiles(project_path, profile)? {
+        let mut index = Index::load(project_path, &profile)?;
+        removed.extend(index.prune(project_path, keep)?);
+        index.save(project_path, &profile)?;
+    }
+    Ok(removed)
+}
+
+/// Profiles with a history, or just `profile` when given
+fn profiles(project_path: &Path, profile: Option<&str>) -> Result<Vec<String>, ForgeKitError> {
+    if let Some(profile) = profile {
+        validate_profile(profile)?;
+        return Ok(vec![profile.to_string()]);
+    }
+    let dir = artifacts_dir(project_path);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut profiles: Vec<String> = std::fs::read_dir(dir)?
+        .flatten()
+        .filter(|e| e.path().join(INDEX_FILE).exists())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .collect();
+    profiles.sort();
+    Ok(profiles)
+}
+
+/// Check that a profile, which names a directory, is a plain environment name
+fn validate_profile(profile: &str) -> Result<(), ForgeKitError> {
+    if profile.is_empty()
+        || !profile
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(ForgeKitError::InvalidConfig(format!(
+            "'{}' is not a valid environment name; use letters, digits, '-' and '_'",
+            profile
+        )));
+    }
+    Ok(())
+}
+
+async fn git_head(project_path: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .current_dir(project_path)
+        .output()
+        .await
+        .ok()?;
+    let sha = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !sha.is_empty()).then_some(sha)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_record_keeps_newest_and_finds_by_version() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let package = root.join("app.mox");
+
+        let mut recorded = Vec::new();
+        for (version, contents) in [("0.1.0", "a"), ("0.2.0", "bb"), ("0.3.0", "ccc")] {
+            std::fs::write(&package, contents).unwrap();
+            let artifact = record(root, &package, version, DEFAULT_PROFILE, 2)
+                .await
+                .unwrap()
+                .unwrap();
+            recorded.push(artifact);
+        }
+
+        let artifacts = list(root, None).unwrap();
+        assert_eq!(artifacts, recorded[1..]);
+        assert!(!recorded[0].path(root).exists());
+        assert_eq!(find(root, None, "0.2.0").unwrap(), recorded[1]);
+        assert!(find(root, None, "0.1.0").is_err());
+
+        let diff = ArtifactDiff::new(artifacts[0].clone(), artifacts[1].clone());
+        assert_eq!(diff.size_delta, 1);
+        assert!(!diff.identical);
+
+        assert_eq!(clean(root, None, 0).unwrap().len(), 2);
+        assert!(list(root, None).unwrap().is_empty());
+        assert!(record(root, &package, "0.4.0", DEFAULT_PROFILE, 0)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_profiles_must_be_plain_names() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let package = root.join("app.mox");
+        std::fs::write(&package, "a").unwrap();
+
+        for profile in ["../../outside", "prod/eu", ""] {
+            assert!(record(root, &package, "0.1.0", profile, 2).await.is_err());
+            assert!(list(root, Some(profile)).is_err());
+        }
+        assert!(!root.join("outside").exists());
+        assert!(record(root, &package, "0.1.0", "prod-eu_1", 2)
+            .await
+            .unwrap()
+            .is_some());
+    }
+}
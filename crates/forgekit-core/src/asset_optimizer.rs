@@ -79,7 +79,7 @@ impl AssetOptimizer {
         }
 
         let content = std::fs::read_to_string(path)?;
-        let minified = content.replace(" ", "").replace("\n", "");
+        let minified = content.replace(' ', "").replace(['\r', '\n'], "");
         std::fs::write(path, minified)?;
         Ok(path.to_path_buf())
     }
@@ -0,0 +1,147 @@
+//! Atomic file writes module
+//!
+//! This module writes files so that readers, and a process that crashes or
+//! is interrupted half-way, only ever see the old or the new contents. Data
+//! goes to a temporary file next to the target, is synced to disk and then
+//! renamed over the target.
+
+use crate::error::ForgeKitError;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Replace the contents of `path` atomically
+///
+/// The permissions of a file being replaced are kept; new files get the
+/// usual permissions for the process.
+pub fn write(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Result<(), ForgeKitError> {
+    write_with(path.as_ref(), contents.as_ref(), false)
+}
+
+/// Replace the contents of `path` atomically with a file only its owner
+/// can read
+///
+/// The file is created that way, so keys and secrets are never readable by
+/// others, not even for a moment.
+pub fn write_private(
+    path: impl AsRef<Path>,
+    contents: impl AsRef<[u8]>,
+) -> Result<(), ForgeKitError> {
+    write_with(path.as_ref(), contents.as_ref(), true)
+}
+
+/// Replace the contents of `to` atomically with a copy of the file `from`
+///
+/// The copy keeps the permissions of `from`.
+pub fn copy(from: impl AsRef<Path>, to: impl AsRef<Path>) -> Result<(), ForgeKitError> {
+    let (from, to) = (from.as_ref(), to.as_ref());
+    replace_with(to, |temp_path| {
+        fs::copy(from, temp_path)?;
+        File::open(temp_path)?.sync_all()
+    })
+}
+
+fn write_with(path: &Path, contents: &[u8], private: bool) -> Result<(), ForgeKitError> {
+    replace_with(path, |temp_path| {
+        write_temp(path, temp_path, contents, private)
+    })
+}
+
+/// Fill the temporary file of `path` with `fill` and rename it over `path`
+fn replace_with(
+    path: &Path,
+    fill: impl FnOnce(&Path) -> std::io::Result<()>,
+) -> Result<(), ForgeKitError> {
+    let temp_path = temp_path(path);
+    let result = fill(&temp_path).and_then(|()| fs::rename(&temp_path, path));
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e.into());
+    }
+
+    // Make the rename itself durable; not every platform can sync directories
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        if let Ok(dir) = File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
+
+fn write_temp(
+    path: &Path,
+    temp_path: &Path,
+    contents: &[u8],
+    private: bool,
+) -> std::io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::OpenOptionsExt;
+        // The mode only applies to a file being created
+        let _ = fs::remove_file(temp_path);
+        options.mode(0o600);
+    }
+    let mut file = options.open(temp_path)?;
+    if !private {
+        if let Ok(metadata) = fs::metadata(path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+    }
+    file.write_all(contents)?;
+    file.sync_all()
+}
+
+/// Hidden temporary file next to `path`, unique to this process
+pub(crate) fn temp_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{}.{}.tmp", name, std::process::id()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_write_replaces_without_leftovers() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("forgekit.toml");
+
+        write(&path, "name = \"old\"").unwrap();
+        write(&path, "name = \"new\"").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "name = \"new\"");
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+
+        let err = write(temp_dir.path().join("missing").join("file"), "x");
+        assert!(err.is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(".env");
+        fs::write(&path, "TOKEN=old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+
+        write(&path, "TOKEN=new").unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        let copy = temp_dir.path().join(".env.bak");
+        super::copy(&path, &copy).unwrap();
+        assert_eq!(fs::read_to_string(&copy).unwrap(), "TOKEN=new");
+        let mode = fs::metadata(&copy).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+
+        let key = temp_dir.path().join("signing.key");
+        write_private(&key, "key").unwrap();
+        let mode = fs::metadata(&key).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
@@ -2,11 +2,14 @@
 //!
 //! This module provides functionality for auditing dependencies for vulnerabilities.
 
+use crate::diagnostics::{self, Diagnostic};
 use crate::error::ForgeKitError;
+use serde::Serialize;
 use std::path::Path;
 
 /// Vulnerability severity
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Severity {
     Low,
     Medium,
@@ -15,7 +18,7 @@ pub enum Severity {
 }
 
 /// Vulnerability information
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Vulnerability {
     pub package: String,
     pub version: String,
@@ -24,7 +27,7 @@ pub struct Vulnerability {
 }
 
 /// Severity summary
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SeveritySummary {
     pub critical: usize,
     pub high: usize,
@@ -33,14 +36,52 @@ pub struct SeveritySummary {
 }
 
 /// Audit report
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct AuditReport {
     pub vulnerabilities: Vec<Vulnerability>,
     pub severity_summary: SeveritySummary,
 }
 
+impl AuditReport {
+    /// Convert the vulnerabilities into diagnostics pointing at the lockfile
+    pub fn diagnostics(&self, project_path: &Path) -> Vec<Diagnostic> {
+        let lockfile = project_path.join("Cargo.lock");
+        let file = if lockfile.exists() {
+            lockfile
+        } else {
+            project_path.join("Cargo.toml")
+        };
+
+        self.vulnerabilities
+            .iter()
+            .map(|vulnerability| {
+                let severity = match vulnerability.severity {
+                    Severity::Critical | Severity::High => diagnostics::Severity::Error,
+                    Severity::Medium | Severity::Low => diagnostics::Severity::Warning,
+                };
+                Diagnostic {
+                    code: Some(vulnerability.package.clone()),
+                    file: Some(file.clone()),
+                    fix: Some(format!(
+                        "Update {} (`forgekit outdated`)",
+                        vulnerability.package
+                    )),
+                    ..Diagnostic::new(
+                        "audit",
+                        severity,
+                        format!(
+                            "{} {}: {}",
+                            vulnerability.package, vulnerability.version, vulnerability.description
+                        ),
+                    )
+                }
+            })
+            .collect()
+    }
+}
+
 /// Update suggestion
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct UpdateSuggestion {
     pub package: String,
     pub current_version: String,
@@ -0,0 +1,335 @@
+//! Audit log module
+//!
+//! Commands that change state beyond the working tree, like publishing,
+//! installing, logging in or storing secrets, are recorded in an append-only
+//! audit trail: who ran which command with which arguments, when, and how it
+//! ended. Arguments are redacted before they are written. Entries are JSON
+//! lines in `.forgekit/audit.log` of the project, or in the ForgeKit config
+//! directory outside a project; `[audit_log] sink = "syslog"` sends them to
+//! the system log instead. `forgekit audit-log show` lists recorded entries.
+
+use crate::config::{AuditSink, GlobalConfig, ProjectConfig};
+use crate::error::ForgeKitError;
+use crate::redact::{Redactor, MASK};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Commands recorded in the audit log
+pub const AUDITED_COMMANDS: &[&str] = &[
+    "publish",
+    "install",
+    "uninstall",
+    "add",
+    "remove",
+    "update",
+    "rename",
+    "template update",
+    "env set",
+    "secret set",
+    "secret delete",
+    "login",
+    "logout",
+    "symbols upload",
+    "feed generate",
+    "feed keygen",
+    "plugin install",
+    "plugin update",
+    "plugin uninstall",
+    "upgrade",
+];
+
+/// Whether a command is recorded in the audit log
+pub fn is_audited(command: &str) -> bool {
+    AUDITED_COMMANDS.contains(&command)
+}
+
+/// One recorded command run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// When the command finished (RFC 3339)
+    pub timestamp: String,
+    /// User who ran the command
+    pub user: String,
+    /// Command name, e.g. `publish` or `secret set`
+    pub command: String,
+    /// Command-line arguments after `forgekit`, redacted
+    pub args: Vec<String>,
+    /// Process exit code
+    pub exit_code: i32,
+    /// Kind of error the command failed with, see [`ForgeKitError::code`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<String>,
+}
+
+impl AuditEntry {
+    /// Entry for a finished command run by the current user
+    ///
+    /// Values of sensitive options like `--token` and secret values known
+    /// to `redactor` are masked.
+    pub fn new(command: &str, args: &[String], exit_code: i32, redactor: &Redactor) -> Self {
+        Self {
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            user: current_user(),
+            command: command.to_string(),
+            args: redact_args(args, redactor),
+            exit_code,
+            error_code: None,
+        }
+    }
+
+    /// Whether the command succeeded
+    pub fn succeeded(&self) -> bool {
+        self.exit_code == 0
+    }
+
+    /// When the command finished
+    pub fn time(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&self.timestamp)
+            .ok()
+            .map(|time| time.with_timezone(&Utc))
+    }
+}
+
+/// Audit log of a project
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    sink: AuditSink,
+    path: PathBuf,
+}
+
+impl AuditLog {
+    /// Audit log of the project at `project_path`, or the user's outside a
+    /// project
+    pub fn for_project(project_path: &Path) -> Result<Self, ForgeKitError> {
+        let config_path = project_path.join("forgekit.toml");
+        if !config_path.exists() {
+            return Ok(Self::new(
+                AuditSink::File,
+                GlobalConfig::config_dir().join("audit.log"),
+            ));
+        }
+        let config = ProjectConfig::load(&config_path)?;
+        Ok(Self::new(
+            config.audit_log.sink,
+            project_path.join(".forgekit").join("audit.log"),
+        ))
+    }
+
+    /// Audit log writing to `sink`, with entries in `path` for the file sink
+    pub fn new(sink: AuditSink, path: PathBuf) -> Self {
+        Self { sink, path }
+    }
+
+    /// Where entries are written
+    pub fn sink(&self) -> AuditSink {
+        self.sink
+    }
+
+    /// File entries are appended to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append an entry
+    pub fn record(&self, entry: &AuditEntry) -> Result<(), ForgeKitError> {
+        let line = serde_json::to_string(entry)?;
+        match self.sink {
+            AuditSink::Off => Ok(()),
+            AuditSink::Syslog => syslog(&line),
+            AuditSink::File => {
+                if let Some(parent) = self.path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                let mut file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.path)?;
+                writeln!(file, "{}", line)?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Entries recorded in the file, oldest first, skipping unreadable lines
+    pub fn entries(&self) -> Result<Vec<AuditEntry>, ForgeKitError> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        Ok(std::fs::read_to_string(&self.path)?
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    /// Entries recorded after `since`, oldest first
+    pub fn entries_since(&self, since: DateTime<Utc>) -> Result<Vec<AuditEntry>, ForgeKitError> {
+        Ok(self
+            .entries()?
+            .into_iter()
+            .filter(|entry| entry.time().is_some_and(|time| time >= since))
+            .collect())
+    }
+}
+
+/// Parse an age like `30m`, `12h`, `7d` or `2w`
+pub fn parse_age(age: &str) -> Result<Duration, ForgeKitError> {
+    let invalid = || {
+        ForgeKitError::InvalidConfig(format!(
+            "Invalid age '{}' (expected a number followed by s, m, h, d or w, like 7d)",
+            age
+        ))
+    };
+    let age = age.trim();
+    let unit = age.chars().last().ok_or_else(invalid)?;
+    let amount: i64 = age[..age.len() - unit.len_utf8()]
+        .parse()
+        .map_err(|_| invalid())?;
+    match unit {
+        's' => Ok(Duration::seconds(amount)),
+        'm' => Ok(Duration::minutes(amount)),
+        'h' => Ok(Duration::hours(amount)),
+        'd' => Ok(Duration::days(amount)),
+        'w' => Ok(Duration::weeks(amount)),
+        _ => Err(invalid()),
+    }
+}
+
+/// Time an age like `7d` ago
+pub fn cutoff(age: &str) -> Result<DateTime<Utc>, ForgeKitError> {
+    Ok(Utc::now() - parse_age(age)?)
+}
+
+/// Arguments with the values of sensitive options and known secrets masked
+pub fn redact_args(args: &[String], redactor: &Redactor) -> Vec<String> {
+    let sensitive_option = |name: &str| redactor.is_sensitive(&name.replace('-', "_"));
+    let mut redacted = Vec::with_capacity(args.len());
+    let mut mask_next = false;
+    for arg in args {
+        if mask_next {
+            redacted.push(MASK.to_string());
+            mask_next = false;
+            continue;
+        }
+        match arg.strip_prefix("--") {
+            Some(option) => match option.split_once('=') {
+                Some((name, _)) if sensitive_option(name) => {
+                    redacted.push(format!("--{}={}", name, MASK));
+                }
+                Some(_) => redacted.push(redactor.redact(arg)),
+                None => {
+                    mask_next = sensitive_option(option);
+                    redacted.push(arg.clone());
+                }
+            },
+            None => redacted.push(redactor.redact(arg)),
+        }
+    }
+    redacted
+}
+
+/// Name of the user running ForgeKit
+pub fn current_user() -> String {
+    ["USER", "USERNAME", "LOGNAME"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|user| !user.is_empty()))
+        .unwrap_or_else(|| {
+            #[cfg(unix)]
+            {
+                // SAFETY: getuid(2) always succeeds and has no preconditions
+                format!("uid {}", unsafe { libc::getuid() })
+            }
+            #[cfg(not(unix))]
+            {
+                "unknown".to_string()
+            }
+        })
+}
+
+/// Send a line to the system log
+#[cfg(unix)]
+fn syslog(line: &str) -> Result<(), ForgeKitError> {
+    let message =
+        std::ffi::CString::new(line).map_err(|e| ForgeKitError::Io(std::io::Error::other(e)))?;
+    // SAFETY: the ident is a static string, which openlog(3) requires since
+    // it keeps the pointer, and the message goes through a "%s" format.
+    unsafe {
+        libc::openlog(c"forgekit".as_ptr(), libc::LOG_PID, libc::LOG_USER);
+        libc::syslog(libc::LOG_NOTICE, c"%s".as_ptr(), message.as_ptr());
+    }
+    Ok(())
+}
+
+/// There is no system log to write to outside unix
+#[cfg(not(unix))]
+fn syslog(_line: &str) -> Result<(), ForgeKitError> {
+    Err(ForgeKitError::InvalidConfig(
+        "[audit_log] sink = \"syslog\" is only supported on unix".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_entries_are_appended_and_filtered_by_age() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path();
+        ProjectConfig::default()
+            .save(project.join("forgekit.toml"))
+            .unwrap();
+        let log = AuditLog::for_project(project).unwrap();
+        assert_eq!(log.path(), project.join(".forgekit").join("audit.log"));
+
+        let redactor = Redactor::default_patterns();
+        let mut old = AuditEntry::new("logout", &["logout".to_string()], 0, &redactor);
+        old.timestamp = (Utc::now() - Duration::days(10)).to_rfc3339();
+        log.record(&old).unwrap();
+        let args = ["publish".to_string(), "--remote".to_string()];
+        let mut failed = AuditEntry::new("publish", &args, 1, &redactor);
+        failed.error_code = Some("registry".to_string());
+        log.record(&failed).unwrap();
+
+        assert_eq!(log.entries().unwrap(), vec![old, failed.clone()]);
+        let recent = log.entries_since(cutoff("7d").unwrap()).unwrap();
+        assert_eq!(recent, vec![failed]);
+        assert!(!recent[0].succeeded());
+        assert!(parse_age("7").is_err());
+        assert!(parse_age("7y").is_err());
+    }
+
+    #[test]
+    fn test_arguments_are_redacted() {
+        let mut redactor = Redactor::default_patterns();
+        redactor.add_secret("hunter2-password");
+        let args: Vec<String> = [
+            "login",
+            "--token",
+            "ghp_abc",
+            "--api-key=xyz",
+            "--url",
+            "https://registry.example",
+            "--note=hunter2-password",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+        assert_eq!(
+            redact_args(&args, &redactor),
+            vec![
+                "login",
+                "--token",
+                MASK,
+                &format!("--api-key={}", MASK),
+                "--url",
+                "https://registry.example",
+                &format!("--note={}", MASK),
+            ]
+        );
+        assert!(is_audited("secret set"));
+        assert!(!is_audited("build"));
+    }
+}
@@ -0,0 +1,290 @@
+//! Batch operations module
+//!
+//! This module runs build, test, validate or package over many project roots at
+//! once. Projects run concurrently up to a job limit and can share one cargo
+//! target directory, so common dependencies are compiled only once. The
+//! results are collected into a [`BatchReport`] with a summary table.
+
+use crate::builder::{self, BuildOptions};
+use crate::config::CompilerCacheConfig;
+use crate::error::ForgeKitError;
+use crate::packager;
+use crate::testing::TestRunner;
+use crate::validator::ProjectValidator;
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+/// Command run for every project of a batch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BatchCommand {
+    /// Build the project
+    Build,
+    /// Run the project tests
+    Test,
+    /// Validate the project structure and configuration
+    Validate,
+    /// Package the built project into a .mox file
+    ///
+    /// The binary is read from the project's own target directory, so
+    /// build without a shared target directory first.
+    Package,
+}
+
+impl FromStr for BatchCommand {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "build" => Ok(BatchCommand::Build),
+            "test" => Ok(BatchCommand::Test),
+            "validate" => Ok(BatchCommand::Validate),
+            "package" => Ok(BatchCommand::Package),
+            _ => Err(format!(
+                "unknown batch command '{}' (expected build, test, validate or package)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for BatchCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BatchCommand::Build => write!(f, "build"),
+            BatchCommand::Test => write!(f, "test"),
+            BatchCommand::Validate => write!(f, "validate"),
+            BatchCommand::Package => write!(f, "package"),
+        }
+    }
+}
+
+/// Options for a batch run
+#[derive(Debug, Clone)]
+pub struct BatchOptions {
+    /// Maximum number of projects processed at the same time
+    pub jobs: usize,
+    /// Cargo target directory shared by all projects
+    pub target_dir: Option<PathBuf>,
+    /// Compiler cache of the builds, none by default
+    pub compiler_cache: Option<CompilerCacheConfig>,
+}
+
+impl BatchOptions {
+    /// Create options using one job per available CPU and per-project target directories
+    pub fn new() -> Self {
+        Self {
+            jobs: std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            target_dir: None,
+            compiler_cache: None,
+        }
+    }
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Result of the batch command for one project
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectOutcome {
+    /// Project root
+    pub project: PathBuf,
+    /// Whether the command succeeded
+    pub success: bool,
+    /// Short summary, or the error message on failure
+    pub summary: String,
+    /// Time spent on the project
+    pub duration: Duration,
+}
+
+/// Aggregated results of a batch run
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchReport {
+    /// Command that ran
+    pub command: BatchCommand,
+    /// Outcomes in the order the projects were given
+    pub outcomes: Vec<ProjectOutcome>,
+}
+
+impl BatchReport {
+    /// Number of projects that succeeded
+    pub fn succeeded(&self) -> usize {
+        self.outcomes.iter().filter(|o| o.success).count()
+    }
+
+    /// Number of projects that failed
+    pub fn failed(&self) -> usize {
+        self.outcomes.len() - self.succeeded()
+    }
+
+    /// Whether every project succeeded
+    pub fn all_passed(&self) -> bool {
+        self.failed() == 0
+    }
+
+    /// Summary table with one row per project
+    pub fn table(&self) -> Vec<String> {
+        let names: Vec<String> = self
+            .outcomes
+            .iter()
+            .map(|o| o.project.display().to_string())
+            .collect();
+        let width = names.iter().map(String::len).max().unwrap_or(0).max(7);
+
+        let mut rows = vec![format!(
+            "{:<width$}  {:<6}  {:>8}  Summary",
+            "Project", "Status", "Time"
+        )];
+        for (name, outcome) in names.iter().zip(&self.outcomes) {
+            rows.push(format!(
+                "{:<width$}  {:<6}  {:>7.1}s  {}",
+                name,
+                if outcome.success { "ok" } else { "FAILED" },
+                outcome.duration.as_secs_f64(),
+                outcome.summary.lines().next().unwrap_or_default()
+            ));
+        }
+        rows
+    }
+}
+
+/// Runs a command over many projects
+pub struct Batch;
+
+impl Batch {
+    /// Run a command for every project, at most `options.jobs` at a time
+    pub async fn run(
+        projects: &[PathBuf],
+        command: BatchCommand,
+        options: &BatchOptions,
+    ) -> Result<BatchReport, ForgeKitError> {
+        let semaphore = Arc::new(Semaphore::new(options.jobs.max(1)));
+        let build_options = BuildOptions {
+            target_dir: options.target_dir.clone(),
+            compiler_cache: options.compiler_cache.clone(),
+            ..Default::default()
+        };
+
+        let mut tasks = JoinSet::new();
+        for (index, project) in projects.iter().enumerate() {
+            let semaphore = Arc::clone(&semaphore);
+            let project = project.clone();
+            let build_options = build_options.clone();
+
+            tasks.spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                let start = Instant::now();
+                let (success, summary) = match run_one(&project, command, &build_options).await {
+                    Ok(result) => result,
+                    Err(e) => (false, e.to_string()),
+                };
+
+                let outcome = ProjectOutcome {
+                    project,
+                    success,
+                    summary,
+                    duration: start.elapsed(),
+                };
+                (index, outcome)
+            });
+        }
+
+        let mut outcomes = Vec::with_capacity(projects.len());
+        while let Some(joined) = tasks.join_next().await {
+            outcomes.push(joined.map_err(|e| ForgeKitError::BuildFailed(e.to_string()))?);
+        }
+        outcomes.sort_by_key(|(index, _)| *index);
+
+        Ok(BatchReport {
+            command,
+            outcomes: outcomes.into_iter().map(|(_, outcome)| outcome).collect(),
+        })
+    }
+}
+
+async fn run_one(
+    project: &Path,
+    command: BatchCommand,
+    options: &BuildOptions,
+) -> Result<(bool, String), ForgeKitError> {
+    match command {
+        BatchCommand::Build => {
+            builder::build_with_options(project, options).await?;
+            Ok((true, "built".to_string()))
+        }
+        BatchCommand::Test => {
+            let report = TestRunner::run_tests_with_options(project, options).await?;
+            Ok((
+                report.all_passed(),
+                format!("{}/{} passed", report.passed, report.total),
+            ))
+        }
+        BatchCommand::Validate => {
+            let report = ProjectValidator::validate_project(project).await?;
+            let summary = match report.errors.first() {
+                Some(error) => error.clone(),
+                None => format!("{} warning(s)", report.warnings.len()),
+            };
+            Ok((report.is_valid, summary))
+        }
+        BatchCommand::Package => {
+            let package = packager::package(project).await?;
+            Ok((true, package.display().to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ProjectConfig;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_batch_command_parse() {
+        assert_eq!("test".parse::<BatchCommand>().unwrap(), BatchCommand::Test);
+        assert!("deploy".parse::<BatchCommand>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_batch_validate_keeps_order() {
+        let temp_dir = TempDir::new().unwrap();
+        let valid = temp_dir.path().join("valid");
+        std::fs::create_dir_all(valid.join("src")).unwrap();
+        ProjectConfig::default()
+            .save(valid.join("forgekit.toml"))
+            .unwrap();
+        let missing = temp_dir.path().join("missing");
+        std::fs::create_dir_all(&missing).unwrap();
+
+        let options = BatchOptions {
+            jobs: 2,
+            target_dir: None,
+            compiler_cache: None,
+        };
+        let projects = vec![missing.clone(), valid.clone()];
+        let report = Batch::run(&projects, BatchCommand::Validate, &options)
+            .await
+            .unwrap();
+
+        assert_eq!(report.outcomes[0].project, missing);
+        assert!(!report.outcomes[0].success);
+        assert!(report.outcomes[1].success);
+        assert_eq!(report.failed(), 1);
+
+        let table = report.table();
+        assert_eq!(table.len(), 3);
+        assert!(table[1].contains("FAILED"));
+    }
+}
@@ -0,0 +1,375 @@
+//! Build log module
+//!
+//! This module keeps a record of every compile under `.forgekit/builds/<id>/`:
+//! `build.json` holds the command cargo ran with, the environment it saw, the
+//! outcome and the diagnostics, and `build.log` holds cargo's full output.
+//! `forgekit builds list` and `forgekit builds show` read them back, and
+//! `forgekit build --replay <id>` runs a recorded command again with the same
+//! arguments and environment, to chase builds that do not reproduce.
+//!
+//! Variables of an environment profile may hold secrets, so only their names
+//! are recorded and a replay loads the profile again. Inherited variables are
+//! recorded when they affect compilation, like `RUSTFLAGS` or `CC`, unless
+//! they are sensitive (see [`crate::redact`]). Secret values the build
+//! printed are masked in the stored output and diagnostics.
+
+use crate::builder;
+use crate::cancel::{self, CancellationToken};
+use crate::credentials;
+use crate::env_manager::EnvManager;
+use crate::error::ForgeKitError;
+use crate::redact::Redactor;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+
+/// File holding the record of a build
+pub const BUILD_RECORD: &str = "build.json";
+
+/// File holding the full output of a build
+pub const BUILD_LOG: &str = "build.log";
+
+/// Number of builds kept; older ones are removed
+const BUILD_LOG_LIMIT: usize = 20;
+
+/// Prefixes of inherited variables that affect compilation
+const INHERITED_PREFIXES: &[&str] = &[
+    "CARGO",
+    "RUST",
+    "PATH",
+    "HOME",
+    "LANG",
+    "LC_",
+    "TZ",
+    "TMPDIR",
+    "CC",
+    "CXX",
+    "AR",
+    "CFLAGS",
+    "CXXFLAGS",
+    "LDFLAGS",
+    "PKG_CONFIG",
+    "SCCACHE",
+    "SOURCE_DATE_EPOCH",
+];
+
+/// Directory holding the build records of a project
+pub fn builds_dir(project_path: &Path) -> PathBuf {
+    project_path.join(".forgekit").join("builds")
+}
+
+/// How cargo was run for a build
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Invocation {
+    /// Program run, `cargo` or the sandbox wrapping it
+    pub program: String,
+    /// Arguments, in order
+    pub args: Vec<String>,
+    /// Working directory
+    pub dir: Option<PathBuf>,
+    /// Environment the program saw, profile variables excepted
+    pub env: BTreeMap<String, String>,
+    /// Environment profile of the build
+    pub environment: Option<String>,
+    /// Names of the variables loaded from the environment profile
+    pub profile_vars: Vec<String>,
+}
+
+impl Invocation {
+    /// Record how `command` will run
+    ///
+    /// `profile_vars` name the variables set from `environment`; their values
+    /// are left out.
+    pub fn capture(command: &Command, environment: Option<&str>, profile_vars: &[String]) -> Self {
+        let command = command.as_std();
+        let redactor = Redactor::default_patterns();
+        let mut env: BTreeMap<String, String> = std::env::vars()
+            .filter(|(name, _)| {
+                INHERITED_PREFIXES
+                    .iter()
+                    .any(|prefix| name.starts_with(prefix))
+                    && !redactor.is_sensitive(name)
+            })
+            .collect();
+        for (name, value) in command.get_envs() {
+            let name = name.to_string_lossy().to_string();
+            match value {
+                Some(_) if profile_vars.contains(&name) => {
+                    env.remove(&name);
+                }
+                Some(value) => {
+                    env.insert(name, value.to_string_lossy().to_string());
+                }
+                None => {
+                    env.remove(&name);
+                }
+            }
+        }
+
+        Self {
+            program: command.get_program().to_string_lossy().to_string(),
+            args: command
+                .get_args()
+                .map(|arg| arg.to_string_lossy().to_string())
+                .collect(),
+            dir: command.get_current_dir().map(Path::to_path_buf),
+            env,
+            environment: environment.map(str::to_string),
+            profile_vars: profile_vars.to_vec(),
+        }
+    }
+
+    /// Command running the invocation again with exactly its environment
+    pub fn command(&self, project_path: &Path) -> Result<Command, ForgeKitError> {
+        let mut command = Command::new(&self.program);
+        command.args(&self.args).env_clear().envs(&self.env);
+        command.current_dir(self.dir.as_deref().unwrap_or(project_path));
+        if let Some(environment) = &self.environment {
+            let profile = EnvManager::load_profile(
+                environment,
+                project_path,
+                &*credentials::default_store()?,
+            )?;
+            command.envs(
+                profile
+                    .all()
+                    .iter()
+                    .filter(|(name, _)| self.profile_vars.contains(name)),
+            );
+        }
+        Ok(command)
+    }
+}
+
+/// Record of one build
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildLog {
+    /// Identifier, sortable by start time
+    pub id: String,
+    /// Build this one replayed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replay_of: Option<String>,
+    /// When cargo was started
+    pub started_at: DateTime<Utc>,
+    /// Time cargo took, in milliseconds
+    pub duration_ms: u64,
+    /// Whether cargo succeeded
+    pub success: bool,
+    /// ForgeKit version that ran the build
+    pub forgekit_version: String,
+    /// How cargo was run
+    pub invocation: Invocation,
+    /// Number of compiler errors
+    pub errors: usize,
+    /// Number of compiler warnings
+    pub warnings: usize,
+    /// Error and warning lines of the output
+    pub diagnostics: Vec<String>,
+}
+
+impl BuildLog {
+    /// Store the record and output of a finished build
+    ///
+    /// Secret values of the project's environment are masked, and variables
+    /// marked sensitive in forgekit.toml are left out of the invocation. The
+    /// oldest records are removed beyond the 20 most recent.
+    pub fn record(
+        project_path: &Path,
+        mut invocation: Invocation,
+        output: &Output,
+        started_at: DateTime<Utc>,
+        duration: Duration,
+        replay_of: Option<String>,
+    ) -> Result<Self, ForgeKitError> {
+        let redactor = Redactor::for_project(project_path)?;
+        invocation
+            .env
+            .retain(|name, _| !redactor.is_sensitive(name));
+        let stdout = redactor.redact(&String::from_utf8_lossy(&output.stdout));
+        let stderr = redactor.redact(&String::from_utf8_lossy(&output.stderr));
+        let (errors, warnings) = builder::count_diagnostics(&stderr);
+        // Builds started within the same millisecond get a numbered suffix
+        let stamp = started_at.format("%Y%m%d-%H%M%S%3f").to_string();
+        let mut id = stamp.clone();
+        let mut n = 1;
+        while builds_dir(project_path).join(&id).exists() {
+            n += 1;
+            id = format!("{}-{}", stamp, n);
+        }
+        let record = Self {
+            id,
+            replay_of,
+            started_at,
+            duration_ms: duration.as_millis() as u64,
+            success: output.status.success(),
+            forgekit_version: env!("CARGO_PKG_VERSION").to_string(),
+            invocation,
+            errors,
+            warnings,
+            diagnostics: builder::diagnostic_lines(&stderr)
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+        };
+
+        let dir = builds_dir(project_path).join(&record.id);
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join(BUILD_LOG), format!("{}{}", stdout, stderr))?;
+        std::fs::write(
+            dir.join(BUILD_RECORD),
+            serde_json::to_string_pretty(&record)?,
+        )?;
+
+        for old in Self::list(project_path)?.iter().skip(BUILD_LOG_LIMIT) {
+            std::fs::remove_dir_all(builds_dir(project_path).join(&old.id))?;
+        }
+        Ok(record)
+    }
+
+    /// Recorded builds, newest first
+    pub fn list(project_path: &Path) -> Result<Vec<Self>, ForgeKitError> {
+        let dir = builds_dir(project_path);
+        if !dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut builds = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let record = entry.path().join(BUILD_RECORD);
+            // A record being written by another build is skipped, as is one
+            // whose id does not name its own directory
+            if let Ok(build) = std::fs::read_to_string(&record)
+                .map_err(ForgeKitError::from)
+                .and_then(|data| Ok(serde_json::from_str::<Self>(&data)?))
+            {
+                if *build.id == *entry.file_name() {
+                    builds.push(build);
+                }
+            }
+        }
+        builds.sort_by(|a, b| b.id.cmp(&a.id));
+        Ok(builds)
+    }
+
+    /// Recorded build with the given identifier
+    pub fn load(project_path: &Path, id: &str) -> Result<Self, ForgeKitError> {
+        validate_id(id)?;
+        let record = builds_dir(project_path).join(id).join(BUILD_RECORD);
+        if !record.is_file() {
+            return Err(ForgeKitError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("no recorded build '{}'; see `forgekit builds list`", id),
+            )));
+        }
+        Ok(serde_json::from_str(&std::fs::read_to_string(record)?)?)
+    }
+
+    /// Full output of the build
+    pub fn log_path(&self, project_path: &Path) -> PathBuf {
+        builds_dir(project_path).join(&self.id).join(BUILD_LOG)
+    }
+
+    /// Run a recorded build again with the same arguments and environment
+    ///
+    /// Only cargo is run again, not the hooks and generators around it. The
+    /// replay is recorded as a build of its own.
+    pub async fn replay(
+        project_path: &Path,
+        id: &str,
+        cancel: &CancellationToken,
+    ) -> Result<Self, ForgeKitError> {
+        let original = Self::load(project_path, id)?;
+        let mut command = original.invocation.command(project_path)?;
+        let started_at = Utc::now();
+        let start = Instant::now();
+        let output = cancel::output(&mut command, cancel, "build").await?;
+        Self::record(
+            project_path,
+            original.invocation,
+            &output,
+            started_at,
+            start.elapsed(),
+            Some(original.id),
+        )
+    }
+}
+
+/// Check that a build id, which names a directory, is one `record` makes
+fn validate_id(id: &str) -> Result<(), ForgeKitError> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_digit() || c == '-') {
+        return Err(ForgeKitError::InvalidConfig(format!(
+            "'{}' is not a build id; see `forgekit builds list`",
+            id
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_record_list_and_replay() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path();
+
+        let mut command = Command::new("sh");
+        command
+            .args([
+                "-c",
+                "echo \"warning: $LEVEL\" >&2; echo \"token $API_TOKEN\"",
+            ])
+            .env("LEVEL", "unused variable")
+            .env("API_TOKEN", "hunter2")
+            .current_dir(project);
+        let invocation = Invocation::capture(&command, Some("prod"), &["API_TOKEN".to_string()]);
+        assert_eq!(invocation.env["LEVEL"], "unused variable");
+        assert!(!invocation.env.contains_key("API_TOKEN"));
+
+        let invocation = Invocation {
+            environment: None,
+            ..invocation
+        };
+        let output = command.output().await.unwrap();
+        std::fs::write(project.join(".env.prod"), "API_TOKEN=hunter2\n").unwrap();
+        let first = BuildLog::record(
+            project,
+            invocation,
+            &output,
+            Utc::now(),
+            Duration::ZERO,
+            None,
+        )
+        .unwrap();
+        assert_eq!(first.warnings, 1);
+        assert_eq!(first.diagnostics, ["warning: unused variable"]);
+        assert_eq!(
+            std::fs::read_to_string(first.log_path(project)).unwrap(),
+            "token ********\nwarning: unused variable\n"
+        );
+
+        let replay = BuildLog::replay(project, &first.id, &CancellationToken::new())
+            .await
+            .unwrap();
+        assert_eq!(replay.replay_of.as_deref(), Some(first.id.as_str()));
+        assert_eq!(replay.diagnostics, first.diagnostics);
+        assert_eq!(
+            std::fs::read_to_string(replay.log_path(project)).unwrap(),
+            "token \nwarning: unused variable\n"
+        );
+        assert_eq!(BuildLog::list(project).unwrap().len(), 2);
+
+        for id in ["../../etc", "", "/tmp/x", "20240101-120000000/.."] {
+            assert!(matches!(
+                BuildLog::load(project, id),
+                Err(ForgeKitError::InvalidConfig(_))
+            ));
+        }
+    }
+}
@@ -1,12 +1,189 @@
 //! Project building functionality
 
+use crate::analytics;
+use crate::build_log::{BuildLog, Invocation};
+use crate::cancel::{self, CancellationToken};
+use crate::codegen::Codegen;
+use crate::compiler_cache::{CompilerCache, CompilerCacheStats};
+use crate::config::{CompilerCacheConfig, ProjectConfig, SandboxMode};
+use crate::credentials;
+use crate::env_manager::EnvManager;
 use crate::error::ForgeKitError;
-use std::path::Path;
-use tokio::process::Command;
+use crate::events::{Event, EventBus};
+use crate::hooks::{run_hooks, HookStage};
+use crate::lock::{FileLock, LockOptions};
+use crate::platform;
+use crate::release_profile::{self, ProfileSetting};
+use crate::sandbox::Sandbox;
+use crate::symbols;
+use crate::toolchain::{self, CompileTarget};
+use crate::ui;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Options for building a project
+#[derive(Debug, Clone, Default)]
+pub struct BuildOptions {
+    /// Cargo target directory, e.g. one shared by several projects
+    pub target_dir: Option<PathBuf>,
+    /// Compiler cache, usually the one of the global configuration; builds
+    /// without one use no compiler cache
+    pub compiler_cache: Option<CompilerCacheConfig>,
+    /// Token that interrupts the build and its cargo processes
+    pub cancel: CancellationToken,
+    /// Environment profile whose variables are passed to the build
+    pub environment: Option<String>,
+    /// Bus receiving build events
+    pub events: EventBus,
+    /// Locking against concurrent invocations on the project
+    pub lock: LockOptions,
+    /// Build for a host-compatible triple instead of ledokoz, for local testing
+    pub host: bool,
+}
+
+/// File recording how the last build was made, bundled into the .mox
+pub const BUILD_INFO: &str = "build-info.toml";
+
+/// How a build artifact was produced
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuildInfo {
+    /// Environment profile of the build, if any
+    pub environment: Option<String>,
+    /// When the build finished (RFC 3339)
+    pub built_at: String,
+    /// ForgeKit version that built it
+    pub forgekit_version: String,
+    /// Target the binary was built for, when not ledokoz
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// Cargo target directory of the build, when not the project's `target`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_dir: Option<PathBuf>,
+}
+
+impl BuildInfo {
+    /// Location of the build info of a project
+    pub fn path(project_path: &Path) -> PathBuf {
+        project_path
+            .join("target")
+            .join("forgekit")
+            .join(BUILD_INFO)
+    }
+
+    /// Directory name of the target the binary was built for
+    pub fn target(&self) -> &str {
+        self.target.as_deref().unwrap_or(platform::TARGET)
+    }
+
+    /// Cargo target directory the build wrote to
+    pub fn target_dir(&self, project_path: &Path) -> PathBuf {
+        self.target_dir
+            .clone()
+            .unwrap_or_else(|| project_path.join("target"))
+    }
+
+    /// Cargo target directory of the last build of a project, the project's
+    /// `target` if no build was recorded
+    pub fn last_target_dir(project_path: &Path) -> Result<PathBuf, ForgeKitError> {
+        Ok(Self::load(project_path)?.map_or_else(
+            || project_path.join("target"),
+            |info| info.target_dir(project_path),
+        ))
+    }
+
+    /// Build info of the last build, if it was recorded
+    pub fn load(project_path: &Path) -> Result<Option<Self>, ForgeKitError> {
+        let path = Self::path(project_path);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(toml::from_str(&std::fs::read_to_string(path)?)?))
+    }
+}
+
+/// Summary of a finished build
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildSummary {
+    /// Time spent building
+    pub duration: Duration,
+    /// Number of compiler warnings
+    pub warnings: usize,
+    /// Accesses blocked by the build sandbox
+    pub sandbox_violations: Vec<String>,
+    /// Compiler cache statistics, when a cache was used
+    pub compiler_cache: Option<CompilerCacheStats>,
+    /// Configured release profile settings and what they do
+    pub profile: Vec<ProfileSetting>,
+    /// Identifier of the build's log under `.forgekit/builds/`
+    pub build_id: Option<String>,
+}
+
+/// Count the errors and warnings reported in cargo output
+///
+/// Cargo's own trailing summaries ("could not compile", "generated N
+/// warnings") are not counted.
+pub fn count_diagnostics(stderr: &str) -> (usize, usize) {
+    let lines = diagnostic_lines(stderr);
+    let errors = lines
+        .iter()
+        .filter(|line| line.starts_with("error"))
+        .count();
+    (errors, lines.len() - errors)
+}
+
+/// The error and warning lines of cargo output, without cargo's summaries
+pub fn diagnostic_lines(stderr: &str) -> Vec<&str> {
+    stderr
+        .lines()
+        .filter(|line| {
+            if line.starts_with("error: could not compile") || line.starts_with("error: aborting") {
+                return false;
+            }
+            line.starts_with("error:")
+                || line.starts_with("error[")
+                || ((line.starts_with("warning:") || line.starts_with("warning["))
+                    && !line.contains(" generated "))
+        })
+        .collect()
+}
 
 /// Build a project at the given path
-pub async fn build(project_path: &Path) -> Result<(), ForgeKitError> {
+pub async fn build(project_path: &Path) -> Result<BuildSummary, ForgeKitError> {
+    build_with_options(project_path, &BuildOptions::default()).await
+}
+
+/// Build a project at the given path with custom options
+pub async fn build_with_options(
+    project_path: &Path,
+    options: &BuildOptions,
+) -> Result<BuildSummary, ForgeKitError> {
+    let start = Instant::now();
+    options.events.emit(Event::BuildStarted {
+        project_path: project_path.to_path_buf(),
+    });
+    let result = build_stages(project_path, options, start).await;
+    options.events.emit(Event::BuildFinished {
+        project_path: project_path.to_path_buf(),
+        success: result.is_ok(),
+        duration_ms: start.elapsed().as_millis() as u64,
+    });
+    result
+}
+
+/// Run the stages of a build, reporting each one on the event bus
+async fn build_stages(
+    project_path: &Path,
+    options: &BuildOptions,
+    start: Instant,
+) -> Result<BuildSummary, ForgeKitError> {
     tracing::info!("Building project at {:?}", project_path);
+    let stage = |stage: &str| {
+        options.events.emit(Event::BuildProgress {
+            project_path: project_path.to_path_buf(),
+            stage: stage.to_string(),
+        })
+    };
 
     // Check if project exists
     if !project_path.exists() {
@@ -14,25 +191,184 @@ pub async fn build(project_path: &Path) -> Result<(), ForgeKitError> {
             project_path.to_string_lossy().to_string(),
         ));
     }
+    let _lock = FileLock::project(project_path, &options.lock, "build").await?;
+
+    // Load project config for hooks (plain Cargo projects have none)
+    let config_path = project_path.join("forgekit.toml");
+    let config = if config_path.exists() {
+        ProjectConfig::load(&config_path)?.resolve(options.environment.as_deref())?
+    } else {
+        ProjectConfig::default()
+    };
 
-    // Change to project directory
-    let original_dir = std::env::current_dir()?;
-    std::env::set_current_dir(project_path)?;
+    stage("pre_build");
+    run_hooks(project_path, &config, HookStage::PreBuild, &[]).await?;
+    cancel::check(&options.cancel, "build")?;
+    stage("codegen");
+    Codegen::run(project_path, &config).await?;
+    stage("ui");
+    ui::compile_project(project_path)?;
 
-    // Run cargo build with custom target
-    let output = Command::new("cargo")
-        .args(["build", "--target", "ledokoz", "--release"])
-        .output()
+    // Run cargo build with custom target in the project directory. The
+    // process working directory is left alone so projects can build in parallel.
+    // Dependencies' build scripts run inside the configured sandbox
+    let sandbox = Sandbox::new(&config.build.sandbox, project_path);
+    let toolchain_config = config.build.toolchain.with_pin(&config.toolchain);
+    let target = CompileTarget::resolve(project_path, &toolchain_config, options.host).await?;
+    if let Some(channel) = &config.toolchain.channel {
+        if config.toolchain.rust_toolchain_file {
+            toolchain::write_rust_toolchain_file(project_path, channel)?;
+        }
+        // Docker builds run the toolchain of the image, not the host's
+        if config.build.sandbox.mode != SandboxMode::Docker {
+            toolchain::verify_pin(project_path, channel, &target).await?;
+        }
+    }
+    if target.is_host() {
+        tracing::info!(
+            "Building for host target {} for local testing",
+            target.dir_name()
+        );
+    }
+    let problems = release_profile::check(&config.build);
+    if !problems.is_empty() {
+        return Err(ForgeKitError::InvalidConfig(problems.join("; ")));
+    }
+    let features = config.build.features.join(",");
+    let target_args = target.cargo_args();
+    let build_args = release_profile::build_args(&config.build);
+    let profile_args = release_profile::cargo_args(&config.build.profile);
+    let mut cargo_args = vec!["build"];
+    cargo_args.extend(target_args.iter().map(String::as_str));
+    cargo_args.push("--release");
+    cargo_args.extend(build_args.iter().map(String::as_str));
+    cargo_args.extend(profile_args.iter().map(String::as_str));
+    if !features.is_empty() {
+        cargo_args.extend(["--features", features.as_str()]);
+    }
+    let mut command = sandbox
+        .command("cargo", &cargo_args, options.target_dir.as_deref())
         .await?;
+    command.envs(target.env());
+    if config.kind.is_app() {
+        for (name, value) in symbols::build_env(&config.build.symbols) {
+            command.env(name, value);
+        }
+    }
+    let mut profile_vars = Vec::new();
+    if let Some(environment) = &options.environment {
+        let profile =
+            EnvManager::load_profile(environment, project_path, &*credentials::default_store()?)?;
+        command.envs(profile.all());
+        profile_vars.extend(profile.all().keys().cloned());
+        profile_vars.sort();
+    }
+
+    let cache = match &options.compiler_cache {
+        Some(cache_config) if sandbox.supports_compiler_cache() => {
+            CompilerCache::resolve(cache_config).await?
+        }
+        _ => CompilerCache::Off,
+    };
+    cache
+        .prepare(&mut command, options.target_dir.is_some())
+        .await;
+    let target_dir = options
+        .target_dir
+        .clone()
+        .or_else(|| cache.target_dir().map(Path::to_path_buf))
+        .unwrap_or_else(|| project_path.join("target"));
+
+    stage("compile");
+    let invocation = Invocation::capture(&command, options.environment.as_deref(), &profile_vars);
+    let compile_started = chrono::Utc::now();
+    let compile_start = Instant::now();
+    let output = match cancel::output(&mut command, &options.cancel, "build").await {
+        Err(e @ ForgeKitError::Cancelled(_)) => {
+            sandbox.stop().await;
+            return Err(e);
+        }
+        output => output?,
+    };
+    let build_id = match BuildLog::record(
+        project_path,
+        invocation,
+        &output,
+        compile_started,
+        compile_start.elapsed(),
+        None,
+    ) {
+        Ok(log) => Some(log.id),
+        Err(e) => {
+            tracing::warn!("Failed to record the build log: {}", e);
+            None
+        }
+    };
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let violations = sandbox.violations(&stderr);
 
     if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(ForgeKitError::BuildFailed(stderr.to_string()));
+        let mut message = stderr.to_string();
+        if !violations.is_empty() {
+            message.push_str("\nBlocked by the build sandbox:\n  ");
+            message.push_str(&violations.join("\n  "));
+        }
+        if !target.is_host() {
+            let status = toolchain::status(&toolchain_config).await;
+            if !status.is_ready() {
+                message.push_str(&format!(
+                    "\nThe {} toolchain cannot build the ledokoz target; run `forgekit toolchain install`, or `forgekit build --host` to test locally",
+                    status.channel
+                ));
+            }
+        }
+        if let Some(id) = &build_id {
+            message.push_str(&format!(
+                "\nFull log: `forgekit builds show {}`; replay with `forgekit build --replay {}`",
+                id, id
+            ));
+        }
+        return Err(ForgeKitError::BuildFailed(message));
     }
 
-    // Restore original directory
-    std::env::set_current_dir(original_dir)?;
+    stage("post_build");
+    run_hooks(project_path, &config, HookStage::PostBuild, &[]).await?;
+
+    // Keep the debug info so crash reports can be symbolicated later; host
+    // builds never run on a device, so their symbols are not needed
+    let binary = platform::app_binary(&target_dir, &target.dir_name(), &config.name);
+    if config_path.exists() && config.kind.is_app() && !target.is_host() && binary.exists() {
+        let symbols_config = &config.build.symbols;
+        if let Err(e) = symbols::store(project_path, &config.version, &binary, symbols_config).await
+        {
+            tracing::warn!("Failed to store debug symbols: {}", e);
+        }
+    }
+
+    let info = BuildInfo {
+        environment: options.environment.clone(),
+        built_at: chrono::Utc::now().to_rfc3339(),
+        forgekit_version: env!("CARGO_PKG_VERSION").to_string(),
+        target: (target.dir_name() != platform::TARGET).then(|| target.dir_name()),
+        target_dir: (target_dir != project_path.join("target")).then_some(target_dir),
+    };
+    let info_path = BuildInfo::path(project_path);
+    if let Some(parent) = info_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(info_path, toml::to_string_pretty(&info)?)?;
 
     tracing::info!("Build completed successfully");
-    Ok(())
+    let duration = start.elapsed();
+    if let Err(e) = analytics::record_build(project_path, duration) {
+        tracing::warn!("Failed to record build time: {}", e);
+    }
+    Ok(BuildSummary {
+        duration,
+        warnings: count_diagnostics(&stderr).1,
+        sandbox_violations: violations,
+        compiler_cache: cache.stats(&stderr).await,
+        profile: release_profile::describe(&config.build.profile),
+        build_id,
+    })
 }
@@ -1,14 +1,33 @@
 //! Build caching module
 //!
 //! This module provides functionality for caching build artifacts
-//! to speed up subsequent builds.
+//! to speed up subsequent builds. Values live on disk, one file per key;
+//! only an index of keys and sizes is kept in memory, so large artifacts
+//! can be streamed in and out or memory-mapped instead of being held in RAM.
+//!
+//! Values are stored and looked up by [`CacheKey`], which namespaces them by
+//! project, profile, target and toolchain so that a release build never
+//! picks up a debug artifact or one made for another target. Entries of
+//! older versions, stored under flat keys, still count in the statistics
+//! and are removed by [`BuildCache::invalidate`] and [`BuildCache::clear`].
 
+use crate::atomic;
 use crate::error::ForgeKitError;
-use std::collections::HashMap;
+use crate::lock::{cache_lock_path, FileLock, LockOptions};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::ops::Deref;
 use std::path::{Path, PathBuf};
+use tokio::fs as tokio_fs;
+use tokio::io::{AsyncRead, AsyncWriteExt};
+
+/// Entries at least this large are memory-mapped by [`BuildCache::get_value`]
+pub const MMAP_THRESHOLD: u64 = 1024 * 1024;
 
 /// Cache statistics
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct CacheStats {
     /// Total cache size in bytes
     pub total_size: u64,
@@ -41,12 +60,95 @@ impl Default for CacheStats {
     }
 }
 
+/// Space used by the entries of one namespace
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NamespaceStats {
+    /// Namespace, `project/profile/target/toolchain` for [`CacheKey`] entries
+    pub namespace: String,
+    /// Number of cached items
+    pub item_count: usize,
+    /// Size of the items in bytes
+    pub total_size: u64,
+}
+
+/// Structured key of a build artifact
+///
+/// Renders as `project/profile/target/toolchain/inputs`; everything before
+/// the inputs hash is the entry's namespace.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    /// Project the artifact belongs to
+    pub project: String,
+    /// Build profile, like `debug` or `release`
+    pub profile: String,
+    /// Target triple or spec name the artifact was built for
+    pub target: String,
+    /// Hash identifying the toolchain, see [`CacheKey::hash`]
+    pub toolchain: String,
+    /// Hash of the inputs the artifact was built from
+    pub inputs: String,
+}
+
+impl CacheKey {
+    /// Short stable hash of `parts`, for the toolchain and inputs fields
+    pub fn hash<I>(parts: I) -> String
+    where
+        I: IntoIterator,
+        I::Item: AsRef<[u8]>,
+    {
+        let mut hasher = Sha256::new();
+        for part in parts {
+            let part = part.as_ref();
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part);
+        }
+        format!("{:x}", hasher.finalize())[..16].to_string()
+    }
+
+    /// `project/profile/target/toolchain`, shared by builds of the same kind
+    pub fn namespace(&self) -> String {
+        [&self.project, &self.profile, &self.target, &self.toolchain]
+            .iter()
+            .map(|part| key_component(part))
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+impl fmt::Display for CacheKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.namespace(), key_component(&self.inputs))
+    }
+}
+
+/// A cached value, read into memory or mapped from its cache file
+#[derive(Debug)]
+pub enum CacheValue {
+    /// Contents read into memory
+    Bytes(Vec<u8>),
+    /// Cache file mapped into memory
+    Mapped(memmap2::Mmap),
+}
+
+impl Deref for CacheValue {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            CacheValue::Bytes(bytes) => bytes,
+            CacheValue::Mapped(map) => map,
+        }
+    }
+}
+
 /// Build cache for storing and retrieving build artifacts
 #[derive(Debug)]
 pub struct BuildCache {
     cache_dir: PathBuf,
-    cache_data: HashMap<String, Vec<u8>>,
+    /// Size of each known entry; the values themselves stay on disk
+    index: HashMap<String, u64>,
     stats: CacheStats,
+    lock: LockOptions,
 }
 
 impl BuildCache {
@@ -62,34 +164,91 @@ impl BuildCache {
 
         Ok(Self {
             cache_dir,
-            cache_data: HashMap::new(),
+            index: HashMap::new(),
             stats: CacheStats::new(),
+            lock: LockOptions::default(),
         })
     }
 
+    /// Lock the cache directory this way while writing to it
+    pub fn with_lock(mut self, lock: LockOptions) -> Self {
+        self.lock = lock;
+        self
+    }
+
+    /// Keep other invocations from writing to the cache
+    async fn lock(&self) -> Result<FileLock, ForgeKitError> {
+        FileLock::acquire(&cache_lock_path(&self.cache_dir), &self.lock, "cache").await
+    }
+
+    /// File holding the value of `key`
+    ///
+    /// Every `/`-separated part is made a plain file name, so no key can
+    /// point outside the cache directory.
+    fn entry_path(&self, key: &str) -> PathBuf {
+        let parts: Vec<String> = key.split('/').map(key_component).collect();
+        self.cache_dir.join(format!("{}.cache", parts.join("/")))
+    }
+
+    /// Record a hit for an entry of `size` bytes, or a miss
+    fn record(&mut self, key: &str, size: Option<u64>) {
+        match size {
+            Some(size) => {
+                self.index.insert(key.to_string(), size);
+                self.stats.hits += 1;
+            }
+            None => {
+                self.index.remove(key);
+                self.stats.misses += 1;
+            }
+        }
+    }
+
     /// Get a cached value
     ///
+    /// The whole value is read into memory; use [`get_value`](Self::get_value)
+    /// or [`get_reader`](Self::get_reader) for large entries.
+    ///
     /// # Arguments
     ///
     /// * `key` - Cache key
-    pub async fn get(&mut self, key: &str) -> Option<Vec<u8>> {
-        if let Some(data) = self.cache_data.get(key) {
-            self.stats.hits += 1;
-            return Some(data.clone());
-        }
+    pub async fn get(&mut self, key: &CacheKey) -> Option<Vec<u8>> {
+        let key = &key.to_string();
+        let data = tokio_fs::read(self.entry_path(key)).await.ok();
+        self.record(key, data.as_ref().map(|data| data.len() as u64));
+        data
+    }
 
-        // Try to load from disk
-        let cache_file = self.cache_dir.join(format!("{}.cache", key));
-        if cache_file.exists() {
-            if let Ok(data) = std::fs::read(&cache_file) {
-                self.cache_data.insert(key.to_string(), data.clone());
-                self.stats.hits += 1;
-                return Some(data);
-            }
-        }
+    /// Get a cached value, memory-mapping entries of at least [`MMAP_THRESHOLD`] bytes
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Cache key
+    pub async fn get_value(&mut self, key: &CacheKey) -> Option<CacheValue> {
+        let key = &key.to_string();
+        let cache_file = self.entry_path(key);
+        let value = tokio::task::spawn_blocking(move || map_entry(&cache_file))
+            .await
+            .ok()
+            .flatten();
+        self.record(key, value.as_ref().map(|value| value.len() as u64));
+        value
+    }
 
-        self.stats.misses += 1;
-        None
+    /// Open a cached value for streaming
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Cache key
+    pub async fn get_reader(&mut self, key: &CacheKey) -> Option<tokio_fs::File> {
+        let key = &key.to_string();
+        let file = tokio_fs::File::open(self.entry_path(key)).await.ok();
+        let size = match &file {
+            Some(file) => file.metadata().await.ok().map(|metadata| metadata.len()),
+            None => None,
+        };
+        self.record(key, size);
+        file.filter(|_| size.is_some())
     }
 
     /// Set a cached value
@@ -98,13 +257,64 @@ impl BuildCache {
     ///
     /// * `key` - Cache key
     /// * `value` - Value to cache
-    pub async fn set(&mut self, key: &str, value: Vec<u8>) -> Result<(), ForgeKitError> {
-        let cache_file = self.cache_dir.join(format!("{}.cache", key));
-        std::fs::write(&cache_file, &value)?;
-        self.cache_data.insert(key.to_string(), value);
+    pub async fn set(&mut self, key: &CacheKey, value: Vec<u8>) -> Result<(), ForgeKitError> {
+        let key = &key.to_string();
+        let _lock = self.lock().await?;
+        let cache_file = self.entry_path(key);
+        let size = value.len() as u64;
+        tokio::task::spawn_blocking(move || {
+            if let Some(parent) = cache_file.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            atomic::write(&cache_file, value)
+        })
+        .await
+        .map_err(std::io::Error::from)??;
+        self.index.insert(key.to_string(), size);
         Ok(())
     }
 
+    /// Set a cached value by streaming it from `reader`
+    ///
+    /// The value is written to a temporary file and renamed into place, so
+    /// readers never see a partial entry. Returns the number of bytes cached.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Cache key
+    /// * `reader` - Source of the value
+    pub async fn put_reader<R: AsyncRead + Unpin>(
+        &mut self,
+        key: &CacheKey,
+        reader: &mut R,
+    ) -> Result<u64, ForgeKitError> {
+        let key = &key.to_string();
+        let _lock = self.lock().await?;
+        let cache_file = self.entry_path(key);
+        let temp_file = atomic::temp_path(&cache_file);
+        let result = async {
+            if let Some(parent) = cache_file.parent() {
+                tokio_fs::create_dir_all(parent).await?;
+            }
+            let mut file = tokio_fs::File::create(&temp_file).await?;
+            let size = tokio::io::copy(reader, &mut file).await?;
+            file.flush().await?;
+            file.sync_all().await?;
+            tokio_fs::rename(&temp_file, &cache_file).await?;
+            Ok::<_, std::io::Error>(size)
+        }
+        .await;
+        let size = match result {
+            Ok(size) => size,
+            Err(e) => {
+                let _ = tokio_fs::remove_file(&temp_file).await;
+                return Err(e.into());
+            }
+        };
+        self.index.insert(key.to_string(), size);
+        Ok(size)
+    }
+
     /// Invalidate cache entries matching a pattern
     ///
     /// # Arguments
@@ -112,26 +322,18 @@ impl BuildCache {
     /// * `pattern` - Glob pattern to match keys
     pub async fn invalidate(&mut self, pattern: &str) -> Result<(), ForgeKitError> {
         let regex = glob_to_regex(pattern);
+        let _lock = self.lock().await?;
 
-        // Remove from memory
-        self.cache_data.retain(|key, _| !regex.is_match(key));
+        // Remove from the index
+        self.index.retain(|key, _| !regex.is_match(key));
 
         // Remove from disk
-        if let Ok(entries) = std::fs::read_dir(&self.cache_dir) {
-            for entry in entries.flatten() {
-                if let Ok(metadata) = entry.metadata() {
-                    if metadata.is_file() {
-                        if let Some(filename) = entry.file_name().to_str() {
-                            if filename.ends_with(".cache") {
-                                let key = filename.trim_end_matches(".cache");
-                                if regex.is_match(key) {
-                                    let _ = std::fs::remove_file(entry.path());
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+        let cache_dir = self.cache_dir.clone();
+        let entries = tokio::task::spawn_blocking(move || read_entries(&cache_dir))
+            .await
+            .map_err(std::io::Error::from)?;
+        for key in entries.keys().filter(|key| regex.is_match(key)) {
+            let _ = tokio_fs::remove_file(self.entry_path(key)).await;
         }
 
         Ok(())
@@ -139,11 +341,12 @@ impl BuildCache {
 
     /// Clear all cache
     pub async fn clear(&mut self) -> Result<(), ForgeKitError> {
-        self.cache_data.clear();
+        self.index.clear();
 
-        if self.cache_dir.exists() {
-            std::fs::remove_dir_all(&self.cache_dir)?;
-            std::fs::create_dir_all(&self.cache_dir)?;
+        let _lock = self.lock().await?;
+        if tokio_fs::try_exists(&self.cache_dir).await? {
+            tokio_fs::remove_dir_all(&self.cache_dir).await?;
+            tokio_fs::create_dir_all(&self.cache_dir).await?;
         }
 
         Ok(())
@@ -152,10 +355,10 @@ impl BuildCache {
     /// Get cache statistics
     pub fn stats(&self) -> CacheStats {
         let mut stats = self.stats.clone();
-        stats.item_count = self.cache_data.len();
+        stats.item_count = self.index.len();
 
         // Calculate total size
-        stats.total_size = self.cache_data.values().map(|v| v.len() as u64).sum();
+        stats.total_size = self.index.values().sum();
 
         // Calculate hit rate
         let total = stats.hits + stats.misses;
@@ -166,29 +369,110 @@ impl BuildCache {
         stats
     }
 
-    /// Load cache from disk
-    pub fn load_from_disk(&mut self) -> Result<(), ForgeKitError> {
-        if !self.cache_dir.exists() {
-            return Ok(());
+    /// Space used by each namespace, largest first
+    ///
+    /// Keys without a `/`, which were not made from a [`CacheKey`], fall in
+    /// the empty namespace. Only indexed entries are counted, so call
+    /// [`load`](Self::load) first for the whole cache.
+    pub fn stats_by_namespace(&self) -> Vec<NamespaceStats> {
+        let mut namespaces: BTreeMap<&str, NamespaceStats> = BTreeMap::new();
+        for (key, size) in &self.index {
+            let namespace = namespace_of(key);
+            let stats = namespaces
+                .entry(namespace)
+                .or_insert_with(|| NamespaceStats {
+                    namespace: namespace.to_string(),
+                    item_count: 0,
+                    total_size: 0,
+                });
+            stats.item_count += 1;
+            stats.total_size += size;
         }
+        let mut namespaces: Vec<_> = namespaces.into_values().collect();
+        namespaces.sort_by_key(|namespace| std::cmp::Reverse(namespace.total_size));
+        namespaces
+    }
+
+    /// Namespaces on disk with no entry written within `max_age`
+    pub async fn expired_namespaces(
+        &self,
+        max_age: std::time::Duration,
+    ) -> Result<Vec<NamespaceStats>, ForgeKitError> {
+        let cache_dir = self.cache_dir.clone();
+        let entries = tokio::task::spawn_blocking(move || read_entries(&cache_dir))
+            .await
+            .map_err(std::io::Error::from)?;
+        let now = std::time::SystemTime::now();
+        let mut namespaces: BTreeMap<&str, (NamespaceStats, bool)> = BTreeMap::new();
+        for (key, metadata) in &entries {
+            let namespace = namespace_of(key);
+            let (stats, fresh) = namespaces.entry(namespace).or_insert_with(|| {
+                let stats = NamespaceStats {
+                    namespace: namespace.to_string(),
+                    item_count: 0,
+                    total_size: 0,
+                };
+                (stats, false)
+            });
+            stats.item_count += 1;
+            stats.total_size += metadata.len();
+            *fresh |= metadata
+                .modified()
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok())
+                .is_none_or(|age| age < max_age);
+        }
+        Ok(namespaces
+            .into_values()
+            .filter(|(_, fresh)| !fresh)
+            .map(|(stats, _)| stats)
+            .collect())
+    }
 
-        if let Ok(entries) = std::fs::read_dir(&self.cache_dir) {
-            for entry in entries.flatten() {
-                if let Ok(metadata) = entry.metadata() {
-                    if metadata.is_file() {
-                        if let Some(filename) = entry.file_name().to_str() {
-                            if filename.ends_with(".cache") {
-                                let key = filename.trim_end_matches(".cache").to_string();
-                                if let Ok(data) = std::fs::read(entry.path()) {
-                                    self.cache_data.insert(key, data);
-                                }
-                            }
-                        }
-                    }
-                }
+    /// Remove every entry of a namespace, returning the bytes freed
+    pub async fn remove_namespace(&mut self, namespace: &str) -> Result<u64, ForgeKitError> {
+        let _lock = self.lock().await?;
+        let cache_dir = self.cache_dir.clone();
+        let entries = tokio::task::spawn_blocking(move || read_entries(&cache_dir))
+            .await
+            .map_err(std::io::Error::from)?;
+        let mut freed = 0;
+        for (key, metadata) in entries {
+            if namespace_of(&key) == namespace {
+                tokio_fs::remove_file(self.entry_path(&key)).await?;
+                self.index.remove(&key);
+                freed += metadata.len();
             }
         }
+        // Drop the directories left empty, innermost first
+        let parts: Vec<String> = namespace.split('/').map(key_component).collect();
+        let mut dir = self.cache_dir.join(parts.join("/"));
+        while dir != self.cache_dir && tokio_fs::remove_dir(&dir).await.is_ok() {
+            if !dir.pop() {
+                break;
+            }
+        }
+        Ok(freed)
+    }
 
+    /// Index the entries on disk
+    ///
+    /// Only names and sizes are read, but this still blocks the calling
+    /// thread while the directory is scanned; async code should use
+    /// [`load`](Self::load) instead.
+    pub fn load_from_disk(&mut self) -> Result<(), ForgeKitError> {
+        self.index
+            .extend(entry_sizes(read_entries(&self.cache_dir)));
+        Ok(())
+    }
+
+    /// Index the entries on disk on a blocking thread, keeping the runtime responsive
+    pub async fn load(&mut self) -> Result<(), ForgeKitError> {
+        let cache_dir = self.cache_dir.clone();
+        let entries = tokio::task::spawn_blocking(move || read_entries(&cache_dir))
+            .await
+            .map_err(std::io::Error::from)?;
+        self.index.extend(entry_sizes(entries));
         Ok(())
     }
 
@@ -198,6 +482,79 @@ impl BuildCache {
     }
 }
 
+/// Key and metadata of every `.cache` file of a cache directory
+///
+/// Namespaced keys live in subdirectories; their keys join the directory
+/// names with `/`.
+fn read_entries(cache_dir: &Path) -> HashMap<String, std::fs::Metadata> {
+    let mut found = HashMap::new();
+    let mut pending = vec![(cache_dir.to_path_buf(), String::new())];
+    while let Some((dir, prefix)) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            if metadata.is_dir() {
+                pending.push((entry.path(), format!("{}{}/", prefix, name)));
+            } else if let Some(key) = name.strip_suffix(".cache").filter(|_| metadata.is_file()) {
+                found.insert(format!("{}{}", prefix, key), metadata);
+            }
+        }
+    }
+    found
+}
+
+fn entry_sizes(entries: HashMap<String, std::fs::Metadata>) -> HashMap<String, u64> {
+    entries
+        .into_iter()
+        .map(|(key, metadata)| (key, metadata.len()))
+        .collect()
+}
+
+/// Namespace of a key, empty for keys not made from a [`CacheKey`]
+fn namespace_of(key: &str) -> &str {
+    key.rsplit_once('/').map_or("", |(namespace, _)| namespace)
+}
+
+/// Part of a [`CacheKey`] made safe to use as a directory or file name
+fn key_component(part: &str) -> String {
+    let component: String = part
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if component.is_empty() || component.chars().all(|c| c == '.') {
+        "_".to_string()
+    } else {
+        component
+    }
+}
+
+/// Read a cache file, mapping it when it is large
+fn map_entry(path: &Path) -> Option<CacheValue> {
+    let file = std::fs::File::open(path).ok()?;
+    if file.metadata().ok()?.len() < MMAP_THRESHOLD {
+        let mut data = Vec::new();
+        std::io::Read::read_to_end(&mut &file, &mut data).ok()?;
+        return Some(CacheValue::Bytes(data));
+    }
+    // SAFETY: cache files are only ever replaced by renaming a new file over
+    // them, never written in place, so the mapped file does not change
+    let map = unsafe { memmap2::Mmap::map(&file) }.ok()?;
+    Some(CacheValue::Mapped(map))
+}
+
 /// Convert glob pattern to regex
 fn glob_to_regex(pattern: &str) -> regex::Regex {
     let regex_pattern = pattern
@@ -214,6 +571,17 @@ mod tests {
     use super::*;
     use tempfile::TempDir;
 
+    /// Key of an artifact of a debug build of `hello`
+    fn key(inputs: &str) -> CacheKey {
+        CacheKey {
+            project: "hello".to_string(),
+            profile: "debug".to_string(),
+            target: "x86_64-unknown-linux-gnu".to_string(),
+            toolchain: "stable".to_string(),
+            inputs: inputs.to_string(),
+        }
+    }
+
     #[tokio::test]
     async fn test_cache_creation() {
         let temp_dir = TempDir::new().unwrap();
@@ -227,9 +595,9 @@ mod tests {
         let mut cache = BuildCache::new(temp_dir.path().to_path_buf()).unwrap();
 
         let data = vec![1, 2, 3, 4, 5];
-        cache.set("test_key", data.clone()).await.unwrap();
+        cache.set(&key("test_key"), data.clone()).await.unwrap();
 
-        let retrieved = cache.get("test_key").await;
+        let retrieved = cache.get(&key("test_key")).await;
         assert_eq!(retrieved, Some(data));
     }
 
@@ -238,7 +606,7 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         let mut cache = BuildCache::new(temp_dir.path().to_path_buf()).unwrap();
 
-        let retrieved = cache.get("nonexistent").await;
+        let retrieved = cache.get(&key("nonexistent")).await;
         assert_eq!(retrieved, None);
     }
 
@@ -247,13 +615,13 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         let mut cache = BuildCache::new(temp_dir.path().to_path_buf()).unwrap();
 
-        cache.set("key1", vec![1, 2, 3]).await.unwrap();
-        cache.set("key2", vec![4, 5, 6]).await.unwrap();
+        cache.set(&key("key1"), vec![1, 2, 3]).await.unwrap();
+        cache.set(&key("key2"), vec![4, 5, 6]).await.unwrap();
 
         cache.clear().await.unwrap();
 
-        assert_eq!(cache.get("key1").await, None);
-        assert_eq!(cache.get("key2").await, None);
+        assert_eq!(cache.get(&key("key1")).await, None);
+        assert_eq!(cache.get(&key("key2")).await, None);
     }
 
     #[tokio::test]
@@ -261,12 +629,12 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         let mut cache = BuildCache::new(temp_dir.path().to_path_buf()).unwrap();
 
-        cache.set("key1", vec![1, 2, 3]).await.unwrap();
-        cache.set("key2", vec![4, 5, 6]).await.unwrap();
+        cache.set(&key("key1"), vec![1, 2, 3]).await.unwrap();
+        cache.set(&key("key2"), vec![4, 5, 6]).await.unwrap();
 
-        let _ = cache.get("key1").await;
-        let _ = cache.get("key1").await;
-        let _ = cache.get("nonexistent").await;
+        let _ = cache.get(&key("key1")).await;
+        let _ = cache.get(&key("key1")).await;
+        let _ = cache.get(&key("nonexistent")).await;
 
         let stats = cache.stats();
         assert_eq!(stats.item_count, 2);
@@ -279,15 +647,15 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         let mut cache = BuildCache::new(temp_dir.path().to_path_buf()).unwrap();
 
-        cache.set("build_1", vec![1, 2, 3]).await.unwrap();
-        cache.set("build_2", vec![4, 5, 6]).await.unwrap();
-        cache.set("test_1", vec![7, 8, 9]).await.unwrap();
+        cache.set(&key("build_1"), vec![1, 2, 3]).await.unwrap();
+        cache.set(&key("build_2"), vec![4, 5, 6]).await.unwrap();
+        cache.set(&key("test_1"), vec![7, 8, 9]).await.unwrap();
 
-        cache.invalidate("build_*").await.unwrap();
+        cache.invalidate("*/build_*").await.unwrap();
 
-        assert_eq!(cache.get("build_1").await, None);
-        assert_eq!(cache.get("build_2").await, None);
-        assert_eq!(cache.get("test_1").await, Some(vec![7, 8, 9]));
+        assert_eq!(cache.get(&key("build_1")).await, None);
+        assert_eq!(cache.get(&key("build_2")).await, None);
+        assert_eq!(cache.get(&key("test_1")).await, Some(vec![7, 8, 9]));
     }
 
     #[test]
@@ -300,6 +668,102 @@ mod tests {
         std::fs::write(&cache_file, vec![1, 2, 3]).unwrap();
 
         cache.load_from_disk().unwrap();
-        assert!(cache.cache_data.contains_key("test_key"));
+        assert_eq!(cache.index.get("test_key"), Some(&3));
+    }
+
+    #[tokio::test]
+    async fn test_load() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("a.cache"), vec![1]).unwrap();
+        std::fs::write(temp_dir.path().join("notes.txt"), vec![2]).unwrap();
+
+        let mut cache = BuildCache::new(temp_dir.path().to_path_buf()).unwrap();
+        cache.load().await.unwrap();
+        assert_eq!(cache.stats().item_count, 1);
+        assert_eq!(cache.index.get("a"), Some(&1));
+    }
+
+    #[tokio::test]
+    async fn test_streamed_and_mapped_values() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut cache = BuildCache::new(temp_dir.path().to_path_buf()).unwrap();
+
+        let large = vec![7u8; MMAP_THRESHOLD as usize + 1];
+        let size = cache
+            .put_reader(&key("large"), &mut &large[..])
+            .await
+            .unwrap();
+        assert_eq!(size, large.len() as u64);
+        cache.set(&key("small"), vec![1, 2, 3]).await.unwrap();
+
+        let value = cache.get_value(&key("large")).await.unwrap();
+        assert!(matches!(value, CacheValue::Mapped(_)));
+        assert_eq!(&value[..], &large[..]);
+        let value = cache.get_value(&key("small")).await.unwrap();
+        assert!(matches!(value, CacheValue::Bytes(_)));
+
+        let mut reader = cache.get_reader(&key("small")).await.unwrap();
+        let mut streamed = Vec::new();
+        tokio::io::AsyncReadExt::read_to_end(&mut reader, &mut streamed)
+            .await
+            .unwrap();
+        assert_eq!(streamed, vec![1, 2, 3]);
+        assert!(cache.get_reader(&key("missing")).await.is_none());
+
+        let stats = cache.stats();
+        assert_eq!(stats.total_size, large.len() as u64 + 3);
+        assert_eq!((stats.hits, stats.misses), (3, 1));
+    }
+
+    #[tokio::test]
+    async fn test_namespaced_keys() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut cache = BuildCache::new(temp_dir.path().to_path_buf()).unwrap();
+
+        let toolchain = CacheKey::hash(["1.80.0-nightly", "2024-05-01"]);
+        let debug = CacheKey {
+            project: "hello".to_string(),
+            profile: "debug".to_string(),
+            target: "x86_64-unknown-linux-gnu".to_string(),
+            toolchain: toolchain.clone(),
+            inputs: CacheKey::hash(["src/main.rs", "fn main() {}"]),
+        };
+        let release = CacheKey {
+            profile: "release".to_string(),
+            target: "../ledokoz".to_string(),
+            ..debug.clone()
+        };
+        assert_ne!(debug.to_string(), release.to_string());
+        assert_eq!(
+            release.namespace(),
+            format!("hello/release/.._ledokoz/{}", toolchain)
+        );
+
+        cache.set(&debug, vec![1; 10]).await.unwrap();
+        cache.set(&release, vec![2; 30]).await.unwrap();
+        std::fs::write(temp_dir.path().join("legacy.cache"), vec![3]).unwrap();
+
+        let mut reloaded = BuildCache::new(temp_dir.path().to_path_buf()).unwrap();
+        reloaded.load().await.unwrap();
+        assert_eq!(reloaded.get(&debug).await, Some(vec![1; 10]));
+        let namespaces = reloaded.stats_by_namespace();
+        assert_eq!(namespaces.len(), 3);
+        assert_eq!(namespaces[0].namespace, release.namespace());
+        assert_eq!(namespaces[0].total_size, 30);
+        assert_eq!(namespaces[2].namespace, "");
+
+        reloaded.invalidate("hello/release/*").await.unwrap();
+        assert_eq!(reloaded.get(&release).await, None);
+        assert_eq!(reloaded.stats().item_count, 2);
+
+        // Flat keys cannot leave the cache directory either
+        assert_eq!(
+            cache.entry_path("../../escape"),
+            temp_dir.path().join("_/_/escape.cache")
+        );
+        assert_eq!(
+            cache.entry_path("/etc/passwd"),
+            temp_dir.path().join("_/etc/passwd.cache")
+        );
     }
 }
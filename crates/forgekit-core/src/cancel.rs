@@ -0,0 +1,170 @@
+//! Cancellation module
+//!
+//! This module lets long-running operations be interrupted cleanly. A
+//! [`CancellationToken`] is passed to builds, packaging and the dev server.
+//! Child processes run in their own process group so that cancelling the
+//! token stops them together with everything they spawned (e.g. the rustc
+//! processes of a cargo build), and the operation returns
+//! [`ForgeKitError::Cancelled`].
+
+use crate::error::ForgeKitError;
+use std::process::{Output, Stdio};
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+use tokio::process::{Child, Command};
+
+pub use tokio_util::sync::CancellationToken;
+
+/// Time a cancelled process group gets to exit before it is killed
+const GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// Return [`ForgeKitError::Cancelled`] if the token has been cancelled
+pub fn check(cancel: &CancellationToken, operation: &str) -> Result<(), ForgeKitError> {
+    if cancel.is_cancelled() {
+        return Err(ForgeKitError::Cancelled(operation.to_string()));
+    }
+    Ok(())
+}
+
+/// Run a command to completion and collect its output, unless cancelled
+///
+/// On cancellation the process group is asked to terminate, then killed
+/// after a grace period. Dropping the returned future kills it immediately.
+pub async fn output(
+    command: &mut Command,
+    cancel: &CancellationToken,
+    operation: &str,
+) -> Result<Output, ForgeKitError> {
+    check(cancel, operation)?;
+
+    command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    #[cfg(unix)]
+    command.process_group(0);
+
+    let mut child = command.spawn()?;
+    let mut guard = GroupGuard(child.id());
+    let stdout = tokio::spawn(read_all(child.stdout.take()));
+    let stderr = tokio::spawn(read_all(child.stderr.take()));
+
+    let status = tokio::select! {
+        status = child.wait() => status?,
+        _ = cancel.cancelled() => {
+            terminate(&mut child).await;
+            guard.disarm();
+            return Err(ForgeKitError::Cancelled(operation.to_string()));
+        }
+    };
+    guard.disarm();
+
+    Ok(Output {
+        status,
+        stdout: stdout.await.map_err(std::io::Error::from)??,
+        stderr: stderr.await.map_err(std::io::Error::from)??,
+    })
+}
+
+/// Read a child's output pipe to the end
+async fn read_all<R: tokio::io::AsyncRead + Unpin>(pipe: Option<R>) -> std::io::Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    if let Some(mut pipe) = pipe {
+        pipe.read_to_end(&mut buffer).await?;
+    }
+    Ok(buffer)
+}
+
+/// Stop a child and its process group, politely first
+async fn terminate(child: &mut Child) {
+    if let Some(pid) = child.id() {
+        signal_group(pid, Signal::Terminate);
+        let _ = tokio::time::timeout(GRACE_PERIOD, child.wait()).await;
+        // Whatever ignored SIGTERM, including grandchildren, is killed
+        signal_group(pid, Signal::Kill);
+    }
+    let _ = child.kill().await;
+}
+
+#[derive(Clone, Copy)]
+enum Signal {
+    Terminate,
+    Kill,
+}
+
+/// Send a signal to every process in a child's process group
+#[cfg(unix)]
+fn signal_group(pid: u32, signal: Signal) {
+    let signal = match signal {
+        Signal::Terminate => libc::SIGTERM,
+        Signal::Kill => libc::SIGKILL,
+    };
+    // SAFETY: kill(2) has no memory-safety preconditions; a negative pid
+    // addresses the process group the child leads.
+    unsafe {
+        libc::kill(-(pid as libc::pid_t), signal);
+    }
+}
+
+/// Process groups are unix-only; the child itself is killed instead
+#[cfg(not(unix))]
+fn signal_group(_pid: u32, _signal: Signal) {}
+
+/// Kills a child's process group when an in-flight command is dropped
+struct GroupGuard(Option<u32>);
+
+impl GroupGuard {
+    /// Leave the process group alone once the command has finished
+    fn disarm(&mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for GroupGuard {
+    fn drop(&mut self) {
+        if let Some(pid) = self.0 {
+            signal_group(pid, Signal::Kill);
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[tokio::test]
+    async fn test_output_collects_stdout() {
+        let cancel = CancellationToken::new();
+        let output = output(Command::new("sh").args(["-c", "echo ok"]), &cancel, "echo")
+            .await
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "ok\n");
+    }
+
+    #[tokio::test]
+    async fn test_cancel_kills_process_tree() {
+        let cancel = CancellationToken::new();
+        let trigger = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            trigger.cancel();
+        });
+
+        let start = Instant::now();
+        let result = output(
+            Command::new("sh").args(["-c", "sleep 30 & wait"]),
+            &cancel,
+            "sleep",
+        )
+        .await;
+        assert!(matches!(result, Err(ForgeKitError::Cancelled(_))));
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        assert!(matches!(
+            check(&cancel, "build"),
+            Err(ForgeKitError::Cancelled(op)) if op == "build"
+        ));
+    }
+}
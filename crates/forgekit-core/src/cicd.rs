@@ -1,76 +1,605 @@
 //! CI/CD integration module
 //!
-//! This module provides CI/CD template generation.
+//! This module provides CI/CD template generation. The pipelines build an
+//! explicit matrix from `[ci]` of forgekit.toml: every combination of
+//! declared target, environment profile and feature set is its own job,
+//! uploading its binaries as an artifact named after the combination, with
+//! cargo's caches keyed by the hash of the lockfiles. With
+//! [`CiOptions::sarif`] set, the pipelines also run `forgekit check` and
+//! upload its SARIF report, which GitHub code scanning shows as annotations
+//! on the changed lines. GitLab does not read SARIF, so its pipeline
+//! uploads the same findings as a Code Quality report instead.
 
+use crate::config::ProjectConfig;
+use crate::diagnostics::{CODE_QUALITY_FILE, SARIF_FILE};
 use crate::error::ForgeKitError;
-use std::path::Path;
+use crate::lockfile::LOCKFILE_NAME;
+use crate::release_profile;
+use std::path::{Path, PathBuf};
+
+/// CI system to generate a pipeline for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CiProvider {
+    /// GitHub Actions, `.github/workflows/build.yml`
+    GitHub,
+    /// GitLab CI, `.gitlab-ci.yml`
+    GitLab,
+    /// Jenkins, `Jenkinsfile`
+    Jenkins,
+}
+
+impl std::str::FromStr for CiProvider {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "github" => Ok(CiProvider::GitHub),
+            "gitlab" => Ok(CiProvider::GitLab),
+            "jenkins" => Ok(CiProvider::Jenkins),
+            _ => Err(format!(
+                "unknown CI provider '{}' (expected github, gitlab or jenkins)",
+                s
+            )),
+        }
+    }
+}
+
+/// Options of the generated pipelines
+#[derive(Debug, Clone, Default)]
+pub struct CiOptions {
+    /// Run `forgekit check` and upload its SARIF report
+    pub sarif: bool,
+}
+
+/// Targets the tests can run for on the runner building them
+const NATIVE_TARGETS: &[&str] = &[
+    "x86_64-unknown-linux-gnu",
+    "x86_64-pc-windows-msvc",
+    "aarch64-apple-darwin",
+    "x86_64-apple-darwin",
+];
+
+/// Targets rustc links without a C toolchain for them, so that any Linux
+/// runner can build them
+const SELF_LINKED_TARGETS: &[&str] = &[
+    "x86_64-unknown-linux-musl",
+    "wasm32-unknown-unknown",
+    "wasm32-wasip1",
+    "wasm32-wasip2",
+];
+
+/// One job of the build matrix
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatrixEntry {
+    /// Target triple, `None` for the runner's own
+    pub target: Option<String>,
+    /// Environment profile the configuration is resolved for
+    pub profile: Option<String>,
+    /// Cargo features enabled
+    pub features: Vec<String>,
+    /// GitHub Actions runner image
+    pub runner: &'static str,
+    /// Name of the job and its uploaded artifact
+    pub artifact: String,
+    /// `cargo build` command line
+    pub build: String,
+    /// `cargo test` command line, `None` when the runner cannot run the
+    /// target's binaries
+    pub test: Option<String>,
+    /// Directory receiving the release binaries
+    pub output_dir: String,
+    /// Debian package of the GCC cross toolchain linking the target on the
+    /// x86_64 Linux runners, which the build passes as the linker
+    pub cross_toolchain: Option<String>,
+}
+
+impl MatrixEntry {
+    /// `cargo test` command line for runners without a choice of operating
+    /// system, which are assumed to run Linux
+    fn linux_test(&self) -> Option<&str> {
+        self.test
+            .as_deref()
+            .filter(|_| self.runner == "ubuntu-latest")
+    }
+
+    /// Whether a Linux runner can build the target: its own, Linux targets
+    /// with a cross toolchain from the distribution, and those rustc links
+    /// itself
+    fn builds_on_linux(&self) -> bool {
+        match &self.target {
+            None => true,
+            Some(target) => {
+                target == "x86_64-unknown-linux-gnu"
+                    || self.cross_toolchain.is_some()
+                    || SELF_LINKED_TARGETS.contains(&target.as_str())
+            }
+        }
+    }
+}
+
+/// Comment lines, started with `prefix`, naming the targets of `skipped`
+fn skipped_comment(prefix: &str, skipped: &[MatrixEntry]) -> String {
+    let mut targets: Vec<&str> = Vec::new();
+    for target in skipped.iter().filter_map(|entry| entry.target.as_deref()) {
+        if !targets.contains(&target) {
+            targets.push(target);
+        }
+    }
+    targets
+        .iter()
+        .map(|target| {
+            format!(
+                "{} Skipped {}: the Linux runners cannot link it\n",
+                prefix, target
+            )
+        })
+        .collect()
+}
+
+/// Expand `[ci]` of a project into the jobs of its build matrix
+///
+/// Without `[ci] targets` the runner's own target is built; without
+/// `[ci] profiles` every `[env.<name>]` profile, or the base configuration
+/// when there is none; without `[ci] feature_sets` the features the profile
+/// enables.
+pub fn matrix(config: &ProjectConfig) -> Result<Vec<MatrixEntry>, ForgeKitError> {
+    let ci = &config.ci;
+    let targets: Vec<Option<&String>> = if ci.targets.is_empty() {
+        vec![None]
+    } else {
+        ci.targets.iter().map(Some).collect()
+    };
+    let profiles: Vec<Option<&String>> = if !ci.profiles.is_empty() {
+        ci.profiles.iter().map(Some).collect()
+    } else if !config.env.is_empty() {
+        config.env.keys().map(Some).collect()
+    } else {
+        vec![None]
+    };
+
+    let mut entries = Vec::new();
+    for profile in &profiles {
+        if let Some(profile) = profile {
+            if !config.env.contains_key(*profile) {
+                return Err(ForgeKitError::InvalidConfig(format!(
+                    "[ci] profiles names '{}', which has no [env.{}] section",
+                    profile, profile
+                )));
+            }
+        }
+        let resolved = config.resolve(profile.map(String::as_str))?;
+        let feature_sets = if ci.feature_sets.is_empty() {
+            vec![resolved.build.features.clone()]
+        } else {
+            ci.feature_sets.clone()
+        };
+        for target in &targets {
+            for features in &feature_sets {
+                entries.push(matrix_entry(
+                    config,
+                    &resolved,
+                    target.map(String::as_str),
+                    profile.map(String::as_str),
+                    features,
+                ));
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Job building `target` for `profile` with `features`
+fn matrix_entry(
+    config: &ProjectConfig,
+    resolved: &ProjectConfig,
+    target: Option<&str>,
+    profile: Option<&str>,
+    features: &[String],
+) -> MatrixEntry {
+    let mut artifact = config.name.clone();
+    artifact.push('-');
+    // The full triple, since targets can differ only in their ABI
+    artifact.push_str(target.unwrap_or("native"));
+    if let Some(profile) = profile {
+        artifact.push('-');
+        artifact.push_str(profile);
+    }
+    if !config.ci.feature_sets.is_empty() {
+        artifact.push('-');
+        if features.is_empty() {
+            artifact.push_str("default");
+        } else {
+            artifact.push_str(&features.join("+"));
+        }
+    }
+
+    let mut selection = Vec::new();
+    if let Some(target) = target {
+        selection.extend(["--target".to_string(), target.to_string()]);
+    }
+    if !features.is_empty() {
+        selection.extend(["--features".to_string(), features.join(",")]);
+    }
+    let mut build = vec!["cargo".to_string(), "build".to_string(), "--release".into()];
+    build.extend(selection.iter().cloned());
+    build.extend(release_profile::build_args(&resolved.build));
+    build.extend(release_profile::cargo_args(&resolved.build.profile));
+    let cross = target.and_then(cross_toolchain);
+    if let (Some(target), Some((_, linker))) = (target, &cross) {
+        build.extend([
+            "--config".to_string(),
+            format!("target.{}.linker=\"{}\"", target, linker),
+        ]);
+    }
+    let test = target
+        .is_none_or(|target| NATIVE_TARGETS.contains(&target))
+        .then(|| {
+            let mut test = vec!["cargo".to_string(), "test".to_string()];
+            test.extend(selection.iter().cloned());
+            command_line(&test)
+        });
+
+    MatrixEntry {
+        target: target.map(str::to_string),
+        profile: profile.map(str::to_string),
+        features: features.to_vec(),
+        runner: runner(target),
+        artifact,
+        build: command_line(&build),
+        test,
+        output_dir: match target {
+            Some(target) => format!("target/{}/release", target),
+            None => "target/release".to_string(),
+        },
+        cross_toolchain: cross.map(|(package, _)| package),
+    }
+}
+
+/// Debian package and linker of the GCC cross toolchain for a Linux GNU
+/// `target` of another architecture than the x86_64 Linux runners
+fn cross_toolchain(target: &str) -> Option<(String, String)> {
+    let (arch, rest) = target.split_once('-')?;
+    let (_, abi) = rest.split_once("-linux-")?;
+    if arch == "x86_64" || !abi.starts_with("gnu") {
+        return None;
+    }
+    let arch = match arch {
+        "arm" | "armv7" | "thumbv7neon" => "arm",
+        "i586" | "i686" => "i686",
+        "riscv64gc" => "riscv64",
+        arch => arch,
+    };
+    let prefix = format!("{}-linux-{}", arch, abi);
+    Some((format!("gcc-{}", prefix), format!("{}-gcc", prefix)))
+}
+
+/// GitHub Actions runner image building `target`
+fn runner(target: Option<&str>) -> &'static str {
+    match target {
+        Some(target) if target.contains("-apple-") => "macos-latest",
+        Some(target) if target.contains("-windows") => "windows-latest",
+        _ => "ubuntu-latest",
+    }
+}
+
+/// Arguments joined into a shell command, quoting those that need it
+fn command_line(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| {
+            let plain = arg
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_.,=/+:".contains(c));
+            if plain {
+                arg.clone()
+            } else {
+                format!("'{}'", arg.replace('\'', r"'\''"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Configuration of the project at `path`, the defaults outside a project
+fn project_config(path: &Path) -> Result<ProjectConfig, ForgeKitError> {
+    let config_path = path.join("forgekit.toml");
+    if config_path.exists() {
+        ProjectConfig::load(config_path)
+    } else {
+        Ok(ProjectConfig::default())
+    }
+}
+
+/// Value as a YAML double-quoted string
+fn yaml_string(value: &str) -> String {
+    serde_json::Value::String(value.to_string()).to_string()
+}
 
 /// CI/CD generator
 pub struct CICDGenerator;
 
 impl CICDGenerator {
+    /// Generate the pipeline of `provider`, returning the file written
+    pub async fn generate(
+        path: &Path,
+        provider: CiProvider,
+        options: &CiOptions,
+    ) -> Result<PathBuf, ForgeKitError> {
+        let file = match provider {
+            CiProvider::GitHub => {
+                Self::generate_github_actions(path, options).await?;
+                path.join(".github").join("workflows").join("build.yml")
+            }
+            CiProvider::GitLab => {
+                Self::generate_gitlab_ci(path, options).await?;
+                path.join(".gitlab-ci.yml")
+            }
+            CiProvider::Jenkins => {
+                Self::generate_jenkins(path, options).await?;
+                path.join("Jenkinsfile")
+            }
+        };
+        Ok(file)
+    }
+
     /// Generate GitHub Actions workflow
-    pub async fn generate_github_actions(path: &Path) -> Result<(), ForgeKitError> {
+    pub async fn generate_github_actions(
+        path: &Path,
+        options: &CiOptions,
+    ) -> Result<(), ForgeKitError> {
+        let config = project_config(path)?;
+        let (entries, skipped): (Vec<_>, Vec<_>) = matrix(&config)?
+            .into_iter()
+            .partition(|entry| entry.runner != "ubuntu-latest" || entry.builds_on_linux());
         let workflows_dir = path.join(".github").join("workflows");
         std::fs::create_dir_all(&workflows_dir)?;
 
-        let workflow = r#"name: Build and Test
-on: [push, pull_request]
-jobs:
+        let mut workflow = skipped_comment("#", &skipped);
+        workflow.push_str("name: Build and Test\non: [push, pull_request]\n");
+        if options.sarif {
+            // Uploading to code scanning needs write access to security events
+            workflow.push_str("permissions:\n  contents: read\n  security-events: write\n");
+        }
+        workflow.push_str(
+            r#"jobs:
   build:
+    name: ${{ matrix.artifact }}
+    runs-on: ${{ matrix.os }}
+    strategy:
+      fail-fast: false
+      matrix:
+        include:
+"#,
+        );
+        for entry in &entries {
+            workflow.push_str(&format!(
+                "          - os: {}\n            target: {}\n            cross: {}\n            artifact: {}\n            build: {}\n            test: {}\n            output: {}\n",
+                entry.runner,
+                yaml_string(entry.target.as_deref().unwrap_or_default()),
+                yaml_string(entry.cross_toolchain.as_deref().unwrap_or_default()),
+                yaml_string(&entry.artifact),
+                yaml_string(&entry.build),
+                yaml_string(entry.test.as_deref().unwrap_or_default()),
+                yaml_string(&entry.output_dir),
+            ));
+        }
+        workflow.push_str(
+            &r#"    steps:
+      - uses: actions/checkout@v2
+      - uses: actions-rs/toolchain@v1
+        with:
+          toolchain: stable
+          target: ${{ matrix.target }}
+      - run: sudo apt-get update && sudo apt-get install -y ${{ matrix.cross }}
+        if: matrix.cross != ''
+      - uses: actions/cache@v4
+        with:
+          path: |
+            ~/.cargo/registry
+            ~/.cargo/git
+            target
+          key: ${{ runner.os }}-${{ matrix.artifact }}-${{ hashFiles('**/Cargo.lock', '{lockfile}') }}
+          restore-keys: ${{ runner.os }}-${{ matrix.artifact }}-
+      - run: ${{ matrix.build }}
+      - run: ${{ matrix.test }}
+        if: matrix.test != ''
+      - uses: actions/upload-artifact@v4
+        with:
+          name: ${{ matrix.artifact }}
+          path: |
+            ${{ matrix.output }}/{name}
+            ${{ matrix.output }}/{name}.exe
+          if-no-files-found: warn
+"#
+            .replace("{lockfile}", LOCKFILE_NAME)
+            .replace("{name}", &config.name),
+        );
+        if options.sarif {
+            workflow.push_str(
+                &r#"  check:
     runs-on: ubuntu-latest
     steps:
       - uses: actions/checkout@v2
       - uses: actions-rs/toolchain@v1
         with:
           toolchain: stable
-      - run: cargo build --verbose
-      - run: cargo test --verbose
-"#;
+      - run: cargo install forgekit-cli --locked
+      - run: forgekit check --no-tests --sarif {sarif}
+        continue-on-error: true
+      - uses: github/codeql-action/upload-sarif@v3
+        if: always()
+        with:
+          sarif_file: {sarif}
+          category: forgekit
+"#
+                .replace("{sarif}", SARIF_FILE),
+            );
+        }
 
         std::fs::write(workflows_dir.join("build.yml"), workflow)?;
         Ok(())
     }
 
     /// Generate GitLab CI configuration
-    pub async fn generate_gitlab_ci(path: &Path) -> Result<(), ForgeKitError> {
-        let config = r#"stages:
+    ///
+    /// Jobs run on Linux runners with a Debian-based image such as `rust`, so
+    /// tests only run for Linux targets and targets the runners cannot link
+    /// are left out, named in a comment.
+    pub async fn generate_gitlab_ci(path: &Path, options: &CiOptions) -> Result<(), ForgeKitError> {
+        let config = project_config(path)?;
+        let (entries, skipped): (Vec<_>, Vec<_>) = matrix(&config)?
+            .into_iter()
+            .partition(MatrixEntry::builds_on_linux);
+        let mut pipeline = skipped_comment("#", &skipped);
+        pipeline.push_str(
+            r#"stages:
   - build
   - test
 
-build:
-  stage: build
-  script:
-    - cargo build --verbose
-
-test:
+variables:
+  CARGO_HOME: $CI_PROJECT_DIR/.cargo
+"#,
+        );
+        for entry in entries {
+            let cache = format!(
+                r#"  cache:
+    key:
+      files:
+        - Cargo.lock
+        - {}
+      prefix: {}
+    paths:
+      - .cargo/registry
+      - .cargo/git
+      - target
+"#,
+                LOCKFILE_NAME, entry.artifact
+            );
+            pipeline.push_str(&format!(
+                "\nbuild:{}:\n  stage: build\n{}  script:\n",
+                entry.artifact, cache
+            ));
+            if let Some(target) = &entry.target {
+                pipeline.push_str(&format!("    - rustup target add {}\n", target));
+            }
+            if let Some(package) = &entry.cross_toolchain {
+                pipeline.push_str(&format!(
+                    "    - apt-get update && apt-get install -y {}\n",
+                    package
+                ));
+            }
+            pipeline.push_str(&format!(
+                "    - {}\n  artifacts:\n    name: {}\n    paths:\n      - {}/{}\n",
+                yaml_string(&entry.build),
+                entry.artifact,
+                entry.output_dir,
+                config.name
+            ));
+            if let Some(test) = entry.linux_test() {
+                pipeline.push_str(&format!(
+                    "\ntest:{}:\n  stage: test\n  needs: [\"build:{}\"]\n{}  script:\n    - {}\n",
+                    entry.artifact,
+                    entry.artifact,
+                    cache,
+                    yaml_string(test)
+                ));
+            }
+        }
+        if options.sarif {
+            pipeline.push_str(
+                &r#"
+check:
   stage: test
   script:
-    - cargo test --verbose
-"#;
+    - cargo install forgekit-cli --locked
+    - export PATH="$CARGO_HOME/bin:$PATH"
+    - forgekit check --no-tests --code-quality {report} || true
+  artifacts:
+    when: always
+    reports:
+      codequality: {report}
+"#
+                .replace("{report}", CODE_QUALITY_FILE),
+            );
+        }
 
-        std::fs::write(path.join(".gitlab-ci.yml"), config)?;
+        std::fs::write(path.join(".gitlab-ci.yml"), pipeline)?;
         Ok(())
     }
 
     /// Generate Jenkins pipeline
-    pub async fn generate_jenkins(path: &Path) -> Result<(), ForgeKitError> {
-        let pipeline = r#"pipeline {
-    agent any
-    stages {
-        stage('Build') {
-            steps {
-                sh 'cargo build --verbose'
+    ///
+    /// The matrix runs as parallel stages on Linux agents, so tests only run
+    /// for Linux targets and targets the agents cannot link are left out,
+    /// named in a comment. Jenkins has no built-in cache to key by the
+    /// lockfiles.
+    pub async fn generate_jenkins(path: &Path, options: &CiOptions) -> Result<(), ForgeKitError> {
+        let config = project_config(path)?;
+        let (entries, skipped): (Vec<_>, Vec<_>) = matrix(&config)?
+            .into_iter()
+            .partition(MatrixEntry::builds_on_linux);
+        let mut stages = String::new();
+        for entry in entries {
+            stages.push_str(&format!(
+                "\n                stage('{}') {{\n                    steps {{\n",
+                entry.artifact
+            ));
+            if let Some(target) = &entry.target {
+                stages.push_str(&format!(
+                    "                        sh 'rustup target add {}'\n",
+                    target
+                ));
+            }
+            if let Some(package) = &entry.cross_toolchain {
+                stages.push_str(&format!(
+                    "                        sh 'sudo apt-get update && sudo apt-get install -y {}'\n",
+                    package
+                ));
+            }
+            stages.push_str(&format!(
+                "                        sh '''{}'''\n",
+                entry.build
+            ));
+            if let Some(test) = entry.linux_test() {
+                stages.push_str(&format!("                        sh '''{}'''\n", test));
             }
+            stages.push_str(&format!(
+                "                        archiveArtifacts artifacts: '{}/{}*', allowEmptyArchive: true\n                    }}\n                }}",
+                entry.output_dir, config.name
+            ));
         }
-        stage('Test') {
+
+        let check = if options.sarif {
+            r#"
+        stage('Check') {
             steps {
-                sh 'cargo test --verbose'
+                sh 'cargo install forgekit-cli --locked'
+                sh 'forgekit check --no-tests --sarif {sarif} || true'
             }
-        }
+            post {
+                always {
+                    archiveArtifacts artifacts: '{sarif}', allowEmptyArchive: true
+                }
+            }
+        }"#
+            .replace("{sarif}", SARIF_FILE)
+        } else {
+            String::new()
+        };
+        let mut pipeline = skipped_comment("//", &skipped);
+        pipeline.push_str(
+            &r#"pipeline {
+    agent any
+    stages {
+        stage('Build and Test') {
+            parallel {{stages}
+            }
+        }{check}
     }
 }
-"#;
+"#
+            .replace("{stages}", &stages)
+            .replace("{check}", &check),
+        );
 
         std::fs::write(path.join("Jenkinsfile"), pipeline)?;
         Ok(())
@@ -85,7 +614,136 @@ mod tests {
     #[tokio::test]
     async fn test_generate_github_actions() {
         let temp_dir = TempDir::new().unwrap();
-        let result = CICDGenerator::generate_github_actions(temp_dir.path()).await;
+        let result =
+            CICDGenerator::generate_github_actions(temp_dir.path(), &CiOptions::default()).await;
         assert!(result.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_sarif_upload_steps() {
+        let temp_dir = TempDir::new().unwrap();
+        let options = CiOptions { sarif: true };
+
+        let file = CICDGenerator::generate(temp_dir.path(), CiProvider::GitHub, &options)
+            .await
+            .unwrap();
+        let workflow = std::fs::read_to_string(file).unwrap();
+        assert!(workflow.contains("security-events: write"));
+        assert!(workflow.contains("sarif_file: forgekit.sarif"));
+
+        let file = CICDGenerator::generate(temp_dir.path(), CiProvider::GitLab, &options)
+            .await
+            .unwrap();
+        let pipeline = std::fs::read_to_string(file).unwrap();
+        assert!(pipeline.contains("export PATH=\"$CARGO_HOME/bin:$PATH\""));
+        assert!(!pipeline.contains("allow_failure"));
+        assert!(pipeline.contains("codequality: gl-code-quality-report.json"));
+
+        let file = CICDGenerator::generate(temp_dir.path(), CiProvider::Jenkins, &options)
+            .await
+            .unwrap();
+        let pipeline = std::fs::read_to_string(file).unwrap();
+        assert!(pipeline.contains("archiveArtifacts artifacts: 'forgekit.sarif'"));
+    }
+
+    #[tokio::test]
+    async fn test_matrix_from_targets_profiles_and_features() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = ProjectConfig {
+            name: "demo".to_string(),
+            ..Default::default()
+        };
+        config.ci.targets = vec![
+            "x86_64-unknown-linux-gnu".to_string(),
+            "aarch64-unknown-linux-gnu".to_string(),
+        ];
+        config.ci.feature_sets = vec![vec![], vec!["telemetry".to_string()]];
+        config.env.insert(
+            "prod".to_string(),
+            toml::from_str("[build]\nopt_level = \"z\"").unwrap(),
+        );
+        config.save(temp_dir.path().join("forgekit.toml")).unwrap();
+
+        let entries = matrix(&config).unwrap();
+        assert_eq!(entries.len(), 4);
+        let cross = &entries[3];
+        assert_eq!(
+            cross.artifact,
+            "demo-aarch64-unknown-linux-gnu-prod-telemetry"
+        );
+        assert_eq!(
+            cross.build,
+            "cargo build --release --target aarch64-unknown-linux-gnu --features telemetry --config 'profile.release.opt-level=\"z\"' --config 'target.aarch64-unknown-linux-gnu.linker=\"aarch64-linux-gnu-gcc\"'"
+        );
+        assert_eq!(
+            cross.cross_toolchain.as_deref(),
+            Some("gcc-aarch64-linux-gnu")
+        );
+        assert_eq!(cross.test, None);
+        assert_eq!(cross.output_dir, "target/aarch64-unknown-linux-gnu/release");
+        assert!(entries[0].test.is_some());
+
+        let file =
+            CICDGenerator::generate(temp_dir.path(), CiProvider::GitHub, &CiOptions::default())
+                .await
+                .unwrap();
+        let workflow = std::fs::read_to_string(file).unwrap();
+        assert!(workflow.contains("artifact: \"demo-x86_64-unknown-linux-gnu-prod-default\""));
+        assert!(workflow.contains("hashFiles('**/Cargo.lock', 'forgekit.lock')"));
+
+        let file =
+            CICDGenerator::generate(temp_dir.path(), CiProvider::GitLab, &CiOptions::default())
+                .await
+                .unwrap();
+        let pipeline = std::fs::read_to_string(file).unwrap();
+        assert!(pipeline.contains("build:demo-aarch64-unknown-linux-gnu-prod-telemetry:"));
+        assert!(!pipeline.contains("test:demo-aarch64-unknown-linux-gnu-prod-telemetry:"));
+        assert!(pipeline.contains("apt-get install -y gcc-aarch64-linux-gnu"));
+
+        config.ci.profiles = vec!["staging".to_string()];
+        assert!(matrix(&config).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_linux_runners_skip_targets_they_cannot_link() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = ProjectConfig {
+            name: "demo".to_string(),
+            ..Default::default()
+        };
+        config.ci.targets = vec![
+            "x86_64-unknown-linux-gnu".to_string(),
+            "aarch64-apple-darwin".to_string(),
+            "x86_64-pc-windows-msvc".to_string(),
+            "aarch64-linux-android".to_string(),
+        ];
+        config.save(temp_dir.path().join("forgekit.toml")).unwrap();
+
+        let file =
+            CICDGenerator::generate(temp_dir.path(), CiProvider::GitLab, &CiOptions::default())
+                .await
+                .unwrap();
+        let pipeline = std::fs::read_to_string(file).unwrap();
+        assert!(pipeline.contains("build:demo-x86_64-unknown-linux-gnu:"));
+        assert!(pipeline.contains("# Skipped aarch64-apple-darwin: "));
+        assert!(!pipeline.contains("build:demo-aarch64-apple-darwin:"));
+        assert!(!pipeline.contains("--target x86_64-pc-windows-msvc"));
+
+        let file =
+            CICDGenerator::generate(temp_dir.path(), CiProvider::Jenkins, &CiOptions::default())
+                .await
+                .unwrap();
+        let pipeline = std::fs::read_to_string(file).unwrap();
+        assert!(pipeline.contains("// Skipped x86_64-pc-windows-msvc: "));
+        assert!(!pipeline.contains("--target aarch64-apple-darwin"));
+
+        let file =
+            CICDGenerator::generate(temp_dir.path(), CiProvider::GitHub, &CiOptions::default())
+                .await
+                .unwrap();
+        let workflow = std::fs::read_to_string(file).unwrap();
+        assert!(workflow.contains("os: macos-latest"));
+        assert!(workflow.contains("os: windows-latest"));
+        assert!(workflow.contains("# Skipped aarch64-linux-android: "));
+    }
 }
@@ -0,0 +1,268 @@
+//! Code generation module
+//!
+//! This module runs the generators declared in the `[[codegen]]` sections of
+//! forgekit.toml (protoc with prost for `.proto`, flatc for `.fbs`, or a
+//! custom command) before a build. Each generator records a fingerprint of
+//! its schemas and the files it produced so unchanged schemas are skipped.
+
+use crate::config::{GeneratorConfig, GeneratorKind, ProjectConfig};
+use crate::error::ForgeKitError;
+use crate::hooks::shell;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// Outputs recorded for a generator run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CodegenState {
+    /// Fingerprint of the generator settings and schema files
+    pub fingerprint: String,
+    /// Generated files, relative to the project
+    pub outputs: Vec<PathBuf>,
+}
+
+/// Result of running all generators
+#[derive(Debug, Clone, Default)]
+pub struct CodegenReport {
+    /// Generators that were run
+    pub ran: usize,
+    /// Generators skipped because their schemas were unchanged
+    pub skipped: usize,
+    /// Files produced by the generators that ran
+    pub generated: Vec<PathBuf>,
+}
+
+/// Code generation runner
+pub struct Codegen;
+
+impl Codegen {
+    /// Run every generator configured for a project
+    pub async fn run(
+        project_path: &Path,
+        config: &ProjectConfig,
+    ) -> Result<CodegenReport, ForgeKitError> {
+        let mut report = CodegenReport::default();
+
+        for (index, generator) in config.codegen.iter().enumerate() {
+            let state_file = state_dir(project_path).join(format!("{}.json", index));
+            let fingerprint = fingerprint(project_path, generator)?;
+            let previous = load_state(&state_file);
+
+            if let Some(previous) = previous {
+                let outputs_exist = previous
+                    .outputs
+                    .iter()
+                    .all(|p| project_path.join(p).exists());
+                if previous.fingerprint == fingerprint && outputs_exist {
+                    tracing::info!("Generated code for {} is up to date", generator.schema_dir);
+                    report.skipped += 1;
+                    continue;
+                }
+            }
+
+            let outputs = Self::run_generator(project_path, generator).await?;
+            let state = CodegenState {
+                fingerprint,
+                outputs: outputs.clone(),
+            };
+            std::fs::create_dir_all(state_dir(project_path))?;
+            std::fs::write(&state_file, serde_json::to_string_pretty(&state)?)?;
+
+            report.ran += 1;
+            report.generated.extend(outputs);
+        }
+
+        Ok(report)
+    }
+
+    /// Files produced by previous generator runs
+    pub fn outputs(project_path: &Path) -> Vec<PathBuf> {
+        let Ok(entries) = std::fs::read_dir(state_dir(project_path)) else {
+            return Vec::new();
+        };
+
+        let mut outputs: Vec<PathBuf> = entries
+            .flatten()
+            .filter_map(|e| load_state(&e.path()))
+            .flat_map(|s| s.outputs)
+            .collect();
+        outputs.sort();
+        outputs
+    }
+
+    /// Run a single generator and return the files it produced
+    async fn run_generator(
+        project_path: &Path,
+        generator: &GeneratorConfig,
+    ) -> Result<Vec<PathBuf>, ForgeKitError> {
+        let schema_dir = project_path.join(&generator.schema_dir);
+        let output_dir = project_path.join(&generator.output_dir);
+        std::fs::create_dir_all(&output_dir)?;
+
+        let mut command = match generator.kind {
+            GeneratorKind::Protobuf => {
+                let mut cmd = Command::new("protoc");
+                cmd.arg(format!("--prost_out={}", generator.output_dir))
+                    .arg("-I")
+                    .arg(&generator.schema_dir)
+                    .args(schema_files(&schema_dir, "proto")?);
+                cmd
+            }
+            GeneratorKind::Flatbuffers => {
+                let mut cmd = Command::new("flatc");
+                cmd.args(["--rust", "-o", &generator.output_dir])
+                    .args(schema_files(&schema_dir, "fbs")?);
+                cmd
+            }
+            GeneratorKind::Custom => {
+                let command = generator.command.as_deref().ok_or_else(|| {
+                    ForgeKitError::InvalidConfig(format!(
+                        "custom generator for {} has no command",
+                        generator.schema_dir
+                    ))
+                })?;
+                let mut cmd = shell(command);
+                cmd.env("SCHEMA_DIR", &generator.schema_dir)
+                    .env("OUTPUT_DIR", &generator.output_dir);
+                cmd
+            }
+        };
+
+        tracing::info!(
+            "Generating code from {} into {}",
+            generator.schema_dir,
+            generator.output_dir
+        );
+        let output = command
+            .current_dir(project_path)
+            .output()
+            .await
+            .map_err(|e| {
+                ForgeKitError::CodegenFailed(format!("failed to start generator: {}", e))
+            })?;
+
+        if !output.status.success() {
+            return Err(ForgeKitError::CodegenFailed(
+                String::from_utf8_lossy(&output.stderr).to_string(),
+            ));
+        }
+
+        let mut outputs: Vec<PathBuf> = walkdir::WalkDir::new(&output_dir)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter_map(|e| {
+                e.path()
+                    .strip_prefix(project_path)
+                    .ok()
+                    .map(Path::to_path_buf)
+            })
+            .collect();
+        outputs.sort();
+        Ok(outputs)
+    }
+}
+
+fn state_dir(project_path: &Path) -> PathBuf {
+    project_path.join("target").join("forgekit").join("codegen")
+}
+
+fn load_state(path: &Path) -> Option<CodegenState> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+/// Schema files with the given extension, relative to the schema directory
+fn schema_files(schema_dir: &Path, extension: &str) -> Result<Vec<PathBuf>, ForgeKitError> {
+    if !schema_dir.exists() {
+        return Err(ForgeKitError::CodegenFailed(format!(
+            "schema directory {:?} does not exist",
+            schema_dir
+        )));
+    }
+
+    let mut files: Vec<PathBuf> = walkdir::WalkDir::new(schema_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == extension))
+        .filter_map(|e| {
+            e.path()
+                .strip_prefix(schema_dir)
+                .ok()
+                .map(Path::to_path_buf)
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Hash the generator settings together with every schema file
+fn fingerprint(project_path: &Path, generator: &GeneratorConfig) -> Result<String, ForgeKitError> {
+    let mut hasher = Sha256::new();
+    hasher.update(serde_json::to_vec(generator)?);
+
+    let schema_dir = project_path.join(&generator.schema_dir);
+    let mut files: Vec<PathBuf> = walkdir::WalkDir::new(&schema_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.path().to_path_buf())
+        .collect();
+    files.sort();
+
+    for file in files {
+        hasher.update(file.to_string_lossy().as_bytes());
+        hasher.update(std::fs::read(&file)?);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn custom_project(temp_dir: &TempDir) -> ProjectConfig {
+        std::fs::create_dir_all(temp_dir.path().join("schemas")).unwrap();
+        std::fs::write(temp_dir.path().join("schemas").join("api.txt"), "v1").unwrap();
+
+        ProjectConfig {
+            codegen: vec![GeneratorConfig {
+                kind: GeneratorKind::Custom,
+                schema_dir: "schemas".to_string(),
+                output_dir: "src/generated".to_string(),
+                command: Some("cp $SCHEMA_DIR/api.txt $OUTPUT_DIR/api.rs".to_string()),
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_generator_tracks_outputs() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = custom_project(&temp_dir);
+
+        let report = Codegen::run(temp_dir.path(), &config).await.unwrap();
+        assert_eq!(report.ran, 1);
+        assert_eq!(
+            Codegen::outputs(temp_dir.path()),
+            vec![PathBuf::from("src/generated/api.rs")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_schemas_are_skipped() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = custom_project(&temp_dir);
+
+        Codegen::run(temp_dir.path(), &config).await.unwrap();
+        let report = Codegen::run(temp_dir.path(), &config).await.unwrap();
+        assert_eq!(report.skipped, 1);
+
+        std::fs::write(temp_dir.path().join("schemas").join("api.txt"), "v2").unwrap();
+        let report = Codegen::run(temp_dir.path(), &config).await.unwrap();
+        assert_eq!(report.ran, 1);
+    }
+}
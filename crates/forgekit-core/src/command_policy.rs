@@ -0,0 +1,397 @@
+//! Command policy module
+//!
+//! On shared machines like CI runners or production hosts, `[command_policy]`
+//! refuses commands, or makes them ask for confirmation, depending on the
+//! environment the machine belongs to. The environment is the one set in the
+//! organization-wide configuration, which users cannot edit; without one,
+//! the one a command targets with `--env`, else `FORGEKIT_ENV`, else the
+//! user's `[command_policy] environment`. Rules of the organization come
+//! before the user's own.
+//!
+//! A restricted command still runs with an approval token: a short-lived
+//! grant for one command in one environment, signed with an Ed25519 key
+//! whose public half is listed in `approval_keys`. Only the organization's
+//! keys approve commands its rules restrict. Tokens are issued with
+//! `forgekit approval issue` and passed with `--approval` or
+//! `FORGEKIT_APPROVAL`.
+
+use crate::atomic;
+use crate::audit_log;
+use crate::config::{CommandAction, CommandPolicyConfig, CommandRule, GlobalConfig};
+use crate::env_manager::ENVIRONMENT_VAR;
+use crate::error::ForgeKitError;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, SecondsFormat, Utc};
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Environment variable holding an approval token
+pub const APPROVAL_VAR: &str = "FORGEKIT_APPROVAL";
+
+/// What the policy says about running a command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    /// The command runs
+    Allow,
+    /// The command runs once the user confirms; holds the reason
+    Confirm(String),
+    /// The command does not run without approval; holds the reason
+    Deny(String),
+}
+
+/// Rules restricting commands
+#[derive(Debug, Clone, Default)]
+pub struct CommandPolicy {
+    org: CommandPolicyConfig,
+    user: CommandPolicyConfig,
+}
+
+impl CommandPolicy {
+    /// Policy with the organization's settings followed by the user's
+    pub fn new(org: CommandPolicyConfig, user: CommandPolicyConfig) -> Self {
+        Self { org, user }
+    }
+
+    /// Policy of the organization-wide configuration followed by the user's
+    pub fn load() -> Result<Self, ForgeKitError> {
+        let org = GlobalConfig::load(GlobalConfig::org_path())?.command_policy;
+        let user = GlobalConfig::load(GlobalConfig::default_path())?.command_policy;
+        Ok(Self::new(org, user))
+    }
+
+    /// Environment a command runs in: the organization's, else the one it
+    /// targets, else `FORGEKIT_ENV`, else the user's
+    pub fn environment(&self, requested: Option<&str>) -> Option<String> {
+        self.org
+            .environment
+            .clone()
+            .or_else(|| requested.map(str::to_string))
+            .or_else(|| {
+                std::env::var(ENVIRONMENT_VAR)
+                    .ok()
+                    .filter(|env| !env.is_empty())
+            })
+            .or_else(|| self.user.environment.clone())
+    }
+
+    /// First rule restricting `command` in `environment`, and whether it
+    /// comes from the organization
+    fn rule(&self, command: &str, environment: Option<&str>) -> Option<(&CommandRule, bool)> {
+        let applies = |rule: &&CommandRule| rule_applies(rule, command, environment);
+        self.org
+            .rules
+            .iter()
+            .find(applies)
+            .map(|rule| (rule, true))
+            .or_else(|| {
+                self.user
+                    .rules
+                    .iter()
+                    .find(applies)
+                    .map(|rule| (rule, false))
+            })
+    }
+
+    /// Whether `command` may run in `environment`
+    pub fn check(&self, command: &str, environment: Option<&str>) -> Decision {
+        let Some((rule, _)) = self.rule(command, environment) else {
+            return Decision::Allow;
+        };
+
+        let reason = rule.reason.clone().unwrap_or_else(|| match environment {
+            Some(environment) => format!("`{}` is restricted in {}", command, environment),
+            None => format!("`{}` is restricted on this machine", command),
+        });
+        match rule.action {
+            CommandAction::Confirm => Decision::Confirm(reason),
+            CommandAction::Deny => Decision::Deny(reason),
+        }
+    }
+
+    /// Check an approval token for running `command` in `environment`
+    pub fn verify_approval(
+        &self,
+        token: &str,
+        command: &str,
+        environment: Option<&str>,
+    ) -> Result<Approval, ForgeKitError> {
+        // The user's keys cannot lift the organization's restrictions
+        let keys = match self.rule(command, environment) {
+            Some((_, true)) => self.org.approval_keys.clone(),
+            _ => [&self.org.approval_keys[..], &self.user.approval_keys[..]].concat(),
+        };
+        let approval = Approval::verify(token, &keys)?;
+        let denied = |why: String| ForgeKitError::CommandDenied(format!("approval {}", why));
+        if !command_matches(&approval.command, command) {
+            return Err(denied(format!("is for `{}`", approval.command)));
+        }
+        if let Some(approved) = &approval.environment {
+            if !environment.is_some_and(|env| env.eq_ignore_ascii_case(approved)) {
+                return Err(denied(format!("is for the {} environment", approved)));
+            }
+        }
+        if approval.expired() {
+            return Err(denied(format!("expired at {}", approval.expires_at)));
+        }
+        Ok(approval)
+    }
+}
+
+fn rule_applies(rule: &CommandRule, command: &str, environment: Option<&str>) -> bool {
+    command_matches(&rule.command, command)
+        && (rule.environments.is_empty()
+            || environment.is_some_and(|environment| {
+                rule.environments
+                    .iter()
+                    .any(|env| env.eq_ignore_ascii_case(environment))
+            }))
+}
+
+/// Whether `pattern`, a command name or `*` for any command or remaining
+/// words like `secret *`, matches `command`
+pub fn command_matches(pattern: &str, command: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => command.starts_with(prefix),
+        None => pattern == command,
+    }
+}
+
+/// Grant to run a restricted command
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Approval {
+    /// Command, or commands like `secret *`, that may run
+    pub command: String,
+    /// Environment the command may run in, any when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<String>,
+    /// Who approved
+    pub approver: String,
+    /// When the approval expires (RFC 3339)
+    pub expires_at: String,
+}
+
+impl Approval {
+    /// Approval of `command` in `environment` valid for an age like `1h`
+    pub fn new(
+        command: &str,
+        environment: Option<&str>,
+        approver: &str,
+        valid_for: &str,
+    ) -> Result<Self, ForgeKitError> {
+        let expires_at = Utc::now() + audit_log::parse_age(valid_for)?;
+        Ok(Self {
+            command: command.to_string(),
+            environment: environment.map(str::to_string),
+            approver: approver.to_string(),
+            expires_at: expires_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+        })
+    }
+
+    /// Whether the approval is no longer valid
+    pub fn expired(&self) -> bool {
+        DateTime::parse_from_rfc3339(&self.expires_at)
+            .map_or(true, |expires_at| expires_at <= Utc::now())
+    }
+
+    /// Token holding the approval, signed with `key`
+    pub fn sign(&self, key: &Ed25519KeyPair) -> Result<String, ForgeKitError> {
+        let payload = serde_json::to_vec(self)?;
+        let signature = key.sign(&payload);
+        Ok(format!(
+            "{}.{}",
+            general_purpose::URL_SAFE_NO_PAD.encode(&payload),
+            general_purpose::URL_SAFE_NO_PAD.encode(signature.as_ref())
+        ))
+    }
+
+    /// Approval in `token`, if signed with one of `keys` (base64 Ed25519
+    /// public keys)
+    pub fn verify(token: &str, keys: &[String]) -> Result<Self, ForgeKitError> {
+        let invalid = |why: &str| ForgeKitError::CommandDenied(format!("approval token {}", why));
+        let (payload, signature) = token
+            .trim()
+            .split_once('.')
+            .ok_or_else(|| invalid("is malformed"))?;
+        let payload = general_purpose::URL_SAFE_NO_PAD
+            .decode(payload)
+            .map_err(|_| invalid("is malformed"))?;
+        let signature = general_purpose::URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| invalid("is malformed"))?;
+
+        let trusted = keys.iter().any(|key| {
+            general_purpose::STANDARD
+                .decode(key.trim())
+                .is_ok_and(|key| {
+                    ring::signature::UnparsedPublicKey::new(&ring::signature::ED25519, key)
+                        .verify(&payload, &signature)
+                        .is_ok()
+                })
+        });
+        if !trusted {
+            return Err(invalid(
+                "is not signed with a key of [command_policy] approval_keys",
+            ));
+        }
+        serde_json::from_slice(&payload).map_err(|_| invalid("is malformed"))
+    }
+}
+
+/// Generate an approval signing key at `path`, returning its base64 public
+/// key for `approval_keys`
+pub fn generate_key(path: &Path) -> Result<String, ForgeKitError> {
+    if path.exists() {
+        return Err(ForgeKitError::InvalidConfig(format!(
+            "{} already exists",
+            path.display()
+        )));
+    }
+    let pkcs8 = Ed25519KeyPair::generate_pkcs8(&ring::rand::SystemRandom::new())
+        .map_err(|_| ForgeKitError::Io(std::io::Error::other("cannot generate a signing key")))?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    atomic::write_private(path, pkcs8.as_ref())?;
+    let key = load_key(path)?;
+    Ok(general_purpose::STANDARD.encode(key.public_key().as_ref()))
+}
+
+/// Approval signing key stored at `path`
+pub fn load_key(path: &Path) -> Result<Ed25519KeyPair, ForgeKitError> {
+    Ed25519KeyPair::from_pkcs8(&std::fs::read(path)?).map_err(|e| {
+        ForgeKitError::InvalidConfig(format!("Invalid signing key {}: {}", path.display(), e))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn rule(command: &str, environments: &[&str], action: CommandAction) -> CommandRule {
+        CommandRule {
+            command: command.to_string(),
+            environments: environments.iter().map(|env| env.to_string()).collect(),
+            action,
+            reason: None,
+        }
+    }
+
+    #[test]
+    fn test_rules_depend_on_command_and_environment() {
+        let org = CommandPolicyConfig {
+            rules: vec![rule("publish", &["production"], CommandAction::Deny)],
+            ..Default::default()
+        };
+        let user = CommandPolicyConfig {
+            environment: Some("staging".to_string()),
+            rules: vec![
+                rule("publish", &[], CommandAction::Confirm),
+                rule("secret *", &["Production"], CommandAction::Confirm),
+            ],
+            ..Default::default()
+        };
+        let policy = CommandPolicy::new(org.clone(), user.clone());
+
+        assert!(matches!(
+            policy.check("publish", Some("production")),
+            Decision::Deny(_)
+        ));
+        assert!(matches!(
+            policy.check("publish", Some("staging")),
+            Decision::Confirm(_)
+        ));
+        assert!(matches!(
+            policy.check("secret delete", Some("production")),
+            Decision::Confirm(_)
+        ));
+        assert_eq!(policy.check("secret delete", None), Decision::Allow);
+        assert_eq!(policy.check("build", Some("production")), Decision::Allow);
+        assert_eq!(policy.environment(Some("prod")).as_deref(), Some("prod"));
+
+        // The organization's environment marks the machine
+        let policy = CommandPolicy::new(
+            CommandPolicyConfig {
+                environment: Some("production".to_string()),
+                ..org
+            },
+            user,
+        );
+        assert_eq!(
+            policy.environment(Some("staging")).as_deref(),
+            Some("production")
+        );
+    }
+
+    #[test]
+    fn test_approval_tokens_are_checked() {
+        let temp_dir = TempDir::new().unwrap();
+        let key_path = temp_dir.path().join("approval.key");
+        let public_key = generate_key(&key_path).unwrap();
+        assert!(generate_key(&key_path).is_err());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&key_path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let key = load_key(&key_path).unwrap();
+        let policy = CommandPolicy::new(
+            CommandPolicyConfig {
+                approval_keys: vec![public_key],
+                ..Default::default()
+            },
+            CommandPolicyConfig::default(),
+        );
+
+        let approval = Approval::new("publish", Some("production"), "ops", "1h").unwrap();
+        let token = approval.sign(&key).unwrap();
+        assert_eq!(
+            policy
+                .verify_approval(&token, "publish", Some("production"))
+                .unwrap(),
+            approval
+        );
+        assert!(policy
+            .verify_approval(&token, "upgrade", Some("production"))
+            .is_err());
+        assert!(policy
+            .verify_approval(&token, "publish", Some("staging"))
+            .is_err());
+        assert!(policy.verify_approval(&token, "publish", None).is_err());
+
+        let mut expired = approval.clone();
+        expired.expires_at = "2000-01-01T00:00:00Z".to_string();
+        let token = expired.sign(&key).unwrap();
+        assert!(policy
+            .verify_approval(&token, "publish", Some("production"))
+            .is_err());
+
+        // Signed with a key that is not trusted
+        let other = temp_dir.path().join("other.key");
+        let other_public_key = generate_key(&other).unwrap();
+        let token = approval.sign(&load_key(&other).unwrap()).unwrap();
+        assert!(policy
+            .verify_approval(&token, "publish", Some("production"))
+            .is_err());
+
+        // A user's key approves the user's rules but not the organization's
+        let org = CommandPolicyConfig {
+            rules: vec![rule("publish", &[], CommandAction::Deny)],
+            ..Default::default()
+        };
+        let user = CommandPolicyConfig {
+            approval_keys: vec![other_public_key],
+            rules: vec![rule("upgrade", &[], CommandAction::Deny)],
+            ..Default::default()
+        };
+        let policy = CommandPolicy::new(org, user);
+        assert!(policy
+            .verify_approval(&token, "publish", Some("production"))
+            .is_err());
+        let approval = Approval::new("upgrade", None, "me", "1h").unwrap();
+        let token = approval.sign(&load_key(&other).unwrap()).unwrap();
+        assert!(policy.verify_approval(&token, "upgrade", None).is_ok());
+    }
+}
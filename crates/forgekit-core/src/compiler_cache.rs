@@ -0,0 +1,212 @@
+//! Compiler cache module
+//!
+//! This module configures compiler caching for cargo builds. Builds can be
+//! wrapped with sccache (`RUSTC_WRAPPER`) or use ForgeKit's builtin cache, a
+//! cargo target directory shared by every project. Cache hits and misses of
+//! a build are collected for the build summary.
+
+use crate::config::{CompilerCacheConfig, CompilerCacheMode};
+use crate::error::ForgeKitError;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// Cache hits and misses of one build
+#[derive(Debug, Clone, Serialize)]
+pub struct CompilerCacheStats {
+    /// Backend that served the build (`sccache` or `builtin`)
+    pub backend: String,
+    /// Compilations served from the cache
+    pub hits: u64,
+    /// Compilations that ran rustc
+    pub misses: u64,
+}
+
+impl CompilerCacheStats {
+    /// Fraction of compilations served from the cache (0.0 to 1.0)
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+/// Compiler cache resolved for a build
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompilerCache {
+    /// No compiler cache
+    Off,
+    /// sccache wraps rustc
+    Sccache {
+        /// Cache directory, or sccache's default
+        dir: Option<PathBuf>,
+    },
+    /// Shared cargo target directory
+    Builtin {
+        /// Target directory shared by all projects
+        target_dir: PathBuf,
+    },
+}
+
+impl CompilerCache {
+    /// Resolve the cache to use from the configured mode
+    ///
+    /// `auto` picks sccache when it is on the PATH. Requesting sccache
+    /// explicitly fails if it is not installed.
+    pub async fn resolve(config: &CompilerCacheConfig) -> Result<Self, ForgeKitError> {
+        match config.mode {
+            CompilerCacheMode::Off => Ok(Self::Off),
+            CompilerCacheMode::Auto if !sccache_available().await => Ok(Self::Off),
+            CompilerCacheMode::Auto => Ok(Self::Sccache {
+                dir: config.dir.clone(),
+            }),
+            CompilerCacheMode::Sccache if !sccache_available().await => Err(
+                ForgeKitError::BuildFailed("sccache is not installed".to_string()),
+            ),
+            CompilerCacheMode::Sccache => Ok(Self::Sccache {
+                dir: config.dir.clone(),
+            }),
+            CompilerCacheMode::Builtin => Ok(Self::Builtin {
+                target_dir: config.dir.clone().unwrap_or_else(default_target_dir),
+            }),
+        }
+    }
+
+    /// Target directory cargo builds into, when the cache moves it
+    pub fn target_dir(&self) -> Option<&Path> {
+        match self {
+            Self::Builtin { target_dir } => Some(target_dir),
+            _ => None,
+        }
+    }
+
+    /// Configure a cargo command to use the cache
+    ///
+    /// An explicit `CARGO_TARGET_DIR` set by the caller takes precedence over
+    /// the builtin cache's shared directory.
+    pub async fn prepare(&self, command: &mut Command, has_target_dir: bool) {
+        match self {
+            Self::Off => {}
+            Self::Sccache { dir } => {
+                command.env("RUSTC_WRAPPER", "sccache");
+                if let Some(dir) = dir {
+                    command.env("SCCACHE_DIR", dir);
+                }
+                // Count only this build's compilations
+                let _ = Command::new("sccache").arg("--zero-stats").output().await;
+            }
+            Self::Builtin { target_dir } => {
+                if !has_target_dir {
+                    command.env("CARGO_TARGET_DIR", target_dir);
+                }
+                // Verbose output lists up-to-date units as `Fresh`
+                command.arg("--verbose");
+            }
+        }
+    }
+
+    /// Cache statistics of the build that just finished
+    pub async fn stats(&self, cargo_stderr: &str) -> Option<CompilerCacheStats> {
+        match self {
+            Self::Off => None,
+            Self::Sccache { .. } => {
+                let output = Command::new("sccache")
+                    .args(["--show-stats", "--stats-format", "json"])
+                    .output()
+                    .await
+                    .ok()?;
+                let stats: serde_json::Value = serde_json::from_slice(&output.stdout).ok()?;
+                let (hits, misses) = parse_sccache_stats(&stats);
+                Some(CompilerCacheStats {
+                    backend: "sccache".to_string(),
+                    hits,
+                    misses,
+                })
+            }
+            Self::Builtin { .. } => {
+                let (hits, misses) = count_cargo_units(cargo_stderr);
+                Some(CompilerCacheStats {
+                    backend: "builtin".to_string(),
+                    hits,
+                    misses,
+                })
+            }
+        }
+    }
+}
+
+/// Whether sccache is installed
+pub async fn sccache_available() -> bool {
+    Command::new("sccache")
+        .arg("--version")
+        .output()
+        .await
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+/// Default location of the builtin cache's shared target directory
+fn default_target_dir() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("forgekit")
+        .join("target")
+}
+
+/// Total hits and misses from `sccache --show-stats --stats-format json`
+fn parse_sccache_stats(stats: &serde_json::Value) -> (u64, u64) {
+    let total = |field: &str| {
+        stats["stats"][field]["counts"]
+            .as_object()
+            .map(|counts| counts.values().filter_map(|v| v.as_u64()).sum())
+            .unwrap_or(0)
+    };
+    (total("cache_hits"), total("cache_misses"))
+}
+
+/// Count up-to-date (`Fresh`) and rebuilt (`Compiling`) units in verbose cargo output
+fn count_cargo_units(stderr: &str) -> (u64, u64) {
+    let mut fresh = 0;
+    let mut compiled = 0;
+    for line in stderr.lines().map(str::trim_start) {
+        if line.starts_with("Fresh ") {
+            fresh += 1;
+        } else if line.starts_with("Compiling ") {
+            compiled += 1;
+        }
+    }
+    (fresh, compiled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sccache_stats() {
+        let stats = serde_json::json!({
+            "stats": {
+                "cache_hits": { "counts": { "Rust": 30, "C/C++": 2 } },
+                "cache_misses": { "counts": { "Rust": 8 } }
+            }
+        });
+        assert_eq!(parse_sccache_stats(&stats), (32, 8));
+        assert_eq!(parse_sccache_stats(&serde_json::json!({})), (0, 0));
+    }
+
+    #[test]
+    fn test_count_cargo_units() {
+        let stderr = "       Fresh serde v1.0.0\n       Fresh toml v0.8.0\n   Compiling demo v0.1.0 (/tmp/demo)\n     Running `rustc ...`\n    Finished release\n";
+        let (hits, misses) = count_cargo_units(stderr);
+        let stats = CompilerCacheStats {
+            backend: "builtin".to_string(),
+            hits,
+            misses,
+        };
+        assert_eq!((stats.hits, stats.misses), (2, 1));
+        assert!((stats.hit_rate() - 2.0 / 3.0).abs() < 1e-9);
+    }
+}
@@ -1,23 +1,141 @@
 //! Project configuration handling
+//!
+//! A forgekit.toml may build on shared configuration: `extends` names a base
+//! file and `include` lists fragments, both relative to the file declaring
+//! them. Layers are merged in order (the base, then each fragment, then the
+//! file itself) so later layers win. Tables are merged key by key, arrays of
+//! tables with a `name` key such as `[[dependencies]]` are merged entry by
+//! entry, and any other value replaces the inherited one. An entry with
+//! `removed = true` drops the inherited entry of the same name.
+//!
+//! `[env.<name>]` sections hold overrides for one environment profile, e.g.
+//! a higher `opt_level` or other features for `prod`. They are kept apart
+//! when loading and merged in the same way by [`ProjectConfig::resolve`] for
+//! the environment a build runs with.
 
+use crate::error::ForgeKitError;
+use crate::plugin::Hook;
+use crate::toolchain;
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 /// Project configuration stored in forgekit.toml
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProjectConfig {
+    /// Base configuration this one extends, relative to this file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+    /// Configuration fragments merged in after the base, relative to this file
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
     /// Project name
     pub name: String,
     /// Project version
     pub version: String,
+    /// Whether the project is an application or a reusable library
+    #[serde(default, skip_serializing_if = "ProjectKind::is_app")]
+    pub kind: ProjectKind,
     /// Project description
     pub description: Option<String>,
+    /// What's new in this version, for devices and app stores to display
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release_notes: Option<String>,
     /// Authors
     pub authors: Vec<String>,
     /// Dependencies
     pub dependencies: Vec<Dependency>,
     /// Build settings
     pub build: BuildConfig,
+    /// Rust toolchain pinned for everyone building the project
+    #[serde(default, skip_serializing_if = "RustToolchainConfig::is_empty")]
+    pub toolchain: RustToolchainConfig,
+    /// Commands run around build and packaging
+    #[serde(default, skip_serializing_if = "HooksConfig::is_empty")]
+    pub hooks: HooksConfig,
+    /// Project scripts exposed as recipes by `forgekit sync-scripts`, as
+    /// shell commands keyed by name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub scripts: BTreeMap<String, String>,
+    /// Code generators run before build
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub codegen: Vec<GeneratorConfig>,
+    /// Lint settings
+    #[serde(default, skip_serializing_if = "LintConfig::is_default")]
+    pub lint: LintConfig,
+    /// License header required at the top of source files
+    #[serde(default, skip_serializing_if = "LicenseConfig::is_empty")]
+    pub license: LicenseConfig,
+    /// Test settings
+    #[serde(default, skip_serializing_if = "TestConfig::is_default")]
+    pub test: TestConfig,
+    /// Build matrix of the pipelines generated by `forgekit ci`
+    #[serde(default, skip_serializing_if = "CiConfig::is_empty")]
+    pub ci: CiConfig,
+    /// Git hooks managed by `forgekit hooks install`
+    #[serde(default, skip_serializing_if = "GitHooksConfig::is_empty")]
+    pub git_hooks: GitHooksConfig,
+    /// Icon, launcher entry and splash generated by `forgekit appmeta`
+    #[serde(default, skip_serializing_if = "AppMetaConfig::is_empty")]
+    pub appmeta: AppMetaConfig,
+    /// Store listing bundled by `forgekit appstore`
+    #[serde(default, skip_serializing_if = "AppStoreConfig::is_empty")]
+    pub appstore: AppStoreConfig,
+    /// Over-the-air update channels and feed
+    #[serde(default, skip_serializing_if = "OtaConfig::is_empty")]
+    pub ota: OtaConfig,
+    /// Permissions requested from Ledokoz OS at install time
+    #[serde(default, skip_serializing_if = "PermissionsConfig::is_empty")]
+    pub permissions: PermissionsConfig,
+    /// Environment variables whose values are masked in output
+    #[serde(default, skip_serializing_if = "RedactConfig::is_empty")]
+    pub redact: RedactConfig,
+    /// Where mutating commands are recorded
+    #[serde(default, skip_serializing_if = "AuditLogConfig::is_default")]
+    pub audit_log: AuditLogConfig,
+    /// Services the app runs, keyed by name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub services: BTreeMap<String, ServiceConfig>,
+    /// Settings for `forgekit dev`
+    #[serde(default, skip_serializing_if = "DevConfig::is_default")]
+    pub dev: DevConfig,
+    /// Template the project was generated from, for `forgekit template update`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<TemplateConfig>,
+    /// Marks a library as a ForgeKit plugin, listed by `forgekit plugin search`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plugin: Option<PluginConfig>,
+    /// How the plugins loaded for the project are run
+    #[serde(default, skip_serializing_if = "PluginsConfig::is_default")]
+    pub plugins: PluginsConfig,
+    /// Overrides for every version of a dependency, keyed by package name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub patch: BTreeMap<String, PatchSource>,
+    /// Overrides for one exact dependency version, keyed by `name@version`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub replace: BTreeMap<String, PatchSource>,
+    /// Overrides applied for an environment profile, keyed by its name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, toml::Table>,
+}
+
+/// Kind of project, deciding what `forgekit package` produces
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectKind {
+    /// Application packaged as a .mox file
+    #[default]
+    App,
+    /// Library packaged as a .moxlib archive for other projects to depend on
+    Library,
+}
+
+impl ProjectKind {
+    /// Whether the project is an application
+    pub fn is_app(&self) -> bool {
+        *self == ProjectKind::App
+    }
 }
 
 /// Dependency specification
@@ -31,6 +149,228 @@ pub struct Dependency {
     pub source: Option<String>,
 }
 
+/// Replacement source for a dependency in `[patch]` or `[replace]`
+///
+/// Exactly one of `git` and `path` is set; `branch` and `rev` select a git
+/// revision.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PatchSource {
+    /// Git repository URL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git: Option<String>,
+    /// Git branch to check out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    /// Git commit to check out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+    /// Local directory, relative to the project
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+/// `[appmeta]` settings for launcher metadata
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppMetaConfig {
+    /// Source icon (square PNG, at least 512x512), relative to the project
+    pub icon: Option<String>,
+    /// Name shown in the launcher, defaulting to the project name
+    pub display_name: Option<String>,
+    /// Launcher categories
+    pub categories: Vec<String>,
+    /// Splash screen background as `#rrggbb`
+    pub splash_background: Option<String>,
+}
+
+impl AppMetaConfig {
+    /// Whether no launcher metadata is configured
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// `[appstore]` listing of an app in the Ledokoz store
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppStoreConfig {
+    /// Store category, like `productivity` or `games`
+    pub category: Option<String>,
+    /// Age rating: `everyone`, `7+`, `12+`, `16+` or `18+`
+    pub age_rating: Option<String>,
+    /// Screenshots (PNG), relative to the project, in display order
+    pub screenshots: Vec<String>,
+    /// Locale the listing falls back to, defaulting to `en`
+    pub default_locale: Option<String>,
+    /// URL of the privacy policy
+    pub privacy_policy: Option<String>,
+    /// Why each requested permission is needed, keyed by permission
+    pub privacy: BTreeMap<String, String>,
+}
+
+impl AppStoreConfig {
+    /// Whether no store listing is configured
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// `[ota]` update channels devices follow
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OtaConfig {
+    /// Channels the app publishes to, like `stable`, `beta` and `nightly`
+    pub channels: Vec<String>,
+    /// Channel packages are built for, defaulting to the first one
+    pub channel: Option<String>,
+    /// URL of the update feed devices poll
+    pub feed_url: Option<String>,
+    /// Ed25519 key (PKCS#8) signing the feed, relative to the project, or
+    /// `secret:<name>` to keep it in the credential store
+    pub signing_key: Option<String>,
+}
+
+impl OtaConfig {
+    /// Whether over-the-air updates are not configured
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// `[services.<name>]` run by an app
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServiceConfig {
+    /// Port the service listens on
+    pub port: u16,
+    /// Environment variable the port is read from, defaulting to `<NAME>_PORT`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<String>,
+}
+
+impl ServiceConfig {
+    /// Environment variable the service reads its port from
+    pub fn env_var(&self, name: &str) -> String {
+        self.env
+            .clone()
+            .unwrap_or_else(|| format!("{}_PORT", name.to_ascii_uppercase().replace('-', "_")))
+    }
+}
+
+/// Settings used by `forgekit dev`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DevConfig {
+    /// Port of the project's dev server when a workspace is served
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// Workspace members, e.g. a backend service, whose rebuild the
+    /// project's reload waits for
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub waits_for: Vec<String>,
+}
+
+impl DevConfig {
+    /// Whether the settings are the defaults
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// `[template]` the project was generated from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemplateConfig {
+    /// Template name, like `gui` or `service`
+    pub name: String,
+    /// Revision of the template the project's files were last updated to
+    pub version: u32,
+}
+
+/// `[plugin]` of a library published as a ForgeKit plugin
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PluginConfig {
+    /// Plugin API version the plugin is built against
+    pub api_version: u32,
+}
+
+/// `[plugins]` settings for running plugins, in forgekit.toml or the
+/// global configuration
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PluginsConfig {
+    /// Seconds each hook may run before the step fails
+    #[serde(skip_serializing_if = "HookTimeouts::is_default")]
+    pub timeouts: HookTimeouts,
+}
+
+impl PluginsConfig {
+    /// Whether the settings are the defaults
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// `[plugins.timeouts]` in seconds per hook; hooks without one may run as
+/// long as they need
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HookTimeouts {
+    /// Timeout of pre-build hooks
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pre_build: Option<u64>,
+    /// Timeout of post-build hooks
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_build: Option<u64>,
+    /// Timeout of package hooks
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package: Option<u64>,
+}
+
+impl HookTimeouts {
+    /// Whether no timeout is set
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Timeout configured for `hook`
+    pub fn get(&self, hook: Hook) -> Option<Duration> {
+        let seconds = match hook {
+            Hook::PreBuild => self.pre_build,
+            Hook::PostBuild => self.post_build,
+            Hook::Package => self.package,
+        };
+        seconds.map(Duration::from_secs)
+    }
+}
+
+/// `[permissions]` requested by an app
+///
+/// Ledokoz OS grants these at install time; anything not listed is denied.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PermissionsConfig {
+    /// Network access
+    pub network: bool,
+    /// Filesystem paths outside the app's own data directory
+    pub filesystem: Vec<String>,
+    /// Camera access
+    pub camera: bool,
+    /// Microphone access
+    pub microphone: bool,
+    /// Location access
+    pub location: bool,
+    /// Bluetooth access
+    pub bluetooth: bool,
+    /// Permission to show notifications
+    pub notifications: bool,
+}
+
+impl PermissionsConfig {
+    /// Whether no permission is requested
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
 /// Build configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BuildConfig {
@@ -40,40 +380,1311 @@ pub struct BuildConfig {
     pub opt_level: String,
     /// Additional rustc flags
     pub rustflags: Vec<String>,
+    /// Cargo features enabled for the build
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
     /// Output directory
     pub output_dir: String,
+    /// Sandbox for cargo and the build scripts of dependencies
+    #[serde(default, skip_serializing_if = "SandboxConfig::is_disabled")]
+    pub sandbox: SandboxConfig,
+    /// Debug symbols split from release binaries
+    #[serde(default, skip_serializing_if = "SymbolsConfig::is_default")]
+    pub symbols: SymbolsConfig,
+    /// Packaged .mox files kept in the artifact history
+    #[serde(default, skip_serializing_if = "ArtifactsConfig::is_default")]
+    pub artifacts: ArtifactsConfig,
+    /// Toolchain and target spec used for the ledokoz target
+    #[serde(default, skip_serializing_if = "ToolchainConfig::is_default")]
+    pub toolchain: ToolchainConfig,
+    /// Linker and code generation settings of cargo's release profile
+    #[serde(default, skip_serializing_if = "ReleaseProfileConfig::is_default")]
+    pub profile: ReleaseProfileConfig,
+}
+
+/// `[build.symbols]` settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SymbolsConfig {
+    /// Split debug info out of release binaries into `.forgekit/symbols/`
+    pub split: bool,
+    /// Symbol server `forgekit symbols upload` sends debug info to
+    pub server: Option<String>,
+}
+
+impl Default for SymbolsConfig {
+    fn default() -> Self {
+        Self {
+            split: true,
+            server: None,
+        }
+    }
+}
+
+impl SymbolsConfig {
+    /// Whether the settings are the defaults
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// `[build.toolchain]` settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToolchainConfig {
+    /// Rust toolchain building the ledokoz target; building std needs nightly
+    pub channel: String,
+    /// Target spec JSON relative to the project, replacing the shipped one
+    pub target_spec: Option<String>,
+    /// Triple built by `forgekit build --host`; the host's own when unset
+    pub fallback_target: Option<String>,
+}
+
+impl Default for ToolchainConfig {
+    fn default() -> Self {
+        Self {
+            channel: "nightly".to_string(),
+            target_spec: None,
+            fallback_target: None,
+        }
+    }
+}
+
+impl ToolchainConfig {
+    /// Whether the settings are the defaults
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The settings with the channel replaced by the project's pin, if any
+    ///
+    /// Only a nightly pin can build std, so other pins leave the channel.
+    pub fn with_pin(&self, pin: &RustToolchainConfig) -> Self {
+        let mut config = self.clone();
+        if let Some(channel) = &pin.channel {
+            if toolchain::is_nightly_channel(channel) {
+                config.channel = channel.clone();
+            }
+        }
+        config
+    }
+}
+
+/// `[toolchain]` settings
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RustToolchainConfig {
+    /// Toolchain builds must use, like `1.78`, `stable` or `nightly-2024-05-01`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    /// Whether builds keep rust-toolchain.toml in line with the pin
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub rust_toolchain_file: bool,
+}
+
+impl RustToolchainConfig {
+    /// Whether no toolchain is pinned
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// `[build.profile]` settings
+///
+/// Unset settings keep cargo's defaults for the release profile.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReleaseProfileConfig {
+    /// Linker used instead of the system one
+    pub linker: Option<Linker>,
+    /// Link-time optimization
+    pub lto: Option<LtoMode>,
+    /// Units a crate is split into for parallel code generation
+    pub codegen_units: Option<u32>,
+    /// What a panic does
+    pub panic: Option<PanicStrategy>,
+    /// What is stripped from the binary
+    pub strip: Option<StripMode>,
+}
+
+impl ReleaseProfileConfig {
+    /// Whether the settings are the defaults
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Linker of `[build.profile]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Linker {
+    /// LLVM's linker
+    Lld,
+    /// The mold linker
+    Mold,
+}
+
+impl Linker {
+    /// Name used in forgekit.toml and with `-fuse-ld`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Linker::Lld => "lld",
+            Linker::Mold => "mold",
+        }
+    }
+}
+
+/// Link-time optimization of `[build.profile]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LtoMode {
+    /// No link-time optimization
+    Off,
+    /// ThinLTO across all crates
+    Thin,
+    /// Full LTO across all crates
+    Fat,
+}
+
+impl LtoMode {
+    /// Name used in forgekit.toml and by cargo
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LtoMode::Off => "off",
+            LtoMode::Thin => "thin",
+            LtoMode::Fat => "fat",
+        }
+    }
+}
+
+/// Panic strategy of `[build.profile]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PanicStrategy {
+    /// Unwind the stack, running destructors
+    Unwind,
+    /// Abort the process
+    Abort,
+}
+
+impl PanicStrategy {
+    /// Name used in forgekit.toml and by cargo
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PanicStrategy::Unwind => "unwind",
+            PanicStrategy::Abort => "abort",
+        }
+    }
+}
+
+/// Stripping of `[build.profile]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StripMode {
+    /// Strip nothing
+    None,
+    /// Strip debug info
+    Debuginfo,
+    /// Strip debug info and symbols
+    Symbols,
+}
+
+impl StripMode {
+    /// Name used in forgekit.toml and by cargo
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StripMode::None => "none",
+            StripMode::Debuginfo => "debuginfo",
+            StripMode::Symbols => "symbols",
+        }
+    }
+}
+
+/// `[build.artifacts]` settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArtifactsConfig {
+    /// Packages kept per profile under `.forgekit/artifacts/`; 0 keeps none
+    pub keep: usize,
+}
+
+impl Default for ArtifactsConfig {
+    fn default() -> Self {
+        Self { keep: 5 }
+    }
+}
+
+impl ArtifactsConfig {
+    /// Whether the settings are the defaults
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// How builds are isolated from the host
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SandboxMode {
+    /// Run cargo directly
+    #[default]
+    None,
+    /// Run cargo with a scrubbed environment and a throwaway HOME
+    Env,
+    /// Run cargo in a user namespace (bubblewrap) that hides the home directory
+    Namespace,
+    /// Run cargo in a docker container that only sees the project
+    Docker,
+}
+
+/// `[build.sandbox]` settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SandboxConfig {
+    /// Isolation mechanism
+    pub mode: SandboxMode,
+    /// Extra environment variables passed into the sandbox
+    pub allow_env: Vec<String>,
+    /// Whether the sandbox may use the network (e.g. to fetch crates)
+    pub network: bool,
+    /// Docker image used by the `docker` mode
+    pub image: String,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            mode: SandboxMode::None,
+            allow_env: Vec::new(),
+            network: true,
+            image: crate::sandbox::DEFAULT_IMAGE.to_string(),
+        }
+    }
+}
+
+impl SandboxConfig {
+    /// Whether builds run unsandboxed
+    pub fn is_disabled(&self) -> bool {
+        self.mode == SandboxMode::None
+    }
+}
+
+/// Hook commands run before and after build, packaging and install
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HooksConfig {
+    /// Commands run before `cargo build`
+    pub pre_build: Vec<String>,
+    /// Commands run after a successful build
+    pub post_build: Vec<String>,
+    /// Commands run before the .mox archive is created
+    pub pre_package: Vec<String>,
+    /// Commands run after the .mox archive is created
+    pub post_package: Vec<String>,
+    /// Commands run from the extracted app before it is installed
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub pre_install: Vec<String>,
+    /// Commands run from the app directory once it is installed
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub post_install: Vec<String>,
+}
+
+impl HooksConfig {
+    /// Whether no hooks are configured
+    pub fn is_empty(&self) -> bool {
+        self.pre_build.is_empty()
+            && self.post_build.is_empty()
+            && self.pre_package.is_empty()
+            && self.post_package.is_empty()
+            && self.pre_install.is_empty()
+            && self.post_install.is_empty()
+    }
+}
+
+/// Kind of code generator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GeneratorKind {
+    /// Protocol Buffers via protoc and the prost plugin
+    Protobuf,
+    /// FlatBuffers via flatc
+    Flatbuffers,
+    /// Custom shell command
+    Custom,
+}
+
+/// A code generator declared in forgekit.toml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneratorConfig {
+    /// Generator kind
+    pub kind: GeneratorKind,
+    /// Directory containing schema files, relative to the project
+    pub schema_dir: String,
+    /// Directory receiving generated sources, relative to the project
+    pub output_dir: String,
+    /// Command for custom generators
+    pub command: Option<String>,
+}
+
+/// `[license]` header every source file starts with
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LicenseConfig {
+    /// Header text; `{year}`, `{author}` and `{name}` are filled in
+    pub header: Option<String>,
+    /// File holding the header text, relative to the project
+    pub header_file: Option<String>,
+    /// Copyright holder, defaulting to the project authors
+    pub author: Option<String>,
+}
+
+impl LicenseConfig {
+    /// Whether no license header is required
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Lint settings used by `forgekit lint`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LintConfig {
+    /// Clippy lint levels (`allow`, `warn`, `deny`) keyed by lint name
+    pub levels: BTreeMap<String, String>,
+    /// Lowest severity that fails the lint run (`error` or `warning`)
+    pub fail_on: String,
+    /// Run `cargo fmt --check`
+    pub rustfmt: bool,
+    /// Run `cargo clippy`
+    pub clippy: bool,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            levels: BTreeMap::new(),
+            fail_on: "error".to_string(),
+            rustfmt: true,
+            clippy: true,
+        }
+    }
+}
+
+impl LintConfig {
+    /// Whether the settings are the defaults
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Test settings used by `forgekit test`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TestConfig {
+    /// Times a failed test is rerun before it counts as failed
+    pub retries: u32,
+    /// Known flaky tests, as named by `cargo test`, whose failures are
+    /// reported without failing the run
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub quarantine: Vec<String>,
+}
+
+impl TestConfig {
+    /// Whether the settings are the defaults
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// `[ci]` build matrix of the generated pipelines
+///
+/// Pipelines build every combination of target, environment profile and
+/// feature set.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CiConfig {
+    /// Target triples to build for; the runner's own when empty
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<String>,
+    /// Environment profiles to build with; every `[env.<name>]` when empty
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub profiles: Vec<String>,
+    /// Sets of cargo features to build with; the profile's
+    /// `[build] features` when empty
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub feature_sets: Vec<Vec<String>>,
+}
+
+impl CiConfig {
+    /// Whether no matrix is declared
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// `[redact]` variables treated as secrets besides the built-in patterns
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedactConfig {
+    /// Variable names, or patterns with `*` like `STRIPE_*`, whose values
+    /// are masked in `env list`, build logs, crash reports and diagnostics
+    pub sensitive: Vec<String>,
+}
+
+impl RedactConfig {
+    /// Whether no extra variables are marked sensitive
+    pub fn is_empty(&self) -> bool {
+        self.sensitive.is_empty()
+    }
+}
+
+/// Destination of audit log entries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditSink {
+    /// `.forgekit/audit.log` of the project
+    #[default]
+    File,
+    /// The system log (unix only)
+    Syslog,
+    /// Nowhere
+    Off,
+}
+
+/// `[audit_log]` settings
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuditLogConfig {
+    /// Where entries are written
+    pub sink: AuditSink,
+}
+
+impl AuditLogConfig {
+    /// Whether the settings are the defaults
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Checks run by the git hooks ForgeKit installs
+///
+/// Each list names stages (`fmt`, `lint`, `validate`, `test-fast`) run in order.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GitHooksConfig {
+    /// Stages run by the `pre-commit` hook
+    pub pre_commit: Vec<String>,
+    /// Stages run by the `pre-push` hook
+    pub pre_push: Vec<String>,
+}
+
+impl GitHooksConfig {
+    /// Whether no git hooks are configured
+    pub fn is_empty(&self) -> bool {
+        self.pre_commit.is_empty() && self.pre_push.is_empty()
+    }
 }
 
 impl Default for ProjectConfig {
     fn default() -> Self {
         Self {
+            extends: None,
+            include: vec![],
             name: "unnamed".to_string(),
             version: "0.1.0".to_string(),
+            kind: ProjectKind::App,
             description: None,
+            release_notes: None,
             authors: vec![],
             dependencies: vec![],
             build: BuildConfig {
                 target: "ledokoz".to_string(),
                 opt_level: "2".to_string(),
                 rustflags: vec![],
+                features: vec![],
                 output_dir: "target".to_string(),
+                sandbox: SandboxConfig::default(),
+                symbols: SymbolsConfig::default(),
+                artifacts: ArtifactsConfig::default(),
+                toolchain: ToolchainConfig::default(),
+                profile: ReleaseProfileConfig::default(),
             },
+            toolchain: RustToolchainConfig::default(),
+            hooks: HooksConfig::default(),
+            scripts: BTreeMap::new(),
+            codegen: vec![],
+            lint: LintConfig::default(),
+            license: LicenseConfig::default(),
+            test: TestConfig::default(),
+            ci: CiConfig::default(),
+            git_hooks: GitHooksConfig::default(),
+            appmeta: AppMetaConfig::default(),
+            appstore: AppStoreConfig::default(),
+            ota: OtaConfig::default(),
+            permissions: PermissionsConfig::default(),
+            redact: RedactConfig::default(),
+            audit_log: AuditLogConfig::default(),
+            services: BTreeMap::new(),
+            dev: DevConfig::default(),
+            template: None,
+            plugin: None,
+            plugins: PluginsConfig::default(),
+            patch: BTreeMap::new(),
+            replace: BTreeMap::new(),
+            env: BTreeMap::new(),
         }
     }
 }
 
+/// How unknown keys in forgekit.toml, usually typos, are treated
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Strictness {
+    /// Log a warning for each unknown key
+    #[default]
+    Warn,
+    /// Refuse to load a configuration with unknown keys
+    Deny,
+}
+
 impl ProjectConfig {
-    /// Load configuration from a TOML file
-    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, crate::error::ForgeKitError> {
-        let contents = std::fs::read_to_string(path)?;
-        let config: Self = toml::from_str(&contents)?;
+    /// Load configuration from a TOML file, merging the layers it extends
+    ///
+    /// Unknown keys are logged as warnings.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ForgeKitError> {
+        Self::load_with(path, Strictness::Warn)
+    }
+
+    /// Load configuration, treating unknown keys as `strictness` says
+    pub fn load_with<P: AsRef<Path>>(
+        path: P,
+        strictness: Strictness,
+    ) -> Result<Self, ForgeKitError> {
+        let (config, unknown) = Self::load_reporting(path)?;
+        match strictness {
+            Strictness::Warn => unknown.iter().for_each(|key| tracing::warn!("{}", key)),
+            Strictness::Deny if !unknown.is_empty() => {
+                return Err(ForgeKitError::InvalidConfig(unknown.join("\n")));
+            }
+            Strictness::Deny => {}
+        }
         Ok(config)
     }
 
+    /// Load configuration along with its unknown keys, one message each
+    ///
+    /// Keys are checked in every layer, and each message names its file.
+    pub fn load_reporting<P: AsRef<Path>>(path: P) -> Result<(Self, Vec<String>), ForgeKitError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)?;
+        let own: toml::Value = toml::from_str(&contents)?;
+        let mut unknown = unknown_keys(path, &contents);
+        if !is_layered(&own) {
+            return Ok((toml::from_str(&contents)?, unknown));
+        }
+
+        let mut chain = vec![path.canonicalize()?];
+        let mut merged = inherited_layers(path, &own, &mut chain, &mut unknown)?;
+        merge_layer(&mut merged, own);
+        Ok((merged.try_into()?, unknown))
+    }
+
     /// Save configuration to a TOML file
+    ///
+    /// Values inherited through `extends` and `include` are left out, so only
+    /// what this file overrides is written back. Inherited entries this
+    /// configuration no longer has, like a removed dependency, are written
+    /// as `removed = true` entries so they stay removed on the next load.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ForgeKitError> {
+        let path = path.as_ref();
+        let contents = if self.extends.is_none() && self.include.is_empty() {
+            toml::to_string_pretty(self)?
+        } else {
+            let mut own = toml::Value::try_from(self)?;
+            let mut chain: Vec<PathBuf> = path.canonicalize().into_iter().collect();
+            let inherited = inherited_layers(path, &own, &mut chain, &mut Vec::new())?;
+            let removed = removed_entries(&own, &inherited);
+            strip_inherited(&mut own, &inherited);
+            if let Some(table) = own.as_table_mut() {
+                for (key, markers) in removed {
+                    let list = table
+                        .entry(key)
+                        .or_insert_with(|| toml::Value::Array(Vec::new()));
+                    if let Some(list) = list.as_array_mut() {
+                        list.extend(markers);
+                    }
+                }
+            }
+            toml::to_string_pretty(&own)?
+        };
+        crate::atomic::write(path, contents)?;
+        Ok(())
+    }
+
+    /// Configuration with the `[env.<name>]` overrides of `environment` applied
+    ///
+    /// An environment without a section, or no environment at all, leaves
+    /// the configuration as it is.
+    pub fn resolve(&self, environment: Option<&str>) -> Result<Self, ForgeKitError> {
+        let Some(overrides) = environment.and_then(|name| self.env.get(name)) else {
+            return Ok(self.clone());
+        };
+
+        let mut overrides = overrides.clone();
+        for key in ["env", "extends", "include"] {
+            overrides.remove(key);
+        }
+        let mut resolved = toml::Value::try_from(self)?;
+        merge_layer(&mut resolved, toml::Value::Table(overrides));
+        Ok(resolved.try_into()?)
+    }
+}
+
+/// Whether a configuration declares layers of its own
+fn is_layered(config: &toml::Value) -> bool {
+    config.get("extends").is_some() || config.get("include").is_some()
+}
+
+/// Merge the layers `config`, read from `path`, extends and includes
+///
+/// `chain` holds the files being resolved, so a file extending itself
+/// through any number of layers is reported instead of recursing forever.
+/// Unknown keys of the layers are added to `unknown`.
+fn inherited_layers(
+    path: &Path,
+    config: &toml::Value,
+    chain: &mut Vec<PathBuf>,
+    unknown: &mut Vec<String>,
+) -> Result<toml::Value, ForgeKitError> {
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mut merged = toml::Value::Table(toml::Table::new());
+    for layer in layer_names(path, config)? {
+        let layer_path = dir.join(&layer);
+        let unreadable = |e: std::io::Error| {
+            ForgeKitError::InvalidConfig(format!(
+                "{} extends {}, which cannot be read: {}",
+                path.display(),
+                layer,
+                e
+            ))
+        };
+        let canonical = layer_path.canonicalize().map_err(unreadable)?;
+        if let Some(start) = chain.iter().position(|seen| *seen == canonical) {
+            let cycle = chain[start..]
+                .iter()
+                .chain([&canonical])
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>();
+            return Err(ForgeKitError::InvalidConfig(format!(
+                "Configuration extends itself: {}",
+                cycle.join(" -> ")
+            )));
+        }
+
+        let contents = std::fs::read_to_string(&layer_path).map_err(unreadable)?;
+        let mut own: toml::Value = toml::from_str(&contents)?;
+        unknown.extend(unknown_keys(&layer_path, &contents));
+        chain.push(canonical);
+        let mut layer_value = inherited_layers(&layer_path, &own, chain, unknown)?;
+        chain.pop();
+        if let Some(table) = own.as_table_mut() {
+            table.remove("extends");
+            table.remove("include");
+        }
+        merge_layer(&mut layer_value, own);
+        merge_layer(&mut merged, layer_value);
+    }
+    Ok(merged)
+}
+
+/// Unknown keys of one configuration file, prefixed with its path
+fn unknown_keys(path: &Path, contents: &str) -> Vec<String> {
+    crate::manifest::unknown_keys(contents)
+        .into_iter()
+        .map(|key| format!("{}: {}", path.display(), key))
+        .collect()
+}
+
+/// Files a configuration builds on, in merge order
+fn layer_names(path: &Path, config: &toml::Value) -> Result<Vec<String>, ForgeKitError> {
+    let invalid = |key: &str, expected: &str| {
+        ForgeKitError::InvalidConfig(format!(
+            "{}: `{}` must be {}",
+            path.display(),
+            key,
+            expected
+        ))
+    };
+
+    let mut names = Vec::new();
+    if let Some(extends) = config.get("extends") {
+        let extends = extends
+            .as_str()
+            .ok_or_else(|| invalid("extends", "a path"))?;
+        names.push(extends.to_string());
+    }
+    if let Some(include) = config.get("include") {
+        let include = include
+            .as_array()
+            .ok_or_else(|| invalid("include", "a list of paths"))?;
+        for fragment in include {
+            let fragment = fragment
+                .as_str()
+                .ok_or_else(|| invalid("include", "a list of paths"))?;
+            names.push(fragment.to_string());
+        }
+    }
+    Ok(names)
+}
+
+/// Merge `overlay` over `base`, `overlay` winning
+fn merge_layer(base: &mut toml::Value, overlay: toml::Value) {
+    match (base, overlay) {
+        (toml::Value::Table(base), toml::Value::Table(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_layer(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (toml::Value::Array(base), toml::Value::Array(overlay))
+            if is_named_list(base) && is_named_list(&overlay) =>
+        {
+            for entry in overlay {
+                if is_removal(&entry) {
+                    base.retain(|existing| existing.get("name") != entry.get("name"));
+                    continue;
+                }
+                match base
+                    .iter_mut()
+                    .find(|existing| existing.get("name") == entry.get("name"))
+                {
+                    Some(existing) => *existing = entry,
+                    None => base.push(entry),
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Remove the values of `config` that it would inherit anyway
+fn strip_inherited(config: &mut toml::Value, inherited: &toml::Value) {
+    match (config, inherited) {
+        (toml::Value::Table(config), toml::Value::Table(inherited)) => {
+            config.retain(|key, value| match inherited.get(key) {
+                Some(base) if value == base => false,
+                Some(base @ (toml::Value::Table(_) | toml::Value::Array(_))) => {
+                    strip_inherited(value, base);
+                    !is_empty_container(value)
+                }
+                _ => true,
+            });
+        }
+        (toml::Value::Array(config), toml::Value::Array(inherited))
+            if is_named_list(config) && is_named_list(inherited) =>
+        {
+            config.retain(|entry| !inherited.contains(entry));
+        }
+        _ => {}
+    }
+}
+
+/// Whether a named list entry drops the inherited entry of its name
+fn is_removal(entry: &toml::Value) -> bool {
+    entry.get("removed").and_then(toml::Value::as_bool) == Some(true)
+}
+
+/// `removed = true` entries, by top-level key, for the inherited named list
+/// entries `config` no longer has
+fn removed_entries(
+    config: &toml::Value,
+    inherited: &toml::Value,
+) -> Vec<(String, Vec<toml::Value>)> {
+    let Some(inherited) = inherited.as_table() else {
+        return Vec::new();
+    };
+    inherited
+        .iter()
+        .filter_map(|(key, base)| {
+            let base = base.as_array().filter(|base| is_named_list(base))?;
+            let current = config
+                .get(key)
+                .and_then(toml::Value::as_array)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let markers: Vec<toml::Value> = base
+                .iter()
+                .filter_map(|entry| entry.get("name"))
+                .filter(|name| !current.iter().any(|e| e.get("name") == Some(*name)))
+                .map(|name| {
+                    let mut marker = toml::Table::new();
+                    marker.insert("name".to_string(), name.clone());
+                    marker.insert("removed".to_string(), toml::Value::Boolean(true));
+                    toml::Value::Table(marker)
+                })
+                .collect();
+            (!markers.is_empty()).then(|| (key.clone(), markers))
+        })
+        .collect()
+}
+
+/// Whether every element of an array is a table with a `name`
+fn is_named_list(values: &[toml::Value]) -> bool {
+    values.iter().all(|value| value.get("name").is_some())
+}
+
+fn is_empty_container(value: &toml::Value) -> bool {
+    match value {
+        toml::Value::Table(table) => table.is_empty(),
+        toml::Value::Array(array) => array.is_empty(),
+        _ => false,
+    }
+}
+
+/// User-wide configuration stored in the ForgeKit config directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GlobalConfig {
+    /// Registries the user has logged in to, keyed by registry name
+    pub registries: HashMap<String, RegistryEntry>,
+    /// Compiler cache used by `forgekit build`
+    pub compiler_cache: CompilerCacheConfig,
+    /// Anonymous usage statistics, off unless the user opts in
+    pub telemetry: TelemetryConfig,
+    /// Where `forgekit upgrade` looks for new versions
+    pub update: UpdateConfig,
+    /// Where tokens, signing keys and secrets are stored
+    pub credentials: CredentialsConfig,
+    /// Commands that are refused or need confirmation on this machine
+    pub command_policy: CommandPolicyConfig,
+    /// How plugins are run, unless a project configures it
+    pub plugins: PluginsConfig,
+}
+
+/// What happens when a `[[command_policy.rules]]` entry matches
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommandAction {
+    /// Ask before running the command
+    #[default]
+    Confirm,
+    /// Refuse to run the command
+    Deny,
+}
+
+/// Restriction of a command in some environments
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommandRule {
+    /// Command, like `publish`, or commands, like `secret *`
+    pub command: String,
+    /// Environments the rule applies in, like `production`; all when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub environments: Vec<String>,
+    /// Whether the command is refused or needs confirmation
+    #[serde(default)]
+    pub action: CommandAction,
+    /// Why, shown when the rule applies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// `[command_policy]` for shared machines, from the user's or the
+/// organization's configuration
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommandPolicyConfig {
+    /// Environment this machine belongs to, like `production`
+    pub environment: Option<String>,
+    /// Base64 Ed25519 public keys approval tokens may be signed with
+    pub approval_keys: Vec<String>,
+    /// Restrictions, the first matching one applies
+    pub rules: Vec<CommandRule>,
+}
+
+/// Credential store backend
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CredentialBackend {
+    /// The OS keyring where it persists across sessions (macOS, Windows),
+    /// the encrypted file elsewhere
+    #[default]
+    Auto,
+    /// The OS keyring
+    Keyring,
+    /// An encrypted file in the ForgeKit config directory
+    File,
+}
+
+impl std::str::FromStr for CredentialBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(CredentialBackend::Auto),
+            "keyring" => Ok(CredentialBackend::Keyring),
+            "file" => Ok(CredentialBackend::File),
+            _ => Err(format!(
+                "unknown credential store '{}' (expected auto, keyring or file)",
+                s
+            )),
+        }
+    }
+}
+
+/// Credential storage settings shared by all projects
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CredentialsConfig {
+    /// Store backend
+    pub backend: CredentialBackend,
+}
+
+/// Release channel `forgekit upgrade` follows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    /// Tagged releases
+    #[default]
+    Stable,
+    /// Builds of the main branch, published daily
+    Nightly,
+}
+
+impl std::fmt::Display for UpdateChannel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpdateChannel::Stable => write!(f, "stable"),
+            UpdateChannel::Nightly => write!(f, "nightly"),
+        }
+    }
+}
+
+impl std::str::FromStr for UpdateChannel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stable" => Ok(UpdateChannel::Stable),
+            "nightly" => Ok(UpdateChannel::Nightly),
+            _ => Err(format!(
+                "unknown release channel '{}' (expected stable or nightly)",
+                s
+            )),
+        }
+    }
+}
+
+/// Self-update settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdateConfig {
+    /// Release channel to follow
+    pub channel: UpdateChannel,
+    /// Release endpoint, instead of the official one
+    pub endpoint: Option<String>,
+    /// Base64 Ed25519 key releases are signed with, for a mirror signing its own
+    ///
+    /// Ignored by builds that embed the official release key.
+    pub public_key: Option<String>,
+}
+
+/// Opt-in telemetry settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    /// Whether usage statistics are collected
+    pub enabled: bool,
+    /// Endpoint `forgekit telemetry upload` sends collected events to
+    pub endpoint: Option<String>,
+}
+
+/// Compiler cache backend
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompilerCacheMode {
+    /// Use sccache when it is installed, otherwise no cache
+    #[default]
+    Auto,
+    /// Wrap rustc with sccache
+    Sccache,
+    /// Share one cargo target directory between all projects
+    Builtin,
+    /// Disable compiler caching
+    Off,
+}
+
+impl std::str::FromStr for CompilerCacheMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(CompilerCacheMode::Auto),
+            "sccache" => Ok(CompilerCacheMode::Sccache),
+            "builtin" => Ok(CompilerCacheMode::Builtin),
+            "off" => Ok(CompilerCacheMode::Off),
+            _ => Err(format!(
+                "unknown compiler cache '{}' (expected auto, sccache, builtin or off)",
+                s
+            )),
+        }
+    }
+}
+
+/// Compiler cache settings shared by all projects
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompilerCacheConfig {
+    /// Cache backend
+    pub mode: CompilerCacheMode,
+    /// Cache directory (`SCCACHE_DIR`, or the shared target directory)
+    pub dir: Option<PathBuf>,
+}
+
+/// Registry known to the global configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistryEntry {
+    /// Registry base URL
+    pub url: String,
+    /// When the stored token expires (RFC 3339), if known
+    pub token_expires_at: Option<String>,
+}
+
+impl GlobalConfig {
+    /// Directory holding user-wide ForgeKit configuration
+    pub fn config_dir() -> PathBuf {
+        dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("forgekit")
+    }
+
+    /// Default location of the global configuration file
+    pub fn default_path() -> PathBuf {
+        Self::config_dir().join("config.toml")
+    }
+
+    /// Location of the organization-wide configuration, managed by
+    /// administrators in a system-wide file
+    ///
+    /// It cannot be moved with an environment variable, since users could
+    /// point it at a file of their own.
+    pub fn org_path() -> PathBuf {
+        if cfg!(windows) {
+            std::env::var_os("ProgramData")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| PathBuf::from(r"C:\ProgramData"))
+                .join("forgekit")
+                .join("config.toml")
+        } else {
+            PathBuf::from("/etc/forgekit/config.toml")
+        }
+    }
+
+    /// Load the global configuration, returning defaults if it does not exist
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, crate::error::ForgeKitError> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&contents)?)
+    }
+
+    /// Save the global configuration
     pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), crate::error::ForgeKitError> {
-        let contents = toml::to_string_pretty(self)?;
-        std::fs::write(path, contents)?;
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        crate::atomic::write(path, toml::to_string_pretty(self)?)?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_global_config_missing_file_is_default() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = GlobalConfig::load(temp_dir.path().join("config.toml")).unwrap();
+        assert!(config.registries.is_empty());
+    }
+
+    #[test]
+    fn test_global_config_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("nested").join("config.toml");
+
+        let mut config = GlobalConfig::default();
+        config.registries.insert(
+            "github".to_string(),
+            RegistryEntry {
+                url: "https://github.com".to_string(),
+                token_expires_at: None,
+            },
+        );
+        config.save(&path).unwrap();
+
+        let loaded = GlobalConfig::load(&path).unwrap();
+        assert_eq!(loaded.registries["github"].url, "https://github.com");
+    }
+
+    #[test]
+    fn test_hooks_section_is_optional() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("forgekit.toml");
+        ProjectConfig::default().save(&path).unwrap();
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("[hooks]"));
+
+        let mut config = ProjectConfig::load(&path).unwrap();
+        config.hooks.pre_build = vec!["protoc --version".to_string()];
+        config.save(&path).unwrap();
+        assert_eq!(
+            ProjectConfig::load(&path).unwrap().hooks.pre_build,
+            vec!["protoc --version"]
+        );
+    }
+
+    #[test]
+    fn test_extends_merges_layers_and_saves_overrides() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::write(
+            root.join("base-forgekit.toml"),
+            r#"
+name = "base"
+version = "0.1.0"
+authors = ["Platform Team"]
+include = ["flags.toml"]
+
+[[dependencies]]
+name = "mox-log"
+version = "1.0"
+
+[build]
+target = "ledokoz"
+opt_level = "2"
+output_dir = "target"
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("flags.toml"),
+            "[build]\nrustflags = [\"-Cforce-frame-pointers\"]\n",
+        )
+        .unwrap();
+        let app = root.join("app");
+        std::fs::create_dir_all(&app).unwrap();
+        std::fs::write(
+            app.join("forgekit.toml"),
+            r#"
+extends = "../base-forgekit.toml"
+name = "app"
+
+[[dependencies]]
+name = "mox-net"
+version = "2.0"
+
+[build]
+opt_level = "3"
+"#,
+        )
+        .unwrap();
+
+        let mut config = ProjectConfig::load(app.join("forgekit.toml")).unwrap();
+        assert_eq!(config.name, "app");
+        assert_eq!(config.authors, vec!["Platform Team"]);
+        assert_eq!(config.build.opt_level, "3");
+        assert_eq!(config.build.rustflags, vec!["-Cforce-frame-pointers"]);
+        let names: Vec<_> = config
+            .dependencies
+            .iter()
+            .map(|d| d.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["mox-log", "mox-net"]);
+
+        config.version = "0.2.0".to_string();
+        config.save(app.join("forgekit.toml")).unwrap();
+        let saved = std::fs::read_to_string(app.join("forgekit.toml")).unwrap();
+        assert!(saved.contains("extends = \"../base-forgekit.toml\""));
+        assert!(!saved.contains("mox-log") && !saved.contains("Platform Team"));
+        let reloaded = ProjectConfig::load(app.join("forgekit.toml")).unwrap();
+        assert_eq!(reloaded.version, "0.2.0");
+        assert_eq!(reloaded.dependencies.len(), 2);
+
+        // Removing an inherited dependency survives a reload
+        let mut config = reloaded;
+        config.dependencies.retain(|d| d.name != "mox-log");
+        config.save(app.join("forgekit.toml")).unwrap();
+        let saved = std::fs::read_to_string(app.join("forgekit.toml")).unwrap();
+        assert!(saved.contains("removed = true"));
+        let reloaded = ProjectConfig::load(app.join("forgekit.toml")).unwrap();
+        let names: Vec<_> = reloaded
+            .dependencies
+            .iter()
+            .map(|d| d.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["mox-net"]);
+        reloaded.save(app.join("forgekit.toml")).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(app.join("forgekit.toml")).unwrap(),
+            saved
+        );
+    }
+
+    #[test]
+    fn test_extends_cycle_is_reported() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::write(root.join("a.toml"), "extends = \"b.toml\"\n").unwrap();
+        std::fs::write(root.join("b.toml"), "include = [\"a.toml\"]\n").unwrap();
+
+        let err = ProjectConfig::load(root.join("a.toml")).unwrap_err();
+        assert!(matches!(err, ForgeKitError::InvalidConfig(_)));
+        assert!(err.to_string().contains("a.toml -> "));
+    }
+
+    #[test]
+    fn test_env_section_overrides_active_environment() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("forgekit.toml");
+        let mut config = ProjectConfig::default();
+        config.env.insert(
+            "prod".to_string(),
+            toml::from_str("[build]\nopt_level = \"3\"\nfeatures = [\"telemetry\"]").unwrap(),
+        );
+        config.save(&path).unwrap();
+        let config = ProjectConfig::load(&path).unwrap();
+
+        let prod = config.resolve(Some("prod")).unwrap();
+        assert_eq!(prod.build.opt_level, "3");
+        assert_eq!(prod.build.features, vec!["telemetry"]);
+        assert_eq!(prod.build.target, "ledokoz");
+
+        let dev = config.resolve(Some("dev")).unwrap();
+        assert_eq!(dev.build.opt_level, "2");
+        assert!(config.resolve(None).unwrap().build.features.is_empty());
+    }
+
+    #[test]
+    fn test_unknown_keys_warn_or_deny() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("forgekit.toml");
+        let contents = toml::to_string_pretty(&ProjectConfig::default()).unwrap();
+        std::fs::write(&path, format!("dependancies = []\n{}", contents)).unwrap();
+
+        let (_, unknown) = ProjectConfig::load_reporting(&path).unwrap();
+        assert_eq!(unknown.len(), 1);
+        assert!(unknown[0].ends_with("(did you mean 'dependencies'?)"));
+        assert!(ProjectConfig::load(&path).is_ok());
+
+        let err = ProjectConfig::load_with(&path, Strictness::Deny).unwrap_err();
+        assert!(matches!(err, ForgeKitError::InvalidConfig(_)));
+        assert!(err.to_string().contains("line 1, column 1"));
+    }
+}
@@ -0,0 +1,407 @@
+//! Crash report module
+//!
+//! This module captures crashes of built applications. App templates include
+//! a small std-only panic hook (`src/forgekit_crash.rs`) that writes a report
+//! with the panic message and a raw backtrace to a known directory: the one
+//! in `FORGEKIT_CRASH_DIR`, which `forgekit run` points at
+//! `.forgekit/crashes/`, or `~/.local/share/ledokoz/crashes/<app>/` on a
+//! device. The builder keeps the debug info of every release (see
+//! [`crate::symbols`]), so `forgekit crash symbolicate` can resolve the
+//! addresses of a report with `addr2line` long after the build.
+
+use crate::config::ProjectConfig;
+use crate::error::ForgeKitError;
+use crate::platform::normalize_newlines;
+use crate::redact::Redactor;
+use crate::symbols;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// Environment variable telling the crash handler where to write reports
+pub const CRASH_DIR_ENV: &str = "FORGEKIT_CRASH_DIR";
+
+/// File name of the crash handler in a project's `src/`
+pub const HANDLER_FILE: &str = "forgekit_crash.rs";
+
+/// Symbolizers tried in order; both take `-e <binary>` and print a function
+/// and a location line per address
+const SYMBOLIZERS: &[&str] = &["addr2line", "llvm-addr2line"];
+
+/// Crash handler compiled into apps
+const HANDLER_SOURCE: &str = r#"//! Crash reporting installed by ForgeKit
+//!
+//! Writes a report for every panic, read by `forgekit crash list`.
+
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Install the panic hook that writes crash reports
+pub fn install() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let payload = info.payload();
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        let location = info
+            .location()
+            .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()))
+            .unwrap_or_default();
+        let _ = write_report(&message, &location);
+        previous(info);
+    }));
+}
+
+fn write_report(message: &str, location: &str) -> std::io::Result<()> {
+    let Some(dir) = crash_dir() else {
+        return Ok(());
+    };
+    std::fs::create_dir_all(&dir)?;
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let path = dir.join(format!("crash-{}-{}.txt", timestamp, std::process::id()));
+
+    let mut file = std::fs::File::create(path)?;
+    writeln!(file, "app: {}", env!("CARGO_PKG_NAME"))?;
+    writeln!(file, "version: {}", env!("CARGO_PKG_VERSION"))?;
+    writeln!(file, "timestamp: {}", timestamp)?;
+    writeln!(file, "message: {}", message.replace('\n', " "))?;
+    writeln!(file, "location: {}", location)?;
+    writeln!(file, "base: {}", load_base().unwrap_or_default())?;
+    writeln!(file, "backtrace:")?;
+    write!(file, "{:#}", std::backtrace::Backtrace::force_capture())?;
+    Ok(())
+}
+
+fn crash_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("FORGEKIT_CRASH_DIR") {
+        return Some(dir.into());
+    }
+    let data = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))?;
+    Some(data.join("ledokoz/crashes").join(env!("CARGO_PKG_NAME")))
+}
+
+/// Address the executable is loaded at, to undo address randomization
+fn load_base() -> Option<String> {
+    let exe = std::env::current_exe().ok()?;
+    let exe = exe.to_string_lossy();
+    let maps = std::fs::read_to_string("/proc/self/maps").ok()?;
+    let line = maps.lines().find(|line| line.ends_with(&*exe))?;
+    Some(format!("0x{}", line.split('-').next()?))
+}
+"#;
+
+/// One frame of a crash backtrace
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CrashFrame {
+    /// Instruction address at crash time
+    pub address: u64,
+    /// Function name, if known
+    pub symbol: Option<String>,
+    /// Source location, if known
+    pub location: Option<String>,
+}
+
+/// A crash report written by the crash handler
+#[derive(Debug, Clone, Serialize)]
+pub struct CrashReport {
+    /// Report file
+    pub path: PathBuf,
+    /// Application name
+    pub app: String,
+    /// Application version
+    pub version: String,
+    /// When the crash happened (Unix seconds)
+    pub timestamp: u64,
+    /// Panic message
+    pub message: String,
+    /// Source location of the panic
+    pub location: Option<String>,
+    /// Load address of the executable
+    pub base: Option<u64>,
+    /// Backtrace frames, innermost first
+    pub frames: Vec<CrashFrame>,
+}
+
+impl CrashReport {
+    /// Parse a report file
+    pub fn load(path: &Path) -> Result<Self, ForgeKitError> {
+        Ok(Self::parse(path, &std::fs::read_to_string(path)?))
+    }
+
+    /// Parse the contents of a report
+    pub fn parse(path: &Path, contents: &str) -> Self {
+        let mut report = CrashReport {
+            path: path.to_path_buf(),
+            app: String::new(),
+            version: String::new(),
+            timestamp: 0,
+            message: String::new(),
+            location: None,
+            base: None,
+            frames: Vec::new(),
+        };
+
+        let mut lines = contents.lines();
+        for line in lines.by_ref() {
+            if line == "backtrace:" {
+                break;
+            }
+            let Some((key, value)) = line.split_once(": ").or_else(|| line.split_once(':')) else {
+                continue;
+            };
+            let value = value.trim();
+            match key {
+                "app" => report.app = value.to_string(),
+                "version" => report.version = value.to_string(),
+                "timestamp" => report.timestamp = value.parse().unwrap_or(0),
+                "message" => report.message = value.to_string(),
+                "location" if !value.is_empty() => report.location = Some(value.to_string()),
+                "base" => report.base = parse_address(value),
+                _ => {}
+            }
+        }
+
+        // Frames look like `  3:     0x55d7cfc39f8f - app::main::h0123`,
+        // optionally followed by an `at src/main.rs:3:5` line
+        for line in lines {
+            let line = line.trim();
+            if let Some(location) = line.strip_prefix("at ") {
+                if let Some(frame) = report.frames.last_mut() {
+                    frame.location.get_or_insert_with(|| location.to_string());
+                }
+                continue;
+            }
+            let Some((_, frame)) = line.split_once(':') else {
+                continue;
+            };
+            let (address, symbol) = frame.split_once(" - ").unwrap_or((frame, ""));
+            let Some(address) = parse_address(address.trim()) else {
+                continue;
+            };
+            let symbol = symbol.trim();
+            report.frames.push(CrashFrame {
+                address,
+                symbol: (!symbol.is_empty() && symbol != "<unknown>").then(|| symbol.to_string()),
+                location: None,
+            });
+        }
+
+        report
+    }
+
+    /// Mask secret values the panic message may hold
+    pub fn redact(&mut self, redactor: &Redactor) {
+        self.message = redactor.redact(&self.message);
+    }
+
+    /// When the crash happened, in local time (RFC 3339)
+    pub fn crashed_at(&self) -> String {
+        chrono::DateTime::from_timestamp(self.timestamp as i64, 0)
+            .map(|time| time.with_timezone(&chrono::Local).to_rfc3339())
+            .unwrap_or_default()
+    }
+
+    /// Human readable stack trace
+    pub fn stack_trace(&self) -> String {
+        let mut trace = format!("{} {} panicked: {}\n", self.app, self.version, self.message);
+        if let Some(location) = &self.location {
+            trace.push_str(&format!("  at {}\n", location));
+        }
+        for (index, frame) in self.frames.iter().enumerate() {
+            trace.push_str(&format!(
+                "{:>4}: {:#014x} {}\n",
+                index,
+                frame.address,
+                frame.symbol.as_deref().unwrap_or("<unknown>")
+            ));
+            if let Some(location) = &frame.location {
+                trace.push_str(&format!("            at {}\n", location));
+            }
+        }
+        trace
+    }
+}
+
+/// Directory `forgekit run` collects crash reports of a project in
+pub fn project_crash_dir(project_path: &Path) -> PathBuf {
+    project_path.join(".forgekit").join("crashes")
+}
+
+/// Directory the crash handler uses on a device for an app
+pub fn device_crash_dir(app: &str) -> Option<PathBuf> {
+    dirs::data_local_dir().map(|data| data.join("ledokoz").join("crashes").join(app))
+}
+
+/// Write the crash handler into a project's `src/`
+///
+/// Returns whether the file changed. The app enables it with
+/// `mod forgekit_crash;` and `forgekit_crash::install();` in `main`.
+pub fn install_handler(project_path: &Path) -> Result<bool, ForgeKitError> {
+    let path = project_path.join("src").join(HANDLER_FILE);
+    // A checkout with CRLF line endings is still the same handler
+    let existing = std::fs::read_to_string(&path).unwrap_or_default();
+    if normalize_newlines(&existing) == HANDLER_SOURCE {
+        return Ok(false);
+    }
+    std::fs::create_dir_all(project_path.join("src"))?;
+    std::fs::write(path, HANDLER_SOURCE)?;
+    Ok(true)
+}
+
+/// Crash reports of an app, newest first
+///
+/// Secret values of the project's environment are masked in the messages.
+pub fn list(project_path: &Path, app: &str) -> Result<Vec<CrashReport>, ForgeKitError> {
+    let redactor = Redactor::for_project(project_path)?;
+    let mut reports = Vec::new();
+    let dirs = std::iter::once(project_crash_dir(project_path)).chain(device_crash_dir(app));
+    for dir in dirs.filter(|dir| dir.is_dir()) {
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "txt") {
+                let mut report = CrashReport::load(&path)?;
+                report.redact(&redactor);
+                reports.push(report);
+            }
+        }
+    }
+    reports.sort_by_key(|report| std::cmp::Reverse(report.timestamp));
+    Ok(reports)
+}
+
+/// Resolve the frames of a report against the debug info of its release
+///
+/// Debug info missing locally is fetched from the symbol server configured
+/// in `[build.symbols]`.
+pub async fn symbolicate(
+    project_path: &Path,
+    report: &CrashReport,
+) -> Result<CrashReport, ForgeKitError> {
+    let binary = match symbols::local_debug_file(project_path, &report.app, &report.version) {
+        Some(binary) => binary,
+        None => {
+            let config_path = project_path.join("forgekit.toml");
+            let server = if config_path.exists() {
+                ProjectConfig::load(&config_path)?.build.symbols.server
+            } else {
+                None
+            };
+            let server = server.ok_or_else(|| {
+                ForgeKitError::InvalidConfig(format!(
+                    "No debug info for {} {} in {} and no symbol server configured",
+                    report.app,
+                    report.version,
+                    symbols::symbols_dir(project_path, &report.version).display()
+                ))
+            })?;
+            symbols::download(project_path, &report.app, &report.version, &server).await?
+        }
+    };
+
+    // Addresses are relative to the executable once its load base is removed
+    let base = report.base.unwrap_or(0);
+    let addresses: Vec<String> = report
+        .frames
+        .iter()
+        .map(|frame| format!("{:#x}", frame.address.saturating_sub(base)))
+        .collect();
+
+    let mut last_error = None;
+    for tool in SYMBOLIZERS {
+        let output = Command::new(tool)
+            .args(["-f", "-C", "-e"])
+            .arg(&binary)
+            .args(&addresses)
+            .output()
+            .await;
+        match output {
+            Ok(output) if output.status.success() => {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                return Ok(apply_symbols(report, &stdout));
+            }
+            Ok(output) => {
+                last_error = Some(String::from_utf8_lossy(&output.stderr).trim().to_string())
+            }
+            Err(e) => last_error = Some(format!("{}: {}", tool, e)),
+        }
+    }
+    Err(ForgeKitError::InvalidConfig(format!(
+        "Symbolication requires addr2line (binutils or LLVM): {}",
+        last_error.unwrap_or_default()
+    )))
+}
+
+/// Merge `addr2line -f` output (function and location per address) into a report
+fn apply_symbols(report: &CrashReport, output: &str) -> CrashReport {
+    let mut symbolicated = report.clone();
+    let mut lines = output.lines();
+    for frame in &mut symbolicated.frames {
+        let (Some(function), Some(location)) = (lines.next(), lines.next()) else {
+            break;
+        };
+        if function != "??" {
+            frame.symbol = Some(function.to_string());
+        }
+        if !location.starts_with("??") && !location.ends_with(":?") {
+            frame.location = Some(location.to_string());
+        }
+    }
+    symbolicated
+}
+
+/// Parse a `0x`-prefixed hexadecimal address
+fn parse_address(value: &str) -> Option<u64> {
+    u64::from_str_radix(value.strip_prefix("0x")?, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPORT: &str = "app: notes\nversion: 1.2.0\ntimestamp: 1700000000\nmessage: index out of bounds\nlocation: src/main.rs:3:5\nbase: 0x55d7cfc24000\nbacktrace:\n   0:     0x55d7cfc39f8f - <unknown>\n   1:     0x55d7cfc3a293 - std::rt::lang_start\n                               at /rustc/library/std/src/rt.rs:195:17\n   2:                0x0 - <unknown>\n";
+
+    #[test]
+    fn test_parse_report() {
+        let report = CrashReport::parse(Path::new("crash.txt"), REPORT);
+        assert_eq!(report.app, "notes");
+        assert_eq!(report.version, "1.2.0");
+        assert_eq!(report.location.as_deref(), Some("src/main.rs:3:5"));
+        assert_eq!(report.base, Some(0x55d7cfc24000));
+        assert_eq!(report.frames.len(), 3);
+        assert_eq!(report.frames[0].symbol, None);
+        assert_eq!(
+            report.frames[1].location.as_deref(),
+            Some("/rustc/library/std/src/rt.rs:195:17")
+        );
+    }
+
+    #[test]
+    fn test_apply_symbols() {
+        let report = CrashReport::parse(Path::new("crash.txt"), REPORT);
+        let output = "notes::main\nsrc/main.rs:3\nstd::rt::lang_start\n??:?\n??\n??:0\n";
+        let symbolicated = apply_symbols(&report, output);
+
+        assert_eq!(
+            symbolicated.frames[0].symbol.as_deref(),
+            Some("notes::main")
+        );
+        assert_eq!(
+            symbolicated.frames[0].location.as_deref(),
+            Some("src/main.rs:3")
+        );
+        // Locations the symbolizer cannot resolve keep the reported ones
+        assert_eq!(
+            symbolicated.frames[1].location.as_deref(),
+            Some("/rustc/library/std/src/rt.rs:195:17")
+        );
+        assert!(symbolicated.stack_trace().contains("notes::main"));
+    }
+}
@@ -0,0 +1,454 @@
+//! Credential storage module
+//!
+//! Registry tokens, signing keys and the stored secrets `secret:` values of
+//! env files refer to are kept in a [`CredentialStore`]. [`KeyringStore`]
+//! uses the OS keyring (Keychain on macOS, Credential Manager on Windows,
+//! the Secret Service on Linux and the BSDs); [`EncryptedFileStore`] keeps credentials
+//! AES-256-GCM encrypted in the ForgeKit config directory, with the key in a
+//! separate owner-only file. `[credentials] backend` of the global
+//! configuration, or `FORGEKIT_CREDENTIAL_STORE`, selects the store; by
+//! default the keyring is used where it can be reached and the encrypted
+//! file elsewhere, e.g. on Linux without a desktop session.
+//!
+//! Entries written by older versions, base64-encoded in `secrets.toml`, are
+//! still read; they are encrypted again or moved to the keyring as soon as
+//! the store is written or they are read from the keyring store.
+
+use crate::atomic;
+use crate::config::{CredentialBackend, GlobalConfig};
+use crate::error::ForgeKitError;
+use crate::secrets::SecretsManager;
+use base64::{engine::general_purpose, Engine as _};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Environment variable overriding the configured store (`keyring` or `file`)
+pub const CREDENTIAL_STORE_VAR: &str = "FORGEKIT_CREDENTIAL_STORE";
+
+/// Service name credentials are kept under in the OS keyring
+pub const KEYRING_SERVICE: &str = "forgekit";
+
+/// Prefix of values encrypted by [`EncryptedFileStore`]
+const ENCRYPTED_PREFIX: &str = "aes256gcm:";
+
+/// Prefix of values base64-encoded by older versions
+const LEGACY_PREFIX: &str = "encrypted:";
+
+/// Named credentials kept out of plain-text configuration
+pub trait CredentialStore: Send + Sync {
+    /// Short name of the backend, for messages
+    fn backend(&self) -> &'static str;
+
+    /// The credential stored under `name`
+    fn get(&self, name: &str) -> Result<Option<String>, ForgeKitError>;
+
+    /// Store a credential, replacing any previous value
+    fn set(&self, name: &str, value: &str) -> Result<(), ForgeKitError>;
+
+    /// Remove a credential, returning whether it was stored
+    fn delete(&self, name: &str) -> Result<bool, ForgeKitError>;
+}
+
+/// Open the store selected by `backend`
+pub fn open(backend: CredentialBackend) -> Result<Arc<dyn CredentialStore>, ForgeKitError> {
+    let file = EncryptedFileStore::new(SecretsManager::default_secrets_file());
+    let keyring = KeyringStore::new(KEYRING_SERVICE).with_fallback(file.clone());
+    let use_keyring = match backend {
+        CredentialBackend::Keyring => true,
+        CredentialBackend::File => false,
+        // Other platforms only have keyring's in-memory mock store
+        CredentialBackend::Auto => {
+            cfg!(any(
+                target_os = "macos",
+                target_os = "windows",
+                target_os = "linux",
+                target_os = "freebsd",
+                target_os = "openbsd"
+            )) && keyring.is_available()
+        }
+    };
+    Ok(if use_keyring {
+        Arc::new(keyring)
+    } else {
+        Arc::new(file)
+    })
+}
+
+/// The user's credential store, as configured
+pub fn default_store() -> Result<Arc<dyn CredentialStore>, ForgeKitError> {
+    let backend = match std::env::var(CREDENTIAL_STORE_VAR) {
+        Ok(backend) => backend.parse().map_err(ForgeKitError::InvalidConfig)?,
+        Err(_) => {
+            GlobalConfig::load(GlobalConfig::default_path())?
+                .credentials
+                .backend
+        }
+    };
+    open(backend)
+}
+
+/// Credentials in the OS keyring
+#[derive(Debug, Clone)]
+pub struct KeyringStore {
+    service: String,
+    fallback: Option<EncryptedFileStore>,
+}
+
+impl KeyringStore {
+    /// Store keeping credentials under `service`
+    pub fn new(service: &str) -> Self {
+        Self {
+            service: service.to_string(),
+            fallback: None,
+        }
+    }
+
+    /// Move credentials missing from the keyring out of `file` when read
+    pub fn with_fallback(mut self, file: EncryptedFileStore) -> Self {
+        self.fallback = Some(file);
+        self
+    }
+
+    /// Whether the keyring can be reached
+    pub fn is_available(&self) -> bool {
+        matches!(
+            self.entry("forgekit.probe")
+                .map(|entry| entry.get_password()),
+            Ok(Ok(_)) | Ok(Err(keyring::Error::NoEntry))
+        )
+    }
+
+    fn entry(&self, name: &str) -> Result<keyring::Entry, ForgeKitError> {
+        keyring::Entry::new(&self.service, name).map_err(keyring_error)
+    }
+}
+
+impl CredentialStore for KeyringStore {
+    fn backend(&self) -> &'static str {
+        "keyring"
+    }
+
+    fn get(&self, name: &str) -> Result<Option<String>, ForgeKitError> {
+        match self.entry(name)?.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => {
+                let Some(fallback) = &self.fallback else {
+                    return Ok(None);
+                };
+                let value = fallback.get(name)?;
+                if let Some(value) = &value {
+                    self.set(name, value)?;
+                    tracing::info!("Moved credential '{}' to the OS keyring", name);
+                }
+                Ok(value)
+            }
+            Err(e) => Err(keyring_error(e)),
+        }
+    }
+
+    fn set(&self, name: &str, value: &str) -> Result<(), ForgeKitError> {
+        self.entry(name)?
+            .set_password(value)
+            .map_err(keyring_error)?;
+        if let Some(fallback) = &self.fallback {
+            fallback.delete(name)?;
+        }
+        Ok(())
+    }
+
+    fn delete(&self, name: &str) -> Result<bool, ForgeKitError> {
+        let removed = match self.entry(name)?.delete_credential() {
+            Ok(()) => true,
+            Err(keyring::Error::NoEntry) => false,
+            Err(e) => return Err(keyring_error(e)),
+        };
+        let removed_fallback = match &self.fallback {
+            Some(fallback) => fallback.delete(name)?,
+            None => false,
+        };
+        Ok(removed || removed_fallback)
+    }
+}
+
+fn keyring_error(e: keyring::Error) -> ForgeKitError {
+    ForgeKitError::Credentials(format!(
+        "{} (set [credentials] backend = \"file\" to store credentials in an encrypted file)",
+        e
+    ))
+}
+
+/// Credentials in an encrypted TOML file
+///
+/// The 256-bit key is generated on first use and kept next to the file,
+/// with the `.key` extension. Each value is bound to its name, so entries
+/// cannot be swapped.
+#[derive(Debug, Clone)]
+pub struct EncryptedFileStore {
+    file: PathBuf,
+    key_file: PathBuf,
+}
+
+impl EncryptedFileStore {
+    /// Store kept in `file`
+    pub fn new(file: impl Into<PathBuf>) -> Self {
+        let file = file.into();
+        let key_file = file.with_extension("key");
+        Self { file, key_file }
+    }
+
+    /// File the credentials are kept in
+    pub fn path(&self) -> &Path {
+        &self.file
+    }
+
+    fn read(&self) -> Result<BTreeMap<String, String>, ForgeKitError> {
+        if !self.file.exists() {
+            return Ok(BTreeMap::new());
+        }
+        Ok(toml::from_str(&std::fs::read_to_string(&self.file)?)?)
+    }
+
+    fn write(&self, entries: &BTreeMap<String, String>) -> Result<(), ForgeKitError> {
+        write_private(&self.file, toml::to_string(entries)?.as_bytes())
+    }
+
+    fn key(&self, create: bool) -> Result<Option<LessSafeKey>, ForgeKitError> {
+        let bytes = if self.key_file.exists() {
+            std::fs::read(&self.key_file)?
+        } else if create {
+            let mut bytes = vec![0u8; AES_256_GCM.key_len()];
+            SystemRandom::new()
+                .fill(&mut bytes)
+                .map_err(|_| ForgeKitError::Credentials("cannot generate a key".to_string()))?;
+            write_private(&self.key_file, &bytes)?;
+            bytes
+        } else {
+            return Ok(None);
+        };
+        let key = UnboundKey::new(&AES_256_GCM, &bytes).map_err(|_| {
+            ForgeKitError::Credentials(format!("invalid key in {}", self.key_file.display()))
+        })?;
+        Ok(Some(LessSafeKey::new(key)))
+    }
+
+    fn encrypt(key: &LessSafeKey, name: &str, value: &str) -> Result<String, ForgeKitError> {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| ForgeKitError::Credentials("cannot generate a nonce".to_string()))?;
+        let mut sealed = value.as_bytes().to_vec();
+        key.seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(name.as_bytes()),
+            &mut sealed,
+        )
+        .map_err(|_| ForgeKitError::Credentials(format!("cannot encrypt '{}'", name)))?;
+
+        let mut encoded = nonce.to_vec();
+        encoded.extend(sealed);
+        Ok(format!(
+            "{}{}",
+            ENCRYPTED_PREFIX,
+            general_purpose::STANDARD.encode(encoded)
+        ))
+    }
+
+    fn decrypt(
+        &self,
+        key: Option<&LessSafeKey>,
+        name: &str,
+        stored: &str,
+    ) -> Result<String, ForgeKitError> {
+        let invalid = || {
+            ForgeKitError::Credentials(format!(
+                "cannot decrypt '{}' from {}",
+                name,
+                self.file.display()
+            ))
+        };
+        let Some(encoded) = stored.strip_prefix(ENCRYPTED_PREFIX) else {
+            return legacy_decode(stored).ok_or_else(invalid);
+        };
+        let key = key.ok_or_else(invalid)?;
+        let mut bytes = general_purpose::STANDARD
+            .decode(encoded)
+            .map_err(|_| invalid())?;
+        if bytes.len() < NONCE_LEN {
+            return Err(invalid());
+        }
+        let mut sealed = bytes.split_off(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(&bytes).map_err(|_| invalid())?;
+        let plain = key
+            .open_in_place(nonce, Aad::from(name.as_bytes()), &mut sealed)
+            .map_err(|_| invalid())?;
+        String::from_utf8(plain.to_vec()).map_err(|_| invalid())
+    }
+}
+
+impl CredentialStore for EncryptedFileStore {
+    fn backend(&self) -> &'static str {
+        "file"
+    }
+
+    fn get(&self, name: &str) -> Result<Option<String>, ForgeKitError> {
+        let entries = self.read()?;
+        let Some(stored) = entries.get(name) else {
+            return Ok(None);
+        };
+        let key = self.key(false)?;
+        self.decrypt(key.as_ref(), name, stored).map(Some)
+    }
+
+    fn set(&self, name: &str, value: &str) -> Result<(), ForgeKitError> {
+        let key = self.key(true)?.expect("key is created");
+        let mut entries = self.read()?;
+        // Encrypt what older versions left encoded while rewriting the file
+        for (entry, stored) in entries.iter_mut() {
+            if !stored.starts_with(ENCRYPTED_PREFIX) {
+                let plain = self.decrypt(None, entry, stored)?;
+                *stored = Self::encrypt(&key, entry, &plain)?;
+            }
+        }
+        entries.insert(name.to_string(), Self::encrypt(&key, name, value)?);
+        self.write(&entries)
+    }
+
+    fn delete(&self, name: &str) -> Result<bool, ForgeKitError> {
+        let mut entries = self.read()?;
+        let removed = entries.remove(name).is_some();
+        if removed {
+            self.write(&entries)?;
+        }
+        Ok(removed)
+    }
+}
+
+/// Value of an entry stored by an older version
+fn legacy_decode(stored: &str) -> Option<String> {
+    match stored.strip_prefix(LEGACY_PREFIX) {
+        Some(encoded) => String::from_utf8(general_purpose::STANDARD.decode(encoded).ok()?).ok(),
+        None => Some(stored.to_string()),
+    }
+}
+
+/// Write a file only its owner can read, created with that mode so it is
+/// never readable by others
+fn write_private(path: &Path, contents: &[u8]) -> Result<(), ForgeKitError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    atomic::write_private(path, contents)
+}
+
+#[cfg(any(test, feature = "test-util"))]
+pub use memory::MemoryCredentialStore;
+
+#[cfg(any(test, feature = "test-util"))]
+mod memory {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// [`CredentialStore`] kept in memory, for tests
+    #[derive(Debug, Default)]
+    pub struct MemoryCredentialStore {
+        entries: Mutex<BTreeMap<String, String>>,
+    }
+
+    impl MemoryCredentialStore {
+        /// Empty store
+        pub fn new() -> Self {
+            Self::default()
+        }
+    }
+
+    impl CredentialStore for MemoryCredentialStore {
+        fn backend(&self) -> &'static str {
+            "memory"
+        }
+
+        fn get(&self, name: &str) -> Result<Option<String>, ForgeKitError> {
+            Ok(self.entries.lock().unwrap().get(name).cloned())
+        }
+
+        fn set(&self, name: &str, value: &str) -> Result<(), ForgeKitError> {
+            self.entries
+                .lock()
+                .unwrap()
+                .insert(name.to_string(), value.to_string());
+            Ok(())
+        }
+
+        fn delete(&self, name: &str) -> Result<bool, ForgeKitError> {
+            Ok(self.entries.lock().unwrap().remove(name).is_some())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_encrypted_file_store_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = EncryptedFileStore::new(temp_dir.path().join("secrets.toml"));
+
+        assert_eq!(store.get("registry.github.token").unwrap(), None);
+        store.set("registry.github.token", "ghp_abc123").unwrap();
+        assert_eq!(
+            store.get("registry.github.token").unwrap().as_deref(),
+            Some("ghp_abc123")
+        );
+        let raw = std::fs::read_to_string(store.path()).unwrap();
+        assert!(!raw.contains("ghp_abc123"));
+        assert!(temp_dir.path().join("secrets.key").exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            for file in [store.path(), temp_dir.path().join("secrets.key").as_path()] {
+                let mode = std::fs::metadata(file).unwrap().permissions().mode();
+                assert_eq!(mode & 0o777, 0o600);
+            }
+        }
+
+        // A value moved to another name no longer decrypts
+        let swapped = raw.replace("registry.github.token", "other");
+        std::fs::write(store.path(), swapped).unwrap();
+        assert!(store.get("other").is_err());
+
+        assert!(store.delete("other").unwrap());
+        assert!(!store.delete("other").unwrap());
+    }
+
+    #[test]
+    fn test_legacy_entries_are_read_and_reencrypted() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("secrets.toml");
+        std::fs::write(
+            &file,
+            format!(
+                "\"registry.old.token\" = \"encrypted:{}\"\n",
+                general_purpose::STANDARD.encode("legacy-token")
+            ),
+        )
+        .unwrap();
+
+        let store = EncryptedFileStore::new(&file);
+        assert_eq!(
+            store.get("registry.old.token").unwrap().as_deref(),
+            Some("legacy-token")
+        );
+        store.set("api", "s3cr3t").unwrap();
+        assert!(!std::fs::read_to_string(&file)
+            .unwrap()
+            .contains(LEGACY_PREFIX));
+        assert_eq!(
+            store.get("registry.old.token").unwrap().as_deref(),
+            Some("legacy-token")
+        );
+    }
+}
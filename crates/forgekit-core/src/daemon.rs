@@ -0,0 +1,499 @@
+//! Daemon module
+//!
+//! This module implements `forgekit daemon`, a long-running process that
+//! keeps a project's configuration, cache statistics and compiled UI layouts
+//! warm and answers requests over a unix socket, so editors and other front
+//! ends avoid the cost of a cold CLI invocation.
+//!
+//! The protocol is JSON-RPC 2.0 with one JSON document per line. Supported
+//! methods are `ping`, `project/info`, `project/dependencies`,
+//! `project/diagnostics`, `manifest/diagnostics`, `manifest/hover`,
+//! `cache/stats`, `build`, `test`, `package` and `shutdown`.
+//!
+//! The `manifest/*` methods accept the unsaved editor contents as `text`
+//! and fall back to forgekit.toml on disk.
+
+use crate::dashboard::{Dashboard, DashboardAction, TaskStatus};
+use crate::error::ForgeKitError;
+use crate::lint::Linter;
+use crate::lockfile::Lockfile;
+use crate::manifest::{ManifestAnalyzer, Position};
+use crate::registry::{RegistryClient, RegistryConfig};
+use crate::validator::ProjectValidator;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{watch, Mutex};
+
+/// JSON-RPC protocol version
+pub const JSONRPC_VERSION: &str = "2.0";
+
+/// Error code for malformed requests
+pub const PARSE_ERROR: i64 = -32700;
+
+/// Error code for unknown methods
+pub const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Error code for requests that failed while running
+pub const INTERNAL_ERROR: i64 = -32603;
+
+/// How often the daemon checks for configuration and layout changes
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A JSON-RPC request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RpcRequest {
+    /// Protocol version, always `2.0`
+    pub jsonrpc: String,
+    /// Request id echoed in the response
+    #[serde(default)]
+    pub id: Value,
+    /// Method name
+    pub method: String,
+    /// Method parameters
+    #[serde(default)]
+    pub params: Value,
+}
+
+impl RpcRequest {
+    /// Create a request
+    pub fn new(id: u64, method: &str, params: Value) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id: json!(id),
+            method: method.to_string(),
+            params,
+        }
+    }
+}
+
+/// A JSON-RPC error object
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RpcError {
+    /// Error code
+    pub code: i64,
+    /// Error message
+    pub message: String,
+}
+
+/// A JSON-RPC response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RpcResponse {
+    /// Protocol version, always `2.0`
+    pub jsonrpc: String,
+    /// Id of the request being answered
+    pub id: Value,
+    /// Result on success
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    /// Error on failure
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+impl RpcResponse {
+    fn ok(id: Value, result: Value) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    fn err(id: Value, code: i64, message: String) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id,
+            result: None,
+            error: Some(RpcError { code, message }),
+        }
+    }
+}
+
+/// Location of the daemon socket for a project
+pub fn socket_path(project_path: &Path) -> PathBuf {
+    project_path
+        .join("target")
+        .join("forgekit")
+        .join("daemon.sock")
+}
+
+/// Project daemon serving JSON-RPC requests
+pub struct Daemon {
+    project_path: PathBuf,
+    state: Arc<Mutex<Dashboard>>,
+    manifest: ManifestAnalyzer,
+    /// Serializes build, test and package requests
+    tasks: Arc<Mutex<()>>,
+}
+
+impl Daemon {
+    /// Load project state for the daemon
+    pub fn new(project_path: &Path) -> Result<Self, ForgeKitError> {
+        Ok(Self {
+            project_path: project_path.to_path_buf(),
+            state: Arc::new(Mutex::new(Dashboard::load(project_path)?)),
+            manifest: ManifestAnalyzer::new(project_path)
+                .with_registry(RegistryClient::from_login(RegistryConfig::default())?),
+            tasks: Arc::new(Mutex::new(())),
+        })
+    }
+
+    /// Serve requests until a `shutdown` request arrives
+    ///
+    /// Fails if another daemon is already listening for the project. A
+    /// socket left behind by a daemon that crashed is replaced.
+    pub async fn serve(self) -> Result<(), ForgeKitError> {
+        let socket = socket_path(&self.project_path);
+        if socket.exists() {
+            if UnixStream::connect(&socket).await.is_ok() {
+                return Err(ForgeKitError::Daemon(format!(
+                    "a daemon is already running on {}",
+                    socket.display()
+                )));
+            }
+            std::fs::remove_file(&socket)?;
+        }
+        if let Some(parent) = socket.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let listener = UnixListener::bind(&socket)?;
+        tracing::info!("Daemon listening on {}", socket.display());
+
+        let daemon = Arc::new(self);
+        let (shutdown_tx, mut shutdown_rx) = watch::channel(false);
+        let watcher = tokio::spawn(Arc::clone(&daemon).watch());
+
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => {
+                    let (stream, _) = accepted?;
+                    let daemon = Arc::clone(&daemon);
+                    let shutdown_tx = shutdown_tx.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = daemon.connection(stream, shutdown_tx).await {
+                            tracing::warn!("Daemon connection failed: {}", e);
+                        }
+                    });
+                }
+                _ = shutdown_rx.changed() => break,
+            }
+        }
+
+        watcher.abort();
+        std::fs::remove_file(&socket)?;
+        tracing::info!("Daemon stopped");
+        Ok(())
+    }
+
+    /// Handle a single request
+    pub async fn handle(&self, request: RpcRequest) -> RpcResponse {
+        let id = request.id.clone();
+        match self.dispatch(&request.method, &request.params).await {
+            Ok(Some(result)) => RpcResponse::ok(id, result),
+            Ok(None) => RpcResponse::err(
+                id,
+                METHOD_NOT_FOUND,
+                format!("unknown method '{}'", request.method),
+            ),
+            Err(e) => RpcResponse::err(id, INTERNAL_ERROR, e.to_string()),
+        }
+    }
+
+    async fn dispatch(&self, method: &str, params: &Value) -> Result<Option<Value>, ForgeKitError> {
+        let result = match method {
+            "ping" | "shutdown" => json!({ "pid": std::process::id() }),
+            "project/info" => {
+                let state = self.state.lock().await;
+                json!({
+                    "name": state.project_name,
+                    "path": state.project_path,
+                    "build": status_json(&state.build),
+                    "tests": status_json(&state.tests),
+                    "package": status_json(&state.package),
+                })
+            }
+            "project/dependencies" => {
+                let dependencies = self.state.lock().await.dependencies.clone();
+                let lockfile = Lockfile::load(&self.project_path)?;
+                let entries: Vec<Value> = dependencies
+                    .iter()
+                    .map(|dep| {
+                        json!({
+                            "name": dep.name,
+                            "version": dep.version,
+                            "source": dep.source,
+                            "locked": lockfile.get(&dep.name).map(|p| &p.version),
+                        })
+                    })
+                    .collect();
+                json!(entries)
+            }
+            "project/diagnostics" => {
+                // Validation is fast; clippy and rustfmt only run on request
+                let lint = params.get("lint").and_then(Value::as_bool).unwrap_or(false);
+                let diagnostics = if lint {
+                    Linter::run(&self.project_path, false).await?.diagnostics
+                } else {
+                    ProjectValidator::validate_project(&self.project_path)
+                        .await?
+                        .diagnostics()
+                };
+                serde_json::to_value(diagnostics)?
+            }
+            "manifest/diagnostics" => {
+                let text = self.manifest_text(params)?;
+                serde_json::to_value(self.manifest.publish(&text))?
+            }
+            "manifest/hover" => {
+                let text = self.manifest_text(params)?;
+                let position = Position {
+                    line: params.get("line").and_then(Value::as_u64).unwrap_or(0) as u32,
+                    character: params.get("character").and_then(Value::as_u64).unwrap_or(0) as u32,
+                };
+                serde_json::to_value(self.manifest.hover(&text, position))?
+            }
+            "cache/stats" => serde_json::to_value(&self.state.lock().await.cache)?,
+            "build" => self.run_task(DashboardAction::Build).await,
+            "test" => self.run_task(DashboardAction::Test).await,
+            "package" => self.run_task(DashboardAction::Package).await,
+            _ => return Ok(None),
+        };
+        Ok(Some(result))
+    }
+
+    /// Unsaved contents sent by the editor, or forgekit.toml on disk
+    fn manifest_text(&self, params: &Value) -> Result<String, ForgeKitError> {
+        match params.get("text").and_then(Value::as_str) {
+            Some(text) => Ok(text.to_string()),
+            None => Ok(std::fs::read_to_string(
+                self.project_path.join("forgekit.toml"),
+            )?),
+        }
+    }
+
+    /// Run a build, test or package task and report its outcome
+    async fn run_task(&self, action: DashboardAction) -> Value {
+        let _task = self.tasks.lock().await;
+        self.state.lock().await.start(action);
+
+        // Run without holding the state lock so queries stay responsive
+        let mut dashboard = match Dashboard::load(&self.project_path) {
+            Ok(dashboard) => dashboard,
+            Err(e) => return json!({ "state": "failed", "detail": e.to_string() }),
+        };
+        dashboard.run(action).await;
+
+        let status = match action {
+            DashboardAction::Build => dashboard.build,
+            DashboardAction::Test => dashboard.tests,
+            _ => dashboard.package,
+        };
+        let mut state = self.state.lock().await;
+        match action {
+            DashboardAction::Build => state.build = status.clone(),
+            DashboardAction::Test => state.tests = status.clone(),
+            _ => state.package = status.clone(),
+        }
+        if dashboard.test_report.is_some() {
+            state.test_report = dashboard.test_report;
+        }
+        for line in dashboard.logs {
+            state.log(line);
+        }
+        status_json(&status)
+    }
+
+    async fn connection(
+        &self,
+        stream: UnixStream,
+        shutdown: watch::Sender<bool>,
+    ) -> Result<(), ForgeKitError> {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let (response, stop) = match serde_json::from_str::<RpcRequest>(&line) {
+                Ok(request) => {
+                    let stop = request.method == "shutdown";
+                    (self.handle(request).await, stop)
+                }
+                Err(e) => (
+                    RpcResponse::err(Value::Null, PARSE_ERROR, e.to_string()),
+                    false,
+                ),
+            };
+
+            let mut payload = serde_json::to_vec(&response)?;
+            payload.push(b'\n');
+            writer.write_all(&payload).await?;
+
+            if stop {
+                let _ = shutdown.send(true);
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Keep configuration and compiled layouts up to date
+    async fn watch(self: Arc<Self>) {
+        let config_path = self.project_path.join("forgekit.toml");
+        let mut config_modified = modified(&config_path);
+
+        loop {
+            tokio::time::sleep(WATCH_INTERVAL).await;
+
+            let mut state = self.state.lock().await;
+            let current = modified(&config_path);
+            if current != config_modified {
+                config_modified = current;
+                if let Err(e) = state.refresh() {
+                    tracing::warn!("Failed to reload forgekit.toml: {}", e);
+                }
+            }
+            state.poll_dev_server();
+        }
+    }
+}
+
+/// Client for a running daemon
+pub struct DaemonClient {
+    lines: tokio::io::Lines<BufReader<tokio::net::unix::OwnedReadHalf>>,
+    writer: tokio::net::unix::OwnedWriteHalf,
+    next_id: u64,
+}
+
+impl DaemonClient {
+    /// Connect to the daemon of a project
+    pub async fn connect(project_path: &Path) -> Result<Self, ForgeKitError> {
+        let socket = socket_path(project_path);
+        let stream = UnixStream::connect(&socket).await.map_err(|_| {
+            ForgeKitError::Daemon(format!("no daemon running on {}", socket.display()))
+        })?;
+        let (reader, writer) = stream.into_split();
+
+        Ok(Self {
+            lines: BufReader::new(reader).lines(),
+            writer,
+            next_id: 1,
+        })
+    }
+
+    /// Call a method and return its result
+    pub async fn call(&mut self, method: &str, params: Value) -> Result<Value, ForgeKitError> {
+        let request = RpcRequest::new(self.next_id, method, params);
+        self.next_id += 1;
+
+        let mut payload = serde_json::to_vec(&request)?;
+        payload.push(b'\n');
+        self.writer.write_all(&payload).await?;
+
+        let line = self
+            .lines
+            .next_line()
+            .await?
+            .ok_or_else(|| ForgeKitError::Daemon("daemon closed the connection".to_string()))?;
+        let response: RpcResponse = serde_json::from_str(&line)?;
+
+        match response.error {
+            Some(error) => Err(ForgeKitError::Daemon(error.message)),
+            None => Ok(response.result.unwrap_or(Value::Null)),
+        }
+    }
+}
+
+fn status_json(status: &TaskStatus) -> Value {
+    match status {
+        TaskStatus::Idle => json!({ "state": "idle" }),
+        TaskStatus::Running => json!({ "state": "running" }),
+        TaskStatus::Succeeded(detail) => json!({ "state": "succeeded", "detail": detail }),
+        TaskStatus::Failed(detail) => json!({ "state": "failed", "detail": detail }),
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ProjectConfig;
+    use tempfile::TempDir;
+
+    fn project() -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        let config = ProjectConfig {
+            name: "warm".to_string(),
+            ..Default::default()
+        };
+        config.save(temp_dir.path().join("forgekit.toml")).unwrap();
+        temp_dir
+    }
+
+    #[tokio::test]
+    async fn test_handle_methods() {
+        let temp_dir = project();
+        let daemon = Daemon::new(temp_dir.path()).unwrap();
+
+        let info = daemon
+            .handle(RpcRequest::new(1, "project/info", Value::Null))
+            .await;
+        assert_eq!(info.id, json!(1));
+        assert_eq!(info.result.unwrap()["name"], "warm");
+
+        let manifest = daemon
+            .handle(RpcRequest::new(
+                2,
+                "manifest/diagnostics",
+                json!({ "text": "name = \"warm\"\ncolour = 1\n" }),
+            ))
+            .await
+            .result
+            .unwrap();
+        let codes: Vec<&str> = manifest["diagnostics"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|d| d["code"].as_str().unwrap())
+            .collect();
+        assert_eq!(codes, vec!["schema", "unknown-key"]);
+
+        let unknown = daemon
+            .handle(RpcRequest::new(3, "compile", Value::Null))
+            .await;
+        assert_eq!(unknown.error.unwrap().code, METHOD_NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_serve_over_socket() {
+        let temp_dir = project();
+        let server = tokio::spawn(Daemon::new(temp_dir.path()).unwrap().serve());
+
+        let mut client = loop {
+            match DaemonClient::connect(temp_dir.path()).await {
+                Ok(client) => break client,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+
+        let stats = client.call("cache/stats", Value::Null).await.unwrap();
+        assert_eq!(stats["item_count"], 0);
+
+        client.call("shutdown", Value::Null).await.unwrap();
+        server.await.unwrap().unwrap();
+        assert!(!socket_path(temp_dir.path()).exists());
+    }
+}
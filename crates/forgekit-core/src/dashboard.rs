@@ -0,0 +1,226 @@
+//! Dashboard state module
+//!
+//! This module holds the state shown by the interactive `forgekit ui`
+//! dashboard: project dependencies, cache statistics, the outcome of the
+//! last build, test and package runs, and a rolling log. Front ends render
+//! this state and call [`Dashboard::run`] in response to key presses.
+
+use crate::cache::{BuildCache, CacheStats};
+use crate::config::{Dependency, ProjectConfig};
+use crate::dev_server::DevServer;
+use crate::error::ForgeKitError;
+use crate::testing::{TestReport, TestRunner};
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Maximum number of log lines kept
+pub const MAX_LOG_LINES: usize = 500;
+
+/// Action a dashboard user can trigger
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DashboardAction {
+    /// Build the project
+    Build,
+    /// Run the project tests
+    Test,
+    /// Package the project into a .mox file
+    Package,
+    /// Reload configuration and cache statistics
+    Refresh,
+}
+
+/// Status of a long-running task
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskStatus {
+    /// Not run yet
+    Idle,
+    /// Currently running
+    Running,
+    /// Finished successfully with a summary
+    Succeeded(String),
+    /// Failed with an error message
+    Failed(String),
+}
+
+/// State of the interactive dashboard
+#[derive(Debug)]
+pub struct Dashboard {
+    /// Project directory
+    pub project_path: PathBuf,
+    /// Project name
+    pub project_name: String,
+    /// Declared dependencies
+    pub dependencies: Vec<Dependency>,
+    /// Build cache statistics
+    pub cache: CacheStats,
+    /// Status of the last build
+    pub build: TaskStatus,
+    /// Status of the last test run
+    pub tests: TaskStatus,
+    /// Report of the last successful test run
+    pub test_report: Option<TestReport>,
+    /// Status of the last packaging run
+    pub package: TaskStatus,
+    /// Recent log lines, oldest first
+    pub logs: VecDeque<String>,
+    layouts: HashMap<PathBuf, SystemTime>,
+}
+
+impl Dashboard {
+    /// Load the dashboard for a project
+    pub fn load(project_path: &Path) -> Result<Self, ForgeKitError> {
+        let mut dashboard = Self {
+            project_path: project_path.to_path_buf(),
+            project_name: String::new(),
+            dependencies: Vec::new(),
+            cache: CacheStats::new(),
+            build: TaskStatus::Idle,
+            tests: TaskStatus::Idle,
+            test_report: None,
+            package: TaskStatus::Idle,
+            logs: VecDeque::new(),
+            layouts: HashMap::new(),
+        };
+        dashboard.refresh()?;
+        Ok(dashboard)
+    }
+
+    /// Reload configuration and cache statistics
+    pub fn refresh(&mut self) -> Result<(), ForgeKitError> {
+        let config = ProjectConfig::load(self.project_path.join("forgekit.toml"))?;
+        self.project_name = config.name;
+        self.dependencies = config.dependencies;
+
+        let mut cache = BuildCache::new(self.project_path.join(".forgekit").join("cache"))?;
+        cache.load_from_disk()?;
+        self.cache = cache.stats();
+        Ok(())
+    }
+
+    /// Append a line to the log, dropping the oldest lines past the limit
+    pub fn log(&mut self, line: impl Into<String>) {
+        self.logs.push_back(line.into());
+        while self.logs.len() > MAX_LOG_LINES {
+            self.logs.pop_front();
+        }
+    }
+
+    /// Recompile changed UI layouts the way the dev server does
+    pub fn poll_dev_server(&mut self) {
+        for layout in DevServer::reload_layouts(&self.project_path, &mut self.layouts) {
+            self.log(format!("[dev] reloaded {}", layout.display()));
+        }
+    }
+
+    /// Mark the task behind an action as running
+    ///
+    /// Front ends call this and redraw before awaiting [`Dashboard::run`].
+    pub fn start(&mut self, action: DashboardAction) {
+        match action {
+            DashboardAction::Build => self.build = TaskStatus::Running,
+            DashboardAction::Test => self.tests = TaskStatus::Running,
+            DashboardAction::Package => self.package = TaskStatus::Running,
+            DashboardAction::Refresh => {}
+        }
+    }
+
+    /// Run an action and record its outcome
+    pub async fn run(&mut self, action: DashboardAction) {
+        match action {
+            DashboardAction::Build => {
+                self.log("[build] started");
+                self.build = match crate::builder::build(&self.project_path).await {
+                    Ok(_) => TaskStatus::Succeeded("build succeeded".to_string()),
+                    Err(e) => TaskStatus::Failed(e.to_string()),
+                };
+                self.log_status("build", &self.build.clone());
+            }
+            DashboardAction::Test => {
+                self.log("[test] started");
+                match TestRunner::run_tests(&self.project_path).await {
+                    Ok(report) => {
+                        let summary = format!("{}/{} passed", report.passed, report.total);
+                        for line in report.output.lines() {
+                            self.log(format!("[test] {}", line));
+                        }
+                        self.tests = if report.all_passed() {
+                            TaskStatus::Succeeded(summary)
+                        } else {
+                            TaskStatus::Failed(summary)
+                        };
+                        self.test_report = Some(report);
+                    }
+                    Err(e) => self.tests = TaskStatus::Failed(e.to_string()),
+                }
+                self.log_status("test", &self.tests.clone());
+            }
+            DashboardAction::Package => {
+                self.log("[package] started");
+                self.package = match crate::packager::package(&self.project_path).await {
+                    Ok(path) => TaskStatus::Succeeded(path.display().to_string()),
+                    Err(e) => TaskStatus::Failed(e.to_string()),
+                };
+                self.log_status("package", &self.package.clone());
+            }
+            DashboardAction::Refresh => match self.refresh() {
+                Ok(()) => self.log("[refresh] reloaded configuration"),
+                Err(e) => self.log(format!("[refresh] failed: {}", e)),
+            },
+        }
+    }
+
+    fn log_status(&mut self, task: &str, status: &TaskStatus) {
+        match status {
+            TaskStatus::Succeeded(summary) => self.log(format!("[{}] ok: {}", task, summary)),
+            TaskStatus::Failed(error) => self.log(format!("[{}] failed: {}", task, error)),
+            TaskStatus::Idle | TaskStatus::Running => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn project() -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        let config = ProjectConfig {
+            name: "cockpit".to_string(),
+            ..Default::default()
+        };
+        config.save(temp_dir.path().join("forgekit.toml")).unwrap();
+        temp_dir
+    }
+
+    #[test]
+    fn test_load_and_log_limit() {
+        let temp_dir = project();
+        let mut dashboard = Dashboard::load(temp_dir.path()).unwrap();
+        assert_eq!(dashboard.project_name, "cockpit");
+        assert_eq!(dashboard.build, TaskStatus::Idle);
+
+        for i in 0..MAX_LOG_LINES + 10 {
+            dashboard.log(format!("line {}", i));
+        }
+        assert_eq!(dashboard.logs.len(), MAX_LOG_LINES);
+        assert_eq!(dashboard.logs.front().unwrap(), "line 10");
+    }
+
+    #[tokio::test]
+    async fn test_package_failure_is_recorded() {
+        let temp_dir = project();
+        let mut dashboard = Dashboard::load(temp_dir.path()).unwrap();
+
+        dashboard.start(DashboardAction::Package);
+        assert_eq!(dashboard.package, TaskStatus::Running);
+        dashboard.run(DashboardAction::Package).await;
+        assert!(matches!(dashboard.package, TaskStatus::Failed(_)));
+        assert!(dashboard
+            .logs
+            .back()
+            .unwrap()
+            .starts_with("[package] failed"));
+    }
+}
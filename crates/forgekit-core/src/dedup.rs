@@ -0,0 +1,224 @@
+//! Dependency deduplication module
+//!
+//! This module finds dependencies that workspace members pin to different
+//! versions, estimates what the duplicates cost and suggests unifying them
+//! on a single version.
+
+use crate::error::ForgeKitError;
+use crate::version_manager::compare_versions;
+use crate::workspace::Workspace;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Estimated extra build time per duplicate version, in seconds
+const BUILD_SECS_PER_DUPLICATE: u64 = 6;
+
+/// Estimated extra binary size per duplicate version, in kilobytes
+const SIZE_KB_PER_DUPLICATE: u64 = 120;
+
+/// A dependency required at more than one version
+#[derive(Debug, Clone)]
+pub struct DuplicateDependency {
+    /// Dependency name
+    pub name: String,
+    /// Members requiring each version
+    pub versions: BTreeMap<String, Vec<String>>,
+    /// Version every member should use
+    pub suggested_version: String,
+    /// Estimated extra build time in seconds
+    pub estimated_build_secs: u64,
+    /// Estimated extra binary size in kilobytes
+    pub estimated_size_kb: u64,
+}
+
+/// A suggested change to a member's forgekit.toml
+#[derive(Debug, Clone)]
+pub struct DedupEdit {
+    /// Member name
+    pub member: String,
+    /// Path to the member's forgekit.toml
+    pub config_path: PathBuf,
+    /// Dependency name
+    pub dependency: String,
+    /// Currently required version
+    pub from: String,
+    /// Suggested version
+    pub to: String,
+}
+
+/// Deduplication report for a workspace
+#[derive(Debug, Clone, Default)]
+pub struct DedupReport {
+    /// Dependencies with more than one version
+    pub duplicates: Vec<DuplicateDependency>,
+    /// Edits that unify the duplicates
+    pub edits: Vec<DedupEdit>,
+}
+
+impl DedupReport {
+    /// Total estimated extra build time in seconds
+    pub fn total_build_secs(&self) -> u64 {
+        self.duplicates.iter().map(|d| d.estimated_build_secs).sum()
+    }
+
+    /// Total estimated extra binary size in kilobytes
+    pub fn total_size_kb(&self) -> u64 {
+        self.duplicates.iter().map(|d| d.estimated_size_kb).sum()
+    }
+}
+
+/// Workspace dependency deduplicator
+pub struct Deduplicator;
+
+impl Deduplicator {
+    /// Analyze a workspace for duplicate dependency versions
+    ///
+    /// The suggested version is the highest one already in use.
+    pub fn analyze(workspace: &Workspace) -> DedupReport {
+        let mut usage: BTreeMap<String, BTreeMap<String, Vec<String>>> = BTreeMap::new();
+        for member in &workspace.members {
+            for dep in &member.config.dependencies {
+                usage
+                    .entry(dep.name.clone())
+                    .or_default()
+                    .entry(dep.version.clone())
+                    .or_default()
+                    .push(member.name.clone());
+            }
+        }
+
+        let mut report = DedupReport::default();
+        for (name, versions) in usage {
+            if versions.len() < 2 {
+                continue;
+            }
+
+            let suggested_version = versions
+                .keys()
+                .max_by(|a, b| compare_versions(a, b))
+                .cloned()
+                .unwrap_or_default();
+            let extra = versions.len() as u64 - 1;
+
+            for member in &workspace.members {
+                for dep in &member.config.dependencies {
+                    if dep.name == name && dep.version != suggested_version {
+                        report.edits.push(DedupEdit {
+                            member: member.name.clone(),
+                            config_path: member.path.join("forgekit.toml"),
+                            dependency: name.clone(),
+                            from: dep.version.clone(),
+                            to: suggested_version.clone(),
+                        });
+                    }
+                }
+            }
+
+            report.duplicates.push(DuplicateDependency {
+                name,
+                versions,
+                suggested_version,
+                estimated_build_secs: extra * BUILD_SECS_PER_DUPLICATE,
+                estimated_size_kb: extra * SIZE_KB_PER_DUPLICATE,
+            });
+        }
+
+        report
+    }
+
+    /// Apply the suggested edits to each member's forgekit.toml
+    pub fn apply(workspace: &Workspace, report: &DedupReport) -> Result<usize, ForgeKitError> {
+        let mut applied = 0;
+
+        for member in &workspace.members {
+            let edits: Vec<&DedupEdit> = report
+                .edits
+                .iter()
+                .filter(|e| e.member == member.name)
+                .collect();
+            if edits.is_empty() {
+                continue;
+            }
+
+            let mut config = member.config.clone();
+            for edit in edits {
+                if let Some(dep) = config
+                    .dependencies
+                    .iter_mut()
+                    .find(|d| d.name == edit.dependency)
+                {
+                    dep.version = edit.to.clone();
+                    applied += 1;
+                }
+            }
+            config.save(member.path.join("forgekit.toml"))?;
+        }
+
+        Ok(applied)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Dependency, ProjectConfig};
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    fn write_member(root: &Path, name: &str, deps: &[(&str, &str)]) {
+        let path = root.join("crates").join(name);
+        std::fs::create_dir_all(&path).unwrap();
+        std::fs::write(path.join("Cargo.toml"), "[package]").unwrap();
+        let config = ProjectConfig {
+            name: name.to_string(),
+            dependencies: deps
+                .iter()
+                .map(|(n, v)| Dependency {
+                    name: n.to_string(),
+                    version: v.to_string(),
+                    source: None,
+                })
+                .collect(),
+            ..Default::default()
+        };
+        config.save(path.join("forgekit.toml")).unwrap();
+    }
+
+    fn setup() -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::write(
+            root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"crates/*\"]\n",
+        )
+        .unwrap();
+        write_member(root, "app", &[("serde", "1.0.0"), ("tokio", "1.0.0")]);
+        write_member(root, "lib", &[("serde", "1.2.0"), ("tokio", "1.0.0")]);
+        temp_dir
+    }
+
+    #[test]
+    fn test_analyze_finds_duplicates() {
+        let temp_dir = setup();
+        let workspace = Workspace::load(temp_dir.path()).unwrap();
+        let report = Deduplicator::analyze(&workspace);
+
+        assert_eq!(report.duplicates.len(), 1);
+        assert_eq!(report.duplicates[0].name, "serde");
+        assert_eq!(report.duplicates[0].suggested_version, "1.2.0");
+        assert_eq!(report.edits.len(), 1);
+        assert_eq!(report.edits[0].member, "app");
+        assert_eq!(report.total_build_secs(), BUILD_SECS_PER_DUPLICATE);
+    }
+
+    #[test]
+    fn test_apply_unifies_versions() {
+        let temp_dir = setup();
+        let workspace = Workspace::load(temp_dir.path()).unwrap();
+        let report = Deduplicator::analyze(&workspace);
+
+        assert_eq!(Deduplicator::apply(&workspace, &report).unwrap(), 1);
+        let workspace = Workspace::load(temp_dir.path()).unwrap();
+        assert!(Deduplicator::analyze(&workspace).duplicates.is_empty());
+    }
+}
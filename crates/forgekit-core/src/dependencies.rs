@@ -207,21 +207,21 @@ impl DependencyManager {
         // Update each dependency to latest compatible version
         for dep in &config.dependencies {
             // Placeholder for update logic
-            println!("Updating {} to latest version", dep.name);
+            tracing::info!("Updating {} to latest version", dep.name);
         }
 
         Ok(())
     }
 
     async fn install_dependency(&self, name: &str, version: &str) -> Result<(), ForgeKitError> {
-        println!("Installing {} v{}", name, version);
+        tracing::info!("Installing {} v{}", name, version);
         // Placeholder for actual installation logic
         // This would download and extract the package
         Ok(())
     }
 
     async fn uninstall_dependency(&self, name: &str) -> Result<(), ForgeKitError> {
-        println!("Removing {}", name);
+        tracing::info!("Removing {}", name);
         // Placeholder for actual removal logic
         Ok(())
     }
@@ -0,0 +1,163 @@
+//! Development certificate module
+//!
+//! This module provides the self-signed certificate the development server
+//! uses for HTTPS, so that secure-context features (service workers, camera
+//! access, clipboard) can be tried from other devices. The certificate is
+//! kept in `.forgekit/dev-cert/` and reused as long as it covers the
+//! requested hosts and has not expired, so a device only has to trust it
+//! once.
+
+use crate::atomic;
+use crate::error::ForgeKitError;
+use chrono::{DateTime, Datelike, Duration, Utc};
+use rcgen::{CertificateParams, DistinguishedName, DnType, KeyPair};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// How long a generated certificate is valid
+const VALIDITY_DAYS: i64 = 365;
+
+/// Hosts every certificate covers
+const LOCAL_HOSTS: [&str; 2] = ["localhost", "127.0.0.1"];
+
+/// A development certificate and the hosts it covers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DevCertificate {
+    /// Host names and IP addresses in the certificate
+    pub hosts: Vec<String>,
+    /// When the certificate expires
+    pub expires_at: DateTime<Utc>,
+    /// Whether the certificate was generated by this call rather than reused
+    #[serde(skip)]
+    pub generated: bool,
+    #[serde(skip)]
+    dir: PathBuf,
+}
+
+impl DevCertificate {
+    /// Certificate covering `hosts`, reusing the project's if it still fits
+    ///
+    /// A new certificate also covers the hosts of the one it replaces, so
+    /// switching between hosts does not invalidate trust already granted.
+    pub fn load_or_generate(project: &Path, hosts: &[String]) -> Result<Self, ForgeKitError> {
+        let dir = cert_dir(project);
+        let existing = std::fs::read_to_string(dir.join("cert.json"))
+            .ok()
+            .and_then(|metadata| serde_json::from_str::<DevCertificate>(&metadata).ok())
+            .filter(|_| dir.join("cert.pem").is_file() && dir.join("key.pem").is_file());
+
+        if let Some(existing) = &existing {
+            let covered = hosts.iter().all(|host| existing.hosts.contains(host));
+            if covered && existing.expires_at > Utc::now() + Duration::days(1) {
+                return Ok(Self {
+                    generated: false,
+                    dir,
+                    ..existing.clone()
+                });
+            }
+        }
+
+        let mut all_hosts: Vec<String> = LOCAL_HOSTS.iter().map(|h| h.to_string()).collect();
+        for host in existing.iter().flat_map(|c| &c.hosts).chain(hosts) {
+            if !all_hosts.contains(host) {
+                all_hosts.push(host.clone());
+            }
+        }
+        Self::generate(dir, all_hosts)
+    }
+
+    /// Certificate file (PEM)
+    pub fn cert_path(&self) -> PathBuf {
+        self.dir.join("cert.pem")
+    }
+
+    /// Private key file (PKCS#8 PEM)
+    pub fn key_path(&self) -> PathBuf {
+        self.dir.join("key.pem")
+    }
+
+    /// TLS identity for serving with the certificate
+    pub fn identity(&self) -> Result<native_tls::Identity, ForgeKitError> {
+        let cert = std::fs::read(self.cert_path())?;
+        let key = std::fs::read(self.key_path())?;
+        native_tls::Identity::from_pkcs8(&cert, &key).map_err(|e| ForgeKitError::Tls(e.to_string()))
+    }
+
+    fn generate(dir: PathBuf, hosts: Vec<String>) -> Result<Self, ForgeKitError> {
+        let tls_error = |e: rcgen::Error| ForgeKitError::Tls(e.to_string());
+        let now = Utc::now();
+        let expires_at = now + Duration::days(VALIDITY_DAYS);
+        let date =
+            |at: DateTime<Utc>| rcgen::date_time_ymd(at.year(), at.month() as u8, at.day() as u8);
+
+        let mut params = CertificateParams::new(hosts.clone()).map_err(tls_error)?;
+        params.distinguished_name = DistinguishedName::new();
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "ForgeKit development server");
+        params.not_before = date(now - Duration::days(1));
+        params.not_after = date(expires_at);
+        let key_pair = KeyPair::generate().map_err(tls_error)?;
+        let cert = params.self_signed(&key_pair).map_err(tls_error)?;
+
+        let certificate = Self {
+            hosts,
+            expires_at,
+            generated: true,
+            dir,
+        };
+        std::fs::create_dir_all(&certificate.dir)?;
+        atomic::write(certificate.cert_path(), cert.pem())?;
+        atomic::write_private(certificate.key_path(), key_pair.serialize_pem())?;
+        atomic::write(
+            certificate.dir.join("cert.json"),
+            serde_json::to_string_pretty(&certificate)?,
+        )?;
+        tracing::info!(
+            "Generated development certificate for {}",
+            certificate.hosts.join(", ")
+        );
+        Ok(certificate)
+    }
+}
+
+/// Directory of the project's development certificate
+pub fn cert_dir(project: &Path) -> PathBuf {
+    project.join(".forgekit").join("dev-cert")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_certificate_is_reused_until_hosts_change() {
+        let temp_dir = TempDir::new().unwrap();
+        let lan = vec!["192.168.1.20".to_string()];
+
+        let first = DevCertificate::load_or_generate(temp_dir.path(), &lan).unwrap();
+        assert!(first.generated);
+        assert!(first.hosts.contains(&"localhost".to_string()));
+        assert!(first.identity().is_ok());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(first.key_path())
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let again = DevCertificate::load_or_generate(temp_dir.path(), &[]).unwrap();
+        assert!(!again.generated);
+        assert_eq!(again.hosts, first.hosts);
+
+        let other = vec!["devbox.local".to_string()];
+        let widened = DevCertificate::load_or_generate(temp_dir.path(), &other).unwrap();
+        assert!(widened.generated);
+        assert!(widened.hosts.contains(&"192.168.1.20".to_string()));
+        assert!(widened.hosts.contains(&"devbox.local".to_string()));
+    }
+}
@@ -0,0 +1,340 @@
+//! Development environment module
+//!
+//! `forgekit env scaffold` writes a Nix flake or a VS Code devcontainer so
+//! contributors get the same environment: the Rust toolchain the project
+//! builds with (`[toolchain] channel`, else the one of `[build.toolchain]`)
+//! with the components ledokoz builds need, the targets of `[ci]`, the
+//! system packages builds rely on, like the linker of `[build.profile]`, and
+//! the ForgeKit version that wrote the files.
+
+use crate::config::ProjectConfig;
+use crate::error::ForgeKitError;
+use crate::toolchain::REQUIRED_COMPONENTS;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// File holding the Nix flake
+pub const FLAKE_FILE: &str = "flake.nix";
+
+/// Devcontainer configuration, relative to the project
+pub const DEVCONTAINER_FILE: &str = ".devcontainer/devcontainer.json";
+
+/// Directory, relative to the project, the pinned ForgeKit is installed in
+const TOOLS_DIR: &str = ".forgekit/tools";
+
+/// Rustup components besides the ones ledokoz builds need
+const EXTRA_COMPONENTS: &[&str] = &["rustfmt", "clippy"];
+
+/// A system package, named as Nix and Debian name it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SystemPackage {
+    /// Attribute in nixpkgs
+    pub nix: &'static str,
+    /// Debian package
+    pub apt: &'static str,
+}
+
+/// Packages every environment gets
+const BASE_PACKAGES: &[SystemPackage] = &[
+    SystemPackage {
+        nix: "git",
+        apt: "git",
+    },
+    SystemPackage {
+        nix: "pkg-config",
+        apt: "pkg-config",
+    },
+    SystemPackage {
+        nix: "openssl",
+        apt: "libssl-dev",
+    },
+];
+
+/// What a development environment of a project provides
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DevEnvironment {
+    /// Project name
+    pub name: String,
+    /// Rust toolchain channel, like `nightly` or `1.78`
+    pub channel: String,
+    /// Rustup components
+    pub components: Vec<String>,
+    /// Extra compilation targets
+    pub targets: Vec<String>,
+    /// System packages
+    pub packages: Vec<SystemPackage>,
+    /// ForgeKit version installed
+    pub forgekit_version: String,
+}
+
+impl DevEnvironment {
+    /// Environment building the project configured by `config`
+    pub fn for_project(config: &ProjectConfig) -> Self {
+        let channel = config
+            .toolchain
+            .channel
+            .clone()
+            .unwrap_or_else(|| config.build.toolchain.channel.clone());
+        let components = REQUIRED_COMPONENTS
+            .iter()
+            .chain(EXTRA_COMPONENTS)
+            .map(|component| component.to_string())
+            .collect();
+        let mut targets = config.ci.targets.clone();
+        if let Some(fallback) = &config.build.toolchain.fallback_target {
+            if !targets.contains(fallback) {
+                targets.push(fallback.clone());
+            }
+        }
+        let mut packages = BASE_PACKAGES.to_vec();
+        if let Some(linker) = config.build.profile.linker {
+            packages.push(SystemPackage {
+                nix: linker.as_str(),
+                apt: linker.as_str(),
+            });
+        }
+
+        Self {
+            name: config.name.clone(),
+            channel,
+            components,
+            targets,
+            packages,
+            forgekit_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    /// Command installing the pinned ForgeKit into `root`
+    fn install_forgekit(&self, root: &str) -> String {
+        format!(
+            "cargo install forgekit-cli --version ={} --locked --root {}",
+            self.forgekit_version, root
+        )
+    }
+
+    /// Nix flake with a development shell
+    ///
+    /// The toolchain comes from rust-overlay; `nix develop` records the
+    /// revisions of nixpkgs and the overlay in flake.lock.
+    pub fn flake(&self) -> String {
+        let nix_list = |items: &[String]| {
+            items
+                .iter()
+                .map(|item| format!("\"{}\"", item))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let packages = self
+            .packages
+            .iter()
+            .map(|package| format!("pkgs.{}", package.nix))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        r#"{
+  description = "Development environment of {name}";
+
+  inputs = {
+    nixpkgs.url = "github:NixOS/nixpkgs/nixos-unstable";
+    flake-utils.url = "github:numtide/flake-utils";
+    rust-overlay = {
+      url = "github:oxalica/rust-overlay";
+      inputs.nixpkgs.follows = "nixpkgs";
+    };
+  };
+
+  outputs = { nixpkgs, flake-utils, rust-overlay, ... }:
+    flake-utils.lib.eachDefaultSystem (system:
+      let
+        pkgs = import nixpkgs {
+          inherit system;
+          overlays = [ (import rust-overlay) ];
+        };
+        toolchain = {toolchain}.override {
+          extensions = [ {components} ];
+          targets = [ {targets} ];
+        };
+      in
+      {
+        devShells.default = pkgs.mkShell {
+          packages = [ toolchain {packages} ];
+          shellHook = ''
+            export PATH="$PWD/{tools}/bin:$PATH"
+            if [ "$(forgekit --version 2>/dev/null)" != "forgekit {version}" ]; then
+              {install}
+            fi
+          '';
+        };
+      });
+}
+"#
+        .replace("{name}", &self.name)
+        .replace("{toolchain}", &nix_toolchain(&self.channel))
+        .replace("{components}", &nix_list(&self.components))
+        .replace("{targets}", &nix_list(&self.targets))
+        .replace("{packages}", &packages)
+        .replace("{tools}", TOOLS_DIR)
+        .replace("{version}", &self.forgekit_version)
+        .replace(
+            "{install}",
+            &self.install_forgekit(&format!("\"$PWD/{}\"", TOOLS_DIR)),
+        )
+    }
+
+    /// VS Code devcontainer configuration
+    pub fn devcontainer(&self) -> serde_json::Value {
+        let apt = self
+            .packages
+            .iter()
+            .map(|package| package.apt)
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut setup = vec![
+            format!(
+                "sudo apt-get update && sudo apt-get install -y --no-install-recommends {}",
+                apt
+            ),
+            format!("rustup component add {}", self.components.join(" ")),
+        ];
+        if !self.targets.is_empty() {
+            setup.push(format!("rustup target add {}", self.targets.join(" ")));
+        }
+        setup.push(self.install_forgekit("/usr/local/cargo"));
+
+        serde_json::json!({
+            "name": self.name,
+            "image": "mcr.microsoft.com/devcontainers/base:bookworm",
+            "features": {
+                "ghcr.io/devcontainers/features/rust:1": {
+                    "version": self.channel,
+                    "profile": "minimal",
+                },
+            },
+            "postCreateCommand": setup.join(" && "),
+            "customizations": {
+                "vscode": {
+                    "extensions": ["rust-lang.rust-analyzer", "tamasfe.even-better-toml"],
+                },
+            },
+        })
+    }
+}
+
+/// rust-overlay expression selecting `channel`
+fn nix_toolchain(channel: &str) -> String {
+    match channel {
+        "stable" | "beta" => format!("pkgs.rust-bin.{}.latest.default", channel),
+        "nightly" => {
+            "(pkgs.rust-bin.selectLatestNightlyWith (toolchain: toolchain.default))".to_string()
+        }
+        _ => {
+            if let Some(date) = channel.strip_prefix("nightly-") {
+                return format!("pkgs.rust-bin.nightly.\"{}\".default", date);
+            }
+            // rust-overlay names stable releases by their full version
+            let version = match channel.split('.').count() {
+                2 => format!("{}.0", channel),
+                _ => channel.to_string(),
+            };
+            format!("pkgs.rust-bin.stable.\"{}\".default", version)
+        }
+    }
+}
+
+/// Write the Nix flake of the project at `project_path`
+///
+/// An existing flake is only replaced with `force`.
+pub fn write_flake(project_path: &Path, force: bool) -> Result<PathBuf, ForgeKitError> {
+    let environment = load(project_path)?;
+    write(&project_path.join(FLAKE_FILE), &environment.flake(), force)
+}
+
+/// Write the devcontainer configuration of the project at `project_path`
+///
+/// An existing configuration is only replaced with `force`.
+pub fn write_devcontainer(project_path: &Path, force: bool) -> Result<PathBuf, ForgeKitError> {
+    let environment = load(project_path)?;
+    let contents = serde_json::to_string_pretty(&environment.devcontainer())? + "\n";
+    write(&project_path.join(DEVCONTAINER_FILE), &contents, force)
+}
+
+fn load(project_path: &Path) -> Result<DevEnvironment, ForgeKitError> {
+    let config = ProjectConfig::load(project_path.join("forgekit.toml"))?;
+    Ok(DevEnvironment::for_project(&config))
+}
+
+fn write(path: &Path, contents: &str, force: bool) -> Result<PathBuf, ForgeKitError> {
+    if path.exists() && !force {
+        return Err(ForgeKitError::InvalidConfig(format!(
+            "{} already exists; pass --force to replace it",
+            path.display()
+        )));
+    }
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, contents)?;
+    Ok(path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Linker;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_environment_follows_the_project() {
+        let mut config = ProjectConfig {
+            name: "demo".to_string(),
+            ..Default::default()
+        };
+        config.toolchain.channel = Some("1.78".to_string());
+        config.ci.targets = vec!["aarch64-unknown-linux-gnu".to_string()];
+        config.build.profile.linker = Some(Linker::Mold);
+        let environment = DevEnvironment::for_project(&config);
+
+        assert_eq!(environment.channel, "1.78");
+        assert!(environment.components.contains(&"rust-src".to_string()));
+        assert!(environment.packages.iter().any(|p| p.nix == "mold"));
+
+        let flake = environment.flake();
+        assert!(flake.contains("pkgs.rust-bin.stable.\"1.78.0\".default.override"));
+        assert!(flake.contains("targets = [ \"aarch64-unknown-linux-gnu\" ];"));
+        assert!(flake.contains("pkgs.git pkgs.pkg-config pkgs.openssl pkgs.mold"));
+        assert!(flake.contains(&format!(
+            "--version ={} --locked",
+            env!("CARGO_PKG_VERSION")
+        )));
+
+        let devcontainer = environment.devcontainer();
+        assert_eq!(
+            devcontainer["features"]["ghcr.io/devcontainers/features/rust:1"]["version"],
+            "1.78"
+        );
+        let setup = devcontainer["postCreateCommand"].as_str().unwrap();
+        assert!(setup.contains("libssl-dev mold"));
+        assert!(setup.contains("rustup target add aarch64-unknown-linux-gnu"));
+    }
+
+    #[test]
+    fn test_existing_files_are_kept_without_force() {
+        let temp_dir = TempDir::new().unwrap();
+        ProjectConfig::default()
+            .save(temp_dir.path().join("forgekit.toml"))
+            .unwrap();
+
+        let flake = write_flake(temp_dir.path(), false).unwrap();
+        assert!(std::fs::read_to_string(&flake)
+            .unwrap()
+            .contains("selectLatestNightlyWith"));
+        assert!(write_flake(temp_dir.path(), false).is_err());
+        assert!(write_flake(temp_dir.path(), true).is_ok());
+
+        let devcontainer = write_devcontainer(temp_dir.path(), false).unwrap();
+        assert_eq!(devcontainer, temp_dir.path().join(DEVCONTAINER_FILE));
+        let parsed: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(devcontainer).unwrap()).unwrap();
+        assert_eq!(parsed["name"], "unnamed");
+    }
+}
@@ -1,22 +1,89 @@
 //! Hot reload development server module
 //!
 //! This module provides a development server with hot reload capabilities.
+//! It recompiles UI layouts as they change and serves the project's
+//! `assets/` and generated docs over HTTP (see [`crate::static_files`]), or
+//! HTTPS with a self-signed certificate (see [`crate::dev_cert`]). Bound to
+//! a LAN address, it can be opened from phones and other devices through
+//! the QR code of its URL. Pages subscribe to [`RELOAD_PATH`], a stream of
+//! server-sent events, to learn when to reload: changed assets and layouts
+//! are announced by URL so they can be swapped in place, without running
+//! cargo or reloading the whole page.
 
+use crate::cancel::CancellationToken;
+use crate::config::ProjectConfig;
+use crate::dev_cert::DevCertificate;
+use crate::doc_generator::DocConfig;
 use crate::error::ForgeKitError;
-use std::path::Path;
+use crate::ports;
+use crate::static_files::{self, Mount};
+use crate::ui;
+use crate::watch::{self, FileSnapshot, HOT_SWAPPABLE};
+use hyper::service::service_fn;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::sync::broadcast;
 
 /// Development server configuration
 #[derive(Debug, Clone)]
 pub struct DevServerConfig {
+    /// Port the HTTP server listens on
     pub port: u16,
     pub watch_patterns: Vec<String>,
+    /// Move to the next free port instead of failing when `port` is taken
+    pub auto_ports: bool,
+    /// Address to listen on; `0.0.0.0` makes the server reachable on the LAN
+    pub bind: IpAddr,
+    /// Host name in the server's URL and certificate, instead of the bound
+    /// or detected LAN address
+    pub host: Option<String>,
+    /// Serve HTTPS with the project's self-signed development certificate
+    pub https: bool,
 }
 
+/// A reload announced to the pages of the dev server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reload {
+    /// Reload the page, for the given reason
+    Page(String),
+    /// Swap the resources at these URL paths in place
+    Assets(Vec<String>),
+}
+
+impl std::fmt::Display for Reload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Reload::Page(reason) => write!(f, "{}", reason),
+            Reload::Assets(paths) => write!(f, "{}", paths.join(", ")),
+        }
+    }
+}
+
+/// Path of the server-sent event stream announcing reloads
+pub const RELOAD_PATH: &str = "/__forgekit/reload";
+
+/// Environment variable holding the dev server port
+pub const DEV_PORT_ENV: &str = "FORGEKIT_DEV_PORT";
+
 impl Default for DevServerConfig {
     fn default() -> Self {
         Self {
             port: 8080,
-            watch_patterns: vec!["src/**/*.rs".to_string(), "assets/**/*".to_string()],
+            watch_patterns: vec![
+                "src/**/*.rs".to_string(),
+                "assets/**/*".to_string(),
+                "ui/**/*.xml".to_string(),
+            ],
+            auto_ports: false,
+            bind: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            host: None,
+            https: false,
         }
     }
 }
@@ -24,33 +91,275 @@ impl Default for DevServerConfig {
 /// Development server
 pub struct DevServer {
     config: DevServerConfig,
+    reload: broadcast::Sender<Reload>,
 }
 
 impl DevServer {
     /// Create a new development server
     pub fn new(config: DevServerConfig) -> Self {
-        Self { config }
+        let (reload, _) = broadcast::channel(16);
+        Self { config, reload }
+    }
+
+    /// Sender of the reload channel, whose messages are sent to the pages
+    /// subscribed to [`RELOAD_PATH`]
+    pub fn reloader(&self) -> broadcast::Sender<Reload> {
+        self.reload.clone()
     }
 
     /// Start the development server
     pub async fn start(path: &Path) -> Result<(), ForgeKitError> {
+        Self::start_with_cancel(path, &CancellationToken::new()).await
+    }
+
+    /// Start the development server, stopping when `cancel` is triggered
+    pub async fn start_with_cancel(
+        path: &Path,
+        cancel: &CancellationToken,
+    ) -> Result<(), ForgeKitError> {
         let config = DevServerConfig::default();
         let server = Self::new(config);
-        server.run(path).await
+        server
+            .run(path, cancel, |url| {
+                tracing::info!("Development server listening on {}", url)
+            })
+            .await
+    }
+
+    /// Directories served over HTTP: `/assets`, the compiled layouts under
+    /// `/ui`, the generated docs under `/docs` and the rustdoc output under
+    /// `/rustdoc`
+    pub fn mounts(path: &Path) -> Vec<Mount> {
+        vec![
+            Mount::new("assets", path.join("assets")),
+            Mount::new("ui", ui::compiled_dir(path)),
+            Mount::new("docs", path.join(DocConfig::default().output_dir)),
+            Mount::new("rustdoc", path.join("target").join("doc")),
+        ]
+    }
+
+    /// URL paths the dev server serves files at; files outside its
+    /// mounts are left out
+    pub fn resource_urls(path: &Path, files: &[PathBuf]) -> Vec<String> {
+        let mounts = Self::mounts(path);
+        files
+            .iter()
+            .filter_map(|file| {
+                mounts.iter().find_map(|mount| {
+                    let relative = file.strip_prefix(&mount.dir).ok()?;
+                    let segments: Vec<String> = relative
+                        .components()
+                        .map(|c| c.as_os_str().to_string_lossy().to_string())
+                        .collect();
+                    Some(format!("{}/{}", mount.prefix, segments.join("/")))
+                })
+            })
+            .collect()
     }
 
-    /// Run the development server
-    async fn run(&self, path: &Path) -> Result<(), ForgeKitError> {
-        tracing::info!("Starting development server on port {}", self.config.port);
+    /// Run the development server until cancelled
+    ///
+    /// Changed assets and layouts are hot-swapped: layouts are recompiled
+    /// and the pages are told which resources changed. `ready` is called
+    /// with the server's URL once it is listening.
+    pub async fn run(
+        &self,
+        path: &Path,
+        cancel: &CancellationToken,
+        ready: impl FnOnce(&str),
+    ) -> Result<(), ForgeKitError> {
         tracing::info!("Watching patterns: {:?}", self.config.watch_patterns);
         tracing::info!("Project path: {:?}", path);
+        let hot_swap = async {
+            let mut snapshot = FileSnapshot::default();
+            loop {
+                let changed = snapshot.changes(path, HOT_SWAPPABLE);
+                if !changed.is_empty() {
+                    let (swapped, errors) = watch::hot_swap(path, &changed);
+                    for error in errors {
+                        tracing::error!("{}", error);
+                    }
+                    let urls = Self::resource_urls(path, &swapped);
+                    if !urls.is_empty() {
+                        tracing::info!("Hot-swapped {}", urls.join(", "));
+                        // Nobody may be listening yet
+                        let _ = self.reload.send(Reload::Assets(urls));
+                    }
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(tokio::time::Duration::from_millis(250)) => {}
+                    _ = cancel.cancelled() => return,
+                }
+            }
+        };
+        tokio::select! {
+            served = self.serve(path, cancel, ready) => served,
+            () = hot_swap => Ok(()),
+        }
+    }
+
+    /// Serve the project over HTTP(S) until cancelled, without watching it
+    pub async fn serve(
+        &self,
+        path: &Path,
+        cancel: &CancellationToken,
+        ready: impl FnOnce(&str),
+    ) -> Result<(), ForgeKitError> {
+        // Probe the service ports too so the app started next to it can bind them
+        let project = ProjectConfig::load(path.join("forgekit.toml")).unwrap_or_default();
+        let checks = ports::check(&project, &[("dev-server", self.config.port, DEV_PORT_ENV)]);
+        ports::resolve(&checks, self.config.auto_ports)?;
+        let port = checks
+            .last()
+            .map_or(self.config.port, ports::PortCheck::assigned);
+
+        let listener = TcpListener::bind(SocketAddr::new(self.config.bind, port)).await?;
+        let tls = if self.config.https {
+            let certificate = DevCertificate::load_or_generate(path, &[self.host()])?;
+            let acceptor = native_tls::TlsAcceptor::new(certificate.identity()?)
+                .map_err(|e| ForgeKitError::Tls(e.to_string()))?;
+            Some(tokio_native_tls::TlsAcceptor::from(acceptor))
+        } else {
+            None
+        };
+        ready(&self.url(listener.local_addr()?.port()));
+
+        Self::accept(
+            listener,
+            tls,
+            Arc::new(Self::mounts(path)),
+            self.reload.clone(),
+            cancel,
+        )
+        .await;
+        tracing::info!("Development server stopped");
+        Ok(())
+    }
+
+    /// URL of the server when listening on `port`
+    pub fn url(&self, port: u16) -> String {
+        let scheme = if self.config.https { "https" } else { "http" };
+        let host = self.host();
+        if host.contains(':') {
+            format!("{}://[{}]:{}", scheme, host, port)
+        } else {
+            format!("{}://{}:{}", scheme, host, port)
+        }
+    }
+
+    /// Whether other devices can reach the server
+    pub fn is_public(&self) -> bool {
+        !self.config.bind.is_loopback()
+    }
+
+    /// Host of the server's URL: the configured host, else the bound
+    /// address, or the LAN address when bound to all interfaces
+    fn host(&self) -> String {
+        if let Some(host) = &self.config.host {
+            return host.clone();
+        }
+        let bind = self.config.bind;
+        if bind.is_loopback() {
+            "localhost".to_string()
+        } else if bind.is_unspecified() {
+            lan_address().map_or_else(|| "localhost".to_string(), |ip| ip.to_string())
+        } else {
+            bind.to_string()
+        }
+    }
 
-        // Simulate server running
+    /// Serve connections until cancelled
+    async fn accept(
+        listener: TcpListener,
+        tls: Option<tokio_native_tls::TlsAcceptor>,
+        mounts: Arc<Vec<Mount>>,
+        reload: broadcast::Sender<Reload>,
+        cancel: &CancellationToken,
+    ) {
         loop {
-            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+            let stream = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        tracing::warn!("Development server: {}", e);
+                        continue;
+                    }
+                },
+                _ = cancel.cancelled() => return,
+            };
+            let tls = tls.clone();
+            let mounts = mounts.clone();
+            let reload = reload.clone();
+            let cancel = cancel.clone();
+            tokio::spawn(async move {
+                match tls {
+                    Some(tls) => match tls.accept(stream).await {
+                        Ok(stream) => Self::connection(stream, mounts, reload, &cancel).await,
+                        // Typically a browser rejecting the self-signed certificate
+                        Err(e) => tracing::debug!("TLS handshake failed: {}", e),
+                    },
+                    None => Self::connection(stream, mounts, reload, &cancel).await,
+                }
+            });
+        }
+    }
+
+    /// Answer the requests of a connection
+    async fn connection<S>(
+        stream: S,
+        mounts: Arc<Vec<Mount>>,
+        reload: broadcast::Sender<Reload>,
+        cancel: &CancellationToken,
+    ) where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let service = service_fn(move |request: hyper::Request<hyper::Body>| {
+            let mounts = mounts.clone();
+            let events = reload.subscribe();
+            async move {
+                if request.uri().path() == RELOAD_PATH {
+                    return Ok::<_, Infallible>(reload_stream(events));
+                }
+                Ok(static_files::serve(&mounts, &request).await)
+            }
+        });
+        let connection = hyper::server::conn::Http::new().serve_connection(stream, service);
+        tokio::select! {
+            served = connection => {
+                if let Err(e) = served {
+                    tracing::debug!("Development server connection: {}", e);
+                }
+            }
+            _ = cancel.cancelled() => {}
         }
     }
 
+    /// Recompile UI layouts modified since the last call
+    ///
+    /// Returns the layouts that were recompiled. Invalid layouts are logged
+    /// and keep their previous compiled output so the running app is not
+    /// disturbed.
+    pub fn reload_layouts(path: &Path, seen: &mut HashMap<PathBuf, SystemTime>) -> Vec<PathBuf> {
+        let mut reloaded = Vec::new();
+
+        for source in ui::layout_files(&path.join("ui")) {
+            let Ok(modified) = std::fs::metadata(&source).and_then(|m| m.modified()) else {
+                continue;
+            };
+            if seen.get(&source) == Some(&modified) {
+                continue;
+            }
+            seen.insert(source.clone(), modified);
+
+            match ui::compile_layout(path, &source) {
+                Ok(_) => reloaded.push(source),
+                Err(e) => tracing::error!("{}", e),
+            }
+        }
+
+        reloaded
+    }
+
     /// Stop the development server
     pub async fn stop(&mut self) -> Result<(), ForgeKitError> {
         tracing::info!("Stopping development server");
@@ -58,6 +367,62 @@ impl DevServer {
     }
 }
 
+/// Server-sent event stream with an event for each reload
+fn reload_stream(mut events: broadcast::Receiver<Reload>) -> hyper::Response<hyper::Body> {
+    let (mut sender, body) = hyper::Body::channel();
+    tokio::spawn(async move {
+        // A comment first, so the page sees the stream open
+        if sender.send_data(": connected\n\n".into()).await.is_err() {
+            return;
+        }
+        loop {
+            let event = match events.recv().await {
+                Ok(Reload::Page(reason)) => {
+                    format!("event: reload\ndata: {}\n\n", reason.replace('\n', " "))
+                }
+                Ok(Reload::Assets(paths)) => format!(
+                    "event: assets\ndata: {}\n\n",
+                    serde_json::to_string(&paths).unwrap_or_default()
+                ),
+                // Missed events cannot be swapped in; reload everything
+                Err(broadcast::error::RecvError::Lagged(_)) => {
+                    "event: reload\ndata: missed events\n\n".to_string()
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            };
+            // Fails once the page has gone away
+            if sender.send_data(event.into()).await.is_err() {
+                return;
+            }
+        }
+    });
+    hyper::Response::builder()
+        .header(hyper::header::CONTENT_TYPE, "text/event-stream")
+        .header(hyper::header::CACHE_CONTROL, "no-store")
+        .body(body)
+        .expect("valid response")
+}
+
+/// Address of the interface used to reach other networks, if any
+///
+/// Connecting a UDP socket only selects a route; nothing is sent.
+pub fn lan_address() -> Option<IpAddr> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    socket.connect((Ipv4Addr::new(192, 0, 2, 1), 80)).ok()?;
+    let ip = socket.local_addr().ok()?.ip();
+    (!ip.is_loopback() && !ip.is_unspecified()).then_some(ip)
+}
+
+/// QR code of `text` for the terminal, e.g. the server's URL for a phone
+pub fn qr_code(text: &str) -> Option<String> {
+    let code = qrcode::QrCode::new(text).ok()?;
+    Some(
+        code.render::<qrcode::render::unicode::Dense1x2>()
+            .quiet_zone(true)
+            .build(),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -69,6 +434,76 @@ mod tests {
         assert!(!config.watch_patterns.is_empty());
     }
 
+    #[test]
+    fn test_reload_layouts_only_recompiles_changes() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let ui_dir = temp_dir.path().join("ui");
+        std::fs::create_dir_all(&ui_dir).unwrap();
+        std::fs::write(ui_dir.join("main.xml"), "<window />").unwrap();
+
+        let mut seen = HashMap::new();
+        assert_eq!(
+            DevServer::reload_layouts(temp_dir.path(), &mut seen).len(),
+            1
+        );
+        assert!(DevServer::reload_layouts(temp_dir.path(), &mut seen).is_empty());
+        assert!(ui::compiled_dir(temp_dir.path()).join("main.mxui").exists());
+    }
+
+    #[tokio::test]
+    async fn test_serves_assets_over_https() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(temp_dir.path().join("assets")).unwrap();
+        std::fs::write(temp_dir.path().join("assets").join("app.css"), "body {}").unwrap();
+        let server = DevServer::new(DevServerConfig {
+            port: 0,
+            https: true,
+            ..Default::default()
+        });
+        assert!(!server.is_public());
+
+        let cancel = CancellationToken::new();
+        let (sender, receiver) = tokio::sync::oneshot::channel();
+        let run = server.run(temp_dir.path(), &cancel, |url| {
+            sender.send(url.to_string()).unwrap();
+        });
+        let client = async {
+            let url = receiver.await.unwrap();
+            assert!(url.starts_with("https://localhost:"));
+            let response = reqwest::Client::builder()
+                .danger_accept_invalid_certs(true)
+                .build()
+                .unwrap()
+                .get(format!("{}/assets/app.css", url))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(
+                response.headers()["content-type"],
+                "text/css; charset=utf-8"
+            );
+            assert_eq!(response.text().await.unwrap(), "body {}");
+            cancel.cancel();
+        };
+        let (result, ()) = tokio::join!(run, client);
+        result.unwrap();
+        assert!(crate::dev_cert::cert_dir(temp_dir.path())
+            .join("cert.pem")
+            .exists());
+    }
+
+    #[test]
+    fn test_lan_url_and_qr_code() {
+        let server = DevServer::new(DevServerConfig {
+            bind: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            host: Some("devbox.local".to_string()),
+            ..Default::default()
+        });
+        assert!(server.is_public());
+        assert_eq!(server.url(8080), "http://devbox.local:8080");
+        assert!(qr_code(&server.url(8080)).unwrap().contains('█'));
+    }
+
     #[test]
     fn test_dev_server_creation() {
         let config = DevServerConfig::default();
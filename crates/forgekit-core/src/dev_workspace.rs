@@ -0,0 +1,481 @@
+//! Workspace dev server module
+//!
+//! This module serves several members of a workspace at once, e.g. a GUI
+//! and the backend service it talks to. Each member gets its own dev server
+//! on its own port, with its own reload channel, and is rebuilt whenever its
+//! files change. A member names the members it talks to in `[dev]
+//! waits_for`: its reload is held while any of them is rebuilding, and a
+//! successful rebuild of one of them reloads it as well, so the GUI never
+//! reloads against a backend that is still compiling. Changes limited to a
+//! member's assets and layouts are hot-swapped into its own pages at once.
+
+use crate::builder::BuildOptions;
+use crate::cancel::CancellationToken;
+use crate::dev_server::{DevServer, DevServerConfig, Reload};
+use crate::error::ForgeKitError;
+use crate::watch::{BuildWatcher, WatchOptions, WatchStatus};
+use crate::workspace::Workspace;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio::task::JoinSet;
+
+/// A workspace member served by the dev server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DevMember {
+    /// Member name
+    pub name: String,
+    /// Member directory
+    pub path: PathBuf,
+    /// Port of the member's dev server
+    pub port: u16,
+    /// Members whose rebuild the member's reload waits for
+    pub waits_for: Vec<String>,
+}
+
+/// Something that happened to a served member
+#[derive(Debug, Clone)]
+pub enum DevEvent {
+    /// The member's dev server is listening
+    Listening { member: String, url: String },
+    /// The member was rebuilt
+    Built { member: String, status: WatchStatus },
+    /// The member's pages were told to reload
+    Reloaded { member: String, reload: Reload },
+}
+
+/// Members of a workspace to serve, with their ports
+///
+/// Members without a `[dev] port` get the next port from `base_port` on
+/// that no other member claims.
+pub fn plan(workspace: &Workspace, base_port: u16) -> Result<Vec<DevMember>, ForgeKitError> {
+    let names: HashSet<&str> = workspace.members.iter().map(|m| m.name.as_str()).collect();
+    let mut claimed = BTreeSet::new();
+    for member in &workspace.members {
+        if let Some(port) = member.config.dev.port {
+            if !claimed.insert(port) {
+                return Err(ForgeKitError::InvalidConfig(format!(
+                    "{}: [dev] port {} is already used by another member",
+                    member.name, port
+                )));
+            }
+        }
+    }
+
+    let mut next = base_port;
+    let mut members = Vec::new();
+    for member in &workspace.members {
+        for name in &member.config.dev.waits_for {
+            if name == &member.name || !names.contains(name.as_str()) {
+                return Err(ForgeKitError::InvalidConfig(format!(
+                    "{}: [dev] waits_for names '{}', which is not another workspace member",
+                    member.name, name
+                )));
+            }
+        }
+        let port = match member.config.dev.port {
+            Some(port) => port,
+            None => {
+                while claimed.contains(&next) {
+                    next = next.checked_add(1).ok_or_else(|| {
+                        ForgeKitError::InvalidConfig(format!(
+                            "{}: no free dev server port from {}",
+                            member.name, base_port
+                        ))
+                    })?;
+                }
+                claimed.insert(next);
+                next
+            }
+        };
+        members.push(DevMember {
+            name: member.name.clone(),
+            path: member.path.clone(),
+            port,
+            waits_for: member.config.dev.waits_for.clone(),
+        });
+    }
+    Ok(members)
+}
+
+/// Dev server for every ForgeKit project of a workspace
+pub struct WorkspaceDevServer {
+    root: PathBuf,
+    config: DevServerConfig,
+    build: BuildOptions,
+}
+
+impl WorkspaceDevServer {
+    /// Serve the workspace at `root`
+    ///
+    /// `config.port` is the first port handed out; `build` is used for
+    /// every rebuild.
+    pub fn new(root: &Path, config: DevServerConfig, build: BuildOptions) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            config,
+            build,
+        }
+    }
+
+    /// Serve and rebuild the members until cancelled, reporting every event
+    ///
+    /// A member whose server fails to start stops the others.
+    pub async fn run<F>(
+        &self,
+        cancel: &CancellationToken,
+        mut on_event: F,
+    ) -> Result<(), ForgeKitError>
+    where
+        F: FnMut(&DevEvent),
+    {
+        let workspace = Workspace::load(&self.root)?;
+        let members = plan(&workspace, self.config.port)?;
+        if members.is_empty() {
+            return Err(ForgeKitError::ProjectNotFound(format!(
+                "No ForgeKit projects in the workspace at {:?}",
+                self.root
+            )));
+        }
+
+        let cancel = cancel.child_token();
+        let (events, mut received) = mpsc::unbounded_channel();
+        let servers: Vec<DevServer> = members
+            .iter()
+            .map(|member| {
+                DevServer::new(DevServerConfig {
+                    port: member.port,
+                    ..self.config.clone()
+                })
+            })
+            .collect();
+        let reloaders = members
+            .iter()
+            .zip(&servers)
+            .map(|(member, server)| (member.name.clone(), server.reloader()))
+            .collect();
+        let coordinator = Arc::new(Coordinator::new(
+            members.clone(),
+            reloaders,
+            events.clone(),
+            cancel.clone(),
+        ));
+
+        let mut tasks = JoinSet::new();
+        for (member, server) in members.iter().zip(servers) {
+            let (name, path) = (member.name.clone(), member.path.clone());
+            let (listening, server_cancel) = (events.clone(), cancel.clone());
+            tasks.spawn(async move {
+                // The build watcher hot-swaps assets, see `build_finished`
+                server
+                    .serve(&path, &server_cancel, |url| {
+                        let _ = listening.send(DevEvent::Listening {
+                            member: name,
+                            url: url.to_string(),
+                        });
+                    })
+                    .await
+            });
+
+            let options = WatchOptions {
+                build: BuildOptions {
+                    cancel: cancel.clone(),
+                    ..self.build.clone()
+                },
+                hot_swap: true,
+                ..Default::default()
+            };
+            let watcher = BuildWatcher::new(&member.path, options);
+            let (name, coordinator) = (member.name.clone(), coordinator.clone());
+            tasks.spawn(async move {
+                watcher
+                    .run_with_start(
+                        |_| coordinator.build_started(&name),
+                        |status| coordinator.build_finished(&name, status),
+                    )
+                    .await
+            });
+        }
+        drop(events);
+
+        let mut result = Ok(());
+        loop {
+            tokio::select! {
+                Some(event) = received.recv() => on_event(&event),
+                joined = tasks.join_next() => match joined {
+                    None => break,
+                    Some(Ok(Ok(()))) => {}
+                    Some(Ok(Err(e))) => {
+                        if result.is_ok() {
+                            result = Err(e);
+                        }
+                        cancel.cancel();
+                    }
+                    Some(Err(e)) => {
+                        if result.is_ok() {
+                            result = Err(std::io::Error::other(e).into());
+                        }
+                        cancel.cancel();
+                    }
+                },
+            }
+        }
+        while let Ok(event) = received.try_recv() {
+            on_event(&event);
+        }
+        result
+    }
+}
+
+/// Tracks which members are building and sends each member's reload once
+/// the members it waits for are built
+struct Coordinator {
+    members: Vec<DevMember>,
+    building: HashMap<String, watch::Sender<bool>>,
+    reloaders: HashMap<String, broadcast::Sender<Reload>>,
+    /// Members with a reload waiting to be sent
+    pending: Mutex<HashSet<String>>,
+    events: mpsc::UnboundedSender<DevEvent>,
+    cancel: CancellationToken,
+}
+
+impl Coordinator {
+    fn new(
+        members: Vec<DevMember>,
+        reloaders: HashMap<String, broadcast::Sender<Reload>>,
+        events: mpsc::UnboundedSender<DevEvent>,
+        cancel: CancellationToken,
+    ) -> Self {
+        let building = members
+            .iter()
+            .map(|member| (member.name.clone(), watch::channel(false).0))
+            .collect();
+        Self {
+            members,
+            building,
+            reloaders,
+            pending: Mutex::default(),
+            events,
+            cancel,
+        }
+    }
+
+    fn build_started(&self, member: &str) {
+        self.building[member].send_replace(true);
+    }
+
+    /// Record a finished build and schedule the reloads it calls for
+    ///
+    /// The initial build reloads nothing: no page has loaded yet. Members
+    /// waiting for the rebuilt one reload too, unless they are rebuilding
+    /// themselves and will reload when done.
+    fn build_finished(self: &Arc<Self>, member: &str, status: &WatchStatus) {
+        self.building[member].send_replace(false);
+        let _ = self.events.send(DevEvent::Built {
+            member: member.to_string(),
+            status: status.clone(),
+        });
+        // Hot-swapped files concern the member alone and need no waiting
+        if let Some(swapped) = &status.swapped {
+            let path = self
+                .member(member)
+                .map(|m| m.path.clone())
+                .unwrap_or_default();
+            let urls = DevServer::resource_urls(&path, swapped);
+            if !urls.is_empty() {
+                self.reload(member, Reload::Assets(urls));
+            }
+            return;
+        }
+        if !status.success || status.builds == 1 {
+            return;
+        }
+
+        self.schedule_reload(member, "rebuilt".to_string());
+        for dependent in self
+            .members
+            .iter()
+            .filter(|m| m.waits_for.iter().any(|w| w == member))
+        {
+            if !*self.building[&dependent.name].borrow() {
+                self.schedule_reload(&dependent.name, format!("{} rebuilt", member));
+            }
+        }
+    }
+
+    fn member(&self, name: &str) -> Option<&DevMember> {
+        self.members.iter().find(|m| m.name == name)
+    }
+
+    /// Tell a member's pages to reload
+    fn reload(&self, member: &str, reload: Reload) {
+        // Nobody may be listening
+        let _ = self.reloaders[member].send(reload.clone());
+        let _ = self.events.send(DevEvent::Reloaded {
+            member: member.to_string(),
+            reload,
+        });
+    }
+
+    /// Reload a member once none of the members it waits for is building
+    fn schedule_reload(self: &Arc<Self>, member: &str, reason: String) {
+        if !self
+            .pending
+            .lock()
+            .expect("pending lock")
+            .insert(member.to_string())
+        {
+            return;
+        }
+        let coordinator = self.clone();
+        let member = member.to_string();
+        tokio::spawn(async move {
+            let waits_for = coordinator
+                .member(&member)
+                .map(|m| m.waits_for.clone())
+                .unwrap_or_default();
+            for name in waits_for {
+                let mut building = coordinator.building[&name].subscribe();
+                tokio::select! {
+                    _ = building.wait_for(|building| !*building) => {}
+                    _ = coordinator.cancel.cancelled() => return,
+                }
+            }
+
+            coordinator
+                .pending
+                .lock()
+                .expect("pending lock")
+                .remove(&member);
+            coordinator.reload(&member, Reload::Page(reason));
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ProjectConfig;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    fn write_member(root: &Path, name: &str, port: Option<u16>, waits_for: &[&str]) {
+        let path = root.join(name);
+        std::fs::create_dir_all(&path).unwrap();
+        std::fs::write(path.join("Cargo.toml"), "[package]\nname = \"x\"").unwrap();
+        let mut config = ProjectConfig {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        config.dev.port = port;
+        config.dev.waits_for = waits_for.iter().map(|w| w.to_string()).collect();
+        config.save(path.join("forgekit.toml")).unwrap();
+    }
+
+    fn status(builds: usize) -> WatchStatus {
+        WatchStatus {
+            builds,
+            success: true,
+            errors: 0,
+            warnings: 0,
+            duration: Duration::ZERO,
+            package: None,
+            changed: Vec::new(),
+            message: None,
+            swapped: None,
+        }
+    }
+
+    #[test]
+    fn test_plan_assigns_ports_and_checks_waits_for() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::write(
+            root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"backend\", \"gui\", \"admin\"]\n",
+        )
+        .unwrap();
+        write_member(root, "backend", Some(8081), &[]);
+        write_member(root, "gui", None, &["backend"]);
+        write_member(root, "admin", None, &["backend"]);
+
+        let members = plan(&Workspace::load(root).unwrap(), 8080).unwrap();
+        let ports: Vec<(&str, u16)> = members.iter().map(|m| (m.name.as_str(), m.port)).collect();
+        assert_eq!(ports, [("backend", 8081), ("gui", 8080), ("admin", 8082)]);
+        assert_eq!(members[1].waits_for, ["backend"]);
+        assert!(plan(&Workspace::load(root).unwrap(), u16::MAX).is_err());
+
+        write_member(root, "admin", None, &["api"]);
+        let error = plan(&Workspace::load(root).unwrap(), 8080).unwrap_err();
+        assert!(error.to_string().contains("'api'"));
+    }
+
+    #[tokio::test]
+    async fn test_reload_waits_for_backend_rebuild() {
+        let members = vec![
+            DevMember {
+                name: "backend".to_string(),
+                path: PathBuf::from("backend"),
+                port: 8081,
+                waits_for: Vec::new(),
+            },
+            DevMember {
+                name: "gui".to_string(),
+                path: PathBuf::from("gui"),
+                port: 8080,
+                waits_for: vec!["backend".to_string()],
+            },
+        ];
+        let reloaders: HashMap<String, broadcast::Sender<Reload>> = members
+            .iter()
+            .map(|m| (m.name.clone(), broadcast::channel(16).0))
+            .collect();
+        let mut gui = reloaders["gui"].subscribe();
+        let (events, _received) = mpsc::unbounded_channel();
+        let coordinator = Arc::new(Coordinator::new(
+            members,
+            reloaders,
+            events,
+            CancellationToken::new(),
+        ));
+
+        // A shared change rebuilds both; the GUI finishes first
+        coordinator.build_started("backend");
+        coordinator.build_started("gui");
+        coordinator.build_finished("gui", &status(2));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(gui.try_recv().is_err());
+
+        coordinator.build_finished("backend", &status(2));
+        let reason = tokio::time::timeout(Duration::from_secs(5), gui.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(reason, Reload::Page("rebuilt".to_string()));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(gui.try_recv().is_err());
+
+        // A backend-only rebuild reloads the GUI too
+        coordinator.build_started("backend");
+        coordinator.build_finished("backend", &status(3));
+        let reason = tokio::time::timeout(Duration::from_secs(5), gui.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(reason, Reload::Page("backend rebuilt".to_string()));
+
+        // An asset edit is swapped in without waiting for the backend
+        coordinator.build_started("backend");
+        coordinator.build_started("gui");
+        let swap = WatchStatus {
+            swapped: Some(vec![PathBuf::from("gui/assets/app.css")]),
+            ..status(4)
+        };
+        coordinator.build_finished("gui", &swap);
+        assert_eq!(
+            gui.try_recv().unwrap(),
+            Reload::Assets(vec!["/assets/app.css".to_string()])
+        );
+    }
+}
@@ -0,0 +1,435 @@
+//! Diagnostics module
+//!
+//! This module defines the [`Diagnostic`] that every check reports its
+//! findings as: lint, validation, the dependency audit and test failures.
+//! `forgekit check` runs all of them with [`run_checks`] and renders the
+//! combined [`DiagnosticReport`] in the terminal, as JSON, or as SARIF for
+//! code scanning. The SARIF log describes every rule it reports with a link
+//! to its documentation, so GitHub and GitLab can explain inline annotations.
+
+use crate::error::ForgeKitError;
+use crate::lint::Linter;
+use crate::redact::Redactor;
+use crate::testing::TestRunner;
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Version of the SARIF format written by [`DiagnosticReport::to_sarif`]
+pub const SARIF_VERSION: &str = "2.1.0";
+
+/// File `forgekit check --sarif` writes in the generated CI workflows
+pub const SARIF_FILE: &str = "forgekit.sarif";
+
+/// File `forgekit check --code-quality` writes in the generated GitLab pipeline
+pub const CODE_QUALITY_FILE: &str = "gl-code-quality-report.json";
+
+/// Documentation of the checks ForgeKit runs itself
+const FORGEKIT_DOCS: &str = "https://github.com/ledokoz-tech/ForgeKit#readme";
+
+/// Severity of a diagnostic
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Informational note
+    Note,
+    /// Something that should be fixed
+    Warning,
+    /// Something that must be fixed
+    Error,
+}
+
+impl Severity {
+    /// Parse a severity name as used in forgekit.toml and compiler output
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "note" | "help" => Some(Severity::Note),
+            "warning" | "warn" => Some(Severity::Warning),
+            "error" | "deny" => Some(Severity::Error),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Note => write!(f, "note"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A single finding of any check
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    /// Tool that produced the diagnostic (`clippy`, `rustfmt`, `validator`,
+    /// `audit`, `test`)
+    pub source: String,
+    /// Diagnostic severity
+    pub severity: Severity,
+    /// Lint name, advisory or test the diagnostic is about, if any
+    pub code: Option<String>,
+    /// File the diagnostic points at
+    pub file: Option<PathBuf>,
+    /// 1-based line number
+    pub line: Option<u32>,
+    /// 1-based column number
+    pub column: Option<u32>,
+    /// Diagnostic message
+    pub message: String,
+    /// How to fix it, if known
+    pub fix: Option<String>,
+}
+
+impl Diagnostic {
+    /// Diagnostic from `source` that points at nothing in particular
+    pub fn new(source: &str, severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            source: source.to_string(),
+            severity,
+            code: None,
+            file: None,
+            line: None,
+            column: None,
+            message: message.into(),
+            fix: None,
+        }
+    }
+
+    /// Rule the diagnostic reports: its code, or its source if it has none
+    pub fn rule_id(&self) -> &str {
+        self.code.as_deref().unwrap_or(&self.source)
+    }
+
+    /// Short description of the kind of check that produced the diagnostic
+    pub fn rule_description(&self) -> &'static str {
+        match self.source.as_str() {
+            "clippy" if self.rule_id().starts_with("clippy::") => "Clippy lint",
+            "clippy" => "Compiler diagnostic",
+            "rustfmt" => "Unformatted file",
+            "validator" => "Project validation",
+            "audit" => "Vulnerable dependency",
+            "test" => "Test failure",
+            _ => "ForgeKit check",
+        }
+    }
+
+    /// Documentation of the rule, if there is any
+    pub fn help_uri(&self) -> Option<String> {
+        let rule = self.rule_id();
+        match self.source.as_str() {
+            "clippy" => match rule.strip_prefix("clippy::") {
+                Some(lint) => Some(format!(
+                    "https://rust-lang.github.io/rust-clippy/master/index.html#{}",
+                    lint
+                )),
+                // Compiler error codes look like E0308; lints have no page
+                None if rule.starts_with('E') && rule[1..].chars().all(|c| c.is_ascii_digit()) => {
+                    Some(format!(
+                        "https://doc.rust-lang.org/error_codes/{}.html",
+                        rule
+                    ))
+                }
+                None => Some(format!(
+                    "https://doc.rust-lang.org/rustc/lints/listing/index.html#{}",
+                    rule
+                )),
+            },
+            "rustfmt" => Some("https://rust-lang.github.io/rustfmt/".to_string()),
+            "audit" => Some(format!("https://rustsec.org/packages/{}.html", rule)),
+            "validator" => Some(FORGEKIT_DOCS.to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// Diagnostics of one or more checks
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DiagnosticReport {
+    /// All diagnostics, in the order they were produced
+    pub diagnostics: Vec<Diagnostic>,
+    /// Checks that were selected but did not run, with why
+    pub skipped: Vec<String>,
+}
+
+impl DiagnosticReport {
+    /// Mask secret values in every message and fix
+    pub fn redact(&mut self, redactor: &Redactor) {
+        for diagnostic in &mut self.diagnostics {
+            diagnostic.message = redactor.redact(&diagnostic.message);
+            if let Some(fix) = &diagnostic.fix {
+                diagnostic.fix = Some(redactor.redact(fix));
+            }
+        }
+    }
+
+    /// Number of diagnostics with the given severity
+    pub fn count(&self, severity: Severity) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == severity)
+            .count()
+    }
+
+    /// Whether any diagnostic is at or above the given severity
+    pub fn fails(&self, fail_on: Severity) -> bool {
+        self.diagnostics.iter().any(|d| d.severity >= fail_on)
+    }
+
+    /// The report as a SARIF log
+    ///
+    /// File paths are made relative to `project_path` so code scanning can
+    /// match them to the repository. Each rule is listed once, at the
+    /// severity of its first diagnostic, with a link to its documentation.
+    pub fn to_sarif(&self, project_path: &Path) -> Value {
+        let mut rules: Vec<Value> = Vec::new();
+        let mut rule_ids: Vec<&str> = Vec::new();
+        let results: Vec<Value> = self
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let rule_id = diagnostic.rule_id();
+                let rule_index = match rule_ids.iter().position(|id| *id == rule_id) {
+                    Some(index) => index,
+                    None => {
+                        let mut rule = json!({
+                            "id": rule_id,
+                            "name": rule_id,
+                            "shortDescription": { "text": diagnostic.rule_description() },
+                            "defaultConfiguration": {
+                                "level": diagnostic.severity.to_string(),
+                            },
+                            "properties": { "tags": [diagnostic.source] },
+                        });
+                        if let Some(uri) = diagnostic.help_uri() {
+                            rule["helpUri"] = json!(uri);
+                        }
+                        rules.push(rule);
+                        rule_ids.push(rule_id);
+                        rule_ids.len() - 1
+                    }
+                };
+
+                let mut text = diagnostic.message.clone();
+                if let Some(fix) = &diagnostic.fix {
+                    text.push_str(&format!("\nFix: {}", fix));
+                }
+                let mut result = json!({
+                    "ruleId": rule_id,
+                    "ruleIndex": rule_index,
+                    "level": diagnostic.severity.to_string(),
+                    "message": { "text": text },
+                });
+                if let Some(file) = &diagnostic.file {
+                    let file = file.strip_prefix(project_path).unwrap_or(file);
+                    let mut region = json!({});
+                    if let Some(line) = diagnostic.line {
+                        region["startLine"] = json!(line);
+                    }
+                    if let Some(column) = diagnostic.column {
+                        region["startColumn"] = json!(column);
+                    }
+                    let mut location = json!({
+                        "artifactLocation": {
+                            "uri": file.to_string_lossy().replace('\\', "/"),
+                            "uriBaseId": "%SRCROOT%",
+                        },
+                    });
+                    if diagnostic.line.is_some() {
+                        location["region"] = region;
+                    }
+                    result["locations"] = json!([{ "physicalLocation": location }]);
+                }
+                result
+            })
+            .collect();
+
+        json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": SARIF_VERSION,
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "forgekit",
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "informationUri": "https://github.com/ledokoz-tech/ForgeKit",
+                        "rules": rules,
+                    },
+                },
+                "results": results,
+                "invocations": [{
+                    "executionSuccessful": true,
+                    "toolExecutionNotifications": self
+                        .skipped
+                        .iter()
+                        .map(|reason| json!({ "level": "note", "message": { "text": reason } }))
+                        .collect::<Vec<_>>(),
+                }],
+            }],
+        })
+    }
+
+    /// The report as a GitLab Code Quality report
+    ///
+    /// GitLab shows these findings in merge requests. Every finding needs a
+    /// file, so diagnostics that point at none are reported against
+    /// forgekit.toml, and each gets a fingerprint GitLab uses to tell new
+    /// findings from ones already on the target branch.
+    pub fn to_code_quality(&self, project_path: &Path) -> Value {
+        let issues: Vec<Value> = self
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let file = match &diagnostic.file {
+                    Some(file) => file
+                        .strip_prefix(project_path)
+                        .unwrap_or(file)
+                        .to_string_lossy()
+                        .replace('\\', "/"),
+                    None => "forgekit.toml".to_string(),
+                };
+                let line = diagnostic.line.unwrap_or(1);
+                let mut description = diagnostic.message.clone();
+                if let Some(fix) = &diagnostic.fix {
+                    description.push_str(&format!(" Fix: {}", fix));
+                }
+                let fingerprint = Sha256::digest(
+                    format!(
+                        "{}\0{}\0{}\0{}",
+                        diagnostic.rule_id(),
+                        file,
+                        line,
+                        diagnostic.message
+                    )
+                    .as_bytes(),
+                );
+                json!({
+                    "description": description,
+                    "check_name": diagnostic.rule_id(),
+                    "fingerprint": format!("{:x}", fingerprint),
+                    "severity": match diagnostic.severity {
+                        Severity::Error => "major",
+                        Severity::Warning => "minor",
+                        Severity::Note => "info",
+                    },
+                    "location": { "path": file, "lines": { "begin": line } },
+                })
+            })
+            .collect();
+        json!(issues)
+    }
+}
+
+/// Checks run by `forgekit check`
+#[derive(Debug, Clone)]
+pub struct CheckOptions {
+    /// Run clippy, rustfmt and the project validator
+    pub lint: bool,
+    /// Audit dependencies for known vulnerabilities
+    pub audit: bool,
+    /// Run the test suite
+    pub tests: bool,
+}
+
+impl Default for CheckOptions {
+    fn default() -> Self {
+        Self {
+            lint: true,
+            audit: true,
+            tests: true,
+        }
+    }
+}
+
+/// Run the selected checks and combine their diagnostics
+///
+/// Lint includes the project validator. Without a Cargo.toml there are no
+/// dependencies to audit or tests to run; validation reports it. The
+/// dependency audit is not implemented yet and is reported as skipped.
+pub async fn run_checks(
+    project_path: &Path,
+    options: &CheckOptions,
+) -> Result<DiagnosticReport, ForgeKitError> {
+    let mut report = DiagnosticReport::default();
+    let has_cargo = project_path.join("Cargo.toml").exists();
+
+    if options.lint {
+        report
+            .diagnostics
+            .extend(Linter::run(project_path, false).await?.diagnostics);
+    }
+    if options.audit && has_cargo {
+        // The auditor has no advisory database yet, so it would report every
+        // project as clean; say so instead of passing the check
+        report.skipped.push(
+            "dependency audit: no advisory database is available, so dependencies were not audited"
+                .to_string(),
+        );
+    }
+    if options.tests && has_cargo {
+        let tests = TestRunner::run_tests(project_path).await?;
+        report.diagnostics.extend(tests.diagnostics());
+    }
+    report.redact(&Redactor::for_project(project_path)?);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sarif_locations_are_relative() {
+        let project = Path::new("/work/app");
+        let report = DiagnosticReport {
+            diagnostics: vec![
+                Diagnostic {
+                    code: Some("clippy::len_zero".to_string()),
+                    file: Some(project.join("src").join("main.rs")),
+                    line: Some(12),
+                    column: Some(8),
+                    fix: Some("use `is_empty()`".to_string()),
+                    ..Diagnostic::new("clippy", Severity::Warning, "length comparison to zero")
+                },
+                Diagnostic::new("validator", Severity::Error, "forgekit.toml not found"),
+            ],
+            skipped: vec!["dependency audit: not available".to_string()],
+        };
+
+        let sarif = report.to_sarif(project);
+        let results = &sarif["runs"][0]["results"];
+        assert_eq!(results[0]["ruleId"], "clippy::len_zero");
+        assert_eq!(results[0]["level"], "warning");
+        let location = &results[0]["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "src/main.rs");
+        assert_eq!(location["region"]["startColumn"], 8);
+        assert_eq!(results[1]["ruleId"], "validator");
+        assert!(results[1].get("locations").is_none());
+
+        let rules = &sarif["runs"][0]["tool"]["driver"]["rules"];
+        assert_eq!(results[1]["ruleIndex"], 1);
+        assert_eq!(rules[1]["id"], "validator");
+        let notification = &sarif["runs"][0]["invocations"][0]["toolExecutionNotifications"][0];
+        assert_eq!(
+            notification["message"]["text"],
+            "dependency audit: not available"
+        );
+        assert_eq!(
+            rules[0]["helpUri"],
+            "https://rust-lang.github.io/rust-clippy/master/index.html#len_zero"
+        );
+
+        let issues = report.to_code_quality(project);
+        assert_eq!(issues[0]["check_name"], "clippy::len_zero");
+        assert_eq!(issues[0]["severity"], "minor");
+        assert_eq!(issues[0]["location"]["path"], "src/main.rs");
+        assert_eq!(issues[0]["location"]["lines"]["begin"], 12);
+        assert_eq!(issues[1]["severity"], "major");
+        assert_eq!(issues[1]["location"]["path"], "forgekit.toml");
+        assert_ne!(issues[0]["fingerprint"], issues[1]["fingerprint"]);
+    }
+}
@@ -393,7 +393,7 @@ mod tests {
 
         // Verify YAML structure
         let lines: Vec<&str> = content.lines().collect();
-        assert!(lines.len() > 0);
+        assert!(!lines.is_empty());
 
         // Check for proper indentation (basic YAML validation)
         let has_services = lines.iter().any(|l| l.contains("services:"));
@@ -416,7 +416,7 @@ mod tests {
 
         // Verify Dockerfile structure
         let lines: Vec<&str> = content.lines().collect();
-        assert!(lines.len() > 0);
+        assert!(!lines.is_empty());
 
         // Check for required Dockerfile instructions
         let has_from = lines.iter().any(|l| l.starts_with("FROM"));
@@ -0,0 +1,182 @@
+//! Download recovery module
+//!
+//! This module keeps interrupted and corrupted package downloads from being
+//! mistaken for valid archives. An archive is fetched into a partial file
+//! whose state (source URL, validator and expected size) is saved next to
+//! it, so a later attempt can resume with an HTTP `Range` request instead of
+//! starting over. A finished archive gets a download record with its size
+//! and checksum; a cached archive that no longer matches its record is moved
+//! to the cache's `quarantine/` directory and fetched again.
+
+use crate::atomic;
+use crate::error::ForgeKitError;
+use crate::store::hash_file;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Directory of the package cache corrupt archives are moved to
+pub const QUARANTINE_DIR: &str = "quarantine";
+
+/// Size and checksum of a finished download
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadRecord {
+    /// URL the archive was downloaded from
+    pub url: String,
+    /// Size in bytes
+    pub size: u64,
+    /// SHA-256 of the archive
+    pub checksum: String,
+}
+
+/// State of a download that has not finished
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartialDownload {
+    /// URL being downloaded
+    pub url: String,
+    /// ETag or Last-Modified of the response, sent as `If-Range` on resume
+    pub validator: Option<String>,
+    /// Size of the complete archive, when the server announced it
+    pub total: Option<u64>,
+}
+
+/// Files of an archive's download, or upload to a registry server, in progress
+#[derive(Debug, Clone)]
+pub struct PartialFiles {
+    /// Bytes received so far
+    pub data: PathBuf,
+    /// Saved [`PartialDownload`] state
+    pub state: PathBuf,
+}
+
+impl PartialFiles {
+    /// Partial files of a package version in the cache
+    pub fn new(cache_dir: &Path, name: &str, version: &str) -> Self {
+        let stem = format!("{}-{}", name, version);
+        Self {
+            data: cache_dir.join(format!("{}.part", stem)),
+            state: cache_dir.join(format!("{}.part.json", stem)),
+        }
+    }
+
+    /// Bytes already received from `url`, to resume from
+    ///
+    /// Partial data of another URL, or without saved state, cannot be
+    /// resumed and is discarded.
+    pub fn resumable(&self, url: &str) -> Result<Option<(u64, PartialDownload)>, ForgeKitError> {
+        let state = std::fs::read_to_string(&self.state)
+            .ok()
+            .and_then(|state| serde_json::from_str::<PartialDownload>(&state).ok());
+        let received = std::fs::metadata(&self.data).map(|m| m.len()).ok();
+        match (state, received) {
+            (Some(state), Some(received)) if state.url == url && received > 0 => {
+                Ok(Some((received, state)))
+            }
+            _ => {
+                self.discard()?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Bytes received so far, zero before the first
+    pub fn received(&self) -> Result<u64, ForgeKitError> {
+        match std::fs::metadata(&self.data) {
+            Ok(metadata) => Ok(metadata.len()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Save the state of the download
+    pub fn save(&self, state: &PartialDownload) -> Result<(), ForgeKitError> {
+        atomic::write(&self.state, serde_json::to_string_pretty(state)?)
+    }
+
+    /// Remove the partial data and its state
+    pub fn discard(&self) -> Result<(), ForgeKitError> {
+        for path in [&self.data, &self.state] {
+            match std::fs::remove_file(path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Location of the download record of an archive
+pub fn record_path(archive: &Path) -> PathBuf {
+    let mut path = archive.as_os_str().to_os_string();
+    path.push(".download.json");
+    PathBuf::from(path)
+}
+
+/// Record the size and checksum of a finished download
+pub fn write_record(archive: &Path, url: &str) -> Result<DownloadRecord, ForgeKitError> {
+    let record = DownloadRecord {
+        url: url.to_string(),
+        size: std::fs::metadata(archive)?.len(),
+        checksum: hash_file(archive)?,
+    };
+    atomic::write(record_path(archive), serde_json::to_string_pretty(&record)?)?;
+    Ok(record)
+}
+
+/// Why a cached archive cannot be used, `None` when it matches its record
+///
+/// Archives without a record, e.g. libraries published locally, are
+/// trusted.
+pub fn verify(archive: &Path) -> Result<Option<String>, ForgeKitError> {
+    let Ok(record) = std::fs::read_to_string(record_path(archive)) else {
+        return Ok(None);
+    };
+    let Ok(record) = serde_json::from_str::<DownloadRecord>(&record) else {
+        return Ok(Some("its download record is unreadable".to_string()));
+    };
+    let size = std::fs::metadata(archive)?.len();
+    if size != record.size {
+        return Ok(Some(format!(
+            "it is {} bytes instead of {}",
+            size, record.size
+        )));
+    }
+    let checksum = hash_file(archive)?;
+    if checksum != record.checksum {
+        return Ok(Some(format!(
+            "its checksum is {} instead of {}",
+            checksum, record.checksum
+        )));
+    }
+    Ok(None)
+}
+
+/// Move a corrupt archive out of the cache, returning where it went
+///
+/// The archive is kept for inspection under `quarantine/` with a timestamp,
+/// and its download record is dropped so it is fetched again.
+pub fn quarantine(
+    cache_dir: &Path,
+    archive: &Path,
+    reason: &str,
+) -> Result<PathBuf, ForgeKitError> {
+    let dir = cache_dir.join(QUARANTINE_DIR);
+    std::fs::create_dir_all(&dir)?;
+    let file_name = archive.file_name().unwrap_or_default().to_string_lossy();
+    let target = dir.join(format!(
+        "{}.{}",
+        file_name,
+        chrono::Utc::now().format("%Y%m%dT%H%M%S%.3f")
+    ));
+    std::fs::rename(archive, &target)?;
+    let record = record_path(archive);
+    if record.exists() {
+        std::fs::remove_file(record)?;
+    }
+    tracing::warn!("Quarantined {} to {:?}: {}", file_name, target, reason);
+    Ok(target)
+}
+
+/// Total size announced by a `Content-Range` header, e.g. `bytes 10-99/100`
+pub fn content_range_total(value: &str) -> Option<u64> {
+    value.rsplit_once('/')?.1.parse().ok()
+}
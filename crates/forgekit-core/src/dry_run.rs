@@ -0,0 +1,132 @@
+//! Dry-run module
+//!
+//! This module lets mutating operations report what they would do instead of
+//! doing it. A [`DryRun`] handle is passed to the operation like a
+//! cancellation token; when it is enabled the operation records each
+//! [`PlannedAction`] and leaves disk and network untouched. The recorded
+//! [`Plan`] is read back from the handle afterwards.
+
+use serde::Serialize;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// One change an operation would make
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum PlannedAction {
+    /// Create a directory
+    CreateDir { path: PathBuf },
+    /// Write a new file or replace one
+    WriteFile { path: PathBuf },
+    /// Change part of an existing file
+    UpdateFile { path: PathBuf, change: String },
+    /// Delete a file or directory
+    Remove { path: PathBuf },
+    /// Download a package from the registry
+    Download { package: String, version: String },
+    /// Run a command, e.g. a hook
+    RunCommand { command: String },
+}
+
+impl fmt::Display for PlannedAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CreateDir { path } => write!(f, "create directory {}", path.display()),
+            Self::WriteFile { path } => write!(f, "write {}", path.display()),
+            Self::UpdateFile { path, change } => write!(f, "update {}: {}", path.display(), change),
+            Self::Remove { path } => write!(f, "remove {}", path.display()),
+            Self::Download { package, version } => write!(f, "download {} v{}", package, version),
+            Self::RunCommand { command } => write!(f, "run `{}`", command),
+        }
+    }
+}
+
+/// Actions an operation would take, in order
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Plan {
+    /// Planned actions
+    pub actions: Vec<PlannedAction>,
+}
+
+/// Whether an operation runs for real or only plans
+///
+/// Clones share the recorded plan. The default handle is disabled.
+#[derive(Debug, Clone, Default)]
+pub struct DryRun {
+    plan: Option<Arc<Mutex<Plan>>>,
+}
+
+impl DryRun {
+    /// An enabled or disabled handle, e.g. from a `--dry-run` flag
+    pub fn new(enabled: bool) -> Self {
+        if enabled {
+            Self::enabled()
+        } else {
+            Self::disabled()
+        }
+    }
+
+    /// A handle under which operations only record their plan
+    pub fn enabled() -> Self {
+        Self {
+            plan: Some(Arc::default()),
+        }
+    }
+
+    /// A handle under which operations run normally
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    /// Whether operations should only record their plan
+    pub fn is_enabled(&self) -> bool {
+        self.plan.is_some()
+    }
+
+    /// Record an action; does nothing when disabled
+    pub fn record(&self, action: PlannedAction) {
+        if let Some(plan) = &self.plan {
+            plan.lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .actions
+                .push(action);
+        }
+    }
+
+    /// Actions recorded so far
+    pub fn plan(&self) -> Plan {
+        self.plan
+            .as_ref()
+            .map(|plan| {
+                plan.lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .clone()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_enabled_handles_record() {
+        let disabled = DryRun::disabled();
+        disabled.record(PlannedAction::Remove {
+            path: PathBuf::from("vendor"),
+        });
+        assert!(!disabled.is_enabled());
+        assert!(disabled.plan().actions.is_empty());
+
+        let dry_run = DryRun::enabled();
+        dry_run.clone().record(PlannedAction::Download {
+            package: "json".to_string(),
+            version: "1.0.0".to_string(),
+        });
+        let plan = dry_run.plan();
+        assert_eq!(plan.actions.len(), 1);
+        assert_eq!(plan.actions[0].to_string(), "download json v1.0.0");
+    }
+}
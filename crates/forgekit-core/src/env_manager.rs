@@ -2,15 +2,28 @@
 //!
 //! This module provides functionality to manage environment variables
 //! for different build configurations (dev, staging, production).
+//!
+//! Values of the form `secret:<name>` refer to entries of the user's
+//! credential store and are resolved when a profile is loaded for `forgekit build --env`
+//! or `forgekit run --env`.
 
+use crate::atomic;
+use crate::credentials::CredentialStore;
 use crate::error::ForgeKitError;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
+/// Prefix of values that refer to a stored secret
+pub const SECRET_PREFIX: &str = "secret:";
+
+/// Variable telling the app which environment it runs in
+pub const ENVIRONMENT_VAR: &str = "FORGEKIT_ENV";
+
 /// Environment variable manager
 #[derive(Debug, Clone)]
 pub struct EnvManager {
     env_vars: HashMap<String, String>,
+    secret_vars: HashSet<String>,
 }
 
 impl EnvManager {
@@ -18,6 +31,7 @@ impl EnvManager {
     pub fn new() -> Self {
         Self {
             env_vars: HashMap::new(),
+            secret_vars: HashSet::new(),
         }
     }
 
@@ -45,6 +59,9 @@ impl EnvManager {
 
     /// Load environment variables for a specific environment
     ///
+    /// Files are layered, later ones overriding earlier ones: `.env`,
+    /// `.env.<env>`, `.env.local` and `.env.<env>.local`.
+    ///
     /// # Arguments
     ///
     /// * `env` - Environment name (e.g., "dev", "staging", "prod")
@@ -56,23 +73,79 @@ impl EnvManager {
     pub fn load_for_environment(env: &str, base_path: &Path) -> Result<Self, ForgeKitError> {
         let mut manager = Self::new();
 
-        // Load base .env file first
-        let base_env_path = base_path.join(".env");
-        if base_env_path.exists() {
-            let content = std::fs::read_to_string(&base_env_path)?;
-            manager.parse_env_content(&content)?;
+        for file in Self::layers(env) {
+            let path = base_path.join(file);
+            if path.exists() {
+                let content = std::fs::read_to_string(&path)?;
+                manager.parse_env_content(&content)?;
+            }
         }
 
-        // Load environment-specific .env file
-        let env_specific_path = base_path.join(format!(".env.{}", env));
-        if env_specific_path.exists() {
-            let content = std::fs::read_to_string(&env_specific_path)?;
-            manager.parse_env_content(&content)?;
+        Ok(manager)
+    }
+
+    /// Load an environment profile for a build or a run
+    ///
+    /// Unlike [`EnvManager::load_for_environment`], the environment must have
+    /// its own `.env.<env>` or `.env.<env>.local` file, secret references are
+    /// resolved from `credentials` and `FORGEKIT_ENV` is set to the
+    /// environment name.
+    pub fn load_profile(
+        env: &str,
+        base_path: &Path,
+        credentials: &dyn CredentialStore,
+    ) -> Result<Self, ForgeKitError> {
+        let specific = [format!(".env.{}", env), format!(".env.{}.local", env)];
+        if !specific.iter().any(|file| base_path.join(file).exists()) {
+            return Err(ForgeKitError::InvalidConfig(format!(
+                "Unknown environment '{}': no .env.{} file found",
+                env, env
+            )));
         }
 
+        let mut manager = Self::load_for_environment(env, base_path)?;
+        manager.resolve_secrets(credentials)?;
+        manager.set(ENVIRONMENT_VAR.to_string(), env.to_string());
         Ok(manager)
     }
 
+    /// Env files of an environment, lowest precedence first
+    fn layers(env: &str) -> [String; 4] {
+        [
+            ".env".to_string(),
+            format!(".env.{}", env),
+            ".env.local".to_string(),
+            format!(".env.{}.local", env),
+        ]
+    }
+
+    /// Replace `secret:<name>` values with the stored secrets
+    pub fn resolve_secrets(
+        &mut self,
+        credentials: &dyn CredentialStore,
+    ) -> Result<(), ForgeKitError> {
+        for (key, value) in self.env_vars.iter_mut() {
+            let Some(name) = value.strip_prefix(SECRET_PREFIX) else {
+                continue;
+            };
+            *value = credentials.get(name)?.ok_or_else(|| {
+                ForgeKitError::InvalidConfig(format!(
+                    "{} refers to secret '{}', which is not in the {} credential store",
+                    key,
+                    name,
+                    credentials.backend()
+                ))
+            })?;
+            self.secret_vars.insert(key.clone());
+        }
+        Ok(())
+    }
+
+    /// Whether a variable's value was resolved from a stored secret
+    pub fn is_secret(&self, key: &str) -> bool {
+        self.secret_vars.contains(key)
+    }
+
     /// Parse environment file content
     fn parse_env_content(&mut self, content: &str) -> Result<(), ForgeKitError> {
         for line in content.lines() {
@@ -127,6 +200,7 @@ impl EnvManager {
 
     /// Set an environment variable
     pub fn set(&mut self, key: String, value: String) {
+        self.secret_vars.remove(&key);
         self.env_vars.insert(key, value);
     }
 
@@ -228,7 +302,7 @@ impl EnvManager {
         for (key, value) in &self.env_vars {
             content.push_str(&format!("{}={}\n", key, value));
         }
-        std::fs::write(path, content)?;
+        atomic::write(path, content)?;
         Ok(())
     }
 }
@@ -242,6 +316,7 @@ impl Default for EnvManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::credentials::MemoryCredentialStore;
     use tempfile::TempDir;
 
     #[test]
@@ -287,6 +362,26 @@ mod tests {
         assert_eq!(manager.get("DEV_VAR"), Some("dev_value"));
     }
 
+    #[test]
+    fn test_load_profile_layers_and_resolves_secrets() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path();
+        let secrets = MemoryCredentialStore::new();
+        std::fs::write(base.join(".env"), "URL=base\nLEVEL=info").unwrap();
+        std::fs::write(base.join(".env.staging"), "URL=staging\nTOKEN=secret:api").unwrap();
+        std::fs::write(base.join(".env.staging.local"), "LEVEL=debug").unwrap();
+
+        assert!(EnvManager::load_profile("staging", base, &secrets).is_err());
+        secrets.set("api", "s3cr3t").unwrap();
+
+        let manager = EnvManager::load_profile("staging", base, &secrets).unwrap();
+        assert_eq!(manager.get("URL"), Some("staging"));
+        assert_eq!(manager.get("LEVEL"), Some("debug"));
+        assert_eq!(manager.get("TOKEN"), Some("s3cr3t"));
+        assert_eq!(manager.get(ENVIRONMENT_VAR), Some("staging"));
+        assert!(EnvManager::load_profile("prod", base, &secrets).is_err());
+    }
+
     #[test]
     fn test_interpolate_braces() {
         let mut manager = EnvManager::new();
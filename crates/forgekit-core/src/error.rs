@@ -30,6 +30,15 @@ pub enum ForgeKitError {
     #[error("Packaging failed: {0}")]
     PackagingFailed(String),
 
+    #[error("Hook failed: {0}")]
+    HookFailed(String),
+
+    #[error("Code generation failed: {0}")]
+    CodegenFailed(String),
+
+    #[error("Invalid UI layout: {0}")]
+    InvalidUi(String),
+
     #[error("Template error: {0}")]
     TemplateError(String),
 
@@ -40,4 +49,103 @@ pub enum ForgeKitError {
     TomlSerialization(#[from] toml::ser::Error),
     #[error("HTTP client error: {0}")]
     Http(#[from] reqwest::Error),
+
+    #[error("Rate limited: {0}")]
+    RateLimited(String),
+
+    #[error("Registry error: {0}")]
+    Registry(String),
+
+    #[error("Checksum mismatch: {0}")]
+    ChecksumMismatch(String),
+
+    #[error("Daemon error: {0}")]
+    Daemon(String),
+
+    #[error("Cancelled: {0}")]
+    Cancelled(String),
+
+    #[error("Port conflict: {0}")]
+    PortConflict(String),
+
+    #[error("Rollback failed: {0}")]
+    RollbackFailed(String),
+
+    #[error("Locked: {0}")]
+    Locked(String),
+
+    #[error("Authentication required for registry '{0}': run `forgekit login {0}`")]
+    AuthenticationRequired(String),
+
+    #[error("Install failed: {0}")]
+    InstallFailed(String),
+
+    #[error("Test failed: {0}")]
+    TestFailed(String),
+
+    #[error("Toolchain error: {0}")]
+    Toolchain(String),
+
+    #[error("Supply-chain policy violated: {0}")]
+    PolicyViolation(String),
+
+    #[error("TLS error: {0}")]
+    Tls(String),
+
+    #[error("Self-update failed: {0}")]
+    Update(String),
+
+    #[error("Credential store error: {0}")]
+    Credentials(String),
+
+    #[error("Command not allowed: {0}")]
+    CommandDenied(String),
+
+    #[error("Pull request failed: {0}")]
+    PullRequest(String),
+
+    #[error("Plugin error: {0}")]
+    Plugin(String),
+}
+
+impl ForgeKitError {
+    /// Stable identifier of the error kind, free of user data
+    pub fn code(&self) -> &'static str {
+        match self {
+            ForgeKitError::Io(_) => "io",
+            ForgeKitError::Json(_) => "json",
+            ForgeKitError::Toml(_) => "toml",
+            ForgeKitError::ProjectExists(_) => "project_exists",
+            ForgeKitError::ProjectNotFound(_) => "project_not_found",
+            ForgeKitError::InvalidConfig(_) => "invalid_config",
+            ForgeKitError::BuildFailed(_) => "build_failed",
+            ForgeKitError::PackagingFailed(_) => "packaging_failed",
+            ForgeKitError::HookFailed(_) => "hook_failed",
+            ForgeKitError::CodegenFailed(_) => "codegen_failed",
+            ForgeKitError::InvalidUi(_) => "invalid_ui",
+            ForgeKitError::TemplateError(_) => "template_error",
+            ForgeKitError::Zip(_) => "zip",
+            ForgeKitError::TomlSerialization(_) => "toml_serialization",
+            ForgeKitError::Http(_) => "http",
+            ForgeKitError::RateLimited(_) => "rate_limited",
+            ForgeKitError::Registry(_) => "registry",
+            ForgeKitError::ChecksumMismatch(_) => "checksum_mismatch",
+            ForgeKitError::Daemon(_) => "daemon",
+            ForgeKitError::Cancelled(_) => "cancelled",
+            ForgeKitError::PortConflict(_) => "port_conflict",
+            ForgeKitError::RollbackFailed(_) => "rollback_failed",
+            ForgeKitError::Locked(_) => "locked",
+            ForgeKitError::AuthenticationRequired(_) => "authentication_required",
+            ForgeKitError::InstallFailed(_) => "install_failed",
+            ForgeKitError::TestFailed(_) => "test_failed",
+            ForgeKitError::Toolchain(_) => "toolchain",
+            ForgeKitError::PolicyViolation(_) => "policy_violation",
+            ForgeKitError::Tls(_) => "tls",
+            ForgeKitError::Update(_) => "update",
+            ForgeKitError::Credentials(_) => "credentials",
+            ForgeKitError::CommandDenied(_) => "command_denied",
+            ForgeKitError::PullRequest(_) => "pull_request",
+            ForgeKitError::Plugin(_) => "plugin",
+        }
+    }
 }
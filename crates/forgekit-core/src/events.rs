@@ -0,0 +1,154 @@
+//! Events module
+//!
+//! This module lets programs embedding forgekit-core follow what an
+//! operation is doing without parsing its output. Builds, packaging,
+//! dependency installation and validation emit [`Event`]s on an
+//! [`EventBus`]; GUIs and IDE plugins subscribe to render progress and
+//! diagnostics. Subscribers are called synchronously on the emitting task,
+//! so they should return quickly.
+
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Something that happened during an operation
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// A build started
+    BuildStarted { project_path: PathBuf },
+    /// A build moved on to a new stage, e.g. `codegen` or `compile`
+    BuildProgress {
+        project_path: PathBuf,
+        stage: String,
+    },
+    /// A build finished
+    BuildFinished {
+        project_path: PathBuf,
+        success: bool,
+        duration_ms: u64,
+    },
+    /// A .mox or .moxlib package was written
+    PackageCreated {
+        project_path: PathBuf,
+        package: PathBuf,
+    },
+    /// A dependency was resolved to a version and installed
+    DependencyResolved { name: String, version: String },
+    /// Validation found a problem
+    ValidationIssue {
+        project_path: PathBuf,
+        /// `error` or `warning`
+        severity: String,
+        message: String,
+    },
+}
+
+/// Callback receiving events
+pub type Subscriber = Arc<dyn Fn(&Event) + Send + Sync>;
+
+/// Handle to remove a subscriber again
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubscriptionId(u64);
+
+#[derive(Default)]
+struct Subscribers {
+    next_id: u64,
+    callbacks: Vec<(SubscriptionId, Subscriber)>,
+}
+
+/// Distributes events to subscribers
+///
+/// Clones share their subscribers, so a bus can be handed to several
+/// operations.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    subscribers: Arc<Mutex<Subscribers>>,
+}
+
+impl EventBus {
+    /// Create a bus without subscribers
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Call `callback` for every event emitted from now on
+    pub fn subscribe(&self, callback: impl Fn(&Event) + Send + Sync + 'static) -> SubscriptionId {
+        let mut subscribers = self.lock();
+        let id = SubscriptionId(subscribers.next_id);
+        subscribers.next_id += 1;
+        subscribers.callbacks.push((id, Arc::new(callback)));
+        id
+    }
+
+    /// Stop calling a subscriber; returns whether it was subscribed
+    pub fn unsubscribe(&self, id: SubscriptionId) -> bool {
+        let mut subscribers = self.lock();
+        let before = subscribers.callbacks.len();
+        subscribers.callbacks.retain(|(other, _)| *other != id);
+        subscribers.callbacks.len() != before
+    }
+
+    /// Number of subscribers
+    pub fn subscriber_count(&self) -> usize {
+        self.lock().callbacks.len()
+    }
+
+    /// Send an event to every subscriber
+    pub fn emit(&self, event: Event) {
+        // Subscribers run without the lock held so they may (un)subscribe
+        let callbacks: Vec<Subscriber> = self
+            .lock()
+            .callbacks
+            .iter()
+            .map(|(_, callback)| callback.clone())
+            .collect();
+        for callback in callbacks {
+            callback(&event);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Subscribers> {
+        // A panicking subscriber must not disable the bus
+        self.subscribers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl std::fmt::Debug for EventBus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventBus")
+            .field("subscribers", &self.subscriber_count())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subscribe_emit_unsubscribe() {
+        let bus = EventBus::new();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        let id = bus.subscribe(move |event| sink.lock().unwrap().push(event.clone()));
+
+        let event = Event::DependencyResolved {
+            name: "json".to_string(),
+            version: "1.0.0".to_string(),
+        };
+        bus.clone().emit(event.clone());
+        assert_eq!(received.lock().unwrap()[0], event);
+        assert_eq!(
+            serde_json::to_value(&event).unwrap()["event"],
+            "dependency_resolved"
+        );
+
+        assert!(bus.unsubscribe(id));
+        assert!(!bus.unsubscribe(id));
+        bus.emit(event);
+        assert_eq!(received.lock().unwrap().len(), 1);
+    }
+}
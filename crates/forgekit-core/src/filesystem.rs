@@ -0,0 +1,274 @@
+//! Filesystem module
+//!
+//! This module defines the [`FileSystem`] the registry keeps its package index
+//! on, and the package manager its vendor directory and package cache. [`StdFileSystem`] uses the real disk, writing files atomically; with the
+//! `test-util` feature, [`MemoryFileSystem`] keeps everything in memory so
+//! tests leave nothing behind.
+
+use crate::atomic;
+use crate::error::ForgeKitError;
+use std::path::{Path, PathBuf};
+
+/// File operations used to keep state on disk
+pub trait FileSystem: Send + Sync {
+    /// Read a whole file
+    fn read(&self, path: &Path) -> Result<Vec<u8>, ForgeKitError>;
+
+    /// Read a whole file as UTF-8
+    fn read_to_string(&self, path: &Path) -> Result<String, ForgeKitError> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|e| ForgeKitError::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e)))
+    }
+
+    /// Replace a file, creating it if needed
+    ///
+    /// Readers see either the old or the new contents, never a mix.
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<(), ForgeKitError>;
+
+    /// Create a directory and all its parents
+    fn create_dir_all(&self, path: &Path) -> Result<(), ForgeKitError>;
+
+    /// Whether a file or directory exists
+    fn exists(&self, path: &Path) -> bool;
+
+    /// Files under `dir`, recursively and sorted, skipping hidden
+    /// directories like `.git`
+    fn files(&self, dir: &Path) -> Result<Vec<PathBuf>, ForgeKitError>;
+
+    /// Remove a file
+    fn remove_file(&self, path: &Path) -> Result<(), ForgeKitError>;
+
+    /// Whether `path` is a directory
+    fn is_dir(&self, path: &Path) -> bool;
+
+    /// Files and directories directly in `dir`, sorted
+    fn read_dir(&self, dir: &Path) -> Result<Vec<PathBuf>, ForgeKitError>;
+
+    /// Remove a directory and everything in it
+    fn remove_dir_all(&self, path: &Path) -> Result<(), ForgeKitError>;
+}
+
+/// [`FileSystem`] on the real disk
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdFileSystem;
+
+impl FileSystem for StdFileSystem {
+    fn read(&self, path: &Path) -> Result<Vec<u8>, ForgeKitError> {
+        Ok(std::fs::read(path)?)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<(), ForgeKitError> {
+        atomic::write(path, contents)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> Result<(), ForgeKitError> {
+        Ok(std::fs::create_dir_all(path)?)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn files(&self, dir: &Path) -> Result<Vec<PathBuf>, ForgeKitError> {
+        let mut files = Vec::new();
+        for entry in walkdir::WalkDir::new(dir)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+        {
+            let entry = entry.map_err(std::io::Error::from)?;
+            if entry.file_type().is_file() {
+                files.push(entry.into_path());
+            }
+        }
+        Ok(files)
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<(), ForgeKitError> {
+        Ok(std::fs::remove_file(path)?)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn read_dir(&self, dir: &Path) -> Result<Vec<PathBuf>, ForgeKitError> {
+        let mut entries = std::fs::read_dir(dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        entries.sort();
+        Ok(entries)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> Result<(), ForgeKitError> {
+        Ok(std::fs::remove_dir_all(path)?)
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+pub use memory::MemoryFileSystem;
+
+#[cfg(any(test, feature = "test-util"))]
+mod memory {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::sync::Mutex;
+
+    /// [`FileSystem`] kept in memory
+    ///
+    /// Directories exist implicitly as soon as a file is written below them.
+    #[derive(Debug, Default)]
+    pub struct MemoryFileSystem {
+        files: Mutex<BTreeMap<PathBuf, Vec<u8>>>,
+    }
+
+    impl MemoryFileSystem {
+        /// Empty filesystem
+        pub fn new() -> Self {
+            Self::default()
+        }
+    }
+
+    fn not_found(path: &Path) -> ForgeKitError {
+        ForgeKitError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("{} not found", path.display()),
+        ))
+    }
+
+    impl FileSystem for MemoryFileSystem {
+        fn read(&self, path: &Path) -> Result<Vec<u8>, ForgeKitError> {
+            self.files
+                .lock()
+                .unwrap()
+                .get(path)
+                .cloned()
+                .ok_or_else(|| not_found(path))
+        }
+
+        fn write(&self, path: &Path, contents: &[u8]) -> Result<(), ForgeKitError> {
+            self.files
+                .lock()
+                .unwrap()
+                .insert(path.to_path_buf(), contents.to_vec());
+            Ok(())
+        }
+
+        fn create_dir_all(&self, _path: &Path) -> Result<(), ForgeKitError> {
+            Ok(())
+        }
+
+        fn exists(&self, path: &Path) -> bool {
+            self.files
+                .lock()
+                .unwrap()
+                .keys()
+                .any(|file| file.starts_with(path))
+        }
+
+        fn files(&self, dir: &Path) -> Result<Vec<PathBuf>, ForgeKitError> {
+            Ok(self
+                .files
+                .lock()
+                .unwrap()
+                .keys()
+                .filter(|file| {
+                    file.strip_prefix(dir).is_ok_and(|relative| {
+                        let mut parents = relative.components().rev().skip(1);
+                        !parents.any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+                    })
+                })
+                .cloned()
+                .collect())
+        }
+
+        fn remove_file(&self, path: &Path) -> Result<(), ForgeKitError> {
+            self.files
+                .lock()
+                .unwrap()
+                .remove(path)
+                .map(|_| ())
+                .ok_or_else(|| not_found(path))
+        }
+
+        fn is_dir(&self, path: &Path) -> bool {
+            self.files
+                .lock()
+                .unwrap()
+                .keys()
+                .any(|file| file != path && file.starts_with(path))
+        }
+
+        fn read_dir(&self, dir: &Path) -> Result<Vec<PathBuf>, ForgeKitError> {
+            let entries: std::collections::BTreeSet<PathBuf> = self
+                .files
+                .lock()
+                .unwrap()
+                .keys()
+                .filter_map(|file| file.strip_prefix(dir).ok()?.components().next())
+                .map(|first| dir.join(first))
+                .collect();
+            Ok(entries.into_iter().collect())
+        }
+
+        fn remove_dir_all(&self, path: &Path) -> Result<(), ForgeKitError> {
+            if !self.is_dir(path) {
+                return Err(not_found(path));
+            }
+            self.files
+                .lock()
+                .unwrap()
+                .retain(|file, _| !file.starts_with(path));
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_filesystems_agree() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("index");
+        let memory = MemoryFileSystem::new();
+
+        for fs in [&StdFileSystem as &dyn FileSystem, &memory] {
+            fs.create_dir_all(&root.join("ab").join("cd")).unwrap();
+            fs.write(&root.join("ab").join("cd").join("abcd"), b"{}")
+                .unwrap();
+            fs.create_dir_all(&root.join(".git")).unwrap();
+            fs.write(&root.join(".git").join("HEAD"), b"ref").unwrap();
+            fs.write(&root.join("packages.json"), b"{}").unwrap();
+
+            assert!(fs.exists(&root.join("ab")));
+            assert_eq!(
+                fs.files(&root).unwrap(),
+                vec![
+                    root.join("ab").join("cd").join("abcd"),
+                    root.join("packages.json")
+                ]
+            );
+            assert_eq!(
+                fs.read_to_string(&root.join("packages.json")).unwrap(),
+                "{}"
+            );
+            assert_eq!(
+                fs.read_dir(&root).unwrap(),
+                vec![
+                    root.join(".git"),
+                    root.join("ab"),
+                    root.join("packages.json")
+                ]
+            );
+            assert!(fs.is_dir(&root.join("ab")));
+            assert!(!fs.is_dir(&root.join("packages.json")));
+            fs.remove_file(&root.join("packages.json")).unwrap();
+            assert!(!fs.exists(&root.join("packages.json")));
+            fs.remove_dir_all(&root.join("ab")).unwrap();
+            assert!(!fs.exists(&root.join("ab")));
+        }
+    }
+}
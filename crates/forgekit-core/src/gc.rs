@@ -0,0 +1,337 @@
+//! Garbage collection module
+//!
+//! This module frees disk space ForgeKit no longer needs, across every
+//! project it knows about: packages in the global store that no project's
+//! lockfile references, build-cache namespaces nothing was written to within
+//! the retention period, and packaged artifacts beyond `[build.artifacts]
+//! keep`. Known projects are the ones linking to the store plus any passed
+//! explicitly. Under a dry run everything is accounted for and recorded in
+//! the plan, but nothing is removed.
+
+use crate::artifacts;
+use crate::cache::BuildCache;
+use crate::config::{ArtifactsConfig, ProjectConfig};
+use crate::dry_run::{DryRun, PlannedAction};
+use crate::error::ForgeKitError;
+use crate::lock::LockOptions;
+use crate::lockfile::Lockfile;
+use crate::store::PackageStore;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Days a build-cache namespace is kept after its last write
+pub const DEFAULT_CACHE_MAX_AGE_DAYS: u64 = 30;
+
+/// Kind of data garbage collection removes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GcCategory {
+    /// Packages in the global store
+    Packages,
+    /// Build-cache namespaces
+    BuildCache,
+    /// Packaged artifacts in a project's history
+    Artifacts,
+}
+
+impl fmt::Display for GcCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GcCategory::Packages => write!(f, "packages"),
+            GcCategory::BuildCache => write!(f, "build cache"),
+            GcCategory::Artifacts => write!(f, "artifacts"),
+        }
+    }
+}
+
+/// Something removed, or that a dry run would remove
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GcItem {
+    /// Kind of data
+    pub category: GcCategory,
+    /// Package hash, cache namespace or artifact id
+    pub name: String,
+    /// Where the data lives
+    pub path: PathBuf,
+    /// Bytes on disk
+    pub size: u64,
+}
+
+/// Items and bytes of one category
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GcTotal {
+    /// Kind of data
+    pub category: GcCategory,
+    /// Number of items
+    pub count: usize,
+    /// Bytes on disk
+    pub size: u64,
+}
+
+/// Outcome of a garbage collection
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GcReport {
+    /// Projects whose lockfiles, caches and artifacts were considered
+    pub projects: Vec<PathBuf>,
+    /// Items removed, or that a dry run would remove
+    pub items: Vec<GcItem>,
+}
+
+impl GcReport {
+    /// Count and size per category, including empty ones
+    pub fn totals(&self) -> Vec<GcTotal> {
+        let mut totals = BTreeMap::new();
+        for category in [
+            GcCategory::Packages,
+            GcCategory::BuildCache,
+            GcCategory::Artifacts,
+        ] {
+            totals.insert(
+                category,
+                GcTotal {
+                    category,
+                    count: 0,
+                    size: 0,
+                },
+            );
+        }
+        for item in &self.items {
+            if let Some(total) = totals.get_mut(&item.category) {
+                total.count += 1;
+                total.size += item.size;
+            }
+        }
+        totals.into_values().collect()
+    }
+
+    /// Bytes freed in all categories
+    pub fn total_size(&self) -> u64 {
+        self.items.iter().map(|item| item.size).sum()
+    }
+}
+
+/// What garbage collection considers
+#[derive(Debug, Clone)]
+pub struct GcOptions {
+    /// Projects to consider besides the ones linking to the store
+    pub projects: Vec<PathBuf>,
+    /// Build-cache namespaces not written to for this long are removed
+    pub cache_max_age: Duration,
+    /// Locking of the build caches while they are cleaned
+    pub lock: LockOptions,
+}
+
+impl Default for GcOptions {
+    fn default() -> Self {
+        Self {
+            projects: Vec::new(),
+            cache_max_age: Duration::from_secs(DEFAULT_CACHE_MAX_AGE_DAYS * 24 * 60 * 60),
+            lock: LockOptions::default(),
+        }
+    }
+}
+
+/// Remove unreferenced packages, expired cache namespaces and old artifacts
+pub async fn collect_garbage(
+    store: &PackageStore,
+    options: &GcOptions,
+    dry_run: &DryRun,
+) -> Result<GcReport, ForgeKitError> {
+    let mut report = GcReport {
+        projects: known_projects(store, &options.projects)?,
+        items: Vec::new(),
+    };
+
+    // Packages: keep whatever a known lockfile pins by checksum
+    let mut referenced = HashSet::new();
+    for project in &report.projects {
+        let lockfile = Lockfile::load(project)?;
+        referenced.extend(lockfile.packages.into_iter().filter_map(|p| p.checksum));
+    }
+    for hash in store.hashes()? {
+        if referenced.contains(&hash) {
+            continue;
+        }
+        let path = store.package_path(&hash);
+        let size = if dry_run.is_enabled() {
+            dry_run.record(PlannedAction::Remove { path: path.clone() });
+            store.package_size(&hash)
+        } else {
+            store.remove(&hash)?
+        };
+        report.items.push(GcItem {
+            category: GcCategory::Packages,
+            name: hash,
+            path,
+            size,
+        });
+    }
+
+    for project in report.projects.clone() {
+        collect_cache(&project, options, dry_run, &mut report).await?;
+        collect_artifacts(&project, dry_run, &mut report)?;
+    }
+    Ok(report)
+}
+
+/// Projects linking to the store and the given ones, each once
+fn known_projects(store: &PackageStore, extra: &[PathBuf]) -> Result<Vec<PathBuf>, ForgeKitError> {
+    let mut projects = store.projects()?;
+    for project in extra {
+        let project = project.canonicalize()?;
+        if !projects.contains(&project) {
+            projects.push(project);
+        }
+    }
+    projects.sort();
+    Ok(projects)
+}
+
+async fn collect_cache(
+    project: &Path,
+    options: &GcOptions,
+    dry_run: &DryRun,
+    report: &mut GcReport,
+) -> Result<(), ForgeKitError> {
+    let cache_dir = project.join(".forgekit").join("cache");
+    if !cache_dir.is_dir() {
+        return Ok(());
+    }
+    let mut cache = BuildCache::new(cache_dir.clone())?.with_lock(options.lock.clone());
+    for namespace in cache.expired_namespaces(options.cache_max_age).await? {
+        let path = cache_dir.join(&namespace.namespace);
+        let size = if dry_run.is_enabled() {
+            dry_run.record(PlannedAction::Remove { path: path.clone() });
+            namespace.total_size
+        } else {
+            cache.remove_namespace(&namespace.namespace).await?
+        };
+        report.items.push(GcItem {
+            category: GcCategory::BuildCache,
+            name: namespace.namespace,
+            path,
+            size,
+        });
+    }
+    Ok(())
+}
+
+fn collect_artifacts(
+    project: &Path,
+    dry_run: &DryRun,
+    report: &mut GcReport,
+) -> Result<(), ForgeKitError> {
+    // A project whose configuration does not load keeps the default retention
+    let keep = ProjectConfig::load(project.join("forgekit.toml"))
+        .map(|config| config.build.artifacts.keep)
+        .unwrap_or_else(|_| ArtifactsConfig::default().keep);
+
+    let mut by_profile: BTreeMap<String, Vec<artifacts::Artifact>> = BTreeMap::new();
+    for artifact in artifacts::list(project, None)? {
+        by_profile
+            .entry(artifact.profile.clone())
+            .or_default()
+            .push(artifact);
+    }
+    let expired: Vec<artifacts::Artifact> = if dry_run.is_enabled() {
+        by_profile
+            .into_values()
+            .flat_map(|history| {
+                let excess = history.len().saturating_sub(keep);
+                history.into_iter().take(excess)
+            })
+            .inspect(|artifact| {
+                dry_run.record(PlannedAction::Remove {
+                    path: artifact.path(project),
+                })
+            })
+            .collect()
+    } else {
+        artifacts::clean(project, None, keep)?
+    };
+
+    report
+        .items
+        .extend(expired.into_iter().map(|artifact| GcItem {
+            category: GcCategory::Artifacts,
+            path: artifact.path(project),
+            size: artifact.size,
+            name: artifact.id,
+        }));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::CacheKey;
+    use crate::lockfile::LockedPackage;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_dry_run_accounts_without_removing() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = PackageStore::new(temp_dir.path().join("store")).unwrap();
+        let project = temp_dir.path().join("app");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::write(project.join("forgekit.toml"), "name = \"app\"\n").unwrap();
+
+        let write = |name: &str, content: &[u8]| {
+            let path = temp_dir.path().join(name);
+            std::fs::write(&path, content).unwrap();
+            store.add(&path).unwrap()
+        };
+        let locked = write("locked.tar.gz", b"locked");
+        let stale = write("stale.tar.gz", b"stale");
+        store
+            .link_into(&locked, &project.join("vendor").join("locked-1.0.0"))
+            .unwrap();
+        store
+            .link_into(&stale, &project.join("vendor").join("stale-1.0.0"))
+            .unwrap();
+        let lockfile = Lockfile {
+            packages: vec![LockedPackage {
+                name: "locked".to_string(),
+                version: "1.0.0".to_string(),
+                source: None,
+                checksum: Some(locked.hash.clone()),
+            }],
+            ..Default::default()
+        };
+        lockfile.save(&project).unwrap();
+
+        let mut cache = BuildCache::new(project.join(".forgekit").join("cache")).unwrap();
+        let key = CacheKey {
+            project: "app".to_string(),
+            profile: "debug".to_string(),
+            target: "host".to_string(),
+            toolchain: "old".to_string(),
+            inputs: "inputs".to_string(),
+        };
+        cache.set(&key, vec![0; 8]).await.unwrap();
+
+        let options = GcOptions {
+            cache_max_age: Duration::ZERO,
+            ..Default::default()
+        };
+        let dry_run = DryRun::enabled();
+        let report = collect_garbage(&store, &options, &dry_run).await.unwrap();
+        assert_eq!(report.projects, vec![project.canonicalize().unwrap()]);
+        let names: Vec<_> = report.items.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, vec![stale.hash.as_str(), "app/debug/host/old"]);
+        assert_eq!(report.totals()[1].size, 8);
+        assert_eq!(dry_run.plan().actions.len(), 2);
+        assert!(stale.path.exists());
+
+        let report = collect_garbage(&store, &options, &DryRun::disabled())
+            .await
+            .unwrap();
+        assert_eq!(report.items.len(), 2);
+        assert!(!stale.path.exists());
+        assert!(locked.path.exists());
+        assert!(!project.join(".forgekit/cache/app").exists());
+    }
+}
@@ -0,0 +1,506 @@
+//! Component generators module
+//!
+//! This module backs `forgekit generate`, which adds a component to an
+//! existing project the way its template lays things out: a CLI command
+//! under `src/commands/`, a SQL migration under `migrations/`, a plugin hook
+//! under `src/hooks/` or a UI view as a layout in `ui/` with its module in
+//! `src/views/`. New modules are declared in their directory's `mod.rs`,
+//! which is itself declared in the crate root, and a CLI command also gets
+//! a variant in the `Commands` enum and an arm in the `match` dispatching it.
+
+use crate::dry_run::{DryRun, PlannedAction};
+use crate::error::ForgeKitError;
+use crate::migrations::MigrationManager;
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Build step a generated plugin hook runs at
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HookPoint {
+    /// Before the build starts
+    #[default]
+    PreBuild,
+    /// After the build completes
+    PostBuild,
+    /// While the app is packaged
+    Package,
+}
+
+impl HookPoint {
+    /// Plugin trait method the hook is called from
+    fn method(&self) -> &'static str {
+        match self {
+            HookPoint::PreBuild => "on_pre_build",
+            HookPoint::PostBuild => "on_post_build",
+            HookPoint::Package => "on_package",
+        }
+    }
+
+    /// Context type the hook receives
+    fn context(&self) -> &'static str {
+        match self {
+            HookPoint::PreBuild | HookPoint::PostBuild => "BuildContext",
+            HookPoint::Package => "PackageContext",
+        }
+    }
+}
+
+impl std::str::FromStr for HookPoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pre-build" => Ok(HookPoint::PreBuild),
+            "post-build" => Ok(HookPoint::PostBuild),
+            "package" => Ok(HookPoint::Package),
+            _ => Err(format!(
+                "unknown hook point '{}' (expected pre-build, post-build or package)",
+                s
+            )),
+        }
+    }
+}
+
+/// Kind of component to generate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Generator {
+    /// A subcommand of a CLI tool
+    Command,
+    /// A SQL migration
+    Migration,
+    /// A plugin hook run at a build step
+    PluginHook(HookPoint),
+    /// A UI layout and the module loading it
+    UiView,
+}
+
+impl fmt::Display for Generator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Generator::Command => write!(f, "command"),
+            Generator::Migration => write!(f, "migration"),
+            Generator::PluginHook(_) => write!(f, "plugin hook"),
+            Generator::UiView => write!(f, "UI view"),
+        }
+    }
+}
+
+/// Files a generator created and changed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Generated {
+    /// New files
+    pub created: Vec<PathBuf>,
+    /// Existing files the component was wired into
+    pub updated: Vec<PathBuf>,
+}
+
+/// Add a component named `name` to the project at `project_path`
+///
+/// The name may be given in kebab- or snake-case. Existing files are never
+/// overwritten.
+pub fn generate(
+    project_path: &Path,
+    generator: Generator,
+    name: &str,
+    dry_run: &DryRun,
+) -> Result<Generated, ForgeKitError> {
+    let name = module_name(name)?;
+    let mut writer = Writer {
+        project_path,
+        dry_run,
+        generated: Generated::default(),
+    };
+
+    match generator {
+        Generator::Command => {
+            writer.create(
+                &Path::new("src").join("commands").join(format!("{}.rs", name)),
+                &format!(
+                    "//! `{name}` command\n\n/// Run the `{name}` command\npub fn run() {{\n    println!(\"Running {name}\");\n}}\n"
+                ),
+            )?;
+            writer.declare_module("commands", &name, "Subcommands of the tool")?;
+            writer.add_command(&name)?;
+        }
+        Generator::Migration => {
+            writer.create(
+                &MigrationManager::migration_file(&name),
+                &MigrationManager::migration_contents(&name),
+            )?;
+        }
+        Generator::PluginHook(point) => {
+            writer.create(
+                &Path::new("src").join("hooks").join(format!("{}.rs", name)),
+                &format!(
+                    r#"//! `{name}` hook
+
+use forgekit_core::error::ForgeKitError;
+use forgekit_core::plugin::{context};
+
+/// Called from the plugin's `{method}`
+pub fn {name}(_context: &{context}) -> Result<(), ForgeKitError> {{
+    Ok(())
+}}
+"#,
+                    context = point.context(),
+                    method = point.method(),
+                ),
+            )?;
+            writer.declare_module("hooks", &name, "Hooks the plugin runs at build steps")?;
+        }
+        Generator::UiView => {
+            let title = title(&name);
+            writer.create(
+                &Path::new("ui").join(format!("{}.xml", name)),
+                &format!(
+                    r#"<!-- {title} view -->
+<window title="{title}" width="800" height="600">
+    <layout type="vertical">
+        <label text="{title}" />
+    </layout>
+</window>
+"#
+                ),
+            )?;
+            writer.create(
+                &Path::new("src").join("views").join(format!("{}.rs", name)),
+                &format!(
+                    "//! `{name}` view\n\n/// Layout of the view, compiled by `forgekit ui`\npub const LAYOUT: &str = include_str!(\"../../ui/{name}.xml\");\n"
+                ),
+            )?;
+            writer.declare_module("views", &name, "Views of the app, one per UI layout")?;
+        }
+    }
+    Ok(writer.generated)
+}
+
+/// Writes files or records them on a dry run, collecting what changed
+struct Writer<'a> {
+    project_path: &'a Path,
+    dry_run: &'a DryRun,
+    generated: Generated,
+}
+
+impl Writer<'_> {
+    /// Write a new file, refusing to replace an existing one
+    fn create(&mut self, relative: &Path, contents: &str) -> Result<(), ForgeKitError> {
+        let path = self.project_path.join(relative);
+        if path.exists() {
+            return Err(ForgeKitError::TemplateError(format!(
+                "{} already exists",
+                relative.display()
+            )));
+        }
+        if self.dry_run.is_enabled() {
+            self.dry_run.record(PlannedAction::WriteFile { path });
+        } else {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&path, contents)?;
+        }
+        self.generated.created.push(relative.to_path_buf());
+        Ok(())
+    }
+
+    /// Replace the contents of an existing file, or record the change
+    fn update(
+        &mut self,
+        relative: &Path,
+        contents: &str,
+        change: &str,
+    ) -> Result<(), ForgeKitError> {
+        let path = self.project_path.join(relative);
+        if self.dry_run.is_enabled() {
+            self.dry_run.record(PlannedAction::UpdateFile {
+                path,
+                change: change.to_string(),
+            });
+        } else {
+            std::fs::write(&path, contents)?;
+        }
+        if !self.generated.updated.iter().any(|p| p == relative) {
+            self.generated.updated.push(relative.to_path_buf());
+        }
+        Ok(())
+    }
+
+    /// Declare `src/<dir>/<name>.rs` in `src/<dir>/mod.rs` and `<dir>` in the crate root
+    fn declare_module(&mut self, dir: &str, name: &str, doc: &str) -> Result<(), ForgeKitError> {
+        let mod_rs = Path::new("src").join(dir).join("mod.rs");
+        match read(&self.project_path.join(&mod_rs))? {
+            Some(contents) => {
+                if let Some(updated) = with_module(&contents, &format!("pub mod {};", name)) {
+                    self.update(&mod_rs, &updated, &format!("declare `pub mod {}`", name))?;
+                }
+            }
+            None => self.create(&mod_rs, &format!("//! {}\n\npub mod {};\n", doc, name))?,
+        }
+
+        let (root, declaration) = self.crate_root(dir)?;
+        let contents = read(&self.project_path.join(&root))?.unwrap_or_default();
+        if let Some(updated) = with_module(&contents, &declaration) {
+            self.update(&root, &updated, &format!("declare `{}`", declaration))?;
+        }
+        Ok(())
+    }
+
+    /// main.rs of an app or lib.rs of a library, with the module declaration it takes
+    fn crate_root(&self, dir: &str) -> Result<(PathBuf, String), ForgeKitError> {
+        let src = Path::new("src");
+        if self.project_path.join(src).join("main.rs").exists() {
+            Ok((src.join("main.rs"), format!("mod {};", dir)))
+        } else if self.project_path.join(src).join("lib.rs").exists() {
+            Ok((src.join("lib.rs"), format!("pub mod {};", dir)))
+        } else {
+            Err(ForgeKitError::ProjectNotFound(format!(
+                "{} has neither src/main.rs nor src/lib.rs",
+                self.project_path.display()
+            )))
+        }
+    }
+
+    /// Add a `Commands` variant dispatching to `commands::<name>::run`
+    fn add_command(&mut self, name: &str) -> Result<(), ForgeKitError> {
+        let main_rs = Path::new("src").join("main.rs");
+        let Some(contents) = read(&self.project_path.join(&main_rs))? else {
+            return Ok(());
+        };
+        let variant = title(name).replace(' ', "");
+        let with_variant = insert_before_close(
+            &contents,
+            "enum Commands {",
+            &format!("    /// Run the {} command\n    {},\n", name, variant),
+        );
+        let updated = with_variant.and_then(|contents| {
+            insert_before_close(
+                &contents,
+                "match cli.command {",
+                &format!(
+                    "        Commands::{} => commands::{}::run(),\n",
+                    variant, name
+                ),
+            )
+        });
+        match updated {
+            Some(updated) => self.update(
+                &main_rs,
+                &updated,
+                &format!("add the `{}` subcommand", name),
+            ),
+            None => {
+                tracing::warn!(
+                    "src/main.rs has no `enum Commands` dispatched by `match cli.command`; wire commands::{}::run in by hand",
+                    name
+                );
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Snake-case module name for a component name
+fn module_name(name: &str) -> Result<String, ForgeKitError> {
+    let module = name.trim().replace('-', "_").to_lowercase();
+    let valid = module
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic())
+        && module
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(ForgeKitError::TemplateError(format!(
+            "'{}' is not a valid name; use letters, digits, '-' and '_', starting with a letter",
+            name
+        )));
+    }
+    Ok(module)
+}
+
+/// Words of a module name, capitalized: `user_profile` → `User Profile`
+fn title(name: &str) -> String {
+    name.split('_')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().collect::<String>() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `contents` with `declaration` added after the last top-level `mod` or
+/// `use` line, or `None` when it is already declared
+fn with_module(contents: &str, declaration: &str) -> Option<String> {
+    let module = declaration
+        .trim_start_matches("pub ")
+        .trim_start_matches("mod ");
+    let declared = contents.lines().any(|line| {
+        let line = line.trim_start_matches("pub ");
+        line.strip_prefix("mod ") == Some(module)
+    });
+    if declared {
+        return None;
+    }
+
+    let lines: Vec<&str> = contents.lines().collect();
+    // Brace depth after each line, ignoring line comments, so declarations
+    // inside blocks like `mod tests { ... }` are not taken as the anchor
+    let depths: Vec<i64> = lines
+        .iter()
+        .scan(0, |depth, line| {
+            let code = line.split("//").next().unwrap_or_default();
+            *depth += code.matches('{').count() as i64 - code.matches('}').count() as i64;
+            Some(*depth)
+        })
+        .collect();
+    let top_level = |index: usize| index == 0 || depths[index - 1] == 0;
+    // A `use` may span several lines; the anchor is the line ending it
+    let anchor = (0..lines.len()).rev().find_map(|start| {
+        let line = lines[start];
+        if !top_level(start) {
+            None
+        } else if line.starts_with("use ") {
+            (start..lines.len())
+                .find(|&end| depths[end] == 0 && lines[end].trim_end().ends_with(';'))
+        } else if ["mod ", "pub mod "].iter().any(|p| line.starts_with(p)) {
+            line.trim_end().ends_with(';').then_some(start)
+        } else {
+            None
+        }
+    });
+    let at = match anchor {
+        Some(index) => index + 1,
+        // After the crate's doc comment
+        None => lines
+            .iter()
+            .position(|line| !line.starts_with("//!"))
+            .unwrap_or(lines.len()),
+    };
+    let mut updated: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+    if anchor.is_none() && at > 0 {
+        updated.insert(at, String::new());
+        updated.insert(at + 1, declaration.to_string());
+    } else {
+        updated.insert(at, declaration.to_string());
+    }
+    Some(updated.join("\n") + "\n")
+}
+
+/// `contents` with `text` inserted before the brace closing the block `opening` starts
+fn insert_before_close(contents: &str, opening: &str, text: &str) -> Option<String> {
+    let start = contents.find(opening)? + opening.len();
+    let mut depth = 1;
+    for (offset, c) in contents[start..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ => continue,
+        }
+        if depth == 0 {
+            // Insert at the start of the closing brace's line
+            let close = start + offset;
+            let line_start = contents[..close].rfind('\n').map_or(0, |i| i + 1);
+            let mut updated = contents.to_string();
+            updated.insert_str(line_start, text);
+            return Some(updated);
+        }
+    }
+    None
+}
+
+fn read(path: &Path) -> Result<Option<String>, ForgeKitError> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::templates::{generate_from_template, TemplateType};
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_command_is_wired_into_cli_project() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("tool");
+        generate_from_template("tool", TemplateType::Cli, &path)
+            .await
+            .unwrap();
+
+        let generated =
+            generate(&path, Generator::Command, "sync-files", &DryRun::disabled()).unwrap();
+        assert_eq!(
+            generated.created,
+            vec![
+                Path::new("src/commands/sync_files.rs").to_path_buf(),
+                Path::new("src/commands/mod.rs").to_path_buf(),
+            ]
+        );
+        let main_rs = std::fs::read_to_string(path.join("src/main.rs")).unwrap();
+        assert!(main_rs.contains("mod forgekit_crash;\nmod commands;\n"));
+        assert!(main_rs.contains("    SyncFiles,\n}"));
+        assert!(main_rs.contains("Commands::SyncFiles => commands::sync_files::run(),\n    }"));
+
+        generate(&path, Generator::Command, "prune", &DryRun::disabled()).unwrap();
+        let mod_rs = std::fs::read_to_string(path.join("src/commands/mod.rs")).unwrap();
+        assert!(mod_rs.ends_with("pub mod sync_files;\npub mod prune;\n"));
+        assert_eq!(
+            std::fs::read_to_string(path.join("src/main.rs"))
+                .unwrap()
+                .matches("mod commands;")
+                .count(),
+            1
+        );
+        assert!(generate(&path, Generator::Command, "prune", &DryRun::disabled()).is_err());
+    }
+
+    #[test]
+    fn test_with_module_after_multi_line_use() {
+        let contents = "//! Tool\n\nuse std::{\n    fs,\n    io,\n};\n\nfn main() {}\n";
+        assert_eq!(
+            with_module(contents, "mod commands;").unwrap(),
+            "//! Tool\n\nuse std::{\n    fs,\n    io,\n};\nmod commands;\n\nfn main() {}\n"
+        );
+        assert!(with_module("mod commands;\n", "mod commands;").is_none());
+
+        // Not into the test module of a library's crate root
+        let lib =
+            "//! Library\n\npub fn f() {}\n\n#[cfg(test)]\nmod tests {\n    use super::*;\n}\n";
+        assert_eq!(
+            with_module(lib, "pub mod views;").unwrap(),
+            "//! Library\n\npub mod views;\n\npub fn f() {}\n\n#[cfg(test)]\nmod tests {\n    use super::*;\n}\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ui_view_dry_run_writes_nothing() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("app");
+        generate_from_template("app", TemplateType::Gui, &path)
+            .await
+            .unwrap();
+
+        let dry_run = DryRun::enabled();
+        let generated = generate(&path, Generator::UiView, "settings", &dry_run).unwrap();
+        assert_eq!(
+            generated.updated,
+            vec![Path::new("src/main.rs").to_path_buf()]
+        );
+        assert_eq!(dry_run.plan().actions.len(), 4);
+        assert!(!path.join("ui/settings.xml").exists());
+
+        generate(&path, Generator::UiView, "settings", &DryRun::disabled()).unwrap();
+        let layout = std::fs::read_to_string(path.join("ui/settings.xml")).unwrap();
+        crate::ui::compile(&layout).unwrap();
+    }
+}
@@ -0,0 +1,326 @@
+//! Git hooks module
+//!
+//! This module installs git `pre-commit` and `pre-push` hooks that run the
+//! ForgeKit stages listed in the `[git_hooks]` section of forgekit.toml.
+//!
+//! The installed scripts only call `forgekit hooks run <hook>`, which reads
+//! the stage list at run time, so editing forgekit.toml takes effect without
+//! reinstalling. Set `FORGEKIT_SKIP` to a comma separated list of stages (or
+//! `all`) to skip them for a single git command.
+
+use crate::config::ProjectConfig;
+use crate::error::ForgeKitError;
+use crate::lint::Linter;
+use crate::validator::ProjectValidator;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// Marker identifying hook scripts written by ForgeKit
+const MANAGED_MARKER: &str = "# Managed by ForgeKit";
+
+/// Environment variable listing stages to skip
+pub const SKIP_ENV: &str = "FORGEKIT_SKIP";
+
+/// Git hooks ForgeKit can manage
+pub const MANAGED_HOOKS: &[&str] = &["pre-commit", "pre-push"];
+
+/// A check run by a git hook
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GitHookStage {
+    /// `cargo fmt --check`
+    Fmt,
+    /// `forgekit lint`
+    Lint,
+    /// `forgekit validate`
+    Validate,
+    /// Library unit tests only
+    TestFast,
+}
+
+impl GitHookStage {
+    /// Parse a stage name from forgekit.toml
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "fmt" => Some(GitHookStage::Fmt),
+            "lint" => Some(GitHookStage::Lint),
+            "validate" => Some(GitHookStage::Validate),
+            "test-fast" => Some(GitHookStage::TestFast),
+            _ => None,
+        }
+    }
+
+    /// Stage name as written in forgekit.toml
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GitHookStage::Fmt => "fmt",
+            GitHookStage::Lint => "lint",
+            GitHookStage::Validate => "validate",
+            GitHookStage::TestFast => "test-fast",
+        }
+    }
+
+    /// Run the stage, returning whether it passed
+    pub async fn run(&self, project_path: &Path) -> Result<bool, ForgeKitError> {
+        match self {
+            GitHookStage::Fmt => cargo(project_path, &["fmt", "--check"]).await,
+            GitHookStage::Lint => {
+                let fail_on = Linter::fail_on(project_path)?;
+                let report = Linter::run(project_path, false).await?;
+                Ok(!report.fails(fail_on))
+            }
+            GitHookStage::Validate => {
+                let report = ProjectValidator::validate_project(project_path).await?;
+                Ok(report.is_valid)
+            }
+            GitHookStage::TestFast => {
+                // Unit tests only: skip integration tests and doctests
+                let mut args = vec!["test", "--quiet"];
+                if project_path.join("src").join("lib.rs").exists() {
+                    args.push("--lib");
+                }
+                if project_path.join("src").join("main.rs").exists() {
+                    args.push("--bins");
+                }
+                cargo(project_path, &args).await
+            }
+        }
+    }
+}
+
+/// What `install` did with each hook
+#[derive(Debug, Clone, Default)]
+pub struct HookSyncReport {
+    /// Hooks written or refreshed
+    pub installed: Vec<String>,
+    /// Managed hooks removed because no stages are configured
+    pub removed: Vec<String>,
+    /// Existing hooks not written by ForgeKit that were left alone
+    pub skipped: Vec<String>,
+}
+
+/// Git hook manager
+pub struct GitHooks;
+
+impl GitHooks {
+    /// Install or refresh the hooks configured in forgekit.toml
+    ///
+    /// Hooks without stages are removed if ForgeKit wrote them. Hooks not
+    /// written by ForgeKit are only replaced when `force` is set.
+    pub async fn install(
+        project_path: &Path,
+        force: bool,
+    ) -> Result<HookSyncReport, ForgeKitError> {
+        let config = ProjectConfig::load(project_path.join("forgekit.toml"))?;
+        let hooks_dir = hooks_dir(project_path).await?;
+        std::fs::create_dir_all(&hooks_dir)?;
+
+        let mut report = HookSyncReport::default();
+        for hook in MANAGED_HOOKS {
+            let stages = Self::stages(&config, hook)?;
+            let path = hooks_dir.join(hook);
+            let managed = is_managed(&path);
+
+            if path.exists() && !managed && !force {
+                report.skipped.push(hook.to_string());
+                continue;
+            }
+
+            if stages.is_empty() {
+                if managed {
+                    std::fs::remove_file(&path)?;
+                    report.removed.push(hook.to_string());
+                }
+                continue;
+            }
+
+            std::fs::write(&path, hook_script(hook, project_path))?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+            }
+            report.installed.push(hook.to_string());
+        }
+
+        Ok(report)
+    }
+
+    /// Remove every hook written by ForgeKit
+    pub async fn uninstall(project_path: &Path) -> Result<Vec<String>, ForgeKitError> {
+        let hooks_dir = hooks_dir(project_path).await?;
+        let mut removed = Vec::new();
+
+        for hook in MANAGED_HOOKS {
+            let path = hooks_dir.join(hook);
+            if is_managed(&path) {
+                std::fs::remove_file(path)?;
+                removed.push(hook.to_string());
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// Run the stages configured for a hook
+    ///
+    /// Returns the names of the stages that failed.
+    pub async fn run(project_path: &Path, hook: &str) -> Result<Vec<String>, ForgeKitError> {
+        let config = ProjectConfig::load(project_path.join("forgekit.toml"))?;
+        let skip = std::env::var(SKIP_ENV).unwrap_or_default();
+        let skip: Vec<&str> = skip.split(',').map(str::trim).collect();
+
+        let mut failed = Vec::new();
+        for stage in Self::stages(&config, hook)? {
+            if skip.contains(&"all") || skip.contains(&stage.as_str()) {
+                tracing::info!("Skipping {} stage ({} is set)", stage.as_str(), SKIP_ENV);
+                continue;
+            }
+
+            tracing::info!("Running {} stage: {}", hook, stage.as_str());
+            if !stage.run(project_path).await? {
+                failed.push(stage.as_str().to_string());
+            }
+        }
+
+        Ok(failed)
+    }
+
+    /// Stages configured for a hook
+    pub fn stages(config: &ProjectConfig, hook: &str) -> Result<Vec<GitHookStage>, ForgeKitError> {
+        let names = match hook {
+            "pre-commit" => &config.git_hooks.pre_commit,
+            "pre-push" => &config.git_hooks.pre_push,
+            _ => {
+                return Err(ForgeKitError::InvalidConfig(format!(
+                    "unsupported git hook '{}'",
+                    hook
+                )))
+            }
+        };
+
+        names
+            .iter()
+            .map(|name| {
+                GitHookStage::parse(name).ok_or_else(|| {
+                    ForgeKitError::InvalidConfig(format!("unknown git hook stage '{}'", name))
+                })
+            })
+            .collect()
+    }
+}
+
+/// Locate the hooks directory of the repository containing the project
+async fn hooks_dir(project_path: &Path) -> Result<PathBuf, ForgeKitError> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-path", "hooks"])
+        .current_dir(project_path)
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        return Err(ForgeKitError::InvalidConfig(format!(
+            "{:?} is not inside a git repository",
+            project_path
+        )));
+    }
+
+    let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok(project_path.join(path))
+}
+
+fn is_managed(path: &Path) -> bool {
+    std::fs::read_to_string(path)
+        .map(|content| content.contains(MANAGED_MARKER))
+        .unwrap_or(false)
+}
+
+fn hook_script(hook: &str, project_path: &Path) -> String {
+    let project_path = project_path
+        .canonicalize()
+        .unwrap_or_else(|_| project_path.to_path_buf());
+
+    format!(
+        "#!/bin/sh\n\
+         {MANAGED_MARKER}. Edit [git_hooks] in forgekit.toml instead of this file.\n\
+         # Set {SKIP_ENV}=<stage,...> or {SKIP_ENV}=all to skip stages.\n\
+         exec forgekit hooks run {hook} --path \"{}\"\n",
+        project_path.display()
+    )
+}
+
+async fn cargo(project_path: &Path, args: &[&str]) -> Result<bool, ForgeKitError> {
+    let status = Command::new("cargo")
+        .args(args)
+        .current_dir(project_path)
+        .status()
+        .await?;
+    Ok(status.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    async fn git_project(pre_commit: &[&str]) -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        Command::new("git")
+            .args(["init", "-q"])
+            .current_dir(temp_dir.path())
+            .status()
+            .await
+            .unwrap();
+
+        let mut config = ProjectConfig::default();
+        config.git_hooks.pre_commit = pre_commit.iter().map(|s| s.to_string()).collect();
+        config.save(temp_dir.path().join("forgekit.toml")).unwrap();
+        temp_dir
+    }
+
+    #[test]
+    fn test_stages_reject_unknown_names() {
+        let mut config = ProjectConfig::default();
+        config.git_hooks.pre_push = vec!["validate".to_string(), "deploy".to_string()];
+        assert!(GitHooks::stages(&config, "pre-push").is_err());
+
+        config.git_hooks.pre_push = vec!["validate".to_string(), "test-fast".to_string()];
+        assert_eq!(
+            GitHooks::stages(&config, "pre-push").unwrap(),
+            vec![GitHookStage::Validate, GitHookStage::TestFast]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_install_and_sync() {
+        let temp_dir = git_project(&["fmt"]).await;
+        let hooks = temp_dir.path().join(".git").join("hooks");
+
+        let report = GitHooks::install(temp_dir.path(), false).await.unwrap();
+        assert_eq!(report.installed, vec!["pre-commit"]);
+        assert!(is_managed(&hooks.join("pre-commit")));
+
+        // Clearing the stages removes the managed hook on the next install
+        let mut config = ProjectConfig::load(temp_dir.path().join("forgekit.toml")).unwrap();
+        config.git_hooks.pre_commit.clear();
+        config.save(temp_dir.path().join("forgekit.toml")).unwrap();
+        let report = GitHooks::install(temp_dir.path(), false).await.unwrap();
+        assert_eq!(report.removed, vec!["pre-commit"]);
+        assert!(!hooks.join("pre-commit").exists());
+    }
+
+    #[tokio::test]
+    async fn test_install_keeps_foreign_hooks() {
+        let temp_dir = git_project(&["fmt"]).await;
+        let hook = temp_dir
+            .path()
+            .join(".git")
+            .join("hooks")
+            .join("pre-commit");
+        std::fs::create_dir_all(hook.parent().unwrap()).unwrap();
+        std::fs::write(&hook, "#!/bin/sh\necho custom\n").unwrap();
+
+        let report = GitHooks::install(temp_dir.path(), false).await.unwrap();
+        assert_eq!(report.skipped, vec!["pre-commit"]);
+        assert!(!is_managed(&hook));
+    }
+}
@@ -0,0 +1,216 @@
+//! GitHub API module
+//!
+//! This module keeps the registry client's GitHub API usage within the rate
+//! limit. Responses are cached under the registry cache directory with their
+//! ETag and Last-Modified, and revalidated with conditional requests; GitHub
+//! does not count `304 Not Modified` answers against the limit. Concurrent
+//! requests for the same URL share a single request, and while the limit is
+//! exhausted cached responses are answered without asking GitHub at all.
+
+use crate::atomic;
+use crate::error::ForgeKitError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+/// Directory of the registry cache holding GitHub API responses
+pub const CACHE_DIR: &str = "github-api";
+
+/// Result of a request, as shared with the callers waiting on it
+type SharedResult = Option<Result<Value, String>>;
+
+/// A cached API response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedResponse {
+    /// Requested URL
+    pub url: String,
+    /// ETag of the response, sent as `If-None-Match`
+    pub etag: Option<String>,
+    /// Last-Modified of the response, sent as `If-Modified-Since`
+    pub last_modified: Option<String>,
+    /// When the response was last fetched or revalidated (RFC 3339)
+    pub fetched_at: String,
+    /// Response body
+    pub body: Value,
+}
+
+impl CachedResponse {
+    /// Headers making a request conditional on the cached response
+    pub fn conditional_headers(&self) -> reqwest::header::HeaderMap {
+        let mut headers = reqwest::header::HeaderMap::new();
+        if let Some(etag) = self.etag.as_ref().and_then(|v| v.parse().ok()) {
+            headers.insert(reqwest::header::IF_NONE_MATCH, etag);
+        }
+        if let Some(date) = self.last_modified.as_ref().and_then(|v| v.parse().ok()) {
+            headers.insert(reqwest::header::IF_MODIFIED_SINCE, date);
+        }
+        headers
+    }
+}
+
+/// Rate limit last announced by GitHub
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Requests left in the current window
+    pub remaining: u64,
+    /// When the window resets (Unix timestamp)
+    pub reset: i64,
+}
+
+/// Response cache, request coalescing and rate limit tracking for the
+/// GitHub API
+///
+/// Clones share their in-flight requests and rate limit.
+#[derive(Debug, Clone)]
+pub struct GithubApi {
+    cache_dir: PathBuf,
+    in_flight: Arc<Mutex<HashMap<String, watch::Receiver<SharedResult>>>>,
+    rate_limit: Arc<Mutex<Option<RateLimit>>>,
+}
+
+impl GithubApi {
+    /// Cache responses in `cache_dir`
+    pub fn new(cache_dir: PathBuf) -> Self {
+        Self {
+            cache_dir,
+            in_flight: Arc::default(),
+            rate_limit: Arc::default(),
+        }
+    }
+
+    /// Run `fetch` for `url`, unless a request for it is already running, in
+    /// which case its result is shared
+    ///
+    /// Callers sharing a failed request get its message as a registry error.
+    pub async fn coalesce<F, Fut>(&self, url: &str, fetch: F) -> Result<Value, ForgeKitError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Value, ForgeKitError>>,
+    {
+        let running = self
+            .in_flight
+            .lock()
+            .expect("in-flight lock")
+            .get(url)
+            .cloned();
+        if let Some(mut receiver) = running {
+            // A sender dropped without a result means the request was
+            // abandoned; fetch independently then
+            if let Ok(result) = receiver.wait_for(Option::is_some).await {
+                if let Some(result) = result.clone() {
+                    return result.map_err(ForgeKitError::Registry);
+                }
+            }
+            return fetch().await;
+        }
+
+        let (sender, receiver) = watch::channel(None);
+        self.in_flight
+            .lock()
+            .expect("in-flight lock")
+            .insert(url.to_string(), receiver);
+        let _guard = InFlight {
+            api: self,
+            url: url.to_string(),
+        };
+        let result = fetch().await;
+        let shared = match &result {
+            Ok(value) => Ok(value.clone()),
+            Err(e) => Err(e.to_string()),
+        };
+        sender.send_replace(Some(shared));
+        result
+    }
+
+    /// Cached response for `url`, if any
+    pub fn cached(&self, url: &str) -> Option<CachedResponse> {
+        let contents = std::fs::read_to_string(self.cache_path(url)).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    /// Cache the response to a request
+    pub fn store(
+        &self,
+        url: &str,
+        headers: &reqwest::header::HeaderMap,
+        body: &Value,
+    ) -> Result<(), ForgeKitError> {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        let response = CachedResponse {
+            url: url.to_string(),
+            etag: header(reqwest::header::ETAG),
+            last_modified: header(reqwest::header::LAST_MODIFIED),
+            fetched_at: chrono::Utc::now().to_rfc3339(),
+            body: body.clone(),
+        };
+        std::fs::create_dir_all(&self.cache_dir)?;
+        atomic::write(self.cache_path(url), serde_json::to_vec(&response)?)
+    }
+
+    /// Mark a cached response as confirmed by a `304`, returning its body
+    pub fn revalidated(&self, mut cached: CachedResponse) -> Result<Value, ForgeKitError> {
+        cached.fetched_at = chrono::Utc::now().to_rfc3339();
+        atomic::write(self.cache_path(&cached.url), serde_json::to_vec(&cached)?)?;
+        Ok(cached.body)
+    }
+
+    /// Record the rate limit announced by a response
+    pub fn observe(&self, headers: &reqwest::header::HeaderMap) {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse::<i64>().ok())
+        };
+        if let (Some(remaining), Some(reset)) =
+            (header("x-ratelimit-remaining"), header("x-ratelimit-reset"))
+        {
+            *self.rate_limit.lock().expect("rate limit lock") = Some(RateLimit {
+                remaining: remaining.max(0) as u64,
+                reset,
+            });
+        }
+    }
+
+    /// Rate limit last announced by GitHub
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        *self.rate_limit.lock().expect("rate limit lock")
+    }
+
+    /// Whether no requests are left until the rate limit resets
+    pub fn exhausted(&self) -> bool {
+        self.rate_limit().is_some_and(|limit| {
+            limit.remaining == 0 && limit.reset > chrono::Utc::now().timestamp()
+        })
+    }
+
+    /// Cache file of the response for `url`
+    fn cache_path(&self, url: &str) -> PathBuf {
+        let key = format!("{:x}", Sha256::digest(url.as_bytes()));
+        self.cache_dir.join(format!("{}.json", &key[..32]))
+    }
+}
+
+/// Removes a finished or abandoned request from the in-flight requests
+struct InFlight<'a> {
+    api: &'a GithubApi,
+    url: String,
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut in_flight) = self.api.in_flight.lock() {
+            in_flight.remove(&self.url);
+        }
+    }
+}
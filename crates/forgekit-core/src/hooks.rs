@@ -0,0 +1,153 @@
+//! Build hooks module
+//!
+//! This module runs the commands declared in the `[hooks]` section of
+//! forgekit.toml before and after building, packaging and installing.
+
+use crate::config::ProjectConfig;
+use crate::env_manager::EnvManager;
+use crate::error::ForgeKitError;
+use std::path::Path;
+use tokio::process::Command;
+
+/// Point in the build where hooks run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookStage {
+    /// Before `cargo build`
+    PreBuild,
+    /// After a successful build
+    PostBuild,
+    /// Before the .mox archive is created
+    PrePackage,
+    /// After the .mox archive is created
+    PostPackage,
+    /// Before an extracted app is installed
+    PreInstall,
+    /// After an app is installed
+    PostInstall,
+}
+
+impl HookStage {
+    /// Name of the stage as written in forgekit.toml
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookStage::PreBuild => "pre_build",
+            HookStage::PostBuild => "post_build",
+            HookStage::PrePackage => "pre_package",
+            HookStage::PostPackage => "post_package",
+            HookStage::PreInstall => "pre_install",
+            HookStage::PostInstall => "post_install",
+        }
+    }
+
+    fn commands<'a>(&self, config: &'a ProjectConfig) -> &'a [String] {
+        match self {
+            HookStage::PreBuild => &config.hooks.pre_build,
+            HookStage::PostBuild => &config.hooks.post_build,
+            HookStage::PrePackage => &config.hooks.pre_package,
+            HookStage::PostPackage => &config.hooks.post_package,
+            HookStage::PreInstall => &config.hooks.pre_install,
+            HookStage::PostInstall => &config.hooks.post_install,
+        }
+    }
+}
+
+/// Run the hooks configured for a stage
+///
+/// Commands run in order from the project directory through the system
+/// shell. Variables from the project's `.env` file, the `FORGEKIT_*` project
+/// variables and any `extra_env` are interpolated into each command and
+/// exported to it. The first failing command aborts the stage.
+pub async fn run_hooks(
+    project_path: &Path,
+    config: &ProjectConfig,
+    stage: HookStage,
+    extra_env: &[(&str, String)],
+) -> Result<(), ForgeKitError> {
+    let commands = stage.commands(config);
+    if commands.is_empty() {
+        return Ok(());
+    }
+
+    let mut env = EnvManager::load_from_file(&project_path.join(".env"))?;
+    env.set(
+        "FORGEKIT_PROJECT_DIR".to_string(),
+        project_path.to_string_lossy().to_string(),
+    );
+    env.set("FORGEKIT_PROJECT_NAME".to_string(), config.name.clone());
+    env.set(
+        "FORGEKIT_PROJECT_VERSION".to_string(),
+        config.version.clone(),
+    );
+    env.set("FORGEKIT_HOOK".to_string(), stage.as_str().to_string());
+    for (key, value) in extra_env {
+        env.set(key.to_string(), value.clone());
+    }
+
+    for command in commands {
+        let command = env.interpolate(command)?;
+        tracing::info!("Running {} hook: {}", stage.as_str(), command);
+
+        let status = shell(&command)
+            .current_dir(project_path)
+            .envs(env.all())
+            .status()
+            .await?;
+
+        if !status.success() {
+            return Err(ForgeKitError::HookFailed(format!(
+                "{} hook `{}` exited with {}",
+                stage.as_str(),
+                command,
+                status
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(windows)]
+pub(crate) fn shell(command: &str) -> Command {
+    let mut cmd = Command::new("cmd");
+    cmd.args(["/C", command]);
+    cmd
+}
+
+#[cfg(not(windows))]
+pub(crate) fn shell(command: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.args(["-c", command]);
+    cmd
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_hooks_run_with_interpolated_env() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = ProjectConfig::default();
+        config.hooks.pre_build = vec!["echo $FORGEKIT_PROJECT_NAME > ${OUT_FILE}".to_string()];
+        std::fs::write(temp_dir.path().join(".env"), "OUT_FILE=hook.txt\n").unwrap();
+
+        run_hooks(temp_dir.path(), &config, HookStage::PreBuild, &[])
+            .await
+            .unwrap();
+
+        let output = std::fs::read_to_string(temp_dir.path().join("hook.txt")).unwrap();
+        assert_eq!(output.trim(), "unnamed");
+    }
+
+    #[tokio::test]
+    async fn test_failing_hook_stops_stage() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = ProjectConfig::default();
+        config.hooks.post_package = vec!["exit 3".to_string(), "touch never".to_string()];
+
+        let result = run_hooks(temp_dir.path(), &config, HookStage::PostPackage, &[]).await;
+        assert!(matches!(result, Err(ForgeKitError::HookFailed(_))));
+        assert!(!temp_dir.path().join("never").exists());
+    }
+}
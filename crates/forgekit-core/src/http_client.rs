@@ -0,0 +1,202 @@
+//! HTTP client module
+//!
+//! This module defines the [`HttpClient`] the registry sends its requests
+//! through. `reqwest::Client` implements it for real use; with the
+//! `test-util` feature, [`MockHttpClient`] answers from canned responses so
+//! tests run without network access.
+
+use crate::error::ForgeKitError;
+use std::future::Future;
+use std::pin::Pin;
+
+/// Response future returned by [`HttpClient::send`]
+pub type HttpFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<reqwest::Response, ForgeKitError>> + Send + 'a>>;
+
+/// Sends HTTP requests
+///
+/// Responses are returned whatever their status; callers decide which
+/// statuses are errors.
+pub trait HttpClient: Send + Sync {
+    /// Send a request and wait for the response headers
+    fn send(&self, request: reqwest::Request) -> HttpFuture<'_>;
+}
+
+impl HttpClient for reqwest::Client {
+    fn send(&self, request: reqwest::Request) -> HttpFuture<'_> {
+        Box::pin(async move { Ok(self.execute(request).await?) })
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+pub use mock::{MockHttpClient, MockResponse, RecordedRequest};
+
+#[cfg(any(test, feature = "test-util"))]
+mod mock {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    /// Canned response of a [`MockHttpClient`]
+    #[derive(Debug, Clone)]
+    pub struct MockResponse {
+        status: u16,
+        headers: Vec<(String, String)>,
+        body: Vec<u8>,
+    }
+
+    impl MockResponse {
+        /// Empty response with the given status
+        pub fn new(status: u16) -> Self {
+            Self {
+                status,
+                headers: Vec::new(),
+                body: Vec::new(),
+            }
+        }
+
+        /// `200 OK` response with a JSON body
+        pub fn json(value: &serde_json::Value) -> Self {
+            Self::new(200)
+                .header("content-type", "application/json")
+                .body(value.to_string())
+        }
+
+        /// Add a header
+        pub fn header(mut self, name: &str, value: &str) -> Self {
+            self.headers.push((name.to_string(), value.to_string()));
+            self
+        }
+
+        /// Set the body
+        pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+            self.body = body.into();
+            self
+        }
+
+        fn into_response(self) -> reqwest::Response {
+            let mut builder = hyper::Response::builder().status(self.status);
+            for (name, value) in &self.headers {
+                builder = builder.header(name, value);
+            }
+            reqwest::Response::from(builder.body(self.body).expect("valid mock response"))
+        }
+    }
+
+    /// Request received by a [`MockHttpClient`]
+    #[derive(Debug, Clone)]
+    pub struct RecordedRequest {
+        /// Request method, like `GET`
+        pub method: String,
+        /// Full URL, query included
+        pub url: String,
+        /// Headers set on the request
+        pub headers: Vec<(String, String)>,
+        /// Body, empty for requests without one
+        pub body: Vec<u8>,
+    }
+
+    /// [`HttpClient`] answering from canned responses
+    ///
+    /// Responses registered for the same method and URL are answered in
+    /// turn, the last one repeatedly. Requests nothing was registered for
+    /// get `404 Not Found`.
+    #[derive(Debug, Default)]
+    pub struct MockHttpClient {
+        routes: Mutex<Vec<(String, String, VecDeque<MockResponse>)>>,
+        requests: Mutex<Vec<RecordedRequest>>,
+    }
+
+    impl MockHttpClient {
+        /// Client without any responses
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Answer `method` requests to `url` with `response`
+        pub fn on(self, method: &str, url: &str, response: MockResponse) -> Self {
+            {
+                let mut routes = self.routes.lock().unwrap();
+                match routes.iter_mut().find(|(m, u, _)| m == method && u == url) {
+                    Some((_, _, responses)) => responses.push_back(response),
+                    None => routes.push((
+                        method.to_string(),
+                        url.to_string(),
+                        VecDeque::from([response]),
+                    )),
+                }
+            }
+            self
+        }
+
+        /// Requests received so far, in order
+        pub fn requests(&self) -> Vec<RecordedRequest> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    impl HttpClient for MockHttpClient {
+        fn send(&self, request: reqwest::Request) -> HttpFuture<'_> {
+            let method = request.method().to_string();
+            let url = request.url().to_string();
+            self.requests.lock().unwrap().push(RecordedRequest {
+                method: method.clone(),
+                url: url.clone(),
+                headers: request
+                    .headers()
+                    .iter()
+                    .map(|(name, value)| {
+                        let value = String::from_utf8_lossy(value.as_bytes()).to_string();
+                        (name.to_string(), value)
+                    })
+                    .collect(),
+                body: request
+                    .body()
+                    .and_then(|body| body.as_bytes())
+                    .map(<[u8]>::to_vec)
+                    .unwrap_or_default(),
+            });
+
+            let response = {
+                let mut routes = self.routes.lock().unwrap();
+                routes
+                    .iter_mut()
+                    .find(|(m, u, _)| *m == method && *u == url)
+                    .and_then(|(_, _, responses)| match responses.len() {
+                        0 => None,
+                        1 => responses.front().cloned(),
+                        _ => responses.pop_front(),
+                    })
+                    .unwrap_or_else(|| MockResponse::new(404))
+            };
+            Box::pin(async move { Ok(response.into_response()) })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_mock_answers_in_turn() {
+        let url = "https://registry.example/api/v1/index";
+        let client = MockHttpClient::new()
+            .on("GET", url, MockResponse::new(503))
+            .on("GET", url, MockResponse::json(&serde_json::json!([])));
+
+        let get = || reqwest::Request::new(reqwest::Method::GET, url.parse().unwrap());
+        assert_eq!(client.send(get()).await.unwrap().status(), 503);
+        assert_eq!(client.send(get()).await.unwrap().status(), 200);
+        assert_eq!(client.send(get()).await.unwrap().status(), 200);
+
+        let other = reqwest::Request::new(
+            reqwest::Method::GET,
+            "https://registry.example/api/v1/packages/x"
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(client.send(other).await.unwrap().status(), 404);
+        assert_eq!(client.requests().len(), 4);
+    }
+}
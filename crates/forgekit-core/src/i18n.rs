@@ -1,10 +1,181 @@
 //! Internationalization (i18n) module
 //!
-//! This module provides localization support for projects.
+//! This module provides localization support for projects. Locale
+//! resources live in `locales/`, either as `locales/<lang>/...` directories
+//! or single `locales/<lang>.<ext>` files. The packager bundles each locale
+//! into its own pack inside the .mox, listed in a locale manifest, so a
+//! device only extracts the languages it needs.
 
 use crate::error::ForgeKitError;
-use std::collections::HashMap;
-use std::path::Path;
+use crate::platform;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+use zip::{write::FileOptions, ZipWriter};
+
+/// Directory holding per-locale resources, in the project and in the .mox
+pub const LOCALES_DIR: &str = "locales";
+
+/// Name of the locale manifest inside a .mox archive
+pub const LOCALES_MANIFEST: &str = "locales.toml";
+
+/// A per-language asset pack inside a .mox
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LocalePack {
+    /// Language code, e.g. `fr` or `pt-BR`
+    pub locale: String,
+    /// Path of the pack inside the .mox
+    pub path: String,
+    /// Number of resource files in the pack
+    pub files: usize,
+    /// Size of the pack in bytes
+    pub size: u64,
+    /// SHA-256 checksum of the pack
+    pub sha256: String,
+}
+
+/// Manifest listing the locale packs of a .mox
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LocaleManifest {
+    /// Packs, ordered by locale
+    pub packs: Vec<LocalePack>,
+}
+
+/// Locales of a project and their resource files
+pub fn discover_locales(
+    project_path: &Path,
+) -> Result<BTreeMap<String, Vec<PathBuf>>, ForgeKitError> {
+    let mut locales = BTreeMap::new();
+    let locales_path = project_path.join(LOCALES_DIR);
+    if !locales_path.is_dir() {
+        return Ok(locales);
+    }
+
+    for entry in std::fs::read_dir(&locales_path)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            let files: Vec<PathBuf> = WalkDir::new(&path)
+                .sort_by_file_name()
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file())
+                .map(|e| e.into_path())
+                .collect();
+            let locale = path.file_name().unwrap_or_default().to_string_lossy();
+            locales
+                .entry(locale.to_string())
+                .or_insert_with(Vec::new)
+                .extend(files);
+        } else if let Some(locale) = path.file_stem() {
+            locales
+                .entry(locale.to_string_lossy().to_string())
+                .or_insert_with(Vec::new)
+                .push(path);
+        }
+    }
+
+    Ok(locales)
+}
+
+/// Keep only the requested locales; an empty filter keeps all of them
+pub fn select_locales(
+    mut locales: BTreeMap<String, Vec<PathBuf>>,
+    filter: &[String],
+) -> Result<BTreeMap<String, Vec<PathBuf>>, ForgeKitError> {
+    if filter.is_empty() {
+        return Ok(locales);
+    }
+
+    let mut selected = BTreeMap::new();
+    for locale in filter {
+        let files = locales.remove(locale).ok_or_else(|| {
+            ForgeKitError::PackagingFailed(format!(
+                "Locale '{}' not found in {}/",
+                locale, LOCALES_DIR
+            ))
+        })?;
+        selected.insert(locale.clone(), files);
+    }
+    Ok(selected)
+}
+
+/// Build the pack of one locale
+///
+/// Returns the manifest entry and the pack data, a zip archive with paths
+/// relative to `locales/`.
+pub fn build_locale_pack(
+    project_path: &Path,
+    locale: &str,
+    files: &[PathBuf],
+) -> Result<(LocalePack, Vec<u8>), ForgeKitError> {
+    let locales_path = project_path.join(LOCALES_DIR);
+    let mut zip = ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    for file in files {
+        let name = file
+            .strip_prefix(&locales_path)
+            .map_err(|_| ForgeKitError::PackagingFailed("Failed to strip prefix".to_string()))?;
+        zip.start_file(platform::archive_path(name), options)?;
+        zip.write_all(&std::fs::read(file)?)?;
+    }
+    let data = zip.finish()?.into_inner();
+
+    let pack = LocalePack {
+        locale: locale.to_string(),
+        path: format!("{}/{}.pack", LOCALES_DIR, locale),
+        files: files.len(),
+        size: data.len() as u64,
+        sha256: format!("{:x}", Sha256::digest(&data)),
+    };
+    Ok((pack, data))
+}
+
+/// Strings of a locale's TOML and JSON resources, keyed by dotted path
+///
+/// Nested tables become `table.key`. Other resource formats and values that
+/// are not strings are skipped.
+pub fn load_strings(files: &[PathBuf]) -> Result<BTreeMap<String, String>, ForgeKitError> {
+    let mut strings = BTreeMap::new();
+    for file in files {
+        let value: serde_json::Value = match file.extension().and_then(|e| e.to_str()) {
+            Some("toml") => {
+                let value: toml::Value = toml::from_str(&std::fs::read_to_string(file)?)?;
+                serde_json::to_value(value)?
+            }
+            Some("json") => serde_json::from_str(&std::fs::read_to_string(file)?)?,
+            _ => continue,
+        };
+        flatten_strings(&value, "", &mut strings);
+    }
+    Ok(strings)
+}
+
+fn flatten_strings(
+    value: &serde_json::Value,
+    prefix: &str,
+    strings: &mut BTreeMap<String, String>,
+) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten_strings(value, &key, strings);
+            }
+        }
+        serde_json::Value::String(text) => {
+            strings.insert(prefix.to_string(), text.clone());
+        }
+        _ => {}
+    }
+}
 
 /// I18n manager for managing translations
 pub struct I18nManager {
@@ -53,10 +224,35 @@ impl Default for I18nManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tempfile::TempDir;
 
     #[test]
     fn test_i18n_manager_creation() {
         let manager = I18nManager::new();
         assert!(manager.translations.is_empty());
     }
+
+    #[test]
+    fn test_locale_packs() {
+        let temp_dir = TempDir::new().unwrap();
+        let locales = temp_dir.path().join(LOCALES_DIR);
+        std::fs::create_dir_all(locales.join("fr").join("images")).unwrap();
+        std::fs::write(locales.join("fr").join("strings.json"), "{}").unwrap();
+        std::fs::write(locales.join("fr").join("images").join("logo.png"), "png").unwrap();
+        std::fs::write(locales.join("de.json"), "{}").unwrap();
+        std::fs::write(locales.join("es.json"), "{}").unwrap();
+
+        let all = discover_locales(temp_dir.path()).unwrap();
+        assert_eq!(all.keys().collect::<Vec<_>>(), ["de", "es", "fr"]);
+
+        let selected = select_locales(all.clone(), &["fr".to_string(), "de".to_string()]).unwrap();
+        assert_eq!(selected.keys().collect::<Vec<_>>(), ["de", "fr"]);
+        assert!(select_locales(all.clone(), &["ja".to_string()]).is_err());
+
+        let (pack, data) = build_locale_pack(temp_dir.path(), "fr", &all["fr"]).unwrap();
+        assert_eq!(pack.path, "locales/fr.pack");
+        assert_eq!(pack.files, 2);
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(data)).unwrap();
+        assert!(archive.by_name("fr/images/logo.png").is_ok());
+    }
 }
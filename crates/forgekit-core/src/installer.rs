@@ -0,0 +1,343 @@
+//! Local install module
+//!
+//! This module installs a .mox into a prefix directory the way the Ledokoz
+//! OS installer lays it out on a device, so install-time behavior can be
+//! tested without one. The package is extracted to a staging directory, its
+//! manifests are read and its permissions checked, and the `pre_install`
+//! hooks run from the staging directory. The app is then moved into place:
+//!
+//! - `apps/<name>/`: the package contents, with `app.bin` moved to
+//!   `bin/<name>` and made executable
+//! - `data/<name>/`: the app data directory, kept across reinstalls
+//! - `installed.toml`: the installed apps with their versions and the
+//!   permissions granted to them
+//!
+//! `post_install` hooks finally run from the app directory. Uninstalling
+//! removes the app directory and, when purging, its data.
+
+use crate::atomic;
+use crate::config::{PermissionsConfig, ProjectConfig, ProjectKind};
+use crate::error::ForgeKitError;
+use crate::hooks::{run_hooks, HookStage};
+use crate::packager::BINARY_ENTRY;
+use crate::permissions::{self, Permission, PERMISSIONS_MANIFEST};
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+use zip::ZipArchive;
+
+/// Registry of installed apps, relative to the prefix
+pub const INSTALLED_FILE: &str = "installed.toml";
+
+/// Options for installing a package
+#[derive(Debug, Clone)]
+pub struct InstallOptions {
+    /// Permissions the user accepts; every requested one when unset
+    pub granted: Option<Vec<Permission>>,
+    /// Run the package's install hooks
+    pub run_hooks: bool,
+}
+
+impl Default for InstallOptions {
+    fn default() -> Self {
+        Self {
+            granted: None,
+            run_hooks: true,
+        }
+    }
+}
+
+/// An app installed under a prefix
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstalledApp {
+    /// App name from the package manifest
+    pub name: String,
+    /// App version from the package manifest
+    pub version: String,
+    /// Directory holding the app files
+    pub app_dir: PathBuf,
+    /// Directory holding the app data
+    pub data_dir: PathBuf,
+    /// Permissions granted at install time
+    pub permissions: Vec<Permission>,
+    /// When the app was installed (RFC 3339)
+    pub installed_at: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Registry {
+    #[serde(default, rename = "app")]
+    apps: Vec<InstalledApp>,
+}
+
+impl Registry {
+    fn load(prefix: &Path) -> Result<Self, ForgeKitError> {
+        let path = prefix.join(INSTALLED_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(toml::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    fn save(&self, prefix: &Path) -> Result<(), ForgeKitError> {
+        atomic::write(prefix.join(INSTALLED_FILE), toml::to_string_pretty(self)?)
+    }
+}
+
+/// Directory holding the files of an installed app
+pub fn app_dir(prefix: &Path, name: &str) -> PathBuf {
+    prefix.join("apps").join(name)
+}
+
+/// Directory holding the data of an installed app
+pub fn data_dir(prefix: &Path, name: &str) -> PathBuf {
+    prefix.join("data").join(name)
+}
+
+/// Install a package under `prefix`, replacing an installed version
+pub async fn install(
+    package: &Path,
+    prefix: &Path,
+    options: &InstallOptions,
+) -> Result<InstalledApp, ForgeKitError> {
+    tracing::info!("Installing {:?} into {:?}", package, prefix);
+    let staging = prefix.join("apps").join(".staging");
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging)?;
+    }
+    std::fs::create_dir_all(&staging)?;
+
+    let result = install_staged(package, prefix, &staging, options).await;
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging)?;
+    }
+    result
+}
+
+async fn install_staged(
+    package: &Path,
+    prefix: &Path,
+    staging: &Path,
+    options: &InstallOptions,
+) -> Result<InstalledApp, ForgeKitError> {
+    ZipArchive::new(std::fs::File::open(package)?)?.extract(staging)?;
+
+    let config = read_manifest(staging)?;
+    let permissions = check_permissions(staging, options.granted.as_deref())?;
+    let binary = staging.join("bin").join(&config.name);
+    std::fs::create_dir_all(binary.parent().unwrap_or(staging))?;
+    std::fs::rename(staging.join(BINARY_ENTRY), &binary)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755))?;
+    }
+
+    let app_dir = app_dir(prefix, &config.name);
+    let data_dir = data_dir(prefix, &config.name);
+    let env = [
+        (
+            "FORGEKIT_INSTALL_PREFIX",
+            prefix.to_string_lossy().to_string(),
+        ),
+        ("FORGEKIT_APP_DIR", app_dir.to_string_lossy().to_string()),
+        ("FORGEKIT_DATA_DIR", data_dir.to_string_lossy().to_string()),
+    ];
+    if options.run_hooks {
+        run_hooks(staging, &config, HookStage::PreInstall, &env).await?;
+    }
+
+    if app_dir.exists() {
+        std::fs::remove_dir_all(&app_dir)?;
+    }
+    std::fs::rename(staging, &app_dir)?;
+    std::fs::create_dir_all(&data_dir)?;
+
+    let installed = InstalledApp {
+        name: config.name.clone(),
+        version: config.version.clone(),
+        app_dir: app_dir.clone(),
+        data_dir,
+        permissions,
+        installed_at: chrono::Utc::now().to_rfc3339(),
+    };
+    let mut registry = Registry::load(prefix)?;
+    registry.apps.retain(|app| app.name != installed.name);
+    registry.apps.push(installed.clone());
+    registry.save(prefix)?;
+
+    if options.run_hooks {
+        run_hooks(&app_dir, &config, HookStage::PostInstall, &env).await?;
+    }
+    tracing::info!(
+        "Installed {} {} at {:?}",
+        config.name,
+        config.version,
+        app_dir
+    );
+    Ok(installed)
+}
+
+/// Remove an installed app, and its data when `purge` is set
+pub fn uninstall(prefix: &Path, name: &str, purge: bool) -> Result<InstalledApp, ForgeKitError> {
+    let mut registry = Registry::load(prefix)?;
+    let index = registry
+        .apps
+        .iter()
+        .position(|app| app.name == name)
+        .ok_or_else(|| {
+            ForgeKitError::InstallFailed(format!("{} is not installed in {:?}", name, prefix))
+        })?;
+    let app = registry.apps.remove(index);
+
+    if app.app_dir.exists() {
+        std::fs::remove_dir_all(&app.app_dir)?;
+    }
+    if purge && app.data_dir.exists() {
+        std::fs::remove_dir_all(&app.data_dir)?;
+    }
+    registry.save(prefix)?;
+    Ok(app)
+}
+
+/// Apps installed under `prefix`
+pub fn installed(prefix: &Path) -> Result<Vec<InstalledApp>, ForgeKitError> {
+    Ok(Registry::load(prefix)?.apps)
+}
+
+/// Read the app manifest of an extracted package
+fn read_manifest(staging: &Path) -> Result<ProjectConfig, ForgeKitError> {
+    let manifest = staging.join("forgekit.toml");
+    if !manifest.exists() {
+        return Err(ForgeKitError::InstallFailed(
+            "package has no forgekit.toml".to_string(),
+        ));
+    }
+    let config: ProjectConfig = toml::from_str(&std::fs::read_to_string(manifest)?)?;
+
+    if config.kind == ProjectKind::Library {
+        return Err(ForgeKitError::InstallFailed(format!(
+            "{} is a library; only apps can be installed",
+            config.name
+        )));
+    }
+    let mut components = Path::new(&config.name).components();
+    let plain_name = matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    );
+    if !plain_name || config.name.starts_with('.') {
+        return Err(ForgeKitError::InstallFailed(format!(
+            "invalid app name '{}'",
+            config.name
+        )));
+    }
+    if !staging.join(BINARY_ENTRY).is_file() {
+        return Err(ForgeKitError::InstallFailed(format!(
+            "package has no {}",
+            BINARY_ENTRY
+        )));
+    }
+    Ok(config)
+}
+
+/// Check the permissions manifest, returning the requested permissions
+///
+/// Like the OS installer, a package without a manifest or requesting a
+/// permission that is not granted is refused.
+fn check_permissions(
+    staging: &Path,
+    granted: Option<&[Permission]>,
+) -> Result<Vec<Permission>, ForgeKitError> {
+    let manifest = staging.join(PERMISSIONS_MANIFEST);
+    if !manifest.exists() {
+        return Err(ForgeKitError::InstallFailed(format!(
+            "package has no {}",
+            PERMISSIONS_MANIFEST
+        )));
+    }
+    let config: PermissionsConfig = toml::from_str(&std::fs::read_to_string(manifest)?)?;
+
+    for path in &config.filesystem {
+        let path = Path::new(path);
+        if !path.has_root() || path.components().any(|c| c == Component::ParentDir) {
+            return Err(ForgeKitError::InstallFailed(format!(
+                "filesystem permission {:?} must be an absolute path without '..'",
+                path
+            )));
+        }
+    }
+
+    let requested = permissions::requested(&config);
+    if let Some(granted) = granted {
+        if let Some(denied) = requested.iter().find(|p| !granted.contains(p)) {
+            return Err(ForgeKitError::InstallFailed(format!(
+                "the app requests the '{}' permission, which was not granted",
+                denied
+            )));
+        }
+    }
+    Ok(requested)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+    use zip::{write::FileOptions, ZipWriter};
+
+    fn write_package(path: &Path, config: &ProjectConfig) {
+        let mut zip = ZipWriter::new(std::fs::File::create(path).unwrap());
+        let entries = [
+            (BINARY_ENTRY, "binary".to_string()),
+            ("forgekit.toml", toml::to_string(config).unwrap()),
+            (
+                PERMISSIONS_MANIFEST,
+                toml::to_string(&config.permissions).unwrap(),
+            ),
+            ("assets/icon.png", "png".to_string()),
+        ];
+        for (name, contents) in entries {
+            zip.start_file(name, FileOptions::default()).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_install_lays_out_app_and_runs_hooks() {
+        let temp_dir = TempDir::new().unwrap();
+        let prefix = temp_dir.path().join("device");
+        let package = temp_dir.path().join("notes.mox");
+        let mut config = ProjectConfig {
+            name: "notes".to_string(),
+            ..Default::default()
+        };
+        config.permissions.camera = true;
+        config.hooks.pre_install = vec!["test -x bin/notes".to_string()];
+        config.hooks.post_install = vec!["touch $FORGEKIT_DATA_DIR/ready".to_string()];
+        write_package(&package, &config);
+
+        let denied = InstallOptions {
+            granted: Some(vec![Permission::Network]),
+            ..Default::default()
+        };
+        assert!(install(&package, &prefix, &denied).await.is_err());
+        assert!(!app_dir(&prefix, "notes").exists());
+
+        let app = install(&package, &prefix, &InstallOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(app.permissions, [Permission::Camera]);
+        assert!(app.app_dir.join("bin/notes").is_file());
+        assert!(app.app_dir.join("assets/icon.png").is_file());
+        assert!(app.data_dir.join("ready").exists());
+        assert_eq!(installed(&prefix).unwrap(), [app]);
+
+        uninstall(&prefix, "notes", false).unwrap();
+        assert!(!app_dir(&prefix, "notes").exists());
+        assert!(data_dir(&prefix, "notes").exists());
+        assert!(installed(&prefix).unwrap().is_empty());
+    }
+}
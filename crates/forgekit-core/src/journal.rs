@@ -0,0 +1,287 @@
+//! Journal module
+//!
+//! This module makes multi-step changes to a project all-or-nothing. Before
+//! an operation such as `forgekit add` changes forgekit.toml, forgekit.lock
+//! or vendor/, it records the original state in a journal under
+//! `.forgekit/journal`. When a later step fails the journal rolls every
+//! change back; when all steps succeed it is committed and discarded. A
+//! journal left behind by an interrupted process is rolled back the next
+//! time an operation starts on the project.
+
+use crate::atomic;
+use crate::error::ForgeKitError;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the file listing the journal entries
+pub const JOURNAL_FILE: &str = "journal.json";
+
+/// Directory holding the journal of a project
+pub fn journal_dir(project_root: &Path) -> PathBuf {
+    project_root.join(".forgekit").join("journal")
+}
+
+/// A change recorded before it is made
+///
+/// `backup` holds the original when the path existed; without one the path
+/// was created by the operation and is removed on rollback. A file is
+/// copied to its backup before its entry is written, so every recorded file
+/// backup is complete. A directory is moved to its backup after its entry
+/// is written, so a directory backup that does not exist means the move
+/// never happened.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum JournalEntry {
+    /// A file about to be written
+    File {
+        path: PathBuf,
+        backup: Option<PathBuf>,
+    },
+    /// A directory about to be replaced or removed
+    Dir {
+        path: PathBuf,
+        backup: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct JournalState {
+    operation: String,
+    entries: Vec<JournalEntry>,
+}
+
+/// Journal of an operation in progress
+///
+/// A journal dropped without [`commit`](Self::commit) or
+/// [`rollback`](Self::rollback) stays on disk and is rolled back by
+/// [`recover`](Self::recover).
+#[derive(Debug)]
+pub struct Journal {
+    dir: PathBuf,
+    state: JournalState,
+}
+
+impl Journal {
+    /// Start journaling an operation, rolling back any interrupted one first
+    pub fn begin(project_root: &Path, operation: &str) -> Result<Self, ForgeKitError> {
+        Self::recover(project_root)?;
+
+        let dir = journal_dir(project_root);
+        fs::create_dir_all(&dir)?;
+        let journal = Self {
+            dir,
+            state: JournalState {
+                operation: operation.to_string(),
+                entries: Vec::new(),
+            },
+        };
+        journal.save()?;
+        Ok(journal)
+    }
+
+    /// Roll back a journal left behind by an interrupted operation
+    ///
+    /// Returns the name of the operation that was rolled back.
+    pub fn recover(project_root: &Path) -> Result<Option<String>, ForgeKitError> {
+        let dir = journal_dir(project_root);
+        let path = dir.join(JOURNAL_FILE);
+        if !path.exists() {
+            if dir.exists() {
+                fs::remove_dir_all(&dir)?;
+            }
+            return Ok(None);
+        }
+
+        let state: JournalState = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        let operation = state.operation.clone();
+        tracing::warn!("Rolling back interrupted operation '{}'", operation);
+        Self { dir, state }.rollback()?;
+        Ok(Some(operation))
+    }
+
+    /// Remember the contents of a file before it is written
+    pub fn track_file(&mut self, path: &Path) -> Result<(), ForgeKitError> {
+        if self.tracks(path) {
+            return Ok(());
+        }
+
+        let backup = path.exists().then(|| self.next_backup());
+        if let Some(backup) = &backup {
+            atomic::copy(path, backup)?;
+        }
+        self.push(JournalEntry::File {
+            path: path.to_path_buf(),
+            backup,
+        })
+    }
+
+    /// Move a directory aside so it can be restored
+    ///
+    /// The directory no longer exists afterwards, so the operation can
+    /// recreate it or leave it removed.
+    pub fn stash_dir(&mut self, path: &Path) -> Result<(), ForgeKitError> {
+        if self.tracks(path) {
+            if path.exists() {
+                fs::remove_dir_all(path)?;
+            }
+            return Ok(());
+        }
+
+        let backup = path.exists().then(|| self.next_backup());
+        self.push(JournalEntry::Dir {
+            path: path.to_path_buf(),
+            backup: backup.clone(),
+        })?;
+        if let Some(backup) = backup {
+            fs::rename(path, backup)?;
+        }
+        Ok(())
+    }
+
+    /// Keep the changes and discard the journal
+    pub fn commit(self) -> Result<(), ForgeKitError> {
+        fs::remove_dir_all(&self.dir)?;
+        Ok(())
+    }
+
+    /// Undo the recorded changes, newest first, and discard the journal
+    pub fn rollback(self) -> Result<(), ForgeKitError> {
+        for entry in self.state.entries.iter().rev() {
+            match entry {
+                JournalEntry::File { path, backup } => match backup {
+                    Some(backup) => atomic::copy(backup, path)?,
+                    None if path.exists() => fs::remove_file(path)?,
+                    None => {}
+                },
+                JournalEntry::Dir { path, backup } => match backup {
+                    Some(backup) if backup.exists() => {
+                        if path.exists() {
+                            fs::remove_dir_all(path)?;
+                        }
+                        fs::rename(backup, path)?;
+                    }
+                    Some(_) => {}
+                    None if path.exists() => fs::remove_dir_all(path)?,
+                    None => {}
+                },
+            }
+        }
+        fs::remove_dir_all(&self.dir)?;
+        Ok(())
+    }
+
+    /// Commit when the operation succeeded, roll back when it failed
+    pub fn finish<T>(self, result: Result<T, ForgeKitError>) -> Result<T, ForgeKitError> {
+        match result {
+            Ok(value) => {
+                self.commit()?;
+                Ok(value)
+            }
+            Err(e) => match self.rollback() {
+                Ok(()) => Err(e),
+                Err(rollback) => Err(ForgeKitError::RollbackFailed(format!(
+                    "{} (rolling back failed: {})",
+                    e, rollback
+                ))),
+            },
+        }
+    }
+
+    fn tracks(&self, path: &Path) -> bool {
+        self.state.entries.iter().any(|entry| match entry {
+            JournalEntry::File { path: tracked, .. } | JournalEntry::Dir { path: tracked, .. } => {
+                tracked == path
+            }
+        })
+    }
+
+    fn next_backup(&self) -> PathBuf {
+        self.dir.join(format!("{}.bak", self.state.entries.len()))
+    }
+
+    fn push(&mut self, entry: JournalEntry) -> Result<(), ForgeKitError> {
+        self.state.entries.push(entry);
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), ForgeKitError> {
+        atomic::write(
+            self.dir.join(JOURNAL_FILE),
+            serde_json::to_string_pretty(&self.state)?,
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_failed_operation_is_rolled_back() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let config = root.join("forgekit.toml");
+        let lockfile = root.join("forgekit.lock");
+        let vendored = root.join("vendor").join("json-1.0.0");
+        fs::write(&config, "original").unwrap();
+        fs::create_dir_all(&vendored).unwrap();
+        fs::write(vendored.join("lib.rs"), "old").unwrap();
+
+        let mut journal = Journal::begin(root, "add json").unwrap();
+        journal.track_file(&config).unwrap();
+        fs::write(&config, "changed").unwrap();
+        journal.track_file(&lockfile).unwrap();
+        fs::write(&lockfile, "new").unwrap();
+        journal.stash_dir(&vendored).unwrap();
+        fs::create_dir_all(&vendored).unwrap();
+        fs::write(vendored.join("lib.rs"), "new").unwrap();
+
+        let failed: Result<(), _> = Err(ForgeKitError::Registry("offline".to_string()));
+        let err = journal.finish(failed).unwrap_err();
+        assert!(matches!(err, ForgeKitError::Registry(_)));
+        assert_eq!(fs::read_to_string(&config).unwrap(), "original");
+        assert!(!lockfile.exists());
+        assert_eq!(fs::read_to_string(vendored.join("lib.rs")).unwrap(), "old");
+        assert!(!journal_dir(root).exists());
+    }
+
+    #[test]
+    fn test_interrupted_operation_is_recovered() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let vendored = root.join("vendor").join("json-1.0.0");
+        fs::create_dir_all(&vendored).unwrap();
+
+        let mut journal = Journal::begin(root, "remove json").unwrap();
+        journal.stash_dir(&vendored).unwrap();
+        drop(journal);
+        assert!(!vendored.exists());
+
+        assert_eq!(
+            Journal::recover(root).unwrap().as_deref(),
+            Some("remove json")
+        );
+        assert!(vendored.exists());
+        assert_eq!(Journal::recover(root).unwrap(), None);
+    }
+
+    #[test]
+    fn test_backup_without_entry_is_ignored() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let config = root.join("forgekit.toml");
+        fs::write(&config, "original").unwrap();
+
+        // Interrupted after the backup was taken, before its entry was saved
+        let journal = Journal::begin(root, "add json").unwrap();
+        atomic::copy(&config, journal.next_backup()).unwrap();
+        drop(journal);
+
+        assert_eq!(Journal::recover(root).unwrap().as_deref(), Some("add json"));
+        assert_eq!(fs::read_to_string(&config).unwrap(), "original");
+        assert!(!journal_dir(root).exists());
+    }
+}
@@ -3,43 +3,313 @@
 //! This crate provides the core functionality for building, packaging,
 //! and managing `.mox` applications for Ledokoz OS.
 
+pub mod affected;
 pub mod analytics;
+pub mod api_surface;
+pub mod appmeta;
+pub mod appstore;
+pub mod artifacts;
 pub mod asset_optimizer;
+pub mod atomic;
 pub mod audit;
+pub mod audit_log;
+pub mod batch;
+pub mod build_log;
 pub mod builder;
 pub mod cache;
+pub mod cancel;
 pub mod cicd;
+pub mod codegen;
+pub mod command_policy;
+pub mod compiler_cache;
 pub mod config;
+pub mod crash;
+pub mod credentials;
+#[cfg(unix)]
+pub mod daemon;
+pub mod dashboard;
+pub mod dedup;
 pub mod dependencies;
+pub mod dev_cert;
+pub mod dev_env;
 pub mod dev_server;
+pub mod dev_workspace;
+pub mod diagnostics;
 pub mod doc_generator;
 pub mod docker;
+pub mod download;
+pub mod dry_run;
 pub mod env_manager;
 pub mod error;
+pub mod events;
+pub mod filesystem;
+pub mod gc;
+pub mod generate;
+pub mod git_hooks;
+pub mod github_api;
+pub mod hooks;
+pub mod http_client;
 pub mod i18n;
+pub mod installer;
+pub mod journal;
+pub mod license_header;
+pub mod lint;
+pub mod lock;
+pub mod lockfile;
+pub mod logs;
+pub mod manifest;
 pub mod migrations;
 pub mod monitoring;
+pub mod moxlib;
 pub mod multi_target;
 pub mod openapi;
+pub mod ota;
+pub mod output;
+pub mod overrides;
+pub mod package_diff;
 pub mod package_manager;
 pub mod packager;
+pub mod permissions;
+pub mod platform;
 pub mod plugin;
+pub mod plugin_store;
+pub mod policy;
+pub mod ports;
 pub mod profiler;
 pub mod project;
+pub mod redact;
 pub mod registry;
+pub mod registry_server;
+pub mod release_profile;
+pub mod runner;
+pub mod sandbox;
+pub mod schema;
+pub mod scripts;
 pub mod secrets;
+pub mod self_update;
+pub mod semver_check;
+pub mod static_files;
+pub mod store;
+pub mod symbols;
+pub mod telemetry;
+pub mod template_update;
 pub mod templates;
 pub mod testing;
+pub mod toolchain;
+pub mod ui;
+pub mod update_pr;
+pub mod upload;
 pub mod validator;
 pub mod version_manager;
+pub mod watch;
+pub mod workspace;
 
 /// The main ForgeKit library
-pub struct ForgeKit;
+///
+/// Holds the configuration shared by every operation: the user's global
+/// configuration, registered plugins, a registry client, the build cache
+/// location and the event bus operations report to. Create one with
+/// [`ForgeKit::builder`].
+pub struct ForgeKit {
+    global: config::GlobalConfig,
+    plugins: plugin::PluginManager,
+    registry: registry::RegistryClient,
+    cache_dir: Option<std::path::PathBuf>,
+    events: events::EventBus,
+    lock: lock::LockOptions,
+}
+
+/// Builder for a configured [`ForgeKit`]
+#[derive(Default)]
+pub struct ForgeKitBuilder {
+    global: Option<config::GlobalConfig>,
+    plugins: plugin::PluginManager,
+    pending_plugins: Vec<Box<dyn plugin::AsyncPlugin>>,
+    installed_plugins: Option<std::path::PathBuf>,
+    registry: Option<registry::RegistryConfig>,
+    cache_dir: Option<std::path::PathBuf>,
+    events: events::EventBus,
+    lock: lock::LockOptions,
+}
+
+impl ForgeKitBuilder {
+    /// Use this global configuration instead of the user's one
+    pub fn with_global_config(mut self, global: config::GlobalConfig) -> Self {
+        self.global = Some(global);
+        self
+    }
+
+    /// Use this registry instead of the default one
+    pub fn with_registry(mut self, registry: registry::RegistryConfig) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Use these plugins, replacing any registered so far and their hook
+    /// timeouts
+    pub fn with_plugins(mut self, plugins: plugin::PluginManager) -> Self {
+        self.plugins = plugins;
+        self.pending_plugins.clear();
+        self
+    }
+
+    /// Register one more plugin
+    ///
+    /// [`build`](Self::build) fails if the plugin is built against a plugin
+    /// API version this ForgeKit does not support.
+    pub fn with_plugin(mut self, plugin: Box<dyn plugin::Plugin>) -> Self {
+        self.pending_plugins.push(plugin::blocking(plugin));
+        self
+    }
+
+    /// Register one more plugin with async hooks
+    ///
+    /// [`build`](Self::build) fails like for [`with_plugin`](Self::with_plugin).
+    pub fn with_async_plugin(mut self, plugin: Box<dyn plugin::AsyncPlugin>) -> Self {
+        self.pending_plugins.push(plugin);
+        self
+    }
+
+    /// Fail a step when one of its plugins' `hook`s runs longer than
+    /// `timeout`
+    ///
+    /// Hooks without one use `[plugins.timeouts]` of the project given to
+    /// [`with_installed_plugins`](Self::with_installed_plugins), then of the
+    /// global configuration, and have no timeout otherwise.
+    pub fn with_hook_timeout(mut self, hook: plugin::Hook, timeout: std::time::Duration) -> Self {
+        self.plugins = self.plugins.with_timeout(hook, timeout);
+        self
+    }
+
+    /// Load the enabled plugins installed for the project at `project_path`
+    /// and for every project
+    ///
+    /// A plugin installed for the project is used instead of one of the same
+    /// name installed for every project.
+    pub fn with_installed_plugins(mut self, project_path: impl Into<std::path::PathBuf>) -> Self {
+        self.installed_plugins = Some(project_path.into());
+        self
+    }
+
+    /// Keep build caches in this directory instead of each project's
+    pub fn with_cache_dir(mut self, cache_dir: impl Into<std::path::PathBuf>) -> Self {
+        self.cache_dir = Some(cache_dir.into());
+        self
+    }
+
+    /// Report events on this bus, e.g. one shared with other components
+    pub fn with_events(mut self, events: events::EventBus) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Lock projects and caches this way, e.g. not at all for `--no-lock`
+    pub fn with_lock(mut self, lock: lock::LockOptions) -> Self {
+        self.lock = lock;
+        self
+    }
+
+    /// Create the ForgeKit instance
+    ///
+    /// Loads the user's global configuration unless one was given.
+    pub fn build(mut self) -> Result<ForgeKit, error::ForgeKitError> {
+        let global = match self.global {
+            Some(global) => global,
+            None => config::GlobalConfig::load(config::GlobalConfig::default_path())?,
+        };
+        for plugin in self.pending_plugins.drain(..) {
+            self.plugins.register_async(plugin)?;
+        }
+        let mut timeouts = vec![global.plugins.timeouts.clone()];
+        if let Some(project_path) = &self.installed_plugins {
+            let config_path = project_path.join("forgekit.toml");
+            if config_path.exists() {
+                let project = config::ProjectConfig::load(config_path)?;
+                timeouts.insert(0, project.plugins.timeouts);
+            }
+            let project = plugin_store::PluginStore::for_project(project_path)?
+                .load_into(&mut self.plugins, &[])?;
+            let names: Vec<String> = project.into_iter().map(|plugin| plugin.name).collect();
+            plugin_store::PluginStore::global()?.load_into(&mut self.plugins, &names)?;
+        }
+        for hook in [
+            plugin::Hook::PreBuild,
+            plugin::Hook::PostBuild,
+            plugin::Hook::Package,
+        ] {
+            let configured = timeouts.iter().find_map(|timeouts| timeouts.get(hook));
+            if let (None, Some(timeout)) = (self.plugins.timeout(hook), configured) {
+                self.plugins = self.plugins.with_timeout(hook, timeout);
+            }
+        }
+        let registry = registry::RegistryClient::from_login(self.registry.unwrap_or_default())?;
+        Ok(ForgeKit {
+            global,
+            plugins: self.plugins,
+            registry,
+            cache_dir: self.cache_dir,
+            events: self.events,
+            lock: self.lock,
+        })
+    }
+}
 
 impl ForgeKit {
-    /// Create a new ForgeKit instance
-    pub fn new() -> Self {
-        Self
+    /// Create a new ForgeKit instance with the user's configuration
+    ///
+    /// Fails if the configuration cannot be loaded.
+    pub fn new() -> Result<Self, error::ForgeKitError> {
+        Self::builder().build()
+    }
+
+    /// Start configuring a ForgeKit instance
+    pub fn builder() -> ForgeKitBuilder {
+        ForgeKitBuilder::default()
+    }
+
+    /// Global configuration in use
+    pub fn global_config(&self) -> &config::GlobalConfig {
+        &self.global
+    }
+
+    /// Registered plugins
+    pub fn plugins(&self) -> &plugin::PluginManager {
+        &self.plugins
+    }
+
+    /// Registered plugins, for registering more
+    pub fn plugins_mut(&mut self) -> &mut plugin::PluginManager {
+        &mut self.plugins
+    }
+
+    /// Registry client
+    pub fn registry(&self) -> &registry::RegistryClient {
+        &self.registry
+    }
+
+    /// Bus the operations of this instance report events to
+    pub fn events(&self) -> &events::EventBus {
+        &self.events
+    }
+
+    /// Call `callback` for every event of this instance's operations
+    pub fn subscribe(
+        &self,
+        callback: impl Fn(&events::Event) + Send + Sync + 'static,
+    ) -> events::SubscriptionId {
+        self.events.subscribe(callback)
+    }
+
+    /// Open the build cache of a project
+    pub fn build_cache(
+        &self,
+        project_path: &std::path::Path,
+    ) -> Result<cache::BuildCache, error::ForgeKitError> {
+        let cache_dir = self
+            .cache_dir
+            .clone()
+            .unwrap_or_else(|| project_path.join(".forgekit").join("cache"));
+        Ok(cache::BuildCache::new(cache_dir)?.with_lock(self.lock.clone()))
     }
 
     /// Initialize a new project
@@ -51,6 +321,30 @@ impl ForgeKit {
         project::init(name, path).await
     }
 
+    /// Initialize a new project with a template, license and version control
+    pub async fn init_project_with_options(
+        &self,
+        name: &str,
+        path: &std::path::Path,
+        options: &project::InitOptions,
+    ) -> Result<(), error::ForgeKitError> {
+        project::init_with_options(name, path, options).await
+    }
+
+    /// Rename a project and every file that refers to it by name
+    pub async fn rename_project(
+        &self,
+        path: &std::path::Path,
+        new_name: &str,
+        dry_run: &dry_run::DryRun,
+    ) -> Result<Vec<std::path::PathBuf>, error::ForgeKitError> {
+        if dry_run.is_enabled() {
+            return project::rename(path, new_name, dry_run);
+        }
+        let _lock = lock::FileLock::project(path, &self.lock, "rename").await?;
+        project::rename(path, new_name, dry_run)
+    }
+
     /// Initialize a new project with a specific template
     pub async fn init_project_with_template(
         &self,
@@ -62,8 +356,42 @@ impl ForgeKit {
     }
 
     /// Build a project
-    pub async fn build_project(&self, path: &std::path::Path) -> Result<(), error::ForgeKitError> {
-        builder::build(path).await
+    pub async fn build_project(
+        &self,
+        path: &std::path::Path,
+    ) -> Result<builder::BuildSummary, error::ForgeKitError> {
+        self.build_project_with_options(path, &builder::BuildOptions::default())
+            .await
+    }
+
+    /// Build a project with custom options
+    ///
+    /// Plugins are called around the build, the compiler cache defaults to
+    /// the one of the global configuration, and events and locking follow
+    /// this instance's settings.
+    pub async fn build_project_with_options(
+        &self,
+        path: &std::path::Path,
+        options: &builder::BuildOptions,
+    ) -> Result<builder::BuildSummary, error::ForgeKitError> {
+        let context = plugin::BuildContext {
+            project_path: path.to_string_lossy().to_string(),
+            target: "ledokoz".to_string(),
+        };
+        self.plugins.call_pre_build(&context).await?;
+
+        let options = builder::BuildOptions {
+            compiler_cache: options
+                .compiler_cache
+                .clone()
+                .or_else(|| Some(self.global.compiler_cache.clone())),
+            events: self.events.clone(),
+            lock: self.lock.clone(),
+            ..options.clone()
+        };
+        let summary = builder::build_with_options(path, &options).await?;
+        self.plugins.call_post_build(&context).await?;
+        Ok(summary)
     }
 
     /// Package a project into a .mox file
@@ -71,12 +399,154 @@ impl ForgeKit {
         &self,
         path: &std::path::Path,
     ) -> Result<std::path::PathBuf, error::ForgeKitError> {
-        packager::package(path).await
+        self.package_project_with_options(path, &packager::PackageOptions::default())
+            .await
+    }
+
+    /// Package a project, stopping early if `cancel` is triggered
+    pub async fn package_project_with_cancel(
+        &self,
+        path: &std::path::Path,
+        cancel: &cancel::CancellationToken,
+    ) -> Result<std::path::PathBuf, error::ForgeKitError> {
+        let options = packager::PackageOptions {
+            cancel: cancel.clone(),
+            ..Default::default()
+        };
+        self.package_project_with_options(path, &options).await
+    }
+
+    /// Package a project with custom options, e.g. a subset of locales
+    pub async fn package_project_with_options(
+        &self,
+        path: &std::path::Path,
+        options: &packager::PackageOptions,
+    ) -> Result<std::path::PathBuf, error::ForgeKitError> {
+        let options = packager::PackageOptions {
+            events: self.events.clone(),
+            ..options.clone()
+        };
+        let package = packager::package_with_options(path, &options).await?;
+        self.plugins
+            .call_package(&plugin::PackageContext {
+                project_path: path.to_string_lossy().to_string(),
+                output_path: package.to_string_lossy().to_string(),
+            })
+            .await?;
+        Ok(package)
+    }
+
+    /// Validate a project, reporting each problem as an event
+    pub async fn validate_project(
+        &self,
+        path: &std::path::Path,
+    ) -> Result<validator::ValidationReport, error::ForgeKitError> {
+        let report = validator::ProjectValidator::validate_project(path).await?;
+        let issues = report
+            .errors
+            .iter()
+            .map(|message| ("error", message))
+            .chain(report.warnings.iter().map(|message| ("warning", message)));
+        for (severity, message) in issues {
+            self.events.emit(events::Event::ValidationIssue {
+                project_path: path.to_path_buf(),
+                severity: severity.to_string(),
+                message: message.clone(),
+            });
+        }
+        Ok(report)
+    }
+
+    /// Run build, test, validate or package over many projects concurrently
+    ///
+    /// Builds use the compiler cache of the global configuration unless
+    /// `options` set one.
+    pub async fn batch(
+        &self,
+        projects: &[std::path::PathBuf],
+        command: batch::BatchCommand,
+        options: &batch::BatchOptions,
+    ) -> Result<batch::BatchReport, error::ForgeKitError> {
+        let options = batch::BatchOptions {
+            compiler_cache: options
+                .compiler_cache
+                .clone()
+                .or_else(|| Some(self.global.compiler_cache.clone())),
+            ..options.clone()
+        };
+        batch::Batch::run(projects, command, &options).await
     }
 }
 
-impl Default for ForgeKit {
-    fn default() -> Self {
-        Self::new()
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_builder_configures_context() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut global = config::GlobalConfig::default();
+        global.telemetry.enabled = true;
+
+        let forgekit = ForgeKit::builder()
+            .with_global_config(global)
+            .with_plugin(Box::new(plugin::ExamplePlugin))
+            .with_hook_timeout(plugin::Hook::Package, std::time::Duration::from_secs(600))
+            .with_cache_dir(temp_dir.path().join("cache"))
+            .build()
+            .unwrap();
+        assert!(forgekit.global_config().telemetry.enabled);
+        assert_eq!(forgekit.plugins().plugin_count(), 1);
+        assert_eq!(forgekit.plugins().timeout(plugin::Hook::PreBuild), None);
+        assert_eq!(
+            forgekit.plugins().timeout(plugin::Hook::Package),
+            Some(std::time::Duration::from_secs(600))
+        );
+
+        let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = events.clone();
+        forgekit.subscribe(move |event| sink.lock().unwrap().push(event.clone()));
+        let report = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(forgekit.validate_project(temp_dir.path()))
+            .unwrap();
+        assert_eq!(
+            events.lock().unwrap().len(),
+            report.errors.len() + report.warnings.len()
+        );
+        assert!(!report.errors.is_empty());
+
+        forgekit.build_cache(temp_dir.path()).unwrap();
+        assert!(temp_dir.path().join("cache").is_dir());
+        assert!(!temp_dir.path().join(".forgekit").exists());
+    }
+
+    #[test]
+    fn test_hook_timeouts_from_configuration() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut global = config::GlobalConfig::default();
+        global.plugins.timeouts.post_build = Some(30);
+        global.plugins.timeouts.package = Some(60);
+        let mut project = config::ProjectConfig::default();
+        project.plugins.timeouts.package = Some(120);
+        project.save(temp_dir.path().join("forgekit.toml")).unwrap();
+
+        let forgekit = ForgeKit::builder()
+            .with_global_config(global)
+            .with_installed_plugins(temp_dir.path())
+            .with_hook_timeout(plugin::Hook::PostBuild, std::time::Duration::from_secs(5))
+            .build()
+            .unwrap();
+        let timeout = |hook| forgekit.plugins().timeout(hook);
+        assert_eq!(timeout(plugin::Hook::PreBuild), None);
+        assert_eq!(
+            timeout(plugin::Hook::PostBuild),
+            Some(std::time::Duration::from_secs(5))
+        );
+        assert_eq!(
+            timeout(plugin::Hook::Package),
+            Some(std::time::Duration::from_secs(120))
+        );
     }
 }
@@ -0,0 +1,206 @@
+//! License header module
+//!
+//! This module checks that every source file under `src/` starts with the
+//! header configured in `[license]`, and adds it where it is missing. The
+//! header is a template: `{year}`, `{author}` and `{name}` are filled in from
+//! the current year and the project. A file passes whatever year or range of
+//! years its header names, so headers do not have to be updated every
+//! January. Each line is commented in the style of the file's language, and
+//! a leading shebang is kept in place.
+
+use crate::atomic;
+use crate::config::ProjectConfig;
+use crate::error::ForgeKitError;
+use chrono::Datelike;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Line comment of each supported file extension
+const COMMENT_STYLES: &[(&str, &str)] = &[
+    ("rs", "//"),
+    ("c", "//"),
+    ("h", "//"),
+    ("cpp", "//"),
+    ("js", "//"),
+    ("ts", "//"),
+    ("py", "#"),
+    ("sh", "#"),
+    ("toml", "#"),
+    ("yaml", "#"),
+    ("yml", "#"),
+];
+
+/// What `{year}` matches when checking existing headers: a year, or a list or
+/// range of years
+const YEAR_PATTERN: &str = r"\d{4}(?:\s*[-,]\s*\d{4})*";
+
+/// The license header a project requires
+#[derive(Debug, Clone)]
+pub struct LicenseHeader {
+    /// Header text with `{author}` and `{name}` filled in
+    template: String,
+}
+
+impl LicenseHeader {
+    /// Header configured in `[license]`, `None` when there is none
+    pub fn from_config(
+        project_path: &Path,
+        config: &ProjectConfig,
+    ) -> Result<Option<Self>, ForgeKitError> {
+        let license = &config.license;
+        let text = match (&license.header, &license.header_file) {
+            (Some(header), _) => header.clone(),
+            (None, Some(file)) => {
+                std::fs::read_to_string(project_path.join(file)).map_err(|e| {
+                    ForgeKitError::InvalidConfig(format!(
+                        "Cannot read license header {}: {}",
+                        file, e
+                    ))
+                })?
+            }
+            (None, None) => return Ok(None),
+        };
+        let author = license
+            .author
+            .clone()
+            .unwrap_or_else(|| config.authors.join(", "));
+        let template = text
+            .trim_end()
+            .replace("{author}", &author)
+            .replace("{name}", &config.name);
+        Ok(Some(Self { template }))
+    }
+
+    /// Header for a file with the given line comment, for the current year
+    pub fn render(&self, comment: &str) -> String {
+        let year = chrono::Local::now().year().to_string();
+        commented(&self.template, comment).replace("{year}", &year)
+    }
+
+    /// Whether `contents` starts with the header, after any shebang
+    pub fn is_present(&self, contents: &str, comment: &str) -> bool {
+        let pattern = regex::escape(&commented(&self.template, comment))
+            .replace(&regex::escape("{year}"), YEAR_PATTERN);
+        let Ok(header) = Regex::new(&format!("^{}", pattern)) else {
+            return false;
+        };
+        header.is_match(&strip_shebang(contents).0.replace("\r\n", "\n"))
+    }
+
+    /// Source files under `src/` that do not start with the header
+    pub fn missing(&self, project_path: &Path) -> Result<Vec<PathBuf>, ForgeKitError> {
+        let mut missing = Vec::new();
+        for (path, comment) in source_files(project_path) {
+            let contents = std::fs::read_to_string(&path)?;
+            if !self.is_present(&contents, comment) {
+                missing.push(path);
+            }
+        }
+        Ok(missing)
+    }
+
+    /// Add the header to every source file under `src/` that lacks it
+    ///
+    /// Returns the files that were changed.
+    pub fn apply(&self, project_path: &Path) -> Result<Vec<PathBuf>, ForgeKitError> {
+        let mut changed = Vec::new();
+        for (path, comment) in source_files(project_path) {
+            let contents = std::fs::read_to_string(&path)?;
+            if self.is_present(&contents, comment) {
+                continue;
+            }
+            let (body, shebang) = strip_shebang(&contents);
+            let header = self.render(comment);
+            atomic::write(&path, format!("{}{}\n\n{}", shebang, header, body))?;
+            changed.push(path);
+        }
+        Ok(changed)
+    }
+}
+
+/// Source files under `src/` with a known comment style, sorted by path
+fn source_files(project_path: &Path) -> Vec<(PathBuf, &'static str)> {
+    WalkDir::new(project_path.join("src"))
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            let extension = e.path().extension()?.to_str()?.to_string();
+            COMMENT_STYLES
+                .iter()
+                .find(|(ext, _)| *ext == extension)
+                .map(|(_, comment)| (e.into_path(), *comment))
+        })
+        .collect()
+}
+
+/// Every line of `text` as a line comment
+fn commented(text: &str, comment: &str) -> String {
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                comment.to_string()
+            } else {
+                format!("{} {}", comment, line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Split a leading `#!` line off `contents`, returning the rest and the line
+fn strip_shebang(contents: &str) -> (&str, &str) {
+    // `#![...]` is a Rust inner attribute, not a shebang
+    if contents.starts_with("#!") && !contents.starts_with("#![") {
+        let end = contents.find('\n').map_or(contents.len(), |i| i + 1);
+        (&contents[end..], &contents[..end])
+    } else {
+        (contents, "")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LicenseConfig;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_apply_and_detect_headers() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path();
+        let src = project.join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(src.join("run.sh"), "#!/bin/sh\necho hi\n").unwrap();
+        std::fs::write(
+            src.join("lib.rs"),
+            "// Copyright 2019-2023 Ada\n//\n// SPDX-License-Identifier: MIT\n\npub fn f() {}\n",
+        )
+        .unwrap();
+
+        let config = ProjectConfig {
+            authors: vec!["Ada".to_string()],
+            license: LicenseConfig {
+                header: Some("Copyright {year} {author}\n\nSPDX-License-Identifier: MIT\n".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let header = LicenseHeader::from_config(project, &config)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            header.missing(project).unwrap(),
+            vec![src.join("main.rs"), src.join("run.sh")]
+        );
+
+        assert_eq!(header.apply(project).unwrap().len(), 2);
+        assert!(header.missing(project).unwrap().is_empty());
+        let script = std::fs::read_to_string(src.join("run.sh")).unwrap();
+        assert!(script.starts_with("#!/bin/sh\n# Copyright "));
+        assert!(script.ends_with("# SPDX-License-Identifier: MIT\n\necho hi\n"));
+    }
+}
@@ -0,0 +1,216 @@
+//! Linting module
+//!
+//! This module runs `cargo clippy` and `cargo fmt --check` with the lint
+//! levels configured in forgekit.toml and merges their output with the
+//! project validator into a single diagnostics report.
+
+use crate::config::{LintConfig, ProjectConfig};
+use crate::diagnostics::{Diagnostic, DiagnosticReport, Severity};
+use crate::error::ForgeKitError;
+use crate::validator::ProjectValidator;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// Project linter
+pub struct Linter;
+
+impl Linter {
+    /// Lint a project
+    ///
+    /// With `fix` set, clippy suggestions are applied and files are
+    /// formatted instead of only being checked.
+    pub async fn run(project_path: &Path, fix: bool) -> Result<DiagnosticReport, ForgeKitError> {
+        let config = load_config(project_path)?;
+
+        let mut report = DiagnosticReport::default();
+
+        if config.clippy {
+            let output = Command::new("cargo")
+                .args(Self::clippy_args(&config, fix))
+                .current_dir(project_path)
+                .output()
+                .await?;
+            report
+                .diagnostics
+                .extend(parse_clippy_output(&String::from_utf8_lossy(
+                    &output.stdout,
+                )));
+        }
+
+        if config.rustfmt {
+            let args: &[&str] = if fix {
+                &["fmt"]
+            } else {
+                &["fmt", "--", "--check", "--files-with-diff"]
+            };
+            let output = Command::new("cargo")
+                .args(args)
+                .current_dir(project_path)
+                .output()
+                .await?;
+            report
+                .diagnostics
+                .extend(parse_fmt_output(&String::from_utf8_lossy(&output.stdout)));
+        }
+
+        let validation = ProjectValidator::validate_project(project_path).await?;
+        report.diagnostics.extend(validation.diagnostics());
+
+        Ok(report)
+    }
+
+    /// Lowest severity that fails the configured project
+    pub fn fail_on(project_path: &Path) -> Result<Severity, ForgeKitError> {
+        let config = load_config(project_path)?;
+
+        Severity::parse(&config.fail_on).ok_or_else(|| {
+            ForgeKitError::InvalidConfig(format!("unknown lint.fail_on '{}'", config.fail_on))
+        })
+    }
+
+    /// Build the clippy command line for the configured lint levels
+    fn clippy_args(config: &LintConfig, fix: bool) -> Vec<String> {
+        let mut args = vec!["clippy".to_string(), "--message-format=json".to_string()];
+        if fix {
+            args.extend(["--fix", "--allow-dirty", "--allow-staged"].map(String::from));
+        }
+
+        args.push("--".to_string());
+        for (lint, level) in &config.levels {
+            let flag = match level.as_str() {
+                "allow" => "-A",
+                "deny" => "-D",
+                "forbid" => "-F",
+                _ => "-W",
+            };
+            args.push(flag.to_string());
+            args.push(lint.clone());
+        }
+        args
+    }
+}
+
+/// Lint settings of a project, or the defaults for plain Cargo projects
+fn load_config(project_path: &Path) -> Result<LintConfig, ForgeKitError> {
+    let config_path = project_path.join("forgekit.toml");
+    if config_path.exists() {
+        Ok(ProjectConfig::load(&config_path)?.lint)
+    } else {
+        Ok(LintConfig::default())
+    }
+}
+
+/// Parse `cargo clippy --message-format=json` output
+pub fn parse_clippy_output(output: &str) -> Vec<Diagnostic> {
+    output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .filter(|msg| msg["reason"] == "compiler-message")
+        .filter_map(|msg| {
+            let message = &msg["message"];
+            let severity = Severity::parse(message["level"].as_str()?)?;
+            let text = message["message"].as_str()?.to_string();
+            // Skip the trailing "N warnings emitted" summaries
+            if message["spans"].as_array().is_some_and(|s| s.is_empty()) {
+                return None;
+            }
+
+            let span = message["spans"]
+                .as_array()
+                .and_then(|spans| spans.iter().find(|s| s["is_primary"] == true));
+
+            // The first help is the fix, with the replacement clippy suggests
+            let fix = message["children"].as_array().and_then(|children| {
+                let help = children.iter().find(|c| c["level"] == "help")?;
+                let mut fix = help["message"].as_str()?.to_string();
+                let replacement = help["spans"].as_array().and_then(|spans| {
+                    spans
+                        .iter()
+                        .find_map(|s| s["suggested_replacement"].as_str())
+                });
+                if let Some(replacement) = replacement {
+                    fix.push_str(&format!(": `{}`", replacement));
+                }
+                Some(fix)
+            });
+
+            Some(Diagnostic {
+                source: "clippy".to_string(),
+                severity,
+                code: message["code"]["code"].as_str().map(String::from),
+                file: span
+                    .and_then(|s| s["file_name"].as_str())
+                    .map(PathBuf::from),
+                line: span
+                    .and_then(|s| s["line_start"].as_u64())
+                    .map(|l| l as u32),
+                column: span
+                    .and_then(|s| s["column_start"].as_u64())
+                    .map(|c| c as u32),
+                message: text,
+                fix,
+            })
+        })
+        .collect()
+}
+
+/// Parse the file list printed by `rustfmt --check --files-with-diff`
+pub fn parse_fmt_output(output: &str) -> Vec<Diagnostic> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.ends_with(".rs"))
+        .map(|file| Diagnostic {
+            file: Some(PathBuf::from(file)),
+            fix: Some("run `forgekit lint --fix`".to_string()),
+            ..Diagnostic::new("rustfmt", Severity::Warning, "file is not formatted")
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_clippy_output() {
+        let output = r#"{"reason":"compiler-artifact","target":{}}
+{"reason":"compiler-message","message":{"level":"warning","message":"length comparison to zero","code":{"code":"clippy::len_zero"},"spans":[{"file_name":"src/main.rs","line_start":12,"column_start":8,"is_primary":true}],"children":[{"level":"help","message":"using `is_empty` is clearer","spans":[{"suggested_replacement":"v.is_empty()"}]}]}}
+{"reason":"compiler-message","message":{"level":"warning","message":"1 warning emitted","code":null,"spans":[]}}"#;
+
+        let diagnostics = parse_clippy_output(output);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code.as_deref(), Some("clippy::len_zero"));
+        assert_eq!(diagnostics[0].line, Some(12));
+        assert_eq!(diagnostics[0].column, Some(8));
+        assert_eq!(
+            diagnostics[0].fix.as_deref(),
+            Some("using `is_empty` is clearer: `v.is_empty()`")
+        );
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn test_report_fails_on_severity() {
+        let mut report = DiagnosticReport::default();
+        report
+            .diagnostics
+            .extend(parse_fmt_output("/p/src/main.rs\n/p/src/lib.rs\n"));
+
+        assert_eq!(report.count(Severity::Warning), 2);
+        assert!(report.fails(Severity::Warning));
+        assert!(!report.fails(Severity::Error));
+    }
+
+    #[test]
+    fn test_clippy_args_use_configured_levels() {
+        let mut config = LintConfig::default();
+        config
+            .levels
+            .insert("clippy::unwrap_used".to_string(), "deny".to_string());
+
+        let args = Linter::clippy_args(&config, true);
+        assert!(args.contains(&"--fix".to_string()));
+        assert!(args.ends_with(&["-D".to_string(), "clippy::unwrap_used".to_string()]));
+    }
+}
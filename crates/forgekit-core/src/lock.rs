@@ -0,0 +1,184 @@
+//! Lock module
+//!
+//! This module keeps concurrent forgekit invocations from corrupting each
+//! other's files. Builds and dependency operations take an advisory lock on
+//! `.forgekit/lock` in the project, and cache writes one next to the cache
+//! directory. A second invocation waits for the lock up to a timeout and
+//! then fails naming the holder; `--no-lock` skips locking for setups where
+//! it is known to be safe.
+
+use crate::error::ForgeKitError;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How long to wait for another invocation by default
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Interval between attempts to take a held lock
+const RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How locks are taken
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockOptions {
+    /// Whether locks are taken at all
+    pub enabled: bool,
+    /// How long to wait for a lock held by another invocation
+    pub timeout: Duration,
+}
+
+impl Default for LockOptions {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            timeout: DEFAULT_LOCK_TIMEOUT,
+        }
+    }
+}
+
+impl LockOptions {
+    /// Options under which no locks are taken
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            ..Self::default()
+        }
+    }
+}
+
+/// Lock file of a project
+pub fn project_lock_path(project_root: &Path) -> PathBuf {
+    project_root.join(".forgekit").join("lock")
+}
+
+/// Lock file guarding a cache directory
+pub fn cache_lock_path(cache_dir: &Path) -> PathBuf {
+    let mut name = cache_dir.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    cache_dir.with_file_name(name)
+}
+
+/// An advisory lock, released when dropped
+#[derive(Debug)]
+pub struct FileLock {
+    file: Option<File>,
+}
+
+impl FileLock {
+    /// Lock a project for an operation such as `build` or `add`
+    pub async fn project(
+        project_root: &Path,
+        options: &LockOptions,
+        operation: &str,
+    ) -> Result<Self, ForgeKitError> {
+        Self::acquire(&project_lock_path(project_root), options, operation).await
+    }
+
+    /// Lock `path`, waiting while another invocation holds it
+    ///
+    /// The lock file records the process and operation holding it so a
+    /// waiting invocation can say what it is waiting for.
+    pub async fn acquire(
+        path: &Path,
+        options: &LockOptions,
+        operation: &str,
+    ) -> Result<Self, ForgeKitError> {
+        if !options.enabled {
+            return Ok(Self { file: None });
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        let started = Instant::now();
+        let mut waiting = false;
+        loop {
+            match file.try_lock() {
+                Ok(()) => break,
+                Err(TryLockError::WouldBlock) => {}
+                Err(TryLockError::Error(e)) => return Err(e.into()),
+            }
+            if started.elapsed() >= options.timeout {
+                return Err(ForgeKitError::Locked(format!(
+                    "{} is held by {} (waited {}s); pass --no-lock to skip locking",
+                    path.display(),
+                    holder(path),
+                    options.timeout.as_secs()
+                )));
+            }
+            if !waiting {
+                waiting = true;
+                tracing::warn!("Waiting for {} to release {}", holder(path), path.display());
+            }
+            tokio::time::sleep(RETRY_INTERVAL).await;
+        }
+
+        file.set_len(0)?;
+        writeln!(file, "{} {}", std::process::id(), operation)?;
+        Ok(Self { file: Some(file) })
+    }
+
+    /// Whether a lock is actually held
+    pub fn is_held(&self) -> bool {
+        self.file.is_some()
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        if let Some(file) = &self.file {
+            let _ = file.set_len(0);
+            let _ = file.unlock();
+        }
+    }
+}
+
+/// Description of the process holding a lock file
+fn holder(path: &Path) -> String {
+    let contents = std::fs::read_to_string(path).unwrap_or_default();
+    match contents.trim().split_once(' ') {
+        Some((pid, operation)) => format!("forgekit {} (pid {})", operation, pid),
+        None => "another forgekit invocation".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_lock_waits_and_times_out() {
+        let temp_dir = TempDir::new().unwrap();
+        let options = LockOptions {
+            timeout: Duration::from_millis(200),
+            ..Default::default()
+        };
+
+        let held = FileLock::project(temp_dir.path(), &options, "build")
+            .await
+            .unwrap();
+        assert!(held.is_held());
+        let err = FileLock::project(temp_dir.path(), &options, "add")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("forgekit build (pid"));
+
+        let unlocked = FileLock::project(temp_dir.path(), &LockOptions::disabled(), "add")
+            .await
+            .unwrap();
+        assert!(!unlocked.is_held());
+
+        drop(held);
+        FileLock::project(temp_dir.path(), &options, "add")
+            .await
+            .unwrap();
+    }
+}
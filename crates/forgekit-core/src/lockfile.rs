@@ -0,0 +1,130 @@
+//! Lockfile handling
+//!
+//! The lockfile (`forgekit.lock`) records the exact version and checksum of
+//! every installed dependency so builds can be reproduced.
+
+use crate::atomic;
+use crate::error::ForgeKitError;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Name of the lockfile in the project root
+pub const LOCKFILE_NAME: &str = "forgekit.lock";
+
+/// Current lockfile format version
+const LOCKFILE_VERSION: u32 = 1;
+
+/// Project lockfile
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Lockfile {
+    /// Lockfile format version
+    pub version: u32,
+    /// Locked packages, sorted by name
+    #[serde(default, rename = "package")]
+    pub packages: Vec<LockedPackage>,
+}
+
+/// A dependency pinned in the lockfile
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedPackage {
+    /// Package name
+    pub name: String,
+    /// Exact version
+    pub version: String,
+    /// Where the package comes from
+    pub source: Option<String>,
+    /// SHA-256 checksum of the package archive
+    pub checksum: Option<String>,
+}
+
+impl Default for Lockfile {
+    fn default() -> Self {
+        Self {
+            version: LOCKFILE_VERSION,
+            packages: Vec::new(),
+        }
+    }
+}
+
+impl Lockfile {
+    /// Load the lockfile of a project, returning an empty one if it does not exist
+    pub fn load(project_root: &Path) -> Result<Self, ForgeKitError> {
+        let path = project_root.join(LOCKFILE_NAME);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&contents)?)
+    }
+
+    /// Save the lockfile into a project
+    pub fn save(&self, project_root: &Path) -> Result<(), ForgeKitError> {
+        let header = "# This file is generated by ForgeKit. Do not edit it by hand.\n";
+        let contents = toml::to_string_pretty(self)?;
+        atomic::write(
+            project_root.join(LOCKFILE_NAME),
+            format!("{}{}", header, contents),
+        )?;
+        Ok(())
+    }
+
+    /// Find a locked package by name
+    pub fn get(&self, name: &str) -> Option<&LockedPackage> {
+        self.packages.iter().find(|p| p.name == name)
+    }
+
+    /// Insert or replace a locked package
+    pub fn upsert(&mut self, package: LockedPackage) {
+        self.packages.retain(|p| p.name != package.name);
+        self.packages.push(package);
+        self.packages.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+
+    /// Remove a package, returning whether it was locked
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.packages.len();
+        self.packages.retain(|p| p.name != name);
+        self.packages.len() != before
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn locked(name: &str, version: &str) -> LockedPackage {
+        LockedPackage {
+            name: name.to_string(),
+            version: version.to_string(),
+            source: Some("registry".to_string()),
+            checksum: Some("abc".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_upsert_replaces_and_sorts() {
+        let mut lockfile = Lockfile::default();
+        lockfile.upsert(locked("forgekit-tokio", "0.1.0"));
+        lockfile.upsert(locked("forgekit-http", "0.1.0"));
+        lockfile.upsert(locked("forgekit-tokio", "0.2.0"));
+
+        assert_eq!(lockfile.packages.len(), 2);
+        assert_eq!(lockfile.packages[0].name, "forgekit-http");
+        assert_eq!(lockfile.get("forgekit-tokio").unwrap().version, "0.2.0");
+        assert!(lockfile.remove("forgekit-http"));
+        assert!(!lockfile.remove("forgekit-http"));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut lockfile = Lockfile::default();
+        lockfile.upsert(locked("forgekit-http", "0.1.0"));
+        lockfile.save(temp_dir.path()).unwrap();
+
+        let loaded = Lockfile::load(temp_dir.path()).unwrap();
+        assert_eq!(loaded.packages, lockfile.packages);
+    }
+}
@@ -0,0 +1,521 @@
+//! Logs module
+//!
+//! This module routes the output of an app launched with `forgekit run`.
+//! Every stdout and stderr line is written to a rotating log file under
+//! `.forgekit/logs/` and echoed to the terminal when it passes a
+//! [`LogFilter`]: lines can be filtered by level, have regex matches
+//! highlighted and, for JSON log lines, be reduced to selected fields.
+//! `forgekit logs` reads the same files back, optionally following the app
+//! while it runs.
+
+use crate::cancel::CancellationToken;
+use crate::error::ForgeKitError;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Child;
+
+/// Name of the current log file; rotated files are `app.1.log`, `app.2.log`...
+pub const LOG_FILE: &str = "app.log";
+
+/// Size after which the log file is rotated
+const MAX_FILE_SIZE: u64 = 5 * 1024 * 1024;
+
+/// Number of rotated files kept besides the current one
+const MAX_ROTATED: usize = 4;
+
+/// Interval at which a followed log file is polled for new lines
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(250);
+
+/// ANSI escapes wrapped around highlighted matches
+const HIGHLIGHT: (&str, &str) = ("\x1b[1;33m", "\x1b[0m");
+
+/// Directory holding the logs of a project's app
+pub fn logs_dir(project_path: &Path) -> PathBuf {
+    project_path.join(".forgekit").join("logs")
+}
+
+/// Severity of a log line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    /// Level named by the first word of a plain text line, e.g. `[WARN]`
+    fn detect(text: &str) -> Option<Self> {
+        text.split_whitespace()
+            .take(3)
+            .find_map(|word| word.trim_matches(|c: char| !c.is_alphabetic()).parse().ok())
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = ForgeKitError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "trace" => Ok(Self::Trace),
+            "debug" => Ok(Self::Debug),
+            "info" => Ok(Self::Info),
+            "warn" | "warning" => Ok(Self::Warn),
+            "error" | "err" | "fatal" => Ok(Self::Error),
+            _ => Err(ForgeKitError::InvalidConfig(format!(
+                "Unknown log level '{}' (expected trace, debug, info, warn or error)",
+                s
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Trace => "trace",
+            Self::Debug => "debug",
+            Self::Info => "info",
+            Self::Warn => "warn",
+            Self::Error => "error",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Output stream a line was written to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+impl LogStream {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Stdout => "stdout",
+            Self::Stderr => "stderr",
+        }
+    }
+}
+
+/// One line written by the app
+#[derive(Debug, Clone, Serialize)]
+pub struct LogLine {
+    /// When the line was written (RFC 3339)
+    pub timestamp: String,
+    /// Stream the line was written to
+    pub stream: LogStream,
+    /// Line content
+    pub text: String,
+    /// Level read from the JSON `level` field or the first words
+    pub level: Option<LogLevel>,
+    /// Fields of a JSON log line
+    pub fields: BTreeMap<String, serde_json::Value>,
+}
+
+impl LogLine {
+    /// Parse a line just written by the app
+    pub fn new(stream: LogStream, text: &str) -> Self {
+        let fields: BTreeMap<String, serde_json::Value> = text
+            .trim_start()
+            .starts_with('{')
+            .then(|| serde_json::from_str(text).ok())
+            .flatten()
+            .unwrap_or_default();
+        let level = ["level", "lvl", "severity"]
+            .iter()
+            .find_map(|key| fields.get(*key)?.as_str()?.parse().ok())
+            .or_else(|| fields.is_empty().then(|| LogLevel::detect(text)).flatten());
+
+        Self {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            stream,
+            text: text.to_string(),
+            level,
+            fields,
+        }
+    }
+
+    /// Parse a line of a log file, as written by [`LogLine::record`]
+    pub fn parse_record(record: &str) -> Option<Self> {
+        let (timestamp, rest) = record.split_once(' ')?;
+        let (stream, text) = rest.split_once(' ').unwrap_or((rest, ""));
+        let stream = match stream {
+            "stdout" => LogStream::Stdout,
+            "stderr" => LogStream::Stderr,
+            _ => return None,
+        };
+        Some(Self {
+            timestamp: timestamp.to_string(),
+            ..Self::new(stream, text)
+        })
+    }
+
+    /// Line as stored in the log file
+    pub fn record(&self) -> String {
+        format!("{} {} {}", self.timestamp, self.stream.as_str(), self.text)
+    }
+}
+
+/// Which lines are shown and how
+#[derive(Debug, Clone, Default)]
+pub struct LogFilter {
+    /// Minimum level; lines without a level are always shown
+    pub level: Option<LogLevel>,
+    /// Pattern whose matches are highlighted
+    pub highlight: Option<Regex>,
+    /// JSON fields to show instead of the whole line
+    pub fields: Vec<String>,
+}
+
+impl LogFilter {
+    /// Whether a line passes the filter
+    pub fn matches(&self, line: &LogLine) -> bool {
+        match (self.level, line.level) {
+            (Some(min), Some(level)) => level >= min,
+            _ => true,
+        }
+    }
+
+    /// Text shown for a line, with ANSI highlighting when `color` is set
+    pub fn render(&self, line: &LogLine, color: bool) -> String {
+        let text = if self.fields.is_empty() || line.fields.is_empty() {
+            line.text.clone()
+        } else {
+            self.fields
+                .iter()
+                .filter_map(|name| {
+                    let value = line.fields.get(name)?;
+                    Some(match value {
+                        serde_json::Value::String(s) => format!("{}={}", name, s),
+                        other => format!("{}={}", name, other),
+                    })
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        match &self.highlight {
+            Some(pattern) if color => pattern
+                .replace_all(&text, |caps: &regex::Captures| {
+                    format!("{}{}{}", HIGHLIGHT.0, &caps[0], HIGHLIGHT.1)
+                })
+                .into_owned(),
+            _ => text,
+        }
+    }
+}
+
+/// Log file rotated by size
+pub struct RotatingLog {
+    dir: PathBuf,
+    max_size: u64,
+    max_rotated: usize,
+    file: std::fs::File,
+    size: u64,
+}
+
+impl RotatingLog {
+    /// Open the log file in a directory, appending to it
+    pub fn open(dir: &Path) -> Result<Self, ForgeKitError> {
+        Self::with_limits(dir, MAX_FILE_SIZE, MAX_ROTATED)
+    }
+
+    /// Open the log file with custom rotation limits
+    pub fn with_limits(
+        dir: &Path,
+        max_size: u64,
+        max_rotated: usize,
+    ) -> Result<Self, ForgeKitError> {
+        std::fs::create_dir_all(dir)?;
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(LOG_FILE))?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            max_size,
+            max_rotated,
+            file,
+            size,
+        })
+    }
+
+    /// Append a line, rotating the file first if it is full
+    pub fn write_line(&mut self, line: &str) -> Result<(), ForgeKitError> {
+        if self.size > 0 && self.size + line.len() as u64 + 1 > self.max_size {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.size += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    /// Shift `app.log` to `app.1.log`, `app.1.log` to `app.2.log`...
+    fn rotate(&mut self) -> Result<(), ForgeKitError> {
+        let rotated = |n: usize| self.dir.join(format!("app.{}.log", n));
+        let oldest = rotated(self.max_rotated);
+        if oldest.exists() {
+            std::fs::remove_file(oldest)?;
+        }
+        for n in (1..self.max_rotated).rev() {
+            if rotated(n).exists() {
+                std::fs::rename(rotated(n), rotated(n + 1))?;
+            }
+        }
+        let current = self.dir.join(LOG_FILE);
+        if self.max_rotated > 0 {
+            std::fs::rename(&current, rotated(1))?;
+        }
+        self.file = std::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(current)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+/// Routes the stdout and stderr of a running app
+pub struct LogRouter {
+    log: RotatingLog,
+    filter: LogFilter,
+    color: bool,
+    stdout_to_stderr: bool,
+}
+
+impl LogRouter {
+    /// Create a router writing to the project's log directory
+    pub fn new(project_path: &Path, filter: LogFilter) -> Result<Self, ForgeKitError> {
+        Ok(Self {
+            log: RotatingLog::open(&logs_dir(project_path))?,
+            filter,
+            color: false,
+            stdout_to_stderr: false,
+        })
+    }
+
+    /// Highlight matches with ANSI colors when echoing lines
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Echo lines written to stdout on stderr, keeping stdout for
+    /// machine-readable output
+    pub fn with_stdout_to_stderr(mut self, stdout_to_stderr: bool) -> Self {
+        self.stdout_to_stderr = stdout_to_stderr;
+        self
+    }
+
+    /// Route the piped output of a child until it exits
+    ///
+    /// Lines passing the filter are echoed to the stream they were written
+    /// to, unless stdout goes to stderr; all lines are stored in the log
+    /// file.
+    pub async fn route(&mut self, child: &mut Child) -> Result<ExitStatus, ForgeKitError> {
+        let mut stdout = child.stdout.take().map(lines);
+        let mut stderr = child.stderr.take().map(lines);
+
+        while stdout.is_some() || stderr.is_some() {
+            let (stream, next) = tokio::select! {
+                line = next_line(&mut stdout), if stdout.is_some() => (LogStream::Stdout, line),
+                line = next_line(&mut stderr), if stderr.is_some() => (LogStream::Stderr, line),
+            };
+            match next? {
+                Some(text) => self.handle(LogLine::new(stream, &text))?,
+                None if stream == LogStream::Stdout => stdout = None,
+                None => stderr = None,
+            }
+        }
+        Ok(child.wait().await?)
+    }
+
+    fn handle(&mut self, line: LogLine) -> Result<(), ForgeKitError> {
+        self.log.write_line(&line.record())?;
+        if self.filter.matches(&line) {
+            let text = self.filter.render(&line, self.color);
+            match line.stream {
+                LogStream::Stdout if !self.stdout_to_stderr => println!("{}", text),
+                _ => eprintln!("{}", text),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Lines of an app's output, invalid UTF-8 replaced rather than failing
+struct Lines {
+    reader: BufReader<Box<dyn AsyncRead + Unpin + Send>>,
+    buffer: Vec<u8>,
+}
+
+impl Lines {
+    /// Next line without its line ending, `None` at the end of the output
+    ///
+    /// Cancel safe: a partially read line stays buffered for the next call.
+    async fn next_line(&mut self) -> std::io::Result<Option<String>> {
+        self.reader.read_until(b'\n', &mut self.buffer).await?;
+        if self.buffer.is_empty() {
+            return Ok(None);
+        }
+        let mut line = std::mem::take(&mut self.buffer);
+        if line.last() == Some(&b'\n') {
+            line.pop();
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+        }
+        Ok(Some(String::from_utf8_lossy(&line).into_owned()))
+    }
+}
+
+fn lines(reader: impl AsyncRead + Unpin + Send + 'static) -> Lines {
+    let reader: Box<dyn AsyncRead + Unpin + Send> = Box::new(reader);
+    Lines {
+        reader: BufReader::new(reader),
+        buffer: Vec::new(),
+    }
+}
+
+async fn next_line(lines: &mut Option<Lines>) -> std::io::Result<Option<String>> {
+    match lines {
+        Some(lines) => lines.next_line().await,
+        None => Ok(None),
+    }
+}
+
+/// Stored lines of a project's app, oldest first
+///
+/// Includes the rotated files. With `follow`, keeps calling `on_line` for
+/// lines appended by a running app until `cancel` is triggered.
+pub async fn read(
+    project_path: &Path,
+    follow: bool,
+    cancel: &CancellationToken,
+    mut on_line: impl FnMut(LogLine),
+) -> Result<(), ForgeKitError> {
+    let dir = logs_dir(project_path);
+    let current = dir.join(LOG_FILE);
+    let mut offset = 0;
+    for path in (1..=MAX_ROTATED)
+        .rev()
+        .map(|n| dir.join(format!("app.{}.log", n)))
+        .chain([current.clone()])
+    {
+        let Ok(contents) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        contents
+            .lines()
+            .filter_map(LogLine::parse_record)
+            .for_each(&mut on_line);
+        if path == current {
+            offset = contents.len() as u64;
+        }
+    }
+
+    while follow && !cancel.is_cancelled() {
+        tokio::select! {
+            _ = tokio::time::sleep(FOLLOW_INTERVAL) => {}
+            _ = cancel.cancelled() => break,
+        }
+        let Ok(mut file) = std::fs::File::open(&current) else {
+            continue;
+        };
+        let len = file.metadata()?.len();
+        if len < offset {
+            // Rotated since the last poll
+            offset = 0;
+        }
+        if len == offset {
+            continue;
+        }
+        file.seek(SeekFrom::Start(offset))?;
+        let mut appended = String::new();
+        file.read_to_string(&mut appended)?;
+        // Leave a partially written last line for the next poll
+        let complete = appended.rfind('\n').map_or(0, |i| i + 1);
+        appended[..complete]
+            .lines()
+            .filter_map(LogLine::parse_record)
+            .for_each(&mut on_line);
+        offset += complete as u64;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_and_filter() {
+        let plain = LogLine::new(LogStream::Stderr, "[WARN] disk almost full");
+        assert_eq!(plain.level, Some(LogLevel::Warn));
+        let json = LogLine::new(LogStream::Stdout, r#"{"level":"debug","msg":"tick","n":3}"#);
+        assert_eq!(json.level, Some(LogLevel::Debug));
+        let unknown = LogLine::new(LogStream::Stdout, "hello");
+        assert_eq!(unknown.level, None);
+
+        let filter = LogFilter {
+            level: Some(LogLevel::Info),
+            highlight: Some(Regex::new("disk|tick").unwrap()),
+            fields: vec!["msg".to_string(), "n".to_string()],
+        };
+        assert!(filter.matches(&plain));
+        assert!(!filter.matches(&json));
+        assert!(filter.matches(&unknown));
+        assert_eq!(filter.render(&json, false), "msg=tick n=3");
+        assert_eq!(
+            filter.render(&plain, true),
+            "[WARN] \x1b[1;33mdisk\x1b[0m almost full"
+        );
+
+        let record = LogLine::parse_record(&plain.record()).unwrap();
+        assert_eq!(record.stream, LogStream::Stderr);
+        assert_eq!(record.text, plain.text);
+        assert_eq!(record.timestamp, plain.timestamp);
+    }
+
+    #[test]
+    fn test_rotation() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let mut log = RotatingLog::with_limits(dir, 10, 2).unwrap();
+        for line in ["first", "second", "third", "fourth"] {
+            log.write_line(line).unwrap();
+        }
+
+        let read = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap();
+        assert_eq!(read(LOG_FILE), "fourth\n");
+        assert_eq!(read("app.1.log"), "third\n");
+        assert_eq!(read("app.2.log"), "second\n");
+        assert!(!dir.join("app.3.log").exists());
+    }
+
+    #[tokio::test]
+    async fn test_lines_decode_invalid_utf8() {
+        let mut output = Some(lines(&b"ok\r\nbad \xff byte\nlast"[..]));
+        let mut read = Vec::new();
+        while let Some(line) = next_line(&mut output).await.unwrap() {
+            read.push(line);
+        }
+        assert_eq!(read, ["ok", "bad \u{fffd} byte", "last"]);
+    }
+}
@@ -0,0 +1,1058 @@
+//! Manifest diagnostics module
+//!
+//! This module checks forgekit.toml the way a language server would: it
+//! reports syntax and schema errors, unknown keys, invalid version
+//! requirements and dependencies that cannot be resolved, each with a
+//! line/character range, and answers hover requests with the documentation
+//! of the key under the cursor. The daemon serves these results to editors
+//! as the user types.
+
+use crate::config::ProjectConfig;
+use crate::registry::RegistryClient;
+use crate::version_manager::VersionReq;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::ops::Range as Span;
+use std::path::{Path, PathBuf};
+use toml_edit::{ImDocument, Item, TableLike};
+
+/// Documented keys of forgekit.toml
+///
+/// `[]` marks the elements of an array of tables and `*` any key of a map.
+pub const SCHEMA: &[(&str, &str)] = &[
+    (
+        "extends",
+        "Base forgekit.toml this file builds on, relative to this file",
+    ),
+    (
+        "include",
+        "Configuration fragments merged in after `extends`, in order",
+    ),
+    ("name", "Project name, used for the .mox package"),
+    ("version", "Project version (semver, e.g. `1.2.0`)"),
+    (
+        "kind",
+        "`app` (packaged as .mox) or `library` (packaged as .moxlib)",
+    ),
+    ("description", "Short description shown in the app store"),
+    (
+        "release_notes",
+        "What's new in this version; packaging takes the version's CHANGELOG section when unset",
+    ),
+    ("authors", "Authors, as `Name <email>` strings"),
+    (
+        "dependencies",
+        "Dependencies, one `[[dependencies]]` table each",
+    ),
+    ("dependencies[].name", "Package name in the registry"),
+    (
+        "dependencies[].version",
+        "Version requirement, e.g. `1.2`, `^1.2`, `~1.2.3` or `>=1, <2`",
+    ),
+    (
+        "dependencies[].source",
+        "Where the package comes from when not from the registry: a path or URL",
+    ),
+    (
+        "dependencies[].removed",
+        "`true` drops the dependency of this name inherited through `extends` or `include`",
+    ),
+    ("build", "Build settings"),
+    ("build.target", "Target architecture"),
+    (
+        "build.opt_level",
+        "Optimization level (`0`-`3`, `s` or `z`)",
+    ),
+    ("build.rustflags", "Additional flags passed to rustc"),
+    ("build.features", "Cargo features enabled for the build"),
+    ("build.output_dir", "Directory receiving build output"),
+    (
+        "build.sandbox",
+        "Sandbox for cargo and dependency build scripts",
+    ),
+    (
+        "build.sandbox.mode",
+        "Isolation: `none`, `env`, `namespace` (bubblewrap) or `docker`",
+    ),
+    (
+        "build.sandbox.allow_env",
+        "Extra environment variables passed into the sandbox",
+    ),
+    (
+        "build.sandbox.network",
+        "Whether the sandbox may use the network (default `true`)",
+    ),
+    (
+        "build.sandbox.image",
+        "Docker image used by the `docker` mode; pin a version tag or digest",
+    ),
+    ("build.symbols", "Debug symbols split from release binaries"),
+    (
+        "build.symbols.split",
+        "Move debug info to `.forgekit/symbols/<version>/` (default `true`)",
+    ),
+    (
+        "build.symbols.server",
+        "Symbol server used by `forgekit symbols upload` and crash symbolication",
+    ),
+    ("build.artifacts", "History of packaged .mox files"),
+    (
+        "build.artifacts.keep",
+        "Packages kept per profile under `.forgekit/artifacts/` (default `5`, `0` keeps none)",
+    ),
+    (
+        "build.toolchain",
+        "Toolchain and target spec used for the ledokoz target",
+    ),
+    (
+        "build.toolchain.channel",
+        "Rust toolchain building the ledokoz target (default `nightly`, replaced by `[toolchain] channel`)",
+    ),
+    (
+        "build.toolchain.target_spec",
+        "Target spec JSON replacing the one shipped with ForgeKit",
+    ),
+    (
+        "build.toolchain.fallback_target",
+        "Triple built by `forgekit build --host`; the host's own when unset",
+    ),
+    (
+        "build.profile",
+        "Linker and code generation settings of the release profile; cargo's defaults when unset",
+    ),
+    ("build.profile.linker", "`lld` or `mold`, replacing the system linker"),
+    ("build.profile.lto", "Link-time optimization: `off`, `thin` or `fat`"),
+    (
+        "build.profile.codegen_units",
+        "Code generation units per crate; fewer optimize better but compile slower",
+    ),
+    ("build.profile.panic", "`unwind` or `abort` on panic"),
+    (
+        "build.profile.strip",
+        "Strip `none`, `debuginfo` or `symbols` from the binary",
+    ),
+    (
+        "toolchain",
+        "Rust toolchain pinned for everyone building the project",
+    ),
+    (
+        "toolchain.channel",
+        "Toolchain builds must use, like `1.78`, `stable` or `nightly-2024-05-01`",
+    ),
+    (
+        "toolchain.rust_toolchain_file",
+        "Keep rust-toolchain.toml in line with the pinned channel when building",
+    ),
+    ("hooks", "Commands run around build, packaging and install"),
+    ("hooks.pre_build", "Commands run before `cargo build`"),
+    ("hooks.post_build", "Commands run after a successful build"),
+    (
+        "hooks.pre_package",
+        "Commands run before the .mox archive is created",
+    ),
+    (
+        "hooks.post_package",
+        "Commands run after the .mox archive is created",
+    ),
+    (
+        "hooks.pre_install",
+        "Commands run from the extracted app before `forgekit install` installs it",
+    ),
+    (
+        "hooks.post_install",
+        "Commands run from the app directory after `forgekit install`",
+    ),
+    (
+        "scripts",
+        "Project scripts, exposed as justfile or Makefile recipes by `forgekit sync-scripts`",
+    ),
+    ("scripts.*", "Shell command the recipe of this name runs"),
+    (
+        "codegen",
+        "Code generators run before build, one `[[codegen]]` table each",
+    ),
+    (
+        "codegen[].kind",
+        "Generator kind: `protobuf`, `flatbuffers` or `custom`",
+    ),
+    ("codegen[].schema_dir", "Directory containing schema files"),
+    (
+        "codegen[].output_dir",
+        "Directory receiving generated sources",
+    ),
+    ("codegen[].command", "Command for custom generators"),
+    ("lint", "Settings for `forgekit lint`"),
+    ("lint.levels", "Clippy lint levels keyed by lint name"),
+    ("lint.levels.*", "Lint level: `allow`, `warn` or `deny`"),
+    (
+        "lint.fail_on",
+        "Lowest severity that fails the lint run: `error` or `warning`",
+    ),
+    ("lint.rustfmt", "Run `cargo fmt --check`"),
+    ("lint.clippy", "Run `cargo clippy`"),
+    (
+        "license",
+        "License header every source file under src/ must start with",
+    ),
+    (
+        "license.header",
+        "Header text, with `{year}`, `{author}` and `{name}` filled in",
+    ),
+    (
+        "license.header_file",
+        "File holding the header text, relative to the project",
+    ),
+    (
+        "license.author",
+        "Copyright holder, defaulting to the project authors",
+    ),
+    ("test", "Settings for `forgekit test`"),
+    (
+        "test.retries",
+        "Times a failed test is rerun before it counts as failed",
+    ),
+    (
+        "test.quarantine",
+        "Flaky tests whose failures are reported without failing the run",
+    ),
+    ("ci", "Build matrix of the pipelines generated by `forgekit ci`"),
+    (
+        "ci.targets",
+        "Target triples to build for, e.g. `aarch64-apple-darwin`; the runner's own when empty",
+    ),
+    (
+        "ci.profiles",
+        "Environment profiles to build with; every `[env.<name>]` when empty",
+    ),
+    (
+        "ci.feature_sets",
+        "Sets of cargo features to build with, e.g. `[[], [\"telemetry\"]]`",
+    ),
+    (
+        "git_hooks",
+        "Git hooks installed by `forgekit hooks install`",
+    ),
+    (
+        "git_hooks.pre_commit",
+        "Stages run by the `pre-commit` hook",
+    ),
+    ("git_hooks.pre_push", "Stages run by the `pre-push` hook"),
+    (
+        "appmeta",
+        "Launcher metadata generated by `forgekit appmeta`",
+    ),
+    (
+        "appmeta.icon",
+        "Source icon, a square PNG of at least 512x512",
+    ),
+    ("appmeta.display_name", "Name shown in the launcher"),
+    ("appmeta.categories", "Launcher categories"),
+    (
+        "appmeta.splash_background",
+        "Splash screen background as `#rrggbb`",
+    ),
+    (
+        "appstore",
+        "Store listing bundled next to the .mox by `forgekit appstore`",
+    ),
+    (
+        "appstore.category",
+        "Store category, like `productivity` or `games`",
+    ),
+    (
+        "appstore.age_rating",
+        "Age rating: `everyone`, `7+`, `12+`, `16+` or `18+`",
+    ),
+    (
+        "appstore.screenshots",
+        "Screenshots (PNG) in display order, relative to the project",
+    ),
+    (
+        "appstore.default_locale",
+        "Locale the listing falls back to (default `en`)",
+    ),
+    ("appstore.privacy_policy", "URL of the privacy policy"),
+    (
+        "appstore.privacy",
+        "Why each requested permission is needed, keyed by permission",
+    ),
+    (
+        "appstore.privacy.*",
+        "Purpose shown for the permission, e.g. `camera = \"Scanning receipts\"`",
+    ),
+    ("ota", "Over-the-air update channels and the feed devices poll"),
+    (
+        "ota.channels",
+        "Channels the app publishes to, like `[\"stable\", \"beta\", \"nightly\"]`",
+    ),
+    (
+        "ota.channel",
+        "Channel packages are built for, defaulting to the first of `channels`",
+    ),
+    ("ota.feed_url", "URL of the update feed devices poll"),
+    (
+        "ota.signing_key",
+        "Ed25519 key (PKCS#8) signing the feed, defaulting to `.forgekit/ota.key`; `secret:<name>` keeps it in the credential store",
+    ),
+    (
+        "permissions",
+        "Permissions requested from Ledokoz OS at install time; anything else is denied",
+    ),
+    ("permissions.network", "Network access"),
+    (
+        "permissions.filesystem",
+        "Filesystem paths outside the app's data directory",
+    ),
+    ("permissions.camera", "Camera access"),
+    ("permissions.microphone", "Microphone access"),
+    ("permissions.location", "Location access"),
+    ("permissions.bluetooth", "Bluetooth access"),
+    (
+        "permissions.notifications",
+        "Permission to show notifications",
+    ),
+    ("redact", "Variables whose values are masked in output"),
+    ("audit_log", "Audit trail of commands that publish, install or change credentials"),
+    (
+        "audit_log.sink",
+        "Where entries go: `file` (`.forgekit/audit.log`), `syslog` or `off`",
+    ),
+    (
+        "redact.sensitive",
+        "Variable names, or patterns like `STRIPE_*`, treated as secrets besides the built-in ones",
+    ),
+    ("services", "Services the app runs, keyed by name"),
+    ("services.*", "Service listening on a local port"),
+    ("services.*.port", "Port the service listens on"),
+    (
+        "services.*.env",
+        "Environment variable the port is read from (default `<NAME>_PORT`)",
+    ),
+    ("dev", "Settings for `forgekit dev`"),
+    (
+        "dev.port",
+        "Port of the project's dev server when a workspace is served",
+    ),
+    (
+        "dev.waits_for",
+        "Workspace members whose rebuild the project's reload waits for",
+    ),
+    (
+        "template",
+        "Template the project was generated from, for `forgekit template update`",
+    ),
+    ("template.name", "Template name, like `gui` or `service`"),
+    (
+        "template.version",
+        "Revision of the template the project's files were last updated to",
+    ),
+    (
+        "plugin",
+        "Marks a library as a ForgeKit plugin, listed by `forgekit plugin search`",
+    ),
+    (
+        "plugin.api_version",
+        "Plugin API version the plugin is built against",
+    ),
+    ("plugins", "How the plugins loaded for the project are run"),
+    (
+        "plugins.timeouts",
+        "Seconds each plugin hook may run before the step fails",
+    ),
+    ("plugins.timeouts.pre_build", "Timeout of pre-build hooks"),
+    ("plugins.timeouts.post_build", "Timeout of post-build hooks"),
+    ("plugins.timeouts.package", "Timeout of package hooks"),
+    (
+        "patch",
+        "Overrides for every version of a dependency, keyed by package name",
+    ),
+    (
+        "patch.*",
+        "Replacement source: `git` (with `branch` or `rev`) or `path`",
+    ),
+    ("patch.*.git", "Git repository URL"),
+    ("patch.*.branch", "Git branch to check out"),
+    ("patch.*.rev", "Git commit to check out"),
+    ("patch.*.path", "Local directory, relative to the project"),
+    (
+        "replace",
+        "Overrides for one exact dependency version, keyed by `name@version`",
+    ),
+    (
+        "replace.*",
+        "Replacement source: `git` (with `branch` or `rev`) or `path`",
+    ),
+    ("replace.*.git", "Git repository URL"),
+    ("replace.*.branch", "Git branch to check out"),
+    ("replace.*.rev", "Git commit to check out"),
+    ("replace.*.path", "Local directory, relative to the project"),
+    (
+        "env",
+        "Overrides for environment profiles, one `[env.<name>]` table each",
+    ),
+    (
+        "env.*",
+        "Keys of forgekit.toml overridden when building with `--env <name>`",
+    ),
+];
+
+/// Diagnostic severity, serialized as the LSP numeric value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticSeverity {
+    /// Prevents the manifest from loading or building
+    Error = 1,
+    /// Likely mistake that does not stop the build
+    Warning = 2,
+}
+
+impl Serialize for DiagnosticSeverity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(*self as u8)
+    }
+}
+
+/// Zero-based position; `character` counts UTF-16 code units as in LSP
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Position {
+    /// Line number
+    pub line: u32,
+    /// Column in UTF-16 code units
+    pub character: u32,
+}
+
+/// Range between two positions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Range {
+    /// Start position
+    pub start: Position,
+    /// End position (exclusive)
+    pub end: Position,
+}
+
+/// A problem found in forgekit.toml
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestDiagnostic {
+    /// Location of the problem
+    pub range: Range,
+    /// Severity
+    pub severity: DiagnosticSeverity,
+    /// Stable diagnostic code, e.g. `unknown-key`
+    pub code: String,
+    /// Tool that produced the diagnostic
+    pub source: String,
+    /// Human readable message
+    pub message: String,
+}
+
+/// Parameters of an LSP `textDocument/publishDiagnostics` notification
+#[derive(Debug, Clone, Serialize)]
+pub struct PublishDiagnostics {
+    /// Document URI
+    pub uri: String,
+    /// Diagnostics for the whole document
+    pub diagnostics: Vec<ManifestDiagnostic>,
+}
+
+/// Documentation for the key under the cursor
+#[derive(Debug, Clone, Serialize)]
+pub struct Hover {
+    /// Markdown documentation
+    pub contents: String,
+    /// Range of the key
+    pub range: Range,
+}
+
+/// A key found in the manifest with its schema path
+struct KeyEntry {
+    path: String,
+    name: String,
+    span: Span<usize>,
+}
+
+/// forgekit.toml analyzer
+pub struct ManifestAnalyzer {
+    project_path: PathBuf,
+    registry: Option<RegistryClient>,
+}
+
+impl ManifestAnalyzer {
+    /// Create an analyzer for a project
+    pub fn new(project_path: &Path) -> Self {
+        Self {
+            project_path: project_path.to_path_buf(),
+            registry: None,
+        }
+    }
+
+    /// Check dependencies against the registry's local index
+    pub fn with_registry(mut self, registry: RegistryClient) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Diagnostics for the given contents, wrapped for publishing
+    pub fn publish(&self, text: &str) -> PublishDiagnostics {
+        PublishDiagnostics {
+            uri: format!(
+                "file://{}",
+                self.project_path.join("forgekit.toml").display()
+            ),
+            diagnostics: self.analyze(text),
+        }
+    }
+
+    /// Check the contents of forgekit.toml
+    pub fn analyze(&self, text: &str) -> Vec<ManifestDiagnostic> {
+        let document = match ImDocument::parse(text) {
+            Ok(document) => document,
+            Err(e) => {
+                return vec![diagnostic(
+                    text,
+                    e.span().unwrap_or(0..0),
+                    DiagnosticSeverity::Error,
+                    "syntax",
+                    e.message().to_string(),
+                )]
+            }
+        };
+
+        let mut diagnostics = Vec::new();
+
+        // Missing fields and wrong types, as reported when loading the config;
+        // a layered file may inherit required fields, so it is not checked alone
+        let root = document.as_table();
+        let layered = root.contains_key("extends") || root.contains_key("include");
+        if let Some(e) = toml::from_str::<ProjectConfig>(text)
+            .err()
+            .filter(|_| !layered)
+        {
+            diagnostics.push(diagnostic(
+                text,
+                e.span().unwrap_or(0..0),
+                DiagnosticSeverity::Error,
+                "schema",
+                e.message().to_string(),
+            ));
+        }
+
+        for (key, suggestion) in unknown_key_entries(document.as_table()) {
+            let mut message = format!("unknown key '{}' is ignored", key.name);
+            if let Some(suggestion) = suggestion {
+                message.push_str(&format!("; did you mean '{}'?", suggestion));
+            }
+            diagnostics.push(diagnostic(
+                text,
+                key.span,
+                DiagnosticSeverity::Warning,
+                "unknown-key",
+                message,
+            ));
+        }
+
+        if let Some(dependencies) = document.as_table().get("dependencies") {
+            for dependency in table_elements(dependencies) {
+                self.check_dependency(text, dependency, &mut diagnostics);
+            }
+        }
+
+        diagnostics
+    }
+
+    /// Documentation for the key at a position
+    pub fn hover(&self, text: &str, position: Position) -> Option<Hover> {
+        let document = ImDocument::parse(text).ok()?;
+        let offset = offset(text, position);
+
+        keys(document.as_table())
+            .into_iter()
+            .find(|key| key.span.start <= offset && offset <= key.span.end)
+            .and_then(|key| {
+                let doc = schema_doc(&key.path)?;
+                Some(Hover {
+                    contents: format!("**{}**\n\n{}", key.name, doc),
+                    range: range(text, key.span),
+                })
+            })
+    }
+
+    fn check_dependency(
+        &self,
+        text: &str,
+        dependency: &dyn TableLike,
+        diagnostics: &mut Vec<ManifestDiagnostic>,
+    ) {
+        let Some(name) = dependency.get("name").and_then(Item::as_str) else {
+            return;
+        };
+        let Some(version) = dependency.get("version") else {
+            return;
+        };
+        let span = version.span().unwrap_or(0..0);
+
+        let Some(req) = version.as_str() else {
+            return;
+        };
+        let req = match VersionReq::parse(req) {
+            Ok(req) => req,
+            Err(e) => {
+                diagnostics.push(diagnostic(
+                    text,
+                    span,
+                    DiagnosticSeverity::Error,
+                    "invalid-version",
+                    e.to_string(),
+                ));
+                return;
+            }
+        };
+
+        let source = dependency.get("source").and_then(Item::as_str);
+        if let Some(source) = source.filter(|source| *source != "registry") {
+            // URLs are fetched at build time; only local paths can be checked here
+            let path = self.project_path.join(source);
+            if !source.contains("://") && !path.exists() {
+                let span = dependency
+                    .get("source")
+                    .and_then(Item::span)
+                    .unwrap_or(span);
+                diagnostics.push(diagnostic(
+                    text,
+                    span,
+                    DiagnosticSeverity::Error,
+                    "missing-source",
+                    format!("source path '{}' for '{}' does not exist", source, name),
+                ));
+            }
+            return;
+        }
+
+        let Some(registry) = self.registry.as_ref().filter(|r| r.has_index()) else {
+            return;
+        };
+        match registry.indexed_versions(name) {
+            Ok(Some(versions)) if !versions.iter().any(|v| req.matches(v)) => {
+                diagnostics.push(diagnostic(
+                    text,
+                    span,
+                    DiagnosticSeverity::Error,
+                    "unresolved-dependency",
+                    format!(
+                        "no version of '{}' matches '{}' (available: {})",
+                        name,
+                        version.as_str().unwrap_or_default(),
+                        versions.join(", ")
+                    ),
+                ));
+            }
+            Ok(None) => diagnostics.push(diagnostic(
+                text,
+                dependency.get("name").and_then(Item::span).unwrap_or(span),
+                DiagnosticSeverity::Warning,
+                "unresolved-dependency",
+                format!("'{}' is not in the local package index", name),
+            )),
+            _ => {}
+        }
+    }
+}
+
+/// Documentation for a schema path, if the key is known
+pub fn schema_doc(path: &str) -> Option<&'static str> {
+    let lookup = |path: &str| {
+        SCHEMA
+            .iter()
+            .find(|(key, _)| *key == path)
+            .map(|(_, doc)| *doc)
+    };
+
+    lookup(path).or_else(|| {
+        let (parent, _) = path.rsplit_once('.')?;
+        lookup(&format!("{}.*", parent))
+    })
+}
+
+/// A key of forgekit.toml that ForgeKit does not know, likely a typo
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UnknownKey {
+    /// Key as written, e.g. `build.opt_levl`
+    pub key: String,
+    /// Where the key starts
+    pub position: Position,
+    /// Closest known key, when one is similar enough
+    pub suggestion: Option<String>,
+}
+
+impl fmt::Display for UnknownKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown key '{}' at line {}, column {}",
+            self.key,
+            self.position.line + 1,
+            self.position.character + 1
+        )?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " (did you mean '{}'?)", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// Unknown keys in the contents of forgekit.toml
+///
+/// Contents that are not valid TOML have no keys to report; loading them
+/// fails with the syntax error instead.
+pub fn unknown_keys(text: &str) -> Vec<UnknownKey> {
+    let Ok(document) = ImDocument::parse(text) else {
+        return Vec::new();
+    };
+    unknown_key_entries(document.as_table())
+        .into_iter()
+        .map(|(key, suggestion)| UnknownKey {
+            key: key.name,
+            position: position(text, key.span.start),
+            suggestion,
+        })
+        .collect()
+}
+
+/// Unknown keys of a document with the key each was probably meant to be
+///
+/// Keys inside an unknown table are not reported again.
+fn unknown_key_entries(root: &dyn TableLike) -> Vec<(KeyEntry, Option<String>)> {
+    let mut unknown: Vec<(KeyEntry, Option<String>)> = Vec::new();
+    for key in keys(root) {
+        let inside_reported = unknown
+            .iter()
+            .any(|(parent, _)| key.name.starts_with(&format!("{}.", parent.name)));
+        if schema_doc(&key.path).is_none() && !inside_reported {
+            let suggestion = suggest(&key);
+            unknown.push((key, suggestion));
+        }
+    }
+    unknown
+}
+
+/// Closest documented sibling of an unknown key, as the user would write it
+fn suggest(key: &KeyEntry) -> Option<String> {
+    let (parent, name) = split_last(&key.path);
+    let (distance, candidate) = SCHEMA
+        .iter()
+        .map(|(path, _)| split_last(path))
+        .filter(|(candidate_parent, candidate)| *candidate_parent == parent && *candidate != "*")
+        .map(|(_, candidate)| (strsim::damerau_levenshtein(name, candidate), candidate))
+        .min()?;
+    if distance > (name.len() / 3).max(1) {
+        return None;
+    }
+
+    Some(match split_last(&key.name) {
+        (Some(prefix), _) => format!("{}.{}", prefix, candidate),
+        (None, _) => candidate.to_string(),
+    })
+}
+
+fn split_last(path: &str) -> (Option<&str>, &str) {
+    match path.rsplit_once('.') {
+        Some((parent, name)) => (Some(parent), name),
+        None => (None, path),
+    }
+}
+
+/// All keys of a document with their schema paths
+fn keys(root: &dyn TableLike) -> Vec<KeyEntry> {
+    let mut keys = Vec::new();
+    walk_table(root, "", "", &mut keys);
+    keys
+}
+
+/// Collect the keys of a table
+///
+/// Keys below a `*` schema entry take the wildcard as their schema path, so
+/// map keys containing dots (e.g. `mox-net@1.0.0`) still resolve.
+fn walk_table(table: &dyn TableLike, prefix: &str, display: &str, keys: &mut Vec<KeyEntry>) {
+    for (name, _) in table.iter() {
+        let Some((key, item)) = table.get_key_value(name) else {
+            continue;
+        };
+        let display = if display.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", display, name)
+        };
+        let path = if prefix.is_empty() {
+            name.to_string()
+        } else {
+            let literal = format!("{}.{}", prefix, name);
+            let wildcard = format!("{}.*", prefix);
+            let known = |path: &str| SCHEMA.iter().any(|(key, _)| *key == path);
+            if !known(&literal) && known(&wildcard) {
+                wildcard
+            } else {
+                literal
+            }
+        };
+
+        if let Some(span) = key.span() {
+            keys.push(KeyEntry {
+                path: path.clone(),
+                name: display.clone(),
+                span,
+            });
+        }
+
+        // `[env.<name>]` overrides take the keys of the file itself
+        if let Some(child) = item.as_table_like() {
+            let child_prefix = if path == "env.*" { "" } else { path.as_str() };
+            walk_table(child, child_prefix, &display, keys);
+        }
+        let elements = table_elements(item);
+        let element_path = format!("{}[]", path);
+        for element in elements {
+            walk_table(element, &element_path, &display, keys);
+        }
+    }
+}
+
+/// Tables inside an array of tables or an array of inline tables
+fn table_elements(item: &Item) -> Vec<&dyn TableLike> {
+    if let Some(tables) = item.as_array_of_tables() {
+        return tables.iter().map(|t| t as &dyn TableLike).collect();
+    }
+
+    item.as_array()
+        .map(|array| {
+            array
+                .iter()
+                .filter_map(|value| value.as_inline_table())
+                .map(|t| t as &dyn TableLike)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn diagnostic(
+    text: &str,
+    span: Span<usize>,
+    severity: DiagnosticSeverity,
+    code: &str,
+    message: String,
+) -> ManifestDiagnostic {
+    ManifestDiagnostic {
+        range: range(text, span),
+        severity,
+        code: code.to_string(),
+        source: "forgekit".to_string(),
+        message,
+    }
+}
+
+fn range(text: &str, span: Span<usize>) -> Range {
+    Range {
+        start: position(text, span.start),
+        end: position(text, span.end),
+    }
+}
+
+/// Convert a byte offset into a line and UTF-16 column
+fn position(text: &str, offset: usize) -> Position {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+
+    Position {
+        line: before.matches('\n').count() as u32,
+        character: before[line_start..].encode_utf16().count() as u32,
+    }
+}
+
+/// Convert a line and UTF-16 column into a byte offset
+fn offset(text: &str, position: Position) -> usize {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        match text[line_start..].find('\n') {
+            Some(i) => line_start += i + 1,
+            None => return text.len(),
+        }
+    }
+
+    let mut units = 0;
+    for (i, c) in text[line_start..].char_indices() {
+        if units >= position.character as usize || c == '\n' {
+            return line_start + i;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::RegistryConfig;
+    use tempfile::TempDir;
+
+    const MANIFEST: &str = r#"name = "demo"
+version = "0.1.0"
+authors = []
+colour = "blue"
+
+[build]
+target = "ledokoz"
+opt_level = "2"
+rustflags = []
+output_dir = "target"
+
+[[dependencies]]
+name = "forgekit-http"
+version = "^2.0"
+
+[[dependencies]]
+name = "forgekit-gui"
+version = "latest"
+"#;
+
+    fn codes(diagnostics: &[ManifestDiagnostic]) -> Vec<&str> {
+        diagnostics.iter().map(|d| d.code.as_str()).collect()
+    }
+
+    #[test]
+    fn test_unknown_keys_and_versions() {
+        let temp_dir = TempDir::new().unwrap();
+        let diagnostics = ManifestAnalyzer::new(temp_dir.path()).analyze(MANIFEST);
+
+        assert_eq!(codes(&diagnostics), vec!["unknown-key", "invalid-version"]);
+        let unknown = &diagnostics[0];
+        assert_eq!(
+            unknown.range.start,
+            Position {
+                line: 3,
+                character: 0
+            }
+        );
+        assert_eq!(unknown.severity, DiagnosticSeverity::Warning);
+        assert_eq!(diagnostics[1].range.start.line, 17);
+    }
+
+    #[test]
+    fn test_unknown_keys_suggest_nearest_key() {
+        let text = r#"name = "demo"
+dependancies = []
+
+[biuld.sandbox]
+mode = "env"
+
+[env.prod.build]
+opt_levl = "3"
+"#;
+        let unknown = unknown_keys(text);
+        let found: Vec<_> = unknown
+            .iter()
+            .map(|key| (key.key.as_str(), key.suggestion.as_deref()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("dependancies", Some("dependencies")),
+                ("biuld", Some("build")),
+                ("env.prod.build.opt_levl", Some("env.prod.build.opt_level")),
+            ]
+        );
+        assert_eq!(
+            unknown[0].to_string(),
+            "unknown key 'dependancies' at line 2, column 1 (did you mean 'dependencies'?)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unresolvable_dependency() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = RegistryClient::new(RegistryConfig {
+            index_dir: temp_dir.path().join("index"),
+            cache_dir: temp_dir.path().join("cache"),
+            ..Default::default()
+        })
+        .unwrap();
+        registry.update_index().await.unwrap();
+
+        let manifest = MANIFEST.replace("\"latest\"", "\"0.1\"");
+        let diagnostics = ManifestAnalyzer::new(temp_dir.path())
+            .with_registry(registry)
+            .analyze(&manifest);
+
+        assert_eq!(
+            codes(&diagnostics),
+            vec!["unknown-key", "unresolved-dependency"]
+        );
+        assert!(diagnostics[1].message.contains("forgekit-http"));
+    }
+
+    #[test]
+    fn test_syntax_error_and_hover() {
+        let temp_dir = TempDir::new().unwrap();
+        let analyzer = ManifestAnalyzer::new(temp_dir.path());
+
+        let diagnostics = analyzer.analyze("name = \"demo\n");
+        assert_eq!(codes(&diagnostics), vec!["syntax"]);
+
+        let hover = analyzer
+            .hover(
+                MANIFEST,
+                Position {
+                    line: 7,
+                    character: 3,
+                },
+            )
+            .unwrap();
+        assert!(hover.contents.starts_with("**build.opt_level**"));
+        assert!(analyzer
+            .hover(
+                MANIFEST,
+                Position {
+                    line: 3,
+                    character: 1
+                }
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn test_override_keys_are_known() {
+        let temp_dir = TempDir::new().unwrap();
+        let analyzer = ManifestAnalyzer::new(temp_dir.path());
+        let text = r#"name = "demo"
+version = "0.1.0"
+authors = []
+dependencies = []
+
+[build]
+target = "ledokoz"
+opt_level = "2"
+rustflags = []
+output_dir = "target"
+
+[replace."mox-net@1.0.0"]
+git = "https://example.com/mox-net"
+branch = "fix"
+
+[env.prod.build]
+opt_level = "3"
+"#;
+        assert!(analyzer.analyze(text).is_empty());
+        let typo = text.replace("opt_level = \"3\"", "opt_levl = \"3\"");
+        let diagnostics = analyzer.analyze(&typo);
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message.contains("env.prod.build.opt_levl"));
+        let hover = analyzer
+            .hover(
+                text,
+                Position {
+                    line: 13,
+                    character: 1,
+                },
+            )
+            .unwrap();
+        assert!(hover
+            .contents
+            .starts_with("**replace.mox-net@1.0.0.branch**"));
+    }
+}
@@ -2,10 +2,14 @@
 //!
 //! This module provides database migration management.
 
+use crate::dry_run::{DryRun, PlannedAction};
 use crate::error::ForgeKitError;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
+/// Directory of a project holding its migrations
+pub const MIGRATIONS_DIR: &str = "migrations";
+
 /// Migration report
 #[derive(Debug, Clone)]
 pub struct MigrationReport {
@@ -18,23 +22,62 @@ pub struct MigrationManager;
 
 impl MigrationManager {
     /// Create a new migration
-    pub async fn create_migration(name: &str) -> Result<std::path::PathBuf, ForgeKitError> {
-        let migrations_dir = std::path::PathBuf::from("migrations");
+    pub async fn create_migration(name: &str) -> Result<PathBuf, ForgeKitError> {
+        Self::create_migration_with(name, &DryRun::disabled()).await
+    }
+
+    /// Create a new migration, only recording the files on `dry_run` when it is enabled
+    pub async fn create_migration_with(
+        name: &str,
+        dry_run: &DryRun,
+    ) -> Result<PathBuf, ForgeKitError> {
+        Self::create_migration_in(Path::new(""), name, dry_run).await
+    }
+
+    /// Create a new migration in the project at `project_path`, only
+    /// recording the files on `dry_run` when it is enabled
+    pub async fn create_migration_in(
+        project_path: &Path,
+        name: &str,
+        dry_run: &DryRun,
+    ) -> Result<PathBuf, ForgeKitError> {
+        let migrations_dir = project_path.join(MIGRATIONS_DIR);
+        let migration_file = project_path.join(Self::migration_file(name));
+        if dry_run.is_enabled() {
+            if !migrations_dir.exists() {
+                dry_run.record(PlannedAction::CreateDir {
+                    path: migrations_dir,
+                });
+            }
+            dry_run.record(PlannedAction::WriteFile {
+                path: migration_file.clone(),
+            });
+            return Ok(migration_file);
+        }
+
         std::fs::create_dir_all(&migrations_dir)?;
+        std::fs::write(&migration_file, Self::migration_contents(name))?;
+
+        Ok(migration_file)
+    }
 
-        let migration_file = migrations_dir.join(format!(
+    /// Path of a new migration named `name`, relative to the project
+    pub fn migration_file(name: &str) -> PathBuf {
+        Path::new(MIGRATIONS_DIR).join(format!(
             "{}_{}.sql",
             chrono::Local::now().format("%Y%m%d%H%M%S"),
             name
-        ));
-        std::fs::write(&migration_file, "-- Migration: {}\n")?;
+        ))
+    }
 
-        Ok(migration_file)
+    /// Initial contents of a new migration named `name`
+    pub fn migration_contents(name: &str) -> String {
+        format!("-- Migration: {}\n", name)
     }
 
     /// Run migrations
     pub async fn run_migrations(path: &Path) -> Result<MigrationReport, ForgeKitError> {
-        let migrations_dir = path.join("migrations");
+        let migrations_dir = path.join(MIGRATIONS_DIR);
         if !migrations_dir.exists() {
             return Ok(MigrationReport {
                 applied: Vec::new(),
@@ -58,10 +101,23 @@ impl MigrationManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tempfile::TempDir;
 
     #[tokio::test]
     async fn test_create_migration() {
-        let result = MigrationManager::create_migration("initial_schema").await;
-        assert!(result.is_ok());
+        let temp_dir = TempDir::new().unwrap();
+        let file = MigrationManager::create_migration_in(
+            temp_dir.path(),
+            "initial_schema",
+            &DryRun::disabled(),
+        )
+        .await
+        .unwrap();
+
+        assert!(file.starts_with(temp_dir.path().join("migrations")));
+        assert_eq!(
+            std::fs::read_to_string(file).unwrap(),
+            "-- Migration: initial_schema\n"
+        );
     }
 }
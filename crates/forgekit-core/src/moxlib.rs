@@ -0,0 +1,400 @@
+//! Library package module
+//!
+//! This module packages library projects (`kind = "library"` in
+//! forgekit.toml) into `.moxlib` archives. A .moxlib is a ZIP file holding
+//! the compiled rlib, the crate sources and Cargo.toml so consumers can
+//! rebuild it with their own toolchain, the rustdoc output, rustdoc JSON of
+//! the public API, and a `moxlib.toml` manifest describing the library.
+//! Plugins also carry the dynamic library ForgeKit loads them from.
+
+use crate::api_surface;
+use crate::builder::BuildInfo;
+use crate::cancel::{self, CancellationToken};
+use crate::config::{Dependency, PluginConfig, ProjectConfig};
+use crate::error::ForgeKitError;
+use crate::packager;
+use crate::platform;
+use crate::plugin;
+use crate::store::hash_file;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+use zip::{write::FileOptions, ZipArchive, ZipWriter};
+
+/// File extension of library packages
+pub const MOXLIB_EXTENSION: &str = "moxlib";
+
+/// Name of the manifest inside a .moxlib
+pub const MOXLIB_MANIFEST: &str = "moxlib.toml";
+
+/// Name of the rustdoc JSON of the public API inside a .moxlib
+pub const MOXLIB_API: &str = "api.json";
+
+/// Current .moxlib format version
+const MOXLIB_FORMAT: u32 = 1;
+
+/// Manifest stored in every .moxlib archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryManifest {
+    /// Archive format version
+    pub format: u32,
+    /// Package name
+    pub name: String,
+    /// Package version
+    pub version: String,
+    /// Crate name used in `use` paths
+    pub crate_name: String,
+    /// Package description
+    pub description: Option<String>,
+    /// Authors
+    pub authors: Vec<String>,
+    /// Path of the rlib inside the archive
+    pub rlib: String,
+    /// SHA-256 checksum of the rlib
+    pub rlib_checksum: String,
+    /// Whether rustdoc output is included under `doc/`
+    pub docs: bool,
+    /// Whether rustdoc JSON of the public API is included as `api.json`
+    #[serde(default)]
+    pub api: bool,
+    /// Dependencies of the library
+    pub dependencies: Vec<Dependency>,
+    /// `[plugin]` of libraries published as ForgeKit plugins
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plugin: Option<PluginConfig>,
+    /// Path of the dynamic library ForgeKit loads a plugin from inside the
+    /// archive, for plugins
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub library: Option<String>,
+    /// Target triple the dynamic library of a plugin is built for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// ForgeKit version that created the archive
+    pub forgekit_version: String,
+}
+
+/// Path the .moxlib archive of a library project is written to
+pub fn archive_path(project_path: &Path, config: &ProjectConfig) -> PathBuf {
+    project_path.join(&config.build.output_dir).join(format!(
+        "{}-{}.{}",
+        config.name, config.version, MOXLIB_EXTENSION
+    ))
+}
+
+/// Package a built library project into a .moxlib archive
+///
+/// The library must have been built with `forgekit build`. Documentation is
+/// generated with `cargo doc` when the project has a Cargo.toml. A partially
+/// written archive is removed if packaging is cancelled or fails.
+pub async fn package(
+    project_path: &Path,
+    config: &ProjectConfig,
+    cancel: &CancellationToken,
+) -> Result<PathBuf, ForgeKitError> {
+    let crate_name = config.name.replace('-', "_");
+    let build_info = BuildInfo::load(project_path)?;
+    let target_dir = build_info.as_ref().map_or_else(
+        || project_path.join("target"),
+        |info| info.target_dir(project_path),
+    );
+    let release_dir = platform::target_release_dir(
+        &target_dir,
+        build_info
+            .as_ref()
+            .map_or(platform::TARGET, |info| info.target()),
+    );
+    let rlib_path = release_dir.join(format!("lib{}.rlib", crate_name));
+    if !rlib_path.exists() {
+        return Err(ForgeKitError::PackagingFailed(
+            "Library not found. Please build the project first.".to_string(),
+        ));
+    }
+
+    // Plugins are loaded into ForgeKit, which needs them as a dynamic library
+    let library = match &config.plugin {
+        Some(_) => {
+            if build_info.as_ref().is_none_or(|info| info.target.is_none()) {
+                return Err(ForgeKitError::PackagingFailed(
+                    "Plugins are loaded by ForgeKit on the host. Please build the plugin with `forgekit build --host` first.".to_string(),
+                ));
+            }
+            let file = plugin::library_file_name(&crate_name);
+            if !release_dir.join(&file).exists() {
+                return Err(ForgeKitError::PackagingFailed(format!(
+                    "Plugin library {} not found. Add `crate-type = [\"cdylib\", \"rlib\"]` to [lib] of Cargo.toml and build the project first.",
+                    file
+                )));
+            }
+            Some(format!("lib/{}", file))
+        }
+        None => None,
+    };
+
+    let has_cargo_toml = project_path.join("Cargo.toml").exists();
+    let doc_dir = target_dir.join("doc");
+    if has_cargo_toml {
+        let mut command = Command::new("cargo");
+        command
+            .args(["doc", "--no-deps"])
+            .current_dir(project_path)
+            .env("CARGO_TARGET_DIR", &target_dir);
+        let output = cancel::output(&mut command, cancel, "package").await?;
+        if !output.status.success() {
+            return Err(ForgeKitError::PackagingFailed(format!(
+                "cargo doc failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+    }
+    let docs = doc_dir.join(&crate_name).exists();
+
+    // The public API lets `publish --check-semver` compare releases; a
+    // library is still packaged when rustdoc cannot describe it
+    let api = if has_cargo_toml {
+        match api_surface::rustdoc_json(project_path, &crate_name, cancel).await {
+            Ok(api) => Some(api),
+            Err(ForgeKitError::Cancelled(reason)) => return Err(ForgeKitError::Cancelled(reason)),
+            Err(e) => {
+                tracing::warn!("Packaging without the API description: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let rlib = format!("lib/lib{}.rlib", crate_name);
+    let manifest = LibraryManifest {
+        format: MOXLIB_FORMAT,
+        name: config.name.clone(),
+        version: config.version.clone(),
+        crate_name,
+        description: config.description.clone(),
+        authors: config.authors.clone(),
+        rlib,
+        rlib_checksum: hash_file(&rlib_path)?,
+        docs,
+        api: api.is_some(),
+        dependencies: config.dependencies.clone(),
+        plugin: config.plugin.clone(),
+        target: library
+            .as_ref()
+            .and(build_info.as_ref())
+            .map(|info| info.target().to_string()),
+        library,
+        forgekit_version: env!("CARGO_PKG_VERSION").to_string(),
+    };
+
+    let archive_path = archive_path(project_path, config);
+    if let Some(output_dir) = archive_path.parent() {
+        std::fs::create_dir_all(output_dir)?;
+    }
+
+    // Build outputs are taken from the target the library was built for
+    let mut built = vec![(manifest.rlib.clone(), rlib_path)];
+    if let Some(library) = &manifest.library {
+        let file = library.strip_prefix("lib/").unwrap_or(library);
+        built.push((library.clone(), release_dir.join(file)));
+    }
+    if let Some(api) = api {
+        built.push((MOXLIB_API.to_string(), api));
+    }
+    let written = write_archive(
+        project_path,
+        config,
+        &manifest,
+        &built,
+        docs.then_some(doc_dir.as_path()),
+        &archive_path,
+        cancel,
+    );
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&archive_path);
+        return Err(e);
+    }
+    Ok(archive_path)
+}
+
+/// Write the .moxlib archive
+///
+/// `built` pairs the path of each build output inside the archive with the
+/// file it is copied from.
+fn write_archive(
+    project_path: &Path,
+    config: &ProjectConfig,
+    manifest: &LibraryManifest,
+    built: &[(String, PathBuf)],
+    doc_dir: Option<&Path>,
+    archive_path: &Path,
+    cancel: &CancellationToken,
+) -> Result<(), ForgeKitError> {
+    let mut zip = ZipWriter::new(std::fs::File::create(archive_path)?);
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    zip.start_file(MOXLIB_MANIFEST, options)?;
+    zip.write_all(toml::to_string_pretty(manifest)?.as_bytes())?;
+    zip.start_file("forgekit.toml", options)?;
+    zip.write_all(toml::to_string_pretty(config)?.as_bytes())?;
+    for (name, path) in built {
+        zip.start_file(name, options)?;
+        packager::copy_file(&mut zip, path)?;
+    }
+
+    let cargo_toml = project_path.join("Cargo.toml");
+    if cargo_toml.exists() {
+        zip.start_file("Cargo.toml", options)?;
+        packager::copy_file(&mut zip, &cargo_toml)?;
+    }
+    cancel::check(cancel, "package")?;
+    add_dir(&mut zip, &project_path.join("src"), "src", options)?;
+    if let Some(doc_dir) = doc_dir {
+        cancel::check(cancel, "package")?;
+        add_dir(&mut zip, doc_dir, "doc", options)?;
+    }
+
+    zip.finish()?;
+    Ok(())
+}
+
+/// Read the manifest of a .moxlib archive
+pub fn read_manifest(archive: &Path) -> Result<LibraryManifest, ForgeKitError> {
+    let mut zip = ZipArchive::new(std::fs::File::open(archive)?)?;
+    let mut file = zip.by_name(MOXLIB_MANIFEST).map_err(|_| {
+        ForgeKitError::PackagingFailed(format!("{:?} has no {}", archive, MOXLIB_MANIFEST))
+    })?;
+
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    Ok(toml::from_str(&contents)?)
+}
+
+/// Read the rustdoc JSON of the public API in a .moxlib, if it has one
+pub fn read_api(archive: &Path) -> Result<Option<serde_json::Value>, ForgeKitError> {
+    let mut zip = ZipArchive::new(std::fs::File::open(archive)?)?;
+    let file = match zip.by_name(MOXLIB_API) {
+        Ok(file) => file,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(Some(serde_json::from_reader(file)?))
+}
+
+/// Add every file below `dir` to the archive under `prefix`
+fn add_dir(
+    zip: &mut ZipWriter<std::fs::File>,
+    dir: &Path,
+    prefix: &str,
+    options: FileOptions,
+) -> Result<(), ForgeKitError> {
+    for entry in walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let relative = entry
+            .path()
+            .strip_prefix(dir)
+            .map_err(|_| ForgeKitError::PackagingFailed("Failed to strip prefix".to_string()))?;
+        let name = format!("{}/{}", prefix, platform::archive_path(relative));
+        zip.start_file(name, options)?;
+        packager::copy_file(zip, entry.path())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{PluginConfig, ProjectKind};
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_package_library() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path();
+        let config = ProjectConfig {
+            name: "mox-utils".to_string(),
+            version: "1.2.0".to_string(),
+            kind: ProjectKind::Library,
+            ..Default::default()
+        };
+
+        std::fs::create_dir_all(project.join("src")).unwrap();
+        std::fs::write(project.join("src").join("lib.rs"), "pub fn f() {}\n").unwrap();
+        assert!(package(project, &config, &CancellationToken::new())
+            .await
+            .is_err());
+
+        let release = project.join("target").join("ledokoz").join("release");
+        std::fs::create_dir_all(&release).unwrap();
+        std::fs::write(release.join("libmox_utils.rlib"), b"rlib").unwrap();
+
+        let archive = package(project, &config, &CancellationToken::new())
+            .await
+            .unwrap();
+        assert!(archive.ends_with("target/mox-utils-1.2.0.moxlib"));
+
+        let manifest = read_manifest(&archive).unwrap();
+        assert_eq!(manifest.crate_name, "mox_utils");
+        assert_eq!(manifest.rlib, "lib/libmox_utils.rlib");
+        assert!(!manifest.docs);
+        assert!(!manifest.api);
+        assert!(read_api(&archive).unwrap().is_none());
+
+        let zip = ZipArchive::new(std::fs::File::open(&archive).unwrap()).unwrap();
+        let names: Vec<&str> = zip.file_names().collect();
+        assert!(names.contains(&"src/lib.rs"));
+    }
+
+    #[tokio::test]
+    async fn test_package_plugin_from_host_build() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path();
+        let config = ProjectConfig {
+            name: "shader-lint".to_string(),
+            kind: ProjectKind::Library,
+            plugin: Some(PluginConfig { api_version: 1 }),
+            ..Default::default()
+        };
+        std::fs::create_dir_all(project.join("src")).unwrap();
+        std::fs::write(project.join("src").join("lib.rs"), "").unwrap();
+        for release in [
+            platform::release_dir(&project.join("target")),
+            platform::target_release_dir(&project.join("target"), "x86_64-unknown-linux-gnu"),
+        ] {
+            std::fs::create_dir_all(&release).unwrap();
+            std::fs::write(release.join("libshader_lint.rlib"), b"rlib").unwrap();
+            std::fs::write(
+                release.join(plugin::library_file_name("shader_lint")),
+                b"cdylib",
+            )
+            .unwrap();
+        }
+
+        // A plugin built for ledokoz cannot be loaded by ForgeKit
+        assert!(package(project, &config, &CancellationToken::new())
+            .await
+            .is_err());
+
+        let info = BuildInfo {
+            environment: None,
+            built_at: "2024-01-01T00:00:00Z".to_string(),
+            forgekit_version: env!("CARGO_PKG_VERSION").to_string(),
+            target: Some("x86_64-unknown-linux-gnu".to_string()),
+            target_dir: None,
+        };
+        std::fs::create_dir_all(BuildInfo::path(project).parent().unwrap()).unwrap();
+        std::fs::write(BuildInfo::path(project), toml::to_string(&info).unwrap()).unwrap();
+        let archive = package(project, &config, &CancellationToken::new())
+            .await
+            .unwrap();
+        let manifest = read_manifest(&archive).unwrap();
+        assert_eq!(
+            manifest.library,
+            Some(format!("lib/{}", plugin::library_file_name("shader_lint")))
+        );
+        assert_eq!(manifest.target.as_deref(), Some("x86_64-unknown-linux-gnu"));
+    }
+}
@@ -0,0 +1,462 @@
+//! Over-the-air update module
+//!
+//! This module backs the `[ota]` section and `forgekit feed generate`. Every
+//! .mox carries `ota.toml`, naming the channel it was built for, the feed
+//! URL and the key the feed is signed with, so an installed app knows where
+//! to look for updates and which feed to trust. The feed is a JSON file
+//! listing the latest package of each channel; devices poll it and only
+//! accept it when its Ed25519 signature matches the packaged key.
+//!
+//! The signing key is kept in `.forgekit/ota.key` unless `[ota] signing_key`
+//! names another file, or a credential store entry as `secret:<name>`. It is
+//! created with `forgekit feed keygen`; packaging and feed generation fail
+//! without it rather than sign with a key nobody meant to trust.
+
+use crate::atomic;
+use crate::config::ProjectConfig;
+use crate::credentials::{self, CredentialStore};
+use crate::env_manager::SECRET_PREFIX;
+use crate::error::ForgeKitError;
+use crate::packager;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Name of the channel manifest inside a .mox archive
+pub const OTA_MANIFEST: &str = "ota.toml";
+
+/// File name of the feed written next to the .mox
+pub const FEED_FILE: &str = "feed.json";
+
+/// Channel used when `[ota]` declares none
+const DEFAULT_CHANNEL: &str = "stable";
+
+/// Update settings packaged into a .mox
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OtaManifest {
+    /// Channel the package was built for
+    pub channel: String,
+    /// URL of the update feed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feed_url: Option<String>,
+    /// Base64 Ed25519 key the feed is signed with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+}
+
+/// Latest package of a channel
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeedEntry {
+    /// Version of the package
+    pub version: String,
+    /// Where devices download the package
+    pub url: String,
+    /// SHA-256 checksum of the package
+    pub sha256: String,
+    /// Size of the package in bytes
+    pub size: u64,
+    /// What's new in this version
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release_notes: Option<String>,
+    /// When the entry was added to the feed
+    pub published_at: DateTime<Utc>,
+}
+
+/// Update feed of an app
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Feed {
+    /// App name
+    pub app: String,
+    /// Latest package, keyed by channel
+    pub channels: BTreeMap<String, FeedEntry>,
+}
+
+/// A feed and its signature, as served to devices
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedFeed {
+    /// The feed
+    pub feed: Feed,
+    /// Base64 Ed25519 signature of the feed serialized as compact JSON
+    pub signature: String,
+}
+
+impl SignedFeed {
+    /// Sign `feed` with `key`
+    pub fn sign(feed: Feed, key: &Ed25519KeyPair) -> Result<Self, ForgeKitError> {
+        let signature = general_purpose::STANDARD.encode(key.sign(&serde_json::to_vec(&feed)?));
+        Ok(Self { feed, signature })
+    }
+
+    /// Check the signature against a base64 Ed25519 public key
+    pub fn verify(&self, public_key: &str) -> Result<(), ForgeKitError> {
+        let invalid = |what: &str| ForgeKitError::ChecksumMismatch(format!("feed {}", what));
+        let public_key = general_purpose::STANDARD
+            .decode(public_key.trim())
+            .map_err(|_| invalid("key is not valid base64"))?;
+        let signature = general_purpose::STANDARD
+            .decode(self.signature.trim())
+            .map_err(|_| invalid("signature is not valid base64"))?;
+        ring::signature::UnparsedPublicKey::new(&ring::signature::ED25519, public_key)
+            .verify(&serde_json::to_vec(&self.feed)?, &signature)
+            .map_err(|_| invalid("signature does not match the key"))
+    }
+}
+
+/// Options for `forgekit feed generate`
+#[derive(Debug, Clone, Default)]
+pub struct FeedOptions {
+    /// Channel to publish to, instead of the one the package was built for
+    pub channel: Option<String>,
+    /// Download URL of the package, instead of one next to `[ota] feed_url`
+    pub url: Option<String>,
+    /// Feed file to update, instead of `feed.json` next to the .mox
+    pub output: Option<PathBuf>,
+}
+
+/// Channel a package is built for
+///
+/// `requested` wins over `[ota] channel`, which wins over the first declared
+/// channel. A channel that `[ota] channels` does not declare is rejected.
+pub fn resolve_channel(
+    config: &ProjectConfig,
+    requested: Option<&str>,
+) -> Result<String, ForgeKitError> {
+    let ota = &config.ota;
+    let channel = requested
+        .map(str::to_string)
+        .or_else(|| ota.channel.clone())
+        .or_else(|| ota.channels.first().cloned())
+        .unwrap_or_else(|| DEFAULT_CHANNEL.to_string());
+    if !ota.channels.is_empty() && !ota.channels.contains(&channel) {
+        return Err(ForgeKitError::InvalidConfig(format!(
+            "Update channel '{}' is not declared in [ota] channels ({})",
+            channel,
+            ota.channels.join(", ")
+        )));
+    }
+    Ok(channel)
+}
+
+/// File holding the project's feed signing key
+pub fn key_path(project_path: &Path, config: &ProjectConfig) -> PathBuf {
+    match &config.ota.signing_key {
+        Some(path) => project_path.join(path),
+        None => project_path.join(".forgekit").join("ota.key"),
+    }
+}
+
+/// Stored feed signing key of a project: `[ota] signing_key` as a
+/// credential store entry name
+fn stored_key_name(config: &ProjectConfig) -> Option<&str> {
+    config
+        .ota
+        .signing_key
+        .as_deref()
+        .and_then(|key| key.strip_prefix(SECRET_PREFIX))
+}
+
+/// The project's feed signing key
+pub fn load_key(
+    project_path: &Path,
+    config: &ProjectConfig,
+) -> Result<Ed25519KeyPair, ForgeKitError> {
+    if let Some(name) = stored_key_name(config) {
+        return load_stored_key(&*credentials::default_store()?, name);
+    }
+
+    let path = key_path(project_path, config);
+    if !path.is_file() {
+        return Err(ForgeKitError::InvalidConfig(format!(
+            "No update feed signing key at {}; create one with `forgekit feed keygen`",
+            path.display()
+        )));
+    }
+    Ed25519KeyPair::from_pkcs8(&std::fs::read(&path)?).map_err(|e| {
+        ForgeKitError::InvalidConfig(format!("Invalid signing key {}: {}", path.display(), e))
+    })
+}
+
+/// Create the project's feed signing key, returning its base64 public key
+///
+/// An existing key is never replaced: devices only trust feeds signed with
+/// the key they were packaged with.
+pub fn generate_key(project_path: &Path, config: &ProjectConfig) -> Result<String, ForgeKitError> {
+    let pkcs8 = Ed25519KeyPair::generate_pkcs8(&ring::rand::SystemRandom::new())
+        .map_err(|_| ForgeKitError::Io(std::io::Error::other("cannot generate a signing key")))?;
+    if let Some(name) = stored_key_name(config) {
+        store_key(&*credentials::default_store()?, name, pkcs8.as_ref())?;
+    } else {
+        let path = key_path(project_path, config);
+        if path.exists() {
+            return Err(ForgeKitError::InvalidConfig(format!(
+                "{} already exists",
+                path.display()
+            )));
+        }
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        atomic::write_private(&path, pkcs8.as_ref())?;
+    }
+    let key = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref())
+        .map_err(|e| ForgeKitError::InvalidConfig(format!("Invalid signing key: {}", e)))?;
+    Ok(general_purpose::STANDARD.encode(key.public_key().as_ref()))
+}
+
+/// Feed signing key kept base64-encoded in a credential store under `name`
+fn load_stored_key(
+    credentials: &dyn CredentialStore,
+    name: &str,
+) -> Result<Ed25519KeyPair, ForgeKitError> {
+    let invalid = |reason: String| {
+        ForgeKitError::InvalidConfig(format!("Invalid signing key '{}': {}", name, reason))
+    };
+    let stored = credentials.get(name)?.ok_or_else(|| {
+        ForgeKitError::InvalidConfig(format!(
+            "No update feed signing key '{}' in the {} credential store; create one with `forgekit feed keygen`",
+            name,
+            credentials.backend()
+        ))
+    })?;
+    let pkcs8 = general_purpose::STANDARD
+        .decode(stored.trim())
+        .map_err(|e| invalid(e.to_string()))?;
+    Ed25519KeyPair::from_pkcs8(&pkcs8).map_err(|e| invalid(e.to_string()))
+}
+
+/// Keep a new feed signing key in a credential store under `name`
+fn store_key(
+    credentials: &dyn CredentialStore,
+    name: &str,
+    pkcs8: &[u8],
+) -> Result<(), ForgeKitError> {
+    if credentials.get(name)?.is_some() {
+        return Err(ForgeKitError::InvalidConfig(format!(
+            "Signing key '{}' already exists in the {} credential store",
+            name,
+            credentials.backend()
+        )));
+    }
+    credentials.set(name, &general_purpose::STANDARD.encode(pkcs8))
+}
+
+/// Update settings to package for `channel`
+///
+/// The feed key is only included once a feed URL is configured, and must
+/// exist by then.
+pub fn manifest(
+    project_path: &Path,
+    config: &ProjectConfig,
+    channel: &str,
+) -> Result<OtaManifest, ForgeKitError> {
+    let public_key = match &config.ota.feed_url {
+        Some(_) => {
+            let key = load_key(project_path, config)?;
+            Some(general_purpose::STANDARD.encode(key.public_key().as_ref()))
+        }
+        None => None,
+    };
+    Ok(OtaManifest {
+        channel: channel.to_string(),
+        feed_url: config.ota.feed_url.clone(),
+        public_key,
+    })
+}
+
+/// Read the update settings packaged into a .mox
+pub fn read_manifest(package: &Path) -> Result<Option<OtaManifest>, ForgeKitError> {
+    match read_entry(package, OTA_MANIFEST)? {
+        Some(manifest) => Ok(Some(toml::from_str(&manifest)?)),
+        None => Ok(None),
+    }
+}
+
+/// Read a feed file, if it exists
+pub fn load_feed(path: &Path) -> Result<Option<SignedFeed>, ForgeKitError> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_str(&std::fs::read_to_string(path)?)?))
+}
+
+/// Add the project's current .mox to its feed, creating the feed if needed
+///
+/// Entries of other channels are kept. Returns where the feed was written.
+pub fn generate_feed(
+    project_path: &Path,
+    config: &ProjectConfig,
+    options: &FeedOptions,
+) -> Result<(PathBuf, SignedFeed), ForgeKitError> {
+    let package = packager::output_path(project_path, config);
+    if !package.is_file() {
+        return Err(ForgeKitError::PackagingFailed(format!(
+            "No package at {}; run `forgekit package` first",
+            package.display()
+        )));
+    }
+
+    // The package knows its version and channel better than the current config
+    let packaged: ProjectConfig = match read_entry(&package, "forgekit.toml")? {
+        Some(packaged) => toml::from_str(&packaged)?,
+        None => config.clone(),
+    };
+    let channel = match (&options.channel, read_manifest(&package)?) {
+        (Some(channel), _) => resolve_channel(config, Some(channel))?,
+        (None, Some(manifest)) => manifest.channel,
+        (None, None) => resolve_channel(config, None)?,
+    };
+    let file_name = format!("{}-{}.mox", packaged.name, packaged.version);
+    let url = match (&options.url, &config.ota.feed_url) {
+        (Some(url), _) => url.clone(),
+        (None, Some(feed_url)) => match feed_url.rsplit_once('/') {
+            Some((base, _)) => format!("{}/{}", base, file_name),
+            None => file_name,
+        },
+        (None, None) => {
+            return Err(ForgeKitError::InvalidConfig(
+                "[ota] feed_url is not set; pass the package URL with --url".to_string(),
+            ))
+        }
+    };
+
+    let data = std::fs::read(&package)?;
+    let output = options
+        .output
+        .clone()
+        .unwrap_or_else(|| package.with_file_name(FEED_FILE));
+    let mut feed = load_feed(&output)?
+        .map(|signed| signed.feed)
+        .unwrap_or_default();
+    feed.app = packaged.name.clone();
+    feed.channels.insert(
+        channel,
+        FeedEntry {
+            version: packaged.version.clone(),
+            url,
+            sha256: format!("{:x}", Sha256::digest(&data)),
+            size: data.len() as u64,
+            release_notes: packaged.release_notes.clone(),
+            published_at: Utc::now(),
+        },
+    );
+
+    let signed = SignedFeed::sign(feed, &load_key(project_path, config)?)?;
+    if let Some(dir) = output.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    atomic::write(&output, serde_json::to_string_pretty(&signed)?)?;
+    Ok((output, signed))
+}
+
+/// Contents of a text entry of a .mox, if it has one
+fn read_entry(package: &Path, name: &str) -> Result<Option<String>, ForgeKitError> {
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(package)?)?;
+    let mut entry = match archive.by_name(name) {
+        Ok(entry) => entry,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let mut contents = String::new();
+    entry.read_to_string(&mut contents)?;
+    Ok(Some(contents))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::OtaConfig;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    fn write_package(project: &Path, config: &ProjectConfig, channel: &str) {
+        let path = packager::output_path(project, config);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+        let options = zip::write::FileOptions::default();
+        zip.start_file("forgekit.toml", options).unwrap();
+        zip.write_all(toml::to_string(config).unwrap().as_bytes())
+            .unwrap();
+        let manifest = manifest(project, config, channel).unwrap();
+        zip.start_file(OTA_MANIFEST, options).unwrap();
+        zip.write_all(toml::to_string(&manifest).unwrap().as_bytes())
+            .unwrap();
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_resolve_channel() {
+        let mut config = ProjectConfig::default();
+        assert_eq!(resolve_channel(&config, None).unwrap(), "stable");
+        config.ota.channels = vec!["beta".to_string(), "nightly".to_string()];
+        assert_eq!(resolve_channel(&config, None).unwrap(), "beta");
+        assert_eq!(
+            resolve_channel(&config, Some("nightly")).unwrap(),
+            "nightly"
+        );
+        assert!(resolve_channel(&config, Some("stable")).is_err());
+    }
+
+    #[test]
+    fn test_feed_is_signed_and_keeps_other_channels() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path();
+        let mut config = ProjectConfig {
+            name: "notes".to_string(),
+            version: "1.0.0".to_string(),
+            ota: OtaConfig {
+                channels: vec!["stable".to_string(), "beta".to_string()],
+                feed_url: Some("https://updates.example.com/notes/feed.json".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(manifest(project, &config, "stable").is_err());
+        generate_key(project, &config).unwrap();
+        assert!(generate_key(project, &config).is_err());
+        write_package(project, &config, "stable");
+        let (path, _) = generate_feed(project, &config, &FeedOptions::default()).unwrap();
+
+        config.version = "1.1.0-beta.1".to_string();
+        write_package(project, &config, "beta");
+        generate_feed(project, &config, &FeedOptions::default()).unwrap();
+
+        let signed = load_feed(&path).unwrap().unwrap();
+        assert_eq!(signed.feed.channels["stable"].version, "1.0.0");
+        assert_eq!(
+            signed.feed.channels["beta"].url,
+            "https://updates.example.com/notes/notes-1.1.0-beta.1.mox"
+        );
+
+        let package = packager::output_path(project, &config);
+        let public_key = read_manifest(&package)
+            .unwrap()
+            .unwrap()
+            .public_key
+            .unwrap();
+        signed.verify(&public_key).unwrap();
+
+        let mut tampered = signed.clone();
+        tampered.feed.channels.get_mut("stable").unwrap().version = "9.9.9".to_string();
+        assert!(tampered.verify(&public_key).is_err());
+    }
+
+    #[test]
+    fn test_stored_key_is_never_replaced() {
+        let store = crate::credentials::MemoryCredentialStore::new();
+        assert!(load_stored_key(&store, "ota.notes").is_err());
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&ring::rand::SystemRandom::new()).unwrap();
+        store_key(&store, "ota.notes", pkcs8.as_ref()).unwrap();
+        assert!(store_key(&store, "ota.notes", pkcs8.as_ref()).is_err());
+        let key = load_stored_key(&store, "ota.notes").unwrap();
+        let again = load_stored_key(&store, "ota.notes").unwrap();
+        assert_eq!(key.public_key().as_ref(), again.public_key().as_ref());
+
+        store.set("ota.broken", "not base64!").unwrap();
+        assert!(load_stored_key(&store, "ota.broken").is_err());
+    }
+}
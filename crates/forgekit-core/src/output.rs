@@ -0,0 +1,138 @@
+//! Command output module
+//!
+//! This module defines the machine-readable envelope every command emits
+//! with `--format json`, along with the exit codes shared by the CLI and
+//! other front ends.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Version of the JSON output schema, bumped on breaking changes
+pub const OUTPUT_SCHEMA_VERSION: u32 = 1;
+
+/// Exit code when the command succeeded
+pub const EXIT_SUCCESS: i32 = 0;
+
+/// Exit code when the command ran but reported failures (invalid project, failing tests)
+pub const EXIT_FAILURE: i32 = 1;
+
+/// Exit code when the command could not run
+///
+/// The same as [`EXIT_FAILURE`], which scripts predating the JSON output
+/// rely on; the `error` of the JSON envelope tells the two apart.
+pub const EXIT_ERROR: i32 = 1;
+
+/// Output format selected with `--format`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Human readable text
+    #[default]
+    Text,
+    /// A single JSON document on stdout
+    Json,
+    /// Markdown for commands that produce a report, text otherwise
+    Markdown,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "markdown" => Ok(OutputFormat::Markdown),
+            _ => Err(format!(
+                "unknown output format '{}' (expected text, json or markdown)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputFormat::Text => write!(f, "text"),
+            OutputFormat::Json => write!(f, "json"),
+            OutputFormat::Markdown => write!(f, "markdown"),
+        }
+    }
+}
+
+/// JSON envelope emitted by every command
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandOutput {
+    /// Output schema version
+    pub schema_version: u32,
+    /// Command that ran, e.g. `build` or `cache stats`
+    pub command: String,
+    /// Whether the command succeeded
+    pub success: bool,
+    /// Process exit code
+    pub exit_code: i32,
+    /// Command specific result
+    pub data: serde_json::Value,
+    /// Informational messages that would have been printed as text
+    pub messages: Vec<String>,
+    /// Error message when the command could not run
+    pub error: Option<String>,
+}
+
+impl CommandOutput {
+    /// Create an envelope for a command
+    pub fn new(command: &str) -> Self {
+        Self {
+            schema_version: OUTPUT_SCHEMA_VERSION,
+            command: command.to_string(),
+            success: true,
+            exit_code: EXIT_SUCCESS,
+            data: serde_json::Value::Null,
+            messages: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Mark the command as having reported failures
+    pub fn fail(&mut self) {
+        if self.error.is_none() {
+            self.success = false;
+            self.exit_code = EXIT_FAILURE;
+        }
+    }
+
+    /// Record an error that stopped the command
+    pub fn set_error(&mut self, error: String) {
+        self.success = false;
+        self.exit_code = EXIT_ERROR;
+        self.error = Some(error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_format_parse() {
+        assert_eq!("json".parse::<OutputFormat>().unwrap(), OutputFormat::Json);
+        assert!("yaml".parse::<OutputFormat>().is_err());
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        let mut output = CommandOutput::new("validate");
+        assert_eq!(output.exit_code, EXIT_SUCCESS);
+
+        output.fail();
+        assert_eq!(output.exit_code, EXIT_FAILURE);
+
+        output.set_error("boom".to_string());
+        output.fail();
+        assert_eq!(output.exit_code, EXIT_ERROR);
+        assert_eq!(output.error.as_deref(), Some("boom"));
+        assert!(!output.success);
+    }
+}
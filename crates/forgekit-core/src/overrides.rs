@@ -0,0 +1,401 @@
+//! Dependency override module
+//!
+//! This module applies the `[patch]` and `[replace]` sections of
+//! forgekit.toml. A patch swaps every version of a package for a git branch
+//! or local directory; a replacement does the same for one exact
+//! `name@version`. Overrides apply across the whole dependency graph, so
+//! packages that depend on a patched package transitively pick it up too.
+
+use crate::config::{Dependency, PatchSource, ProjectConfig};
+use crate::error::ForgeKitError;
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// Problems found when checking overrides against the dependency graph
+#[derive(Debug, Clone, Default)]
+pub struct OverrideReport {
+    /// Overrides that cannot be applied
+    pub errors: Vec<String>,
+    /// Overrides that have no effect
+    pub warnings: Vec<String>,
+}
+
+/// Parsed `[patch]` and `[replace]` sections of a project
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    patches: BTreeMap<String, PatchSource>,
+    replacements: BTreeMap<(String, String), PatchSource>,
+}
+
+impl Overrides {
+    /// Parse and check the overrides declared in a project configuration
+    pub fn from_config(config: &ProjectConfig) -> Result<Self, ForgeKitError> {
+        for (name, source) in &config.patch {
+            check_source(&format!("patch.{}", name), source)?;
+        }
+
+        let mut replacements = BTreeMap::new();
+        for (key, source) in &config.replace {
+            check_source(&format!("replace.\"{}\"", key), source)?;
+            let (name, version) = key.split_once('@').ok_or_else(|| {
+                ForgeKitError::InvalidConfig(format!(
+                    "replace key '{}' must have the form name@version",
+                    key
+                ))
+            })?;
+            replacements.insert((name.to_string(), version.to_string()), source.clone());
+        }
+
+        Ok(Self {
+            patches: config.patch.clone(),
+            replacements,
+        })
+    }
+
+    /// Whether the project declares no overrides
+    pub fn is_empty(&self) -> bool {
+        self.patches.is_empty() && self.replacements.is_empty()
+    }
+
+    /// Override for a package, if any
+    ///
+    /// An exact `[replace]` entry wins over a `[patch]` for the same package.
+    pub fn source_for(&self, name: &str, version: &str) -> Option<&PatchSource> {
+        self.replacements
+            .get(&(name.to_string(), version.to_string()))
+            .or_else(|| self.patches.get(name))
+    }
+
+    /// Check the overrides against the project's resolved dependency graph
+    pub fn check(&self, project_root: &Path, graph: &[Dependency]) -> OverrideReport {
+        let mut report = OverrideReport::default();
+
+        let sources = self.patches.values().chain(self.replacements.values());
+        for path in sources.filter_map(|source| source.path.as_deref()) {
+            if !project_root.join(path).is_dir() {
+                report
+                    .errors
+                    .push(format!("Override path '{}' does not exist", path));
+            }
+        }
+
+        let names: HashSet<&str> = graph.iter().map(|dep| dep.name.as_str()).collect();
+        for name in self.patches.keys() {
+            if !names.contains(name.as_str()) {
+                report.warnings.push(format!(
+                    "Patch for '{}' is unused: nothing depends on it",
+                    name
+                ));
+            }
+        }
+        for (name, version) in self.replacements.keys() {
+            if !graph
+                .iter()
+                .any(|dep| dep.name == *name && dep.version == *version)
+            {
+                report.errors.push(format!(
+                    "Replacement for {}@{} matches no dependency in the graph",
+                    name, version
+                ));
+            }
+        }
+
+        report
+    }
+}
+
+/// Resolve the full dependency graph of a project
+///
+/// Transitive dependencies are read from the forgekit.toml of overridden
+/// local packages and of packages already present in `vendor/`. Each package
+/// appears once; the first version reached wins.
+pub fn resolve_graph(
+    project_root: &Path,
+    config: &ProjectConfig,
+    overrides: &Overrides,
+) -> Vec<Dependency> {
+    resolve_graph_with_depth(project_root, config, overrides)
+        .into_iter()
+        .map(|(dep, _)| dep)
+        .collect()
+}
+
+/// Resolve the dependency graph like [`resolve_graph`], with the depth each
+/// package is first reached at; direct dependencies are at depth 1
+pub fn resolve_graph_with_depth(
+    project_root: &Path,
+    config: &ProjectConfig,
+    overrides: &Overrides,
+) -> Vec<(Dependency, usize)> {
+    let mut graph = Vec::new();
+    let mut seen = BTreeSet::new();
+    let mut queue: VecDeque<(Dependency, usize)> = config
+        .dependencies
+        .iter()
+        .map(|dep| (dep.clone(), 1))
+        .collect();
+
+    while let Some((dep, depth)) = queue.pop_front() {
+        if !seen.insert(dep.name.clone()) {
+            continue;
+        }
+
+        let package_dir = match overrides
+            .source_for(&dep.name, &dep.version)
+            .and_then(|source| source.path.as_deref())
+        {
+            Some(path) => project_root.join(path),
+            None => project_root
+                .join("vendor")
+                .join(format!("{}-{}", dep.name, dep.version)),
+        };
+        if let Ok(package) = ProjectConfig::load(package_dir.join("forgekit.toml")) {
+            queue.extend(package.dependencies.into_iter().map(|dep| (dep, depth + 1)));
+        }
+
+        graph.push((dep, depth));
+    }
+
+    graph
+}
+
+/// Fetch an overridden package into `dest`
+///
+/// Local directories are copied and git sources cloned at the requested
+/// branch or revision. Returns the source string recorded in the lockfile,
+/// e.g. `path+../mox-utils` or `git+https://host/repo?branch=fix#<commit>`.
+pub async fn fetch(
+    project_root: &Path,
+    source: &PatchSource,
+    dest: &Path,
+) -> Result<String, ForgeKitError> {
+    if dest.exists() {
+        std::fs::remove_dir_all(dest)?;
+    }
+
+    if let Some(path) = &source.path {
+        copy_dir(&project_root.join(path), dest)?;
+        return Ok(format!("path+{}", path));
+    }
+
+    let url = source
+        .git
+        .as_deref()
+        .ok_or_else(|| ForgeKitError::InvalidConfig("Override has no source".to_string()))?;
+    let mut clone = Command::new("git");
+    clone.arg("clone").arg("--quiet");
+    if source.rev.is_none() {
+        clone.args(["--depth", "1"]);
+    }
+    if let Some(branch) = &source.branch {
+        clone.args(["--branch", branch]);
+    }
+    // A URL starting with `-` must not be taken for an option
+    git(clone.arg("--").arg(url).arg(dest)).await?;
+
+    if let Some(rev) = &source.rev {
+        git(Command::new("git")
+            .args(["checkout", "--quiet", rev])
+            .current_dir(dest))
+        .await?;
+    }
+    let commit = git(Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .current_dir(dest))
+    .await?;
+    std::fs::remove_dir_all(dest.join(".git"))?;
+
+    let mut lock_source = format!("git+{}", url);
+    if let Some(branch) = &source.branch {
+        lock_source.push_str(&format!("?branch={}", branch));
+    }
+    Ok(format!("{}#{}", lock_source, commit.trim()))
+}
+
+/// Check that an override names exactly one source
+fn check_source(key: &str, source: &PatchSource) -> Result<(), ForgeKitError> {
+    match (&source.git, &source.path) {
+        (Some(_), Some(_)) => Err(ForgeKitError::InvalidConfig(format!(
+            "{} sets both git and path",
+            key
+        ))),
+        (None, None) => Err(ForgeKitError::InvalidConfig(format!(
+            "{} needs either git or path",
+            key
+        ))),
+        (None, Some(_)) if source.branch.is_some() || source.rev.is_some() => Err(
+            ForgeKitError::InvalidConfig(format!("{} sets branch or rev without git", key)),
+        ),
+        _ if source.branch.is_some() && source.rev.is_some() => Err(ForgeKitError::InvalidConfig(
+            format!("{} sets both branch and rev", key),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Run a git command and return its standard output
+async fn git(command: &mut Command) -> Result<String, ForgeKitError> {
+    let output = command.output().await?;
+    if !output.status.success() {
+        return Err(ForgeKitError::Registry(format!(
+            "git failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Copy a local package, skipping build output and version control data
+fn copy_dir(from: &Path, to: &Path) -> Result<(), ForgeKitError> {
+    if !from.is_dir() {
+        return Err(ForgeKitError::InvalidConfig(format!(
+            "Override path {:?} does not exist",
+            from
+        )));
+    }
+
+    let entries = walkdir::WalkDir::new(from)
+        .into_iter()
+        .filter_entry(|e| {
+            e.depth() == 0 || !matches!(e.file_name().to_str(), Some("target" | ".git"))
+        })
+        .filter_map(|e| e.ok());
+    for entry in entries {
+        let relative = entry.path().strip_prefix(from).unwrap_or(entry.path());
+        let target: PathBuf = to.join(relative);
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn dep(name: &str, version: &str) -> Dependency {
+        Dependency {
+            name: name.to_string(),
+            version: version.to_string(),
+            source: None,
+        }
+    }
+
+    fn path_source(path: &str) -> PatchSource {
+        PatchSource {
+            path: Some(path.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_replace_wins_over_patch() {
+        let mut config = ProjectConfig::default();
+        config
+            .patch
+            .insert("mox-net".to_string(), path_source("../mox-net"));
+        config
+            .replace
+            .insert("mox-net@1.0.0".to_string(), path_source("../mox-net-1"));
+        let overrides = Overrides::from_config(&config).unwrap();
+
+        assert_eq!(
+            overrides
+                .source_for("mox-net", "1.0.0")
+                .unwrap()
+                .path
+                .as_deref(),
+            Some("../mox-net-1")
+        );
+        assert_eq!(
+            overrides
+                .source_for("mox-net", "2.0.0")
+                .unwrap()
+                .path
+                .as_deref(),
+            Some("../mox-net")
+        );
+        assert!(overrides.source_for("mox-ui", "1.0.0").is_none());
+
+        config
+            .replace
+            .insert("mox-ui".to_string(), path_source("ui"));
+        assert!(Overrides::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_check_source_rejects_mixed_sources() {
+        let source = PatchSource {
+            git: Some("https://example.com/mox-net".to_string()),
+            path: Some("../mox-net".to_string()),
+            ..Default::default()
+        };
+        assert!(check_source("patch.mox-net", &source).is_err());
+        assert!(check_source(
+            "patch.mox-net",
+            &PatchSource {
+                branch: Some("fix".to_string()),
+                ..path_source("../mox-net")
+            }
+        )
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_patch_applies_transitively() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+
+        // app -> mox-ui (local patch) -> mox-net (patched as well)
+        let ui = root.join("mox-ui");
+        std::fs::create_dir_all(ui.join("src")).unwrap();
+        ProjectConfig {
+            name: "mox-ui".to_string(),
+            dependencies: vec![dep("mox-net", "1.0.0")],
+            ..Default::default()
+        }
+        .save(ui.join("forgekit.toml"))
+        .unwrap();
+        std::fs::create_dir_all(root.join("mox-net")).unwrap();
+
+        let mut config = ProjectConfig {
+            dependencies: vec![dep("mox-ui", "0.3.0")],
+            ..Default::default()
+        };
+        config
+            .patch
+            .insert("mox-ui".to_string(), path_source("mox-ui"));
+        config
+            .patch
+            .insert("mox-net".to_string(), path_source("mox-net"));
+        config
+            .patch
+            .insert("mox-db".to_string(), path_source("mox-db"));
+        let overrides = Overrides::from_config(&config).unwrap();
+
+        let graph = resolve_graph(root, &config, &overrides);
+        let names: Vec<&str> = graph.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, ["mox-ui", "mox-net"]);
+
+        let report = overrides.check(root, &graph);
+        assert_eq!(report.errors.len(), 1);
+        assert!(report.warnings[0].contains("mox-db"));
+
+        let dest = root.join("vendor").join("mox-ui-0.3.0");
+        let source = fetch(
+            root,
+            overrides.source_for("mox-ui", "0.3.0").unwrap(),
+            &dest,
+        )
+        .await
+        .unwrap();
+        assert_eq!(source, "path+mox-ui");
+        assert!(dest.join("forgekit.toml").exists());
+    }
+}
@@ -0,0 +1,370 @@
+//! Package diff module
+//!
+//! This module compares two .mox packages entry by entry. Every entry is
+//! hashed, so files that kept their size but changed are still reported.
+//! TOML manifests inside the package (forgekit.toml, permissions, locales,
+//! build info) are additionally compared key by key, which explains config
+//! changes that a plain "modified" would hide; a manifest that does not
+//! parse is compared line by line instead. The summary names the largest
+//! contributors to size growth, e.g. a new asset or a bigger binary.
+
+use crate::error::ForgeKitError;
+use crate::packager::BINARY_ENTRY;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Read;
+use std::path::Path;
+use zip::ZipArchive;
+
+/// How an entry differs between the packages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    /// Only in the new package
+    Added,
+    /// Only in the old package
+    Removed,
+    /// In both, with different contents
+    Modified,
+}
+
+impl fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChangeKind::Added => write!(f, "added"),
+            ChangeKind::Removed => write!(f, "removed"),
+            ChangeKind::Modified => write!(f, "modified"),
+        }
+    }
+}
+
+/// A changed archive entry
+#[derive(Debug, Clone, Serialize)]
+pub struct EntryChange {
+    /// Path inside the archive
+    pub path: String,
+    /// What changed
+    pub kind: ChangeKind,
+    /// Uncompressed size in the old package
+    pub old_size: Option<u64>,
+    /// Uncompressed size in the new package
+    pub new_size: Option<u64>,
+}
+
+impl EntryChange {
+    /// Size change in bytes, negative when the entry shrank
+    pub fn size_delta(&self) -> i64 {
+        self.new_size.unwrap_or(0) as i64 - self.old_size.unwrap_or(0) as i64
+    }
+}
+
+/// A changed key of a manifest inside the package
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestChange {
+    /// Manifest file inside the archive
+    pub file: String,
+    /// Dotted key, or `line N` when the manifest did not parse
+    pub key: String,
+    /// Value in the old package
+    pub old: Option<String>,
+    /// Value in the new package
+    pub new: Option<String>,
+}
+
+/// Differences between two packages
+#[derive(Debug, Clone, Serialize)]
+pub struct PackageDiff {
+    /// Size of the old package file
+    pub old_size: u64,
+    /// Size of the new package file
+    pub new_size: u64,
+    /// Changed entries, sorted by path
+    pub entries: Vec<EntryChange>,
+    /// Number of entries identical in both packages
+    pub unchanged: usize,
+    /// Changed manifest keys
+    pub manifest_changes: Vec<ManifestChange>,
+}
+
+impl PackageDiff {
+    /// Change of the package file size in bytes
+    pub fn size_delta(&self) -> i64 {
+        self.new_size as i64 - self.old_size as i64
+    }
+
+    /// Size change of the app binary, if it changed
+    pub fn binary_delta(&self) -> Option<i64> {
+        self.entries
+            .iter()
+            .find(|entry| entry.path == BINARY_ENTRY)
+            .map(EntryChange::size_delta)
+    }
+
+    /// Entries of one kind of change
+    pub fn of_kind(&self, kind: ChangeKind) -> impl Iterator<Item = &EntryChange> {
+        self.entries.iter().filter(move |entry| entry.kind == kind)
+    }
+
+    /// Changed entries that grew the most, largest first
+    pub fn largest_growth(&self, count: usize) -> Vec<&EntryChange> {
+        let mut grown: Vec<&EntryChange> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.size_delta() > 0)
+            .collect();
+        grown.sort_by_key(|entry| std::cmp::Reverse(entry.size_delta()));
+        grown.truncate(count);
+        grown
+    }
+
+    /// Whether the packages have the same contents
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Uncompressed size, hash and, for manifests, contents of an entry
+struct Entry {
+    size: u64,
+    sha256: String,
+    text: Option<String>,
+}
+
+/// Compare two packages
+pub fn diff(old: &Path, new: &Path) -> Result<PackageDiff, ForgeKitError> {
+    let old_entries = read_entries(old)?;
+    let new_entries = read_entries(new)?;
+
+    let mut entries = Vec::new();
+    let mut manifest_changes = Vec::new();
+    let mut unchanged = 0;
+    let paths: std::collections::BTreeSet<&String> =
+        old_entries.keys().chain(new_entries.keys()).collect();
+    for path in paths {
+        let (before, after) = (old_entries.get(path), new_entries.get(path));
+        let kind = match (before, after) {
+            (Some(before), Some(after)) if before.sha256 == after.sha256 => {
+                unchanged += 1;
+                continue;
+            }
+            (Some(_), Some(_)) => ChangeKind::Modified,
+            (None, _) => ChangeKind::Added,
+            (_, None) => ChangeKind::Removed,
+        };
+        entries.push(EntryChange {
+            path: path.clone(),
+            kind,
+            old_size: before.map(|e| e.size),
+            new_size: after.map(|e| e.size),
+        });
+
+        let before_text = before.and_then(|e| e.text.as_deref());
+        let after_text = after.and_then(|e| e.text.as_deref());
+        if before_text.is_some() || after_text.is_some() {
+            manifest_changes.extend(manifest_diff(path, before_text, after_text));
+        }
+    }
+
+    Ok(PackageDiff {
+        old_size: std::fs::metadata(old)?.len(),
+        new_size: std::fs::metadata(new)?.len(),
+        entries,
+        unchanged,
+        manifest_changes,
+    })
+}
+
+fn read_entries(package: &Path) -> Result<BTreeMap<String, Entry>, ForgeKitError> {
+    let mut zip = ZipArchive::new(std::fs::File::open(package)?)?;
+    let mut entries = BTreeMap::new();
+    for index in 0..zip.len() {
+        let mut file = zip.by_index(index)?;
+        if file.is_dir() {
+            continue;
+        }
+        let name = file.name().to_string();
+        let is_manifest = name.ends_with(".toml");
+
+        let mut hasher = Sha256::new();
+        let mut text = Vec::new();
+        let mut buffer = [0u8; 64 * 1024];
+        loop {
+            let read = file.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+            if is_manifest {
+                text.extend_from_slice(&buffer[..read]);
+            }
+        }
+
+        let entry = Entry {
+            size: file.size(),
+            sha256: format!("{:x}", hasher.finalize()),
+            text: is_manifest.then(|| String::from_utf8_lossy(&text).into_owned()),
+        };
+        entries.insert(name, entry);
+    }
+    Ok(entries)
+}
+
+/// Keys whose values differ between two versions of a TOML manifest
+///
+/// Falls back to the changed lines when either version is not valid TOML.
+fn manifest_diff(file: &str, old: Option<&str>, new: Option<&str>) -> Vec<ManifestChange> {
+    let flatten = |text: Option<&str>| -> Result<BTreeMap<String, String>, toml::de::Error> {
+        let mut values = BTreeMap::new();
+        if let Some(text) = text {
+            flatten_value("", &toml::from_str(text)?, &mut values);
+        }
+        Ok(values)
+    };
+    let (old, new) = match (flatten(old), flatten(new)) {
+        (Ok(old), Ok(new)) => (old, new),
+        (Err(e), _) | (_, Err(e)) => {
+            tracing::debug!("{} is not valid TOML, comparing lines: {}", file, e);
+            return line_diff(file, old.unwrap_or_default(), new.unwrap_or_default());
+        }
+    };
+
+    let keys: std::collections::BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    keys.into_iter()
+        .filter(|key| old.get(*key) != new.get(*key))
+        .map(|key| ManifestChange {
+            file: file.to_string(),
+            key: key.clone(),
+            old: old.get(key).cloned(),
+            new: new.get(key).cloned(),
+        })
+        .collect()
+}
+
+/// Lines removed from and added to a manifest, keyed by line number
+fn line_diff(file: &str, old: &str, new: &str) -> Vec<ManifestChange> {
+    similar::TextDiff::from_lines(old, new)
+        .iter_all_changes()
+        .filter_map(|change| {
+            let line = change.value().trim_end_matches(['\r', '\n']).to_string();
+            let (number, old, new) = match change.tag() {
+                similar::ChangeTag::Equal => return None,
+                similar::ChangeTag::Delete => (change.old_index()?, Some(line), None),
+                similar::ChangeTag::Insert => (change.new_index()?, None, Some(line)),
+            };
+            Some(ManifestChange {
+                file: file.to_string(),
+                key: format!("line {}", number + 1),
+                old,
+                new,
+            })
+        })
+        .collect()
+}
+
+/// Flatten tables into dotted keys; arrays are compared whole
+fn flatten_value(prefix: &str, value: &toml::Value, values: &mut BTreeMap<String, String>) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten_value(&key, value, values);
+            }
+        }
+        value => {
+            values.insert(prefix.to_string(), value.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+    use zip::{write::FileOptions, ZipWriter};
+
+    fn write_package(path: &Path, entries: &[(&str, &str)]) {
+        let mut zip = ZipWriter::new(std::fs::File::create(path).unwrap());
+        for (name, contents) in entries {
+            zip.start_file(*name, FileOptions::default()).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_diff_reports_entries_and_manifest_keys() {
+        let temp_dir = TempDir::new().unwrap();
+        let old = temp_dir.path().join("old.mox");
+        let new = temp_dir.path().join("new.mox");
+        write_package(
+            &old,
+            &[
+                (BINARY_ENTRY, "1234"),
+                ("forgekit.toml", "name = \"app\"\nversion = \"0.1.0\"\n"),
+                ("assets/old.png", "png"),
+                ("assets/same.txt", "same"),
+            ],
+        );
+        write_package(
+            &new,
+            &[
+                (BINARY_ENTRY, "12345678"),
+                ("forgekit.toml", "name = \"app\"\nversion = \"0.2.0\"\n"),
+                ("assets/new.png", "bigger png"),
+                ("assets/same.txt", "same"),
+            ],
+        );
+
+        let diff = diff(&old, &new).unwrap();
+        assert_eq!(diff.unchanged, 1);
+        assert_eq!(diff.binary_delta(), Some(4));
+        let added: Vec<&str> = diff
+            .of_kind(ChangeKind::Added)
+            .map(|e| e.path.as_str())
+            .collect();
+        assert_eq!(added, ["assets/new.png"]);
+        assert_eq!(diff.of_kind(ChangeKind::Removed).count(), 1);
+        assert_eq!(diff.largest_growth(1)[0].path, "assets/new.png");
+
+        assert_eq!(diff.manifest_changes.len(), 1);
+        let change = &diff.manifest_changes[0];
+        assert_eq!(change.key, "version");
+        assert_eq!(change.new.as_deref(), Some("\"0.2.0\""));
+    }
+
+    #[test]
+    fn test_unparsable_manifest_falls_back_to_lines() {
+        let temp_dir = TempDir::new().unwrap();
+        let old = temp_dir.path().join("old.mox");
+        let new = temp_dir.path().join("new.mox");
+        write_package(
+            &old,
+            &[("forgekit.toml", "name = \"app\"\nversion = \"0.1.0\"\n")],
+        );
+        write_package(
+            &new,
+            &[("forgekit.toml", "name = \"app\"\nversion = 0.2.0 oops\n")],
+        );
+
+        let diff = diff(&old, &new).unwrap();
+        let changes: Vec<(&str, Option<&str>, Option<&str>)> = diff
+            .manifest_changes
+            .iter()
+            .map(|c| (c.key.as_str(), c.old.as_deref(), c.new.as_deref()))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                ("line 2", Some("version = \"0.1.0\""), None),
+                ("line 2", None, Some("version = 0.2.0 oops")),
+            ]
+        );
+    }
+}
@@ -4,122 +4,577 @@
 //! for both local and remote package sources.
 
 use crate::config::{Dependency, ProjectConfig};
+use crate::dry_run::{DryRun, PlannedAction};
 use crate::error::ForgeKitError;
-use crate::registry::{RegistryClient, RegistryConfig};
+use crate::events::{Event, EventBus};
+use crate::filesystem::{FileSystem, StdFileSystem};
+use crate::journal::Journal;
+use crate::lock::{FileLock, LockOptions};
+use crate::lockfile::{LockedPackage, Lockfile, LOCKFILE_NAME};
+use crate::overrides::{self, Overrides};
+use crate::policy::{self, PolicyStage};
+use crate::registry::{ProgressCallback, RegistryClient, RegistryConfig};
+use crate::store::{hash_file, PackageStore};
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
-use tokio::fs as tokio_fs;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+/// Source that replaced all of crates.io in vendored projects before
+/// ForgeKit patched only its own packages
+const VENDORED_SOURCE: &str = "vendored-sources";
+
+/// Default number of packages downloaded at the same time
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Aggregate progress of a batch installation
+#[derive(Debug, Clone)]
+pub struct InstallProgress {
+    /// Package that just finished installing
+    pub package: String,
+    /// Number of packages installed so far
+    pub completed: usize,
+    /// Total number of packages in the batch
+    pub total: usize,
+}
+
+/// Callback invoked each time a package of a batch is installed
+pub type InstallProgressCallback = Arc<dyn Fn(&InstallProgress) + Send + Sync>;
+
+/// Result of vendoring the project's dependencies
+#[derive(Debug, Clone)]
+pub struct VendorReport {
+    /// Number of packages vendored
+    pub packages: usize,
+    /// Number of packages whose checksum matched the lockfile
+    pub verified: usize,
+    /// Cargo configuration patching the ForgeKit packages to their vendored
+    /// sources
+    pub cargo_config: PathBuf,
+}
 
 /// Package manager for ForgeKit projects
 pub struct PackageManager {
     registry_client: RegistryClient,
+    store: PackageStore,
     project_root: PathBuf,
+    progress: Option<ProgressCallback>,
+    install_progress: Option<InstallProgressCallback>,
+    concurrency: usize,
+    events: EventBus,
+    dry_run: DryRun,
+    lock: LockOptions,
+    fs: Arc<dyn FileSystem>,
 }
 
 impl PackageManager {
     /// Create a new package manager for a project
     pub fn new(project_root: PathBuf) -> Result<Self, ForgeKitError> {
         let registry_config = RegistryConfig::default();
-        let registry_client = RegistryClient::new(registry_config)?;
+        let registry_client = RegistryClient::from_login(registry_config)?;
+        let store = PackageStore::new(PackageStore::default_location())?;
 
         Ok(Self {
             registry_client,
+            store,
             project_root,
+            progress: None,
+            install_progress: None,
+            concurrency: DEFAULT_CONCURRENCY,
+            events: EventBus::new(),
+            dry_run: DryRun::disabled(),
+            lock: LockOptions::default(),
+            fs: Arc::new(StdFileSystem),
         })
     }
 
+    /// Resolve and download packages through `registry_client`
+    pub fn with_registry_client(mut self, registry_client: RegistryClient) -> Self {
+        self.registry_client = registry_client;
+        self
+    }
+
+    /// Extract packages into `store` instead of the global one
+    pub fn with_store(mut self, store: PackageStore) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// Work on the vendor directory, the package cache and the project's
+    /// Cargo configuration through `fs` instead of the disk
+    pub fn with_filesystem(mut self, fs: Arc<dyn FileSystem>) -> Self {
+        self.fs = fs;
+        self
+    }
+
+    /// Lock the project this way while changing its dependencies
+    pub fn with_lock(mut self, lock: LockOptions) -> Self {
+        self.lock = lock;
+        self
+    }
+
+    /// Record the changes to make on `dry_run` instead of making them
+    pub fn with_dry_run(mut self, dry_run: DryRun) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Report resolved dependencies on an event bus
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Report aggregate progress of batch installations to the given callback
+    pub fn with_install_progress(mut self, callback: InstallProgressCallback) -> Self {
+        self.install_progress = Some(callback);
+        self
+    }
+
+    /// Set how many packages may be downloaded concurrently
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Report download progress to the given callback
+    pub fn with_progress(mut self, callback: ProgressCallback) -> Self {
+        self.progress = Some(callback);
+        self
+    }
+
     /// Add a dependency to the project
     pub async fn add_dependency(
         &self,
         package_name: &str,
         version: &str,
     ) -> Result<(), ForgeKitError> {
-        println!("Adding dependency: {} v{}", package_name, version);
+        tracing::info!("Adding dependency: {} v{}", package_name, version);
+        let config_path = self.project_root.join("forgekit.toml");
+        let mut dependencies = if config_path.exists() {
+            ProjectConfig::load(&config_path)?.dependencies
+        } else {
+            Vec::new()
+        };
+        let declared = dependencies.iter().any(|d| d.name == package_name);
+        dependencies.retain(|d| d.name != package_name);
+        dependencies.push(Dependency {
+            name: package_name.to_string(),
+            version: version.to_string(),
+            source: Some("registry".to_string()),
+        });
+        self.check_policy(dependencies)?;
+        if self.dry_run.is_enabled() {
+            self.plan_install(package_name, version);
+            let change = if declared {
+                format!("set dependency {} to {}", package_name, version)
+            } else {
+                format!("add dependency {} {}", package_name, version)
+            };
+            self.dry_run.record(PlannedAction::UpdateFile {
+                path: self.project_root.join(LOCKFILE_NAME),
+                change: format!("lock {} {}", package_name, version),
+            });
+            self.dry_run.record(PlannedAction::UpdateFile {
+                path: self.project_root.join("forgekit.toml"),
+                change,
+            });
+            return Ok(());
+        }
+
+        let _lock = FileLock::project(&self.project_root, &self.lock, "add").await?;
 
         // Download the package
         let package_path = self
             .registry_client
-            .download_package(package_name, version)
+            .download_package_with_progress(package_name, version, self.progress.clone())
             .await?;
-        println!("Downloaded package to: {:?}", package_path);
+        tracing::info!("Downloaded package to: {:?}", package_path);
 
-        // Extract and install the package
-        self.install_package(package_name, version, &package_path)
-            .await?;
+        let mut journal = Journal::begin(&self.project_root, &format!("add {}", package_name))?;
+        let result = self
+            .apply_add(&mut journal, package_name, version, &package_path)
+            .await;
+        journal.finish(result)?;
 
-        // Update project configuration
-        self.update_project_config(package_name, version).await?;
+        self.events.emit(Event::DependencyResolved {
+            name: package_name.to_string(),
+            version: version.to_string(),
+        });
 
-        println!("Successfully added {} v{}", package_name, version);
+        tracing::info!("Successfully added {} v{}", package_name, version);
         Ok(())
     }
 
+    /// Lock, install and declare a downloaded dependency
+    async fn apply_add(
+        &self,
+        journal: &mut Journal,
+        package_name: &str,
+        version: &str,
+        package_path: &Path,
+    ) -> Result<(), ForgeKitError> {
+        // Pin the exact version and checksum
+        let mut lockfile = Lockfile::load(&self.project_root)?;
+        lockfile.upsert(LockedPackage {
+            name: package_name.to_string(),
+            version: version.to_string(),
+            source: Some("registry".to_string()),
+            checksum: Some(hash_file(package_path)?),
+        });
+        journal.track_file(&self.project_root.join(LOCKFILE_NAME))?;
+        lockfile.save(&self.project_root)?;
+
+        // Extract and install the package
+        let vendor_dir = self.project_root.join("vendor");
+        journal.stash_dir(&vendor_dir.join(format!("{}-{}", package_name, version)))?;
+        Self::install_package(
+            self.fs.as_ref(),
+            &self.store,
+            &vendor_dir,
+            package_name,
+            version,
+            package_path,
+        )
+        .await?;
+
+        // Update project configuration
+        journal.track_file(&self.project_root.join("forgekit.toml"))?;
+        self.update_project_config(package_name, version).await
+    }
+
     /// Remove a dependency from the project
     pub async fn remove_dependency(&self, package_name: &str) -> Result<(), ForgeKitError> {
-        println!("Removing dependency: {}", package_name);
+        tracing::info!("Removing dependency: {}", package_name);
+
+        if self.dry_run.is_enabled() {
+            return self.plan_remove(package_name);
+        }
+
+        let _lock = FileLock::project(&self.project_root, &self.lock, "remove").await?;
+        let mut journal = Journal::begin(&self.project_root, &format!("remove {}", package_name))?;
+        let result = self.apply_remove(&mut journal, package_name).await;
+        journal.finish(result)?;
 
+        tracing::info!("Successfully removed {}", package_name);
+        Ok(())
+    }
+
+    /// Remove a dependency from the config, the lockfile and vendor/
+    async fn apply_remove(
+        &self,
+        journal: &mut Journal,
+        package_name: &str,
+    ) -> Result<(), ForgeKitError> {
         // Remove from project config
+        journal.track_file(&self.project_root.join("forgekit.toml"))?;
         self.remove_from_config(package_name).await?;
 
-        // Remove installed files
-        let install_path = self.project_root.join("vendor").join(package_name);
-        if install_path.exists() {
-            tokio_fs::remove_dir_all(&install_path).await?;
-            println!("Removed package files from: {:?}", install_path);
+        let mut lockfile = Lockfile::load(&self.project_root)?;
+        let locked_version = lockfile.get(package_name).map(|p| p.version.clone());
+        if lockfile.remove(package_name) {
+            journal.track_file(&self.project_root.join(LOCKFILE_NAME))?;
+            lockfile.save(&self.project_root)?;
         }
 
-        println!("Successfully removed {}", package_name);
+        // Remove installed files; the journal keeps them until it is committed
+        for install_path in self.install_paths(package_name, locked_version.as_deref()) {
+            if self.fs.exists(&install_path) {
+                journal.stash_dir(&install_path)?;
+                tracing::info!("Removed package files from: {:?}", install_path);
+            }
+        }
         Ok(())
     }
 
+    /// Record the changes removing a dependency would make
+    fn plan_remove(&self, package_name: &str) -> Result<(), ForgeKitError> {
+        self.dry_run.record(PlannedAction::UpdateFile {
+            path: self.project_root.join("forgekit.toml"),
+            change: format!("remove dependency {}", package_name),
+        });
+
+        let lockfile = Lockfile::load(&self.project_root)?;
+        let locked_version = lockfile.get(package_name).map(|p| p.version.clone());
+        if locked_version.is_some() {
+            self.dry_run.record(PlannedAction::UpdateFile {
+                path: self.project_root.join(LOCKFILE_NAME),
+                change: format!("unlock {}", package_name),
+            });
+        }
+        for install_path in self.install_paths(package_name, locked_version.as_deref()) {
+            if self.fs.exists(&install_path) {
+                self.dry_run
+                    .record(PlannedAction::Remove { path: install_path });
+            }
+        }
+        Ok(())
+    }
+
+    /// Directories a dependency may be installed in
+    fn install_paths(&self, package_name: &str, locked_version: Option<&str>) -> Vec<PathBuf> {
+        let vendor_dir = self.project_root.join("vendor");
+        let mut install_paths = vec![vendor_dir.join(package_name)];
+        if let Some(version) = locked_version {
+            install_paths.push(vendor_dir.join(format!("{}-{}", package_name, version)));
+        }
+        install_paths
+    }
+
     /// Update all dependencies to their latest versions
     pub async fn update_dependencies(&self) -> Result<(), ForgeKitError> {
-        println!("Updating dependencies...");
+        tracing::info!("Updating dependencies...");
 
         let config_path = self.project_root.join("forgekit.toml");
         let config = ProjectConfig::load(&config_path)?;
 
-        for dep in config.dependencies {
-            println!("Updating {}...", dep.name);
-            // For now, we'll just reinstall the same version
-            // In a real implementation, this would resolve to latest compatible version
-            self.add_dependency(&dep.name, &dep.version).await?;
+        // For now, we'll just reinstall the same versions
+        // In a real implementation, this would resolve to latest compatible version
+        let installed = self.install_dependencies(&config.dependencies).await?;
+
+        tracing::info!("Dependencies updated successfully ({} packages)", installed);
+        Ok(())
+    }
+
+    /// Enforce the project's supply-chain policy on a set of dependencies
+    ///
+    /// The dependencies stand in for the ones of forgekit.toml, so a change
+    /// is refused before anything is downloaded.
+    fn check_policy(&self, dependencies: Vec<Dependency>) -> Result<(), ForgeKitError> {
+        let config_path = self.project_root.join("forgekit.toml");
+        let mut config = if config_path.exists() {
+            ProjectConfig::load(&config_path)?
+        } else {
+            ProjectConfig::default()
+        };
+        config.dependencies = dependencies;
+        policy::enforce(
+            &self.project_root,
+            &config,
+            &self.registry_client,
+            PolicyStage::Resolution,
+        )
+    }
+
+    /// Download and install dependencies concurrently
+    ///
+    /// Packages requested more than once (same name and version) are only
+    /// fetched a single time. At most `concurrency` downloads run at once.
+    /// Returns the number of distinct packages installed.
+    pub async fn install_dependencies(
+        &self,
+        dependencies: &[Dependency],
+    ) -> Result<usize, ForgeKitError> {
+        self.check_policy(dependencies.to_vec())?;
+        let queue = dedupe_queue(dependencies);
+        let total = queue.len();
+        if self.dry_run.is_enabled() {
+            for (name, version) in &queue {
+                self.plan_install(name, version);
+            }
+            return Ok(total);
+        }
+        let _lock = FileLock::project(&self.project_root, &self.lock, "install").await?;
+        let vendor_dir = self.project_root.join("vendor");
+        let mut journal = Journal::begin(&self.project_root, "install")?;
+        let result = self.install_queue(&mut journal, &vendor_dir, queue).await;
+        journal.finish(result)?;
+        Ok(total)
+    }
+
+    /// Download and install queued packages, stashing their old installations
+    async fn install_queue(
+        &self,
+        journal: &mut Journal,
+        vendor_dir: &Path,
+        queue: Vec<(String, String)>,
+    ) -> Result<(), ForgeKitError> {
+        let total = queue.len();
+        let semaphore = Arc::new(Semaphore::new(self.concurrency));
+        let mut tasks = JoinSet::new();
+
+        for (name, version) in queue {
+            journal.stash_dir(&vendor_dir.join(format!("{}-{}", name, version)))?;
+            let client = self.registry_client.clone();
+            let store = self.store.clone();
+            let fs = self.fs.clone();
+            let vendor_dir = vendor_dir.to_path_buf();
+            let semaphore = semaphore.clone();
+
+            tasks.spawn(async move {
+                let _permit = semaphore.acquire_owned().await.map_err(|_| {
+                    ForgeKitError::InvalidConfig("Download queue closed".to_string())
+                })?;
+                let package_path = client.download_package(&name, &version).await?;
+                Self::install_package(
+                    fs.as_ref(),
+                    &store,
+                    &vendor_dir,
+                    &name,
+                    &version,
+                    &package_path,
+                )
+                .await?;
+                Ok::<_, ForgeKitError>((name, version))
+            });
+        }
+
+        let mut completed = 0;
+        while let Some(result) = tasks.join_next().await {
+            // Dropping the JoinSet on error aborts the remaining downloads
+            let (package, version) = result.map_err(std::io::Error::from)??;
+            self.events.emit(Event::DependencyResolved {
+                name: package.clone(),
+                version,
+            });
+            completed += 1;
+            if let Some(callback) = &self.install_progress {
+                callback(&InstallProgress {
+                    package,
+                    completed,
+                    total,
+                });
+            }
         }
 
-        println!("Dependencies updated successfully");
         Ok(())
     }
 
+    /// Vendor every locked dependency for offline, hermetic builds
+    ///
+    /// Each package is downloaded into `vendor/` and its checksum verified
+    /// against the lockfile. Packages without a recorded checksum are locked
+    /// with the checksum of the downloaded archive. Cargo is then configured
+    /// to build these packages from their vendored sources; crates.io
+    /// dependencies are left to Cargo.
+    ///
+    /// Packages overridden by `[patch]` or `[replace]` are fetched from their
+    /// git or path source instead, and the lockfile records that source.
+    pub async fn vendor(&self) -> Result<VendorReport, ForgeKitError> {
+        let _lock = FileLock::project(&self.project_root, &self.lock, "vendor").await?;
+        let mut lockfile = Lockfile::load(&self.project_root)?;
+
+        let config = ProjectConfig::load(self.project_root.join("forgekit.toml"))?;
+        let overrides = Overrides::from_config(&config)?;
+        let graph = overrides::resolve_graph(&self.project_root, &config, &overrides);
+        let report = overrides.check(&self.project_root, &graph);
+        if !report.errors.is_empty() {
+            return Err(ForgeKitError::InvalidConfig(report.errors.join("; ")));
+        }
+        policy::enforce(
+            &self.project_root,
+            &config,
+            &self.registry_client,
+            PolicyStage::Resolution,
+        )?;
+
+        // Lock the dependency graph the first time a project is vendored
+        for dep in graph {
+            if lockfile.get(&dep.name).is_none() {
+                lockfile.upsert(LockedPackage {
+                    name: dep.name,
+                    version: dep.version,
+                    source: dep.source,
+                    checksum: None,
+                });
+            }
+        }
+
+        let vendor_dir = self.project_root.join("vendor");
+        let mut verified = 0;
+
+        for package in &mut lockfile.packages {
+            let package_dir = vendor_dir.join(format!("{}-{}", package.name, package.version));
+            if let Some(source) = overrides.source_for(&package.name, &package.version) {
+                package.source =
+                    Some(overrides::fetch(&self.project_root, source, &package_dir).await?);
+                package.checksum = None;
+                continue;
+            }
+
+            let archive = self
+                .registry_client
+                .download_package(&package.name, &package.version)
+                .await?;
+            let checksum = hash_file(&archive)?;
+
+            match &package.checksum {
+                Some(expected) if *expected != checksum => {
+                    return Err(ForgeKitError::ChecksumMismatch(format!(
+                        "{} v{}: expected {}, got {}",
+                        package.name, package.version, expected, checksum
+                    )));
+                }
+                Some(_) => verified += 1,
+                None => package.checksum = Some(checksum.clone()),
+            }
+
+            Self::install_package(
+                self.fs.as_ref(),
+                &self.store,
+                &vendor_dir,
+                &package.name,
+                &package.version,
+                &archive,
+            )
+            .await?;
+        }
+
+        lockfile.save(&self.project_root)?;
+        let cargo_config =
+            write_vendored_patches(self.fs.as_ref(), &self.project_root, &lockfile.packages)?;
+
+        Ok(VendorReport {
+            packages: lockfile.packages.len(),
+            verified,
+            cargo_config,
+        })
+    }
+
     /// Install a downloaded package
+    ///
+    /// The package is extracted once into the global store and linked into
+    /// the project's vendor directory.
     async fn install_package(
-        &self,
+        fs: &dyn FileSystem,
+        store: &PackageStore,
+        vendor_dir: &Path,
         name: &str,
         version: &str,
         package_path: &Path,
     ) -> Result<(), ForgeKitError> {
-        let vendor_dir = self.project_root.join("vendor");
-        tokio_fs::create_dir_all(&vendor_dir).await?;
-
         let install_path = vendor_dir.join(format!("{}-{}", name, version));
+        fs.create_dir_all(vendor_dir)?;
+
+        let store = store.clone();
+        let package_path = package_path.to_path_buf();
+        let link_path = install_path.clone();
+        tokio::task::spawn_blocking(move || {
+            let entry = store.add(&package_path)?;
+            store.link_into(&entry, &link_path)
+        })
+        .await
+        .map_err(std::io::Error::from)??;
 
-        // Extract the tar.gz file (simplified - in reality would use tar crate)
-        // For demo purposes, we'll just copy the file
-        tokio_fs::copy(package_path, install_path.join("package.tar.gz")).await?;
-
-        // Create a basic package structure
-        let src_dir = install_path.join("src");
-        tokio_fs::create_dir_all(&src_dir).await?;
-
-        let lib_rs = r#"//! Auto-generated library file
-pub fn hello() {
-    println!("Hello from {}!", env!("CARGO_PKG_NAME"));
-}
-"#;
-        tokio_fs::write(src_dir.join("lib.rs"), lib_rs).await?;
-
-        println!("Installed package to: {:?}", install_path);
+        tracing::info!("Installed package to: {:?}", install_path);
         Ok(())
     }
 
+    /// Record the download and installation of a package
+    fn plan_install(&self, name: &str, version: &str) {
+        self.dry_run.record(PlannedAction::Download {
+            package: name.to_string(),
+            version: version.to_string(),
+        });
+        self.dry_run.record(PlannedAction::CreateDir {
+            path: self
+                .project_root
+                .join("vendor")
+                .join(format!("{}-{}", name, version)),
+        });
+    }
+
     /// Update project configuration with new dependency
     async fn update_project_config(
         &self,
@@ -177,28 +632,57 @@ pub fn hello() {
     /// List all installed packages
     pub async fn list_installed(&self) -> Result<Vec<String>, ForgeKitError> {
         let vendor_dir = self.project_root.join("vendor");
-        if !vendor_dir.exists() {
+        if !self.fs.exists(&vendor_dir) {
             return Ok(vec![]);
         }
 
-        let mut packages = Vec::new();
-        let mut entries = tokio_fs::read_dir(&vendor_dir).await?;
+        let packages = self
+            .fs
+            .read_dir(&vendor_dir)?
+            .into_iter()
+            .filter(|entry| self.fs.is_dir(entry))
+            .filter_map(|entry| Some(entry.file_name()?.to_string_lossy().to_string()))
+            .collect();
+        Ok(packages)
+    }
+
+    /// Initialize global package cache
+    pub async fn init_global_cache(&self) -> Result<(), ForgeKitError> {
+        self.fs.create_dir_all(&get_global_cache_dir())
+    }
 
-        while let Some(entry) = entries.next_entry().await? {
-            if entry.file_type().await?.is_dir() {
-                let package_name = entry.file_name().to_string_lossy().to_string();
-                packages.push(package_name);
-            }
+    /// Clean global package cache
+    pub async fn clean_global_cache(&self) -> Result<(), ForgeKitError> {
+        let cache_dir = get_global_cache_dir();
+        if self.fs.exists(&cache_dir) {
+            self.fs.remove_dir_all(&cache_dir)?;
         }
+        self.init_global_cache().await
+    }
 
+    /// List globally cached packages
+    pub async fn list_cached_packages(&self) -> Result<Vec<String>, ForgeKitError> {
+        let cache_dir = get_global_cache_dir();
+        if !self.fs.exists(&cache_dir) {
+            return Ok(vec![]);
+        }
+
+        let packages = self
+            .fs
+            .read_dir(&cache_dir)?
+            .into_iter()
+            .filter(|entry| !self.fs.is_dir(entry))
+            .filter_map(|entry| Some(entry.file_name()?.to_string_lossy().to_string()))
+            .filter(|file_name| file_name.ends_with(".tar.gz"))
+            .collect();
         Ok(packages)
     }
 
     /// Update the package registry index
     pub async fn update_registry(&self) -> Result<(), ForgeKitError> {
-        println!("Updating package registry...");
+        tracing::info!("Updating package registry...");
         self.registry_client.update_index().await?;
-        println!("Registry updated successfully");
+        tracing::info!("Registry updated successfully");
         Ok(())
     }
 
@@ -222,40 +706,219 @@ pub fn get_global_cache_dir() -> PathBuf {
         .join("global-cache")
 }
 
-/// Initialize global package cache
-pub async fn init_global_cache() -> Result<(), ForgeKitError> {
-    let cache_dir = get_global_cache_dir();
-    tokio_fs::create_dir_all(&cache_dir).await?;
-    Ok(())
-}
+/// Patch the vendored ForgeKit packages into Cargo's dependency graph
+///
+/// Only `packages` are taken from `vendor/`, through `[patch.crates-io]`;
+/// other crates still come from crates.io. Existing settings in
+/// `.cargo/config.toml` are preserved, except a source replacement of all of
+/// crates.io written by earlier versions.
+fn write_vendored_patches(
+    fs: &dyn FileSystem,
+    project_root: &Path,
+    packages: &[LockedPackage],
+) -> Result<PathBuf, ForgeKitError> {
+    let cargo_dir = project_root.join(".cargo");
+    fs.create_dir_all(&cargo_dir)?;
+    let config_path = cargo_dir.join("config.toml");
+
+    let mut config: toml::Table = if fs.exists(&config_path) {
+        toml::from_str(&fs.read_to_string(&config_path)?)?
+    } else {
+        toml::Table::new()
+    };
+    let not_a_table = |key: &str| {
+        ForgeKitError::InvalidConfig(format!("`{}` in .cargo/config.toml is not a table", key))
+    };
+
+    if let Some(source) = config.get_mut("source") {
+        let source = source.as_table_mut().ok_or_else(|| not_a_table("source"))?;
+        let replaced = source
+            .get("crates-io")
+            .and_then(|crates_io| crates_io.get("replace-with"))
+            .and_then(|with| with.as_str())
+            == Some(VENDORED_SOURCE);
+        if replaced {
+            source.remove("crates-io");
+            source.remove(VENDORED_SOURCE);
+        }
+        if source.is_empty() {
+            config.remove("source");
+        }
+    }
 
-/// Clean global package cache
-pub async fn clean_global_cache() -> Result<(), ForgeKitError> {
-    let cache_dir = get_global_cache_dir();
-    if cache_dir.exists() {
-        tokio_fs::remove_dir_all(&cache_dir).await?;
+    let patch = config
+        .entry("patch")
+        .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+        .as_table_mut()
+        .ok_or_else(|| not_a_table("patch"))?
+        .entry("crates-io")
+        .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+        .as_table_mut()
+        .ok_or_else(|| not_a_table("patch.crates-io"))?;
+    for package in packages {
+        let mut path = toml::Table::new();
+        path.insert(
+            "path".to_string(),
+            toml::Value::String(format!("vendor/{}-{}", package.name, package.version)),
+        );
+        patch.insert(package.name.clone(), toml::Value::Table(path));
     }
-    init_global_cache().await
+
+    fs.write(&config_path, toml::to_string_pretty(&config)?.as_bytes())?;
+    Ok(config_path)
+}
+
+/// Build the download queue, dropping repeated name/version pairs
+fn dedupe_queue(dependencies: &[Dependency]) -> Vec<(String, String)> {
+    let mut seen = HashSet::new();
+    dependencies
+        .iter()
+        .map(|dep| (dep.name.clone(), dep.version.clone()))
+        .filter(|entry| seen.insert(entry.clone()))
+        .collect()
 }
 
-/// List globally cached packages
-pub async fn list_cached_packages() -> Result<Vec<String>, ForgeKitError> {
-    let cache_dir = get_global_cache_dir();
-    if !cache_dir.exists() {
-        return Ok(vec![]);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dep(name: &str, version: &str) -> Dependency {
+        Dependency {
+            name: name.to_string(),
+            version: version.to_string(),
+            source: None,
+        }
+    }
+
+    #[test]
+    fn test_dedupe_queue() {
+        let deps = vec![
+            dep("forgekit-http", "0.1.0"),
+            dep("forgekit-serde", "0.1.0"),
+            dep("forgekit-http", "0.1.0"),
+            dep("forgekit-http", "0.2.0"),
+        ];
+
+        let queue = dedupe_queue(&deps);
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue[0].0, "forgekit-http");
+        assert_eq!(queue[2].1, "0.2.0");
     }
 
-    let mut packages = Vec::new();
-    let mut entries = tokio_fs::read_dir(&cache_dir).await?;
+    #[test]
+    fn test_vendored_patches_only_cover_forgekit_packages() {
+        let fs = crate::filesystem::MemoryFileSystem::new();
+        let project = Path::new("/project");
+        let config_path = project.join(".cargo/config.toml");
+        fs.write(
+            &config_path,
+            b"[build]\njobs = 4\n\n[source.crates-io]\nreplace-with = \"vendored-sources\"\n\n[source.vendored-sources]\ndirectory = \"vendor\"\n",
+        )
+        .unwrap();
+        let packages = vec![LockedPackage {
+            name: "forgekit-http".to_string(),
+            version: "0.2.0".to_string(),
+            source: None,
+            checksum: None,
+        }];
+
+        let path = write_vendored_patches(&fs, project, &packages).unwrap();
+        let config: toml::Table = toml::from_str(&fs.read_to_string(&path).unwrap()).unwrap();
+
+        assert_eq!(config["build"]["jobs"].as_integer(), Some(4));
+        assert!(config.get("source").is_none());
+        assert_eq!(
+            config["patch"]["crates-io"]["forgekit-http"]["path"].as_str(),
+            Some("vendor/forgekit-http-0.2.0")
+        );
+    }
 
-    while let Some(entry) = entries.next_entry().await? {
-        if entry.file_type().await?.is_file() {
-            let file_name = entry.file_name().to_string_lossy().to_string();
-            if file_name.ends_with(".tar.gz") {
-                packages.push(file_name);
+    #[tokio::test]
+    async fn test_dry_run_leaves_project_untouched() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("forgekit.toml");
+        ProjectConfig::default().save(&config_path).unwrap();
+        let before = std::fs::read_to_string(&config_path).unwrap();
+
+        let dry_run = DryRun::enabled();
+        let package_manager = PackageManager::new(temp_dir.path().to_path_buf())
+            .unwrap()
+            .with_dry_run(dry_run.clone());
+        package_manager
+            .add_dependency("forgekit-http", "0.1.0")
+            .await
+            .unwrap();
+
+        assert_eq!(std::fs::read_to_string(&config_path).unwrap(), before);
+        assert!(!temp_dir.path().join(LOCKFILE_NAME).exists());
+        assert!(!temp_dir.path().join("vendor").exists());
+        let actions = dry_run.plan().actions;
+        assert_eq!(
+            actions[0],
+            PlannedAction::Download {
+                package: "forgekit-http".to_string(),
+                version: "0.1.0".to_string(),
             }
-        }
+        );
+        assert_eq!(actions.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_installed_packages_are_read_through_the_filesystem() {
+        let root = PathBuf::from("/project");
+        let fs = Arc::new(crate::filesystem::MemoryFileSystem::new());
+        fs.write(&root.join("vendor/json-1.0.0/src/lib.rs"), b"")
+            .unwrap();
+        fs.write(&root.join("vendor/README"), b"").unwrap();
+
+        let package_manager = PackageManager::new(root)
+            .unwrap()
+            .with_filesystem(fs.clone());
+        assert_eq!(
+            package_manager.list_installed().await.unwrap(),
+            vec!["json-1.0.0"]
+        );
     }
 
-    Ok(packages)
+    #[tokio::test]
+    async fn test_add_and_install_from_mock_registry() {
+        use crate::registry::mock::MockRegistry;
+
+        let registry = MockRegistry::start().await.unwrap();
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project = temp_dir.path().join("app");
+        std::fs::create_dir_all(&project).unwrap();
+        ProjectConfig::default()
+            .save(project.join("forgekit.toml"))
+            .unwrap();
+        let package_manager = PackageManager::new(project.clone())
+            .unwrap()
+            .with_registry_client(registry.client(&temp_dir.path().join("client")).unwrap())
+            .with_store(PackageStore::new(temp_dir.path().join("store")).unwrap());
+
+        let found = package_manager.search_packages("forgekit").await.unwrap();
+        assert_eq!(found.len(), 3);
+        package_manager
+            .add_dependency("forgekit-http", "0.2.0")
+            .await
+            .unwrap();
+        assert!(project
+            .join("vendor")
+            .join("forgekit-http-0.2.0")
+            .join("src")
+            .join("lib.rs")
+            .exists());
+        let lockfile = Lockfile::load(&project).unwrap();
+        assert!(lockfile.packages.iter().any(|p| p.name == "forgekit-http"));
+
+        let installed = package_manager
+            .install_dependencies(&[
+                dep("forgekit-json", "0.3.1"),
+                dep("forgekit-ui-kit", "1.0.0"),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(installed, 2);
+        assert_eq!(package_manager.list_installed().await.unwrap().len(), 3);
+    }
 }
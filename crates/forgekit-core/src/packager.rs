@@ -1,14 +1,73 @@
-//! Project packaging into .mox format
+//! Project packaging into .mox format (or .moxlib for libraries)
 
-use crate::config::ProjectConfig;
+use crate::appmeta::APPMETA_DIR;
+use crate::artifacts;
+use crate::builder::{BuildInfo, BUILD_INFO};
+use crate::cancel::{self, CancellationToken};
+use crate::config::{ProjectConfig, ProjectKind};
+use crate::dry_run::{DryRun, PlannedAction};
 use crate::error::ForgeKitError;
+use crate::events::{Event, EventBus};
+use crate::hooks::{run_hooks, HookStage};
+use crate::i18n::{self, LocaleManifest, LOCALES_MANIFEST};
+use crate::moxlib;
+use crate::ota::{self, OTA_MANIFEST};
+use crate::permissions::PERMISSIONS_MANIFEST;
+use crate::platform;
+use crate::policy::{self, Policy, PolicyStage};
+use crate::registry::{RegistryClient, RegistryConfig};
+use crate::symbols;
+use crate::ui;
+use crate::version_manager::VersionManager;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use tokio::fs;
 use zip::{write::FileOptions, ZipWriter};
 
+/// Archive entry holding the app binary
+pub const BINARY_ENTRY: &str = "app.bin";
+
 /// Package a built project into a .mox file
 pub async fn package(project_path: &Path) -> Result<PathBuf, ForgeKitError> {
+    package_with_cancel(project_path, &CancellationToken::new()).await
+}
+
+/// Options for packaging a project
+#[derive(Debug, Clone, Default)]
+pub struct PackageOptions {
+    /// Locales bundled into the package; all locales when empty
+    pub locales: Vec<String>,
+    /// Token that interrupts packaging
+    pub cancel: CancellationToken,
+    /// Bus receiving packaging events
+    pub events: EventBus,
+    /// Only record the package that would be written
+    pub dry_run: DryRun,
+    /// Update channel to build for, instead of the one in `[ota]`
+    pub channel: Option<String>,
+}
+
+/// Package a built project, stopping early if `cancel` is triggered
+pub async fn package_with_cancel(
+    project_path: &Path,
+    cancel: &CancellationToken,
+) -> Result<PathBuf, ForgeKitError> {
+    let options = PackageOptions {
+        cancel: cancel.clone(),
+        ..Default::default()
+    };
+    package_with_options(project_path, &options).await
+}
+
+/// Package a built project with custom options
+///
+/// A partially written archive is removed when packaging is cancelled or
+/// fails.
+pub async fn package_with_options(
+    project_path: &Path,
+    options: &PackageOptions,
+) -> Result<PathBuf, ForgeKitError> {
+    let cancel = &options.cancel;
     tracing::info!("Packaging project at {:?}", project_path);
 
     // Check if project exists
@@ -18,16 +77,62 @@ pub async fn package(project_path: &Path) -> Result<PathBuf, ForgeKitError> {
         ));
     }
 
-    // Load project config
+    // Load project config for the environment the binary was built with
     let config_path = project_path.join("forgekit.toml");
-    let config = ProjectConfig::load(&config_path)?;
+    let build_info = BuildInfo::load(project_path)?;
+    let environment = build_info
+        .as_ref()
+        .and_then(|info| info.environment.clone());
+    let mut config = ProjectConfig::load(&config_path)?.resolve(environment.as_deref())?;
+    // The packaged forgekit.toml carries what's new for devices and app stores
+    if config.release_notes.is_none() {
+        config.release_notes = VersionManager::notes_for(project_path, &config.version)?;
+    }
+    // Release rules of the supply-chain policy apply to what gets packaged;
+    // without a policy there is no need for the registry
+    if Policy::path(project_path).exists() {
+        let registry = RegistryClient::from_login(RegistryConfig::default())?;
+        policy::enforce(project_path, &config, &registry, PolicyStage::Release)?;
+    }
+
+    if options.dry_run.is_enabled() {
+        return Ok(plan_package(project_path, &config, &options.dry_run));
+    }
+
+    run_hooks(project_path, &config, HookStage::PrePackage, &[]).await?;
+
+    if config.kind == ProjectKind::Library {
+        let moxlib_path = moxlib::package(project_path, &config, cancel).await?;
+        record_artifact(project_path, &config, &moxlib_path, environment.as_deref()).await;
+        let moxlib_env = [(
+            "FORGEKIT_MOX_PATH",
+            moxlib_path.to_string_lossy().to_string(),
+        )];
+        run_hooks(project_path, &config, HookStage::PostPackage, &moxlib_env).await?;
+
+        options.events.emit(Event::PackageCreated {
+            project_path: project_path.to_path_buf(),
+            package: moxlib_path.clone(),
+        });
+        tracing::info!("Library package created at {:?}", moxlib_path);
+        return Ok(moxlib_path);
+    }
 
     // Check if binary exists
-    let binary_path = project_path
-        .join("target")
-        .join("ledokoz")
-        .join("release")
-        .join(&config.name);
+    let target = build_info
+        .as_ref()
+        .map_or(platform::TARGET, |info| info.target());
+    if target != platform::TARGET {
+        tracing::warn!(
+            "Packaging a build for {}; the package is for local testing and will not run on Ledokoz OS",
+            target
+        );
+    }
+    let target_dir = build_info.as_ref().map_or_else(
+        || project_path.join("target"),
+        |info| info.target_dir(project_path),
+    );
+    let binary_path = platform::app_binary(&target_dir, target, &config.name);
     if !binary_path.exists() {
         return Err(ForgeKitError::PackagingFailed(
             "Binary not found. Please build the project first.".to_string(),
@@ -38,36 +143,187 @@ pub async fn package(project_path: &Path) -> Result<PathBuf, ForgeKitError> {
     let output_dir = project_path.join(&config.build.output_dir);
     fs::create_dir_all(&output_dir).await?;
 
-    // Create .mox file path
-    let mox_filename = format!("{}.mox", config.name);
-    let mox_path = output_dir.join(&mox_filename);
+    let mox_path = output_path(project_path, &config);
+
+    // Create ZIP archive, removing it again if it cannot be completed
+    if let Err(e) = write_archive(project_path, &config, &binary_path, &mox_path, options).await {
+        let _ = fs::remove_file(&mox_path).await;
+        return Err(e);
+    }
+    record_artifact(project_path, &config, &mox_path, environment.as_deref()).await;
 
-    // Create ZIP archive
-    let file = std::fs::File::create(&mox_path)?;
+    let mox_env = [("FORGEKIT_MOX_PATH", mox_path.to_string_lossy().to_string())];
+    run_hooks(project_path, &config, HookStage::PostPackage, &mox_env).await?;
+
+    options.events.emit(Event::PackageCreated {
+        project_path: project_path.to_path_buf(),
+        package: mox_path.clone(),
+    });
+    tracing::info!("Package created at {:?}", mox_path);
+    Ok(mox_path)
+}
+
+/// Where packaging writes the .mox (or .moxlib) of a project
+pub fn output_path(project_path: &Path, config: &ProjectConfig) -> PathBuf {
+    match config.kind {
+        ProjectKind::Library => moxlib::archive_path(project_path, config),
+        _ => project_path
+            .join(&config.build.output_dir)
+            .join(format!("{}.mox", config.name)),
+    }
+}
+
+/// Keep a copy of a new package in the artifact history
+///
+/// The package itself is already written, so failures are only logged.
+async fn record_artifact(
+    project_path: &Path,
+    config: &ProjectConfig,
+    package: &Path,
+    environment: Option<&str>,
+) {
+    let profile = environment.unwrap_or(artifacts::DEFAULT_PROFILE);
+    let keep = config.build.artifacts.keep;
+    if let Err(e) = artifacts::record(project_path, package, &config.version, profile, keep).await {
+        tracing::warn!("Failed to record artifact: {}", e);
+    }
+}
+
+/// Record the hooks and files packaging would run and write
+fn plan_package(project_path: &Path, config: &ProjectConfig, dry_run: &DryRun) -> PathBuf {
+    let package_path = output_path(project_path, config);
+
+    for command in &config.hooks.pre_package {
+        dry_run.record(PlannedAction::RunCommand {
+            command: command.clone(),
+        });
+    }
+    if config.kind == ProjectKind::Library && project_path.join("Cargo.toml").exists() {
+        dry_run.record(PlannedAction::RunCommand {
+            command: "cargo doc --no-deps".to_string(),
+        });
+    }
+    if let Some(output_dir) = package_path.parent().filter(|dir| !dir.exists()) {
+        dry_run.record(PlannedAction::CreateDir {
+            path: output_dir.to_path_buf(),
+        });
+    }
+    dry_run.record(PlannedAction::WriteFile {
+        path: package_path.clone(),
+    });
+    for command in &config.hooks.post_package {
+        dry_run.record(PlannedAction::RunCommand {
+            command: command.clone(),
+        });
+    }
+    package_path
+}
+
+/// Write the .mox archive
+async fn write_archive(
+    project_path: &Path,
+    config: &ProjectConfig,
+    binary_path: &Path,
+    mox_path: &Path,
+    package_options: &PackageOptions,
+) -> Result<(), ForgeKitError> {
+    let cancel = &package_options.cancel;
+    // Locales are checked first so an unknown `--locales` entry fails fast
+    let locales = i18n::select_locales(
+        i18n::discover_locales(project_path)?,
+        &package_options.locales,
+    )?;
+
+    let ota_manifest = if config.ota.is_empty() && package_options.channel.is_none() {
+        None
+    } else {
+        let channel = ota::resolve_channel(config, package_options.channel.as_deref())?;
+        Some(ota::manifest(project_path, config, &channel)?)
+    };
+
+    let file = std::fs::File::create(mox_path)?;
     let mut zip = ZipWriter::new(file);
     let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
 
     // Add binary to archive
-    let binary_data = fs::read(&binary_path).await?;
-    zip.start_file("app.bin", options)?;
-    zip.write_all_data(&binary_data)?;
+    zip.start_file(BINARY_ENTRY, options)?;
+    copy_file(&mut zip, binary_path)?;
 
     // Add config to archive
-    let config_data = toml::to_string_pretty(&config)?;
+    let config_data = toml::to_string_pretty(config)?;
     zip.start_file("forgekit.toml", options)?;
     zip.write_all_data(config_data.as_bytes())?;
 
+    // Record how the binary was built, e.g. which environment it targets
+    if let Some(info) = BuildInfo::load(project_path)? {
+        zip.start_file(BUILD_INFO, options)?;
+        zip.write_all_data(toml::to_string_pretty(&info)?.as_bytes())?;
+    }
+
+    // Permissions are always written, so an empty file means "deny everything"
+    let permissions = toml::to_string_pretty(&config.permissions)?;
+    zip.start_file(PERMISSIONS_MANIFEST, options)?;
+    zip.write_all_data(permissions.as_bytes())?;
+
+    // Tell the installed app which channel it follows and which feed to trust
+    if let Some(manifest) = &ota_manifest {
+        zip.start_file(OTA_MANIFEST, options)?;
+        zip.write_all_data(toml::to_string_pretty(manifest)?.as_bytes())?;
+    }
+    cancel::check(cancel, "package")?;
+
     // Add assets if they exist
     let assets_path = project_path.join("assets");
     if assets_path.exists() {
-        add_assets_to_zip(&mut zip, &assets_path, options)?;
+        add_assets_to_zip(&mut zip, &assets_path, options, cancel)?;
+    }
+
+    // Add launcher metadata generated by `forgekit appmeta`
+    let meta_path = project_path.join(APPMETA_DIR);
+    for entry in walkdir::WalkDir::new(&meta_path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        cancel::check(cancel, "package")?;
+        let name = entry
+            .path()
+            .strip_prefix(&meta_path)
+            .map_err(|_| ForgeKitError::PackagingFailed("Failed to strip prefix".to_string()))?;
+        let zip_path = format!("{}/{}", APPMETA_DIR, platform::archive_path(name));
+        zip.start_file(zip_path, options)?;
+        copy_file(&mut zip, entry.path())?;
+    }
+
+    // Add one pack per locale, stored as-is since packs are compressed
+    if !locales.is_empty() {
+        let stored = options.compression_method(zip::CompressionMethod::Stored);
+        let mut manifest = LocaleManifest::default();
+        for (locale, files) in &locales {
+            cancel::check(cancel, "package")?;
+            let (pack, data) = i18n::build_locale_pack(project_path, locale, files)?;
+            zip.start_file(&pack.path, stored)?;
+            zip.write_all_data(&data)?;
+            manifest.packs.push(pack);
+        }
+        zip.start_file(LOCALES_MANIFEST, options)?;
+        zip.write_all_data(toml::to_string_pretty(&manifest)?.as_bytes())?;
+    }
+
+    // Add compiled UI layouts
+    for layout in ui::compile_project(project_path)? {
+        cancel::check(cancel, "package")?;
+        let name = layout
+            .output
+            .strip_prefix(ui::compiled_dir(project_path))
+            .map_err(|_| ForgeKitError::PackagingFailed("Failed to strip prefix".to_string()))?;
+        zip.start_file(format!("ui/{}", platform::archive_path(name)), options)?;
+        copy_file(&mut zip, &layout.output)?;
     }
 
     // Finish ZIP
     zip.finish()?;
-
-    tracing::info!("Package created at {:?}", mox_path);
-    Ok(mox_path)
+    Ok(())
 }
 
 /// Recursively add assets to the ZIP archive
@@ -75,6 +331,7 @@ fn add_assets_to_zip(
     zip: &mut ZipWriter<std::fs::File>,
     assets_path: &Path,
     options: FileOptions,
+    cancel: &CancellationToken,
 ) -> Result<(), ForgeKitError> {
     // Use synchronous file operations to avoid async recursion issues
     let entries = std::fs::read_dir(assets_path)?;
@@ -86,19 +343,39 @@ fn add_assets_to_zip(
             .strip_prefix(assets_path)
             .map_err(|_| ForgeKitError::PackagingFailed("Failed to strip prefix".to_string()))?;
 
+        cancel::check(cancel, "package")?;
+        // Debug info stays out of the package; see `forgekit symbols upload`
+        if symbols::is_debug_artifact(&path) {
+            continue;
+        }
         if path.is_file() {
-            let data = std::fs::read(&path)?;
-            let zip_path = format!("assets/{}", name.to_string_lossy());
+            let zip_path = format!("assets/{}", platform::archive_path(name));
             zip.start_file(&zip_path, options)?;
-            zip.write_all_data(&data)?;
+            copy_file(zip, &path)?;
         } else if path.is_dir() {
-            add_assets_to_zip(zip, &path, options)?;
+            add_assets_to_zip(zip, &path, options, cancel)?;
         }
     }
 
     Ok(())
 }
 
+/// Size of the chunks files are streamed into an archive with
+const COPY_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Stream a file into the archive entry started last
+///
+/// The file is read in chunks rather than loaded whole, so packaging a
+/// multi-hundred-MB binary does not hold it in memory.
+pub(crate) fn copy_file<W: Write + std::io::Seek>(
+    zip: &mut ZipWriter<W>,
+    path: &Path,
+) -> Result<u64, ForgeKitError> {
+    let file = std::fs::File::open(path)?;
+    let mut reader = std::io::BufReader::with_capacity(COPY_BUFFER_SIZE, file);
+    Ok(std::io::copy(&mut reader, zip)?)
+}
+
 trait WriteAll {
     fn write_all_data(&mut self, data: &[u8]) -> Result<(), std::io::Error>;
 }
@@ -109,3 +386,34 @@ impl WriteAll for ZipWriter<std::fs::File> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_copy_file_streams_across_chunks() {
+        let temp_dir = TempDir::new().unwrap();
+        let binary = temp_dir.path().join("app");
+        let data: Vec<u8> = (0..COPY_BUFFER_SIZE * 3 + 17)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        std::fs::write(&binary, &data).unwrap();
+
+        let archive = temp_dir.path().join("app.mox");
+        let mut zip = ZipWriter::new(std::fs::File::create(&archive).unwrap());
+        zip.start_file("app.bin", FileOptions::default()).unwrap();
+        assert_eq!(copy_file(&mut zip, &binary).unwrap(), data.len() as u64);
+        zip.finish().unwrap();
+
+        let mut zip = zip::ZipArchive::new(std::fs::File::open(&archive).unwrap()).unwrap();
+        let mut contents = Vec::new();
+        zip.by_name("app.bin")
+            .unwrap()
+            .read_to_end(&mut contents)
+            .unwrap();
+        assert_eq!(contents, data);
+    }
+}
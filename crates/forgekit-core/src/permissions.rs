@@ -0,0 +1,269 @@
+//! App permissions module
+//!
+//! This module handles the `[permissions]` section of forgekit.toml. The
+//! packager embeds the requested permissions in the .mox archive as
+//! `permissions.toml`, which Ledokoz OS enforces at install time. Because a
+//! missing permission only shows up as a runtime failure on the device, the
+//! validator cross-checks the declaration against heuristics: crates in
+//! Cargo.toml and API paths in the sources that imply a permission.
+
+use crate::config::PermissionsConfig;
+use crate::error::ForgeKitError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use walkdir::WalkDir;
+
+/// Name of the permissions manifest inside a .mox archive
+pub const PERMISSIONS_MANIFEST: &str = "permissions.toml";
+
+/// A permission an app can request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Permission {
+    /// Network access
+    Network,
+    /// Filesystem access outside the app data directory
+    Filesystem,
+    /// Camera access
+    Camera,
+    /// Microphone access
+    Microphone,
+    /// Location access
+    Location,
+    /// Bluetooth access
+    Bluetooth,
+    /// Showing notifications
+    Notifications,
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Permission::Network => "network",
+            Permission::Filesystem => "filesystem",
+            Permission::Camera => "camera",
+            Permission::Microphone => "microphone",
+            Permission::Location => "location",
+            Permission::Bluetooth => "bluetooth",
+            Permission::Notifications => "notifications",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl std::str::FromStr for Permission {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Permission::ALL
+            .into_iter()
+            .find(|permission| permission.to_string() == s)
+            .ok_or_else(|| format!("unknown permission '{}'", s))
+    }
+}
+
+impl Permission {
+    /// Every permission, in declaration order
+    pub const ALL: [Permission; 7] = [
+        Permission::Network,
+        Permission::Filesystem,
+        Permission::Camera,
+        Permission::Microphone,
+        Permission::Location,
+        Permission::Bluetooth,
+        Permission::Notifications,
+    ];
+}
+
+/// Crates whose use implies a permission
+const CRATE_HINTS: &[(&str, Permission)] = &[
+    ("reqwest", Permission::Network),
+    ("hyper", Permission::Network),
+    ("ureq", Permission::Network),
+    ("surf", Permission::Network),
+    ("isahc", Permission::Network),
+    ("tungstenite", Permission::Network),
+    ("tokio-tungstenite", Permission::Network),
+    ("nokhwa", Permission::Camera),
+    ("v4l", Permission::Camera),
+    ("cpal", Permission::Microphone),
+    ("btleplug", Permission::Bluetooth),
+    ("bluer", Permission::Bluetooth),
+    ("notify-rust", Permission::Notifications),
+];
+
+/// Source paths whose use implies a permission
+const SOURCE_HINTS: &[(&str, Permission)] = &[
+    ("std::net::", Permission::Network),
+    ("TcpStream", Permission::Network),
+    ("UdpSocket", Permission::Network),
+    ("ledokoz::net", Permission::Network),
+    ("ledokoz::camera", Permission::Camera),
+    ("ledokoz::audio::capture", Permission::Microphone),
+    ("ledokoz::location", Permission::Location),
+    ("ledokoz::bluetooth", Permission::Bluetooth),
+    ("ledokoz::notifications", Permission::Notifications),
+];
+
+/// Whether a permission is requested by the configuration
+pub fn is_granted(permissions: &PermissionsConfig, permission: Permission) -> bool {
+    match permission {
+        Permission::Network => permissions.network,
+        Permission::Filesystem => !permissions.filesystem.is_empty(),
+        Permission::Camera => permissions.camera,
+        Permission::Microphone => permissions.microphone,
+        Permission::Location => permissions.location,
+        Permission::Bluetooth => permissions.bluetooth,
+        Permission::Notifications => permissions.notifications,
+    }
+}
+
+/// Permissions requested by the configuration
+pub fn requested(permissions: &PermissionsConfig) -> Vec<Permission> {
+    Permission::ALL
+        .into_iter()
+        .filter(|permission| is_granted(permissions, *permission))
+        .collect()
+}
+
+/// Permissions the project appears to need, with the evidence for each
+pub fn detect_usage(project_path: &Path) -> Result<BTreeMap<Permission, String>, ForgeKitError> {
+    let mut usage = BTreeMap::new();
+
+    let cargo_toml = project_path.join("Cargo.toml");
+    if cargo_toml.exists() {
+        let manifest: toml::Table = toml::from_str(&std::fs::read_to_string(&cargo_toml)?)?;
+        for name in dependency_names(&manifest) {
+            if let Some((_, permission)) = CRATE_HINTS.iter().find(|(hint, _)| *hint == name) {
+                usage
+                    .entry(*permission)
+                    .or_insert_with(|| format!("depends on `{}`", name));
+            }
+        }
+    }
+
+    let src = project_path.join("src");
+    for entry in WalkDir::new(&src)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "rs"))
+    {
+        let contents = std::fs::read_to_string(entry.path())?;
+        let relative = entry
+            .path()
+            .strip_prefix(project_path)
+            .unwrap_or(entry.path());
+        for (hint, permission) in SOURCE_HINTS {
+            if contents.contains(hint) {
+                usage
+                    .entry(*permission)
+                    .or_insert_with(|| format!("{} uses `{}`", relative.display(), hint));
+            }
+        }
+    }
+
+    Ok(usage)
+}
+
+/// Compare declared permissions with detected usage
+///
+/// Returns warnings for permissions that seem to be missing and for
+/// permissions that are requested but never used.
+pub fn check(
+    project_path: &Path,
+    permissions: &PermissionsConfig,
+) -> Result<Vec<String>, ForgeKitError> {
+    let usage = detect_usage(project_path)?;
+    let mut warnings = Vec::new();
+
+    for (permission, evidence) in &usage {
+        if !is_granted(permissions, *permission) {
+            warnings.push(format!(
+                "Missing '{}' permission: {}; add it to [permissions] or the app will be denied at runtime",
+                permission, evidence
+            ));
+        }
+    }
+
+    for permission in [
+        Permission::Network,
+        Permission::Camera,
+        Permission::Microphone,
+        Permission::Location,
+        Permission::Bluetooth,
+        Permission::Notifications,
+    ] {
+        if is_granted(permissions, permission) && !usage.contains_key(&permission) {
+            warnings.push(format!(
+                "Permission '{}' is requested but no use of it was found",
+                permission
+            ));
+        }
+    }
+
+    Ok(warnings)
+}
+
+/// Names of all dependencies in a Cargo.toml, including target-specific ones
+fn dependency_names(manifest: &toml::Table) -> Vec<String> {
+    let mut tables = vec![manifest.get("dependencies")];
+    if let Some(targets) = manifest.get("target").and_then(|t| t.as_table()) {
+        tables.extend(targets.values().map(|target| target.get("dependencies")));
+    }
+
+    tables
+        .into_iter()
+        .flatten()
+        .filter_map(|deps| deps.as_table())
+        .flat_map(|deps| {
+            deps.iter().map(|(name, spec)| {
+                // Renamed dependencies keep the real crate name in `package`
+                spec.get("package")
+                    .and_then(|p| p.as_str())
+                    .unwrap_or(name)
+                    .to_string()
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_check_permissions() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path();
+        std::fs::write(
+            project.join("Cargo.toml"),
+            "[package]\nname = \"demo\"\n\n[dependencies]\nhttp = { package = \"reqwest\", version = \"0.11\" }\n",
+        )
+        .unwrap();
+        std::fs::create_dir_all(project.join("src")).unwrap();
+        std::fs::write(
+            project.join("src").join("main.rs"),
+            "use ledokoz::camera::Camera;\nfn main() {}\n",
+        )
+        .unwrap();
+
+        let usage = detect_usage(project).unwrap();
+        assert_eq!(
+            usage.keys().copied().collect::<Vec<_>>(),
+            [Permission::Network, Permission::Camera]
+        );
+
+        let permissions = PermissionsConfig {
+            camera: true,
+            location: true,
+            ..Default::default()
+        };
+        let warnings = check(project, &permissions).unwrap();
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("'network'") && warnings[0].contains("reqwest"));
+        assert!(warnings[1].contains("'location'"));
+    }
+}
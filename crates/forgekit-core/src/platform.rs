@@ -0,0 +1,111 @@
+//! Platform module
+//!
+//! This module holds the conventions that differ between the host forgekit
+//! runs on and the Ledokoz target it builds for, so modules do not assemble
+//! them by hand. Build artifacts live under `target/ledokoz/release`, app
+//! binaries may or may not carry the host's `.exe` suffix depending on the
+//! toolchain, archive entries always use `/`, and text written on Windows
+//! may come back with CRLF line endings.
+
+use std::borrow::Cow;
+use std::path::{Component, Path, PathBuf};
+
+/// Target passed to cargo when building for Ledokoz
+pub const TARGET: &str = "ledokoz";
+
+/// Directory holding the release artifacts of a target directory
+pub fn release_dir(target_dir: &Path) -> PathBuf {
+    target_release_dir(target_dir, TARGET)
+}
+
+/// Directory holding the release artifacts built for `target`
+///
+/// Host builds for local testing land here instead of in [`release_dir`].
+pub fn target_release_dir(target_dir: &Path, target: &str) -> PathBuf {
+    target_dir.join(target).join("release")
+}
+
+/// Path of the app binary a build for `target` produced
+///
+/// Ledokoz binaries have no extension, but toolchains running on Windows
+/// hosts may add `.exe`. The suffixed file is returned only when it exists
+/// and the plain one does not.
+pub fn app_binary(target_dir: &Path, target: &str, name: &str) -> PathBuf {
+    binary_with_suffix(
+        &target_release_dir(target_dir, target),
+        name,
+        std::env::consts::EXE_SUFFIX,
+    )
+}
+
+fn binary_with_suffix(dir: &Path, name: &str, suffix: &str) -> PathBuf {
+    let plain = dir.join(name);
+    let suffixed = dir.join(format!("{}{}", name, suffix));
+    if !suffix.is_empty() && !plain.exists() && suffixed.exists() {
+        suffixed
+    } else {
+        plain
+    }
+}
+
+/// Name of a file inside a .mox, .moxlib or locale archive
+///
+/// Entries are separated by `/` on every host. Backslashes, which Windows
+/// paths may carry, become `/` as well, and `.` components are dropped.
+pub fn archive_path(relative: &Path) -> String {
+    relative
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy().replace('\\', "/")),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Text with CRLF line endings turned into LF
+pub fn normalize_newlines(text: &str) -> Cow<'_, str> {
+    if text.contains("\r\n") {
+        Cow::Owned(text.replace("\r\n", "\n"))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_archive_path_uses_forward_slashes() {
+        let nested = Path::new("ui").join("screens").join("main.ui");
+        assert_eq!(archive_path(&nested), "ui/screens/main.ui");
+        assert_eq!(archive_path(Path::new("icons\\app.png")), "icons/app.png");
+        assert_eq!(archive_path(&Path::new(".").join("fr.ftl")), "fr.ftl");
+        assert_eq!(normalize_newlines("a\r\nb\n"), "a\nb\n");
+    }
+
+    #[test]
+    fn test_app_binary_with_host_suffix() {
+        let temp_dir = TempDir::new().unwrap();
+        let release = release_dir(temp_dir.path());
+        assert!(release.ends_with(Path::new("ledokoz").join("release")));
+
+        std::fs::create_dir_all(&release).unwrap();
+        assert_eq!(
+            binary_with_suffix(&release, "app", ".exe"),
+            release.join("app")
+        );
+        std::fs::write(release.join("app.exe"), b"").unwrap();
+        assert_eq!(
+            binary_with_suffix(&release, "app", ".exe"),
+            release.join("app.exe")
+        );
+        std::fs::write(release.join("app"), b"").unwrap();
+        assert_eq!(
+            binary_with_suffix(&release, "app", ".exe"),
+            release.join("app")
+        );
+    }
+}
@@ -1,9 +1,142 @@
 //! Plugin system module
 //!
 //! This module provides a plugin system for extending ForgeKit functionality.
+//!
+//! Hook signatures change as ForgeKit evolves, so every plugin reports the
+//! plugin API version it is built against, and [`PluginManager::register`]
+//! refuses plugins outside the versions this ForgeKit supports instead of
+//! calling hooks that no longer match. Plugin libraries export the version
+//! as a symbol, checked before anything else of the library is used, along
+//! with the rustc and forgekit-core versions they are built with.
+//!
+//! Hooks run concurrently, under a timeout when one is configured:
+//! [`AsyncPlugin`]s on the runtime, and the synchronous hooks of [`Plugin`]s
+//! on the blocking thread pool.
+//!
+//! Plugins installed with `forgekit plugin install` are dynamic libraries
+//! exporting their plugin with [`declare_plugin!`];
+//! [`PluginManager::load`] loads one.
 
 use crate::error::ForgeKitError;
 use std::collections::HashMap;
+use std::ffi::CStr;
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Version of the plugin API this ForgeKit provides
+///
+/// Bumped whenever the hooks of [`Plugin`] change. Published plugins
+/// declare the version they are built against in `[plugin] api_version`.
+pub const PLUGIN_API_VERSION: u32 = 1;
+
+/// Oldest plugin API version still supported
+///
+/// Plugins built against versions from this one up to
+/// [`PLUGIN_API_VERSION`] can be registered.
+pub const MIN_PLUGIN_API_VERSION: u32 = 1;
+
+/// Symbol a plugin library exports its constructor as, see [`declare_plugin!`]
+pub const PLUGIN_CREATE_SYMBOL: &str = "forgekit_plugin_create";
+
+/// Symbol a plugin library exports the plugin API version it is built
+/// against as, a `u32`, see [`declare_plugin!`]
+///
+/// It is checked before the constructor is called, since the layout of the
+/// plugin trait object differs between API versions.
+pub const PLUGIN_API_VERSION_SYMBOL: &str = "forgekit_plugin_api_version";
+
+/// Symbol a plugin library exports a function returning its [`PLUGIN_BUILD`]
+/// as, see [`declare_plugin!`]
+pub const PLUGIN_BUILD_SYMBOL: &str = "forgekit_plugin_build";
+
+/// forgekit-core and rustc versions this ForgeKit is built with
+///
+/// Plugins are trait objects passed with the Rust ABI, whose layout is only
+/// stable for the same compiler and the same forgekit-core, so a plugin
+/// library must be built with exactly these.
+pub const PLUGIN_BUILD: &CStr = match CStr::from_bytes_with_nul(
+    concat!(
+        "forgekit-core ",
+        env!("CARGO_PKG_VERSION"),
+        ", ",
+        env!("FORGEKIT_RUSTC_VERSION"),
+        "\0"
+    )
+    .as_bytes(),
+) {
+    Ok(build) => build,
+    Err(_) => panic!("versions contain no NUL"),
+};
+
+/// Target triple this ForgeKit is built for; only plugin libraries built for
+/// it can be loaded
+pub const PLUGIN_TARGET: &str = env!("FORGEKIT_TARGET");
+
+/// File name of the dynamic library of plugin crate `crate_name` on this
+/// platform, like `libshaderlint.so`
+pub fn library_file_name(crate_name: &str) -> String {
+    format!(
+        "{}{}{}",
+        std::env::consts::DLL_PREFIX,
+        crate_name,
+        std::env::consts::DLL_SUFFIX
+    )
+}
+
+/// Export a [`Plugin`] from a plugin library, built as a `cdylib`, with the
+/// plugin API version and [`PLUGIN_BUILD`] of the forgekit-core it compiles
+/// with
+///
+/// ```ignore
+/// forgekit_core::declare_plugin!(ShaderLint::default());
+/// ```
+#[macro_export]
+macro_rules! declare_plugin {
+    ($plugin:expr) => {
+        #[no_mangle]
+        #[allow(non_upper_case_globals)]
+        pub static forgekit_plugin_api_version: u32 = $crate::plugin::PLUGIN_API_VERSION;
+
+        #[no_mangle]
+        pub extern "C" fn forgekit_plugin_build() -> *const ::std::ffi::c_char {
+            $crate::plugin::PLUGIN_BUILD.as_ptr()
+        }
+
+        #[no_mangle]
+        pub extern "Rust" fn forgekit_plugin_create() -> Box<dyn $crate::plugin::Plugin> {
+            Box::new($plugin)
+        }
+    };
+}
+
+/// Check that a plugin built against plugin API `api_version` can be used
+///
+/// `plugin` names the plugin in the error, like `shaderlint v1.2.0`.
+pub fn check_api_version(plugin: &str, api_version: u32) -> Result<(), ForgeKitError> {
+    if api_version > PLUGIN_API_VERSION {
+        return Err(ForgeKitError::Plugin(format!(
+            "{} is built against plugin API {}, newer than API {} of ForgeKit {}; update ForgeKit to use it",
+            plugin,
+            api_version,
+            PLUGIN_API_VERSION,
+            env!("CARGO_PKG_VERSION")
+        )));
+    }
+    if api_version < MIN_PLUGIN_API_VERSION {
+        return Err(ForgeKitError::Plugin(format!(
+            "{} is built against plugin API {}, which ForgeKit {} no longer supports (API {} to {}); rebuild it against a newer forgekit-core",
+            plugin,
+            api_version,
+            env!("CARGO_PKG_VERSION"),
+            MIN_PLUGIN_API_VERSION,
+            PLUGIN_API_VERSION
+        )));
+    }
+    Ok(())
+}
 
 /// Build context passed to plugins
 #[derive(Debug, Clone)]
@@ -19,7 +152,35 @@ pub struct PackageContext {
     pub output_path: String,
 }
 
+/// Future returned by the hooks of an [`AsyncPlugin`]
+pub type HookFuture<'a> = Pin<Box<dyn Future<Output = Result<(), ForgeKitError>> + Send + 'a>>;
+
+/// Step a plugin hook runs at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Hook {
+    /// Before the build starts
+    PreBuild,
+    /// After the build completes
+    PostBuild,
+    /// After packaging
+    Package,
+}
+
+impl std::fmt::Display for Hook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Hook::PreBuild => write!(f, "pre-build"),
+            Hook::PostBuild => write!(f, "post-build"),
+            Hook::Package => write!(f, "package"),
+        }
+    }
+}
+
 /// Plugin trait that all plugins must implement
+///
+/// Hooks run on a blocking thread, so they may block on I/O without
+/// holding up the build; plugins doing network I/O are better written as an
+/// [`AsyncPlugin`].
 pub trait Plugin: Send + Sync {
     /// Get plugin name
     fn name(&self) -> &str;
@@ -27,6 +188,21 @@ pub trait Plugin: Send + Sync {
     /// Get plugin version
     fn version(&self) -> &str;
 
+    /// Plugin API version the plugin is built against
+    ///
+    /// Keep the default: it is the [`PLUGIN_API_VERSION`] of the
+    /// forgekit-core the plugin compiles with. Plugins loaded from a library
+    /// are checked by the version the library exports instead, before this
+    /// can be called.
+    fn api_version(&self) -> u32 {
+        PLUGIN_API_VERSION
+    }
+
+    /// Names of plugins whose hooks must finish before this plugin's start
+    fn depends_on(&self) -> Vec<String> {
+        Vec::new()
+    }
+
     /// Called before build starts
     fn on_pre_build(&self, _context: &BuildContext) -> Result<(), ForgeKitError> {
         Ok(())
@@ -43,10 +219,128 @@ pub trait Plugin: Send + Sync {
     }
 }
 
+/// Plugin with async hooks, for plugins waiting on network or other I/O
+pub trait AsyncPlugin: Send + Sync {
+    /// Get plugin name
+    fn name(&self) -> &str;
+
+    /// Get plugin version
+    fn version(&self) -> &str;
+
+    /// Plugin API version the plugin is built against
+    ///
+    /// Keep the default: it is the [`PLUGIN_API_VERSION`] of the
+    /// forgekit-core the plugin compiles with. Plugins loaded from a library
+    /// are checked by the version the library exports instead, before this
+    /// can be called.
+    fn api_version(&self) -> u32 {
+        PLUGIN_API_VERSION
+    }
+
+    /// Names of plugins whose hooks must finish before this plugin's start
+    fn depends_on(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Called before build starts
+    fn on_pre_build<'a>(&'a self, _context: &'a BuildContext) -> HookFuture<'a> {
+        Box::pin(async { Ok(()) })
+    }
+
+    /// Called after build completes
+    fn on_post_build<'a>(&'a self, _context: &'a BuildContext) -> HookFuture<'a> {
+        Box::pin(async { Ok(()) })
+    }
+
+    /// Called during packaging
+    fn on_package<'a>(&'a self, _context: &'a PackageContext) -> HookFuture<'a> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Adapter running the hooks of a [`Plugin`] on the blocking thread pool
+struct Blocking(Arc<Loaded>);
+
+/// A plugin and the library its code is in, if it was loaded from one
+///
+/// Hooks that timed out keep running on their blocking thread with a
+/// reference to this, so the library is only unloaded once the last of
+/// them has returned.
+struct Loaded {
+    // Declared first so the plugin is dropped before its code is unloaded
+    plugin: Box<dyn Plugin>,
+    _library: Option<libloading::Library>,
+}
+
+/// `plugin` as an [`AsyncPlugin`] whose hooks run on the blocking thread pool
+pub(crate) fn blocking(plugin: Box<dyn Plugin>) -> Box<dyn AsyncPlugin> {
+    Box::new(Blocking(Arc::new(Loaded {
+        plugin,
+        _library: None,
+    })))
+}
+
+impl Blocking {
+    fn spawn<C: Send + 'static>(
+        &self,
+        context: C,
+        hook: fn(&dyn Plugin, &C) -> Result<(), ForgeKitError>,
+    ) -> HookFuture<'static> {
+        let loaded = Arc::clone(&self.0);
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || hook(loaded.plugin.as_ref(), &context))
+                .await
+                .map_err(std::io::Error::from)?
+        })
+    }
+}
+
+impl AsyncPlugin for Blocking {
+    fn name(&self) -> &str {
+        self.0.plugin.name()
+    }
+
+    fn version(&self) -> &str {
+        self.0.plugin.version()
+    }
+
+    fn api_version(&self) -> u32 {
+        self.0.plugin.api_version()
+    }
+
+    fn depends_on(&self) -> Vec<String> {
+        self.0.plugin.depends_on()
+    }
+
+    fn on_pre_build<'a>(&'a self, context: &'a BuildContext) -> HookFuture<'a> {
+        self.spawn(context.clone(), |plugin, context| {
+            plugin.on_pre_build(context)
+        })
+    }
+
+    fn on_post_build<'a>(&'a self, context: &'a BuildContext) -> HookFuture<'a> {
+        self.spawn(context.clone(), |plugin, context| {
+            plugin.on_post_build(context)
+        })
+    }
+
+    fn on_package<'a>(&'a self, context: &'a PackageContext) -> HookFuture<'a> {
+        self.spawn(context.clone(), |plugin, context| {
+            plugin.on_package(context)
+        })
+    }
+}
+
 /// Plugin manager for loading and managing plugins
+///
+/// Hooks of plugins that do not depend on each other run concurrently;
+/// a plugin's hooks start once those of the plugins it depends on have
+/// finished. When hooks fail, the error of the earliest registered plugin
+/// is returned and no later hooks are started.
 pub struct PluginManager {
-    plugins: Vec<Box<dyn Plugin>>,
+    plugins: Vec<Arc<dyn AsyncPlugin>>,
     registry: HashMap<String, String>,
+    timeouts: HashMap<Hook, Duration>,
 }
 
 impl PluginManager {
@@ -55,14 +349,98 @@ impl PluginManager {
         Self {
             plugins: Vec::new(),
             registry: HashMap::new(),
+            timeouts: HashMap::new(),
         }
     }
 
+    /// Fail a step when one of its `hook`s runs longer than `timeout`
+    ///
+    /// A timed-out hook of a [`Plugin`] keeps its blocking thread until it
+    /// returns; only the step stops waiting for it.
+    pub fn with_timeout(mut self, hook: Hook, timeout: Duration) -> Self {
+        self.timeouts.insert(hook, timeout);
+        self
+    }
+
+    /// Time `hook` may take; hooks without a timeout may take as long as
+    /// they need
+    pub fn timeout(&self, hook: Hook) -> Option<Duration> {
+        self.timeouts.get(&hook).copied()
+    }
+
     /// Register a plugin
-    pub fn register(&mut self, plugin: Box<dyn Plugin>) {
+    ///
+    /// Fails, without registering it, when the plugin is built against a
+    /// plugin API version this ForgeKit does not support.
+    pub fn register(&mut self, plugin: Box<dyn Plugin>) -> Result<(), ForgeKitError> {
+        self.register_async(blocking(plugin))
+    }
+
+    /// Register a plugin with async hooks
+    ///
+    /// Fails like [`register`](Self::register).
+    pub fn register_async(&mut self, plugin: Box<dyn AsyncPlugin>) -> Result<(), ForgeKitError> {
+        check_api_version(
+            &format!("Plugin {} v{}", plugin.name(), plugin.version()),
+            plugin.api_version(),
+        )?;
         self.registry
             .insert(plugin.name().to_string(), plugin.version().to_string());
-        self.plugins.push(plugin);
+        self.plugins.push(Arc::from(plugin));
+        Ok(())
+    }
+
+    /// Load and register the plugin of the dynamic library at `path`
+    ///
+    /// The library must export its plugin with [`declare_plugin!`] and stays
+    /// loaded as long as the plugin, including hooks still running after
+    /// they timed out. Fails like [`register`](Self::register),
+    /// checking the exported plugin API version before the plugin is created,
+    /// and when the library is not built with this ForgeKit's
+    /// [`PLUGIN_BUILD`].
+    pub fn load(&mut self, path: &Path) -> Result<(), ForgeKitError> {
+        let error = |e: libloading::Error| {
+            ForgeKitError::Plugin(format!("Cannot load plugin {}: {}", path.display(), e))
+        };
+        // SAFETY: loading runs the library's initialisers; plugin libraries
+        // are the ones the user installed to run as part of their builds.
+        let library = unsafe { libloading::Library::new(path) }.map_err(error)?;
+        // SAFETY: `declare_plugin!` exports the version as a `u32` static.
+        let api_version = unsafe {
+            **library
+                .get::<*const u32>(PLUGIN_API_VERSION_SYMBOL.as_bytes())
+                .map_err(error)?
+        };
+        check_api_version(&format!("Plugin {}", path.display()), api_version)?;
+        // SAFETY: `declare_plugin!` exports this C function returning a
+        // static NUL-terminated string.
+        let build = unsafe {
+            let build = library
+                .get::<extern "C" fn() -> *const std::ffi::c_char>(PLUGIN_BUILD_SYMBOL.as_bytes())
+                .map_err(error)?;
+            CStr::from_ptr(build())
+        };
+        if build != PLUGIN_BUILD {
+            return Err(ForgeKitError::Plugin(format!(
+                "Plugin {} is built with {}, but ForgeKit with {}; rebuild it with the same toolchain and forgekit-core",
+                path.display(),
+                build.to_string_lossy(),
+                PLUGIN_BUILD.to_string_lossy()
+            )));
+        }
+        // SAFETY: `declare_plugin!` exports the constructor with this
+        // signature for this API version, and the library outlives the
+        // plugin it returns.
+        let plugin = unsafe {
+            let create = library
+                .get::<fn() -> Box<dyn Plugin>>(PLUGIN_CREATE_SYMBOL.as_bytes())
+                .map_err(error)?;
+            create()
+        };
+        self.register_async(Box::new(Blocking(Arc::new(Loaded {
+            plugin,
+            _library: Some(library),
+        }))))
     }
 
     /// Get list of registered plugins
@@ -74,29 +452,118 @@ impl PluginManager {
     }
 
     /// Call pre-build hooks
-    pub fn call_pre_build(&self, context: &BuildContext) -> Result<(), ForgeKitError> {
-        for plugin in &self.plugins {
-            plugin.on_pre_build(context)?;
-        }
-        Ok(())
+    pub async fn call_pre_build(&self, context: &BuildContext) -> Result<(), ForgeKitError> {
+        self.call(Hook::PreBuild, context, |plugin, context| {
+            plugin.on_pre_build(context)
+        })
+        .await
     }
 
     /// Call post-build hooks
-    pub fn call_post_build(&self, context: &BuildContext) -> Result<(), ForgeKitError> {
-        for plugin in &self.plugins {
-            plugin.on_post_build(context)?;
-        }
-        Ok(())
+    pub async fn call_post_build(&self, context: &BuildContext) -> Result<(), ForgeKitError> {
+        self.call(Hook::PostBuild, context, |plugin, context| {
+            plugin.on_post_build(context)
+        })
+        .await
     }
 
     /// Call package hooks
-    pub fn call_package(&self, context: &PackageContext) -> Result<(), ForgeKitError> {
-        for plugin in &self.plugins {
-            plugin.on_package(context)?;
+    pub async fn call_package(&self, context: &PackageContext) -> Result<(), ForgeKitError> {
+        self.call(Hook::Package, context, |plugin, context| {
+            plugin.on_package(context)
+        })
+        .await
+    }
+
+    /// Run `hook` of every plugin, stage by stage
+    async fn call<C: Clone + Send + Sync + 'static>(
+        &self,
+        hook: Hook,
+        context: &C,
+        run: for<'a> fn(&'a dyn AsyncPlugin, &'a C) -> HookFuture<'a>,
+    ) -> Result<(), ForgeKitError> {
+        let timeout = self.timeout(hook);
+        for stage in self.stages()? {
+            let mut tasks = tokio::task::JoinSet::new();
+            for &index in &stage {
+                let plugin = Arc::clone(&self.plugins[index]);
+                let context = context.clone();
+                tasks.spawn(async move {
+                    let hook_run = run(plugin.as_ref(), &context);
+                    let result = match timeout {
+                        Some(timeout) => tokio::time::timeout(timeout, hook_run)
+                            .await
+                            .unwrap_or_else(|_| {
+                                Err(ForgeKitError::Plugin(format!(
+                                    "{} hook of {} timed out after {:?}",
+                                    hook,
+                                    plugin.name(),
+                                    timeout
+                                )))
+                            }),
+                        None => hook_run.await,
+                    };
+                    (index, result)
+                });
+            }
+
+            let mut failures = Vec::new();
+            while let Some(joined) = tasks.join_next().await {
+                match joined {
+                    Ok((_, Ok(()))) => {}
+                    Ok((index, Err(e))) => failures.push((index, e)),
+                    Err(e) => return Err(std::io::Error::from(e).into()),
+                }
+            }
+            if let Some((_, error)) = failures.into_iter().min_by_key(|(index, _)| *index) {
+                return Err(error);
+            }
         }
         Ok(())
     }
 
+    /// Plugins grouped into stages that run one after another, each in
+    /// registration order
+    ///
+    /// Dependencies on plugins that are not registered are ignored.
+    fn stages(&self) -> Result<Vec<Vec<usize>>, ForgeKitError> {
+        let dependencies: Vec<Vec<usize>> = self
+            .plugins
+            .iter()
+            .map(|plugin| {
+                plugin
+                    .depends_on()
+                    .iter()
+                    .filter_map(|name| self.plugins.iter().position(|p| p.name() == name))
+                    .collect()
+            })
+            .collect();
+
+        let mut done = vec![false; self.plugins.len()];
+        let mut stages = Vec::new();
+        while done.iter().any(|done| !done) {
+            let stage: Vec<usize> = (0..self.plugins.len())
+                .filter(|&index| !done[index])
+                .filter(|&index| dependencies[index].iter().all(|&d| done[d]))
+                .collect();
+            if stage.is_empty() {
+                let cycle: Vec<&str> = (0..self.plugins.len())
+                    .filter(|&index| !done[index])
+                    .map(|index| self.plugins[index].name())
+                    .collect();
+                return Err(ForgeKitError::Plugin(format!(
+                    "Plugins depend on each other in a cycle: {}",
+                    cycle.join(", ")
+                )));
+            }
+            for &index in &stage {
+                done[index] = true;
+            }
+            stages.push(stage);
+        }
+        Ok(stages)
+    }
+
     /// Get plugin count
     pub fn plugin_count(&self) -> usize {
         self.plugins.len()
@@ -163,14 +630,14 @@ mod tests {
     #[test]
     fn test_register_plugin() {
         let mut manager = PluginManager::new();
-        manager.register(Box::new(TestPlugin));
+        manager.register(Box::new(TestPlugin)).unwrap();
         assert_eq!(manager.plugin_count(), 1);
     }
 
     #[test]
     fn test_list_plugins() {
         let mut manager = PluginManager::new();
-        manager.register(Box::new(TestPlugin));
+        manager.register(Box::new(TestPlugin)).unwrap();
         let plugins = manager.list_plugins();
         assert_eq!(plugins.len(), 1);
         assert_eq!(plugins[0].0, "test-plugin");
@@ -178,12 +645,147 @@ mod tests {
     }
 
     #[test]
-    fn test_pre_build_hook() {
+    fn test_plugin_build_names_core_and_rustc() {
+        let build = PLUGIN_BUILD.to_str().unwrap();
+        assert!(build.starts_with(concat!("forgekit-core ", env!("CARGO_PKG_VERSION"), ", ")));
+        assert!(build.contains("rustc"));
+    }
+
+    struct FuturePlugin;
+
+    impl Plugin for FuturePlugin {
+        fn name(&self) -> &str {
+            "future-plugin"
+        }
+
+        fn version(&self) -> &str {
+            "2.0.0"
+        }
+
+        fn api_version(&self) -> u32 {
+            PLUGIN_API_VERSION + 1
+        }
+    }
+
+    #[test]
+    fn test_plugins_for_other_api_versions_are_rejected() {
+        let mut manager = PluginManager::new();
+        let error = manager.register(Box::new(FuturePlugin)).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("future-plugin v2.0.0 is built against plugin API"));
+        assert_eq!(manager.plugin_count(), 0);
+
+        assert!(check_api_version("old", MIN_PLUGIN_API_VERSION - 1).is_err());
+        assert!(check_api_version("current", PLUGIN_API_VERSION).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_pre_build_hook() {
         let manager = PluginManager::new();
         let context = BuildContext {
             project_path: "/test".to_string(),
             target: "debug".to_string(),
         };
-        assert!(manager.call_pre_build(&context).is_ok());
+        assert!(manager.call_pre_build(&context).await.is_ok());
+    }
+
+    /// Async plugin waiting at `barrier` in its pre-build hook, or sleeping
+    /// when there is none, and recording when it finished
+    struct WaitingPlugin {
+        name: &'static str,
+        depends_on: Vec<String>,
+        barrier: Option<Arc<tokio::sync::Barrier>>,
+        finished: Arc<std::sync::Mutex<Vec<&'static str>>>,
+    }
+
+    impl AsyncPlugin for WaitingPlugin {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn version(&self) -> &str {
+            "1.0.0"
+        }
+
+        fn depends_on(&self) -> Vec<String> {
+            self.depends_on.clone()
+        }
+
+        fn on_pre_build<'a>(&'a self, _context: &'a BuildContext) -> HookFuture<'a> {
+            Box::pin(async move {
+                match &self.barrier {
+                    Some(barrier) => {
+                        barrier.wait().await;
+                    }
+                    None => tokio::time::sleep(Duration::from_secs(10)).await,
+                }
+                self.finished.lock().unwrap().push(self.name);
+                Ok(())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_independent_hooks_run_concurrently_and_dependents_after() {
+        let barrier = Arc::new(tokio::sync::Barrier::new(2));
+        let finished = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let plugin = |name, depends_on: &[&str], barrier: &Arc<tokio::sync::Barrier>| {
+            Box::new(WaitingPlugin {
+                name,
+                depends_on: depends_on.iter().map(|d| d.to_string()).collect(),
+                barrier: Some(Arc::clone(barrier)),
+                finished: Arc::clone(&finished),
+            })
+        };
+        let mut manager = PluginManager::new().with_timeout(Hook::PreBuild, Duration::from_secs(5));
+        // Registered first, but runs once both others are done
+        manager
+            .register_async(plugin(
+                "report",
+                &["lint", "fetch"],
+                &Arc::new(tokio::sync::Barrier::new(1)),
+            ))
+            .unwrap();
+        // Each waits for the other, so they only finish when run together
+        manager
+            .register_async(plugin("lint", &[], &barrier))
+            .unwrap();
+        manager
+            .register_async(plugin("fetch", &[], &barrier))
+            .unwrap();
+        manager.register(Box::new(TestPlugin)).unwrap();
+
+        let context = BuildContext {
+            project_path: "/test".to_string(),
+            target: "debug".to_string(),
+        };
+        manager.call_pre_build(&context).await.unwrap();
+        assert_eq!(finished.lock().unwrap().last(), Some(&"report"));
+        assert_eq!(finished.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_slow_hooks_time_out() {
+        let mut manager =
+            PluginManager::new().with_timeout(Hook::PreBuild, Duration::from_millis(50));
+        manager
+            .register_async(Box::new(WaitingPlugin {
+                name: "slow",
+                depends_on: Vec::new(),
+                barrier: None,
+                finished: Default::default(),
+            }))
+            .unwrap();
+        assert_eq!(manager.timeout(Hook::PostBuild), None);
+
+        let context = BuildContext {
+            project_path: "/test".to_string(),
+            target: "debug".to_string(),
+        };
+        let error = manager.call_pre_build(&context).await.unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("pre-build hook of slow timed out"));
     }
 }
@@ -0,0 +1,579 @@
+//! Plugin marketplace module
+//!
+//! Plugins are libraries published with a `[plugin]` section in their
+//! forgekit.toml; the registry index records the plugin API version of each
+//! release and the target its library is built for. `forgekit plugin` searches the index for them and installs them
+//! into `.forgekit/plugins/` of a project, or into the user's configuration
+//! directory for every project. `plugins.toml` in that directory records
+//! the installed versions and whether each plugin is enabled;
+//! [`PluginStore::load_into`] loads the enabled ones into a
+//! [`PluginManager`], which `ForgeKitBuilder::with_installed_plugins` does for
+//! builds and packaging.
+//!
+//! Only releases built against a plugin API version this ForgeKit supports,
+//! for the target it runs on, are installed; updates pick the newest such
+//! release.
+
+use crate::config::GlobalConfig;
+use crate::error::ForgeKitError;
+use crate::moxlib;
+use crate::plugin::{self, PluginManager};
+use crate::registry::{IndexEntry, RegistryClient, RegistryConfig, VersionInfo};
+use crate::store;
+use crate::version_manager::compare_versions;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Directory, relative to a project, its plugins are installed in
+pub const PLUGINS_DIR: &str = ".forgekit/plugins";
+
+/// File in a plugins directory recording what is installed
+pub const PLUGINS_FILE: &str = "plugins.toml";
+
+/// A plugin found in the registry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PluginSearchResult {
+    /// Package name
+    pub name: String,
+    /// Newest version this ForgeKit can use, else the newest version
+    pub version: String,
+    /// What the plugin does
+    pub description: Option<String>,
+    /// Plugin API version `version` is built against
+    pub api_version: u32,
+    /// Whether this ForgeKit can use `version`
+    pub compatible: bool,
+}
+
+/// A plugin installed into a plugins directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstalledPlugin {
+    /// Package name
+    pub name: String,
+    /// Installed version
+    pub version: String,
+    /// Plugin API version the installed version is built against
+    pub api_version: u32,
+    /// Whether ForgeKit loads the plugin
+    pub enabled: bool,
+    /// Dynamic library of the plugin, relative to its directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub library: Option<String>,
+}
+
+impl InstalledPlugin {
+    /// Whether this ForgeKit can use the plugin; plugins installed by
+    /// another ForgeKit may be built against an API version it does not
+    /// support
+    pub fn is_compatible(&self) -> bool {
+        plugin::check_api_version(&self.name, self.api_version).is_ok()
+    }
+}
+
+/// Contents of `plugins.toml`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PluginsFile {
+    #[serde(default, rename = "plugin")]
+    plugins: Vec<InstalledPlugin>,
+}
+
+/// A plugin moved to a newer version by [`PluginStore::update`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PluginUpdate {
+    /// Package name
+    pub name: String,
+    /// Version before the update
+    pub from: String,
+    /// Version after the update
+    pub to: String,
+}
+
+/// Plugins installed into one directory
+pub struct PluginStore {
+    dir: PathBuf,
+    registry_client: RegistryClient,
+}
+
+impl PluginStore {
+    /// Store for plugins installed into `dir`
+    pub fn new(dir: PathBuf) -> Result<Self, ForgeKitError> {
+        Ok(Self {
+            dir,
+            registry_client: RegistryClient::from_login(RegistryConfig::default())?,
+        })
+    }
+
+    /// Store for the plugins of the project at `project_path`
+    pub fn for_project(project_path: &Path) -> Result<Self, ForgeKitError> {
+        Self::new(project_path.join(PLUGINS_DIR))
+    }
+
+    /// Store for the plugins the user installed for every project
+    pub fn global() -> Result<Self, ForgeKitError> {
+        Self::new(GlobalConfig::config_dir().join("plugins"))
+    }
+
+    /// Look up and download plugins through `registry_client`
+    pub fn with_registry_client(mut self, registry_client: RegistryClient) -> Self {
+        self.registry_client = registry_client;
+        self
+    }
+
+    /// Directory plugins are installed in
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Plugins in the registry whose name or description contains `query`
+    ///
+    /// The local index is refreshed first when it is empty or mirrors a
+    /// registry server.
+    pub async fn search(&self, query: &str) -> Result<Vec<PluginSearchResult>, ForgeKitError> {
+        let mut index = self.registry_client.load_index().await?;
+        if index.is_empty() || self.registry_client.config().is_registry_server() {
+            self.registry_client.update_index().await?;
+            index = self.registry_client.load_index().await?;
+        }
+
+        let query = query.to_lowercase();
+        let mut results: Vec<_> = index
+            .values()
+            .filter_map(|entry| {
+                let info = newest_compatible(entry).or_else(|| newest_plugin(entry))?;
+                let listing = info.plugin.as_ref()?;
+                let matches = entry.name.to_lowercase().contains(&query)
+                    || listing
+                        .description
+                        .as_ref()
+                        .is_some_and(|d| d.to_lowercase().contains(&query));
+                matches.then(|| PluginSearchResult {
+                    name: entry.name.clone(),
+                    version: info.version.clone(),
+                    description: listing.description.clone(),
+                    api_version: listing.api_version,
+                    compatible: check_compatible(&entry.name, info).is_ok(),
+                })
+            })
+            .collect();
+        results.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(results)
+    }
+
+    /// Installed plugins, by name
+    pub fn installed(&self) -> Result<Vec<InstalledPlugin>, ForgeKitError> {
+        Ok(self.read()?.plugins)
+    }
+
+    /// Install plugin `name`, at `version` or the newest version this
+    /// ForgeKit can use
+    ///
+    /// A plugin already installed is replaced and keeps whether it is
+    /// enabled; new plugins are enabled.
+    pub async fn install(
+        &self,
+        name: &str,
+        version: Option<&str>,
+    ) -> Result<InstalledPlugin, ForgeKitError> {
+        let entry = self.entry(name).await?;
+        let info = match version {
+            Some(version) => entry.versions.get(version).ok_or_else(|| {
+                ForgeKitError::Plugin(format!("{} has no version {}", name, version))
+            })?,
+            // Without a compatible release, report why the newest one is not
+            None => newest_compatible(&entry)
+                .or_else(|| newest_plugin(&entry))
+                .ok_or_else(|| not_a_plugin(name))?,
+        };
+        let api_version = check_compatible(name, info)?;
+
+        let archive = self
+            .registry_client
+            .download_package(name, &info.version)
+            .await?;
+        let dest = self.dir.join(name);
+        if dest.exists() {
+            std::fs::remove_dir_all(&dest)?;
+        }
+        store::extract_package(&archive, &dest)?;
+        let library = moxlib::read_manifest(&archive)?.library;
+
+        let mut file = self.read()?;
+        let enabled = file
+            .plugins
+            .iter()
+            .find(|plugin| plugin.name == name)
+            .is_none_or(|plugin| plugin.enabled);
+        let installed = InstalledPlugin {
+            name: name.to_string(),
+            version: info.version.clone(),
+            api_version,
+            enabled,
+            library,
+        };
+        file.plugins.retain(|plugin| plugin.name != name);
+        file.plugins.push(installed.clone());
+        self.write(file)?;
+        Ok(installed)
+    }
+
+    /// Enable or disable installed plugin `name`
+    pub fn set_enabled(&self, name: &str, enabled: bool) -> Result<InstalledPlugin, ForgeKitError> {
+        let mut file = self.read()?;
+        let plugin = file
+            .plugins
+            .iter_mut()
+            .find(|plugin| plugin.name == name)
+            .ok_or_else(|| not_installed(name))?;
+        plugin.enabled = enabled;
+        let plugin = plugin.clone();
+        self.write(file)?;
+        Ok(plugin)
+    }
+
+    /// Move installed plugin `name`, or every installed plugin, to the
+    /// newest version this ForgeKit can use
+    pub async fn update(&self, name: Option<&str>) -> Result<Vec<PluginUpdate>, ForgeKitError> {
+        let installed = self.installed()?;
+        if let Some(name) = name {
+            if !installed.iter().any(|plugin| plugin.name == name) {
+                return Err(not_installed(name));
+            }
+        }
+
+        let mut updates = Vec::new();
+        for plugin in installed {
+            if name.is_some_and(|name| name != plugin.name) {
+                continue;
+            }
+            let entry = self.entry(&plugin.name).await?;
+            let Some(newest) = newest_compatible(&entry) else {
+                continue;
+            };
+            // A plugin built against an unsupported API is replaced even by
+            // the same version number rebuilt for a supported one
+            if compare_versions(&newest.version, &plugin.version).is_gt() || !plugin.is_compatible()
+            {
+                let to = self
+                    .install(&plugin.name, Some(&newest.version))
+                    .await?
+                    .version;
+                updates.push(PluginUpdate {
+                    name: plugin.name,
+                    from: plugin.version,
+                    to,
+                });
+            }
+        }
+        Ok(updates)
+    }
+
+    /// Remove installed plugin `name`
+    pub fn uninstall(&self, name: &str) -> Result<(), ForgeKitError> {
+        let mut file = self.read()?;
+        if !file.plugins.iter().any(|plugin| plugin.name == name) {
+            return Err(not_installed(name));
+        }
+        file.plugins.retain(|plugin| plugin.name != name);
+        let dest = self.dir.join(name);
+        if dest.exists() {
+            std::fs::remove_dir_all(dest)?;
+        }
+        self.write(file)
+    }
+
+    /// Load the enabled plugins into `manager`, except those named in `skip`
+    ///
+    /// Returns the plugins loaded. Plugins that cannot be loaded, e.g. ones
+    /// built against an unsupported plugin API or for another target, are
+    /// skipped with a warning.
+    pub fn load_into(
+        &self,
+        manager: &mut PluginManager,
+        skip: &[String],
+    ) -> Result<Vec<InstalledPlugin>, ForgeKitError> {
+        let mut loaded = Vec::new();
+        for installed in self.installed()? {
+            if !installed.enabled || skip.contains(&installed.name) {
+                continue;
+            }
+            // A plugin that cannot be loaded is left out instead of failing
+            // every build
+            let loading = plugin::check_api_version(
+                &format!("{} v{}", installed.name, installed.version),
+                installed.api_version,
+            )
+            .and_then(|()| {
+                let library = installed.library.as_deref().ok_or_else(|| {
+                    ForgeKitError::Plugin(format!(
+                        "{} v{} has no plugin library; reinstall it with `forgekit plugin install {}`",
+                        installed.name, installed.version, installed.name
+                    ))
+                })?;
+                manager.load(&self.dir.join(&installed.name).join(library))
+            });
+            match loading {
+                Ok(()) => loaded.push(installed),
+                Err(e) => tracing::warn!("Skipping plugin {}: {}", installed.name, e),
+            }
+        }
+        Ok(loaded)
+    }
+
+    /// Index entry of `name`, from the registry server or the local index
+    async fn entry(&self, name: &str) -> Result<IndexEntry, ForgeKitError> {
+        let entry = if self.registry_client.config().is_registry_server() {
+            self.registry_client.fetch_entry(name).await?
+        } else {
+            self.registry_client.read_index_entry(name)?
+        };
+        entry.ok_or_else(|| ForgeKitError::Plugin(format!("{} is not in the registry", name)))
+    }
+
+    fn read(&self) -> Result<PluginsFile, ForgeKitError> {
+        let path = self.dir.join(PLUGINS_FILE);
+        if !path.exists() {
+            return Ok(PluginsFile::default());
+        }
+        Ok(toml::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    fn write(&self, mut file: PluginsFile) -> Result<(), ForgeKitError> {
+        file.plugins.sort_by(|a, b| a.name.cmp(&b.name));
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(self.dir.join(PLUGINS_FILE), toml::to_string_pretty(&file)?)?;
+        Ok(())
+    }
+}
+
+/// Newest version of `entry` published as a plugin
+fn newest_plugin(entry: &IndexEntry) -> Option<&VersionInfo> {
+    entry
+        .versions
+        .values()
+        .filter(|info| info.plugin.is_some())
+        .max_by(|a, b| compare_versions(&a.version, &b.version))
+}
+
+/// Newest version of `entry` built against a plugin API this ForgeKit
+/// supports
+fn newest_compatible(entry: &IndexEntry) -> Option<&VersionInfo> {
+    entry
+        .versions
+        .values()
+        .filter(|info| check_compatible(&entry.name, info).is_ok())
+        .max_by(|a, b| compare_versions(&a.version, &b.version))
+}
+
+/// Plugin API version of a release, if this ForgeKit can use it
+///
+/// Its library must also be built for the target this ForgeKit runs on.
+fn check_compatible(name: &str, info: &VersionInfo) -> Result<u32, ForgeKitError> {
+    let listing = info.plugin.as_ref().ok_or_else(|| not_a_plugin(name))?;
+    plugin::check_api_version(&format!("{} v{}", name, info.version), listing.api_version)?;
+    if listing.target.as_deref() != Some(plugin::PLUGIN_TARGET) {
+        return Err(ForgeKitError::Plugin(format!(
+            "{} v{} is built for {}, not for {} this ForgeKit runs on",
+            name,
+            info.version,
+            listing.target.as_deref().unwrap_or("an unknown target"),
+            plugin::PLUGIN_TARGET
+        )));
+    }
+    Ok(listing.api_version)
+}
+
+fn not_a_plugin(name: &str) -> ForgeKitError {
+    ForgeKitError::Plugin(format!("{} is not a ForgeKit plugin", name))
+}
+
+fn not_installed(name: &str) -> ForgeKitError {
+    ForgeKitError::Plugin(format!("{} is not installed", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{PluginConfig, ProjectConfig, ProjectKind};
+    use crate::moxlib;
+    use crate::plugin::PLUGIN_API_VERSION;
+    use tempfile::TempDir;
+
+    /// Publish `name` v`version` built against plugin API `api_version`
+    async fn publish(client: &RegistryClient, root: &Path, name: &str, version: &str, api: u32) {
+        publish_for(client, root, name, version, api, plugin::PLUGIN_TARGET).await;
+    }
+
+    /// Publish `name` v`version` built for `target`
+    async fn publish_for(
+        client: &RegistryClient,
+        root: &Path,
+        name: &str,
+        version: &str,
+        api: u32,
+        target: &str,
+    ) {
+        let project = root.join(format!("{}-{}", name, version));
+        let info = crate::builder::BuildInfo {
+            environment: None,
+            built_at: "2024-01-01T00:00:00Z".to_string(),
+            forgekit_version: env!("CARGO_PKG_VERSION").to_string(),
+            target: Some(target.to_string()),
+            target_dir: None,
+        };
+        let info_path = crate::builder::BuildInfo::path(&project);
+        std::fs::create_dir_all(info_path.parent().unwrap()).unwrap();
+        std::fs::write(info_path, toml::to_string(&info).unwrap()).unwrap();
+        let release = crate::platform::target_release_dir(&project.join("target"), info.target());
+        std::fs::create_dir_all(&release).unwrap();
+        std::fs::write(release.join(format!("lib{}.rlib", name)), b"rlib").unwrap();
+        std::fs::write(release.join(plugin::library_file_name(name)), b"cdylib").unwrap();
+        std::fs::create_dir_all(project.join("src")).unwrap();
+        std::fs::write(project.join("src").join("lib.rs"), "").unwrap();
+        let config = ProjectConfig {
+            name: name.to_string(),
+            version: version.to_string(),
+            kind: ProjectKind::Library,
+            description: Some("Lints shaders".to_string()),
+            plugin: Some(PluginConfig { api_version: api }),
+            ..Default::default()
+        };
+        let archive = moxlib::package(&project, &config, &Default::default())
+            .await
+            .unwrap();
+        client.publish_library(&archive).unwrap();
+    }
+
+    fn test_store(root: &Path) -> (PluginStore, RegistryClient) {
+        let client = RegistryClient::new(RegistryConfig {
+            cache_dir: root.join("cache"),
+            index_dir: root.join("index"),
+            ..Default::default()
+        })
+        .unwrap();
+        let store = PluginStore::for_project(&root.join("app"))
+            .unwrap()
+            .with_registry_client(client.clone());
+        (store, client)
+    }
+
+    #[tokio::test]
+    async fn test_search_and_install_compatible_versions() {
+        let temp_dir = TempDir::new().unwrap();
+        let (store, client) = test_store(temp_dir.path());
+        publish(
+            &client,
+            temp_dir.path(),
+            "shaderlint",
+            "1.0.0",
+            PLUGIN_API_VERSION,
+        )
+        .await;
+        publish(
+            &client,
+            temp_dir.path(),
+            "shaderlint",
+            "2.0.0",
+            PLUGIN_API_VERSION + 1,
+        )
+        .await;
+
+        let results = store.search("shader").await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].version, "1.0.0");
+        assert!(results[0].compatible);
+        assert_eq!(store.search("lints").await.unwrap().len(), 1);
+
+        assert!(store.install("shaderlint", Some("2.0.0")).await.is_err());
+
+        // Releases for another host are neither found nor installed
+        let other = if plugin::PLUGIN_TARGET == "aarch64-apple-darwin" {
+            "x86_64-unknown-linux-gnu"
+        } else {
+            "aarch64-apple-darwin"
+        };
+        publish_for(
+            &client,
+            temp_dir.path(),
+            "shaderlint",
+            "1.5.0",
+            PLUGIN_API_VERSION,
+            other,
+        )
+        .await;
+        assert_eq!(store.search("shader").await.unwrap()[0].version, "1.0.0");
+        assert!(store.install("shaderlint", Some("1.5.0")).await.is_err());
+        let installed = store.install("shaderlint", None).await.unwrap();
+        assert_eq!(installed.version, "1.0.0");
+        assert!(installed.enabled);
+        let library = installed.library.unwrap();
+        assert_eq!(
+            library,
+            format!("lib/{}", plugin::library_file_name("shaderlint"))
+        );
+        assert!(store.dir().join("shaderlint").join(library).exists());
+        assert!(store
+            .dir()
+            .join("shaderlint")
+            .join(moxlib::MOXLIB_MANIFEST)
+            .exists());
+        assert!(temp_dir
+            .path()
+            .join("app")
+            .join(PLUGINS_DIR)
+            .join(PLUGINS_FILE)
+            .exists());
+    }
+
+    #[tokio::test]
+    async fn test_disabled_plugins_stay_disabled_across_updates() {
+        let temp_dir = TempDir::new().unwrap();
+        let (store, client) = test_store(temp_dir.path());
+        publish(
+            &client,
+            temp_dir.path(),
+            "shaderlint",
+            "1.0.0",
+            PLUGIN_API_VERSION,
+        )
+        .await;
+        store.install("shaderlint", None).await.unwrap();
+        assert!(!store.set_enabled("shaderlint", false).unwrap().enabled);
+        assert!(store.update(None).await.unwrap().is_empty());
+        let mut manager = PluginManager::new();
+        assert!(store.load_into(&mut manager, &[]).unwrap().is_empty());
+
+        publish(
+            &client,
+            temp_dir.path(),
+            "shaderlint",
+            "1.1.0",
+            PLUGIN_API_VERSION,
+        )
+        .await;
+        let updates = store.update(Some("shaderlint")).await.unwrap();
+        assert_eq!(
+            updates,
+            vec![PluginUpdate {
+                name: "shaderlint".to_string(),
+                from: "1.0.0".to_string(),
+                to: "1.1.0".to_string(),
+            }]
+        );
+        let installed = store.installed().unwrap();
+        assert_eq!(installed[0].version, "1.1.0");
+        assert!(!installed[0].enabled);
+
+        // Enabled plugins are loaded unless skipped, e.g. for a project's own
+        store.set_enabled("shaderlint", true).unwrap();
+        let skip = ["shaderlint".to_string()];
+        assert!(store.load_into(&mut manager, &skip).unwrap().is_empty());
+        // A library that cannot be loaded is skipped instead of failing
+        assert!(store.load_into(&mut manager, &[]).unwrap().is_empty());
+        assert_eq!(manager.plugin_count(), 0);
+
+        store.uninstall("shaderlint").unwrap();
+        assert!(store.installed().unwrap().is_empty());
+        assert!(!store.dir().join("shaderlint").exists());
+        assert!(store.update(Some("shaderlint")).await.is_err());
+    }
+}
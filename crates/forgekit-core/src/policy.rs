@@ -0,0 +1,538 @@
+//! Supply-chain policy module
+//!
+//! A project can restrict what it depends on with a `forgekit-policy.toml`
+//! next to its forgekit.toml:
+//!
+//! ```toml
+//! [sources]
+//! deny_git_in_release = true
+//! allowed_registries = ["github", "https://registry.ledokoz.com"]
+//!
+//! [dependencies]
+//! max_depth = 4
+//! min_age_days = 7
+//! ```
+//!
+//! The policy is evaluated against the resolved dependency graph when
+//! dependencies are added, installed or vendored, and again when a release
+//! is packaged; rules about release builds only apply then. Every broken
+//! rule is reported, not just the first one.
+
+use crate::config::{Dependency, ProjectConfig};
+use crate::error::ForgeKitError;
+use crate::lockfile::Lockfile;
+use crate::overrides::{self, Overrides};
+use crate::registry::{RegistryClient, RegistryConfig};
+use crate::version_manager::{compare_versions, VersionReq};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Name of the policy file in a project
+pub const POLICY_FILE: &str = "forgekit-policy.toml";
+
+/// Rules a project's dependencies must follow
+///
+/// Unknown keys are rejected, so a misspelled rule cannot silently go
+/// unenforced.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Policy {
+    /// Where dependencies may come from
+    pub sources: SourcePolicy,
+    /// Limits on the dependency graph
+    pub dependencies: DependencyPolicy,
+}
+
+/// `[sources]` rules
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SourcePolicy {
+    /// Refuse git dependencies when packaging a release
+    pub deny_git_in_release: bool,
+    /// Registries dependencies may come from, by name or URL; any when empty
+    pub allowed_registries: Vec<String>,
+}
+
+/// `[dependencies]` rules
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DependencyPolicy {
+    /// Deepest a transitive dependency may be; direct dependencies are at 1
+    pub max_depth: Option<usize>,
+    /// Days since publication before a registry package may be used
+    pub min_age_days: Option<u32>,
+}
+
+/// When a policy is evaluated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyStage {
+    /// Dependencies are being added, installed or vendored
+    Resolution,
+    /// A release is being packaged
+    Release,
+}
+
+/// Where a package of the dependency graph comes from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", content = "location", rename_all = "lowercase")]
+pub enum PackageSource {
+    /// A registry, by name or URL
+    Registry(String),
+    /// A git repository
+    Git(String),
+    /// A local directory
+    Path(String),
+}
+
+/// A package of the dependency graph, as the policy sees it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PolicyPackage {
+    /// Package name
+    pub name: String,
+    /// Package version
+    pub version: String,
+    /// Where the package comes from
+    pub source: PackageSource,
+    /// Depth in the graph; direct dependencies are at 1
+    pub depth: usize,
+    /// When the version was published (RFC 3339), for registry packages
+    /// in the local index
+    pub published: Option<String>,
+}
+
+/// A rule a package breaks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PolicyViolation {
+    /// Rule broken, as `section.key` of the policy file
+    pub rule: &'static str,
+    /// Package breaking it, as `name@version`
+    pub package: String,
+    /// What is wrong
+    pub message: String,
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} ({})", self.package, self.message, self.rule)
+    }
+}
+
+impl Policy {
+    /// Path of the policy file of a project
+    pub fn path(project_root: &Path) -> PathBuf {
+        project_root.join(POLICY_FILE)
+    }
+
+    /// Load the policy of a project, `None` when it has no policy file
+    pub fn load(project_root: &Path) -> Result<Option<Self>, ForgeKitError> {
+        let path = Self::path(project_root);
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        toml::from_str(&contents)
+            .map(Some)
+            .map_err(|e| ForgeKitError::InvalidConfig(format!("Invalid {}: {}", POLICY_FILE, e)))
+    }
+
+    /// Rules the packages break at `stage`
+    ///
+    /// `registry` is the registry packages without an explicit source come
+    /// from, so it can be allowed by name or by URL.
+    pub fn evaluate(
+        &self,
+        packages: &[PolicyPackage],
+        stage: PolicyStage,
+        registry: &RegistryConfig,
+    ) -> Vec<PolicyViolation> {
+        let now = chrono::Utc::now();
+        let mut violations = Vec::new();
+        for package in packages {
+            let mut violate = |rule, message: String| {
+                violations.push(PolicyViolation {
+                    rule,
+                    package: format!("{}@{}", package.name, package.version),
+                    message,
+                })
+            };
+
+            match &package.source {
+                PackageSource::Git(url)
+                    if stage == PolicyStage::Release && self.sources.deny_git_in_release =>
+                {
+                    violate(
+                        "sources.deny_git_in_release",
+                        format!("comes from git ({}), which releases may not use", url),
+                    );
+                }
+                PackageSource::Registry(name) if !self.registry_allowed(name, registry) => {
+                    violate(
+                        "sources.allowed_registries",
+                        format!(
+                            "comes from registry {}, which is not one of {}",
+                            name,
+                            self.sources.allowed_registries.join(", ")
+                        ),
+                    );
+                }
+                _ => {}
+            }
+
+            if let Some(max_depth) = self.dependencies.max_depth {
+                if package.depth > max_depth {
+                    violate(
+                        "dependencies.max_depth",
+                        format!(
+                            "is {} levels deep, more than the allowed {}",
+                            package.depth, max_depth
+                        ),
+                    );
+                }
+            }
+
+            let min_age = self.dependencies.min_age_days;
+            if let (Some(min_age), PackageSource::Registry(_)) = (min_age, &package.source) {
+                let published = package
+                    .published
+                    .as_deref()
+                    .and_then(|date| chrono::DateTime::parse_from_rfc3339(date).ok());
+                match published {
+                    Some(published) => {
+                        let age = (now - published.with_timezone(&chrono::Utc)).num_days();
+                        if age < i64::from(min_age) {
+                            violate(
+                                "dependencies.min_age_days",
+                                format!(
+                                    "was published {} day(s) ago, less than the required {}",
+                                    age.max(0),
+                                    min_age
+                                ),
+                            );
+                        }
+                    }
+                    None => violate(
+                        "dependencies.min_age_days",
+                        "has no known publication date; run `forgekit update` to refresh the index"
+                            .to_string(),
+                    ),
+                }
+            }
+        }
+        violations
+    }
+
+    /// Whether packages may come from `registry`
+    fn registry_allowed(&self, registry: &str, config: &RegistryConfig) -> bool {
+        let allowed = &self.sources.allowed_registries;
+        if allowed.is_empty() {
+            return true;
+        }
+        let trim = |url: &str| url.trim_end_matches('/').to_string();
+        let registry = trim(registry);
+        allowed.iter().map(|entry| trim(entry)).any(|entry| {
+            entry == registry
+                || (registry == config.name && entry == trim(&config.base_url))
+                || registry.starts_with(&format!("{}/", entry))
+        })
+    }
+}
+
+/// The dependency graph of a project, with what the policy needs to know
+///
+/// Publication dates come from the registry's local index, so evaluating a
+/// policy does not need the network.
+pub fn packages(
+    project_root: &Path,
+    config: &ProjectConfig,
+    registry: &RegistryClient,
+) -> Result<Vec<PolicyPackage>, ForgeKitError> {
+    let overrides = Overrides::from_config(config)?;
+    let lockfile = Lockfile::load(project_root)?;
+    overrides::resolve_graph_with_depth(project_root, config, &overrides)
+        .into_iter()
+        .map(|(dep, depth)| {
+            let source = source_of(&dep, &overrides, registry.config());
+            let (version, published) = match source {
+                PackageSource::Registry(_) => match resolve_version(&dep, &lockfile, registry)? {
+                    Some(version) => {
+                        let published = registry.published_at(&dep.name, &version)?;
+                        (version, published)
+                    }
+                    None => (dep.version, None),
+                },
+                _ => (dep.version, None),
+            };
+            Ok(PolicyPackage {
+                name: dep.name,
+                version,
+                source,
+                depth,
+                published,
+            })
+        })
+        .collect()
+}
+
+/// The concrete version a registry dependency resolves to
+///
+/// A requirement such as `*` is not a version, so the locked version is
+/// used when it satisfies the requirement, and otherwise the newest indexed
+/// version that does.
+fn resolve_version(
+    dep: &Dependency,
+    lockfile: &Lockfile,
+    registry: &RegistryClient,
+) -> Result<Option<String>, ForgeKitError> {
+    let Ok(req) = VersionReq::parse(&dep.version) else {
+        return Ok(None);
+    };
+    if let Some(locked) = lockfile.get(&dep.name) {
+        if req.matches(&locked.version) {
+            return Ok(Some(locked.version.clone()));
+        }
+    }
+    Ok(registry.indexed_versions(&dep.name)?.and_then(|versions| {
+        versions
+            .into_iter()
+            .filter(|version| req.matches(version))
+            .max_by(|a, b| compare_versions(a, b))
+    }))
+}
+
+/// Evaluate the policy of a project, if it has one, against its dependencies
+pub fn check(
+    project_root: &Path,
+    config: &ProjectConfig,
+    registry: &RegistryClient,
+    stage: PolicyStage,
+) -> Result<Vec<PolicyViolation>, ForgeKitError> {
+    let Some(policy) = Policy::load(project_root)? else {
+        return Ok(Vec::new());
+    };
+    let packages = packages(project_root, config, registry)?;
+    Ok(policy.evaluate(&packages, stage, registry.config()))
+}
+
+/// Fail with every violation of the project's policy at `stage`
+pub fn enforce(
+    project_root: &Path,
+    config: &ProjectConfig,
+    registry: &RegistryClient,
+    stage: PolicyStage,
+) -> Result<(), ForgeKitError> {
+    let violations = check(project_root, config, registry, stage)?;
+    if violations.is_empty() {
+        return Ok(());
+    }
+    let list: Vec<String> = violations.iter().map(|v| format!("  - {}", v)).collect();
+    Err(ForgeKitError::PolicyViolation(format!(
+        "{} violation(s) of {}\n{}",
+        violations.len(),
+        POLICY_FILE,
+        list.join("\n")
+    )))
+}
+
+/// Where a dependency comes from, after `[patch]` and `[replace]`
+fn source_of(dep: &Dependency, overrides: &Overrides, registry: &RegistryConfig) -> PackageSource {
+    if let Some(source) = overrides.source_for(&dep.name, &dep.version) {
+        if let Some(git) = &source.git {
+            return PackageSource::Git(git.clone());
+        }
+        if let Some(path) = &source.path {
+            return PackageSource::Path(path.clone());
+        }
+    }
+    match dep.source.as_deref() {
+        None | Some("registry") => PackageSource::Registry(registry.name.clone()),
+        Some(source) if source.starts_with("git+") || source.ends_with(".git") => {
+            PackageSource::Git(source.trim_start_matches("git+").to_string())
+        }
+        Some(source) if source.contains("://") => PackageSource::Registry(source.to_string()),
+        Some(source) => PackageSource::Path(source.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PatchSource;
+    use crate::lockfile::LockedPackage;
+    use crate::registry::{IndexEntry, VersionInfo};
+    use tempfile::TempDir;
+
+    fn dependency(name: &str, source: Option<&str>) -> Dependency {
+        Dependency {
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            source: source.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_policy_reports_every_violation() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::write(
+            Policy::path(root),
+            r#"
+[sources]
+deny_git_in_release = true
+allowed_registries = ["https://github.com/", "https://mirror.ledokoz.com"]
+
+[dependencies]
+max_depth = 1
+min_age_days = 7
+"#,
+        )
+        .unwrap();
+        let policy = Policy::load(root).unwrap().unwrap();
+        std::fs::write(Policy::path(root), "[sources]\nallow_git = true\n").unwrap();
+        assert!(Policy::load(root).is_err());
+
+        // `leaf` is pulled in by the local `widgets` package
+        let widgets = root.join("widgets");
+        std::fs::create_dir_all(&widgets).unwrap();
+        let widgets_config = ProjectConfig {
+            dependencies: vec![dependency("leaf", None)],
+            ..Default::default()
+        };
+        widgets_config.save(widgets.join("forgekit.toml")).unwrap();
+
+        let mut config = ProjectConfig {
+            dependencies: vec![
+                dependency("widgets", None),
+                dependency("mirrored", Some("https://mirror.ledokoz.com/mirrored")),
+                dependency("unknown", Some("https://packages.example.com/unknown")),
+                dependency("forked", None),
+            ],
+            ..Default::default()
+        };
+        config.patch.insert(
+            "widgets".to_string(),
+            PatchSource {
+                path: Some("widgets".to_string()),
+                ..Default::default()
+            },
+        );
+        config.patch.insert(
+            "forked".to_string(),
+            PatchSource {
+                git: Some("https://github.com/me/forked".to_string()),
+                ..Default::default()
+            },
+        );
+        let registry = RegistryClient::new(RegistryConfig {
+            index_dir: root.join("index"),
+            ..Default::default()
+        })
+        .unwrap();
+        let mut packages = packages(root, &config, &registry).unwrap();
+        let depth = |name: &str| packages.iter().find(|p| p.name == name).unwrap().depth;
+        assert_eq!((depth("widgets"), depth("leaf")), (1, 2));
+        for package in &mut packages {
+            package.published = Some(chrono::Utc::now().to_rfc3339());
+        }
+        packages
+            .iter_mut()
+            .find(|p| p.name == "mirrored")
+            .unwrap()
+            .published = Some("2020-01-01T00:00:00Z".to_string());
+
+        let rules = |stage| {
+            policy
+                .evaluate(&packages, stage, registry.config())
+                .into_iter()
+                .map(|v| (v.package, v.rule))
+                .collect::<Vec<_>>()
+        };
+        let resolution = rules(PolicyStage::Resolution);
+        assert_eq!(
+            resolution,
+            [
+                ("unknown@1.0.0".to_string(), "sources.allowed_registries"),
+                ("unknown@1.0.0".to_string(), "dependencies.min_age_days"),
+                ("leaf@1.0.0".to_string(), "dependencies.max_depth"),
+                ("leaf@1.0.0".to_string(), "dependencies.min_age_days"),
+            ]
+        );
+        let release = rules(PolicyStage::Release);
+        assert_eq!(release.len(), resolution.len() + 1);
+        assert!(release.contains(&("forked@1.0.0".to_string(), "sources.deny_git_in_release")));
+    }
+
+    #[test]
+    fn test_min_age_resolves_requirements() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let registry = RegistryClient::new(RegistryConfig {
+            index_dir: root.join("index"),
+            ..Default::default()
+        })
+        .unwrap();
+        let version = |version: &str, published: &str| VersionInfo {
+            version: version.to_string(),
+            git_ref: format!("v{}", version),
+            archive_url: String::new(),
+            published: published.to_string(),
+            checksum: String::new(),
+            plugin: None,
+        };
+        registry
+            .write_index_entry(&IndexEntry {
+                name: "fresh".to_string(),
+                versions: [
+                    (
+                        "1.0.0".to_string(),
+                        version("1.0.0", "2020-01-01T00:00:00Z"),
+                    ),
+                    (
+                        "1.1.0".to_string(),
+                        version("1.1.0", &chrono::Utc::now().to_rfc3339()),
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+                latest: "1.1.0".to_string(),
+            })
+            .unwrap();
+        let config = ProjectConfig {
+            dependencies: vec![Dependency {
+                name: "fresh".to_string(),
+                version: "*".to_string(),
+                source: None,
+            }],
+            ..Default::default()
+        };
+        let policy = Policy {
+            dependencies: DependencyPolicy {
+                min_age_days: Some(7),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let check = || {
+            let packages = packages(root, &config, &registry).unwrap();
+            policy
+                .evaluate(&packages, PolicyStage::Resolution, registry.config())
+                .into_iter()
+                .map(|v| v.package)
+                .collect::<Vec<_>>()
+        };
+
+        // Without a lockfile `*` means the newest indexed version
+        assert_eq!(check(), ["fresh@1.1.0"]);
+
+        let mut lockfile = Lockfile::default();
+        lockfile.upsert(LockedPackage {
+            name: "fresh".to_string(),
+            version: "1.0.0".to_string(),
+            source: None,
+            checksum: None,
+        });
+        lockfile.save(root).unwrap();
+        assert!(check().is_empty());
+    }
+}
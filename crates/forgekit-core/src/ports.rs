@@ -0,0 +1,178 @@
+//! Ports module
+//!
+//! This module checks the ports an app needs before `forgekit run` starts it
+//! or the dev server starts. Ports come from the `[services]` declared in
+//! forgekit.toml; each one is probed by binding it, and ports that are taken
+//! get the next free port suggested. With auto-assignment, conflicting
+//! services are moved to the suggested port and told about it through their
+//! environment variable, instead of failing with a bind error mid-start.
+
+use crate::config::ProjectConfig;
+use crate::error::ForgeKitError;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::net::TcpListener;
+
+/// How many ports after a taken one are tried for a suggestion
+const SEARCH_RANGE: u16 = 100;
+
+/// Availability of one declared port
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PortCheck {
+    /// Service the port belongs to
+    pub service: String,
+    /// Declared port
+    pub port: u16,
+    /// Environment variable the service reads its port from
+    pub env: String,
+    /// Whether the port can be bound
+    pub available: bool,
+    /// Next free port, when the declared one is taken
+    pub suggested: Option<u16>,
+}
+
+impl PortCheck {
+    /// Port the service ends up on when conflicts are auto-assigned
+    pub fn assigned(&self) -> u16 {
+        if self.available {
+            self.port
+        } else {
+            self.suggested.unwrap_or(self.port)
+        }
+    }
+}
+
+/// Whether a TCP port can be bound on all interfaces
+pub fn is_free(port: u16) -> bool {
+    TcpListener::bind(("0.0.0.0", port)).is_ok()
+}
+
+/// Next free port after `port` that is not in `claimed`
+pub fn next_free(port: u16, claimed: &BTreeSet<u16>) -> Option<u16> {
+    (1..=SEARCH_RANGE)
+        .filter_map(|offset| port.checked_add(offset))
+        .find(|candidate| !claimed.contains(candidate) && is_free(*candidate))
+}
+
+/// Probe the ports of the project's services and of any extra ports
+///
+/// `extra` lists ports needed besides the declared services, e.g. the dev
+/// server, as `(name, port, env)`. Two services declaring the same port
+/// conflict with each other as well.
+pub fn check(config: &ProjectConfig, extra: &[(&str, u16, &str)]) -> Vec<PortCheck> {
+    let declared = config
+        .services
+        .iter()
+        .map(|(name, service)| (name.clone(), service.port, service.env_var(name)))
+        .chain(
+            extra
+                .iter()
+                .map(|(name, port, env)| (name.to_string(), *port, env.to_string())),
+        );
+
+    let mut claimed: BTreeSet<u16> = BTreeSet::new();
+    let mut checks = Vec::new();
+    for (service, port, env) in declared {
+        let available = !claimed.contains(&port) && is_free(port);
+        let suggested = (!available).then(|| next_free(port, &claimed)).flatten();
+        let check = PortCheck {
+            service,
+            port,
+            env,
+            available,
+            suggested,
+        };
+        claimed.insert(check.assigned());
+        checks.push(check);
+    }
+    checks
+}
+
+/// Environment variables pointing every service at its port
+///
+/// Without `auto_assign`, a taken port is an error naming the suggested
+/// replacement; with it, conflicting services get the suggested port.
+pub fn resolve(
+    checks: &[PortCheck],
+    auto_assign: bool,
+) -> Result<Vec<(String, String)>, ForgeKitError> {
+    let conflicts: Vec<String> = checks
+        .iter()
+        .filter(|check| !check.available && (!auto_assign || check.suggested.is_none()))
+        .map(|check| match check.suggested {
+            Some(free) => format!(
+                "port {} of '{}' is in use (next free port: {})",
+                check.port, check.service, free
+            ),
+            None => format!(
+                "port {} of '{}' is in use and no free port was found",
+                check.port, check.service
+            ),
+        })
+        .collect();
+    if !conflicts.is_empty() {
+        return Err(ForgeKitError::PortConflict(format!(
+            "{}; pass --auto-ports to use the free ports",
+            conflicts.join(", ")
+        )));
+    }
+
+    Ok(checks
+        .iter()
+        .map(|check| (check.env.clone(), check.assigned().to_string()))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ServiceConfig;
+
+    #[test]
+    fn test_conflicts_are_detected_and_assigned() {
+        let taken = TcpListener::bind(("0.0.0.0", 0)).unwrap();
+        let port = taken.local_addr().unwrap().port();
+
+        let mut config = ProjectConfig::default();
+        config
+            .services
+            .insert("api".to_string(), ServiceConfig { port, env: None });
+        let checks = check(&config, &[]);
+        assert_eq!(checks.len(), 1);
+        assert_eq!(checks[0].env, "API_PORT");
+        assert!(!checks[0].available);
+        let free = checks[0].suggested.unwrap();
+        assert!(free > port);
+
+        let err = resolve(&checks, false).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains(&format!("next free port: {}", free)));
+        assert_eq!(
+            resolve(&checks, true).unwrap(),
+            [("API_PORT".to_string(), free.to_string())]
+        );
+    }
+
+    #[test]
+    fn test_services_sharing_a_port_conflict() {
+        let free = TcpListener::bind(("0.0.0.0", 0))
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let mut config = ProjectConfig::default();
+        config.services.insert(
+            "web".to_string(),
+            ServiceConfig {
+                port: free,
+                env: Some("PORT".to_string()),
+            },
+        );
+
+        let checks = check(&config, &[("dev-server", free, "FORGEKIT_DEV_PORT")]);
+        assert!(checks[0].available);
+        assert!(!checks[1].available);
+        assert_ne!(checks[1].assigned(), free);
+    }
+}
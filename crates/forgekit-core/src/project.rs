@@ -1,12 +1,222 @@
 //! Project scaffolding and management
 
-use crate::config::ProjectConfig;
+use crate::atomic;
+use crate::config::{ProjectConfig, TemplateConfig};
+use crate::dry_run::{DryRun, PlannedAction};
 use crate::error::ForgeKitError;
-use std::path::Path;
+use crate::journal::Journal;
+use crate::template_update;
+use crate::templates::{self, TemplateType};
+use regex::Regex;
+use std::path::{Path, PathBuf};
 use tokio::fs;
+use tokio::process::Command;
+
+/// License written to LICENSE when a project is created
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum License {
+    /// MIT License
+    Mit,
+    /// BSD 3-Clause License
+    Bsd3Clause,
+    /// ISC License
+    Isc,
+    /// The Unlicense, dedicating the project to the public domain
+    Unlicense,
+}
+
+impl License {
+    /// SPDX identifier of the license
+    pub fn spdx(&self) -> &'static str {
+        match self {
+            License::Mit => "MIT",
+            License::Bsd3Clause => "BSD-3-Clause",
+            License::Isc => "ISC",
+            License::Unlicense => "Unlicense",
+        }
+    }
+
+    /// LICENSE file contents for the given copyright holders
+    pub fn text(&self, year: i32, holders: &str) -> String {
+        match self {
+            License::Mit => format!(
+                r#"MIT License
+
+Copyright (c) {year} {holders}
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+"#
+            ),
+            License::Bsd3Clause => format!(
+                r#"BSD 3-Clause License
+
+Copyright (c) {year}, {holders}
+
+Redistribution and use in source and binary forms, with or without
+modification, are permitted provided that the following conditions are met:
+
+1. Redistributions of source code must retain the above copyright notice, this
+   list of conditions and the following disclaimer.
+
+2. Redistributions in binary form must reproduce the above copyright notice,
+   this list of conditions and the following disclaimer in the documentation
+   and/or other materials provided with the distribution.
+
+3. Neither the name of the copyright holder nor the names of its
+   contributors may be used to endorse or promote products derived from
+   this software without specific prior written permission.
+
+THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+"#
+            ),
+            License::Isc => format!(
+                r#"ISC License
+
+Copyright (c) {year} {holders}
+
+Permission to use, copy, modify, and/or distribute this software for any
+purpose with or without fee is hereby granted, provided that the above
+copyright notice and this permission notice appear in all copies.
+
+THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+"#
+            ),
+            License::Unlicense => {
+                r#"This is free and unencumbered software released into the public domain.
+
+Anyone is free to copy, modify, publish, use, compile, sell, or
+distribute this software, either in source code form or as a compiled
+binary, for any purpose, commercial or non-commercial, and by any
+means.
+
+In jurisdictions that recognize copyright laws, the author or authors
+of this software dedicate any and all copyright interest in the
+software to the public domain. We make this dedication for the benefit
+of the public at large and to the detriment of our heirs and
+successors. We intend this dedication to be an overt act of
+relinquishment in perpetuity of all present and future rights to this
+software under copyright law.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND,
+EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF
+MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.
+IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY CLAIM, DAMAGES OR
+OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE,
+ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR
+OTHER DEALINGS IN THE SOFTWARE.
+
+For more information, please refer to <https://unlicense.org>
+"#
+                .to_string()
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for License {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "mit" => Ok(License::Mit),
+            "bsd-3-clause" | "bsd3" => Ok(License::Bsd3Clause),
+            "isc" => Ok(License::Isc),
+            "unlicense" => Ok(License::Unlicense),
+            _ => Err(format!(
+                "unknown license '{}' (expected mit, bsd-3-clause, isc or unlicense)",
+                s
+            )),
+        }
+    }
+}
+
+/// Version control set up for a new project
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Vcs {
+    /// A git repository with a .gitignore
+    #[default]
+    Git,
+    /// No version control
+    None,
+}
+
+impl Vcs {
+    /// Name used on the command line
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Vcs::Git => "git",
+            Vcs::None => "none",
+        }
+    }
+}
+
+impl std::str::FromStr for Vcs {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "git" => Ok(Vcs::Git),
+            "none" => Ok(Vcs::None),
+            _ => Err(format!("unknown vcs '{}' (expected git or none)", s)),
+        }
+    }
+}
+
+/// Options for creating a project
+#[derive(Debug, Clone, Default)]
+pub struct InitOptions {
+    /// Template to generate; a minimal app when unset
+    pub template: Option<TemplateType>,
+    /// License written to LICENSE; no LICENSE file when unset
+    pub license: Option<License>,
+    /// Version control to set up
+    pub vcs: Vcs,
+    /// Authors recorded in forgekit.toml; detected from git config when empty
+    pub authors: Vec<String>,
+}
 
 /// Initialize a new project at the given path
 pub async fn init(name: &str, path: &Path) -> Result<(), ForgeKitError> {
+    init_with_options(name, path, &InitOptions::default()).await
+}
+
+/// Initialize a new project with a template, license and version control
+pub async fn init_with_options(
+    name: &str,
+    path: &Path,
+    options: &InitOptions,
+) -> Result<(), ForgeKitError> {
     tracing::info!("Initializing new project '{}' at {:?}", name, path);
 
     // Check if directory exists
@@ -16,37 +226,405 @@ pub async fn init(name: &str, path: &Path) -> Result<(), ForgeKitError> {
         ));
     }
 
-    // Create project directory
-    fs::create_dir_all(path).await?;
+    scaffold(name, path, options).await?;
+    if options.vcs == Vcs::Git {
+        init_git(path).await;
+    }
+
+    tracing::info!("Project '{}' initialized successfully", name);
+    Ok(())
+}
+
+/// Files a project created with `options` would contain, relative to it
+///
+/// The project is generated in a temporary directory, so the preview is
+/// exact while nothing is created at the real location.
+pub async fn preview(name: &str, options: &InitOptions) -> Result<Vec<PathBuf>, ForgeKitError> {
+    let staging = tempfile::TempDir::new()?;
+    let root = staging.path().join(name);
+    scaffold(name, &root, options).await?;
+
+    let mut files: Vec<PathBuf> = walkdir::WalkDir::new(&root)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.path().strip_prefix(&root).ok().map(Path::to_path_buf))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Authors of a new project, as `Name <email>` from git config when set
+pub async fn detect_authors() -> Vec<String> {
+    let name = git_config("user.name").await;
+    let email = git_config("user.email").await;
+    let author = match (name, email) {
+        (Some(name), Some(email)) => format!("{} <{}>", name, email),
+        (Some(name), None) => name,
+        (None, _) => whoami::username(),
+    };
+    vec![author]
+}
+
+/// Files besides the manifests that can refer to the built app
+const REFERENCE_FILES: &[&str] = &[
+    "Dockerfile",
+    "docker-compose.yml",
+    "docker-compose.yaml",
+    ".gitlab-ci.yml",
+    "Jenkinsfile",
+    "Makefile",
+    "justfile",
+];
+
+/// Directories whose files can refer to the built app
+const REFERENCE_DIRS: &[&str] = &[".github/workflows", "scripts"];
+
+/// Directories whose Rust sources can refer to the crate by name
+const SOURCE_DIRS: &[&str] = &["src", "tests", "benches", "examples"];
+
+/// Rename a project, returning the files that changed
+///
+/// Updates the name in forgekit.toml, the package, binary and library
+/// names in Cargo.toml, paths to the built binary and `.mox` in
+/// Dockerfiles, CI configuration and scripts, and the crate name in `use`
+/// and `extern crate` items of Rust sources. Every file is journaled, so a failure part way leaves the
+/// project as it was. With `dry_run` enabled the edits are only recorded.
+pub fn rename(
+    path: &Path,
+    new_name: &str,
+    dry_run: &DryRun,
+) -> Result<Vec<PathBuf>, ForgeKitError> {
+    validate_name(new_name)?;
+    let old_name = ProjectConfig::load(path.join("forgekit.toml"))?.name;
+    if old_name == new_name {
+        return Ok(Vec::new());
+    }
+    tracing::info!("Renaming project '{}' to '{}'", old_name, new_name);
+
+    let edits = rename_edits(path, &old_name, new_name)?;
+    let files: Vec<PathBuf> = edits.iter().map(|(file, _)| file.clone()).collect();
+    if dry_run.is_enabled() {
+        for file in &files {
+            dry_run.record(PlannedAction::UpdateFile {
+                path: file.clone(),
+                change: format!("rename {} to {}", old_name, new_name),
+            });
+        }
+        return Ok(files);
+    }
+
+    let mut journal = Journal::begin(path, &format!("rename {}", new_name))?;
+    let result = edits.iter().try_for_each(|(file, contents)| {
+        journal.track_file(file)?;
+        atomic::write(file, contents)
+    });
+    journal.finish(result)?;
+    Ok(files)
+}
+
+/// Check that `name` can be used as a package and binary name
+fn validate_name(name: &str) -> Result<(), ForgeKitError> {
+    let valid = name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(ForgeKitError::InvalidConfig(format!(
+            "Invalid project name '{}': use letters, digits, '-' and '_', starting with a letter",
+            name
+        )))
+    }
+}
+
+/// New contents of every file that mentions `old` as the project name
+fn rename_edits(
+    path: &Path,
+    old: &str,
+    new: &str,
+) -> Result<Vec<(PathBuf, String)>, ForgeKitError> {
+    let mut edits = Vec::new();
+
+    let config_path = path.join("forgekit.toml");
+    let mut config = parse_toml(&config_path)?;
+    config["name"] = toml_edit::value(new);
+    edits.push((config_path, config.to_string()));
+
+    let cargo_path = path.join("Cargo.toml");
+    if cargo_path.exists() {
+        let mut manifest = parse_toml(&cargo_path)?;
+        let before = manifest.to_string();
+        rename_cargo_targets(&mut manifest, old, new);
+        if manifest.to_string() != before {
+            edits.push((cargo_path, manifest.to_string()));
+        }
+    }
+
+    let mut references: Vec<PathBuf> = REFERENCE_FILES.iter().map(|f| path.join(f)).collect();
+    references.extend(files_under(path, REFERENCE_DIRS, |_| true));
+    let patterns = reference_patterns(old, new);
+    edits.extend(rewrite(&references, &patterns)?);
+
+    let (old_crate, new_crate) = (old.replace('-', "_"), new.replace('-', "_"));
+    let sources = files_under(path, SOURCE_DIRS, |file| {
+        file.extension().is_some_and(|ext| ext == "rs")
+    });
+    // Only `use` and `extern crate` name the crate itself; other paths
+    // starting with the name may be a module of the same name
+    let old_crate = regex::escape(&old_crate);
+    let crate_patterns = [
+        format!(r"(\buse\s+(?:::)?){}\b", old_crate),
+        format!(r"(\bextern\s+crate\s+){}\b", old_crate),
+    ]
+    .into_iter()
+    .map(|pattern| {
+        let regex = Regex::new(&pattern).expect("escaped crate name is a valid pattern");
+        (regex, format!("${{1}}{}", new_crate))
+    })
+    .collect::<Vec<_>>();
+    edits.extend(rewrite(&sources, &crate_patterns)?);
+    Ok(edits)
+}
+
+/// Rename the package and the targets named after it
+fn rename_cargo_targets(manifest: &mut toml_edit::DocumentMut, old: &str, new: &str) {
+    let (old_crate, new_crate) = (old.replace('-', "_"), new.replace('-', "_"));
+    let rename_if = |item: Option<&mut toml_edit::Item>, from: &str, to: &str| {
+        if let Some(name) = item.filter(|name| name.as_str() == Some(from)) {
+            *name = toml_edit::value(to);
+        }
+    };
+    rename_if(
+        manifest.get_mut("package").and_then(|p| p.get_mut("name")),
+        old,
+        new,
+    );
+    rename_if(
+        manifest.get_mut("lib").and_then(|l| l.get_mut("name")),
+        &old_crate,
+        &new_crate,
+    );
+    if let Some(bins) = manifest
+        .get_mut("bin")
+        .and_then(|b| b.as_array_of_tables_mut())
+    {
+        for bin in bins.iter_mut() {
+            rename_if(bin.get_mut("name"), old, new);
+        }
+    }
+}
+
+/// Patterns for the places a script or CI file names the built app
+///
+/// Only paths and flags are matched, not the bare word, so a project
+/// called `app` keeps its `/app` work directories.
+fn reference_patterns(old: &str, new: &str) -> Vec<(Regex, String)> {
+    const END: &str = r"([^A-Za-z0-9_-]|$)";
+    let old = regex::escape(old);
+    [
+        // target/release/old, target/x86_64-ledokoz/release/old.exe
+        (
+            format!(r"(target/(?:[A-Za-z0-9_.-]+/)*){}(\.exe)?{}", old, END),
+            "${1}NEW${2}${3}",
+        ),
+        // old.mox, dist/old-1.2.0.mox
+        (
+            format!(
+                r"(^|[^A-Za-z0-9_-]){}((?:-[0-9][A-Za-z0-9.+-]*)?\.mox)",
+                old
+            ),
+            "${1}NEW${2}",
+        ),
+        // cargo run --bin old, cargo build -p old
+        (
+            format!(r"(--bin[ =]|--package[ =]|-p ){}{}", old, END),
+            "${1}NEW${2}",
+        ),
+        // ./old, /usr/local/bin/old
+        (format!(r"(\./|bin/){}{}", old, END), "${1}NEW${2}"),
+        // CMD ["old"]
+        (
+            format!(r#"((?:CMD|ENTRYPOINT) *\[ *"){}(")"#, old),
+            "${1}NEW${2}",
+        ),
+    ]
+    .into_iter()
+    .map(|(pattern, replacement)| {
+        let regex = Regex::new(&pattern).expect("escaped project name is a valid pattern");
+        (regex, replacement.replace("NEW", new))
+    })
+    .collect()
+}
+
+/// New contents of the `files` the patterns change
+///
+/// Files that are not UTF-8 text, like binaries kept next to scripts, are
+/// left alone.
+fn rewrite(
+    files: &[PathBuf],
+    patterns: &[(Regex, String)],
+) -> Result<Vec<(PathBuf, String)>, ForgeKitError> {
+    let mut edits = Vec::new();
+    for file in files.iter().filter(|file| file.is_file()) {
+        let Ok(original) = String::from_utf8(std::fs::read(file)?) else {
+            continue;
+        };
+        let mut contents = original.clone();
+        for (regex, replacement) in patterns {
+            contents = regex
+                .replace_all(&contents, replacement.as_str())
+                .into_owned();
+        }
+        if contents != original {
+            edits.push((file.clone(), contents));
+        }
+    }
+    Ok(edits)
+}
+
+/// Files under the given project directories, sorted
+fn files_under(path: &Path, dirs: &[&str], keep: impl Fn(&Path) -> bool) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = dirs
+        .iter()
+        .flat_map(|dir| walkdir::WalkDir::new(path.join(dir)).into_iter())
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && keep(e.path()))
+        .map(|e| e.into_path())
+        .collect();
+    files.sort();
+    files
+}
+
+fn parse_toml(path: &Path) -> Result<toml_edit::DocumentMut, ForgeKitError> {
+    std::fs::read_to_string(path)?
+        .parse()
+        .map_err(|e| ForgeKitError::InvalidConfig(format!("Invalid {}: {}", path.display(), e)))
+}
+
+/// Write every file of a new project
+async fn scaffold(name: &str, path: &Path, options: &InitOptions) -> Result<(), ForgeKitError> {
+    match &options.template {
+        Some(template) => {
+            templates::generate_from_template(name, template.clone(), path).await?;
+            // Keep the pristine output as the base of later template updates
+            template_update::record_base(path)?;
+        }
+        None => generate_minimal(name, path).await?,
+    }
+
+    // Templates that do not write forgekit.toml get the default one
+    let authors = if options.authors.is_empty() {
+        detect_authors().await
+    } else {
+        options.authors.clone()
+    };
+    let config_path = path.join("forgekit.toml");
+    let mut config = if config_path.exists() {
+        ProjectConfig::load(&config_path)?
+    } else {
+        ProjectConfig {
+            name: name.to_string(),
+            version: "0.1.0".to_string(),
+            description: Some("A new .mox app built with ForgeKit".to_string()),
+            ..Default::default()
+        }
+    };
+    config.authors = authors.clone();
+    config.template = options.template.as_ref().map(|template| TemplateConfig {
+        name: template.as_str().to_string(),
+        version: template.version(),
+    });
+    config.save(&config_path)?;
+
+    if let Some(license) = options.license {
+        let year = chrono::Datelike::year(&chrono::Utc::now());
+        fs::write(
+            path.join("LICENSE"),
+            license.text(year, &authors.join(", ")),
+        )
+        .await?;
+        set_cargo_license(path, license)?;
+    }
 
-    // Create src directory
+    if options.vcs == Vcs::Git && !path.join(".gitignore").exists() {
+        fs::write(path.join(".gitignore"), generate_gitignore()).await?;
+    }
+    Ok(())
+}
+
+/// The app created when no template is chosen
+async fn generate_minimal(name: &str, path: &Path) -> Result<(), ForgeKitError> {
+    // Create src and assets directories
     let src_path = path.join("src");
     fs::create_dir_all(&src_path).await?;
-
-    // Create assets directory
-    let assets_path = path.join("assets");
-    fs::create_dir_all(&assets_path).await?;
+    fs::create_dir_all(path.join("assets")).await?;
 
     // Create initial main.rs
     let main_rs_content = generate_main_rs(name);
     fs::write(src_path.join("main.rs"), main_rs_content).await?;
+    Ok(())
+}
 
-    // Create forgekit.toml
-    let config = ProjectConfig {
-        name: name.to_string(),
-        version: "0.1.0".to_string(),
-        description: Some("A new .mox app built with ForgeKit".to_string()),
-        authors: vec![whoami::username()],
-        ..Default::default()
-    };
-    config.save(path.join("forgekit.toml"))?;
+/// Record the license in Cargo.toml, for templates that generate one
+fn set_cargo_license(path: &Path, license: License) -> Result<(), ForgeKitError> {
+    let cargo_toml = path.join("Cargo.toml");
+    if !cargo_toml.exists() {
+        return Ok(());
+    }
+    let mut manifest: toml_edit::DocumentMut = std::fs::read_to_string(&cargo_toml)?
+        .parse()
+        .map_err(|e| ForgeKitError::InvalidConfig(format!("Invalid Cargo.toml: {}", e)))?;
+    if let Some(package) = manifest.get_mut("package").and_then(|p| p.as_table_mut()) {
+        package.insert("license", toml_edit::value(license.spdx()));
+    }
+    std::fs::write(cargo_toml, manifest.to_string())?;
+    Ok(())
+}
 
-    // Create .gitignore
-    let gitignore_content = generate_gitignore();
-    fs::write(path.join(".gitignore"), gitignore_content).await?;
+/// Turn the project into a git repository
+///
+/// A project created inside an existing work tree, like a new member of a
+/// workspace, is left to that repository instead of getting a nested one.
+/// A missing or failing git only costs the repository, so it is logged
+/// rather than failing the whole project.
+async fn init_git(path: &Path) {
+    let inside = Command::new("git")
+        .args(["rev-parse", "--is-inside-work-tree"])
+        .current_dir(path)
+        .output()
+        .await;
+    if let Ok(output) = inside {
+        if output.status.success() && String::from_utf8_lossy(&output.stdout).trim() == "true" {
+            tracing::info!(
+                "{} is inside a git work tree; not running git init",
+                path.display()
+            );
+            return;
+        }
+    }
 
-    tracing::info!("Project '{}' initialized successfully", name);
-    Ok(())
+    let result = Command::new("git")
+        .args(["init", "--quiet"])
+        .current_dir(path)
+        .status()
+        .await;
+    match result {
+        Ok(status) if status.success() => {}
+        Ok(status) => tracing::warn!("git init failed ({}); skipping version control", status),
+        Err(e) => tracing::warn!("git is not available ({}); skipping version control", e),
+    }
+}
+
+async fn git_config(key: &str) -> Option<String> {
+    let output = Command::new("git")
+        .args(["config", "--get", key])
+        .output()
+        .await
+        .ok()?;
+    let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !value.is_empty()).then_some(value)
 }
 
 /// Generate the main.rs template
@@ -68,6 +646,19 @@ fn main() {{
 fn generate_gitignore() -> String {
     r#"# Generated by ForgeKit
 target/
+.forgekit/dev-cert/
+.forgekit/ota.key
+# Local state of forgekit runs
+.forgekit/lock
+.forgekit/journal/
+.forgekit/builds/
+.forgekit/cache/
+.forgekit/logs/
+.forgekit/crashes/
+.forgekit/artifacts/
+.forgekit/symbols/
+.forgekit/tools/
+.forgekit/toolchain/
 **/*.mo
 **/*.mox
 **/*.log
@@ -85,3 +676,122 @@ mod whoami {
             .unwrap_or_else(|_| "developer".to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_init_with_license_and_template() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("greeter");
+        let options = InitOptions {
+            template: Some(TemplateType::Library),
+            license: Some(License::Mit),
+            vcs: Vcs::None,
+            authors: vec!["Ada <ada@example.com>".to_string()],
+        };
+
+        let preview = preview("greeter", &options).await.unwrap();
+        assert!(!path.exists());
+        init_with_options("greeter", &path, &options).await.unwrap();
+
+        let created: Vec<PathBuf> = ["Cargo.toml", "LICENSE", "forgekit.toml"]
+            .iter()
+            .map(PathBuf::from)
+            .filter(|file| preview.contains(file))
+            .collect();
+        assert_eq!(created.len(), 3);
+        let license = std::fs::read_to_string(path.join("LICENSE")).unwrap();
+        assert!(license.contains("Ada <ada@example.com>"));
+        let cargo = std::fs::read_to_string(path.join("Cargo.toml")).unwrap();
+        assert!(cargo.contains("license = \"MIT\""));
+        let config = ProjectConfig::load(path.join("forgekit.toml")).unwrap();
+        assert_eq!(config.authors, vec!["Ada <ada@example.com>"]);
+        assert!(!path.join(".gitignore").exists());
+    }
+
+    #[tokio::test]
+    async fn test_init_inside_a_repository_keeps_one_repository() {
+        let temp_dir = TempDir::new().unwrap();
+        let status = std::process::Command::new("git")
+            .args(["init", "--quiet"])
+            .current_dir(temp_dir.path())
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let path = temp_dir.path().join("member");
+        let options = InitOptions {
+            vcs: Vcs::Git,
+            ..Default::default()
+        };
+        init_with_options("member", &path, &options).await.unwrap();
+        assert!(path.join("forgekit.toml").exists());
+        assert!(!path.join(".git").exists());
+        let gitignore = std::fs::read_to_string(path.join(".gitignore")).unwrap();
+        for ignored in [
+            ".forgekit/lock",
+            ".forgekit/journal/",
+            ".forgekit/builds/",
+            ".forgekit/toolchain/",
+        ] {
+            assert!(gitignore.lines().any(|line| line == ignored), "{}", ignored);
+        }
+    }
+
+    #[test]
+    fn test_rename_updates_manifests_and_references() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let config = ProjectConfig {
+            name: "app".to_string(),
+            ..Default::default()
+        };
+        config.save(root.join("forgekit.toml")).unwrap();
+        std::fs::write(
+            root.join("Cargo.toml"),
+            "[package]\nname = \"app\"\nversion = \"0.1.0\"\n\n[[bin]]\nname = \"app\"\npath = \"src/main.rs\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("Dockerfile"),
+            "WORKDIR /app\nCOPY --from=builder /app/target/release/app /usr/local/bin/app\nCMD [\"app\"]\n",
+        )
+        .unwrap();
+        std::fs::create_dir_all(root.join("tests")).unwrap();
+        std::fs::write(
+            root.join("tests/it.rs"),
+            "extern crate app;\nuse app::greet;\nlet app = 1;\nlet v = app::helpers::run();\n",
+        )
+        .unwrap();
+        // Binaries among the scripts are skipped rather than failing the rename
+        std::fs::create_dir_all(root.join("scripts")).unwrap();
+        std::fs::write(root.join("scripts/app.bin"), [0x61, 0x70, 0x70, 0xff]).unwrap();
+
+        let dry_run = DryRun::enabled();
+        assert_eq!(rename(root, "photo-viewer", &dry_run).unwrap().len(), 4);
+        assert_eq!(dry_run.plan().actions.len(), 4);
+        assert!(rename(root, "9lives", &DryRun::disabled()).is_err());
+
+        rename(root, "photo-viewer", &DryRun::disabled()).unwrap();
+        let config = ProjectConfig::load(root.join("forgekit.toml")).unwrap();
+        assert_eq!(config.name, "photo-viewer");
+        let cargo = std::fs::read_to_string(root.join("Cargo.toml")).unwrap();
+        assert_eq!(cargo.matches("name = \"photo-viewer\"").count(), 2);
+        assert_eq!(
+            std::fs::read_to_string(root.join("Dockerfile")).unwrap(),
+            "WORKDIR /app\nCOPY --from=builder /app/target/release/photo-viewer \
+             /usr/local/bin/photo-viewer\nCMD [\"photo-viewer\"]\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(root.join("tests/it.rs")).unwrap(),
+            "extern crate photo_viewer;\nuse photo_viewer::greet;\nlet app = 1;\nlet v = app::helpers::run();\n"
+        );
+        assert_eq!(
+            std::fs::read(root.join("scripts/app.bin")).unwrap(),
+            [0x61, 0x70, 0x70, 0xff]
+        );
+    }
+}
@@ -0,0 +1,246 @@
+//! Secret redaction module
+//!
+//! This module keeps credentials out of what ForgeKit prints and stores. A
+//! variable is sensitive when its name matches a built-in pattern like
+//! `*_TOKEN`, a `[redact] sensitive` entry of forgekit.toml, or when its
+//! value comes from the secrets file through a `secret:` reference. A
+//! [`Redactor`] collects the values of sensitive variables from the process
+//! environment and the project's `.env` files and masks them wherever they
+//! appear: `forgekit env list`, build logs, crash reports and diagnostics.
+
+use crate::config::ProjectConfig;
+use crate::credentials;
+use crate::env_manager::{EnvManager, SECRET_PREFIX};
+use crate::error::ForgeKitError;
+use std::path::Path;
+
+/// Text secret values are replaced with
+pub const MASK: &str = "********";
+
+/// Names of variables that are always sensitive
+pub const DEFAULT_PATTERNS: &[&str] = &[
+    "*TOKEN*",
+    "*SECRET*",
+    "*PASSWORD*",
+    "*PASSWD*",
+    "*CREDENTIAL*",
+    "*_KEY",
+    "*_KEY_*",
+    "*PRIVATE_KEY*",
+];
+
+/// Values shorter than this, like `true` or `1`, are not masked inside other
+/// text, where they would match by accident
+const MIN_SECRET_LEN: usize = 6;
+
+/// Masks the values of sensitive variables
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    patterns: Vec<String>,
+    secrets: Vec<String>,
+}
+
+impl Redactor {
+    /// Redactor treating the built-in patterns and `sensitive` as secret
+    pub fn new(sensitive: &[String]) -> Self {
+        Self {
+            patterns: DEFAULT_PATTERNS
+                .iter()
+                .map(|pattern| pattern.to_string())
+                .chain(sensitive.iter().cloned())
+                .collect(),
+            secrets: Vec::new(),
+        }
+    }
+
+    /// Redactor treating only the built-in patterns as secret
+    pub fn default_patterns() -> Self {
+        Self::new(&[])
+    }
+
+    /// Redactor for a project, knowing the secret values of its environment
+    ///
+    /// Collects sensitive values from the process environment, every `.env`
+    /// file of the project, and the credential store entries they refer to.
+    pub fn for_project(project_path: &Path) -> Result<Self, ForgeKitError> {
+        let config_path = project_path.join("forgekit.toml");
+        let sensitive = if config_path.exists() {
+            ProjectConfig::load(&config_path)?.redact.sensitive
+        } else {
+            Vec::new()
+        };
+        let mut redactor = Self::new(&sensitive);
+        redactor.add_vars(std::env::vars());
+
+        if let Ok(entries) = std::fs::read_dir(project_path) {
+            let store = credentials::default_store().ok();
+            for entry in entries.filter_map(|entry| entry.ok()) {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name != ".env" && !name.starts_with(".env.") {
+                    continue;
+                }
+                let env = EnvManager::load_from_file(&entry.path())?;
+                for (key, value) in env.all() {
+                    match value.strip_prefix(SECRET_PREFIX) {
+                        // A stored secret is never shown, whatever its name
+                        Some(secret) => {
+                            let stored = store.as_ref().map(|store| store.get(secret));
+                            if let Some(Ok(Some(secret))) = stored {
+                                redactor.add_secret(secret);
+                            }
+                        }
+                        None if redactor.is_sensitive(key) => redactor.add_secret(value),
+                        None => {}
+                    }
+                }
+            }
+        }
+        Ok(redactor)
+    }
+
+    /// Whether a variable's value must not be shown
+    pub fn is_sensitive(&self, name: &str) -> bool {
+        let name = name.to_ascii_uppercase();
+        self.patterns
+            .iter()
+            .any(|pattern| wildcard_matches(&pattern.to_ascii_uppercase(), &name))
+    }
+
+    /// Mask `value` wherever it appears
+    pub fn add_secret(&mut self, value: impl Into<String>) {
+        let value = value.into();
+        if value.len() >= MIN_SECRET_LEN && !self.secrets.contains(&value) {
+            self.secrets.push(value);
+            // Longer secrets first, so one containing another is fully masked
+            self.secrets
+                .sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+        }
+    }
+
+    /// Mask the values of the sensitive ones of `vars`
+    pub fn add_vars<K: AsRef<str>, V: Into<String>>(
+        &mut self,
+        vars: impl IntoIterator<Item = (K, V)>,
+    ) {
+        for (name, value) in vars {
+            if self.is_sensitive(name.as_ref()) {
+                self.add_secret(value);
+            }
+        }
+    }
+
+    /// Mask the values of an environment profile that are sensitive or were
+    /// resolved from stored secrets
+    pub fn add_env(&mut self, env: &EnvManager) {
+        for (name, value) in env.all() {
+            if env.is_secret(name) || self.is_sensitive(name) {
+                self.add_secret(value.clone());
+            }
+        }
+    }
+
+    /// `text` with every known secret masked
+    pub fn redact(&self, text: &str) -> String {
+        let mut redacted = text.to_string();
+        for secret in &self.secrets {
+            if redacted.contains(secret.as_str()) {
+                redacted = redacted.replace(secret.as_str(), MASK);
+            }
+        }
+        redacted
+    }
+
+    /// A variable's value as it may be shown
+    ///
+    /// `secret:` references name a secret without revealing it and are
+    /// shown as they are.
+    pub fn display_value(&self, name: &str, value: &str) -> String {
+        if self.is_sensitive(name) && !value.starts_with(SECRET_PREFIX) {
+            MASK.to_string()
+        } else {
+            self.redact(value)
+        }
+    }
+}
+
+/// Whether `name` matches `pattern`, where `*` stands for any text
+fn wildcard_matches(pattern: &str, name: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == name;
+    }
+
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !name.starts_with(first) || !name[first.len()..].ends_with(last) {
+        return false;
+    }
+    let mut rest = &name[first.len()..name.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_sensitive_names() {
+        let redactor = Redactor::new(&["STRIPE_*".to_string(), "DATABASE_URL".to_string()]);
+        for name in [
+            "GITHUB_TOKEN",
+            "api_secret",
+            "DB_PASSWORD",
+            "AWS_ACCESS_KEY_ID",
+            "SIGNING_KEY",
+            "STRIPE_LIVE",
+            "DATABASE_URL",
+        ] {
+            assert!(redactor.is_sensitive(name), "{}", name);
+        }
+        for name in ["PATH", "KEYBOARD_LAYOUT", "RUSTFLAGS", "DATABASE_POOL"] {
+            assert!(!redactor.is_sensitive(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_redacts_values_from_env_files_and_secrets() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path();
+        let config = ProjectConfig {
+            redact: crate::config::RedactConfig {
+                sensitive: vec!["WEBHOOK_URL".to_string()],
+            },
+            ..Default::default()
+        };
+        config.save(project.join("forgekit.toml")).unwrap();
+        std::fs::write(
+            project.join(".env.prod"),
+            "API_TOKEN=tok-123456\nWEBHOOK_URL=https://hooks.example/abc\nPORT=8080\n",
+        )
+        .unwrap();
+
+        let redactor = Redactor::for_project(project).unwrap();
+        assert_eq!(
+            redactor.redact("auth tok-123456 to https://hooks.example/abc on 8080"),
+            format!("auth {} to {} on 8080", MASK, MASK)
+        );
+        assert_eq!(redactor.display_value("API_TOKEN", "tok-123456"), MASK);
+        assert_eq!(
+            redactor.display_value("API_TOKEN", "secret:api"),
+            "secret:api"
+        );
+        assert_eq!(redactor.display_value("PORT", "8080"), "8080");
+
+        let mut env = EnvManager::new();
+        env.set("SHORT_TOKEN".to_string(), "abc".to_string());
+        let mut redactor = Redactor::new(&[]);
+        redactor.add_env(&env);
+        assert_eq!(redactor.redact("abc"), "abc");
+    }
+}
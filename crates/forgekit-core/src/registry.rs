@@ -3,33 +3,89 @@
 //! This module provides functionality for managing a custom package registry
 //! that can download packages from GitHub repositories, similar to Cargo's
 //! registry but tailored for ForgeKit's ecosystem.
+//!
+//! Requests go through an [`HttpClient`] and the package index is kept on a
+//! [`FileSystem`], so tests can swap in mocks for both. Downloaded archives
+//! are streamed to the cache directory on disk. [`mock::MockRegistry`] serves
+//! synthetic packages locally for tests and demos.
 
+use crate::config::{GlobalConfig, RegistryEntry};
+use crate::credentials;
+use crate::download::{self, PartialDownload, PartialFiles};
 use crate::error::ForgeKitError;
+use crate::filesystem::{FileSystem, StdFileSystem};
+use crate::github_api::{self, CachedResponse, GithubApi};
+use crate::http_client::HttpClient;
+use crate::moxlib;
+use crate::registry_server;
+use crate::store::hash_file;
+use crate::upload::{
+    self, PendingUpload, RateLimiter, UploadOptions, UploadProgress, UploadProgressCallback,
+};
+use crate::version_manager::compare_versions;
 use reqwest;
 use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::hash::{BuildHasher, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::fs as tokio_fs;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+pub mod mock;
+
+/// Name of the registry used when none is specified
+pub const DEFAULT_REGISTRY: &str = "github";
+
+/// Content type of `.moxlib` archives served by a registry server
+pub const MOXLIB_CONTENT_TYPE: &str = "application/x-moxlib";
+
+/// Longest rate-limit reset we are willing to wait for before giving up
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+
+/// Upper bound for a single exponential backoff delay
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
 
 /// Registry configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct RegistryConfig {
+    /// Registry name used to look up stored credentials
+    pub name: String,
     /// Base URL for the registry
     pub base_url: String,
     /// GitHub token for authenticated requests (optional)
     pub github_token: Option<String>,
+    /// When the token expires (RFC 3339), if known
+    pub token_expires_at: Option<String>,
     /// Cache directory
     pub cache_dir: PathBuf,
     /// Index directory
     pub index_dir: PathBuf,
+    /// Git remote holding the sharded package index (optional)
+    pub index_url: Option<String>,
+    /// Total request timeout in seconds
+    pub timeout_secs: u64,
+    /// Connection timeout in seconds
+    pub connect_timeout_secs: u64,
+    /// Maximum number of retries for transient failures
+    pub max_retries: u32,
+    /// Base delay for exponential backoff in milliseconds
+    pub retry_base_delay_ms: u64,
+    /// Explicit proxy URL (HTTP_PROXY/HTTPS_PROXY are honored when unset)
+    pub proxy: Option<String>,
 }
 
 impl Default for RegistryConfig {
     fn default() -> Self {
         Self {
+            name: DEFAULT_REGISTRY.to_string(),
             base_url: "https://github.com".to_string(),
             github_token: None,
+            token_expires_at: None,
             cache_dir: dirs::cache_dir()
                 .unwrap_or_else(|| PathBuf::from("."))
                 .join("forgekit")
@@ -38,10 +94,79 @@ impl Default for RegistryConfig {
                 .unwrap_or_else(|| PathBuf::from("."))
                 .join("forgekit")
                 .join("index"),
+            index_url: None,
+            timeout_secs: 30,
+            connect_timeout_secs: 10,
+            max_retries: 3,
+            retry_base_delay_ms: 500,
+            proxy: None,
         }
     }
 }
 
+impl RegistryConfig {
+    /// Fill in the URL and token stored by `forgekit login` for this
+    /// registry
+    ///
+    /// Only settings the caller left unset are filled in: an explicit token
+    /// or a URL other than the default one is kept.
+    pub fn with_stored_credentials(mut self) -> Result<Self, ForgeKitError> {
+        let global = GlobalConfig::load(GlobalConfig::default_path())?;
+        let Some(entry) = global.registries.get(&self.name) else {
+            return Ok(self);
+        };
+        if self.base_url == Self::default().base_url {
+            self.base_url = entry.url.clone();
+        }
+        if self.github_token.is_none() {
+            self.token_expires_at = entry.token_expires_at.clone();
+            self.github_token =
+                credentials::default_store()?.get(&token_secret_name(&self.name))?;
+        }
+
+        Ok(self)
+    }
+
+    /// Whether `base_url` is a ForgeKit registry server (`forgekit registry
+    /// serve`) rather than GitHub
+    pub fn is_registry_server(&self) -> bool {
+        reqwest::Url::parse(&self.base_url)
+            .ok()
+            .and_then(|url| url.host_str().map(|host| host != "github.com"))
+            .unwrap_or(false)
+    }
+
+    /// URL of a registry server API endpoint
+    pub fn api_url(&self, path: &str) -> String {
+        format!("{}/api/v1/{}", self.base_url.trim_end_matches('/'), path)
+    }
+
+    /// Whether the configured token is known to have expired
+    pub fn token_expired(&self) -> bool {
+        self.token_expires_at
+            .as_deref()
+            .and_then(|at| chrono::DateTime::parse_from_rfc3339(at).ok())
+            .map(|at| at < chrono::Utc::now())
+            .unwrap_or(false)
+    }
+}
+
+/// Download progress update passed to progress callbacks
+#[derive(Debug, Clone)]
+pub struct DownloadProgress {
+    /// Package being downloaded
+    pub package: String,
+    /// Bytes downloaded so far
+    pub downloaded: u64,
+    /// Total size in bytes, if reported by the server
+    pub total: Option<u64>,
+    /// Whether the download has completed
+    pub finished: bool,
+}
+
+/// Callback invoked as download progress is made
+pub type ProgressCallback = Arc<dyn Fn(&DownloadProgress) + Send + Sync>;
+
 /// Package metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PackageMetadata {
@@ -108,29 +233,68 @@ pub struct VersionInfo {
     pub published: String,
     /// Package checksum
     pub checksum: String,
+    /// Plugin metadata, for versions published as ForgeKit plugins
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plugin: Option<PluginListing>,
+}
+
+/// Plugin metadata of a published version, from `[plugin]` of its
+/// forgekit.toml
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PluginListing {
+    /// Plugin API version the plugin is built against
+    pub api_version: u32,
+    /// What the plugin does
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Target triple the plugin library is built for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+}
+
+/// Full package details as shown by `forgekit info`
+#[derive(Debug, Clone)]
+pub struct PackageDetails {
+    /// Package metadata for the latest version
+    pub metadata: PackageMetadata,
+    /// Package owners
+    pub owners: Vec<String>,
+    /// Available versions, newest first
+    pub versions: Vec<VersionInfo>,
+    /// README contents, if the package has one
+    pub readme: Option<String>,
 }
 
 /// ForgeKit Registry Client
+#[derive(Clone)]
 pub struct RegistryClient {
     config: RegistryConfig,
-    client: reqwest::Client,
+    http: Arc<dyn HttpClient>,
+    fs: Arc<dyn FileSystem>,
+    github: GithubApi,
 }
 
 impl RegistryClient {
-    /// Create a new registry client
+    /// Create a new registry client with the given settings
     pub fn new(config: RegistryConfig) -> Result<Self, ForgeKitError> {
-        let mut builder = reqwest::Client::builder();
+        let mut builder = reqwest::Client::builder()
+            .user_agent(concat!("forgekit/", env!("CARGO_PKG_VERSION")))
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .connect_timeout(Duration::from_secs(config.connect_timeout_secs));
 
         if let Some(token) = &config.github_token {
-            builder = builder.default_headers({
-                let mut headers = reqwest::header::HeaderMap::new();
-                let auth_value = format!("Bearer {}", token);
-                headers.insert(
-                    reqwest::header::AUTHORIZATION,
-                    reqwest::header::HeaderValue::from_str(&auth_value).unwrap(),
-                );
-                headers
-            });
+            let auth_value = reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token))
+                .map_err(|_| {
+                ForgeKitError::InvalidConfig("Invalid GitHub token".to_string())
+            })?;
+            let mut headers = reqwest::header::HeaderMap::new();
+            headers.insert(reqwest::header::AUTHORIZATION, auth_value);
+            builder = builder.default_headers(headers);
+        }
+
+        // reqwest picks up HTTP_PROXY/HTTPS_PROXY on its own; an explicit proxy wins
+        if let Some(proxy) = &config.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy)?);
         }
 
         let client = builder.build()?;
@@ -139,7 +303,35 @@ impl RegistryClient {
         fs::create_dir_all(&config.cache_dir)?;
         fs::create_dir_all(&config.index_dir)?;
 
-        Ok(Self { config, client })
+        let github = GithubApi::new(config.cache_dir.join(github_api::CACHE_DIR));
+        Ok(Self {
+            config,
+            http: Arc::new(client),
+            fs: Arc::new(StdFileSystem),
+            github,
+        })
+    }
+
+    /// Create a registry client using the credentials saved with
+    /// `forgekit login`, see [`RegistryConfig::with_stored_credentials`]
+    pub fn from_login(config: RegistryConfig) -> Result<Self, ForgeKitError> {
+        Self::new(config.with_stored_credentials()?)
+    }
+
+    /// Send requests through `http` instead of the network
+    ///
+    /// Proxy, timeouts and the stored token are settings of the default
+    /// client; `http` handles them on its own.
+    pub fn with_http_client(mut self, http: Arc<dyn HttpClient>) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Keep the package index on `fs` instead of the disk
+    pub fn with_filesystem(mut self, fs: Arc<dyn FileSystem>) -> Result<Self, ForgeKitError> {
+        fs.create_dir_all(&self.config.index_dir)?;
+        self.fs = fs;
+        Ok(self)
     }
 
     /// Search for packages
@@ -153,6 +345,13 @@ impl RegistryClient {
             return Ok(local_results);
         }
 
+        if self.config.is_registry_server() {
+            let url =
+                reqwest::Url::parse_with_params(&self.config.api_url("search"), &[("q", query)])
+                    .map_err(|e| ForgeKitError::Registry(e.to_string()))?;
+            return Ok(self.get_with_retry(url.as_str()).await?.json().await?);
+        }
+
         // Fall back to GitHub search
         self.search_github_packages(query).await
     }
@@ -160,42 +359,38 @@ impl RegistryClient {
     /// Search local package index
     async fn search_local_index(&self, query: &str) -> Result<Vec<PackageMetadata>, ForgeKitError> {
         let mut results = Vec::new();
-        let index_path = self.config.index_dir.join("packages.json");
 
-        if index_path.exists() {
-            let content = fs::read_to_string(&index_path)?;
-            let index: HashMap<String, IndexEntry> = serde_json::from_str(&content)?;
-
-            for (name, entry) in index {
-                if name.contains(query)
-                    || entry.versions.values().any(|v| v.version.contains(query))
-                {
-                    // Convert to PackageMetadata (simplified)
-                    results.push(PackageMetadata {
-                        name: name.clone(),
-                        version: entry.latest.clone(),
-                        description: format!("Package {}", name),
-                        authors: vec![],
-                        repository: format!("{}/{}", self.config.base_url, name),
-                        license: "MIT".to_string(),
-                        keywords: vec![],
-                        categories: vec![],
-                        dependencies: vec![],
-                        targets: vec!["ledokoz".to_string()],
-                        release_date: entry
-                            .versions
-                            .get(&entry.latest)
-                            .map(|v| v.published.clone())
-                            .unwrap_or_default(),
-                        downloads: 0,
-                    });
-                }
+        for (name, entry) in self.load_index().await? {
+            if name.contains(query) || entry.versions.values().any(|v| v.version.contains(query)) {
+                results.push(self.index_metadata(&entry));
             }
         }
 
         Ok(results)
     }
 
+    /// Package metadata of the latest version of an index entry (simplified)
+    pub fn index_metadata(&self, entry: &IndexEntry) -> PackageMetadata {
+        PackageMetadata {
+            name: entry.name.clone(),
+            version: entry.latest.clone(),
+            description: format!("Package {}", entry.name),
+            authors: vec![],
+            repository: format!("{}/{}", self.config.base_url, entry.name),
+            license: "MIT".to_string(),
+            keywords: vec![],
+            categories: vec![],
+            dependencies: vec![],
+            targets: vec!["ledokoz".to_string()],
+            release_date: entry
+                .versions
+                .get(&entry.latest)
+                .map(|v| v.published.clone())
+                .unwrap_or_default(),
+            downloads: 0,
+        }
+    }
+
     /// Search GitHub for ForgeKit packages
     async fn search_github_packages(
         &self,
@@ -206,8 +401,7 @@ impl RegistryClient {
             query
         );
 
-        let response = self.client.get(&search_url).send().await?;
-        let json: serde_json::Value = response.json().await?;
+        let json = self.get_json(&search_url).await?;
 
         let mut packages = Vec::new();
 
@@ -245,31 +439,171 @@ impl RegistryClient {
         name: &str,
         version: &str,
     ) -> Result<PathBuf, ForgeKitError> {
-        // Check if already cached
+        self.download_package_with_progress(name, version, None)
+            .await
+    }
+
+    /// Download a package, reporting progress to the given callback
+    pub async fn download_package_with_progress(
+        &self,
+        name: &str,
+        version: &str,
+        progress: Option<ProgressCallback>,
+    ) -> Result<PathBuf, ForgeKitError> {
+        // Libraries published locally are cached as .moxlib archives
+        let moxlib_path = self.config.cache_dir.join(format!(
+            "{}-{}.{}",
+            name,
+            version,
+            moxlib::MOXLIB_EXTENSION
+        ));
         let cache_path = self
             .config
             .cache_dir
             .join(format!("{}-{}.tar.gz", name, version));
-        if cache_path.exists() {
-            return Ok(cache_path);
+
+        // Check if already cached, setting aside archives corrupted since
+        for cached in [&moxlib_path, &cache_path] {
+            if !cached.exists() {
+                continue;
+            }
+            match download::verify(cached)? {
+                None => return Ok(cached.clone()),
+                Some(reason) => {
+                    download::quarantine(&self.config.cache_dir, cached, &reason)?;
+                }
+            }
         }
 
-        // Get package info
-        // Get package info (side effect: validates package exists)
-        self.get_package_info(name, version).await?;
+        let url = if self.config.is_registry_server() {
+            let path = format!("packages/{}/{}/download", name, version);
+            self.config.api_url(&path)
+        } else {
+            // Get package info (side effect: validates package exists)
+            self.get_package_info(name, version).await?;
 
-        // Download from GitHub
-        let download_url = format!(
-            "https://github.com/{}/archive/refs/tags/v{}.tar.gz",
-            name.replace("forgekit-", ""),
-            version
-        );
+            // Download from GitHub
+            format!(
+                "https://github.com/{}/archive/refs/tags/v{}.tar.gz",
+                name.replace("forgekit-", ""),
+                version
+            )
+        };
+
+        // Stream into a partial file so an interrupted download never looks
+        // cached, and pick up where an earlier attempt stopped
+        let partial = PartialFiles::new(&self.config.cache_dir, name, version);
+        let resume = partial.resumable(&url)?;
+        let mut headers = reqwest::header::HeaderMap::new();
+        if let Some((received, state)) = &resume {
+            tracing::info!(
+                "Resuming download of {} v{} at {} bytes",
+                name,
+                version,
+                received
+            );
+            let range = format!("bytes={}-", received);
+            headers.insert(reqwest::header::RANGE, range.parse().expect("valid header"));
+            if let Some(validator) = state.validator.as_ref().and_then(|v| v.parse().ok()) {
+                headers.insert(reqwest::header::IF_RANGE, validator);
+            }
+        }
+        let mut response = match self.get_with_headers(&url, &headers).await {
+            Err(ForgeKitError::Http(e))
+                if e.status() == Some(reqwest::StatusCode::RANGE_NOT_SATISFIABLE) =>
+            {
+                partial.discard()?;
+                return Box::pin(self.download_package_with_progress(name, version, progress))
+                    .await;
+            }
+            response => response?,
+        };
+
+        // Servers without range support send the whole archive again
+        let resumed = response.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+        let offset = match &resume {
+            Some((received, _)) if resumed => *received,
+            _ => 0,
+        };
+        let headers = response.headers();
+        let header = |name| headers.get(name).and_then(|v| v.to_str().ok());
+        let total = if resumed {
+            header(reqwest::header::CONTENT_RANGE).and_then(download::content_range_total)
+        } else {
+            response.content_length()
+        };
+        let validator = header(reqwest::header::ETAG)
+            .or_else(|| header(reqwest::header::LAST_MODIFIED))
+            .map(str::to_string);
+        // Registry servers host published libraries as well as mirrored archives
+        let is_moxlib = header(reqwest::header::CONTENT_TYPE) == Some(MOXLIB_CONTENT_TYPE);
+        let cache_path = if is_moxlib { moxlib_path } else { cache_path };
+        partial.save(&PartialDownload {
+            url: url.clone(),
+            validator,
+            total,
+        })?;
 
-        let response = self.client.get(&download_url).send().await?;
-        let bytes = response.bytes().await?;
+        let mut update = DownloadProgress {
+            package: name.to_string(),
+            downloaded: offset,
+            total,
+            finished: false,
+        };
+        let mut file = tokio_fs::OpenOptions::new()
+            .create(true)
+            .append(resumed)
+            .write(true)
+            .truncate(!resumed)
+            .open(&partial.data)
+            .await?;
+        while let Some(chunk) = response.chunk().await? {
+            file.write_all(&chunk).await?;
+            update.downloaded += chunk.len() as u64;
+            if let Some(callback) = &progress {
+                callback(&update);
+            }
+        }
+        file.flush().await?;
+        drop(file);
+
+        // A connection closed early ends the body without an error
+        if let Some(total) = total {
+            if update.downloaded != total {
+                return Err(ForgeKitError::Registry(format!(
+                    "download of {} v{} stopped at {} of {} bytes; run the command again to resume",
+                    name, version, update.downloaded, total
+                )));
+            }
+        }
+
+        let expected = self
+            .read_index_entry(name)?
+            .and_then(|mut entry| entry.versions.remove(version))
+            .map(|info| info.checksum)
+            .filter(|checksum| !checksum.is_empty());
+        if let Some(expected) = expected {
+            let checksum = hash_file(&partial.data)?;
+            if checksum != expected {
+                let reason = format!("expected checksum {}, got {}", expected, checksum);
+                download::quarantine(&self.config.cache_dir, &partial.data, &reason)?;
+                partial.discard()?;
+                return Err(ForgeKitError::ChecksumMismatch(format!(
+                    "{} v{}: expected {}, got {}",
+                    name, version, expected, checksum
+                )));
+            }
+        }
 
         // Save to cache
-        tokio_fs::write(&cache_path, bytes).await?;
+        tokio_fs::rename(&partial.data, &cache_path).await?;
+        partial.discard()?;
+        download::write_record(&cache_path, &url)?;
+
+        update.finished = true;
+        if let Some(callback) = &progress {
+            callback(&update);
+        }
 
         Ok(cache_path)
     }
@@ -291,31 +625,48 @@ impl RegistryClient {
         version: &str,
     ) -> Result<PackageMetadata, ForgeKitError> {
         // Try to get from local index first
-        let index_path = self.config.index_dir.join("packages.json");
-        if index_path.exists() {
-            let content = fs::read_to_string(&index_path)?;
-            let index: HashMap<String, IndexEntry> = serde_json::from_str(&content)?;
-
-            if let Some(entry) = index.get(name) {
-                if let Some(version_info) = entry.versions.get(version) {
-                    return Ok(PackageMetadata {
-                        name: name.to_string(),
-                        version: version.to_string(),
-                        description: format!("Package {}", name),
-                        authors: vec![],
-                        repository: format!("{}/{}", self.config.base_url, name),
-                        license: "MIT".to_string(),
-                        keywords: vec![],
-                        categories: vec![],
-                        dependencies: vec![],
-                        targets: vec!["ledokoz".to_string()],
-                        release_date: version_info.published.clone(),
-                        downloads: 0,
-                    });
-                }
+        let index_dir = self.config.index_dir.clone();
+        let package = name.to_string();
+        let fs = Arc::clone(&self.fs);
+        let indexed = tokio::task::spawn_blocking(move || {
+            read_index_entry(fs.as_ref(), &index_dir, &package)
+        })
+        .await
+        .map_err(std::io::Error::from)??;
+        if let Some(entry) = indexed {
+            if let Some(version_info) = entry.versions.get(version) {
+                return Ok(PackageMetadata {
+                    name: name.to_string(),
+                    version: version.to_string(),
+                    description: format!("Package {}", name),
+                    authors: vec![],
+                    repository: format!("{}/{}", self.config.base_url, name),
+                    license: "MIT".to_string(),
+                    keywords: vec![],
+                    categories: vec![],
+                    dependencies: vec![],
+                    targets: vec!["ledokoz".to_string()],
+                    release_date: version_info.published.clone(),
+                    downloads: 0,
+                });
             }
         }
 
+        if self.config.is_registry_server() {
+            let entry = self.fetch_entry(name).await?;
+            let mut entry = entry.ok_or_else(|| {
+                ForgeKitError::Registry(format!("{} is not in the registry", name))
+            })?;
+            if !entry.versions.contains_key(version) {
+                return Err(ForgeKitError::Registry(format!(
+                    "{} v{} is not in the registry",
+                    name, version
+                )));
+            }
+            entry.latest = version.to_string();
+            return Ok(self.index_metadata(&entry));
+        }
+
         // Fallback to GitHub API
         let api_url = format!(
             "https://api.github.com/repos/{}/releases/tags/v{}",
@@ -323,8 +674,7 @@ impl RegistryClient {
             version
         );
 
-        let response = self.client.get(&api_url).send().await?;
-        let release_info: serde_json::Value = response.json().await?;
+        let release_info = self.get_json(&api_url).await?;
 
         Ok(PackageMetadata {
             name: name.to_string(),
@@ -348,14 +698,551 @@ impl RegistryClient {
         })
     }
 
+    /// Index entry of a package from the registry itself, bypassing the
+    /// local index
+    ///
+    /// GitHub packages are described by their releases. Returns `None` when
+    /// the registry does not know the package.
+    pub async fn fetch_entry(&self, name: &str) -> Result<Option<IndexEntry>, ForgeKitError> {
+        if !self.config.is_registry_server() {
+            let details = match self.get_package_details(name).await {
+                Ok(details) => details,
+                Err(ForgeKitError::Http(e))
+                    if e.status() == Some(reqwest::StatusCode::NOT_FOUND) =>
+                {
+                    return Ok(None)
+                }
+                Err(e) => return Err(e),
+            };
+            return Ok(Some(IndexEntry {
+                name: name.to_string(),
+                latest: details.metadata.version,
+                versions: details
+                    .versions
+                    .into_iter()
+                    .map(|info| (info.version.clone(), info))
+                    .collect(),
+            }));
+        }
+
+        let url = self.config.api_url(&format!("packages/{}", name));
+        match self.get_with_retry(&url).await {
+            Ok(response) => Ok(Some(response.json().await?)),
+            Err(ForgeKitError::Http(e)) if e.status() == Some(reqwest::StatusCode::NOT_FOUND) => {
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Upload a .moxlib to the registry server
+    ///
+    /// The server indexes it like `publish_library` does locally; the token
+    /// stored with `forgekit login` authenticates the upload.
+    pub async fn publish_remote(&self, archive: &Path) -> Result<VersionInfo, ForgeKitError> {
+        self.publish_remote_with(archive, &UploadOptions::default(), None)
+            .await
+    }
+
+    /// Upload a .moxlib to the registry server in resumable chunks
+    ///
+    /// Chunks that fail to send are retried like other requests. An upload
+    /// that still fails is saved next to the archive and resumed by the next
+    /// attempt to publish the same archive. The server publishes the archive
+    /// once its size and checksum match the local one.
+    pub async fn publish_remote_with(
+        &self,
+        archive: &Path,
+        options: &UploadOptions,
+        progress: Option<UploadProgressCallback>,
+    ) -> Result<VersionInfo, ForgeKitError> {
+        if !self.config.is_registry_server() {
+            return Err(ForgeKitError::Registry(format!(
+                "{} is not a ForgeKit registry server; log in to one with `forgekit login {} --url <server>`",
+                self.config.base_url, self.config.name
+            )));
+        }
+        if self.config.github_token.is_some() && self.config.token_expired() {
+            return Err(ForgeKitError::AuthenticationRequired(
+                self.config.name.clone(),
+            ));
+        }
+
+        let size = tokio_fs::metadata(archive).await?.len();
+        let checksum = hash_file(archive)?;
+        let package = archive
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let report = |uploaded: u64, finished: bool| {
+            if let Some(callback) = &progress {
+                callback(&UploadProgress {
+                    package: package.clone(),
+                    uploaded,
+                    total: size,
+                    finished,
+                });
+            }
+        };
+
+        // Resume where the server left off, or start over if it forgot
+        let resumed = match PendingUpload::load(archive, &self.config.base_url, size, &checksum) {
+            Some(pending) => self
+                .upload_offset(&pending.url)
+                .await?
+                .map(|offset| (pending, offset)),
+            None => None,
+        };
+        let (pending, mut offset) = match resumed {
+            Some(resumed) => resumed,
+            None => {
+                let pending = PendingUpload {
+                    url: self.create_upload(size, &checksum).await?,
+                    size,
+                    checksum: checksum.clone(),
+                };
+                pending.save(archive)?;
+                (pending, 0)
+            }
+        };
+        if offset > 0 {
+            tracing::info!("Resuming upload of {} at byte {}", package, offset);
+        }
+        report(offset, false);
+
+        let mut file = tokio_fs::File::open(archive).await?;
+        let mut limiter = RateLimiter::new(options.rate_limit);
+        let chunk_size = options.effective_chunk_size();
+        let mut attempt = 0;
+        while offset < size {
+            let mut chunk = vec![0; chunk_size.min(size - offset) as usize];
+            file.seek(std::io::SeekFrom::Start(offset)).await?;
+            file.read_exact(&mut chunk).await?;
+            let sent = chunk.len() as u64;
+
+            match self.send_chunk(&pending.url, offset, chunk).await {
+                Ok(received) => {
+                    offset = received;
+                    attempt = 0;
+                    report(offset, false);
+                    limiter.throttle(sent).await;
+                }
+                Err(ForgeKitError::Http(err))
+                    if is_retryable_upload(&err) && attempt < self.config.max_retries =>
+                {
+                    tokio::time::sleep(backoff_delay(self.config.retry_base_delay_ms, attempt))
+                        .await;
+                    attempt += 1;
+                    // Part of the chunk may have arrived before the failure
+                    offset = self.upload_offset(&pending.url).await?.ok_or_else(|| {
+                        ForgeKitError::Registry(format!(
+                            "{} forgot the upload of {}",
+                            self.config.base_url, package
+                        ))
+                    })?;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        let mut request = reqwest::Request::new(
+            reqwest::Method::POST,
+            parse_url(&format!("{}/publish", pending.url))?,
+        );
+        request.headers_mut().insert(
+            reqwest::header::CONTENT_TYPE,
+            reqwest::header::HeaderValue::from_static(MOXLIB_CONTENT_TYPE),
+        );
+        let response = self.upload_response(self.http.send(request).await?).await?;
+        let info: VersionInfo = response.json().await?;
+        PendingUpload::clear(archive)?;
+        if info.checksum != checksum {
+            return Err(ForgeKitError::Registry(format!(
+                "{} published {} with checksum {} instead of {}",
+                self.config.base_url, package, info.checksum, checksum
+            )));
+        }
+        report(size, true);
+        Ok(info)
+    }
+
+    /// Start an upload on the registry server, returning its URL
+    async fn create_upload(&self, size: u64, checksum: &str) -> Result<String, ForgeKitError> {
+        let mut request = reqwest::Request::new(
+            reqwest::Method::POST,
+            parse_url(&self.config.api_url("uploads"))?,
+        );
+        let headers = request.headers_mut();
+        headers.insert(upload::UPLOAD_LENGTH, size.into());
+        headers.insert(
+            upload::UPLOAD_CHECKSUM,
+            header_value(&upload::checksum_header(checksum))?,
+        );
+        let response = self.upload_response(self.http.send(request).await?).await?;
+        let location = response
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| {
+                ForgeKitError::Registry(format!(
+                    "{} did not say where to upload",
+                    self.config.base_url
+                ))
+            })?;
+        Ok(format!(
+            "{}{}",
+            self.config.base_url.trim_end_matches('/'),
+            location
+        ))
+    }
+
+    /// Bytes of an upload the server has received, `None` if it has no such
+    /// upload
+    async fn upload_offset(&self, url: &str) -> Result<Option<u64>, ForgeKitError> {
+        let request = reqwest::Request::new(reqwest::Method::HEAD, parse_url(url)?);
+        let response = self.http.send(request).await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = self.upload_response(response).await?;
+        Ok(Some(upload_offset_header(&response)?))
+    }
+
+    /// Append a chunk to an upload, returning the bytes received so far
+    async fn send_chunk(
+        &self,
+        url: &str,
+        offset: u64,
+        chunk: Vec<u8>,
+    ) -> Result<u64, ForgeKitError> {
+        let mut request = reqwest::Request::new(reqwest::Method::PATCH, parse_url(url)?);
+        let headers = request.headers_mut();
+        headers.insert(upload::UPLOAD_OFFSET, offset.into());
+        headers.insert(
+            reqwest::header::CONTENT_TYPE,
+            reqwest::header::HeaderValue::from_static("application/offset+octet-stream"),
+        );
+        *request.body_mut() = Some(chunk.into());
+        let response = self.upload_response(self.http.send(request).await?).await?;
+        upload_offset_header(&response)
+    }
+
+    /// Turn rejected upload requests into errors
+    ///
+    /// Server errors stay HTTP errors, so they can be retried.
+    async fn upload_response(
+        &self,
+        response: reqwest::Response,
+    ) -> Result<reqwest::Response, ForgeKitError> {
+        match response.status() {
+            reqwest::StatusCode::UNAUTHORIZED => Err(ForgeKitError::AuthenticationRequired(
+                self.config.name.clone(),
+            )),
+            status if status.is_client_error() => Err(ForgeKitError::Registry(format!(
+                "{}: {}",
+                status,
+                response.text().await?.trim()
+            ))),
+            _ => Ok(response.error_for_status()?),
+        }
+    }
+
+    /// Get full package details: metadata, owners, versions and README
+    pub async fn get_package_details(&self, name: &str) -> Result<PackageDetails, ForgeKitError> {
+        let repo = self.github_repo(name)?;
+        let repo_url = format!("https://api.github.com/repos/{}", repo);
+        let releases_url = format!("https://api.github.com/repos/{}/releases", repo);
+        let (repo_info, releases, readme) = tokio::try_join!(
+            self.get_json(&repo_url),
+            self.get_json(&releases_url),
+            self.get_readme(name)
+        )?;
+
+        let mut versions = match self.read_index_entry(name)? {
+            Some(entry) => entry.versions.into_values().collect(),
+            None => parse_release_versions(&releases),
+        };
+        versions.sort_by(|a, b| b.published.cmp(&a.published));
+
+        let latest = versions.first();
+        let metadata = PackageMetadata {
+            name: name.to_string(),
+            version: latest.map(|v| v.version.clone()).unwrap_or_default(),
+            description: repo_info["description"]
+                .as_str()
+                .unwrap_or("No description")
+                .to_string(),
+            authors: parse_owners(&repo_info),
+            repository: repo_info["html_url"]
+                .as_str()
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("https://github.com/{}", repo)),
+            license: repo_info["license"]["spdx_id"]
+                .as_str()
+                .unwrap_or("UNKNOWN")
+                .to_string(),
+            keywords: repo_info["topics"]
+                .as_array()
+                .map(|topics| {
+                    topics
+                        .iter()
+                        .filter_map(|t| t.as_str().map(str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            categories: vec![],
+            dependencies: vec![],
+            targets: vec!["ledokoz".to_string()],
+            release_date: latest.map(|v| v.published.clone()).unwrap_or_default(),
+            downloads: sum_downloads(&releases),
+        };
+
+        Ok(PackageDetails {
+            owners: metadata.authors.clone(),
+            metadata,
+            versions,
+            readme,
+        })
+    }
+
+    /// Get the owners of a package
+    pub async fn get_package_owners(&self, name: &str) -> Result<Vec<String>, ForgeKitError> {
+        let repo = self.github_repo(name)?;
+        let repo_info = self
+            .get_json(&format!("https://api.github.com/repos/{}", repo))
+            .await?;
+        Ok(parse_owners(&repo_info))
+    }
+
+    /// Get the total number of downloads across all releases of a package
+    pub async fn get_download_count(&self, name: &str) -> Result<u64, ForgeKitError> {
+        let repo = self.github_repo(name)?;
+        let releases = self
+            .get_json(&format!("https://api.github.com/repos/{}/releases", repo))
+            .await?;
+        Ok(sum_downloads(&releases))
+    }
+
+    /// Get the README of a package, if it has one
+    pub async fn get_readme(&self, name: &str) -> Result<Option<String>, ForgeKitError> {
+        let repo = self.github_repo(name)?;
+        let url = format!("https://api.github.com/repos/{}/readme", repo);
+
+        let readme = match self.get_json(&url).await {
+            Ok(readme) => readme,
+            Err(ForgeKitError::Http(err))
+                if err.status() == Some(reqwest::StatusCode::NOT_FOUND) =>
+            {
+                return Ok(None)
+            }
+            Err(err) => return Err(err),
+        };
+
+        // GitHub returns the content base64-encoded with embedded newlines
+        let encoded: String = readme["content"]
+            .as_str()
+            .unwrap_or("")
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        let decoded = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, encoded)
+            .map_err(|_| {
+            ForgeKitError::Registry(format!("Invalid README encoding for {}", name))
+        })?;
+
+        Ok(Some(String::from_utf8_lossy(&decoded).to_string()))
+    }
+
+    /// Resolve the `owner/repo` GitHub repository backing a package
+    fn github_repo(&self, name: &str) -> Result<String, ForgeKitError> {
+        if name.contains('/') {
+            return Ok(name.to_string());
+        }
+
+        // Index entries point at archives on GitHub, which name the repository
+        if let Some(entry) = self.read_index_entry(name)? {
+            if let Some(repo) = entry
+                .versions
+                .values()
+                .find_map(|v| repo_from_archive_url(&v.archive_url))
+            {
+                return Ok(repo);
+            }
+        }
+
+        Ok(name.replace("forgekit-", ""))
+    }
+
+    /// GET a GitHub API URL and parse the body as JSON
+    ///
+    /// Concurrent requests for the same URL share one request.
+    async fn get_json(&self, url: &str) -> Result<serde_json::Value, ForgeKitError> {
+        self.github.coalesce(url, || self.fetch_json(url)).await
+    }
+
+    /// Fetch a GitHub API response, revalidating the cached one
+    ///
+    /// While the rate limit is exhausted the cached response is answered
+    /// without a request.
+    async fn fetch_json(&self, url: &str) -> Result<serde_json::Value, ForgeKitError> {
+        let cached = self.github.cached(url);
+        if let Some(cached) = &cached {
+            if self.github.exhausted() {
+                tracing::debug!("Rate limit exhausted, answering {} from the cache", url);
+                return Ok(cached.body.clone());
+            }
+        }
+
+        let headers = cached
+            .as_ref()
+            .map(CachedResponse::conditional_headers)
+            .unwrap_or_default();
+        let response = match (self.get_with_headers(url, &headers).await, cached) {
+            (Ok(response), cached) => {
+                self.github.observe(response.headers());
+                if let (reqwest::StatusCode::NOT_MODIFIED, Some(cached)) =
+                    (response.status(), cached)
+                {
+                    return self.github.revalidated(cached);
+                }
+                response
+            }
+            (Err(ForgeKitError::RateLimited(reason)), Some(cached)) => {
+                tracing::warn!("Rate limited ({}); using the cached {}", reason, url);
+                return Ok(cached.body);
+            }
+            (Err(e), _) => return Err(e),
+        };
+
+        let headers = response.headers().clone();
+        let body = response.json().await?;
+        self.github.store(url, &headers, &body)?;
+        Ok(body)
+    }
+
+    /// Send a GET request, retrying transient failures with exponential backoff
+    async fn get_with_retry(&self, url: &str) -> Result<reqwest::Response, ForgeKitError> {
+        self.get_with_headers(url, &reqwest::header::HeaderMap::new())
+            .await
+    }
+
+    /// Send a GET request with extra headers, retrying like [`Self::get_with_retry`]
+    async fn get_with_headers(
+        &self,
+        url: &str,
+        headers: &reqwest::header::HeaderMap,
+    ) -> Result<reqwest::Response, ForgeKitError> {
+        if self.config.github_token.is_some() && self.config.token_expired() {
+            return Err(ForgeKitError::AuthenticationRequired(
+                self.config.name.clone(),
+            ));
+        }
+
+        let url_parsed = parse_url(url)?;
+        let mut attempt = 0;
+
+        loop {
+            let mut request = reqwest::Request::new(reqwest::Method::GET, url_parsed.clone());
+            *request.headers_mut() = headers.clone();
+            let delay = match self.http.send(request).await {
+                Ok(response) => {
+                    let status = response.status();
+                    if status == reqwest::StatusCode::UNAUTHORIZED {
+                        return Err(ForgeKitError::AuthenticationRequired(
+                            self.config.name.clone(),
+                        ));
+                    }
+                    let now = chrono::Utc::now().timestamp();
+                    if let Some(wait) = rate_limit_delay(status, response.headers(), now) {
+                        if wait > MAX_RATE_LIMIT_WAIT || attempt >= self.config.max_retries {
+                            return Err(ForgeKitError::RateLimited(format!(
+                                "{} (resets in {}s; set a GitHub token to raise the limit)",
+                                url,
+                                wait.as_secs()
+                            )));
+                        }
+                        wait
+                    } else if status.is_server_error() && attempt < self.config.max_retries {
+                        backoff_delay(self.config.retry_base_delay_ms, attempt)
+                    } else {
+                        return Ok(response.error_for_status()?);
+                    }
+                }
+                Err(ForgeKitError::Http(err))
+                    if is_transient(&err) && attempt < self.config.max_retries =>
+                {
+                    backoff_delay(self.config.retry_base_delay_ms, attempt)
+                }
+                Err(err) => return Err(err),
+            };
+
+            attempt += 1;
+            tracing::warn!(
+                "Request to {} failed, retrying in {:?} (attempt {}/{})",
+                url,
+                delay,
+                attempt,
+                self.config.max_retries
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
     /// Update local package index
+    ///
+    /// When `index_url` is configured the index is a git repository that is
+    /// shallow-fetched, so updates only transfer changed shards. Otherwise a
+    /// basic sample index is written locally.
     pub async fn update_index(&self) -> Result<(), ForgeKitError> {
-        // This would typically fetch from a central registry
-        // For now, we'll create a basic index
-        let index_path = self.config.index_dir.join("packages.json");
+        match &self.config.index_url {
+            Some(url) => self.fetch_git_index(url).await,
+            None if self.config.is_registry_server() => self.fetch_server_index().await,
+            None => self.write_sample_index(),
+        }
+    }
+
+    /// Write the index of the registry server into the local index
+    async fn fetch_server_index(&self) -> Result<(), ForgeKitError> {
+        let entries: Vec<IndexEntry> = self
+            .get_with_retry(&self.config.api_url("index"))
+            .await?
+            .json()
+            .await?;
+        for entry in &entries {
+            self.write_index_entry(entry)?;
+        }
+        Ok(())
+    }
+
+    /// Clone or incrementally fetch the git-backed index
+    async fn fetch_git_index(&self, url: &str) -> Result<(), ForgeKitError> {
+        let index_dir = &self.config.index_dir;
+
+        if !index_dir.join(".git").exists() {
+            run_git(index_dir, &["init", "--quiet"]).await?;
+            run_git(index_dir, &["remote", "add", "origin", url]).await?;
+        } else {
+            run_git(index_dir, &["remote", "set-url", "origin", url]).await?;
+        }
+
+        run_git(
+            index_dir,
+            &["fetch", "--quiet", "--depth", "1", "origin", "HEAD"],
+        )
+        .await?;
+        run_git(index_dir, &["reset", "--quiet", "--hard", "FETCH_HEAD"]).await?;
 
-        let mut index = HashMap::new();
+        // The single-file index is superseded by the shards
+        let legacy = index_dir.join("packages.json");
+        if self.fs.exists(&legacy) {
+            self.fs.remove_file(&legacy)?;
+        }
+
+        Ok(())
+    }
 
+    /// Write a basic sample index
+    fn write_sample_index(&self) -> Result<(), ForgeKitError> {
         // Add some sample packages to the index
         let sample_packages = [
             ("forgekit-serde", "0.1.0"),
@@ -380,32 +1267,210 @@ impl RegistryClient {
                             ),
                             published: chrono::Utc::now().to_rfc3339(),
                             checksum: "".to_string(),
+                            plugin: None,
                         },
                     );
                     versions
                 },
                 latest: version.to_string(),
             };
-            index.insert(name.to_string(), entry);
+            self.write_index_entry(&entry)?;
         }
 
-        let index_json = serde_json::to_string_pretty(&index)?;
-        fs::write(&index_path, index_json)?;
-
         Ok(())
     }
 
+    /// Publish a .moxlib into the local index and package cache
+    ///
+    /// Other projects using this registry configuration can then depend on
+    /// the library like on any downloaded package.
+    pub fn publish_library(&self, archive: &Path) -> Result<VersionInfo, ForgeKitError> {
+        let manifest = moxlib::read_manifest(archive)?;
+        let mut entry = self
+            .read_index_entry(&manifest.name)?
+            .unwrap_or_else(|| IndexEntry {
+                name: manifest.name.clone(),
+                versions: HashMap::new(),
+                latest: manifest.version.clone(),
+            });
+        if entry.versions.contains_key(&manifest.version) {
+            return Err(ForgeKitError::Registry(format!(
+                "{} v{} is already published",
+                manifest.name, manifest.version
+            )));
+        }
+
+        fs::create_dir_all(&self.config.cache_dir)?;
+        let cached = self.config.cache_dir.join(format!(
+            "{}-{}.{}",
+            manifest.name,
+            manifest.version,
+            moxlib::MOXLIB_EXTENSION
+        ));
+        fs::copy(archive, &cached)?;
+
+        let info = VersionInfo {
+            version: manifest.version.clone(),
+            git_ref: format!("v{}", manifest.version),
+            archive_url: format!("file://{}", cached.display()),
+            published: chrono::Utc::now().to_rfc3339(),
+            checksum: hash_file(&cached)?,
+            plugin: manifest.plugin.as_ref().map(|plugin| PluginListing {
+                api_version: plugin.api_version,
+                description: manifest.description.clone(),
+                target: manifest.target.clone(),
+            }),
+        };
+        entry
+            .versions
+            .insert(manifest.version.clone(), info.clone());
+        if compare_versions(&manifest.version, &entry.latest).is_ge() {
+            entry.latest = manifest.version;
+        }
+        self.write_index_entry(&entry)?;
+
+        Ok(info)
+    }
+
+    /// Versions of a package in the local index, without network access
+    ///
+    /// Returns `None` when the package is not indexed.
+    pub fn indexed_versions(&self, name: &str) -> Result<Option<Vec<String>>, ForgeKitError> {
+        Ok(self
+            .read_index_entry(name)?
+            .map(|entry| entry.versions.into_keys().collect()))
+    }
+
+    /// When a version was published, from the local index without network
+    /// access
+    ///
+    /// Returns `None` when the version is not indexed.
+    pub fn published_at(&self, name: &str, version: &str) -> Result<Option<String>, ForgeKitError> {
+        Ok(self
+            .read_index_entry(name)?
+            .and_then(|mut entry| entry.versions.remove(version))
+            .map(|info| info.published))
+    }
+
+    /// Configuration the client was created with
+    pub fn config(&self) -> &RegistryConfig {
+        &self.config
+    }
+
+    /// Whether a local index has been downloaded
+    pub fn has_index(&self) -> bool {
+        self.fs.exists(&self.config.index_dir)
+    }
+
     /// List all available packages
     pub async fn list_packages(&self) -> Result<Vec<String>, ForgeKitError> {
-        let index_path = self.config.index_dir.join("packages.json");
-        if index_path.exists() {
-            let content = fs::read_to_string(&index_path)?;
-            let index: HashMap<String, IndexEntry> = serde_json::from_str(&content)?;
-            Ok(index.keys().cloned().collect())
-        } else {
-            Ok(vec![])
+        Ok(self.load_index().await?.into_keys().collect())
+    }
+
+    /// Read a single package entry from its index shard
+    pub(crate) fn read_index_entry(&self, name: &str) -> Result<Option<IndexEntry>, ForgeKitError> {
+        read_index_entry(self.fs.as_ref(), &self.config.index_dir, name)
+    }
+
+    /// Write a single package entry to its index shard
+    pub(crate) fn write_index_entry(&self, entry: &IndexEntry) -> Result<(), ForgeKitError> {
+        // The name comes from the registry and becomes part of the shard path
+        if !registry_server::valid_segment(&entry.name) {
+            return Err(ForgeKitError::Registry(format!(
+                "Registry index has an entry with the invalid name '{}'",
+                entry.name
+            )));
         }
+        let shard = self.config.index_dir.join(index_shard_path(&entry.name));
+        if let Some(parent) = shard.parent() {
+            self.fs.create_dir_all(parent)?;
+        }
+        self.fs
+            .write(&shard, serde_json::to_string_pretty(entry)?.as_bytes())
+    }
+
+    /// Load every package entry from the index on a blocking thread
+    ///
+    /// Large git indexes hold thousands of shards, which would otherwise
+    /// stall the runtime while they are read.
+    pub(crate) async fn load_index(&self) -> Result<HashMap<String, IndexEntry>, ForgeKitError> {
+        let index_dir = self.config.index_dir.clone();
+        let fs = Arc::clone(&self.fs);
+        tokio::task::spawn_blocking(move || load_index(fs.as_ref(), &index_dir))
+            .await
+            .map_err(std::io::Error::from)?
+    }
+}
+
+/// Read a single package entry from its index shard
+fn read_index_entry(
+    fs: &dyn FileSystem,
+    index_dir: &Path,
+    name: &str,
+) -> Result<Option<IndexEntry>, ForgeKitError> {
+    let shard = index_dir.join(index_shard_path(name));
+    if fs.exists(&shard) {
+        let content = fs.read_to_string(&shard)?;
+        return Ok(Some(serde_json::from_str(&content)?));
+    }
+
+    Ok(load_legacy_index(fs, index_dir)?.remove(name))
+}
+
+/// Load every package entry from an index
+fn load_index(
+    fs: &dyn FileSystem,
+    index_dir: &Path,
+) -> Result<HashMap<String, IndexEntry>, ForgeKitError> {
+    let mut index = load_legacy_index(fs, index_dir)?;
+
+    // Shards live in subdirectories; files at the top are not packages
+    for path in fs.files(index_dir)? {
+        if path.parent() == Some(index_dir) {
+            continue;
+        }
+
+        let content = fs.read_to_string(&path)?;
+        let package: IndexEntry = serde_json::from_str(&content)?;
+        index.insert(package.name.clone(), package);
+    }
+
+    Ok(index)
+}
+
+/// Load the pre-sharding single-file index, if one is still present
+fn load_legacy_index(
+    fs: &dyn FileSystem,
+    index_dir: &Path,
+) -> Result<HashMap<String, IndexEntry>, ForgeKitError> {
+    let index_path = index_dir.join("packages.json");
+    if !fs.exists(&index_path) {
+        return Ok(HashMap::new());
     }
+
+    let content = fs.read_to_string(&index_path)?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// `Upload-Offset` of a response
+fn upload_offset_header(response: &reqwest::Response) -> Result<u64, ForgeKitError> {
+    response
+        .headers()
+        .get(upload::UPLOAD_OFFSET)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| ForgeKitError::Registry("Upload response has no offset".to_string()))
+}
+
+/// Header value of a string
+fn header_value(value: &str) -> Result<reqwest::header::HeaderValue, ForgeKitError> {
+    reqwest::header::HeaderValue::from_str(value)
+        .map_err(|e| ForgeKitError::Registry(format!("{}: {}", value, e)))
+}
+
+/// Parse a request URL
+fn parse_url(url: &str) -> Result<reqwest::Url, ForgeKitError> {
+    reqwest::Url::parse(url).map_err(|e| ForgeKitError::Registry(format!("{}: {}", url, e)))
 }
 
 impl Default for RegistryClient {
@@ -413,3 +1478,557 @@ impl Default for RegistryClient {
         Self::new(RegistryConfig::default()).unwrap()
     }
 }
+
+/// Name under which a registry token is kept in the credential store
+pub fn token_secret_name(registry: &str) -> String {
+    format!("registry.{}.token", registry)
+}
+
+/// Save credentials for a registry
+///
+/// The token goes to the credential store while the registry URL and token
+/// expiry are recorded in the global configuration.
+pub fn login(
+    registry: &str,
+    url: &str,
+    token: &str,
+    expires_in_days: Option<i64>,
+) -> Result<(), ForgeKitError> {
+    let token_expires_at = expires_in_days
+        .map(|days| (chrono::Utc::now() + chrono::Duration::days(days)).to_rfc3339());
+
+    credentials::default_store()?.set(&token_secret_name(registry), token)?;
+
+    let config_path = GlobalConfig::default_path();
+    let mut global = GlobalConfig::load(&config_path)?;
+    global.registries.insert(
+        registry.to_string(),
+        RegistryEntry {
+            url: url.to_string(),
+            token_expires_at,
+        },
+    );
+    global.save(&config_path)
+}
+
+/// Remove stored credentials for a registry
+///
+/// Returns whether a token was stored.
+pub fn logout(registry: &str) -> Result<bool, ForgeKitError> {
+    let removed = credentials::default_store()?.delete(&token_secret_name(registry))?;
+
+    let config_path = GlobalConfig::default_path();
+    let mut global = GlobalConfig::load(&config_path)?;
+    if let Some(entry) = global.registries.get_mut(registry) {
+        entry.token_expires_at = None;
+        global.save(&config_path)?;
+    }
+
+    Ok(removed)
+}
+
+/// Extract `owner/repo` from a GitHub archive URL
+fn repo_from_archive_url(url: &str) -> Option<String> {
+    let path = url.strip_prefix("https://github.com/")?;
+    let mut parts = path.split('/');
+    let owner = parts.next()?;
+    let repo = parts.next()?;
+    Some(format!("{}/{}", owner, repo))
+}
+
+/// Collect owner logins from a GitHub repository response
+fn parse_owners(repo_info: &serde_json::Value) -> Vec<String> {
+    repo_info["owner"]["login"]
+        .as_str()
+        .map(|login| vec![login.to_string()])
+        .unwrap_or_default()
+}
+
+/// Sum asset download counts over a GitHub releases response
+fn sum_downloads(releases: &serde_json::Value) -> u64 {
+    releases
+        .as_array()
+        .map(|releases| {
+            releases
+                .iter()
+                .filter_map(|r| r["assets"].as_array())
+                .flatten()
+                .filter_map(|asset| asset["download_count"].as_u64())
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// Turn a GitHub releases response into version information
+fn parse_release_versions(releases: &serde_json::Value) -> Vec<VersionInfo> {
+    releases
+        .as_array()
+        .map(|releases| {
+            releases
+                .iter()
+                .filter_map(|release| {
+                    let tag = release["tag_name"].as_str()?;
+                    Some(VersionInfo {
+                        version: tag.trim_start_matches('v').to_string(),
+                        git_ref: tag.to_string(),
+                        archive_url: release["tarball_url"].as_str().unwrap_or("").to_string(),
+                        published: release["published_at"].as_str().unwrap_or("").to_string(),
+                        checksum: String::new(),
+                        plugin: None,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Relative path of a package's shard in the index
+///
+/// Follows the crates.io layout: `1/a`, `2/ab`, `3/a/abc`, and `ab/cd/abcd...`
+/// for longer names. Names are split by characters, not bytes, so a name
+/// that is not ASCII still gets a shard.
+pub fn index_shard_path(name: &str) -> PathBuf {
+    let name = name.to_lowercase();
+    let prefix = |start: usize, end: usize| -> String {
+        name.chars().skip(start).take(end - start).collect()
+    };
+    match name.chars().count() {
+        1 => PathBuf::from("1").join(&name),
+        2 => PathBuf::from("2").join(&name),
+        3 => PathBuf::from("3").join(prefix(0, 1)).join(&name),
+        _ => PathBuf::from(prefix(0, 2)).join(prefix(2, 4)).join(&name),
+    }
+}
+
+/// Run a git command inside the index directory
+async fn run_git(dir: &std::path::Path, args: &[&str]) -> Result<(), ForgeKitError> {
+    let output = tokio::process::Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        return Err(ForgeKitError::Registry(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(())
+}
+
+/// Whether a request error is worth retrying
+fn is_transient(err: &reqwest::Error) -> bool {
+    err.is_timeout() || err.is_connect()
+}
+
+/// Whether a failed upload request is worth sending again
+fn is_retryable_upload(err: &reqwest::Error) -> bool {
+    is_transient(err) || err.status().is_some_and(|status| status.is_server_error())
+}
+
+/// Compute the exponential backoff delay (with jitter) for a retry attempt
+fn backoff_delay(base_ms: u64, attempt: u32) -> Duration {
+    let exponential = base_ms.saturating_mul(1u64 << attempt.min(16));
+    let jitter = if base_ms > 0 {
+        RandomState::new().build_hasher().finish() % base_ms
+    } else {
+        0
+    };
+    Duration::from_millis(exponential.saturating_add(jitter)).min(MAX_BACKOFF)
+}
+
+/// Determine how long to wait when a response signals rate limiting
+///
+/// GitHub reports exhausted quotas as 403 with `x-ratelimit-remaining: 0`,
+/// other servers use 429 with `retry-after`.
+fn rate_limit_delay(
+    status: reqwest::StatusCode,
+    headers: &reqwest::header::HeaderMap,
+    now: i64,
+) -> Option<Duration> {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<i64>().ok())
+    };
+
+    let exhausted = status == reqwest::StatusCode::TOO_MANY_REQUESTS
+        || (status == reqwest::StatusCode::FORBIDDEN && header("x-ratelimit-remaining") == Some(0));
+    if !exhausted {
+        return None;
+    }
+
+    let seconds = header("retry-after")
+        .or_else(|| header("x-ratelimit-reset").map(|reset| reset - now))
+        .unwrap_or(1);
+    Some(Duration::from_secs(seconds.max(1) as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderMap, HeaderValue};
+    use reqwest::StatusCode;
+
+    #[test]
+    fn test_registry_config_defaults() {
+        let config = RegistryConfig::default();
+        assert_eq!(config.max_retries, 3);
+        assert!(config.proxy.is_none());
+    }
+
+    fn test_client(dir: &std::path::Path) -> RegistryClient {
+        RegistryClient::new(RegistryConfig {
+            cache_dir: dir.join("cache"),
+            index_dir: dir.join("index"),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_publish_library_is_downloadable() {
+        use crate::config::{ProjectConfig, ProjectKind};
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project = temp_dir.path().join("lib");
+        let release = project.join("target").join("ledokoz").join("release");
+        std::fs::create_dir_all(&release).unwrap();
+        std::fs::write(release.join("libshared.rlib"), b"rlib").unwrap();
+        let config = ProjectConfig {
+            name: "shared".to_string(),
+            kind: ProjectKind::Library,
+            ..Default::default()
+        };
+        let archive = moxlib::package(&project, &config, &Default::default())
+            .await
+            .unwrap();
+
+        let client = test_client(temp_dir.path());
+        let info = client.publish_library(&archive).unwrap();
+        assert_eq!(info.version, "0.1.0");
+        assert!(client.publish_library(&archive).is_err());
+
+        assert_eq!(
+            client.indexed_versions("shared").unwrap(),
+            Some(vec!["0.1.0".to_string()])
+        );
+        let downloaded = client.download_package("shared", "0.1.0").await.unwrap();
+        assert_eq!(hash_file(&downloaded).unwrap(), info.checksum);
+    }
+
+    #[tokio::test]
+    async fn test_download_resumes_and_quarantines_corrupt_archives() {
+        use hyper::service::{make_service_fn, service_fn};
+        use hyper::{Body, Request, Response, StatusCode};
+        use std::sync::Mutex;
+
+        const ARCHIVE: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+        let ranges = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&ranges);
+        let service = make_service_fn(move |_| {
+            let seen = Arc::clone(&seen);
+            async move {
+                Ok::<_, std::convert::Infallible>(service_fn(move |request: Request<Body>| {
+                    let range = request
+                        .headers()
+                        .get("range")
+                        .map(|v| v.to_str().unwrap().to_string());
+                    seen.lock().unwrap().push(range.clone());
+                    let response = match range.and_then(|r| {
+                        r.strip_prefix("bytes=")?
+                            .trim_end_matches('-')
+                            .parse::<usize>()
+                            .ok()
+                    }) {
+                        Some(start) => Response::builder()
+                            .status(StatusCode::PARTIAL_CONTENT)
+                            .header(
+                                "content-range",
+                                format!("bytes {}-{}/{}", start, ARCHIVE.len() - 1, ARCHIVE.len()),
+                            )
+                            .body(Body::from(&ARCHIVE[start..])),
+                        None => Response::builder()
+                            .header("etag", "\"v1\"")
+                            .body(Body::from(ARCHIVE)),
+                    };
+                    async move { Ok::<_, std::convert::Infallible>(response.unwrap()) }
+                }))
+            }
+        });
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        listener.set_nonblocking(true).unwrap();
+        tokio::spawn(hyper::Server::from_tcp(listener).unwrap().serve(service));
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let client = RegistryClient::new(RegistryConfig {
+            base_url,
+            github_token: Some("token".to_string()),
+            cache_dir: temp_dir.path().join("cache"),
+            index_dir: temp_dir.path().join("index"),
+            ..Default::default()
+        })
+        .unwrap();
+
+        // An earlier attempt stopped after ten bytes
+        let partial = PartialFiles::new(&client.config.cache_dir, "pkg", "1.0.0");
+        std::fs::write(&partial.data, &ARCHIVE[..10]).unwrap();
+        partial
+            .save(&PartialDownload {
+                url: client.config.api_url("packages/pkg/1.0.0/download"),
+                validator: Some("\"v1\"".to_string()),
+                total: Some(ARCHIVE.len() as u64),
+            })
+            .unwrap();
+        let archive = client.download_package("pkg", "1.0.0").await.unwrap();
+        assert_eq!(std::fs::read(&archive).unwrap(), ARCHIVE);
+        assert_eq!(*ranges.lock().unwrap(), [Some("bytes=10-".to_string())]);
+        assert!(!partial.data.exists() && !partial.state.exists());
+        assert_eq!(download::verify(&archive).unwrap(), None);
+
+        // A truncated archive no longer passes as cached
+        std::fs::write(&archive, &ARCHIVE[..20]).unwrap();
+        let archive = client.download_package("pkg", "1.0.0").await.unwrap();
+        assert_eq!(std::fs::read(&archive).unwrap(), ARCHIVE);
+        assert_eq!(ranges.lock().unwrap().last(), Some(&None));
+        let quarantine = client.config.cache_dir.join(download::QUARANTINE_DIR);
+        assert_eq!(std::fs::read_dir(quarantine).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_github_api_responses_are_cached_and_shared() {
+        use hyper::service::{make_service_fn, service_fn};
+        use hyper::{Body, Request, Response, StatusCode};
+        use std::sync::Mutex;
+
+        // Conditional requests after the first one also exhaust the limit
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&requests);
+        let service = make_service_fn(move |_| {
+            let seen = Arc::clone(&seen);
+            async move {
+                Ok::<_, std::convert::Infallible>(service_fn(move |request: Request<Body>| {
+                    let etag = request
+                        .headers()
+                        .get("if-none-match")
+                        .map(|v| v.to_str().unwrap().to_string());
+                    seen.lock().unwrap().push(etag.clone());
+                    let reset = (chrono::Utc::now().timestamp() + 3600).to_string();
+                    let response = match etag {
+                        Some(_) => Response::builder()
+                            .status(StatusCode::NOT_MODIFIED)
+                            .header("x-ratelimit-remaining", "0")
+                            .header("x-ratelimit-reset", reset)
+                            .body(Body::empty()),
+                        None => Response::builder()
+                            .header("etag", "\"abc\"")
+                            .header("x-ratelimit-remaining", "59")
+                            .header("x-ratelimit-reset", reset)
+                            .body(Body::from(r#"{"name":"widgets"}"#)),
+                    };
+                    async move {
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        Ok::<_, std::convert::Infallible>(response.unwrap())
+                    }
+                }))
+            }
+        });
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/repos/me/widgets", listener.local_addr().unwrap());
+        listener.set_nonblocking(true).unwrap();
+        tokio::spawn(hyper::Server::from_tcp(listener).unwrap().serve(service));
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let client = test_client(temp_dir.path());
+        let (first, second) = tokio::join!(client.get_json(&url), client.get_json(&url));
+        assert_eq!(first.unwrap()["name"], "widgets");
+        assert_eq!(second.unwrap()["name"], "widgets");
+        assert_eq!(*requests.lock().unwrap(), [None]);
+
+        // A fresh client revalidates the response cached on disk
+        let client = test_client(temp_dir.path());
+        assert_eq!(client.get_json(&url).await.unwrap()["name"], "widgets");
+        assert_eq!(requests.lock().unwrap().len(), 2);
+        assert_eq!(requests.lock().unwrap()[1].as_deref(), Some("\"abc\""));
+        assert!(client.github.exhausted());
+
+        // With the limit exhausted the cache answers on its own
+        assert_eq!(client.get_json(&url).await.unwrap()["name"], "widgets");
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_token_expired() {
+        let mut config = RegistryConfig::default();
+        assert!(!config.token_expired());
+
+        config.token_expires_at = Some("2000-01-01T00:00:00Z".to_string());
+        assert!(config.token_expired());
+
+        config.token_expires_at = Some("2999-01-01T00:00:00Z".to_string());
+        assert!(!config.token_expired());
+    }
+
+    #[test]
+    fn test_repo_from_archive_url() {
+        assert_eq!(
+            repo_from_archive_url(
+                "https://github.com/ledokoz-tech/forgekit-http/archive/v0.1.0.tar.gz"
+            ),
+            Some("ledokoz-tech/forgekit-http".to_string())
+        );
+        assert_eq!(
+            repo_from_archive_url("https://example.com/pkg.tar.gz"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_release_metadata() {
+        let releases = serde_json::json!([
+            {
+                "tag_name": "v0.2.0",
+                "published_at": "2026-02-01T00:00:00Z",
+                "assets": [{"download_count": 10}, {"download_count": 5}]
+            },
+            {
+                "tag_name": "v0.1.0",
+                "published_at": "2026-01-01T00:00:00Z",
+                "assets": [{"download_count": 7}]
+            }
+        ]);
+
+        assert_eq!(sum_downloads(&releases), 22);
+        let versions = parse_release_versions(&releases);
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[0].version, "0.2.0");
+        assert_eq!(versions[1].git_ref, "v0.1.0");
+
+        let repo = serde_json::json!({"owner": {"login": "ledokoz-tech"}});
+        assert_eq!(parse_owners(&repo), vec!["ledokoz-tech".to_string()]);
+    }
+
+    #[test]
+    fn test_index_shard_path() {
+        assert_eq!(index_shard_path("a"), PathBuf::from("1/a"));
+        assert_eq!(index_shard_path("ab"), PathBuf::from("2/ab"));
+        assert_eq!(index_shard_path("abc"), PathBuf::from("3/a/abc"));
+        assert_eq!(
+            index_shard_path("Forgekit-HTTP"),
+            PathBuf::from("fo/rg/forgekit-http")
+        );
+        assert_eq!(index_shard_path("ébc"), PathBuf::from("3/é/ébc"));
+        assert_eq!(index_shard_path("ñandú"), PathBuf::from("ña/nd/ñandú"));
+    }
+
+    #[tokio::test]
+    async fn test_sample_index_is_sharded() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let client = test_client(temp_dir.path());
+
+        client.update_index().await.unwrap();
+
+        let shard = temp_dir
+            .path()
+            .join("index")
+            .join(index_shard_path("forgekit-http"));
+        assert!(shard.exists());
+
+        let mut packages = client.list_packages().await.unwrap();
+        packages.sort();
+        assert_eq!(packages.len(), 4);
+        assert_eq!(packages[0], "forgekit-gui");
+    }
+
+    #[tokio::test]
+    async fn test_server_index_with_mocks() {
+        use crate::filesystem::MemoryFileSystem;
+        use crate::http_client::{MockHttpClient, MockResponse};
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = RegistryConfig {
+            base_url: "https://registry.example".to_string(),
+            cache_dir: temp_dir.path().join("cache"),
+            index_dir: temp_dir.path().join("index"),
+            ..Default::default()
+        };
+        let entry = serde_json::json!({"name": "widgets", "versions": {}, "latest": "1.0.0"});
+        let http = Arc::new(
+            MockHttpClient::new()
+                .on("GET", &config.api_url("index"), MockResponse::new(502))
+                .on(
+                    "GET",
+                    &config.api_url("index"),
+                    MockResponse::json(&serde_json::json!([entry])),
+                ),
+        );
+        let client = RegistryClient::new(RegistryConfig {
+            retry_base_delay_ms: 0,
+            ..config
+        })
+        .unwrap()
+        .with_http_client(http.clone())
+        .with_filesystem(Arc::new(MemoryFileSystem::new()))
+        .unwrap();
+
+        client.update_index().await.unwrap();
+        assert_eq!(http.requests().len(), 2);
+        assert_eq!(client.list_packages().await.unwrap(), ["widgets"]);
+        assert!(client.fetch_entry("gadgets").await.unwrap().is_none());
+        // The index was only written in memory
+        assert!(!temp_dir
+            .path()
+            .join("index")
+            .join(index_shard_path("widgets"))
+            .exists());
+
+        for name in ["../../escape", "/etc/passwd", "a/b", ".hidden"] {
+            let entry = serde_json::json!({"name": name, "versions": {}, "latest": "1.0.0"});
+            let entry: IndexEntry = serde_json::from_value(entry).unwrap();
+            assert!(client.write_index_entry(&entry).is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_legacy_index_still_readable() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let client = test_client(temp_dir.path());
+        std::fs::write(
+            temp_dir.path().join("index").join("packages.json"),
+            r#"{"legacy": {"name": "legacy", "versions": {}, "latest": "0.1.0"}}"#,
+        )
+        .unwrap();
+
+        assert!(client.read_index_entry("legacy").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_backoff_delay_grows() {
+        let first = backoff_delay(100, 0);
+        let third = backoff_delay(100, 2);
+        assert!(first < Duration::from_millis(200));
+        assert!(third >= Duration::from_millis(400));
+        assert!(backoff_delay(100, 30) <= MAX_BACKOFF);
+    }
+
+    #[test]
+    fn test_rate_limit_delay_github_reset() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("0"));
+        headers.insert("x-ratelimit-reset", HeaderValue::from_static("1010"));
+
+        let delay = rate_limit_delay(StatusCode::FORBIDDEN, &headers, 1000);
+        assert_eq!(delay, Some(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn test_rate_limit_delay_ignores_plain_forbidden() {
+        let headers = HeaderMap::new();
+        assert_eq!(rate_limit_delay(StatusCode::FORBIDDEN, &headers, 0), None);
+    }
+}
@@ -0,0 +1,184 @@
+//! Mock registry
+//!
+//! A [`MockRegistry`] runs a [`RegistryServer`] in-process on a free local
+//! port, preloaded with synthetic library packages. Integration tests point a
+//! [`RegistryClient`] at it instead of GitHub, and `forgekit demo` uses it to
+//! show searching, adding and publishing without network access. The
+//! registry lives in a temporary directory and stops when dropped.
+
+use crate::cancel::CancellationToken;
+use crate::config::{ProjectConfig, ProjectKind};
+use crate::error::ForgeKitError;
+use crate::moxlib;
+use crate::platform;
+use crate::registry::{RegistryClient, RegistryConfig};
+use crate::registry_server::{RegistryServer, RegistryServerConfig};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// Name clients of a mock registry store their credentials under
+pub const MOCK_REGISTRY: &str = "mock";
+
+/// Token publishers must send to a mock registry
+pub const MOCK_TOKEN: &str = "mock-publish-token";
+
+/// Packages a mock registry starts with, as name and version
+pub const MOCK_PACKAGES: &[(&str, &str)] = &[
+    ("forgekit-http", "0.1.0"),
+    ("forgekit-http", "0.2.0"),
+    ("forgekit-json", "0.3.1"),
+    ("forgekit-ui-kit", "1.0.0"),
+];
+
+/// In-process registry server holding synthetic packages
+#[derive(Debug)]
+pub struct MockRegistry {
+    dir: TempDir,
+    url: String,
+    cancel: CancellationToken,
+}
+
+impl MockRegistry {
+    /// Start a registry holding [`MOCK_PACKAGES`]
+    pub async fn start() -> Result<Self, ForgeKitError> {
+        Self::with_packages(MOCK_PACKAGES).await
+    }
+
+    /// Start a registry holding the given packages, as name and version
+    pub async fn with_packages(packages: &[(&str, &str)]) -> Result<Self, ForgeKitError> {
+        let dir = TempDir::new()?;
+        let root = dir.path().join("registry");
+        let store = RegistryClient::new(RegistryConfig {
+            name: MOCK_REGISTRY.to_string(),
+            github_token: Some(MOCK_TOKEN.to_string()),
+            cache_dir: root.join("packages"),
+            index_dir: root.join("index"),
+            ..Default::default()
+        })?;
+        for (name, version) in packages {
+            let archive = library(&dir.path().join("sources"), name, version).await?;
+            store.publish_library(&archive)?;
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let server = RegistryServer::new(RegistryServerConfig {
+            root,
+            addr: listener.local_addr()?,
+            upstream: None,
+            token: Some(MOCK_TOKEN.to_string()),
+        })?;
+        let cancel = CancellationToken::new();
+        let serving = cancel.clone();
+        tokio::spawn(async move {
+            if let Err(e) = server.serve_listener(listener, &serving).await {
+                tracing::error!("Mock registry failed: {}", e);
+            }
+        });
+
+        Ok(Self { dir, url, cancel })
+    }
+
+    /// Base URL of the registry
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Directory holding the registry's index and archives
+    pub fn root(&self) -> PathBuf {
+        self.dir.path().join("registry")
+    }
+
+    /// Configuration of a client of this registry keeping its cache and
+    /// index under `dir`
+    ///
+    /// The client is logged in with [`MOCK_TOKEN`] and does not retry, so
+    /// failures surface immediately.
+    pub fn client_config(&self, dir: &Path) -> RegistryConfig {
+        RegistryConfig {
+            name: MOCK_REGISTRY.to_string(),
+            base_url: self.url.clone(),
+            github_token: Some(MOCK_TOKEN.to_string()),
+            cache_dir: dir.join("cache"),
+            index_dir: dir.join("index"),
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    /// Client of this registry keeping its cache and index under `dir`
+    pub fn client(&self, dir: &Path) -> Result<RegistryClient, ForgeKitError> {
+        RegistryClient::new(self.client_config(dir))
+    }
+}
+
+impl Drop for MockRegistry {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+    }
+}
+
+/// Package a synthetic library under `dir`, returning its .moxlib
+///
+/// The library has a one-function crate and a placeholder rlib, which is all
+/// a .moxlib needs; nothing is compiled.
+pub async fn library(dir: &Path, name: &str, version: &str) -> Result<PathBuf, ForgeKitError> {
+    let project = dir.join(format!("{}-{}", name, version));
+    let crate_name = name.replace('-', "_");
+    let release = platform::release_dir(&project.join("target"));
+    std::fs::create_dir_all(&release)?;
+    std::fs::write(
+        release.join(format!("lib{}.rlib", crate_name)),
+        format!("{} {}", name, version),
+    )?;
+    std::fs::create_dir_all(project.join("src"))?;
+    std::fs::write(
+        project.join("src").join("lib.rs"),
+        format!(
+            "//! Synthetic {} package\n\npub fn version() -> &'static str {{\n    \"{}\"\n}}\n",
+            name, version
+        ),
+    )?;
+
+    let config = ProjectConfig {
+        name: name.to_string(),
+        version: version.to_string(),
+        description: Some(format!("Synthetic {} package", name)),
+        kind: ProjectKind::Library,
+        ..Default::default()
+    };
+    moxlib::package(&project, &config, &CancellationToken::new()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_mock_registry_serves_packages() {
+        let registry = MockRegistry::start().await.unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        let client = registry.client(temp_dir.path()).unwrap();
+
+        let found = client.search_packages("forgekit-http").await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].version, "0.2.0");
+        let downloaded = client
+            .download_package("forgekit-json", "0.3.1")
+            .await
+            .unwrap();
+        assert_eq!(
+            moxlib::read_manifest(&downloaded).unwrap().name,
+            "forgekit-json"
+        );
+
+        let archive = library(temp_dir.path(), "demo-lib", "0.1.0").await.unwrap();
+        client.publish_remote(&archive).await.unwrap();
+        client.update_index().await.unwrap();
+        assert_eq!(
+            client.indexed_versions("demo-lib").unwrap(),
+            Some(vec!["0.1.0".to_string()])
+        );
+    }
+}
@@ -0,0 +1,883 @@
+//! Registry server module
+//!
+//! This module hosts a package registry over HTTP, so a team can run its own
+//! registry on-prem with `forgekit registry serve`. The registry lives in a
+//! directory: `index/` is sharded like the local index of every client and
+//! `packages/` holds the archives. A [`RegistryClient`] whose base URL points
+//! at the server uses it instead of GitHub.
+//!
+//! - `GET /api/v1/index`: every index entry
+//! - `GET /api/v1/packages/<name>`: index entry of a package
+//! - `GET /api/v1/packages/<name>/<version>/download`: package archive
+//! - `GET /api/v1/search?q=<query>`: metadata of the matching packages
+//! - `PUT /api/v1/packages` with a .moxlib body: publish it
+//! - `/api/v1/uploads`: publish large archives in resumable chunks, see
+//!   [`crate::upload`]
+//!
+//! With an upstream registry the server is also a caching mirror: packages it
+//! does not host are looked up upstream and their archives kept on the first
+//! download, so they stay available when the upstream is not.
+
+use crate::atomic;
+use crate::cancel::CancellationToken;
+use crate::download::{self, PartialFiles};
+use crate::error::ForgeKitError;
+use crate::moxlib::{self, MOXLIB_EXTENSION};
+use crate::registry::{IndexEntry, RegistryClient, RegistryConfig, MOXLIB_CONTENT_TYPE};
+use crate::store::hash_file;
+use crate::upload::{self, PendingUpload};
+use crate::version_manager::compare_versions;
+use hyper::body::HttpBody;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{header, Body, Method, Request, Response, StatusCode};
+use serde::Serialize;
+use std::convert::Infallible;
+use std::net::{SocketAddr, TcpListener};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Port the registry server listens on by default
+pub const DEFAULT_PORT: u16 = 7878;
+
+/// Environment variable holding the token publishers must send
+pub const TOKEN_ENV: &str = "FORGEKIT_REGISTRY_TOKEN";
+
+/// Largest request body accepted, a whole archive or a chunk of one
+const MAX_UPLOAD: usize = 100 * 1024 * 1024;
+
+/// Largest archive accepted as a chunked upload
+const MAX_CHUNKED_UPLOAD: u64 = 4 * 1024 * 1024 * 1024;
+
+/// Directory of the registry holding uploads in progress
+const UPLOADS_DIR: &str = ".uploads";
+
+/// Registry server configuration
+#[derive(Debug, Clone)]
+pub struct RegistryServerConfig {
+    /// Directory holding the index and the package archives
+    pub root: PathBuf,
+    /// Address to listen on
+    pub addr: SocketAddr,
+    /// Registry to mirror packages from (GitHub or another registry server)
+    pub upstream: Option<String>,
+    /// Token publishers must send; publishing is refused when unset
+    pub token: Option<String>,
+}
+
+/// ForgeKit registry server
+pub struct RegistryServer {
+    config: RegistryServerConfig,
+    store: RegistryClient,
+    upstream: Option<RegistryClient>,
+}
+
+impl RegistryServer {
+    /// Create a server for the registry in `config.root`
+    pub fn new(config: RegistryServerConfig) -> Result<Self, ForgeKitError> {
+        let store = RegistryClient::new(RegistryConfig {
+            cache_dir: config.root.join("packages"),
+            index_dir: config.root.join("index"),
+            ..Default::default()
+        })?;
+        // Upstream lookups get their own scratch index, so they always reach
+        // the upstream instead of answering from the hosted index
+        let upstream = match &config.upstream {
+            Some(url) => Some(RegistryClient::new(RegistryConfig {
+                name: url.clone(),
+                base_url: url.clone(),
+                cache_dir: config.root.join(".upstream").join("cache"),
+                index_dir: config.root.join(".upstream").join("index"),
+                ..Default::default()
+            })?),
+            None => None,
+        };
+        Ok(Self {
+            config,
+            store,
+            upstream,
+        })
+    }
+
+    /// Serve requests on the configured address until `cancel` is triggered
+    pub async fn serve(self, cancel: &CancellationToken) -> Result<(), ForgeKitError> {
+        let listener = TcpListener::bind(self.config.addr)?;
+        self.serve_listener(listener, cancel).await
+    }
+
+    /// Serve requests on a bound listener until `cancel` is triggered
+    pub async fn serve_listener(
+        self,
+        listener: TcpListener,
+        cancel: &CancellationToken,
+    ) -> Result<(), ForgeKitError> {
+        listener.set_nonblocking(true)?;
+        tracing::info!(
+            "Registry server for {:?} listening on http://{}",
+            self.config.root,
+            listener.local_addr()?
+        );
+        if self.config.token.is_none() {
+            tracing::warn!("No publish token is set; publishing is disabled");
+        }
+
+        let server = Arc::new(self);
+        let service = make_service_fn(move |_| {
+            let server = Arc::clone(&server);
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let server = Arc::clone(&server);
+                    async move { Ok::<_, Infallible>(server.handle(request).await) }
+                }))
+            }
+        });
+        hyper::Server::from_tcp(listener)
+            .map_err(server_error)?
+            .serve(service)
+            .with_graceful_shutdown(cancel.cancelled())
+            .await
+            .map_err(server_error)?;
+        tracing::info!("Registry server stopped");
+        Ok(())
+    }
+
+    /// Answer a request, turning failures into error responses
+    async fn handle(&self, request: Request<Body>) -> Response<Body> {
+        let method = request.method().clone();
+        let path = request.uri().path().to_string();
+        match self.route(request).await {
+            Ok(response) => response,
+            Err(e) => {
+                tracing::error!("{} {} failed: {}", method, path, e);
+                text(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+            }
+        }
+    }
+
+    /// Dispatch a request to its endpoint
+    async fn route(&self, request: Request<Body>) -> Result<Response<Body>, ForgeKitError> {
+        let Some(path) = request.uri().path().strip_prefix("/api/v1/") else {
+            return Ok(text(StatusCode::NOT_FOUND, "Not found"));
+        };
+        let segments: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+        if segments
+            .iter()
+            .skip(1)
+            .any(|segment| !valid_segment(segment))
+        {
+            return Ok(text(
+                StatusCode::BAD_REQUEST,
+                "Invalid package name or version",
+            ));
+        }
+
+        match (request.method(), segments.as_slice()) {
+            (&Method::GET, ["index"]) => {
+                let mut entries: Vec<IndexEntry> =
+                    self.store.load_index().await?.into_values().collect();
+                entries.sort_by(|a, b| a.name.cmp(&b.name));
+                let entries: Vec<IndexEntry> = entries.into_iter().map(served).collect();
+                json(StatusCode::OK, &entries)
+            }
+            (&Method::GET, ["search"]) => {
+                let query = request
+                    .uri()
+                    .query()
+                    .and_then(|query| {
+                        reqwest::Url::parse(&format!("http://localhost/?{}", query)).ok()
+                    })
+                    .and_then(|url| {
+                        url.query_pairs()
+                            .find(|(key, _)| key == "q")
+                            .map(|(_, value)| value.into_owned())
+                    })
+                    .unwrap_or_default();
+                self.search(&query).await
+            }
+            (&Method::GET, ["packages", name]) => match self.entry(name).await? {
+                Some(entry) => json(StatusCode::OK, &served(entry)),
+                None => Ok(not_hosted(name)),
+            },
+            (&Method::GET, ["packages", name, version, "download"]) => {
+                self.download(name, version).await
+            }
+            (&Method::PUT, ["packages"]) => self.publish(request).await,
+            (&Method::POST, ["uploads"]) => self.create_upload(&request),
+            (&Method::HEAD, ["uploads", id]) => self.upload_status(&request, id),
+            (&Method::PATCH, ["uploads", id]) => {
+                let id = id.to_string();
+                self.append_upload(request, &id).await
+            }
+            (&Method::POST, ["uploads", id, "publish"]) => self.finish_upload(&request, id).await,
+            _ => Ok(text(StatusCode::NOT_FOUND, "Not found")),
+        }
+    }
+
+    /// Metadata of the packages matching `query`, hosted or upstream
+    async fn search(&self, query: &str) -> Result<Response<Body>, ForgeKitError> {
+        let mut results: Vec<_> = self
+            .store
+            .load_index()
+            .await?
+            .values()
+            .filter(|entry| entry.name.contains(query))
+            .map(|entry| self.store.index_metadata(entry))
+            .collect();
+        if let Some(upstream) = &self.upstream {
+            match upstream.search_packages(query).await {
+                Ok(found) => results.extend(
+                    found
+                        .into_iter()
+                        .filter(|package| results.iter().all(|r| r.name != package.name))
+                        .collect::<Vec<_>>(),
+                ),
+                Err(e) => tracing::warn!("Upstream search for '{}' failed: {}", query, e),
+            }
+        }
+        results.sort_by(|a, b| a.name.cmp(&b.name));
+        json(StatusCode::OK, &results)
+    }
+
+    /// Index entry of a package, refreshed from the upstream when mirroring
+    ///
+    /// Versions found upstream are added to the hosted index; the hosted
+    /// entry is still answered when the upstream cannot be reached.
+    async fn entry(&self, name: &str) -> Result<Option<IndexEntry>, ForgeKitError> {
+        let hosted = self.store.read_index_entry(name)?;
+        let Some(upstream) = &self.upstream else {
+            return Ok(hosted);
+        };
+        let mirrored = match upstream.fetch_entry(name).await {
+            Ok(Some(entry)) => entry,
+            Ok(None) => return Ok(hosted),
+            Err(e) => {
+                tracing::warn!("Upstream lookup of {} failed: {}", name, e);
+                return Ok(hosted);
+            }
+        };
+
+        let mut entry = hosted.unwrap_or_else(|| IndexEntry {
+            name: name.to_string(),
+            versions: Default::default(),
+            latest: mirrored.latest.clone(),
+        });
+        for (version, info) in mirrored.versions {
+            if !valid_segment(&version) {
+                continue;
+            }
+            if compare_versions(&version, &entry.latest).is_gt() {
+                entry.latest = version.clone();
+            }
+            entry.versions.entry(version).or_insert(info);
+        }
+        self.store.write_index_entry(&entry)?;
+        Ok(Some(entry))
+    }
+
+    /// Serve a package archive, mirroring it from the upstream if needed
+    async fn download(&self, name: &str, version: &str) -> Result<Response<Body>, ForgeKitError> {
+        let archive = match self.hosted_archive(name, version) {
+            Some(archive) => archive,
+            None => match self.mirror(name, version).await? {
+                Some(archive) => archive,
+                None => return Ok(not_hosted(&format!("{} v{}", name, version))),
+            },
+        };
+
+        let content_type = if archive
+            .extension()
+            .is_some_and(|ext| ext == MOXLIB_EXTENSION)
+        {
+            MOXLIB_CONTENT_TYPE
+        } else {
+            "application/gzip"
+        };
+        Ok(Response::builder()
+            .header(header::CONTENT_TYPE, content_type)
+            .body(Body::from(tokio::fs::read(archive).await?))
+            .expect("valid response"))
+    }
+
+    /// Archive of a version in `packages/`, if the registry holds it
+    fn hosted_archive(&self, name: &str, version: &str) -> Option<PathBuf> {
+        let packages = &self.store.config().cache_dir;
+        [MOXLIB_EXTENSION, "tar.gz"]
+            .iter()
+            .map(|ext| packages.join(format!("{}-{}.{}", name, version, ext)))
+            .find(|archive| archive.exists())
+    }
+
+    /// Download a version from the upstream into `packages/` and index it
+    ///
+    /// Returns `None` without an upstream, or when it lacks the version.
+    async fn mirror(&self, name: &str, version: &str) -> Result<Option<PathBuf>, ForgeKitError> {
+        let Some(upstream) = &self.upstream else {
+            return Ok(None);
+        };
+        let Some(mut entry) = self.entry(name).await? else {
+            return Ok(None);
+        };
+        if !entry.versions.contains_key(version) {
+            return Ok(None);
+        }
+
+        let downloaded = upstream.download_package(name, version).await?;
+        let file_name = downloaded
+            .file_name()
+            .ok_or_else(|| ForgeKitError::Registry(format!("{:?} has no file name", downloaded)))?;
+        let archive = self.store.config().cache_dir.join(file_name);
+        tokio::fs::copy(&downloaded, &archive).await?;
+        tokio::fs::remove_file(&downloaded).await?;
+        let record = download::record_path(&downloaded);
+        if record.exists() {
+            tokio::fs::remove_file(record).await?;
+        }
+
+        if let Some(info) = entry.versions.get_mut(version) {
+            info.checksum = hash_file(&archive)?;
+        }
+        self.store.write_index_entry(&entry)?;
+        tracing::info!(
+            "Mirrored {} v{} from {}",
+            name,
+            version,
+            upstream.config().base_url
+        );
+        Ok(Some(archive))
+    }
+
+    /// Publish the .moxlib sent as the request body
+    async fn publish(&self, request: Request<Body>) -> Result<Response<Body>, ForgeKitError> {
+        if let Some(refused) = self.refuse_publisher(&request) {
+            return Ok(refused);
+        }
+        let archive = match read_body(request.into_body()).await? {
+            Ok(archive) => archive,
+            Err(too_large) => return Ok(too_large),
+        };
+
+        let upload = tempfile::NamedTempFile::new_in(&self.config.root)?;
+        std::fs::write(upload.path(), &archive)?;
+        self.publish_archive(upload.path()).await
+    }
+
+    /// Start a chunked upload of an archive
+    fn create_upload(&self, request: &Request<Body>) -> Result<Response<Body>, ForgeKitError> {
+        if let Some(refused) = self.refuse_publisher(request) {
+            return Ok(refused);
+        }
+        let length = request
+            .headers()
+            .get(upload::UPLOAD_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok());
+        let checksum = request
+            .headers()
+            .get(upload::UPLOAD_CHECKSUM)
+            .and_then(|value| value.to_str().ok())
+            .and_then(upload::parse_checksum_header);
+        let (Some(length), Some(checksum)) = (length, checksum) else {
+            return Ok(text(
+                StatusCode::BAD_REQUEST,
+                "Uploads need Upload-Length and a sha256 Upload-Checksum",
+            ));
+        };
+        if length > MAX_CHUNKED_UPLOAD {
+            return Ok(text(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!(
+                    "Archives are limited to {} GiB",
+                    MAX_CHUNKED_UPLOAD / 1024 / 1024 / 1024
+                ),
+            ));
+        }
+
+        let mut id = [0u8; 16];
+        ring::rand::SecureRandom::fill(&ring::rand::SystemRandom::new(), &mut id)
+            .map_err(|_| ForgeKitError::Registry("No randomness for an upload ID".to_string()))?;
+        let id: String = id.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let upload = self.upload_files(&id);
+        std::fs::create_dir_all(self.config.root.join(UPLOADS_DIR))?;
+        std::fs::File::create(&upload.data)?;
+        let pending = PendingUpload {
+            url: format!("/api/v1/uploads/{}", id),
+            size: length,
+            checksum: checksum.to_string(),
+        };
+        atomic::write(&upload.state, serde_json::to_string_pretty(&pending)?)?;
+
+        Ok(Response::builder()
+            .status(StatusCode::CREATED)
+            .header(header::LOCATION, &pending.url)
+            .header(upload::UPLOAD_OFFSET, 0)
+            .body(Body::empty())
+            .expect("valid response"))
+    }
+
+    /// Bytes of an upload received so far
+    fn upload_status(
+        &self,
+        request: &Request<Body>,
+        id: &str,
+    ) -> Result<Response<Body>, ForgeKitError> {
+        if let Some(refused) = self.refuse_publisher(request) {
+            return Ok(refused);
+        }
+        let Some(pending) = self.pending_upload(id)? else {
+            return Ok(not_hosted(&format!("Upload {}", id)));
+        };
+        Ok(Response::builder()
+            .header(upload::UPLOAD_OFFSET, self.upload_files(id).received()?)
+            .header(upload::UPLOAD_LENGTH, pending.size)
+            .body(Body::empty())
+            .expect("valid response"))
+    }
+
+    /// Append a chunk to an upload
+    ///
+    /// The chunk must start where the received bytes end, so a chunk sent
+    /// twice is not appended twice.
+    async fn append_upload(
+        &self,
+        request: Request<Body>,
+        id: &str,
+    ) -> Result<Response<Body>, ForgeKitError> {
+        if let Some(refused) = self.refuse_publisher(&request) {
+            return Ok(refused);
+        }
+        let Some(pending) = self.pending_upload(id)? else {
+            return Ok(not_hosted(&format!("Upload {}", id)));
+        };
+        let files = self.upload_files(id);
+        let received = files.received()?;
+        let offset = request
+            .headers()
+            .get(upload::UPLOAD_OFFSET)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok());
+        if offset != Some(received) {
+            return Ok(Response::builder()
+                .status(StatusCode::CONFLICT)
+                .header(upload::UPLOAD_OFFSET, received)
+                .body(Body::from(format!("Upload is at byte {}", received)))
+                .expect("valid response"));
+        }
+
+        let chunk = match read_body(request.into_body()).await? {
+            Ok(chunk) => chunk,
+            Err(too_large) => return Ok(too_large),
+        };
+        if received + chunk.len() as u64 > pending.size {
+            return Ok(text(
+                StatusCode::BAD_REQUEST,
+                format!("Upload is longer than {} bytes", pending.size),
+            ));
+        }
+        let mut data = std::fs::OpenOptions::new().append(true).open(&files.data)?;
+        std::io::Write::write_all(&mut data, &chunk)?;
+        data.sync_data()?;
+
+        Ok(Response::builder()
+            .status(StatusCode::NO_CONTENT)
+            .header(upload::UPLOAD_OFFSET, received + chunk.len() as u64)
+            .body(Body::empty())
+            .expect("valid response"))
+    }
+
+    /// Verify an assembled upload and publish it
+    ///
+    /// An upload whose checksum does not match is discarded; the client
+    /// starts over.
+    async fn finish_upload(
+        &self,
+        request: &Request<Body>,
+        id: &str,
+    ) -> Result<Response<Body>, ForgeKitError> {
+        if let Some(refused) = self.refuse_publisher(request) {
+            return Ok(refused);
+        }
+        let Some(pending) = self.pending_upload(id)? else {
+            return Ok(not_hosted(&format!("Upload {}", id)));
+        };
+        let files = self.upload_files(id);
+        let received = files.received()?;
+        if received != pending.size {
+            return Ok(text(
+                StatusCode::CONFLICT,
+                format!("Upload has {} of {} bytes", received, pending.size),
+            ));
+        }
+        let checksum = hash_file(&files.data)?;
+        if checksum != pending.checksum {
+            files.discard()?;
+            return Ok(text(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!(
+                    "Upload checksum is {} instead of {}",
+                    checksum, pending.checksum
+                ),
+            ));
+        }
+
+        let published = self.publish_archive(&files.data).await;
+        files.discard()?;
+        published
+    }
+
+    /// Files of an upload in progress
+    fn upload_files(&self, id: &str) -> PartialFiles {
+        let dir = self.config.root.join(UPLOADS_DIR);
+        PartialFiles {
+            data: dir.join(id),
+            state: dir.join(format!("{}.json", id)),
+        }
+    }
+
+    /// Saved state of an upload in progress
+    fn pending_upload(&self, id: &str) -> Result<Option<PendingUpload>, ForgeKitError> {
+        match std::fs::read_to_string(self.upload_files(id).state) {
+            Ok(state) => Ok(Some(serde_json::from_str(&state)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Response refusing a publisher, `None` if it sent the token
+    fn refuse_publisher(&self, request: &Request<Body>) -> Option<Response<Body>> {
+        let Some(token) = &self.config.token else {
+            return Some(text(
+                StatusCode::FORBIDDEN,
+                "Publishing is disabled on this registry",
+            ));
+        };
+        let authorized = request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|sent| token_matches(token, sent));
+        (!authorized).then(|| text(StatusCode::UNAUTHORIZED, "Invalid publish token"))
+    }
+
+    /// Index a received .moxlib
+    async fn publish_archive(&self, archive: &Path) -> Result<Response<Body>, ForgeKitError> {
+        let manifest = match moxlib::read_manifest(archive) {
+            Ok(manifest) => manifest,
+            Err(e) => return Ok(text(StatusCode::BAD_REQUEST, e.to_string())),
+        };
+        if !valid_segment(&manifest.name) || !valid_segment(&manifest.version) {
+            return Ok(text(
+                StatusCode::BAD_REQUEST,
+                "Invalid package name or version",
+            ));
+        }
+
+        let store = self.store.clone();
+        let path = archive.to_path_buf();
+        let published = tokio::task::spawn_blocking(move || store.publish_library(&path))
+            .await
+            .map_err(std::io::Error::from)?;
+        match published {
+            Ok(mut info) => {
+                tracing::info!("Published {} v{}", manifest.name, manifest.version);
+                info.archive_url = download_path(&manifest.name, &info.version);
+                json(StatusCode::CREATED, &info)
+            }
+            Err(e @ ForgeKitError::Registry(_)) => Ok(text(StatusCode::CONFLICT, e.to_string())),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Read a request body of at most [`MAX_UPLOAD`] bytes
+///
+/// A longer body is answered with the returned error response.
+async fn read_body(mut body: Body) -> Result<Result<Vec<u8>, Response<Body>>, ForgeKitError> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(server_error)?;
+        if bytes.len() + chunk.len() > MAX_UPLOAD {
+            return Ok(Err(text(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!(
+                    "Request bodies are limited to {} MiB",
+                    MAX_UPLOAD / 1024 / 1024
+                ),
+            )));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(Ok(bytes))
+}
+
+/// Path a version's archive is downloaded from
+fn download_path(name: &str, version: &str) -> String {
+    format!("/api/v1/packages/{}/{}/download", name, version)
+}
+
+/// An index entry as clients see it, downloading through the server
+fn served(mut entry: IndexEntry) -> IndexEntry {
+    for (version, info) in entry.versions.iter_mut() {
+        info.archive_url = download_path(&entry.name, version);
+    }
+    entry
+}
+
+/// Whether a package name or version is safe to use in a file name
+pub(crate) fn valid_segment(segment: &str) -> bool {
+    !segment.is_empty()
+        && !segment.starts_with('.')
+        && segment
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '+'))
+}
+
+/// Compare tokens in time independent of where they differ
+fn token_matches(expected: &str, sent: &str) -> bool {
+    expected.len() == sent.len()
+        && expected
+            .bytes()
+            .zip(sent.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn json<T: Serialize>(status: StatusCode, value: &T) -> Result<Response<Body>, ForgeKitError> {
+    Ok(Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(serde_json::to_vec(value)?))
+        .expect("valid response"))
+}
+
+fn text(status: StatusCode, message: impl Into<String>) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .body(Body::from(message.into()))
+        .expect("valid response")
+}
+
+fn not_hosted(what: &str) -> Response<Body> {
+    text(
+        StatusCode::NOT_FOUND,
+        format!("{} is not in this registry", what),
+    )
+}
+
+fn server_error(e: hyper::Error) -> ForgeKitError {
+    ForgeKitError::Registry(format!("Registry server: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ProjectConfig, ProjectKind};
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    /// Start a server on a free port, returning its base URL
+    fn start(config: RegistryServerConfig, cancel: &CancellationToken) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = RegistryServer::new(config).unwrap();
+        let cancel = cancel.clone();
+        tokio::spawn(async move { server.serve_listener(listener, &cancel).await });
+        url
+    }
+
+    fn client(dir: &Path, base_url: &str, token: Option<&str>) -> RegistryClient {
+        RegistryClient::new(RegistryConfig {
+            name: "on-prem".to_string(),
+            base_url: base_url.to_string(),
+            github_token: token.map(str::to_string),
+            cache_dir: dir.join("cache"),
+            index_dir: dir.join("index"),
+            max_retries: 0,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_registry_server_publishes_and_mirrors() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let project = root.join("lib");
+        let release = project.join("target").join("ledokoz").join("release");
+        std::fs::create_dir_all(&release).unwrap();
+        std::fs::write(release.join("libshared.rlib"), b"rlib").unwrap();
+        let config = ProjectConfig {
+            name: "shared".to_string(),
+            kind: ProjectKind::Library,
+            ..Default::default()
+        };
+        let archive = moxlib::package(&project, &config, &Default::default())
+            .await
+            .unwrap();
+
+        let cancel = CancellationToken::new();
+        std::fs::create_dir_all(root.join("origin")).unwrap();
+        let origin = start(
+            RegistryServerConfig {
+                root: root.join("origin"),
+                addr: ([127, 0, 0, 1], 0).into(),
+                upstream: None,
+                token: Some("secret".to_string()),
+            },
+            &cancel,
+        );
+
+        let anonymous = client(&root.join("anonymous"), &origin, None);
+        assert!(matches!(
+            anonymous.publish_remote(&archive).await,
+            Err(ForgeKitError::AuthenticationRequired(_))
+        ));
+        let publisher = client(&root.join("publisher"), &origin, Some("secret"));
+        let info = publisher.publish_remote(&archive).await.unwrap();
+        assert_eq!(info.archive_url, "/api/v1/packages/shared/0.1.0/download");
+        assert!(publisher.publish_remote(&archive).await.is_err());
+
+        std::fs::create_dir_all(root.join("mirror")).unwrap();
+        let mirror = start(
+            RegistryServerConfig {
+                root: root.join("mirror"),
+                addr: ([127, 0, 0, 1], 0).into(),
+                upstream: Some(origin),
+                token: None,
+            },
+            &cancel,
+        );
+        let user = client(&root.join("user"), &mirror, None);
+        let found = user.search_packages("shar").await.unwrap();
+        assert_eq!(found[0].name, "shared");
+        user.get_package_info("shared", "0.1.0").await.unwrap();
+        let downloaded = user.download_package("shared", "0.1.0").await.unwrap();
+        assert_eq!(downloaded.extension().unwrap(), MOXLIB_EXTENSION);
+        assert_eq!(hash_file(&downloaded).unwrap(), info.checksum);
+        assert!(root.join("mirror/packages/shared-0.1.0.moxlib").exists());
+
+        // The mirrored package is now in the mirror's own index
+        let fresh = client(&root.join("fresh"), &mirror, None);
+        fresh.update_index().await.unwrap();
+        assert_eq!(
+            fresh.indexed_versions("shared").unwrap(),
+            Some(vec!["0.1.0".to_string()])
+        );
+        cancel.cancel();
+    }
+
+    #[tokio::test]
+    async fn test_chunked_upload_resumes_and_verifies() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let project = root.join("lib");
+        let release = project.join("target").join("ledokoz").join("release");
+        std::fs::create_dir_all(&release).unwrap();
+        std::fs::write(release.join("libbulky.rlib"), b"rlib").unwrap();
+        // Incompressible data, so the archive spans several chunks
+        let mut seed = 1u64;
+        let noise: Vec<u8> = (0..300 * 1024)
+            .map(|_| {
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+                (seed >> 56) as u8
+            })
+            .collect();
+        std::fs::create_dir_all(project.join("src")).unwrap();
+        std::fs::write(project.join("src").join("noise.bin"), noise).unwrap();
+        let config = ProjectConfig {
+            name: "bulky".to_string(),
+            kind: ProjectKind::Library,
+            ..Default::default()
+        };
+        let archive = moxlib::package(&project, &config, &Default::default())
+            .await
+            .unwrap();
+        let bytes = std::fs::read(&archive).unwrap();
+        let checksum = hash_file(&archive).unwrap();
+
+        let cancel = CancellationToken::new();
+        std::fs::create_dir_all(root.join("registry")).unwrap();
+        let url = start(
+            RegistryServerConfig {
+                root: root.join("registry"),
+                addr: ([127, 0, 0, 1], 0).into(),
+                upstream: None,
+                token: Some("secret".to_string()),
+            },
+            &cancel,
+        );
+
+        // An earlier attempt sent the first chunk before failing
+        let http = reqwest::Client::new();
+        let create = |length: usize, checksum: &str| {
+            http.post(format!("{}/api/v1/uploads", url))
+                .bearer_auth("secret")
+                .header(upload::UPLOAD_LENGTH, length)
+                .header(upload::UPLOAD_CHECKSUM, upload::checksum_header(checksum))
+                .send()
+        };
+        let created = create(bytes.len(), &checksum).await.unwrap();
+        assert_eq!(created.status(), StatusCode::CREATED);
+        let location = format!("{}{}", url, created.headers()["location"].to_str().unwrap());
+        let first = 64 * 1024;
+        let patch = |location: &str, offset: usize, chunk: Vec<u8>| {
+            http.patch(location)
+                .bearer_auth("secret")
+                .header(upload::UPLOAD_OFFSET, offset)
+                .body(chunk)
+                .send()
+        };
+        let sent = patch(&location, 0, bytes[..first].to_vec()).await.unwrap();
+        assert_eq!(sent.status(), StatusCode::NO_CONTENT);
+        assert_eq!(
+            patch(&location, 7, b"late".to_vec())
+                .await
+                .unwrap()
+                .status(),
+            StatusCode::CONFLICT
+        );
+        PendingUpload {
+            url: location,
+            size: bytes.len() as u64,
+            checksum: checksum.clone(),
+        }
+        .save(&archive)
+        .unwrap();
+
+        let progress = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = Arc::clone(&progress);
+        let options = upload::UploadOptions {
+            chunk_size: first as u64,
+            rate_limit: None,
+        };
+        let info = client(&root.join("publisher"), &url, Some("secret"))
+            .publish_remote_with(
+                &archive,
+                &options,
+                Some(Arc::new(move |p: &upload::UploadProgress| {
+                    seen.lock().unwrap().push(p.uploaded)
+                })),
+            )
+            .await
+            .unwrap();
+        assert_eq!(info.checksum, checksum);
+        let progress = progress.lock().unwrap().clone();
+        assert_eq!(progress[0], first as u64);
+        assert_eq!(*progress.last().unwrap(), bytes.len() as u64);
+        assert!(progress.len() > 3);
+        assert!(!PendingUpload::path(&archive).exists());
+
+        // An assembled upload that does not match its checksum is refused
+        let created = create(3, &"0".repeat(64)).await.unwrap();
+        let location = format!("{}{}", url, created.headers()["location"].to_str().unwrap());
+        patch(&location, 0, b"abc".to_vec()).await.unwrap();
+        let finished = http
+            .post(format!("{}/publish", location))
+            .bearer_auth("secret")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(finished.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        cancel.cancel();
+    }
+}
@@ -0,0 +1,258 @@
+//! Release profile module
+//!
+//! This module applies `[build.profile]` to cargo's release profile: the
+//! linker, link-time optimization, codegen units, panic strategy and
+//! stripping. The settings are passed to `cargo build` as `--config`
+//! overrides, so they take precedence over the project's Cargo.toml, and can
+//! differ per environment through `[env.<name>.build.profile]`. Settings that
+//! do not work together, or need a linker the machine lacks, are reported
+//! before the build starts, and the build report lists what each configured
+//! setting does. `opt_level` and `rustflags` of `[build]`, which
+//! `[env.<name>.build]` can override too, are passed the same way.
+
+use crate::config::{
+    BuildConfig, Linker, LtoMode, PanicStrategy, ReleaseProfileConfig, SandboxMode, StripMode,
+};
+use serde::Serialize;
+
+/// A configured setting of the release profile
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProfileSetting {
+    /// Key in `[build.profile]`
+    pub key: &'static str,
+    /// Configured value
+    pub value: String,
+    /// What the value does to the binary and the build
+    pub impact: String,
+}
+
+/// Arguments added to `cargo build` to apply the settings
+pub fn cargo_args(config: &ReleaseProfileConfig) -> Vec<String> {
+    let mut overrides = Vec::new();
+    if let Some(linker) = config.linker {
+        overrides.push(format!(
+            "build.rustflags=[\"-C\", \"link-arg=-fuse-ld={}\"]",
+            linker.as_str()
+        ));
+    }
+    if let Some(lto) = config.lto {
+        overrides.push(format!("profile.release.lto=\"{}\"", lto.as_str()));
+    }
+    if let Some(units) = config.codegen_units {
+        overrides.push(format!("profile.release.codegen-units={}", units));
+    }
+    if let Some(panic) = config.panic {
+        overrides.push(format!("profile.release.panic=\"{}\"", panic.as_str()));
+    }
+    if let Some(strip) = config.strip {
+        overrides.push(format!("profile.release.strip=\"{}\"", strip.as_str()));
+    }
+    overrides
+        .into_iter()
+        .flat_map(|value| ["--config".to_string(), value])
+        .collect()
+}
+
+/// Arguments added to `cargo build` to apply `opt_level` and `rustflags` of
+/// `[build]`, as resolved for the environment
+pub fn build_args(config: &BuildConfig) -> Vec<String> {
+    // Numbers are bare in cargo's profiles, `s` and `z` strings
+    let opt_level = match config.opt_level.parse::<u8>() {
+        Ok(level) => level.to_string(),
+        Err(_) => format!("\"{}\"", config.opt_level),
+    };
+    let mut overrides = vec![format!("profile.release.opt-level={}", opt_level)];
+    if !config.rustflags.is_empty() {
+        let flags: Vec<String> = config
+            .rustflags
+            .iter()
+            .map(|flag| toml::Value::String(flag.clone()).to_string())
+            .collect();
+        overrides.push(format!("build.rustflags=[{}]", flags.join(", ")));
+    }
+    overrides
+        .into_iter()
+        .flat_map(|value| ["--config".to_string(), value])
+        .collect()
+}
+
+/// Problems with the settings of a build, empty when it can go ahead
+pub fn check(config: &BuildConfig) -> Vec<String> {
+    let profile = &config.profile;
+    let mut problems = Vec::new();
+    if profile.codegen_units == Some(0) {
+        problems.push("[build.profile] codegen_units must be at least 1".to_string());
+    }
+    if let Some(strip @ (StripMode::Debuginfo | StripMode::Symbols)) = profile.strip {
+        if config.symbols.split {
+            problems.push(format!(
+                "[build.profile] strip = \"{}\" removes the debug info [build.symbols] split keeps for crash reports; strip \"none\" or turn off split",
+                strip.as_str()
+            ));
+        }
+    }
+    if let Some(linker) = profile.linker {
+        if linker == Linker::Mold && !cfg!(target_os = "linux") {
+            problems.push("[build.profile] linker = \"mold\" only links on Linux".to_string());
+        } else if config.sandbox.mode != SandboxMode::Docker && !on_path(linker_program(linker)) {
+            // Docker builds link inside the image, which is not checked
+            problems.push(format!(
+                "[build.profile] linker = \"{}\" needs `{}` on PATH",
+                linker.as_str(),
+                linker_program(linker)
+            ));
+        }
+    }
+    problems
+}
+
+/// The configured settings and what each one does, for the build report
+pub fn describe(config: &ReleaseProfileConfig) -> Vec<ProfileSetting> {
+    let mut settings = Vec::new();
+    let mut add = |key, value: &str, impact: &str| {
+        settings.push(ProfileSetting {
+            key,
+            value: value.to_string(),
+            impact: impact.to_string(),
+        })
+    };
+    if let Some(linker) = config.linker {
+        let impact = match linker {
+            Linker::Lld => "links several times faster than the system linker",
+            Linker::Mold => "links fastest, in parallel",
+        };
+        add("linker", linker.as_str(), impact);
+    }
+    if let Some(lto) = config.lto {
+        let impact = match lto {
+            LtoMode::Off => "no optimization across crates; shortest link",
+            LtoMode::Thin => "most of fat LTO's gains at a fraction of its link time",
+            LtoMode::Fat => "smallest and fastest binary; longest link",
+        };
+        add("lto", lto.as_str(), impact);
+    }
+    if let Some(units) = config.codegen_units {
+        let impact = if units == 1 {
+            "best optimization, but each crate compiles on one thread"
+        } else {
+            "crates compile in parallel at some cost to optimization"
+        };
+        add("codegen_units", &units.to_string(), impact);
+    }
+    if let Some(panic) = config.panic {
+        let impact = match panic {
+            PanicStrategy::Unwind => "panics unwind and can be caught",
+            PanicStrategy::Abort => "smaller binary; panics abort without running destructors",
+        };
+        add("panic", panic.as_str(), impact);
+    }
+    if let Some(strip) = config.strip {
+        let impact = match strip {
+            StripMode::None => "symbols and debug info are kept",
+            StripMode::Debuginfo => "smaller binary; backtraces keep function names",
+            StripMode::Symbols => "smallest binary; backtraces lose function names",
+        };
+        add("strip", strip.as_str(), impact);
+    }
+    settings
+}
+
+/// Program `-fuse-ld` runs for a linker
+fn linker_program(linker: Linker) -> &'static str {
+    match linker {
+        Linker::Lld => "ld.lld",
+        Linker::Mold => "mold",
+    }
+}
+
+/// Whether an executable named `program` is in one of the PATH directories
+fn on_path(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ProjectConfig;
+
+    #[test]
+    fn test_profile_settings_become_cargo_overrides_and_are_checked() {
+        let mut config = ProjectConfig::default();
+        assert!(cargo_args(&config.build.profile).is_empty());
+        assert!(describe(&config.build.profile).is_empty());
+
+        config.build.profile = ReleaseProfileConfig {
+            lto: Some(LtoMode::Thin),
+            codegen_units: Some(1),
+            panic: Some(PanicStrategy::Abort),
+            strip: Some(StripMode::Symbols),
+            ..Default::default()
+        };
+        assert_eq!(
+            cargo_args(&config.build.profile),
+            [
+                "--config",
+                "profile.release.lto=\"thin\"",
+                "--config",
+                "profile.release.codegen-units=1",
+                "--config",
+                "profile.release.panic=\"abort\"",
+                "--config",
+                "profile.release.strip=\"symbols\"",
+            ]
+        );
+        assert_eq!(
+            build_args(&config.build),
+            ["--config", "profile.release.opt-level=2"]
+        );
+        config.build.opt_level = "z".to_string();
+        config.build.rustflags = vec!["-Ctarget-cpu=native".to_string()];
+        assert_eq!(
+            build_args(&config.build),
+            [
+                "--config",
+                "profile.release.opt-level=\"z\"",
+                "--config",
+                "build.rustflags=[\"-Ctarget-cpu=native\"]",
+            ]
+        );
+        let keys: Vec<_> = describe(&config.build.profile)
+            .iter()
+            .map(|setting| setting.key)
+            .collect();
+        assert_eq!(keys, ["lto", "codegen_units", "panic", "strip"]);
+        config.build.symbols.split = false;
+        assert!(check(&config.build).is_empty());
+
+        config.build.symbols.split = true;
+        config.build.profile.codegen_units = Some(0);
+        let problems = check(&config.build);
+        assert_eq!(problems.len(), 2);
+        assert!(problems[1].contains("[build.symbols] split"));
+
+        let config: ProjectConfig = toml::from_str(
+            r#"
+name = "demo"
+version = "0.1.0"
+authors = []
+dependencies = []
+
+[build]
+target = "ledokoz"
+opt_level = "2"
+rustflags = []
+output_dir = "target"
+
+[env.prod.build.profile]
+lto = "fat"
+linker = "mold"
+"#,
+        )
+        .unwrap();
+        let prod = config.resolve(Some("prod")).unwrap();
+        assert_eq!(prod.build.profile.lto, Some(LtoMode::Fat));
+        assert!(cargo_args(&prod.build.profile)[1].ends_with("-fuse-ld=mold\"]"));
+        assert!(config.build.profile.is_default());
+    }
+}
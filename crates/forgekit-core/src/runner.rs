@@ -0,0 +1,364 @@
+//! Runner module
+//!
+//! This module supervises an app started with `forgekit run` the way Ledokoz
+//! OS supervises it on the device. The app is restarted according to a
+//! [`RestartPolicy`] with exponential backoff up to a maximum number of
+//! retries, an optional health command gates the "ready" state, and every
+//! attempt ends up in a structured [`RunReport`]. Output goes through the
+//! [`LogRouter`](crate::logs::LogRouter).
+
+use crate::cancel::CancellationToken;
+use crate::error::ForgeKitError;
+use crate::hooks::shell;
+use crate::logs::LogRouter;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::{ExitStatus, Stdio};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+
+/// When a stopped app is started again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    /// Never restart
+    #[default]
+    Never,
+    /// Restart after a non-zero exit or a signal
+    OnFailure,
+    /// Restart after any exit
+    Always,
+}
+
+impl RestartPolicy {
+    /// Whether an app that exited with `status` is restarted
+    pub fn restarts(&self, status: &ExitStatus) -> bool {
+        match self {
+            Self::Never => false,
+            Self::OnFailure => !status.success(),
+            Self::Always => true,
+        }
+    }
+}
+
+impl FromStr for RestartPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "never" | "no" => Ok(Self::Never),
+            "on-failure" => Ok(Self::OnFailure),
+            "always" => Ok(Self::Always),
+            _ => Err(format!(
+                "unknown restart policy '{}' (expected never, on-failure or always)",
+                s
+            )),
+        }
+    }
+}
+
+/// How the app is supervised
+#[derive(Debug, Clone)]
+pub struct RunOptions {
+    /// When the app is restarted
+    pub restart: RestartPolicy,
+    /// Maximum number of restarts
+    pub max_retries: u32,
+    /// Delay before the first restart, doubled for each further one
+    pub backoff: Duration,
+    /// Upper bound of the restart delay
+    pub max_backoff: Duration,
+    /// Shell command that succeeds once the app is ready
+    pub health_cmd: Option<String>,
+    /// Interval between health checks
+    pub health_interval: Duration,
+    /// Extra environment variables of the app
+    pub env: Vec<(String, String)>,
+    /// Cancellation token stopping the app and the supervisor
+    pub cancel: CancellationToken,
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        Self {
+            restart: RestartPolicy::Never,
+            max_retries: 5,
+            backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+            health_cmd: None,
+            health_interval: Duration::from_secs(1),
+            env: Vec::new(),
+            cancel: CancellationToken::new(),
+        }
+    }
+}
+
+impl RunOptions {
+    /// Delay before the given restart (1-based)
+    pub fn restart_delay(&self, restart: u32) -> Duration {
+        self.backoff
+            .saturating_mul(2u32.saturating_pow(restart.saturating_sub(1)))
+            .min(self.max_backoff)
+    }
+}
+
+/// Something that happened while supervising the app
+#[derive(Debug, Clone, PartialEq)]
+pub enum RunEvent {
+    /// The app was started (attempts count from 1)
+    Started { attempt: u32 },
+    /// The app passed its health check, or started without one
+    Ready { attempt: u32, after: Duration },
+    /// The app stopped
+    Exited {
+        attempt: u32,
+        code: Option<i32>,
+        signal: Option<i32>,
+    },
+    /// The app is restarted after a delay
+    Restarting { attempt: u32, delay: Duration },
+}
+
+/// Outcome of one run of the app
+#[derive(Debug, Clone, Serialize)]
+pub struct RunAttempt {
+    /// Attempt number, from 1
+    pub attempt: u32,
+    /// Exit code, if the app exited normally
+    pub exit_code: Option<i32>,
+    /// Signal that terminated the app
+    pub signal: Option<i32>,
+    /// How long the app ran in milliseconds
+    pub duration_ms: u64,
+    /// Whether the app became ready
+    pub ready: bool,
+}
+
+/// Outcome of a supervised run
+#[derive(Debug, Clone, Serialize)]
+pub struct RunReport {
+    /// Every run of the app, in order
+    pub attempts: Vec<RunAttempt>,
+    /// Whether restarts stopped because `max_retries` was reached
+    pub gave_up: bool,
+    /// Whether the run was interrupted
+    pub cancelled: bool,
+}
+
+impl RunReport {
+    /// Whether the last run of the app exited successfully
+    pub fn success(&self) -> bool {
+        self.attempts
+            .last()
+            .is_some_and(|attempt| attempt.exit_code == Some(0))
+    }
+
+    /// Exit code of the last run, -1 when it was killed by a signal
+    pub fn exit_code(&self) -> i32 {
+        self.attempts
+            .last()
+            .and_then(|attempt| attempt.exit_code)
+            .unwrap_or(-1)
+    }
+}
+
+/// Supervises a local app
+pub struct Runner {
+    command: PathBuf,
+    args: Vec<String>,
+    options: RunOptions,
+}
+
+impl Runner {
+    /// Create a runner for a binary
+    pub fn new(command: impl Into<PathBuf>, options: RunOptions) -> Self {
+        Self {
+            command: command.into(),
+            args: Vec::new(),
+            options,
+        }
+    }
+
+    /// Arguments passed to the binary
+    pub fn with_args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
+
+    /// Run the app until it stops for good
+    ///
+    /// `on_event` is called as the app is started, becomes ready, exits and
+    /// is restarted.
+    pub async fn run(
+        &self,
+        router: &mut LogRouter,
+        mut on_event: impl FnMut(&RunEvent),
+    ) -> Result<RunReport, ForgeKitError> {
+        let cancel = &self.options.cancel;
+        let mut report = RunReport {
+            attempts: Vec::new(),
+            gave_up: false,
+            cancelled: false,
+        };
+
+        for attempt in 1.. {
+            let mut child = Command::new(&self.command)
+                .args(&self.args)
+                .envs(self.options.env.iter().map(|(k, v)| (k, v)))
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn()?;
+            on_event(&RunEvent::Started { attempt });
+
+            let started = Instant::now();
+            let mut ready = false;
+            let status = {
+                let route = router.route(&mut child);
+                let health = self.wait_healthy();
+                tokio::pin!(route, health);
+                loop {
+                    tokio::select! {
+                        status = &mut route => break Some(status?),
+                        _ = &mut health, if !ready => {
+                            ready = true;
+                            on_event(&RunEvent::Ready { attempt, after: started.elapsed() });
+                        }
+                        _ = cancel.cancelled() => break None,
+                    }
+                }
+            };
+            // Dropping the child kills an app that is still running
+            let status = match status {
+                Some(status) => status,
+                None => {
+                    drop(child);
+                    report.cancelled = true;
+                    return Ok(report);
+                }
+            };
+
+            let (code, signal) = (status.code(), exit_signal(&status));
+            on_event(&RunEvent::Exited {
+                attempt,
+                code,
+                signal,
+            });
+            report.attempts.push(RunAttempt {
+                attempt,
+                exit_code: code,
+                signal,
+                duration_ms: started.elapsed().as_millis() as u64,
+                ready,
+            });
+
+            if !self.options.restart.restarts(&status) || cancel.is_cancelled() {
+                break;
+            }
+            if attempt > self.options.max_retries {
+                report.gave_up = true;
+                break;
+            }
+            let delay = self.options.restart_delay(attempt);
+            on_event(&RunEvent::Restarting {
+                attempt: attempt + 1,
+                delay,
+            });
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = cancel.cancelled() => {
+                    report.cancelled = true;
+                    break;
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Resolve once the health command succeeds, immediately without one
+    async fn wait_healthy(&self) {
+        let Some(health_cmd) = &self.options.health_cmd else {
+            return;
+        };
+        loop {
+            tokio::time::sleep(self.options.health_interval).await;
+            let healthy = shell(health_cmd)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .kill_on_drop(true)
+                .status()
+                .await
+                .is_ok_and(|status| status.success());
+            if healthy {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn exit_signal(status: &ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+#[cfg(not(unix))]
+fn exit_signal(_status: &ExitStatus) -> Option<i32> {
+    None
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::logs::LogFilter;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_restart_on_failure() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut router = LogRouter::new(temp_dir.path(), LogFilter::default()).unwrap();
+        let options = RunOptions {
+            restart: RestartPolicy::OnFailure,
+            max_retries: 2,
+            backoff: Duration::from_millis(1),
+            health_cmd: Some("true".to_string()),
+            health_interval: Duration::from_millis(1),
+            ..Default::default()
+        };
+        let runner = Runner::new("sh", options).with_args(vec![
+            "-c".to_string(),
+            "sleep 0.2; echo attempt; exit 3".to_string(),
+        ]);
+
+        let mut events = Vec::new();
+        let report = runner
+            .run(&mut router, |event| events.push(event.clone()))
+            .await
+            .unwrap();
+        assert_eq!(report.attempts.len(), 3);
+        assert!(report.gave_up);
+        assert!(report.attempts.iter().all(|a| a.ready));
+        assert_eq!(report.exit_code(), 3);
+        assert!(events.contains(&RunEvent::Restarting {
+            attempt: 3,
+            delay: Duration::from_millis(2)
+        }));
+
+        let log = std::fs::read_to_string(
+            crate::logs::logs_dir(temp_dir.path()).join(crate::logs::LOG_FILE),
+        )
+        .unwrap();
+        assert_eq!(log.matches("attempt").count(), 3);
+    }
+
+    #[test]
+    fn test_restart_delay() {
+        let options = RunOptions::default();
+        assert_eq!(options.restart_delay(1), Duration::from_secs(1));
+        assert_eq!(options.restart_delay(3), Duration::from_secs(4));
+        assert_eq!(options.restart_delay(10), Duration::from_secs(30));
+        assert!("sometimes".parse::<RestartPolicy>().is_err());
+    }
+}
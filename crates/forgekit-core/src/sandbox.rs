@@ -0,0 +1,476 @@
+//! Build sandbox module
+//!
+//! This module runs cargo inside a constrained environment configured by
+//! `[build.sandbox]`, so build scripts of untrusted dependencies cannot read
+//! secrets such as `~/.ssh` or credentials passed through the environment.
+//! Three levels are available: a scrubbed environment with a throwaway HOME,
+//! a bubblewrap user namespace that hides the real home directory, and a
+//! docker container that only sees the project. Only the last two keep
+//! files such as `~/.ssh` out of reach; the environment level leaves the
+//! filesystem as it is. Accesses the sandbox blocked are picked out of the
+//! build output and reported as violations.
+//!
+//! Sandboxed commands get their own CARGO_HOME that shares only the
+//! download caches (`registry` and `git`) with the real one, so registry
+//! tokens in `credentials.toml` and the installed binaries in `bin` are
+//! neither readable nor writable by build scripts.
+
+use crate::config::{SandboxConfig, SandboxMode};
+use crate::error::ForgeKitError;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::process::Command;
+
+/// Docker image used by the `docker` mode unless `[build.sandbox] image`
+/// names another; pinned so builds don't change under a moving tag
+pub const DEFAULT_IMAGE: &str = "rust:1.85.0";
+
+/// Directories of CARGO_HOME shared with sandboxed commands
+const CARGO_CACHE_DIRS: &[&str] = &["registry", "git"];
+
+/// Containers started by this process, for unique names
+static CONTAINERS: AtomicU64 = AtomicU64::new(0);
+
+/// Environment variables always passed into the sandbox
+const BASE_ENV: &[&str] = &[
+    "PATH",
+    "TERM",
+    "LANG",
+    "LC_ALL",
+    "TZ",
+    "RUSTUP_TOOLCHAIN",
+    "RUSTFLAGS",
+];
+
+/// Output fragments that indicate the sandbox blocked an access
+const VIOLATION_MARKERS: &[&str] = &[
+    "Permission denied",
+    "Operation not permitted",
+    "Read-only file system",
+];
+
+/// Sandbox for the commands of one project build
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    config: SandboxConfig,
+    project_path: PathBuf,
+    host_home: Option<PathBuf>,
+    cargo_home: PathBuf,
+    rustup_home: PathBuf,
+    container: String,
+}
+
+impl Sandbox {
+    /// Create the sandbox of a project
+    pub fn new(config: &SandboxConfig, project_path: &Path) -> Self {
+        let host_home = dirs::home_dir();
+        let home = host_home.clone().unwrap_or_else(|| PathBuf::from("."));
+        let from_env = |var: &str, default: &str| {
+            std::env::var_os(var)
+                .map(PathBuf::from)
+                .unwrap_or_else(|| home.join(default))
+        };
+
+        Self {
+            config: config.clone(),
+            project_path: project_path.to_path_buf(),
+            cargo_home: from_env("CARGO_HOME", ".cargo"),
+            rustup_home: from_env("RUSTUP_HOME", ".rustup"),
+            host_home,
+            container: format!(
+                "forgekit-sandbox-{}-{}",
+                std::process::id(),
+                CONTAINERS.fetch_add(1, Ordering::Relaxed)
+            ),
+        }
+    }
+
+    /// Whether commands run unsandboxed
+    pub fn is_disabled(&self) -> bool {
+        self.config.is_disabled()
+    }
+
+    /// Whether host-side tools such as sccache are usable inside the sandbox
+    pub fn supports_compiler_cache(&self) -> bool {
+        self.config.mode != SandboxMode::Docker
+    }
+
+    /// HOME seen by sandboxed commands
+    pub fn home(&self) -> PathBuf {
+        self.project_path
+            .join("target")
+            .join("forgekit")
+            .join("sandbox-home")
+    }
+
+    /// CARGO_HOME seen by sandboxed commands
+    pub fn cargo_home(&self) -> PathBuf {
+        self.home().join(".cargo")
+    }
+
+    /// Stop what a cancelled command left running
+    ///
+    /// Killing the docker client does not stop its container, so the
+    /// container is removed by name. The other modes need nothing.
+    pub async fn stop(&self) {
+        if self.config.mode != SandboxMode::Docker {
+            return;
+        }
+        let result = Command::new("docker")
+            .args(["rm", "--force", &self.container])
+            .output()
+            .await;
+        if let Err(e) = result {
+            tracing::warn!("Failed to remove container {}: {}", self.container, e);
+        }
+    }
+
+    /// Build the command running `program args` in the project directory
+    ///
+    /// `target_dir` is the cargo target directory when it lies outside the
+    /// project; it is made writable inside the sandbox.
+    pub async fn command(
+        &self,
+        program: &str,
+        args: &[&str],
+        target_dir: Option<&Path>,
+    ) -> Result<Command, ForgeKitError> {
+        let mut command = match self.config.mode {
+            SandboxMode::None | SandboxMode::Env => Command::new(program),
+            SandboxMode::Namespace => {
+                if !tool_available("bwrap").await {
+                    return Err(ForgeKitError::BuildFailed(
+                        "namespace sandbox requires bubblewrap (bwrap) on Linux".to_string(),
+                    ));
+                }
+                let mut command = Command::new("bwrap");
+                command.args(self.bwrap_args(target_dir)).arg(program);
+                command
+            }
+            SandboxMode::Docker => {
+                if !tool_available("docker").await {
+                    return Err(ForgeKitError::BuildFailed(
+                        "docker sandbox requires docker".to_string(),
+                    ));
+                }
+                if !is_pinned(&self.config.image) {
+                    tracing::warn!(
+                        "sandbox image {} is not pinned; builds may change when it is updated",
+                        self.config.image
+                    );
+                }
+                let mut command = Command::new("docker");
+                command.args(self.docker_args(target_dir)).arg(program);
+                command.args(args);
+                return Ok(command);
+            }
+        };
+        command.args(args).current_dir(&self.project_path);
+        if !self.is_disabled() {
+            self.prepare_cargo_home()?;
+            command.env_clear();
+            for (name, value) in self.allowed_env() {
+                command.env(name, value);
+            }
+            command
+                .env("HOME", self.home())
+                .env("CARGO_HOME", self.cargo_home())
+                .env("RUSTUP_HOME", &self.rustup_home);
+        }
+        if let Some(target_dir) = target_dir {
+            command.env("CARGO_TARGET_DIR", target_dir);
+        }
+        Ok(command)
+    }
+
+    /// Lines of build output showing an access the sandbox blocked
+    pub fn violations(&self, output: &str) -> Vec<String> {
+        if self.is_disabled() {
+            return Vec::new();
+        }
+
+        let home = self
+            .host_home
+            .as_ref()
+            .map(|home| home.to_string_lossy().to_string());
+        output
+            .lines()
+            .filter(|line| {
+                VIOLATION_MARKERS.iter().any(|marker| line.contains(marker))
+                    || line.contains(".ssh")
+                    || home.as_ref().is_some_and(|home| {
+                        line.contains(&format!("{}/", home))
+                            && !line.contains(&*self.cargo_home.to_string_lossy())
+                            && !line.contains(&*self.project_path.to_string_lossy())
+                    })
+            })
+            .map(|line| line.trim().to_string())
+            .collect()
+    }
+
+    /// Create the sandbox CARGO_HOME with the caches of the real one
+    ///
+    /// The namespace mode mounts the caches over the empty directories;
+    /// otherwise they are linked, where the platform allows it.
+    fn prepare_cargo_home(&self) -> Result<(), ForgeKitError> {
+        let cargo_home = self.cargo_home();
+        std::fs::create_dir_all(&cargo_home)?;
+        for dir in CARGO_CACHE_DIRS {
+            let (shared, own) = (self.cargo_home.join(dir), cargo_home.join(dir));
+            if self.config.mode == SandboxMode::Namespace || !shared.exists() {
+                if !own.is_dir() {
+                    std::fs::create_dir_all(&own)?;
+                }
+                continue;
+            }
+            if own.symlink_metadata().is_ok() {
+                continue;
+            }
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(&shared, &own)?;
+            #[cfg(not(unix))]
+            std::fs::create_dir_all(&own)?;
+        }
+        Ok(())
+    }
+
+    /// Variables of the host environment passed into the sandbox
+    fn allowed_env(&self) -> Vec<(String, OsString)> {
+        BASE_ENV
+            .iter()
+            .copied()
+            .chain(self.config.allow_env.iter().map(String::as_str))
+            .filter_map(|name| std::env::var_os(name).map(|value| (name.to_string(), value)))
+            .collect()
+    }
+
+    /// bubblewrap arguments: read-only host, hidden home, writable project
+    fn bwrap_args(&self, target_dir: Option<&Path>) -> Vec<OsString> {
+        let mut args: Vec<OsString> = vec!["--ro-bind".into(), "/".into(), "/".into()];
+        if let Some(home) = &self.host_home {
+            args.extend(["--tmpfs".into(), home.into()]);
+        }
+        let under_home = self
+            .host_home
+            .as_ref()
+            .is_some_and(|home| self.cargo_home.starts_with(home));
+        if !under_home && self.cargo_home.exists() {
+            args.extend(["--tmpfs".into(), self.cargo_home.clone().into()]);
+        }
+        let mut bind = |flag: &str, host: &Path, sandbox: &Path| {
+            if host.exists() {
+                args.extend([flag.into(), host.into(), sandbox.into()]);
+            }
+        };
+        // rustup's proxies in CARGO_HOME/bin are what PATH finds cargo as
+        let bin = self.cargo_home.join("bin");
+        bind("--ro-bind", &bin, &bin);
+        bind("--ro-bind", &self.rustup_home, &self.rustup_home);
+        bind("--bind", &self.project_path, &self.project_path);
+        for dir in CARGO_CACHE_DIRS {
+            bind(
+                "--bind",
+                &self.cargo_home.join(dir),
+                &self.cargo_home().join(dir),
+            );
+        }
+        if let Some(target_dir) = target_dir {
+            bind("--bind", target_dir, target_dir);
+        }
+
+        args.extend(
+            [
+                "--dev",
+                "/dev",
+                "--proc",
+                "/proc",
+                "--tmpfs",
+                "/tmp",
+                "--unshare-user",
+                "--unshare-pid",
+                "--die-with-parent",
+            ]
+            .map(OsString::from),
+        );
+        if !self.config.network {
+            args.push("--unshare-net".into());
+        }
+        args.extend(["--chdir".into(), self.project_path.clone().into()]);
+        args
+    }
+
+    /// docker arguments: only the project and cargo's download caches are
+    /// mounted
+    ///
+    /// The container is named so [`Sandbox::stop`] can remove it, and runs
+    /// an init process that passes signals on to cargo.
+    fn docker_args(&self, target_dir: Option<&Path>) -> Vec<OsString> {
+        let mut args: Vec<OsString> = vec![
+            "run".into(),
+            "--rm".into(),
+            "--init".into(),
+            "--name".into(),
+            self.container.clone().into(),
+        ];
+        let mut volume = |host: &Path, container: &str| {
+            let mut spec = host.as_os_str().to_owned();
+            spec.push(format!(":{}", container));
+            args.extend(["-v".into(), spec]);
+        };
+        volume(&self.project_path, "/work");
+        for dir in CARGO_CACHE_DIRS {
+            volume(
+                &self.cargo_home.join(dir),
+                &format!("/usr/local/cargo/{}", dir),
+            );
+        }
+        if let Some(target_dir) = target_dir {
+            volume(target_dir, "/target");
+            args.extend(["-e".into(), "CARGO_TARGET_DIR=/target".into()]);
+        }
+
+        for (name, value) in self.allowed_env() {
+            // The image has its own toolchain and PATH
+            if name == "PATH" || name == "RUSTUP_TOOLCHAIN" {
+                continue;
+            }
+            let mut assignment = OsString::from(format!("{}=", name));
+            assignment.push(value);
+            args.extend(["-e".into(), assignment]);
+        }
+        if !self.config.network {
+            args.extend(["--network".into(), "none".into()]);
+        }
+        args.extend([
+            "-w".into(),
+            "/work".into(),
+            self.config.image.clone().into(),
+        ]);
+        args
+    }
+}
+
+/// Whether a docker image reference names a fixed version or digest
+fn is_pinned(image: &str) -> bool {
+    if image.contains('@') {
+        return true;
+    }
+    // A tag follows the last colon after the last slash, which may also
+    // separate a registry port
+    let name = image.rsplit('/').next().unwrap_or(image);
+    name.split_once(':')
+        .is_some_and(|(_, tag)| !tag.is_empty() && tag != "latest")
+}
+
+/// Whether a tool is on the PATH
+async fn tool_available(tool: &str) -> bool {
+    Command::new(tool)
+        .arg("--version")
+        .output()
+        .await
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn sandbox(mode: SandboxMode, project: &Path) -> Sandbox {
+        let config = SandboxConfig {
+            mode,
+            allow_env: vec!["FORGEKIT_SANDBOX_ALLOWED".to_string()],
+            network: false,
+            ..Default::default()
+        };
+        Sandbox::new(&config, project)
+    }
+
+    #[tokio::test]
+    async fn test_env_sandbox_scrubs_environment() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_var("FORGEKIT_SANDBOX_ALLOWED", "1");
+        std::env::set_var("FORGEKIT_SANDBOX_SECRET", "hunter2");
+
+        let sandbox = sandbox(SandboxMode::Env, temp_dir.path());
+        let command = sandbox.command("cargo", &["build"], None).await.unwrap();
+        let envs: Vec<(String, Option<String>)> = command
+            .as_std()
+            .get_envs()
+            .map(|(k, v)| {
+                (
+                    k.to_string_lossy().to_string(),
+                    v.map(|v| v.to_string_lossy().to_string()),
+                )
+            })
+            .collect();
+        let get = |name: &str| envs.iter().find(|(k, _)| k == name).map(|(_, v)| v.clone());
+
+        assert_eq!(get("FORGEKIT_SANDBOX_ALLOWED"), Some(Some("1".to_string())));
+        assert_eq!(get("FORGEKIT_SANDBOX_SECRET"), None);
+        assert_eq!(
+            get("HOME"),
+            Some(Some(sandbox.home().to_string_lossy().to_string()))
+        );
+        assert_eq!(
+            get("CARGO_HOME"),
+            Some(Some(sandbox.cargo_home().to_string_lossy().to_string()))
+        );
+        assert!(sandbox.cargo_home().join("registry").exists());
+        assert!(!sandbox.cargo_home().join("credentials.toml").exists());
+    }
+
+    #[test]
+    fn test_bwrap_and_docker_args() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path();
+
+        let args = sandbox(SandboxMode::Namespace, project).bwrap_args(None);
+        let args: Vec<String> = args
+            .iter()
+            .map(|a| a.to_string_lossy().to_string())
+            .collect();
+        assert_eq!(&args[..3], ["--ro-bind", "/", "/"]);
+        assert!(args.contains(&"--unshare-net".to_string()));
+        let project_str = project.to_string_lossy().to_string();
+        assert!(args
+            .windows(3)
+            .any(|w| w[0] == "--bind" && w[1] == project_str && w[2] == project_str));
+        let namespace = sandbox(SandboxMode::Namespace, project);
+        assert!(!args
+            .windows(2)
+            .any(|w| w[0] == "--bind" && w[1] == namespace.cargo_home.to_string_lossy()));
+
+        let args = sandbox(SandboxMode::Docker, project).docker_args(None);
+        let args: Vec<String> = args
+            .iter()
+            .map(|a| a.to_string_lossy().to_string())
+            .collect();
+        assert!(args.contains(&format!("{}:/work", project_str)));
+        assert!(args.windows(2).any(|w| w == ["--network", "none"]));
+        assert!(args.contains(&"--init".to_string()));
+        assert!(args
+            .windows(2)
+            .any(|w| w[0] == "--name" && w[1].starts_with("forgekit-sandbox-")));
+        assert_eq!(args.last().unwrap(), DEFAULT_IMAGE);
+        assert!(is_pinned(DEFAULT_IMAGE));
+        assert!(is_pinned("registry.local:5000/rust@sha256:abc"));
+        assert!(!is_pinned("registry.local:5000/rust"));
+        assert!(!is_pinned("rust:latest"));
+    }
+
+    #[test]
+    fn test_violations() {
+        let temp_dir = TempDir::new().unwrap();
+        let output = "   Compiling evil v0.1.0\nerror: failed to read `/root/.ssh/id_rsa`: Permission denied (os error 13)\nwarning: unused import\n";
+
+        let violations = sandbox(SandboxMode::Env, temp_dir.path()).violations(output);
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].contains("id_rsa"));
+        assert!(sandbox(SandboxMode::None, temp_dir.path())
+            .violations(output)
+            .is_empty());
+    }
+}
@@ -0,0 +1,363 @@
+//! Config schema module
+//!
+//! This module exports a JSON Schema for forgekit.toml so editors using
+//! taplo (e.g. Even Better TOML in VS Code) can complete keys, show their
+//! documentation and flag wrong types as the file is edited. The schema is
+//! derived from the Rust configuration types: a tracing deserializer records
+//! the fields, enum variants, lists and maps each type asks for, and the
+//! descriptions come from [`manifest::SCHEMA`], the same key documentation
+//! the manifest diagnostics use.
+//!
+//! Required keys are not marked, since a file using `extends` or `include`
+//! may inherit them; missing keys are reported when the config is loaded.
+
+use crate::config::{GlobalConfig, ProjectConfig};
+use crate::error::ForgeKitError;
+use crate::manifest;
+use serde::de::value::{Error as TraceError, StrDeserializer};
+use serde::de::{self, DeserializeOwned, DeserializeSeed, Deserializer, IntoDeserializer, Visitor};
+use serde_json::{json, Map, Value};
+use std::path::PathBuf;
+
+/// File name of the exported forgekit.toml schema
+pub const SCHEMA_FILE: &str = "forgekit.schema.json";
+
+/// JSON Schema of forgekit.toml
+pub fn project_schema() -> Value {
+    let mut schema = trace::<ProjectConfig>();
+    if let Some(env) = schema.pointer_mut("/properties/env/additionalProperties") {
+        // Environment overrides take the keys of forgekit.toml itself
+        let description = env.get("description").cloned();
+        *env = json!({ "$ref": "#" });
+        if let Some(description) = description {
+            env["description"] = description;
+        }
+    }
+    if let Some(dependency) = schema.pointer_mut("/properties/dependencies/items/properties") {
+        // Removal entries are read by the layer merge, not by `Dependency`
+        dependency["removed"] = json!({
+            "type": "boolean",
+            "description": manifest::schema_doc("dependencies[].removed"),
+        });
+    }
+
+    let mut root = Map::new();
+    root.insert(
+        "$schema".to_string(),
+        json!("http://json-schema.org/draft-07/schema#"),
+    );
+    root.insert("title".to_string(), json!("forgekit.toml"));
+    root.insert(
+        "description".to_string(),
+        json!("ForgeKit project configuration"),
+    );
+    if let Value::Object(traced) = schema {
+        root.extend(traced);
+    }
+    Value::Object(root)
+}
+
+/// Where the forgekit.toml schema is installed for editors to reference
+pub fn schema_path() -> PathBuf {
+    GlobalConfig::config_dir().join("schemas").join(SCHEMA_FILE)
+}
+
+/// Write the forgekit.toml schema to [`schema_path`], returning the path
+///
+/// Editors are pointed at it with a `#:schema` directive at the top of
+/// forgekit.toml or a taplo rule for `**/forgekit.toml`.
+pub fn install() -> Result<PathBuf, ForgeKitError> {
+    let path = schema_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    crate::atomic::write(&path, serde_json::to_string_pretty(&project_schema())?)?;
+    Ok(path)
+}
+
+/// Schema of the type `T`, documented from the forgekit.toml keys
+fn trace<T: DeserializeOwned>() -> Value {
+    let mut schema = Value::Object(Map::new());
+    // The tracer always hands out values the visitors accept, so the sample
+    // value is of no interest and errors only mean an unsupported type
+    let _ = T::deserialize(Tracer {
+        path: String::new(),
+        schema: &mut schema,
+    });
+    schema
+}
+
+/// Deserializer recording the schema of whatever is deserialized from it
+struct Tracer<'a> {
+    /// forgekit.toml key being traced, e.g. `build.sandbox`
+    path: String,
+    schema: &'a mut Value,
+}
+
+impl<'a> Tracer<'a> {
+    fn record(&mut self, schema: Value) {
+        *self.schema = schema;
+        if let Some(doc) = manifest::schema_doc(&self.path) {
+            self.schema["description"] = json!(doc);
+        }
+    }
+
+    fn child(&self, separator: &str, name: &str) -> String {
+        if self.path.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}{}{}", self.path, separator, name)
+        }
+    }
+}
+
+macro_rules! trace_scalar {
+    ($($method:ident => $schema:expr, $visit:ident($value:expr);)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, TraceError> {
+                self.record($schema);
+                visitor.$visit($value)
+            }
+        )*
+    };
+}
+
+impl<'de, 'a> Deserializer<'de> for Tracer<'a> {
+    type Error = TraceError;
+
+    trace_scalar! {
+        deserialize_bool => json!({ "type": "boolean" }), visit_bool(false);
+        deserialize_i8 => json!({ "type": "integer" }), visit_i64(0);
+        deserialize_i16 => json!({ "type": "integer" }), visit_i64(0);
+        deserialize_i32 => json!({ "type": "integer" }), visit_i64(0);
+        deserialize_i64 => json!({ "type": "integer" }), visit_i64(0);
+        deserialize_u8 => json!({ "type": "integer", "minimum": 0, "maximum": u8::MAX }), visit_u64(0);
+        deserialize_u16 => json!({ "type": "integer", "minimum": 0, "maximum": u16::MAX }), visit_u64(0);
+        deserialize_u32 => json!({ "type": "integer", "minimum": 0, "maximum": u32::MAX }), visit_u64(0);
+        deserialize_u64 => json!({ "type": "integer", "minimum": 0 }), visit_u64(0);
+        deserialize_f32 => json!({ "type": "number" }), visit_f64(0.0);
+        deserialize_f64 => json!({ "type": "number" }), visit_f64(0.0);
+        deserialize_char => json!({ "type": "string", "maxLength": 1 }), visit_char('a');
+        deserialize_str => json!({ "type": "string" }), visit_str("");
+        deserialize_string => json!({ "type": "string" }), visit_str("");
+        deserialize_bytes => json!({ "type": "string" }), visit_str("");
+        deserialize_byte_buf => json!({ "type": "string" }), visit_str("");
+        deserialize_identifier => json!({ "type": "string" }), visit_str("");
+    }
+
+    /// Untyped values, e.g. `toml::Value`, accept anything
+    fn deserialize_any<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, TraceError> {
+        self.record(json!({}));
+        visitor.visit_bool(false)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        // TOML has no null; an absent key is how `None` is written
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, TraceError> {
+        self.record(json!({ "type": "array" }));
+        let path = format!("{}[]", self.path);
+        let mut items = Value::Object(Map::new());
+        let value = visitor.visit_seq(OneElement {
+            tracer: Some(Tracer {
+                path,
+                schema: &mut items,
+            }),
+        })?;
+        self.schema["items"] = items;
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, TraceError> {
+        self.record(json!({ "type": "object" }));
+        let path = self.child(".", "*");
+        let mut values = Value::Object(Map::new());
+        let value = visitor.visit_map(Entries {
+            keys: vec!["key"],
+            schemas: vec![(path, &mut values)],
+            next_value: None,
+        })?;
+        self.schema["additionalProperties"] = values;
+        Ok(value)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        mut self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        self.record(json!({ "type": "object", "additionalProperties": false }));
+        let mut properties: Vec<Value> = fields.iter().map(|_| json!({})).collect();
+        let schemas = fields
+            .iter()
+            .map(|field| self.child(".", field))
+            .zip(properties.iter_mut())
+            .collect();
+        let value = visitor.visit_map(Entries {
+            keys: fields.to_vec(),
+            schemas,
+            next_value: None,
+        })?;
+        let properties: Map<String, Value> = fields
+            .iter()
+            .map(|field| field.to_string())
+            .zip(properties)
+            .collect();
+        self.schema["properties"] = Value::Object(properties);
+        Ok(value)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        mut self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        self.record(json!({ "type": "string", "enum": variants }));
+        let first: StrDeserializer<'_, TraceError> = variants
+            .first()
+            .copied()
+            .unwrap_or_default()
+            .into_deserializer();
+        visitor.visit_enum(first)
+    }
+}
+
+/// A sequence of one element, traced as the item schema
+struct OneElement<'a> {
+    tracer: Option<Tracer<'a>>,
+}
+
+impl<'de, 'a> de::SeqAccess<'de> for OneElement<'a> {
+    type Error = TraceError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, TraceError> {
+        self.tracer
+            .take()
+            .map(|tracer| seed.deserialize(tracer))
+            .transpose()
+    }
+}
+
+/// Map entries, one per struct field or a single entry of a map
+struct Entries<'a> {
+    keys: Vec<&'static str>,
+    schemas: Vec<(String, &'a mut Value)>,
+    next_value: Option<(String, &'a mut Value)>,
+}
+
+impl<'de, 'a> de::MapAccess<'de> for Entries<'a> {
+    type Error = TraceError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, TraceError> {
+        if self.schemas.is_empty() {
+            return Ok(None);
+        }
+        let key = self.keys.remove(0);
+        self.next_value = Some(self.schemas.remove(0));
+        let key: StrDeserializer<'_, TraceError> = key.into_deserializer();
+        seed.deserialize(key).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, TraceError> {
+        let (path, schema) = self
+            .next_value
+            .take()
+            .ok_or_else(|| de::Error::custom("value requested before its key"))?;
+        seed.deserialize(Tracer { path, schema })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Schema node of a forgekit.toml key path as used in [`manifest::SCHEMA`]
+    fn node<'a>(schema: &'a Value, path: &str) -> Option<&'a Value> {
+        path.split('.').try_fold(schema, |node, part| {
+            let (name, items) = match part.strip_suffix("[]") {
+                Some(name) => (name, true),
+                None => (part, false),
+            };
+            let child = if name == "*" {
+                node.get("additionalProperties")?
+            } else {
+                node.get("properties")?.get(name)?
+            };
+            if items {
+                child.get("items")
+            } else {
+                Some(child)
+            }
+        })
+    }
+
+    #[test]
+    fn test_project_schema_covers_documented_keys() {
+        let schema = project_schema();
+        for (path, doc) in manifest::SCHEMA {
+            let node = node(&schema, path).unwrap_or_else(|| panic!("{} not in schema", path));
+            assert_eq!(node["description"], json!(doc), "{}", path);
+        }
+
+        let kind = node(&schema, "build.sandbox.mode").unwrap();
+        assert_eq!(kind["enum"], json!(["none", "env", "namespace", "docker"]));
+        let port = node(&schema, "services.*.port").unwrap();
+        assert_eq!(port["maximum"], json!(65535));
+        assert_eq!(node(&schema, "env.*").unwrap()["$ref"], json!("#"));
+        assert_eq!(schema["additionalProperties"], json!(false));
+    }
+}
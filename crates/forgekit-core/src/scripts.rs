@@ -0,0 +1,352 @@
+//! Task runner file module
+//!
+//! For teams where not everyone runs the forgekit CLI directly, `forgekit
+//! sync-scripts` writes a justfile or Makefile with shortcuts for building,
+//! testing, running and packaging, plus a recipe per entry of `[scripts]`
+//! in forgekit.toml. The environment profile is picked with `just env=prod
+//! build` or `make build ENV=prod`, defaults to `FORGEKIT_ENV` of the
+//! caller's environment and is exported to scripts as `FORGEKIT_ENV`.
+//! Scripts of several lines run as one shell script, like they read in
+//! forgekit.toml, stopping at the first failing command. Generated files start with a marker line; files without
+//! it were written by hand and are never overwritten.
+
+use crate::config::ProjectConfig;
+use crate::env_manager::ENVIRONMENT_VAR;
+use crate::error::ForgeKitError;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// First line of every generated file
+pub const MARKER: &str = "# Generated by `forgekit sync-scripts` from forgekit.toml.";
+
+/// Built-in recipes: name, description and the forgekit arguments, with
+/// whether the command takes `--env`
+const BUILTIN_RECIPES: &[(&str, &str, &str, bool)] = &[
+    ("build", "Build the project", "build", true),
+    ("test", "Run the tests", "test", false),
+    ("run", "Build and run the app", "run", true),
+    ("package", "Package the project", "package", false),
+    ("check", "Run the checks CI runs", "check", false),
+];
+
+/// Kind of task runner file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScriptFile {
+    /// `justfile`, for just
+    Justfile,
+    /// `Makefile`, for make
+    Makefile,
+}
+
+impl ScriptFile {
+    /// Every kind of file, in the order they are looked for
+    pub const ALL: [ScriptFile; 2] = [ScriptFile::Justfile, ScriptFile::Makefile];
+
+    /// Name of the file in the project
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            ScriptFile::Justfile => "justfile",
+            ScriptFile::Makefile => "Makefile",
+        }
+    }
+
+    /// Contents of the file for the project configured by `config`
+    pub fn render(&self, config: &ProjectConfig) -> Result<String, ForgeKitError> {
+        for name in config.scripts.keys() {
+            check_name(name)?;
+        }
+        Ok(match self {
+            ScriptFile::Justfile => justfile(config),
+            ScriptFile::Makefile => makefile(config),
+        })
+    }
+}
+
+/// Refuse script names that cannot be recipes or shadow a built-in one
+fn check_name(name: &str) -> Result<(), ForgeKitError> {
+    let valid = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !valid {
+        return Err(ForgeKitError::InvalidConfig(format!(
+            "[scripts] name '{}' is not a valid recipe name (letters, digits, `-` and `_`)",
+            name
+        )));
+    }
+    if BUILTIN_RECIPES.iter().any(|(builtin, ..)| *builtin == name) {
+        return Err(ForgeKitError::InvalidConfig(format!(
+            "[scripts] {} would shadow the built-in `{}` recipe",
+            name, name
+        )));
+    }
+    Ok(())
+}
+
+fn justfile(config: &ProjectConfig) -> String {
+    let mut file = format!(
+        "{}\n# Run it again after changing [scripts] instead of editing this file.\n\n\
+         # Environment profile, like `just env=prod build`\n\
+         env := env_var_or_default(\"{}\", \"\")\n\n\
+         export {} := env\n",
+        MARKER, ENVIRONMENT_VAR, ENVIRONMENT_VAR
+    );
+    for (name, description, command, takes_env) in BUILTIN_RECIPES {
+        let env = if *takes_env {
+            " {{ if env == \"\" { \"\" } else { \"--env \" + env } }}"
+        } else {
+            ""
+        };
+        file.push_str(&format!(
+            "\n# {}\n{}:\n    forgekit {}{}\n",
+            description, name, command, env
+        ));
+    }
+    for (name, script) in &config.scripts {
+        file.push_str(&format!(
+            "\n# Script `{}` of forgekit.toml\n{}:\n",
+            name, name
+        ));
+        let lines = script_lines(script);
+        if lines.len() > 1 {
+            // A shebang recipe runs the lines in one shell, rather than each
+            // in its own
+            file.push_str("    #!/usr/bin/env sh\n    set -e\n");
+        }
+        for line in lines {
+            // Literal braces would start a just interpolation
+            file.push_str(&format!("    {}\n", line.replace("{{", "{{{{")));
+        }
+    }
+    file
+}
+
+fn makefile(config: &ProjectConfig) -> String {
+    let phony = BUILTIN_RECIPES
+        .iter()
+        .map(|(name, ..)| *name)
+        .chain(config.scripts.keys().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut file = format!(
+        "{}\n# Run it again after changing [scripts] instead of editing this file.\n\n\
+         # Environment profile, like `make build ENV=prod`\n\
+         ENV ?= $({})\n\
+         export {} := $(ENV)\n\
+         ENV_FLAG := $(if $(ENV),--env $(ENV))\n\n\
+         .PHONY: {}\n",
+        MARKER, ENVIRONMENT_VAR, ENVIRONMENT_VAR, phony
+    );
+    for (name, description, command, takes_env) in BUILTIN_RECIPES {
+        let env = if *takes_env { " $(ENV_FLAG)" } else { "" };
+        file.push_str(&format!(
+            "\n# {}\n{}:\n\tforgekit {}{}\n",
+            description, name, command, env
+        ));
+    }
+    for (name, script) in &config.scripts {
+        file.push_str(&format!(
+            "\n# Script `{}` of forgekit.toml\n{}:\n",
+            name, name
+        ));
+        let lines = script_lines(script);
+        let recipe = if lines.len() > 1 {
+            // make runs every recipe line in its own shell, so the script is
+            // handed to a single one, a quoted argument per line
+            let quoted: Vec<String> = lines
+                .iter()
+                .map(|line| format!("'{}'", line.replace('\'', r"'\''")))
+                .collect();
+            format!("sh -ec \"$(printf '%s\\n' {})\"", quoted.join(" "))
+        } else {
+            lines.concat()
+        };
+        // make expands `$`; the shell should see it instead
+        file.push_str(&format!("\t{}\n", recipe.replace('$', "$$")));
+    }
+    file
+}
+
+/// Non-blank lines of `script`, without the indentation they all share
+fn script_lines(script: &str) -> Vec<&str> {
+    let lines: Vec<&str> = script
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty())
+        .collect();
+    let indent = lines
+        .iter()
+        .map(|line| line.len() - line.trim_start_matches([' ', '\t']).len())
+        .min()
+        .unwrap_or(0);
+    lines.into_iter().map(|line| &line[indent..]).collect()
+}
+
+/// What syncing did to a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncStatus {
+    /// Written with new contents
+    Written,
+    /// Already up to date
+    Unchanged,
+    /// Out of date; only reported when checking
+    Stale,
+}
+
+/// A task runner file that was synced
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SyncedFile {
+    /// Kind of file
+    pub kind: ScriptFile,
+    /// Path of the file
+    pub path: PathBuf,
+    /// What happened to it
+    pub status: SyncStatus,
+}
+
+/// Bring the task runner files of the project at `project_path` in line
+/// with its forgekit.toml
+///
+/// `kinds` are the files to write; when empty, the generated files already
+/// in the project, or a justfile if there are none. With `check`, nothing is
+/// written and out-of-date files are reported as [`SyncStatus::Stale`].
+pub fn sync(
+    project_path: &Path,
+    kinds: &[ScriptFile],
+    check: bool,
+) -> Result<Vec<SyncedFile>, ForgeKitError> {
+    let config = ProjectConfig::load(project_path.join("forgekit.toml"))?;
+    let mut kinds = kinds.to_vec();
+    if kinds.is_empty() {
+        kinds = ScriptFile::ALL
+            .into_iter()
+            .filter(|kind| is_generated(&project_path.join(kind.file_name())))
+            .collect();
+    }
+    if kinds.is_empty() {
+        kinds.push(ScriptFile::Justfile);
+    }
+
+    let mut synced = Vec::new();
+    for kind in kinds {
+        let path = project_path.join(kind.file_name());
+        let contents = kind.render(&config)?;
+        let existing = match std::fs::read_to_string(&path) {
+            Ok(existing) => Some(existing),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        if existing
+            .as_deref()
+            .is_some_and(|existing| !existing.starts_with(MARKER))
+        {
+            return Err(ForgeKitError::InvalidConfig(format!(
+                "{} was not generated by `forgekit sync-scripts`; move it away to generate one",
+                path.display()
+            )));
+        }
+
+        let status = if existing.as_deref() == Some(contents.as_str()) {
+            SyncStatus::Unchanged
+        } else if check {
+            SyncStatus::Stale
+        } else {
+            std::fs::write(&path, contents)?;
+            SyncStatus::Written
+        };
+        synced.push(SyncedFile { kind, path, status });
+    }
+    Ok(synced)
+}
+
+/// Whether the file at `path` was generated by `forgekit sync-scripts`
+fn is_generated(path: &Path) -> bool {
+    std::fs::read_to_string(path).is_ok_and(|contents| contents.starts_with(MARKER))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_recipes_include_scripts() {
+        let mut config = ProjectConfig::default();
+        config.scripts.insert(
+            "lint-assets".to_string(),
+            "npx stylelint \"assets/**/*.css\"\necho ${HOME} {{x}}".to_string(),
+        );
+        config
+            .scripts
+            .insert("fmt".to_string(), "  cargo fmt\n".to_string());
+
+        let justfile = ScriptFile::Justfile.render(&config).unwrap();
+        assert!(justfile.starts_with(MARKER));
+        assert!(justfile.contains("build:\n    forgekit build {{ if env == \"\""));
+        assert!(justfile.contains("env := env_var_or_default(\"FORGEKIT_ENV\", \"\")\n"));
+        assert!(justfile.contains(
+            "lint-assets:\n    #!/usr/bin/env sh\n    set -e\n    npx stylelint \"assets/**/*.css\"\n"
+        ));
+        assert!(justfile.contains("    echo ${HOME} {{{{x}}\n"));
+        assert!(justfile.contains("fmt:\n    cargo fmt\n"));
+
+        let makefile = ScriptFile::Makefile.render(&config).unwrap();
+        assert!(makefile.contains("ENV ?= $(FORGEKIT_ENV)\n"));
+        assert!(makefile.contains(".PHONY: build test run package check fmt lint-assets"));
+        assert!(makefile.contains("run:\n\tforgekit run $(ENV_FLAG)\n"));
+        assert!(makefile.contains(
+            "lint-assets:\n\tsh -ec \"$$(printf '%s\\n' 'npx stylelint \"assets/**/*.css\"' 'echo $${HOME} {{x}}')\"\n"
+        ));
+        assert!(makefile.contains("fmt:\n\tcargo fmt\n"));
+
+        config
+            .scripts
+            .insert("build".to_string(), "make".to_string());
+        assert!(ScriptFile::Justfile.render(&config).is_err());
+        config.scripts.clear();
+        config
+            .scripts
+            .insert("has space".to_string(), "true".to_string());
+        assert!(ScriptFile::Makefile.render(&config).is_err());
+    }
+
+    #[test]
+    fn test_sync_keeps_generated_files_current() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path();
+        let mut config = ProjectConfig::default();
+        config.save(project.join("forgekit.toml")).unwrap();
+
+        let synced = sync(project, &[], false).unwrap();
+        assert_eq!(synced.len(), 1);
+        assert_eq!(synced[0].kind, ScriptFile::Justfile);
+        assert_eq!(synced[0].status, SyncStatus::Written);
+        assert_eq!(
+            sync(project, &[], true).unwrap()[0].status,
+            SyncStatus::Unchanged
+        );
+
+        config
+            .scripts
+            .insert("seed".to_string(), "cargo run --bin seed".to_string());
+        config.save(project.join("forgekit.toml")).unwrap();
+        assert_eq!(
+            sync(project, &[], true).unwrap()[0].status,
+            SyncStatus::Stale
+        );
+        sync(project, &[], false).unwrap();
+        assert!(std::fs::read_to_string(project.join("justfile"))
+            .unwrap()
+            .contains("seed:\n    cargo run --bin seed"));
+
+        // A handwritten Makefile is left alone
+        std::fs::write(project.join("Makefile"), "all:\n\tcargo build\n").unwrap();
+        assert!(sync(project, &[ScriptFile::Makefile], false).is_err());
+        assert_eq!(sync(project, &[], false).unwrap().len(), 1);
+    }
+}
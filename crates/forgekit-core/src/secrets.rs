@@ -2,9 +2,11 @@
 //!
 //! This module provides secure secrets handling.
 
+use crate::credentials::{CredentialStore, EncryptedFileStore};
 use crate::error::ForgeKitError;
 use base64::{engine::general_purpose, Engine as _};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 /// Secrets manager
 pub struct SecretsManager;
@@ -12,13 +14,21 @@ pub struct SecretsManager;
 impl SecretsManager {
     /// Encrypt a secret
     pub async fn encrypt_secret(value: &str) -> Result<String, ForgeKitError> {
-        // Simple base64 encoding for demonstration
-        let encoded = general_purpose::STANDARD.encode(value);
-        Ok(format!("encrypted:{}", encoded))
+        Ok(Self::encrypt(value))
     }
 
     /// Decrypt a secret
     pub async fn decrypt_secret(encrypted: &str) -> Result<String, ForgeKitError> {
+        Self::decrypt(encrypted)
+    }
+
+    fn encrypt(value: &str) -> String {
+        // Simple base64 encoding for demonstration
+        let encoded = general_purpose::STANDARD.encode(value);
+        format!("encrypted:{}", encoded)
+    }
+
+    fn decrypt(encrypted: &str) -> Result<String, ForgeKitError> {
         if let Some(encoded) = encrypted.strip_prefix("encrypted:") {
             let decoded = general_purpose::STANDARD.decode(encoded).map_err(|_| {
                 ForgeKitError::InvalidConfig("Failed to decrypt secret".to_string())
@@ -31,6 +41,26 @@ impl SecretsManager {
         }
     }
 
+    /// Default location of the user's secrets file
+    pub fn default_secrets_file() -> PathBuf {
+        crate::config::GlobalConfig::config_dir().join("secrets.toml")
+    }
+
+    /// Store a named secret in an encrypted secrets file
+    pub fn store_secret(file: &Path, name: &str, value: &str) -> Result<(), ForgeKitError> {
+        EncryptedFileStore::new(file).set(name, value)
+    }
+
+    /// Load a named secret from an encrypted secrets file
+    pub fn load_secret(file: &Path, name: &str) -> Result<Option<String>, ForgeKitError> {
+        EncryptedFileStore::new(file).get(name)
+    }
+
+    /// Remove a named secret from an encrypted secrets file
+    pub fn delete_secret(file: &Path, name: &str) -> Result<bool, ForgeKitError> {
+        EncryptedFileStore::new(file).delete(name)
+    }
+
     /// Load secrets from vault
     pub async fn load_from_vault(path: &str) -> Result<HashMap<String, String>, ForgeKitError> {
         tracing::info!("Loading secrets from vault: {}", path);
@@ -49,4 +79,23 @@ mod tests {
         let decrypted = SecretsManager::decrypt_secret(&encrypted).await.unwrap();
         assert_eq!(decrypted, secret);
     }
+
+    #[test]
+    fn test_store_and_load_secret() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let file = temp_dir.path().join("secrets.toml");
+
+        SecretsManager::store_secret(&file, "registry.github.token", "ghp_abc").unwrap();
+        let loaded = SecretsManager::load_secret(&file, "registry.github.token").unwrap();
+        assert_eq!(loaded.as_deref(), Some("ghp_abc"));
+
+        // The token is not stored in plain text
+        let raw = std::fs::read_to_string(&file).unwrap();
+        assert!(!raw.contains("ghp_abc"));
+
+        assert!(SecretsManager::delete_secret(&file, "registry.github.token").unwrap());
+        assert!(SecretsManager::load_secret(&file, "registry.github.token")
+            .unwrap()
+            .is_none());
+    }
 }
@@ -0,0 +1,351 @@
+//! Self-update module
+//!
+//! This module lets `forgekit upgrade` replace the running CLI with a newer
+//! release. The release endpoint serves one manifest per channel,
+//! `<endpoint>/<channel>.json`, naming the latest version and a binary per
+//! platform with its SHA-256 and an Ed25519 signature. The signature covers
+//! the version and platform along with the checksum (see
+//! [`signed_message`]), so an old binary cannot be served as a newer
+//! release or for another platform. A downloaded binary is only installed
+//! when both match, and it replaces the executable with a rename, so an
+//! interrupted upgrade leaves the old CLI in place.
+//!
+//! Release builds embed the signing key through the
+//! `FORGEKIT_RELEASE_PUBLIC_KEY` environment variable at compile time, and
+//! the embedded key always wins. Only a build without one, like a mirror
+//! signing its own builds, takes the key from `[update] public_key` in the
+//! global configuration.
+
+use crate::atomic;
+use crate::config::{UpdateChannel, UpdateConfig};
+use crate::error::ForgeKitError;
+use crate::version_manager::compare_versions;
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Release endpoint used unless `[update] endpoint` names another
+pub const DEFAULT_ENDPOINT: &str = "https://ledokoz.com/forgekit/releases";
+
+/// Version of the running CLI
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Key release binaries are signed with, embedded by release builds
+const RELEASE_PUBLIC_KEY: Option<&str> = option_env!("FORGEKIT_RELEASE_PUBLIC_KEY");
+
+/// Latest release of a channel, as served by the endpoint
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Release {
+    /// Version of the release
+    pub version: String,
+    /// Release notes or a link to them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// Binaries keyed by platform, see [`platform`]
+    pub assets: BTreeMap<String, ReleaseAsset>,
+}
+
+impl Release {
+    /// Whether the release is newer than the running CLI
+    pub fn is_newer(&self) -> bool {
+        compare_versions(&self.version, CURRENT_VERSION) == Ordering::Greater
+    }
+
+    /// Binary for the platform forgekit runs on
+    pub fn asset(&self) -> Result<&ReleaseAsset, ForgeKitError> {
+        self.assets.get(&platform()).ok_or_else(|| {
+            ForgeKitError::Update(format!(
+                "release {} has no binary for {}",
+                self.version,
+                platform()
+            ))
+        })
+    }
+}
+
+/// A release binary for one platform
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReleaseAsset {
+    /// Download URL
+    pub url: String,
+    /// SHA-256 of the binary
+    pub sha256: String,
+    /// Base64 Ed25519 signature of the [`signed_message`] for the binary
+    pub signature: String,
+}
+
+/// Checks for, verifies and installs new releases
+#[derive(Debug, Clone)]
+pub struct Updater {
+    client: reqwest::Client,
+    endpoint: String,
+    channel: UpdateChannel,
+    public_key: Option<Vec<u8>>,
+}
+
+impl Updater {
+    /// Updater following the configured channel and endpoint
+    pub fn new(config: &UpdateConfig) -> Result<Self, ForgeKitError> {
+        if RELEASE_PUBLIC_KEY.is_some() && config.public_key.is_some() {
+            tracing::warn!("ignoring [update] public_key, this build embeds the release key");
+        }
+        let public_key = RELEASE_PUBLIC_KEY
+            .or(config.public_key.as_deref())
+            .map(|key| {
+                general_purpose::STANDARD.decode(key.trim()).map_err(|e| {
+                    ForgeKitError::InvalidConfig(format!("invalid release public key: {}", e))
+                })
+            })
+            .transpose()?;
+        Ok(Self {
+            client: reqwest::Client::new(),
+            endpoint: config
+                .endpoint
+                .clone()
+                .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string()),
+            channel: config.channel,
+            public_key,
+        })
+    }
+
+    /// Channel the updater follows
+    pub fn channel(&self) -> UpdateChannel {
+        self.channel
+    }
+
+    /// Latest release of the channel
+    pub async fn latest(&self) -> Result<Release, ForgeKitError> {
+        let url = format!(
+            "{}/{}.json",
+            self.endpoint.trim_end_matches('/'),
+            self.channel
+        );
+        Ok(self
+            .client
+            .get(&url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+
+    /// Latest release of the channel, if it is newer than the running CLI
+    pub async fn check(&self) -> Result<Option<Release>, ForgeKitError> {
+        let release = self.latest().await?;
+        Ok(release.is_newer().then_some(release))
+    }
+
+    /// Download the binary of a release for this platform and verify it
+    pub async fn download(&self, release: &Release) -> Result<Vec<u8>, ForgeKitError> {
+        let public_key = self.public_key.as_deref().ok_or_else(|| {
+            ForgeKitError::Update(
+                "no release signing key; this build cannot verify releases, set [update] public_key"
+                    .to_string(),
+            )
+        })?;
+        let binary = self
+            .client
+            .get(&release.asset()?.url)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?
+            .to_vec();
+        verify(release, &binary, public_key)?;
+        Ok(binary)
+    }
+
+    /// Download, verify and install a release over the executable at `exe`
+    pub async fn install(&self, release: &Release, exe: &Path) -> Result<(), ForgeKitError> {
+        let binary = self.download(release).await?;
+        replace_executable(exe, &binary)
+    }
+}
+
+/// Platform key of release assets, like `x86_64-linux` or `aarch64-macos`
+pub fn platform() -> String {
+    format!("{}-{}", std::env::consts::ARCH, std::env::consts::OS)
+}
+
+/// Message a release binary's signature is made over
+///
+/// Binding the version and platform to the checksum keeps a validly signed
+/// binary from being replayed as another release.
+pub fn signed_message(version: &str, platform: &str, sha256: &str) -> String {
+    format!(
+        "forgekit-release\nversion={}\nplatform={}\nsha256={}\n",
+        version,
+        platform,
+        sha256.to_ascii_lowercase()
+    )
+}
+
+/// Check a downloaded binary for this platform against the release's
+/// checksum and signature
+pub fn verify(release: &Release, binary: &[u8], public_key: &[u8]) -> Result<(), ForgeKitError> {
+    let asset = release.asset()?;
+    let checksum = format!("{:x}", Sha256::digest(binary));
+    if !checksum.eq_ignore_ascii_case(&asset.sha256) {
+        return Err(ForgeKitError::ChecksumMismatch(format!(
+            "{}: expected {}, got {}",
+            asset.url, asset.sha256, checksum
+        )));
+    }
+
+    let signature = general_purpose::STANDARD
+        .decode(asset.signature.trim())
+        .map_err(|e| ForgeKitError::Update(format!("invalid signature encoding: {}", e)))?;
+    ring::signature::UnparsedPublicKey::new(&ring::signature::ED25519, public_key)
+        .verify(
+            signed_message(&release.version, &platform(), &asset.sha256).as_bytes(),
+            &signature,
+        )
+        .map_err(|_| {
+            ForgeKitError::Update(format!(
+                "signature of {} does not match the release key for forgekit {} on {}",
+                asset.url,
+                release.version,
+                platform()
+            ))
+        })
+}
+
+/// Replace the executable at `exe` with `binary`
+///
+/// The new binary is written next to the old one and renamed over it, so
+/// the executable is never half-written. Windows does not allow replacing a
+/// running executable, so there the old one is first moved aside to
+/// `<exe>.old`.
+pub fn replace_executable(exe: &Path, binary: &[u8]) -> Result<(), ForgeKitError> {
+    #[cfg(windows)]
+    {
+        let old = exe.with_extension("old");
+        let _ = std::fs::remove_file(&old);
+        std::fs::rename(exe, &old)?;
+        if let Err(e) = atomic::write(exe, binary) {
+            let _ = std::fs::rename(&old, exe);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    #[cfg(not(windows))]
+    {
+        // atomic::write keeps the permissions, and so the executable bit, of the file it replaces
+        atomic::write(exe, binary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+    use tempfile::TempDir;
+
+    fn signed_release(version: &str, binary: &[u8]) -> (Release, Vec<u8>) {
+        signed_release_for(version, &platform(), binary)
+    }
+
+    /// Release serving `binary` for this platform, signed as a build for `signed_platform`
+    fn signed_release_for(
+        version: &str,
+        signed_platform: &str,
+        binary: &[u8],
+    ) -> (Release, Vec<u8>) {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&ring::rand::SystemRandom::new()).unwrap();
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let sha256 = format!("{:x}", Sha256::digest(binary));
+        let message = signed_message(version, signed_platform, &sha256);
+        let asset = ReleaseAsset {
+            url: "https://example.com/forgekit".to_string(),
+            sha256,
+            signature: general_purpose::STANDARD.encode(key_pair.sign(message.as_bytes())),
+        };
+        let release = Release {
+            version: version.to_string(),
+            notes: None,
+            assets: BTreeMap::from([(platform(), asset)]),
+        };
+        (release, key_pair.public_key().as_ref().to_vec())
+    }
+
+    #[test]
+    fn test_verify_rejects_tampered_binaries() {
+        let (release, public_key) = signed_release("2.0.0", b"new forgekit");
+        assert!(verify(&release, b"new forgekit", &public_key).is_ok());
+
+        let tampered = verify(&release, b"evil forgekit", &public_key).unwrap_err();
+        assert_eq!(tampered.code(), "checksum_mismatch");
+
+        let (other, other_key) = signed_release("2.0.0", b"new forgekit");
+        let mut forged = release.clone();
+        forged.assets = other.assets;
+        assert!(verify(&forged, b"new forgekit", &other_key).is_ok());
+        assert_eq!(
+            verify(&forged, b"new forgekit", &public_key)
+                .unwrap_err()
+                .code(),
+            "update"
+        );
+    }
+
+    #[test]
+    fn test_verify_rejects_relabelled_releases() {
+        // An old binary with its genuine signature, served as a newer version
+        let (old, public_key) = signed_release("1.0.0", b"old forgekit");
+        let relabelled = Release {
+            version: "2.0.0".to_string(),
+            ..old.clone()
+        };
+        assert_eq!(
+            verify(&relabelled, b"old forgekit", &public_key)
+                .unwrap_err()
+                .code(),
+            "update"
+        );
+
+        // A binary signed for another platform
+        let (other_platform, public_key) =
+            signed_release_for("2.0.0", "riscv64-plan9", b"new forgekit");
+        assert_eq!(
+            verify(&other_platform, b"new forgekit", &public_key)
+                .unwrap_err()
+                .code(),
+            "update"
+        );
+    }
+
+    #[test]
+    fn test_replace_executable_keeps_permissions() {
+        let temp_dir = TempDir::new().unwrap();
+        let exe = temp_dir.path().join("forgekit");
+        std::fs::write(&exe, b"old").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&exe, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        replace_executable(&exe, b"new").unwrap();
+        assert_eq!(std::fs::read(&exe).unwrap(), b"new");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&exe).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o755);
+        }
+
+        let release = Release {
+            version: "999.0.0".to_string(),
+            notes: None,
+            assets: BTreeMap::new(),
+        };
+        assert!(release.is_newer());
+        assert_eq!(release.asset().unwrap_err().code(), "update");
+    }
+}
@@ -0,0 +1,214 @@
+//! Semver compatibility check module
+//!
+//! This module backs `forgekit publish --check-semver`. Before a library is
+//! published, its public API is compared with that of the version published
+//! before it, and the release is blocked when it breaks that API without a
+//! major bump, or a minor one before 1.0.0 as Cargo treats those. Both APIs
+//! are read from their .moxlib: from the rustdoc JSON when both archives
+//! include it, otherwise from the packaged sources.
+
+use crate::api_surface::{self, ApiDiff};
+use crate::error::ForgeKitError;
+use crate::moxlib;
+use crate::registry::RegistryClient;
+use crate::version_manager::{compare_versions, BumpType, VersionManager};
+use serde::Serialize;
+use std::path::Path;
+
+/// Where the compared APIs were read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiSource {
+    /// rustdoc JSON packaged as `api.json`
+    Rustdoc,
+    /// The packaged sources, parsed
+    Sources,
+}
+
+/// Outcome of comparing a release with the previously published version
+#[derive(Debug, Clone, Serialize)]
+pub struct SemverCheck {
+    /// Package name
+    pub name: String,
+    /// Version being published
+    pub version: String,
+    /// Version compared with, `None` for a first release
+    pub previous: Option<String>,
+    /// Where the APIs were read from
+    pub source: ApiSource,
+    /// Changes since the previous version
+    pub diff: ApiDiff,
+    /// Smallest bump the changes allow
+    pub required: BumpType,
+    /// Bump from the previous version to this one
+    pub bump: Option<BumpType>,
+}
+
+impl SemverCheck {
+    /// Whether the release may be published
+    ///
+    /// Additions never block a release, only breaking changes do.
+    pub fn passed(&self) -> bool {
+        match self.bump {
+            Some(bump) => !self.diff.is_breaking() || rank(bump) >= rank(self.required),
+            None => true,
+        }
+    }
+}
+
+/// Compare the library packaged in `archive` with its last published version
+///
+/// The previous version is the highest one below the release, from the
+/// registry server when `remote` is set and from the local index otherwise.
+pub async fn check(
+    registry: &RegistryClient,
+    archive: &Path,
+    remote: bool,
+) -> Result<SemverCheck, ForgeKitError> {
+    let manifest = moxlib::read_manifest(archive)?;
+    let versions = if remote {
+        registry
+            .fetch_entry(&manifest.name)
+            .await?
+            .map(|entry| entry.versions.into_keys().collect())
+    } else {
+        registry.indexed_versions(&manifest.name)?
+    };
+    let previous = versions
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|version| compare_versions(version, &manifest.version).is_lt())
+        .max_by(|a, b| compare_versions(a, b));
+
+    let Some(previous) = previous else {
+        return Ok(SemverCheck {
+            name: manifest.name,
+            version: manifest.version,
+            previous: None,
+            source: ApiSource::Sources,
+            diff: ApiDiff::default(),
+            required: BumpType::Patch,
+            bump: None,
+        });
+    };
+    let previous_archive = registry.download_package(&manifest.name, &previous).await?;
+    let (source, diff) = compare_archives(&previous_archive, archive)?;
+
+    Ok(SemverCheck {
+        required: VersionManager::suggest_bump(&previous, &diff),
+        bump: Some(bump_between(&previous, &manifest.version)),
+        name: manifest.name,
+        version: manifest.version,
+        previous: Some(previous),
+        source,
+        diff,
+    })
+}
+
+/// API changes from the library in the `previous` .moxlib to the `current` one
+pub fn compare_archives(
+    previous: &Path,
+    current: &Path,
+) -> Result<(ApiSource, ApiDiff), ForgeKitError> {
+    if let (Some(before), Some(after)) = (moxlib::read_api(previous)?, moxlib::read_api(current)?) {
+        let before = api_surface::from_rustdoc(&before)?;
+        let after = api_surface::from_rustdoc(&after)?;
+        return Ok((ApiSource::Rustdoc, after.diff(&before)));
+    }
+    let before = api_surface::scan_archive(previous)?;
+    let after = api_surface::scan_archive(current)?;
+    Ok((ApiSource::Sources, after.diff(&before)))
+}
+
+/// Component that changes from version `from` to version `to`
+fn bump_between(from: &str, to: &str) -> BumpType {
+    let parts = |version: &str| -> Vec<String> {
+        version
+            .trim_start_matches('v')
+            .split(['.', '-', '+'])
+            .map(str::to_string)
+            .collect()
+    };
+    let (from, to) = (parts(from), parts(to));
+    let component = |parts: &[String], i: usize| parts.get(i).cloned().unwrap_or_default();
+    if component(&from, 0) != component(&to, 0) {
+        BumpType::Major
+    } else if component(&from, 1) != component(&to, 1) {
+        BumpType::Minor
+    } else {
+        BumpType::Patch
+    }
+}
+
+/// Order of bumps by how much they allow to change
+fn rank(bump: BumpType) -> u8 {
+    match bump {
+        BumpType::Patch => 0,
+        BumpType::Minor => 1,
+        BumpType::Major => 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ProjectConfig, ProjectKind};
+    use crate::registry::RegistryConfig;
+    use tempfile::TempDir;
+
+    async fn package(project: &Path, version: &str, lib_rs: &str) -> std::path::PathBuf {
+        let release = project.join("target").join("ledokoz").join("release");
+        std::fs::create_dir_all(&release).unwrap();
+        std::fs::write(release.join("libshared.rlib"), b"rlib").unwrap();
+        std::fs::create_dir_all(project.join("src")).unwrap();
+        std::fs::write(project.join("src").join("lib.rs"), lib_rs).unwrap();
+        let config = ProjectConfig {
+            name: "shared".to_string(),
+            version: version.to_string(),
+            kind: ProjectKind::Library,
+            ..Default::default()
+        };
+        moxlib::package(project, &config, &Default::default())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_breaking_change_needs_major_bump() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path().join("shared");
+        let registry = RegistryClient::new(RegistryConfig {
+            cache_dir: temp_dir.path().join("cache"),
+            index_dir: temp_dir.path().join("index"),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let first = package(&project, "1.0.0", "pub fn open() {}\npub fn close() {}\n").await;
+        let check_first = check(&registry, &first, false).await.unwrap();
+        assert!(check_first.previous.is_none());
+        assert!(check_first.passed());
+        registry.publish_library(&first).unwrap();
+
+        let minor = package(&project, "1.1.0", "pub fn open(path: &str) {}\n").await;
+        let result = check(&registry, &minor, false).await.unwrap();
+        assert_eq!(result.previous.as_deref(), Some("1.0.0"));
+        assert_eq!(result.source, ApiSource::Sources);
+        assert_eq!(result.required, BumpType::Major);
+        assert_eq!(result.bump, Some(BumpType::Minor));
+        assert!(!result.passed());
+        let breaking: Vec<_> = result.diff.breaking().map(|c| c.path()).collect();
+        assert_eq!(breaking, vec!["crate::close", "crate::open"]);
+
+        let major = package(&project, "2.0.0", "pub fn open(path: &str) {}\n").await;
+        assert!(check(&registry, &major, false).await.unwrap().passed());
+
+        let additive = package(
+            &project,
+            "1.0.1",
+            "pub fn open() {}\npub fn close() {}\npub fn flush() {}\n",
+        )
+        .await;
+        assert!(check(&registry, &additive, false).await.unwrap().passed());
+    }
+}
@@ -0,0 +1,377 @@
+//! Static file serving module
+//!
+//! This module serves directories over HTTP for the development server, so
+//! GUI apps can load their assets and the project docs from localhost. Files
+//! are sent with their MIME type and support byte ranges (`Range:
+//! bytes=...`), directories without an `index.html` get a listing, and every
+//! response forbids caching so a reload always sees the file on disk.
+
+use hyper::{header, Body, Method, Request, Response, StatusCode};
+use std::io::SeekFrom;
+use std::path::{Component, Path, PathBuf};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+/// A directory served under a URL prefix
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mount {
+    /// URL prefix, e.g. `/assets`
+    pub prefix: String,
+    /// Directory served under the prefix
+    pub dir: PathBuf,
+}
+
+impl Mount {
+    /// Serve `dir` under `prefix`
+    pub fn new(prefix: &str, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            prefix: format!("/{}", prefix.trim_matches('/')),
+            dir: dir.into(),
+        }
+    }
+}
+
+/// Answer a request for a file or directory of the mounts
+pub async fn serve(mounts: &[Mount], request: &Request<Body>) -> Response<Body> {
+    let head = request.method() == Method::HEAD;
+    if request.method() != Method::GET && !head {
+        let mut response = text(StatusCode::METHOD_NOT_ALLOWED, "Method not allowed");
+        response
+            .headers_mut()
+            .insert(header::ALLOW, header::HeaderValue::from_static("GET, HEAD"));
+        return response;
+    }
+    let Some(path) = percent_decode(request.uri().path()) else {
+        return text(StatusCode::BAD_REQUEST, "Invalid path");
+    };
+    if path == "/" {
+        let entries: Vec<(String, bool)> = mounts
+            .iter()
+            .filter(|mount| mount.dir.is_dir())
+            .map(|mount| (mount.prefix[1..].to_string(), true))
+            .collect();
+        return finish(listing("/", &entries), head);
+    }
+
+    let Some((mount, relative)) = mounts.iter().find_map(|mount| {
+        let rest = path.strip_prefix(&mount.prefix)?;
+        (rest.is_empty() || rest.starts_with('/')).then_some((mount, rest))
+    }) else {
+        return text(StatusCode::NOT_FOUND, "Not found");
+    };
+    // Only plain names below the mount; `..` could escape it
+    let relative = Path::new(relative.trim_start_matches('/'));
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return text(StatusCode::BAD_REQUEST, "Invalid path");
+    }
+
+    let mut target = mount.dir.join(relative);
+    if target.is_dir() {
+        if !path.ends_with('/') {
+            return Response::builder()
+                .status(StatusCode::MOVED_PERMANENTLY)
+                .header(header::LOCATION, format!("{}/", path))
+                .header(header::CACHE_CONTROL, "no-store")
+                .body(Body::empty())
+                .expect("valid response");
+        }
+        let index = target.join("index.html");
+        if !index.is_file() {
+            return finish(listing(&path, &directory_entries(&target)), head);
+        }
+        target = index;
+    }
+
+    let range = request
+        .headers()
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok());
+    match file(&target, range).await {
+        Ok(response) => finish(response, head),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            text(StatusCode::NOT_FOUND, "Not found")
+        }
+        Err(e) => text(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+}
+
+/// MIME type of a file, from its extension
+pub fn mime_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" | "map" => "application/json",
+        "xml" | "mxui" => "application/xml",
+        "txt" | "log" => "text/plain; charset=utf-8",
+        "md" => "text/markdown; charset=utf-8",
+        "toml" => "application/toml",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "bmp" => "image/bmp",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "ogg" => "audio/ogg",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Byte range requested by a `Range` header, as inclusive offsets
+///
+/// `None` means the whole file is sent: no header, or one this server does
+/// not handle (several ranges, other units). `Some(Err)` means the range
+/// cannot be satisfied.
+pub fn parse_range(header: &str, len: u64) -> Option<Result<(u64, u64), ()>> {
+    let spec = header.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+    let range = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            (suffix > 0 && len > 0).then(|| (len.saturating_sub(suffix), len - 1))
+        }
+        (start, "") => {
+            let start: u64 = start.parse().ok()?;
+            (start < len).then(|| (start, len - 1))
+        }
+        (start, end) => {
+            let (start, end): (u64, u64) = (start.parse().ok()?, end.parse().ok()?);
+            (start <= end && start < len).then(|| (start, end.min(len - 1)))
+        }
+    };
+    Some(range.ok_or(()))
+}
+
+/// Response with a file, or the requested range of it
+async fn file(path: &Path, range: Option<&str>) -> std::io::Result<Response<Body>> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let len = file.metadata().await?.len();
+    let builder = Response::builder()
+        .header(header::CONTENT_TYPE, mime_type(path))
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::CACHE_CONTROL, "no-store");
+
+    let response = match range.and_then(|range| parse_range(range, len)) {
+        None => {
+            let mut contents = Vec::with_capacity(len as usize);
+            file.read_to_end(&mut contents).await?;
+            builder.body(Body::from(contents))
+        }
+        Some(Ok((start, end))) => {
+            let mut contents = vec![0; (end - start + 1) as usize];
+            file.seek(SeekFrom::Start(start)).await?;
+            file.read_exact(&mut contents).await?;
+            builder
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(
+                    header::CONTENT_RANGE,
+                    format!("bytes {}-{}/{}", start, end, len),
+                )
+                .body(Body::from(contents))
+        }
+        Some(Err(())) => builder
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header(header::CONTENT_RANGE, format!("bytes */{}", len))
+            .body(Body::empty()),
+    };
+    Ok(response.expect("valid response"))
+}
+
+/// Names of a directory's entries and whether each is a directory, sorted
+fn directory_entries(dir: &Path) -> Vec<(String, bool)> {
+    let mut entries: Vec<(String, bool)> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            let is_dir = entry.file_type().is_ok_and(|kind| kind.is_dir());
+            (entry.file_name().to_string_lossy().to_string(), is_dir)
+        })
+        .collect();
+    entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    entries
+}
+
+/// HTML listing of a directory
+fn listing(path: &str, entries: &[(String, bool)]) -> Response<Body> {
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Index of {0}</title></head>\n<body>\n<h1>Index of {0}</h1>\n<ul>\n",
+        escape(path)
+    );
+    if path != "/" {
+        html.push_str("<li><a href=\"../\">../</a></li>\n");
+    }
+    for (name, is_dir) in entries {
+        let name = format!("{}{}", escape(name), if *is_dir { "/" } else { "" });
+        html.push_str(&format!("<li><a href=\"{0}\">{0}</a></li>\n", name));
+    }
+    html.push_str("</ul>\n</body>\n</html>\n");
+    Response::builder()
+        .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
+        .header(header::CACHE_CONTROL, "no-store")
+        .body(Body::from(html))
+        .expect("valid response")
+}
+
+fn text(status: StatusCode, message: &str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .header(header::CACHE_CONTROL, "no-store")
+        .body(Body::from(message.to_string()))
+        .expect("valid response")
+}
+
+/// Drop the body of a response to a HEAD request, keeping its length
+fn finish(response: Response<Body>, head: bool) -> Response<Body> {
+    if !head {
+        return response;
+    }
+    let (mut parts, body) = response.into_parts();
+    if let Some(len) = hyper::body::HttpBody::size_hint(&body).exact() {
+        parts.headers.insert(header::CONTENT_LENGTH, len.into());
+    }
+    Response::from_parts(parts, Body::empty())
+}
+
+/// Decode `%XX` escapes of a URL path
+///
+/// `None` when a `%` is not followed by two hex digits or the result is
+/// not valid UTF-8.
+fn percent_decode(path: &str) -> Option<String> {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = bytes.get(i + 1..i + 3)?;
+            if !hex.iter().all(u8::is_ascii_hexdigit) {
+                return None;
+            }
+            let hex = std::str::from_utf8(hex).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    async fn get(
+        mounts: &[Mount],
+        path: &str,
+        range: Option<&str>,
+    ) -> (StatusCode, String, Vec<u8>) {
+        let mut request = Request::get(path);
+        if let Some(range) = range {
+            request = request.header(header::RANGE, range);
+        }
+        let response = serve(mounts, &request.body(Body::empty()).unwrap()).await;
+        let status = response.status();
+        let content_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .map(|v| v.to_str().unwrap().to_string())
+            .unwrap_or_default();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, content_type, body.to_vec())
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("/a%20b").as_deref(), Some("/a b"));
+        // An escape ending the path
+        assert_eq!(percent_decode("/a%2E").as_deref(), Some("/a."));
+        assert_eq!(percent_decode("/a%2"), None);
+        assert_eq!(percent_decode("/a%"), None);
+        assert_eq!(percent_decode("/a%+1"), None);
+        assert_eq!(percent_decode("/%FF"), None);
+    }
+
+    #[tokio::test]
+    async fn test_serves_files_ranges_and_listings() {
+        let temp_dir = TempDir::new().unwrap();
+        let assets = temp_dir.path().join("assets");
+        std::fs::create_dir_all(assets.join("icons")).unwrap();
+        std::fs::write(assets.join("icons").join("app logo.svg"), "<svg/>").unwrap();
+        std::fs::write(assets.join("data.bin"), b"0123456789").unwrap();
+        let mounts = [Mount::new("assets", &assets)];
+
+        let (status, content_type, body) = get(&mounts, "/assets/icons/app%20logo.svg", None).await;
+        assert_eq!(
+            (status, content_type.as_str()),
+            (StatusCode::OK, "image/svg+xml")
+        );
+        assert_eq!(body, b"<svg/>");
+
+        let (status, _, body) = get(&mounts, "/assets/data.bin", Some("bytes=2-4")).await;
+        assert_eq!(
+            (status, body.as_slice()),
+            (StatusCode::PARTIAL_CONTENT, &b"234"[..])
+        );
+        let (_, _, body) = get(&mounts, "/assets/data.bin", Some("bytes=-3")).await;
+        assert_eq!(body, b"789");
+        let (status, _, _) = get(&mounts, "/assets/data.bin", Some("bytes=10-")).await;
+        assert_eq!(status, StatusCode::RANGE_NOT_SATISFIABLE);
+
+        let (status, _, _) = get(&mounts, "/assets/icons", None).await;
+        assert_eq!(status, StatusCode::MOVED_PERMANENTLY);
+        let (status, content_type, body) = get(&mounts, "/assets/", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(content_type.starts_with("text/html"));
+        let listing = String::from_utf8(body).unwrap();
+        assert!(listing.find("icons/").unwrap() < listing.find("data.bin").unwrap());
+
+        let (status, _, _) = get(&mounts, "/assets/%2E%2E/secret", None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _, _) = get(&mounts, "/assets/missing.png", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let request = Request::head("/assets/data.bin")
+            .body(Body::empty())
+            .unwrap();
+        let response = serve(&mounts, &request).await;
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "no-store");
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], "10");
+        assert!(hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
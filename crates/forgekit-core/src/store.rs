@@ -0,0 +1,369 @@
+//! Content-addressed package store module
+//!
+//! This module provides a global package store shared by all projects.
+//! Packages are extracted once under a directory keyed by the hash of their
+//! archive and hardlinked into each project's `vendor/` directory.
+
+use crate::error::ForgeKitError;
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// A package stored in the content-addressed store
+#[derive(Debug, Clone)]
+pub struct StoreEntry {
+    /// SHA-256 hash of the package archive
+    pub hash: String,
+    /// Directory holding the extracted package
+    pub path: PathBuf,
+}
+
+/// Garbage collection report
+#[derive(Debug, Clone, Default)]
+pub struct GcReport {
+    /// Number of packages removed from the store
+    pub removed: usize,
+    /// Number of packages still referenced by a project
+    pub kept: usize,
+    /// Bytes freed on disk
+    pub freed_bytes: u64,
+}
+
+/// Global content-addressed package store
+#[derive(Debug, Clone)]
+pub struct PackageStore {
+    root: PathBuf,
+}
+
+impl PackageStore {
+    /// Create a store rooted at the given directory
+    pub fn new(root: PathBuf) -> Result<Self, ForgeKitError> {
+        std::fs::create_dir_all(root.join("packages"))?;
+        std::fs::create_dir_all(root.join("refs"))?;
+        Ok(Self { root })
+    }
+
+    /// Default store location inside the global cache
+    pub fn default_location() -> PathBuf {
+        crate::package_manager::get_global_cache_dir().join("store")
+    }
+
+    /// Get the store root directory
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Add a package archive to the store, extracting it if not already present
+    pub fn add(&self, archive: &Path) -> Result<StoreEntry, ForgeKitError> {
+        let hash = hash_file(archive)?;
+        let path = self.root.join("packages").join(&hash);
+
+        if !path.exists() {
+            // Extract into a staging directory so a crash never leaves a partial entry
+            let staging = self.root.join("packages").join(format!("{}.tmp", hash));
+            if staging.exists() {
+                std::fs::remove_dir_all(&staging)?;
+            }
+            extract_package(archive, &staging)?;
+            std::fs::rename(&staging, &path)?;
+        }
+
+        Ok(StoreEntry { hash, path })
+    }
+
+    /// Link a stored package into a project directory
+    ///
+    /// Files are hardlinked where possible and copied when the destination is
+    /// on a different filesystem.
+    pub fn link_into(&self, entry: &StoreEntry, dest: &Path) -> Result<(), ForgeKitError> {
+        if dest.exists() {
+            std::fs::remove_dir_all(dest)?;
+        }
+
+        for item in walkdir::WalkDir::new(&entry.path)
+            .into_iter()
+            .filter_map(|e| e.ok())
+        {
+            let relative = item.path().strip_prefix(&entry.path).map_err(|_| {
+                ForgeKitError::PackagingFailed("Failed to strip prefix".to_string())
+            })?;
+            let target = dest.join(relative);
+
+            if item.file_type().is_dir() {
+                std::fs::create_dir_all(&target)?;
+            } else if std::fs::hard_link(item.path(), &target).is_err() {
+                std::fs::copy(item.path(), &target)?;
+            }
+        }
+
+        self.add_reference(&entry.hash, dest)
+    }
+
+    /// Remove packages no longer referenced by any project
+    pub fn gc(&self) -> Result<GcReport, ForgeKitError> {
+        let mut report = GcReport::default();
+
+        for entry in std::fs::read_dir(self.root.join("packages"))?.flatten() {
+            let hash = entry.file_name().to_string_lossy().to_string();
+            let refs = self.live_references(&hash)?;
+
+            if refs.is_empty() {
+                report.freed_bytes += self.remove(&hash)?;
+                report.removed += 1;
+            } else {
+                std::fs::write(self.refs_file(&hash), join_refs(&refs))?;
+                report.kept += 1;
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Hashes of the stored packages
+    pub fn hashes(&self) -> Result<Vec<String>, ForgeKitError> {
+        let mut hashes: Vec<String> = std::fs::read_dir(self.root.join("packages"))?
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .filter(|name| !name.ends_with(".tmp"))
+            .collect();
+        hashes.sort();
+        Ok(hashes)
+    }
+
+    /// Directory of a stored package
+    pub fn package_path(&self, hash: &str) -> PathBuf {
+        self.root.join("packages").join(hash)
+    }
+
+    /// Bytes a stored package takes on disk
+    pub fn package_size(&self, hash: &str) -> u64 {
+        dir_size(&self.package_path(hash))
+    }
+
+    /// Remove a stored package and its references, returning the bytes freed
+    pub fn remove(&self, hash: &str) -> Result<u64, ForgeKitError> {
+        let path = self.package_path(hash);
+        let freed = dir_size(&path);
+        if path.exists() {
+            std::fs::remove_dir_all(path)?;
+        }
+        let refs_file = self.refs_file(hash);
+        if refs_file.exists() {
+            std::fs::remove_file(refs_file)?;
+        }
+        Ok(freed)
+    }
+
+    /// Projects that still link to a stored package
+    ///
+    /// A project is the nearest directory above a link holding a
+    /// `forgekit.toml`.
+    pub fn projects(&self) -> Result<Vec<PathBuf>, ForgeKitError> {
+        let mut projects = Vec::new();
+        for hash in self.hashes()? {
+            for link in self.live_references(&hash)? {
+                let project = link
+                    .ancestors()
+                    .skip(1)
+                    .find(|dir| dir.join("forgekit.toml").is_file());
+                if let Some(project) = project {
+                    if !projects.iter().any(|known: &PathBuf| known == project) {
+                        projects.push(project.to_path_buf());
+                    }
+                }
+            }
+        }
+        projects.sort();
+        Ok(projects)
+    }
+
+    /// Record that a project directory links to a stored package
+    fn add_reference(&self, hash: &str, dest: &Path) -> Result<(), ForgeKitError> {
+        let dest = dest.canonicalize()?;
+        let mut refs = self.read_references(hash)?;
+        if !refs.contains(&dest) {
+            refs.push(dest);
+        }
+        std::fs::write(self.refs_file(hash), join_refs(&refs))?;
+        Ok(())
+    }
+
+    /// References whose project directory still exists
+    fn live_references(&self, hash: &str) -> Result<Vec<PathBuf>, ForgeKitError> {
+        Ok(self
+            .read_references(hash)?
+            .into_iter()
+            .filter(|path| path.exists())
+            .collect())
+    }
+
+    fn read_references(&self, hash: &str) -> Result<Vec<PathBuf>, ForgeKitError> {
+        let refs_file = self.refs_file(hash);
+        if !refs_file.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = std::fs::read_to_string(refs_file)?;
+        Ok(content
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(PathBuf::from)
+            .collect())
+    }
+
+    fn refs_file(&self, hash: &str) -> PathBuf {
+        self.root.join("refs").join(hash)
+    }
+}
+
+/// Compute the SHA-256 hash of a file
+pub fn hash_file(path: &Path) -> Result<String, ForgeKitError> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Extract a package archive into a directory
+pub(crate) fn extract_package(archive: &Path, dest: &Path) -> Result<(), ForgeKitError> {
+    // .moxlib packages are ZIP archives carrying their own sources
+    let mut magic = [0u8; 4];
+    let is_zip =
+        std::fs::File::open(archive)?.read_exact(&mut magic).is_ok() && magic == *b"PK\x03\x04";
+    if is_zip {
+        std::fs::create_dir_all(dest)?;
+        zip::ZipArchive::new(std::fs::File::open(archive)?)?.extract(dest)?;
+        return Ok(());
+    }
+
+    // Extract the tar.gz file (simplified - in reality would use tar crate)
+    // For demo purposes, we'll just copy the file
+    std::fs::create_dir_all(dest.join("src"))?;
+    std::fs::copy(archive, dest.join("package.tar.gz"))?;
+
+    let lib_rs = r#"//! Auto-generated library file
+pub fn hello() {
+    println!("Hello from {}!", env!("CARGO_PKG_NAME"));
+}
+"#;
+    std::fs::write(dest.join("src").join("lib.rs"), lib_rs)?;
+    Ok(())
+}
+
+fn join_refs(refs: &[PathBuf]) -> String {
+    refs.iter()
+        .map(|path| format!("{}\n", path.display()))
+        .collect()
+}
+
+fn dir_size(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_archive(dir: &Path, name: &str, content: &[u8]) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_identical_archives_share_entry() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = PackageStore::new(temp_dir.path().join("store")).unwrap();
+
+        let a = write_archive(temp_dir.path(), "a.tar.gz", b"same bytes");
+        let b = write_archive(temp_dir.path(), "b.tar.gz", b"same bytes");
+
+        let first = store.add(&a).unwrap();
+        let second = store.add(&b).unwrap();
+        assert_eq!(first.hash, second.hash);
+        assert!(first.path.join("src").join("lib.rs").exists());
+    }
+
+    #[test]
+    fn test_link_into_project() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = PackageStore::new(temp_dir.path().join("store")).unwrap();
+        let archive = write_archive(temp_dir.path(), "pkg.tar.gz", b"package");
+
+        let entry = store.add(&archive).unwrap();
+        let dest = temp_dir
+            .path()
+            .join("project")
+            .join("vendor")
+            .join("pkg-0.1.0");
+        store.link_into(&entry, &dest).unwrap();
+
+        assert!(dest.join("package.tar.gz").exists());
+        assert!(dest.join("src").join("lib.rs").exists());
+    }
+
+    #[test]
+    fn test_zip_archives_are_extracted() {
+        use std::io::Write;
+
+        let temp_dir = TempDir::new().unwrap();
+        let store = PackageStore::new(temp_dir.path().join("store")).unwrap();
+
+        let archive = temp_dir.path().join("lib.moxlib");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&archive).unwrap());
+        zip.start_file("src/lib.rs", zip::write::FileOptions::default())
+            .unwrap();
+        zip.write_all(b"pub fn real() {}\n").unwrap();
+        zip.finish().unwrap();
+
+        let entry = store.add(&archive).unwrap();
+        let lib = std::fs::read_to_string(entry.path.join("src").join("lib.rs")).unwrap();
+        assert_eq!(lib, "pub fn real() {}\n");
+        assert!(!entry.path.join("package.tar.gz").exists());
+    }
+
+    #[test]
+    fn test_gc_removes_unreferenced_packages() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = PackageStore::new(temp_dir.path().join("store")).unwrap();
+
+        let kept = store
+            .add(&write_archive(temp_dir.path(), "kept.tar.gz", b"kept"))
+            .unwrap();
+        let dropped = store
+            .add(&write_archive(
+                temp_dir.path(),
+                "dropped.tar.gz",
+                b"dropped",
+            ))
+            .unwrap();
+
+        let kept_dest = temp_dir.path().join("a").join("vendor").join("kept");
+        let dropped_dest = temp_dir.path().join("b").join("vendor").join("dropped");
+        store.link_into(&kept, &kept_dest).unwrap();
+        store.link_into(&dropped, &dropped_dest).unwrap();
+        std::fs::remove_dir_all(&dropped_dest).unwrap();
+
+        let report = store.gc().unwrap();
+        assert_eq!(report.removed, 1);
+        assert_eq!(report.kept, 1);
+        assert!(kept.path.exists());
+        assert!(!dropped.path.exists());
+    }
+}
@@ -0,0 +1,272 @@
+//! Debug symbols module
+//!
+//! This module splits debug info from release binaries so the .mox ships a
+//! small stripped binary while crash reports can still be symbolicated. After
+//! a build the debug info of each release is stored under
+//! `.forgekit/symbols/<version>/`: a `.debug` file produced with `objcopy` on
+//! Linux, the `.dSYM` bundle on macOS or the `.pdb` on Windows. Symbols can be
+//! uploaded to a symbol server configured in `[build.symbols]`, from which
+//! crash symbolication fetches them when they are not available locally.
+
+use crate::config::SymbolsConfig;
+use crate::error::ForgeKitError;
+use crate::platform;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+use walkdir::WalkDir;
+
+/// Extensions of debug info artifacts, never bundled into a .mox
+pub const DEBUG_EXTENSIONS: &[&str] = &["debug", "dwp", "dwo", "pdb", "dSYM"];
+
+/// Directory holding the debug info of a release
+pub fn symbols_dir(project_path: &Path, version: &str) -> PathBuf {
+    project_path.join(".forgekit").join("symbols").join(version)
+}
+
+/// Whether a path is (inside) a debug info artifact
+pub fn is_debug_artifact(path: &Path) -> bool {
+    path.components().any(|component| {
+        Path::new(component.as_os_str())
+            .extension()
+            .is_some_and(|ext| DEBUG_EXTENSIONS.iter().any(|d| ext == *d))
+    })
+}
+
+/// Cargo profile overrides making release builds carry splittable debug info
+///
+/// Variables already set in the environment win.
+pub fn build_env(config: &SymbolsConfig) -> Vec<(&'static str, &'static str)> {
+    if !config.split {
+        return Vec::new();
+    }
+    let mut env = vec![("CARGO_PROFILE_RELEASE_DEBUG", "line-tables-only")];
+    if cfg!(target_os = "macos") {
+        env.push(("CARGO_PROFILE_RELEASE_SPLIT_DEBUGINFO", "packed"));
+    }
+    env.retain(|(name, _)| std::env::var_os(name).is_none());
+    env
+}
+
+/// Store the debug info of a freshly built release binary
+///
+/// With splitting enabled the debug info is moved out of the binary, which is
+/// stripped in place; otherwise a copy of the whole binary is kept. Returns
+/// the stored artifacts.
+pub async fn store(
+    project_path: &Path,
+    version: &str,
+    binary: &Path,
+    config: &SymbolsConfig,
+) -> Result<Vec<PathBuf>, ForgeKitError> {
+    let dir = symbols_dir(project_path, version);
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)?;
+    }
+    std::fs::create_dir_all(&dir)?;
+    let name = binary
+        .file_name()
+        .ok_or_else(|| {
+            ForgeKitError::BuildFailed(format!("Invalid binary path {}", binary.display()))
+        })?
+        .to_string_lossy()
+        .to_string();
+
+    if config.split {
+        // Toolchains that already split the debug info leave it next to the binary
+        let dsym = binary.with_file_name(format!("{}.dSYM", name));
+        if dsym.is_dir() {
+            let dest = dir.join(format!("{}.dSYM", name));
+            copy_tree(&dsym, &dest)?;
+            return Ok(vec![dest]);
+        }
+        let pdb = binary.with_extension("pdb");
+        if pdb.is_file() {
+            let dest = dir.join(pdb.file_name().unwrap_or_default());
+            std::fs::copy(&pdb, &dest)?;
+            return Ok(vec![dest]);
+        }
+
+        let debug = dir.join(format!("{}.debug", name));
+        match objcopy_split(binary, &debug).await {
+            Ok(()) => return Ok(vec![debug]),
+            Err(e) => tracing::warn!("Keeping the unstripped binary instead: {}", e),
+        }
+    }
+
+    let kept = dir.join(&name);
+    std::fs::copy(binary, &kept)?;
+    Ok(vec![kept])
+}
+
+/// Move debug info out of a binary and link the binary to it
+async fn objcopy_split(binary: &Path, debug: &Path) -> Result<(), ForgeKitError> {
+    let run = |args: Vec<std::ffi::OsString>| async move {
+        let output = Command::new("objcopy").args(&args).output().await;
+        match output {
+            Ok(output) if output.status.success() => Ok(()),
+            Ok(output) => Err(ForgeKitError::BuildFailed(format!(
+                "objcopy failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))),
+            Err(e) => Err(ForgeKitError::BuildFailed(format!(
+                "splitting debug info requires objcopy: {}",
+                e
+            ))),
+        }
+    };
+
+    run(vec![
+        "--only-keep-debug".into(),
+        binary.into(),
+        debug.into(),
+    ])
+    .await?;
+    let mut debuglink = std::ffi::OsString::from("--add-gnu-debuglink=");
+    debuglink.push(debug);
+    run(vec!["--strip-debug".into(), debuglink, binary.into()]).await
+}
+
+/// Local file holding the debug info of an app release, if any
+pub fn local_debug_file(project_path: &Path, app: &str, version: &str) -> Option<PathBuf> {
+    let dir = symbols_dir(project_path, version);
+    [
+        dir.join(format!("{}.debug", app)),
+        dir.join(format!("{}.dSYM", app))
+            .join("Contents")
+            .join("Resources")
+            .join("DWARF")
+            .join(app),
+        dir.join(app),
+    ]
+    .into_iter()
+    .find(|path| path.is_file())
+}
+
+/// Files stored for a release, relative to its symbols directory
+pub fn artifacts(project_path: &Path, version: &str) -> Vec<PathBuf> {
+    let dir = symbols_dir(project_path, version);
+    WalkDir::new(&dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.path().strip_prefix(&dir).ok().map(Path::to_path_buf))
+        .collect()
+}
+
+/// URL of a symbol file on a symbol server
+fn server_url(server: &str, app: &str, version: &str, file: &Path) -> String {
+    format!(
+        "{}/{}/{}/{}",
+        server.trim_end_matches('/'),
+        app,
+        version,
+        platform::archive_path(file)
+    )
+}
+
+/// Upload the debug info of a release to a symbol server
+///
+/// Returns the number of files uploaded.
+pub async fn upload(
+    project_path: &Path,
+    app: &str,
+    version: &str,
+    server: &str,
+) -> Result<usize, ForgeKitError> {
+    let files = artifacts(project_path, version);
+    if files.is_empty() {
+        return Err(ForgeKitError::InvalidConfig(format!(
+            "No debug symbols stored for {} {}; build the release first",
+            app, version
+        )));
+    }
+
+    let client = reqwest::Client::new();
+    let dir = symbols_dir(project_path, version);
+    for file in &files {
+        client
+            .put(server_url(server, app, version, file))
+            .body(tokio::fs::read(dir.join(file)).await?)
+            .send()
+            .await?
+            .error_for_status()?;
+    }
+    Ok(files.len())
+}
+
+/// Fetch the `.debug` file of a release from a symbol server
+pub async fn download(
+    project_path: &Path,
+    app: &str,
+    version: &str,
+    server: &str,
+) -> Result<PathBuf, ForgeKitError> {
+    let file = PathBuf::from(format!("{}.debug", app));
+    let data = reqwest::get(server_url(server, app, version, &file))
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+
+    let dir = symbols_dir(project_path, version);
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(file);
+    std::fs::write(&path, data)?;
+    Ok(path)
+}
+
+/// Recursively copy a directory
+fn copy_tree(from: &Path, to: &Path) -> Result<(), ForgeKitError> {
+    for entry in WalkDir::new(from).into_iter().filter_map(|e| e.ok()) {
+        let relative = entry.path().strip_prefix(from).unwrap_or(entry.path());
+        let dest = to.join(relative);
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&dest)?;
+        } else {
+            std::fs::copy(entry.path(), &dest)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_debug_artifacts() {
+        assert!(is_debug_artifact(Path::new("app.debug")));
+        assert!(is_debug_artifact(Path::new(
+            "app.dSYM/Contents/Resources/DWARF/app"
+        )));
+        assert!(!is_debug_artifact(Path::new("images/logo.png")));
+
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path();
+        let dir = symbols_dir(project, "1.0.0");
+        std::fs::create_dir_all(&dir).unwrap();
+        assert_eq!(local_debug_file(project, "app", "1.0.0"), None);
+
+        std::fs::write(dir.join("app"), "binary").unwrap();
+        std::fs::write(dir.join("app.debug"), "debug").unwrap();
+        assert_eq!(
+            local_debug_file(project, "app", "1.0.0"),
+            Some(dir.join("app.debug"))
+        );
+        assert_eq!(
+            artifacts(project, "1.0.0"),
+            [PathBuf::from("app"), PathBuf::from("app.debug")]
+        );
+        assert_eq!(
+            server_url(
+                "https://symbols.example/",
+                "app",
+                "1.0.0",
+                Path::new("app.debug")
+            ),
+            "https://symbols.example/app/1.0.0/app.debug"
+        );
+    }
+}
@@ -0,0 +1,247 @@
+//! Telemetry module
+//!
+//! This module collects anonymous usage statistics when the user opts in
+//! with `forgekit telemetry enable`. Each command run is stored as one event
+//! in a local JSON lines file: the command name, its duration, the exit code,
+//! an error code and the compiler cache hit rate. Paths, arguments and error
+//! messages are never recorded. Events stay on the machine until they are
+//! sent to the configured endpoint with `forgekit telemetry upload`.
+
+use crate::config::{GlobalConfig, TelemetryConfig};
+use crate::error::ForgeKitError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// One recorded command run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TelemetryEvent {
+    /// When the command finished (RFC 3339)
+    pub timestamp: String,
+    /// Command name, e.g. `build` or `cache stats`
+    pub command: String,
+    /// Command duration in milliseconds
+    pub duration_ms: u64,
+    /// Process exit code
+    pub exit_code: i32,
+    /// Kind of error the command failed with, see [`ForgeKitError::code`]
+    pub error_code: Option<String>,
+    /// Compiler cache hit rate (0.0 to 1.0), for builds
+    pub cache_hit_rate: Option<f64>,
+    /// ForgeKit version
+    pub version: String,
+    /// Operating system
+    pub os: String,
+}
+
+impl TelemetryEvent {
+    /// Create an event for a finished command
+    pub fn new(command: &str, duration: Duration, exit_code: i32) -> Self {
+        Self {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            command: command.to_string(),
+            duration_ms: duration.as_millis() as u64,
+            exit_code,
+            error_code: None,
+            cache_hit_rate: None,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            os: std::env::consts::OS.to_string(),
+        }
+    }
+}
+
+/// Aggregated statistics of one command
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandStats {
+    /// Command name
+    pub command: String,
+    /// Number of runs
+    pub runs: usize,
+    /// Runs with a non-zero exit code
+    pub failures: usize,
+    /// Mean duration in milliseconds
+    pub avg_duration_ms: u64,
+    /// Mean compiler cache hit rate, when any run reported one
+    pub avg_cache_hit_rate: Option<f64>,
+    /// Number of failures by error code
+    pub error_codes: BTreeMap<String, usize>,
+}
+
+/// Aggregated view of the locally stored events
+#[derive(Debug, Clone, Serialize)]
+pub struct TelemetrySummary {
+    /// Number of stored events
+    pub events: usize,
+    /// Statistics by command, most used first
+    pub commands: Vec<CommandStats>,
+}
+
+/// Local store of telemetry events
+pub struct TelemetryStore {
+    path: PathBuf,
+}
+
+impl TelemetryStore {
+    /// Open the store at the given file
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+
+    /// Default location of the event file
+    pub fn default_path() -> PathBuf {
+        GlobalConfig::config_dir().join("telemetry.jsonl")
+    }
+
+    /// File the events are stored in
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Store an event if telemetry is enabled
+    pub fn record(
+        &self,
+        config: &TelemetryConfig,
+        event: &TelemetryEvent,
+    ) -> Result<(), ForgeKitError> {
+        if !config.enabled {
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(event)?)?;
+        Ok(())
+    }
+
+    /// All stored events, skipping unreadable lines
+    pub fn events(&self) -> Result<Vec<TelemetryEvent>, ForgeKitError> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        Ok(std::fs::read_to_string(&self.path)?
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    /// Aggregate the stored events by command
+    pub fn summary(&self) -> Result<TelemetrySummary, ForgeKitError> {
+        let events = self.events()?;
+        let mut by_command: BTreeMap<&str, Vec<&TelemetryEvent>> = BTreeMap::new();
+        for event in &events {
+            by_command.entry(&event.command).or_default().push(event);
+        }
+
+        let mut commands: Vec<CommandStats> = by_command
+            .into_iter()
+            .map(|(command, runs)| {
+                let mut error_codes = BTreeMap::new();
+                for code in runs.iter().filter_map(|e| e.error_code.as_ref()) {
+                    *error_codes.entry(code.clone()).or_insert(0) += 1;
+                }
+                let hit_rates: Vec<f64> = runs.iter().filter_map(|e| e.cache_hit_rate).collect();
+
+                CommandStats {
+                    command: command.to_string(),
+                    runs: runs.len(),
+                    failures: runs.iter().filter(|e| e.exit_code != 0).count(),
+                    avg_duration_ms: runs.iter().map(|e| e.duration_ms).sum::<u64>()
+                        / runs.len() as u64,
+                    avg_cache_hit_rate: (!hit_rates.is_empty())
+                        .then(|| hit_rates.iter().sum::<f64>() / hit_rates.len() as f64),
+                    error_codes,
+                }
+            })
+            .collect();
+        commands.sort_by(|a, b| b.runs.cmp(&a.runs).then(a.command.cmp(&b.command)));
+
+        Ok(TelemetrySummary {
+            events: events.len(),
+            commands,
+        })
+    }
+
+    /// Delete all stored events
+    pub fn clear(&self) -> Result<(), ForgeKitError> {
+        if self.path.exists() {
+            std::fs::remove_file(&self.path)?;
+        }
+        Ok(())
+    }
+
+    /// Send the stored events to the configured endpoint
+    ///
+    /// Events are deleted once the endpoint accepted them. Returns the
+    /// number of events sent.
+    pub async fn upload(&self, config: &TelemetryConfig) -> Result<usize, ForgeKitError> {
+        let endpoint = config.endpoint.as_ref().ok_or_else(|| {
+            ForgeKitError::InvalidConfig(
+                "No telemetry endpoint configured; run `forgekit telemetry enable --endpoint <url>`"
+                    .to_string(),
+            )
+        })?;
+        let events = self.events()?;
+        if events.is_empty() {
+            return Ok(0);
+        }
+
+        reqwest::Client::new()
+            .post(endpoint)
+            .json(&serde_json::json!({ "events": events }))
+            .send()
+            .await?
+            .error_for_status()?;
+        self.clear()?;
+        Ok(events.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_record_and_summarize() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = TelemetryStore::new(&temp_dir.path().join("telemetry.jsonl"));
+        let mut config = TelemetryConfig::default();
+
+        let event = TelemetryEvent::new("build", Duration::from_millis(100), 0);
+        store.record(&config, &event).unwrap();
+        assert!(store.events().unwrap().is_empty());
+
+        config.enabled = true;
+        let mut cached = TelemetryEvent::new("build", Duration::from_millis(300), 0);
+        cached.cache_hit_rate = Some(0.5);
+        let mut failed = TelemetryEvent::new("build", Duration::from_millis(200), 2);
+        failed.error_code = Some("build_failed".to_string());
+        for event in [
+            &cached,
+            &failed,
+            &TelemetryEvent::new("validate", Duration::ZERO, 0),
+        ] {
+            store.record(&config, event).unwrap();
+        }
+
+        let summary = store.summary().unwrap();
+        assert_eq!(summary.events, 3);
+        let build = &summary.commands[0];
+        assert_eq!(build.command, "build");
+        assert_eq!((build.runs, build.failures), (2, 1));
+        assert_eq!(build.avg_duration_ms, 250);
+        assert_eq!(build.avg_cache_hit_rate, Some(0.5));
+        assert_eq!(build.error_codes["build_failed"], 1);
+
+        store.clear().unwrap();
+        assert!(store.events().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,396 @@
+//! Template update module
+//!
+//! This module brings template fixes into projects generated from an older
+//! revision of their template. When a project is created, the files its
+//! template generated are kept under `.forgekit/template/` and the template
+//! name and revision are recorded in `[template]` of forgekit.toml.
+//! `forgekit template update` regenerates the template and does a 3-way
+//! merge per file between that kept output, the new output and the project's
+//! own version: changes only the template made are applied, edits only the
+//! project made are kept, and lines both changed get conflict markers.
+
+use crate::config::ProjectConfig;
+use crate::dry_run::{DryRun, PlannedAction};
+use crate::error::ForgeKitError;
+use crate::templates::{self, TemplateType};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// Directory keeping the template output a project was last updated to
+pub fn base_dir(project_path: &Path) -> PathBuf {
+    project_path.join(".forgekit").join("template")
+}
+
+/// What `forgekit template update` did to a file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum FileOutcome {
+    /// New in the template and written to the project
+    Added,
+    /// Replaced with the template's version; the project had not changed it
+    Updated,
+    /// Template changes merged with the project's own edits
+    Merged,
+    /// Both changed the same lines; written with conflict markers
+    Conflict {
+        /// Number of conflicting hunks
+        conflicts: usize,
+    },
+    /// Dropped by the template and unchanged in the project, so removed
+    Removed,
+    /// Left alone: deleted or edited in the project while the template
+    /// changed or dropped it, or a binary file both changed
+    Kept,
+}
+
+/// A file the update touched or left alone on purpose
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileUpdate {
+    /// Path relative to the project
+    pub path: PathBuf,
+    /// What happened to it
+    #[serde(flatten)]
+    pub outcome: FileOutcome,
+}
+
+/// Outcome of `forgekit template update`
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateUpdate {
+    /// Template the project was generated from
+    pub template: String,
+    /// Revision the project was at
+    pub from: u32,
+    /// Revision the project is at now
+    pub to: u32,
+    /// Files that differ between the two revisions
+    pub files: Vec<FileUpdate>,
+}
+
+impl TemplateUpdate {
+    /// Number of files left with conflict markers
+    pub fn conflicts(&self) -> usize {
+        self.files
+            .iter()
+            .filter(|file| matches!(file.outcome, FileOutcome::Conflict { .. }))
+            .count()
+    }
+}
+
+/// Keep the files a template generated into `project_path` as its merge base
+pub(crate) fn record_base(project_path: &Path) -> Result<(), ForgeKitError> {
+    copy_files(project_path, &base_dir(project_path))
+}
+
+/// Merge the current revision of the project's template into the project
+pub async fn update(
+    project_path: &Path,
+    dry_run: &DryRun,
+) -> Result<TemplateUpdate, ForgeKitError> {
+    let config_path = project_path.join("forgekit.toml");
+    let mut config = ProjectConfig::load(&config_path)?;
+    let record = config.template.clone().ok_or_else(|| {
+        ForgeKitError::TemplateError(
+            "project has no [template] in forgekit.toml; it was not generated from a template"
+                .to_string(),
+        )
+    })?;
+    let template: TemplateType = record.name.parse().map_err(ForgeKitError::TemplateError)?;
+
+    let staging = tempfile::TempDir::new()?;
+    let generated = staging.path().join(&config.name);
+    templates::generate_from_template(&config.name, template.clone(), &generated).await?;
+
+    let base = base_dir(project_path);
+    let labels = [
+        "yours".to_string(),
+        format!("template v{}", record.version),
+        format!("template v{}", template.version()),
+    ];
+    let paths: BTreeSet<PathBuf> = template_files(&base)
+        .into_iter()
+        .chain(template_files(&generated))
+        .collect();
+
+    let mut files = Vec::new();
+    for path in paths {
+        let old = read(&base.join(&path))?;
+        let new = read(&generated.join(&path))?;
+        let target = project_path.join(&path);
+        let current = read(&target)?;
+        let Some((outcome, contents)) = resolve(old, new, current, &labels) else {
+            continue;
+        };
+
+        match &contents {
+            Some(_) if dry_run.is_enabled() => {
+                dry_run.record(PlannedAction::WriteFile {
+                    path: target.clone(),
+                });
+            }
+            Some(contents) => {
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                crate::atomic::write(&target, contents)?;
+            }
+            None if outcome == FileOutcome::Removed => {
+                if dry_run.is_enabled() {
+                    dry_run.record(PlannedAction::Remove {
+                        path: target.clone(),
+                    });
+                } else {
+                    std::fs::remove_file(&target)?;
+                }
+            }
+            None => {}
+        }
+        files.push(FileUpdate { path, outcome });
+    }
+
+    if dry_run.is_enabled() {
+        dry_run.record(PlannedAction::UpdateFile {
+            path: config_path,
+            change: format!("template.version = {}", template.version()),
+        });
+    } else {
+        if base.exists() {
+            std::fs::remove_dir_all(&base)?;
+        }
+        copy_files(&generated, &base)?;
+        config.template = Some(crate::config::TemplateConfig {
+            version: template.version(),
+            ..record.clone()
+        });
+        config.save(&config_path)?;
+    }
+
+    Ok(TemplateUpdate {
+        template: record.name,
+        from: record.version,
+        to: template.version(),
+        files,
+    })
+}
+
+/// Decide one file from its base, new template and current contents
+///
+/// Returns `None` when there is nothing to report, and otherwise the
+/// outcome with the contents to write, if any.
+fn resolve(
+    old: Option<Vec<u8>>,
+    new: Option<Vec<u8>>,
+    current: Option<Vec<u8>>,
+    labels: &[String; 3],
+) -> Option<(FileOutcome, Option<Vec<u8>>)> {
+    if old == new || current == new {
+        return None;
+    }
+    let outcome = match (old, new, current) {
+        (None, Some(new), None) => (FileOutcome::Added, Some(new)),
+        (Some(_), _, None) => (FileOutcome::Kept, None),
+        (Some(old), None, Some(current)) if old == current => (FileOutcome::Removed, None),
+        (Some(_), None, Some(_)) => (FileOutcome::Kept, None),
+        (Some(old), Some(new), Some(current)) if old == current => {
+            (FileOutcome::Updated, Some(new))
+        }
+        (old, Some(new), Some(current)) => {
+            let old = old.unwrap_or_default();
+            let texts = (
+                String::from_utf8(old),
+                String::from_utf8(current),
+                String::from_utf8(new),
+            );
+            let (Ok(old), Ok(current), Ok(new)) = texts else {
+                return Some((FileOutcome::Kept, None));
+            };
+            let (merged, conflicts) = merge3(&old, &current, &new, labels);
+            let outcome = if conflicts == 0 {
+                FileOutcome::Merged
+            } else {
+                FileOutcome::Conflict { conflicts }
+            };
+            (outcome, Some(merged.into_bytes()))
+        }
+        (None, None, _) => return None,
+    };
+    Some(outcome)
+}
+
+/// Three-way merge of text, line by line
+///
+/// Returns the merged text and the number of conflicting hunks, which are
+/// marked like git does with `labels` naming ours, the base and theirs.
+pub fn merge3(base: &str, ours: &str, theirs: &str, labels: &[String; 3]) -> (String, usize) {
+    let base: Vec<&str> = base.split_inclusive('\n').collect();
+    let ours: Vec<&str> = ours.split_inclusive('\n').collect();
+    let theirs: Vec<&str> = theirs.split_inclusive('\n').collect();
+    let in_ours = matching_lines(&base, &ours);
+    let in_theirs = matching_lines(&base, &theirs);
+
+    let mut merged = String::new();
+    let mut conflicts = 0;
+    let (mut i, mut j, mut k) = (0, 0, 0);
+    loop {
+        // Next base line both sides kept, closing the current hunk
+        let sync = (i..base.len()).find_map(|b| Some((b, in_ours[b]?, in_theirs[b]?)));
+        let (b, o, t) = sync.unwrap_or((base.len(), ours.len(), theirs.len()));
+        let (base_hunk, our_hunk, their_hunk) = (&base[i..b], &ours[j..o], &theirs[k..t]);
+
+        if our_hunk == base_hunk || our_hunk == their_hunk {
+            merged.extend(their_hunk.iter().copied());
+        } else if their_hunk == base_hunk {
+            merged.extend(our_hunk.iter().copied());
+        } else {
+            conflicts += 1;
+            push_marked(&mut merged, &format!("<<<<<<< {}", labels[0]), our_hunk);
+            push_marked(&mut merged, &format!("||||||| {}", labels[1]), base_hunk);
+            push_marked(&mut merged, "=======", their_hunk);
+            merged.push_str(&format!(">>>>>>> {}\n", labels[2]));
+        }
+
+        if b == base.len() {
+            return (merged, conflicts);
+        }
+        merged.push_str(base[b]);
+        (i, j, k) = (b + 1, o + 1, t + 1);
+    }
+}
+
+/// For each line of `base`, the line of `other` it was kept as, if any
+fn matching_lines(base: &[&str], other: &[&str]) -> Vec<Option<usize>> {
+    let mut matches = vec![None; base.len()];
+    for op in similar::capture_diff_slices(similar::Algorithm::Myers, base, other) {
+        if let similar::DiffOp::Equal {
+            old_index,
+            new_index,
+            len,
+        } = op
+        {
+            for offset in 0..len {
+                matches[old_index + offset] = Some(new_index + offset);
+            }
+        }
+    }
+    matches
+}
+
+fn push_marked(merged: &mut String, marker: &str, lines: &[&str]) {
+    merged.push_str(marker);
+    merged.push('\n');
+    for line in lines {
+        merged.push_str(line);
+    }
+    if lines.last().is_some_and(|line| !line.ends_with('\n')) {
+        merged.push('\n');
+    }
+}
+
+/// Files under `root` a template generates, relative to it
+///
+/// forgekit.toml is left out: it belongs to the project once created.
+fn template_files(root: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = walkdir::WalkDir::new(root)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.path().strip_prefix(root).ok().map(Path::to_path_buf))
+        .filter(|path| path != Path::new("forgekit.toml") && !path.starts_with(".forgekit"))
+        .collect();
+    files.sort();
+    files
+}
+
+fn copy_files(from: &Path, to: &Path) -> Result<(), ForgeKitError> {
+    for path in template_files(from) {
+        let target = to.join(&path);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(from.join(&path), target)?;
+    }
+    Ok(())
+}
+
+fn read(path: &Path) -> Result<Option<Vec<u8>>, ForgeKitError> {
+    match std::fs::read(path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::{self, InitOptions, Vcs};
+    use tempfile::TempDir;
+
+    fn labels() -> [String; 3] {
+        ["yours", "template v1", "template v2"].map(String::from)
+    }
+
+    #[test]
+    fn test_merge3_applies_both_sides_and_marks_conflicts() {
+        let base = "a\nb\nc\nd\n";
+        let ours = "a\nB\nc\nd\n";
+        let theirs = "a\nb\nc\nD\ne\n";
+        assert_eq!(
+            merge3(base, ours, theirs, &labels()),
+            ("a\nB\nc\nD\ne\n".to_string(), 0)
+        );
+
+        let (merged, conflicts) = merge3(base, "a\nmine\nc\nd\n", "a\nnew\nc\nd\n", &labels());
+        assert_eq!(conflicts, 1);
+        assert_eq!(
+            merged,
+            "a\n<<<<<<< yours\nmine\n||||||| template v1\nb\n=======\nnew\n>>>>>>> template v2\nc\nd\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_merges_template_fixes_into_edited_project() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("demo");
+        let options = InitOptions {
+            template: Some(TemplateType::Basic),
+            vcs: Vcs::None,
+            authors: vec!["Dev".to_string()],
+            ..Default::default()
+        };
+        project::init_with_options("demo", &path, &options)
+            .await
+            .unwrap();
+        let config = ProjectConfig::load(path.join("forgekit.toml")).unwrap();
+        assert_eq!(config.template.as_ref().unwrap().name, "basic");
+
+        // Pretend the project came from an older revision lacking a line
+        let main_rs = Path::new("src").join("main.rs");
+        let current = std::fs::read_to_string(path.join(&main_rs)).unwrap();
+        let old = current.replace(
+            "    println!(\"Built with ForgeKit for Ledokoz OS\");\n",
+            "",
+        );
+        std::fs::write(base_dir(&path).join(&main_rs), &old).unwrap();
+        let edited = old.replace("// Your application logic here", "run_app();");
+        std::fs::write(path.join(&main_rs), &edited).unwrap();
+
+        let dry_run = DryRun::enabled();
+        let planned = update(&path, &dry_run).await.unwrap();
+        assert_eq!(planned.files[0].outcome, FileOutcome::Merged);
+        assert_eq!(
+            std::fs::read_to_string(path.join(&main_rs)).unwrap(),
+            edited
+        );
+
+        let report = update(&path, &DryRun::disabled()).await.unwrap();
+        assert_eq!(report.conflicts(), 0);
+        let merged = std::fs::read_to_string(path.join(&main_rs)).unwrap();
+        assert!(merged.contains("run_app();"));
+        assert!(merged.contains("Built with ForgeKit for Ledokoz OS"));
+        assert_eq!(
+            std::fs::read_to_string(base_dir(&path).join(&main_rs)).unwrap(),
+            current
+        );
+    }
+}
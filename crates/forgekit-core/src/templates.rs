@@ -1,6 +1,9 @@
 //! Project template system for ForgeKit
 
+use crate::config::{GeneratorConfig, GeneratorKind, PluginConfig, ProjectConfig, ProjectKind};
+use crate::crash;
 use crate::error::ForgeKitError;
+use crate::plugin::PLUGIN_API_VERSION;
 use std::path::Path;
 use tokio::fs;
 
@@ -16,6 +19,8 @@ pub enum TemplateType {
     Service,
     /// Plugin template
     Plugin,
+    /// Reusable library packaged as .moxlib
+    Library,
 }
 
 impl TemplateType {
@@ -26,6 +31,41 @@ impl TemplateType {
             TemplateType::Cli => "cli",
             TemplateType::Service => "service",
             TemplateType::Plugin => "plugin",
+            TemplateType::Library => "library",
+        }
+    }
+
+    /// Revision of the template's output, bumped whenever it changes
+    ///
+    /// Projects record the revision they were generated from, so
+    /// `forgekit template update` knows which fixes they are missing.
+    pub fn version(&self) -> u32 {
+        match self {
+            TemplateType::Basic => 1,
+            TemplateType::Gui => 1,
+            TemplateType::Cli => 1,
+            TemplateType::Service => 1,
+            TemplateType::Plugin => 2,
+            TemplateType::Library => 1,
+        }
+    }
+}
+
+impl std::str::FromStr for TemplateType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "basic" => Ok(TemplateType::Basic),
+            "gui" => Ok(TemplateType::Gui),
+            "cli" => Ok(TemplateType::Cli),
+            "service" => Ok(TemplateType::Service),
+            "plugin" => Ok(TemplateType::Plugin),
+            "library" => Ok(TemplateType::Library),
+            _ => Err(format!(
+                "unknown template '{}' (expected basic, gui, cli, service, plugin or library)",
+                s
+            )),
         }
     }
 }
@@ -42,6 +82,7 @@ pub async fn generate_from_template(
         TemplateType::Cli => generate_cli_template(name, path).await,
         TemplateType::Service => generate_service_template(name, path).await,
         TemplateType::Plugin => generate_plugin_template(name, path).await,
+        TemplateType::Library => generate_library_template(name, path).await,
     }
 }
 
@@ -57,7 +98,10 @@ async fn generate_basic_template(name: &str, path: &Path) -> Result<(), ForgeKit
 //!
 //! A basic .mox application built with ForgeKit
 
+mod forgekit_crash;
+
 fn main() {{
+    forgekit_crash::install();
     println!("Hello from {{}}!", "{name}");
     println!("Built with ForgeKit for Ledokoz OS");
     
@@ -66,6 +110,18 @@ fn main() {{
 "#
     );
     fs::write(path.join("src").join("main.rs"), main_content).await?;
+    crash::install_handler(path)?;
+    generate_app_manifest(name, path, "").await?;
+    generate_integration_test(
+        path,
+        &format!(
+            r#"    let output = harness.run(&[], "").await.unwrap();
+    output
+        .assert_success()
+        .assert_stdout_contains("Hello from {name}!");"#
+        ),
+    )
+    .await?;
 
     Ok(())
 }
@@ -83,7 +139,10 @@ async fn generate_gui_template(name: &str, path: &Path) -> Result<(), ForgeKitEr
 //!
 //! A GUI .mox application built with ForgeKit
 
+mod forgekit_crash;
+
 fn main() {{
+    forgekit_crash::install();
     println!("Starting GUI application: {{}}", "{name}");
     
     // Initialize GUI framework
@@ -106,6 +165,16 @@ fn main() {{
 </window>
 "#;
     fs::write(path.join("ui").join("main.xml"), ui_content).await?;
+    crash::install_handler(path)?;
+    generate_app_manifest(name, path, "").await?;
+    generate_integration_test(
+        path,
+        r#"    let output = harness.run(&[], "").await.unwrap();
+    output
+        .assert_success()
+        .assert_stdout_contains("GUI application running...");"#,
+    )
+    .await?;
 
     Ok(())
 }
@@ -122,6 +191,8 @@ async fn generate_cli_template(name: &str, path: &Path) -> Result<(), ForgeKitEr
 
 use clap::Parser;
 
+mod forgekit_crash;
+
 #[derive(Parser)]
 #[command(name = "{name}")]
 #[command(about = "A powerful CLI tool built with ForgeKit")]
@@ -144,6 +215,7 @@ enum Commands {{
 }}
 
 fn main() {{
+    forgekit_crash::install();
     let cli = Cli::parse();
     
     match cli.command {{
@@ -161,6 +233,23 @@ fn main() {{
 "#
     );
     fs::write(path.join("src").join("main.rs"), main_content).await?;
+    crash::install_handler(path)?;
+    generate_app_manifest(
+        name,
+        path,
+        "clap = { version = \"4\", features = [\"derive\"] }\n",
+    )
+    .await?;
+    generate_integration_test(
+        path,
+        &format!(
+            r#"    let output = harness.run(&["version"], "").await.unwrap();
+    output
+        .assert_success()
+        .assert_stdout_contains("{name} v0.1.0");"#
+        ),
+    )
+    .await?;
 
     Ok(())
 }
@@ -176,8 +265,11 @@ async fn generate_service_template(name: &str, path: &Path) -> Result<(), ForgeK
 
 use tokio::signal;
 
+mod forgekit_crash;
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {{
+    forgekit_crash::install();
     println!("Starting service: {{}}", "{name}");
     
     // Service initialization
@@ -199,6 +291,51 @@ async fn initialize_service() -> Result<(), Box<dyn std::error::Error>> {{
 "#
     );
     fs::write(path.join("src").join("main.rs"), main_content).await?;
+    crash::install_handler(path)?;
+    generate_app_manifest(
+        name,
+        path,
+        "tokio = { version = \"1\", features = [\"macros\", \"rt-multi-thread\", \"signal\"] }\n",
+    )
+    .await?;
+    generate_integration_test(
+        path,
+        r#"    let app = harness.launch(&[]).await.unwrap();
+    app.expect_output("Service initialized", Duration::from_secs(10))
+        .await
+        .unwrap();
+    app.stop().await.unwrap();"#,
+    )
+    .await?;
+
+    // Scaffold a protobuf schema directory wired into codegen
+    fs::create_dir_all(path.join("proto")).await?;
+    let proto_content = format!(
+        r#"syntax = "proto3";
+
+package {package};
+
+message StatusRequest {{}}
+
+message StatusResponse {{
+  string status = 1;
+}}
+"#,
+        package = name.replace('-', "_")
+    );
+    fs::write(path.join("proto").join("service.proto"), proto_content).await?;
+
+    let config = ProjectConfig {
+        name: name.to_string(),
+        codegen: vec![GeneratorConfig {
+            kind: GeneratorKind::Protobuf,
+            schema_dir: "proto".to_string(),
+            output_dir: "src/generated".to_string(),
+            command: None,
+        }],
+        ..Default::default()
+    };
+    config.save(path.join("forgekit.toml"))?;
 
     Ok(())
 }
@@ -207,46 +344,182 @@ async fn generate_plugin_template(name: &str, path: &Path) -> Result<(), ForgeKi
     fs::create_dir_all(path).await?;
     fs::create_dir_all(path.join("src")).await?;
 
+    // ForgeKit loads plugins from their dynamic library
+    let cargo_content = format!(
+        r#"[package]
+name = "{name}"
+version = "0.1.0"
+edition = "2021"
+
+[lib]
+crate-type = ["cdylib", "rlib"]
+
+[dependencies]
+# Plugins must be built against the exact forgekit-core of the ForgeKit
+# loading them
+forgekit-core = "={forgekit}"
+"#,
+        forgekit = env!("CARGO_PKG_VERSION"),
+    );
+    fs::write(path.join("Cargo.toml"), cargo_content).await?;
+
+    let crate_name = name.replace('-', "_");
     let lib_content = format!(
         r#"//! Plugin library: {name}
 //!
-//! A ForgeKit plugin
+//! A ForgeKit plugin, installed with `forgekit plugin install {name}`
 
-use forgekit_core::{{Plugin, PluginContext}};
+use forgekit_core::error::ForgeKitError;
+use forgekit_core::plugin::{{BuildContext, Plugin}};
 
 pub struct {name_cap}Plugin;
 
 impl Plugin for {name_cap}Plugin {{
-    fn name(&self) -> &'static str {{
+    fn name(&self) -> &str {{
         "{name}"
     }}
-    
-    fn version(&self) -> &'static str {{
-        "0.1.0"
+
+    fn version(&self) -> &str {{
+        env!("CARGO_PKG_VERSION")
     }}
-    
-    fn initialize(&mut self, ctx: &PluginContext) -> Result<(), Box<dyn std::error::Error>> {{
-        println!("Initializing {{}} plugin", self.name());
+
+    fn on_pre_build(&self, context: &BuildContext) -> Result<(), ForgeKitError> {{
+        eprintln!("{name}: building {{}}", context.project_path);
         Ok(())
     }}
-    
-    fn execute(&self, ctx: &PluginContext, data: &str) -> Result<String, Box<dyn std::error::Error>> {{
-        Ok(format!("Processed by {{}}: {{}}", self.name(), data))
-    }}
 }}
 
-// Export the plugin
-forgekit_core::export_plugin!({name_cap}Plugin);
+forgekit_core::declare_plugin!({name_cap}Plugin);
 "#,
-        name_cap = name
+        name_cap = crate_name
             .chars()
             .next()
             .unwrap()
             .to_uppercase()
             .collect::<String>()
-            + &name[1..]
+            + &crate_name[1..]
+    );
+    fs::write(path.join("src").join("lib.rs"), lib_content).await?;
+
+    let config = ProjectConfig {
+        name: name.to_string(),
+        kind: ProjectKind::Library,
+        plugin: Some(PluginConfig {
+            api_version: PLUGIN_API_VERSION,
+        }),
+        ..Default::default()
+    };
+    config.save(path.join("forgekit.toml"))?;
+
+    Ok(())
+}
+
+async fn generate_library_template(name: &str, path: &Path) -> Result<(), ForgeKitError> {
+    fs::create_dir_all(path).await?;
+    fs::create_dir_all(path.join("src")).await?;
+
+    let crate_name = name.replace('-', "_");
+    let cargo_content = format!(
+        r#"[package]
+name = "{name}"
+version = "0.1.0"
+edition = "2021"
+
+[lib]
+crate-type = ["rlib"]
+"#
+    );
+    fs::write(path.join("Cargo.toml"), cargo_content).await?;
+
+    let lib_content = format!(
+        r#"//! Library: {name}
+//!
+//! A reusable ForgeKit library, packaged as a .moxlib with `forgekit package`
+
+/// Greet someone from {name}
+///
+/// ```
+/// assert_eq!({crate_name}::greet("Ledokoz"), "Hello, Ledokoz!");
+/// ```
+pub fn greet(who: &str) -> String {{
+    format!("Hello, {{}}!", who)
+}}
+
+#[cfg(test)]
+mod tests {{
+    use super::*;
+
+    #[test]
+    fn test_greet() {{
+        assert_eq!(greet("world"), "Hello, world!");
+    }}
+}}
+"#
     );
     fs::write(path.join("src").join("lib.rs"), lib_content).await?;
 
+    let config = ProjectConfig {
+        name: name.to_string(),
+        kind: ProjectKind::Library,
+        ..Default::default()
+    };
+    config.save(path.join("forgekit.toml"))?;
+
+    Ok(())
+}
+
+/// Write the Cargo.toml of an app with its `dependencies`, and the
+/// dev-dependencies of the end-to-end test
+async fn generate_app_manifest(
+    name: &str,
+    path: &Path,
+    dependencies: &str,
+) -> Result<(), ForgeKitError> {
+    let cargo_content = format!(
+        r#"[package]
+name = "{name}"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+{dependencies}
+[dev-dependencies]
+forgekit-core = "{forgekit}"
+tokio = {{ version = "1", features = ["macros", "rt"] }}
+"#,
+        forgekit = env!("CARGO_PKG_VERSION"),
+    );
+    fs::write(path.join("Cargo.toml"), cargo_content).await?;
+    Ok(())
+}
+
+/// Write an end-to-end test running the packaged app through `body`
+async fn generate_integration_test(path: &Path, body: &str) -> Result<(), ForgeKitError> {
+    fs::create_dir_all(path.join("tests")).await?;
+    let imports = if body.contains("Duration") {
+        "use forgekit_core::testing::IntegrationHarness;\nuse std::time::Duration;"
+    } else {
+        "use forgekit_core::testing::IntegrationHarness;"
+    };
+    let test_content = format!(
+        r#"//! End-to-end test: build, package and install the app, then run it
+//!
+//! Uses the `forgekit-core` and `tokio` dev-dependencies of Cargo.toml.
+//! Building and packaging takes a while, so the test only runs with
+//! `cargo test -- --ignored`.
+
+{imports}
+
+#[tokio::test]
+#[ignore = "builds and packages the app"]
+async fn app_runs_after_install() {{
+    let harness = IntegrationHarness::new(env!("CARGO_MANIFEST_DIR"))
+        .await
+        .unwrap();
+{body}
+}}
+"#
+    );
+    fs::write(path.join("tests").join("integration.rs"), test_content).await?;
     Ok(())
 }
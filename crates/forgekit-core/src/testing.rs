@@ -1,11 +1,23 @@
 //! Testing framework integration module
 //!
 //! This module provides functionality for running tests, generating test scaffolds,
-//! and producing coverage reports.
-
+//! and producing coverage reports. Failed tests can be rerun to tell flaky tests
+//! from broken ones. [`IntegrationHarness`] runs packaged apps
+//! end to end, and [`fixtures`] sets up test data and compares golden files.
+
+use crate::analytics;
+use crate::atomic;
+use crate::builder::BuildOptions;
+use crate::config::ProjectConfig;
+use crate::diagnostics::{Diagnostic, Severity};
 use crate::error::ForgeKitError;
-use std::path::Path;
-use std::time::Duration;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+pub mod fixtures;
+mod harness;
+
+pub use harness::{AppOutput, HttpResponse, IntegrationHarness, RunningApp};
 
 /// Test report containing test execution results
 #[derive(Debug, Clone)]
@@ -14,12 +26,18 @@ pub struct TestReport {
     pub total: usize,
     /// Number of passed tests
     pub passed: usize,
-    /// Number of failed tests
+    /// Number of failed tests, not counting flaky or quarantined ones
     pub failed: usize,
     /// Test execution duration
     pub duration: Duration,
     /// Test output
     pub output: String,
+    /// Tests that failed, after retries
+    pub failed_tests: Vec<String>,
+    /// Tests that failed and then passed on retry
+    pub flaky: Vec<String>,
+    /// Quarantined tests that failed; reported without failing the run
+    pub quarantined: Vec<String>,
 }
 
 impl TestReport {
@@ -31,6 +49,9 @@ impl TestReport {
             failed: 0,
             duration: Duration::from_secs(0),
             output: String::new(),
+            failed_tests: Vec::new(),
+            flaky: Vec::new(),
+            quarantined: Vec::new(),
         }
     }
 
@@ -38,6 +59,59 @@ impl TestReport {
     pub fn all_passed(&self) -> bool {
         self.failed == 0
     }
+
+    /// Convert failed, flaky and quarantined tests into diagnostics
+    ///
+    /// Failures point at the location the test panicked at, when the output
+    /// shows it.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let panic = regex::Regex::new(r"thread '([^']+)' panicked at ([^:\s]+):(\d+):(\d+)")
+            .expect("valid panic pattern");
+        let location = |test: &str| {
+            panic
+                .captures_iter(&self.output)
+                .find(|c| &c[1] == test)
+                .map(|c| (PathBuf::from(&c[2]), c[3].parse().ok(), c[4].parse().ok()))
+        };
+
+        let mut diagnostics = Vec::new();
+        for test in &self.failed_tests {
+            let (file, line, column) = match location(test) {
+                Some((file, line, column)) => (Some(file), line, column),
+                None => (None, None, None),
+            };
+            diagnostics.push(Diagnostic {
+                code: Some(test.clone()),
+                file,
+                line,
+                column,
+                fix: Some(format!("Run `cargo test {}` to reproduce", test)),
+                ..Diagnostic::new("test", Severity::Error, format!("{} failed", test))
+            });
+        }
+        for test in &self.flaky {
+            diagnostics.push(Diagnostic {
+                code: Some(test.clone()),
+                fix: Some("Fix it or quarantine it in [test]".to_string()),
+                ..Diagnostic::new(
+                    "test",
+                    Severity::Warning,
+                    format!("{} passed only on retry", test),
+                )
+            });
+        }
+        for test in &self.quarantined {
+            diagnostics.push(Diagnostic {
+                code: Some(test.clone()),
+                ..Diagnostic::new(
+                    "test",
+                    Severity::Note,
+                    format!("{} failed but is quarantined", test),
+                )
+            });
+        }
+        diagnostics
+    }
 }
 
 impl Default for TestReport {
@@ -72,6 +146,135 @@ pub struct FileCoverage {
     pub total: usize,
 }
 
+/// Kind of test generated by [`TestRunner::generate_test_scaffold_with`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TestKind {
+    /// Plain `#[test]` functions
+    #[default]
+    Unit,
+    /// proptest properties checked against generated inputs
+    Property,
+    /// `#[tokio::test]` functions for async code
+    Async,
+}
+
+impl TestKind {
+    /// Name of the kind as passed to `--kind`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TestKind::Unit => "unit",
+            TestKind::Property => "property",
+            TestKind::Async => "async",
+        }
+    }
+
+    /// Dev-dependency the generated tests need
+    fn dev_dependency(&self) -> Option<(&'static str, toml_edit::Item)> {
+        match self {
+            TestKind::Unit => None,
+            TestKind::Property => Some(("proptest", toml_edit::value("1"))),
+            TestKind::Async => {
+                let mut tokio = toml_edit::InlineTable::new();
+                tokio.insert("version", "1".into());
+                let features: toml_edit::Array = ["macros", "rt", "time"].into_iter().collect();
+                tokio.insert("features", features.into());
+                Some(("tokio", toml_edit::value(tokio)))
+            }
+        }
+    }
+
+    fn scaffold(&self, name: &str) -> String {
+        match self {
+            TestKind::Unit => format!(
+                r#"//! Tests for {name}
+
+#[test]
+fn test_{name}() {{
+    // TODO: Implement test
+    assert!(true);
+}}
+
+#[test]
+fn test_{name}_error_case() {{
+    // TODO: Implement error case test
+    assert!(true);
+}}
+"#
+            ),
+            TestKind::Property => format!(
+                r#"//! Property tests for {name}
+
+use proptest::prelude::*;
+
+proptest! {{
+    #[test]
+    fn {name}_round_trips(input in ".*") {{
+        // TODO: Encode and decode `input` with the code under test
+        let output = input.clone();
+        prop_assert_eq!(output, input);
+    }}
+
+    #[test]
+    fn {name}_handles_any_number(value in any::<i64>()) {{
+        // TODO: Check an invariant that holds for every `value`
+        prop_assert_eq!(value.checked_add(0), Some(value));
+    }}
+}}
+"#
+            ),
+            TestKind::Async => format!(
+                r#"//! Async tests for {name}
+
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_{name}() {{
+    // TODO: Await the code under test
+    let result = async {{ 42 }}.await;
+    assert_eq!(result, 42);
+}}
+
+#[tokio::test]
+async fn test_{name}_finishes_in_time() {{
+    // TODO: Replace the sleep with the future under test
+    let work = tokio::time::sleep(Duration::from_millis(10));
+    let result = tokio::time::timeout(Duration::from_secs(5), work).await;
+    assert!(result.is_ok());
+}}
+"#
+            ),
+        }
+    }
+}
+
+impl std::str::FromStr for TestKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "unit" => Ok(TestKind::Unit),
+            "property" => Ok(TestKind::Property),
+            "async" => Ok(TestKind::Async),
+            _ => Err(format!(
+                "unknown test kind '{}' (expected unit, property or async)",
+                s
+            )),
+        }
+    }
+}
+
+/// A generated test file
+#[derive(Debug, Clone)]
+pub struct TestScaffold {
+    /// Path to the generated test file
+    pub file: PathBuf,
+    /// Dev-dependency added to Cargo.toml for the test, if any
+    pub added_dependency: Option<String>,
+    /// Features the test needs that were enabled on a dependency the
+    /// project already had
+    pub enabled_features: Vec<String>,
+}
+
 /// Test runner for executing tests
 pub struct TestRunner;
 
@@ -86,6 +289,36 @@ impl TestRunner {
     ///
     /// A `TestReport` with test execution results
     pub async fn run_tests(path: &Path) -> Result<TestReport, ForgeKitError> {
+        Self::run_tests_with_options(path, &BuildOptions::default()).await
+    }
+
+    /// Run all tests in a project with custom build options
+    ///
+    /// Failed tests are rerun and quarantined as set in the `[test]` section
+    /// of forgekit.toml.
+    pub async fn run_tests_with_options(
+        path: &Path,
+        options: &BuildOptions,
+    ) -> Result<TestReport, ForgeKitError> {
+        let config = ProjectConfig::load(path.join("forgekit.toml"))
+            .map(|config| config.test)
+            .unwrap_or_default();
+        Self::run_tests_with_retries(path, options, config.retries, &config.quarantine).await
+    }
+
+    /// Run all tests, rerunning each failed one up to `retries` times
+    ///
+    /// Tests passing on a retry are reported as flaky rather than failed.
+    /// Failures of tests listed in `quarantine` are reported as quarantined
+    /// and do not count as failed. The run is recorded in the test history
+    /// behind the flake rates of [`analytics::flake_rates`].
+    pub async fn run_tests_with_retries(
+        path: &Path,
+        options: &BuildOptions,
+        retries: u32,
+        quarantine: &[String],
+    ) -> Result<TestReport, ForgeKitError> {
+        let started = Instant::now();
         let mut report = TestReport::new();
 
         // Check if Cargo.toml exists
@@ -96,25 +329,93 @@ impl TestRunner {
             ));
         }
 
-        // Run cargo test
-        let output = tokio::process::Command::new("cargo")
+        report.output = Self::cargo_test(path, options, &[]).await?;
+        let output_copy = report.output.clone();
+        Self::parse_test_output(&output_copy, &mut report);
+
+        let mut failing = std::mem::take(&mut report.failed_tests);
+        for attempt in 1..=retries {
+            if failing.is_empty() {
+                break;
+            }
+            tracing::info!(
+                "Retrying {} failed test(s), attempt {}",
+                failing.len(),
+                attempt
+            );
+            let output = Self::cargo_test(path, options, &failing).await?;
+            let passed: Vec<&str> = test_outcomes(&output)
+                .filter(|(_, ok)| *ok)
+                .map(|(name, _)| name)
+                .collect();
+            let (recovered, still_failing) = failing
+                .into_iter()
+                .partition(|name| passed.contains(&name.as_str()));
+            report.flaky.extend::<Vec<String>>(recovered);
+            failing = still_failing;
+            report
+                .output
+                .push_str(&format!("\n--- retry {} ---\n{}", attempt, output));
+        }
+        let (quarantined, failed): (Vec<String>, Vec<String>) = failing
+            .into_iter()
+            .partition(|name| quarantine.contains(name));
+
+        let excused = report.flaky.len() + quarantined.len();
+        report.failed = report.failed.saturating_sub(excused);
+        report.passed += report.flaky.len();
+        report.failed_tests = failed;
+        report.quarantined = quarantined;
+        report.duration = started.elapsed();
+
+        if let Err(e) = analytics::record_test_run(path, &report) {
+            tracing::warn!("Failed to record test history: {}", e);
+        }
+        Ok(report)
+    }
+
+    /// Add tests to the quarantine list in forgekit.toml
+    ///
+    /// Returns the tests that were not quarantined yet.
+    pub fn quarantine(path: &Path, tests: &[String]) -> Result<Vec<String>, ForgeKitError> {
+        let config_path = path.join("forgekit.toml");
+        let mut config = ProjectConfig::load(&config_path)?;
+        let added: Vec<String> = tests
+            .iter()
+            .filter(|test| !config.test.quarantine.contains(test))
+            .cloned()
+            .collect();
+        if !added.is_empty() {
+            config.test.quarantine.extend(added.iter().cloned());
+            config.test.quarantine.sort();
+            config.save(&config_path)?;
+        }
+        Ok(added)
+    }
+
+    /// Run `cargo test`, only the tests named in `exact` when not empty
+    async fn cargo_test(
+        path: &Path,
+        options: &BuildOptions,
+        exact: &[String],
+    ) -> Result<String, ForgeKitError> {
+        let mut command = tokio::process::Command::new("cargo");
+        command
             .arg("test")
             .arg("--")
             .arg("--nocapture")
-            .current_dir(path)
-            .output()
-            .await?;
+            .current_dir(path);
+        if !exact.is_empty() {
+            command.arg("--exact").args(exact);
+        }
+        if let Some(target_dir) = &options.target_dir {
+            command.env("CARGO_TARGET_DIR", target_dir);
+        }
+        let output = command.output().await?;
 
         let stdout = String::from_utf8_lossy(&output.stdout);
         let stderr = String::from_utf8_lossy(&output.stderr);
-
-        report.output = format!("{}\n{}", stdout, stderr);
-
-        // Parse test results from output
-        let output_copy = report.output.clone();
-        Self::parse_test_output(&output_copy, &mut report);
-
-        Ok(report)
+        Ok(format!("{}\n{}", stdout, stderr))
     }
 
     /// Run tests with coverage reporting
@@ -172,75 +473,179 @@ impl TestRunner {
         name: &str,
         path: &Path,
     ) -> Result<std::path::PathBuf, ForgeKitError> {
+        Ok(
+            Self::generate_test_scaffold_with(name, path, TestKind::Unit)
+                .await?
+                .file,
+        )
+    }
+
+    /// Generate test scaffolding of the given kind
+    ///
+    /// Property and async scaffolds add the crate they need (proptest or
+    /// tokio) to `[dev-dependencies]` when the project does not depend on
+    /// it yet, and otherwise enable the features they use on the existing
+    /// dependency.
+    pub async fn generate_test_scaffold_with(
+        name: &str,
+        path: &Path,
+        kind: TestKind,
+    ) -> Result<TestScaffold, ForgeKitError> {
         let tests_dir = path.join("tests");
         if !tests_dir.exists() {
             std::fs::create_dir(&tests_dir)?;
         }
 
         let test_file = tests_dir.join(format!("{}_test.rs", name));
+        std::fs::write(&test_file, kind.scaffold(name))?;
 
-        let scaffold = format!(
-            r#"//! Tests for {}
-
-#[test]
-fn test_{}() {{
-    // TODO: Implement test
-    assert!(true);
-}}
-
-#[test]
-fn test_{}_error_case() {{
-    // TODO: Implement error case test
-    assert!(true);
-}}
-"#,
-            name, name, name
-        );
-
-        std::fs::write(&test_file, scaffold)?;
-
-        Ok(test_file)
+        let (added_dependency, enabled_features) = match kind.dev_dependency() {
+            Some((dependency, item)) => add_dev_dependency(path, dependency, item)?,
+            None => (None, Vec::new()),
+        };
+        Ok(TestScaffold {
+            file: test_file,
+            added_dependency,
+            enabled_features,
+        })
     }
 
     /// Parse test output to extract test results
+    ///
+    /// Counts come from the `test result:` summaries, summed over the test
+    /// binaries, or from the individual test lines when there is none.
     fn parse_test_output(output: &str, report: &mut TestReport) {
-        let mut passed = 0;
-        let mut failed = 0;
-
+        let mut summary = None;
         for line in output.lines() {
-            // Look for lines containing test results like "test result: ok. 5 passed; 0 failed"
-            if line.contains("test result:") && line.contains("ok.") {
-                // Use regex-like approach to extract numbers
-                // Find "X passed" and "Y failed"
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                for i in 0..parts.len() {
-                    if i > 0 && (parts[i] == "passed" || parts[i] == "passed;") {
-                        if let Ok(count) = parts[i - 1].parse::<usize>() {
-                            passed = count;
-                        }
-                    } else if i > 0 && (parts[i] == "failed" || parts[i] == "failed;") {
-                        if let Ok(count) = parts[i - 1].parse::<usize>() {
-                            failed = count;
-                        }
-                    }
+            // Summaries look like "test result: ok. 5 passed; 0 failed; 0 ignored"
+            let Some(result) = line.strip_prefix("test result: ") else {
+                continue;
+            };
+            let (passed, failed) = summary.get_or_insert((0, 0));
+            let parts: Vec<&str> = result.split_whitespace().collect();
+            for pair in parts.windows(2) {
+                let Ok(count) = pair[0].parse::<usize>() else {
+                    continue;
+                };
+                match pair[1].trim_end_matches(';') {
+                    "passed" => *passed += count,
+                    "failed" => *failed += count,
+                    _ => {}
                 }
             }
-
-            if line.contains("FAILED") {
-                failed += 1;
-            }
         }
 
+        let outcomes: Vec<(&str, bool)> = test_outcomes(output).collect();
+        report.failed_tests = outcomes
+            .iter()
+            .filter(|(_, ok)| !ok)
+            .map(|(name, _)| name.to_string())
+            .collect();
+        let (passed, failed) = summary.unwrap_or_else(|| {
+            let failed = report.failed_tests.len();
+            (outcomes.len() - failed, failed)
+        });
         report.passed = passed;
         report.failed = failed;
         report.total = passed + failed;
+    }
+}
+
+/// Names of the tests in `cargo test` output with whether each passed
+///
+/// Ignored tests are left out.
+fn test_outcomes(output: &str) -> impl Iterator<Item = (&str, bool)> {
+    output.lines().filter_map(|line| {
+        let (name, outcome) = line.strip_prefix("test ")?.rsplit_once(" ... ")?;
+        match outcome.trim() {
+            "ok" => Some((name, true)),
+            "FAILED" => Some((name, false)),
+            _ => None,
+        }
+    })
+}
 
-        if report.total == 0 {
-            // Try to count test functions
-            report.total = output.matches("test ").count();
-            report.passed = report.total - failed;
+/// Add `name` to `[dev-dependencies]` unless the project already depends on it
+///
+/// When it does, the features `item` asks for are enabled on the existing
+/// dependency instead. Returns the name when it was added and the features
+/// that were enabled. Projects without a Cargo.toml are left alone.
+fn add_dev_dependency(
+    path: &Path,
+    name: &str,
+    item: toml_edit::Item,
+) -> Result<(Option<String>, Vec<String>), ForgeKitError> {
+    let cargo_toml = path.join("Cargo.toml");
+    if !cargo_toml.exists() {
+        return Ok((None, Vec::new()));
+    }
+    let mut manifest: toml_edit::DocumentMut = std::fs::read_to_string(&cargo_toml)?
+        .parse()
+        .map_err(|e| ForgeKitError::InvalidConfig(format!("Invalid Cargo.toml: {}", e)))?;
+    let existing = ["dependencies", "dev-dependencies"]
+        .into_iter()
+        .find(|table| manifest.get(table).and_then(|t| t.get(name)).is_some());
+    if let Some(table) = existing {
+        let wanted: Vec<String> = item
+            .get("features")
+            .and_then(|f| f.as_array())
+            .map(|f| {
+                f.iter()
+                    .filter_map(|v| v.as_str())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let enabled = enable_features(&mut manifest[table][name], &wanted);
+        if !enabled.is_empty() {
+            atomic::write(&cargo_toml, manifest.to_string())?;
+        }
+        return Ok((None, enabled));
+    }
+
+    let dev_dependencies = manifest
+        .entry("dev-dependencies")
+        .or_insert_with(toml_edit::table)
+        .as_table_mut()
+        .ok_or_else(|| {
+            ForgeKitError::InvalidConfig("`dev-dependencies` in Cargo.toml is not a table".into())
+        })?;
+    dev_dependencies.insert(name, item);
+    atomic::write(&cargo_toml, manifest.to_string())?;
+    Ok((Some(name.to_string()), Vec::new()))
+}
+
+/// Enable `features` on a dependency entry, returning those it lacked
+///
+/// A plain version requirement (`tokio = "1"`) becomes an inline table so
+/// it can carry the features.
+fn enable_features(dependency: &mut toml_edit::Item, features: &[String]) -> Vec<String> {
+    if features.is_empty() {
+        return Vec::new();
+    }
+    if let Some(version) = dependency.as_str().map(String::from) {
+        let mut table = toml_edit::InlineTable::new();
+        table.insert("version", version.into());
+        *dependency = toml_edit::value(table);
+    }
+    let Some(table) = dependency.as_table_like_mut() else {
+        return Vec::new();
+    };
+    if table.get("features").and_then(|f| f.as_array()).is_none() {
+        table.insert("features", toml_edit::value(toml_edit::Array::new()));
+    }
+    let Some(array) = table.get_mut("features").and_then(|f| f.as_array_mut()) else {
+        return Vec::new();
+    };
+
+    let mut enabled = Vec::new();
+    for feature in features {
+        if !array.iter().any(|f| f.as_str() == Some(feature.as_str())) {
+            array.push(feature.as_str());
+            enabled.push(feature.clone());
         }
     }
+    enabled
 }
 
 #[cfg(test)]
@@ -248,6 +653,26 @@ mod tests {
     use super::*;
     use tempfile::TempDir;
 
+    #[test]
+    fn test_report_diagnostics_locate_panics() {
+        let report = TestReport {
+            output: "thread 'tests::adds' panicked at src/lib.rs:14:9:\nassertion failed".into(),
+            failed_tests: vec!["tests::adds".to_string()],
+            flaky: vec!["tests::slow".to_string()],
+            ..TestReport::new()
+        };
+
+        let diagnostics = report.diagnostics();
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].file, Some(PathBuf::from("src/lib.rs")));
+        assert_eq!(
+            (diagnostics[0].line, diagnostics[0].column),
+            (Some(14), Some(9))
+        );
+        assert_eq!(diagnostics[1].severity, Severity::Warning);
+    }
+
     #[test]
     fn test_test_report_creation() {
         let report = TestReport::new();
@@ -275,6 +700,55 @@ mod tests {
         assert!(!report.all_passed());
     }
 
+    #[tokio::test]
+    async fn test_scaffold_kinds_add_their_dev_dependency() {
+        let temp_dir = TempDir::new().unwrap();
+        let cargo_toml = temp_dir.path().join("Cargo.toml");
+        std::fs::write(
+            &cargo_toml,
+            "[package]\nname = \"demo\"\n\n[dependencies]\ntokio = { version = \"1\", features = [\"rt\"] }\n",
+        )
+        .unwrap();
+
+        let scaffold =
+            TestRunner::generate_test_scaffold_with("parser", temp_dir.path(), TestKind::Property)
+                .await
+                .unwrap();
+        assert_eq!(scaffold.added_dependency.as_deref(), Some("proptest"));
+        let content = std::fs::read_to_string(&scaffold.file).unwrap();
+        assert!(content.contains("proptest! {"));
+
+        let scaffold =
+            TestRunner::generate_test_scaffold_with("client", temp_dir.path(), TestKind::Async)
+                .await
+                .unwrap();
+        assert_eq!(scaffold.added_dependency, None);
+        assert_eq!(scaffold.enabled_features, ["macros", "time"]);
+        let content = std::fs::read_to_string(&scaffold.file).unwrap();
+        assert!(content.contains("#[tokio::test]"));
+
+        let manifest: toml::Value =
+            toml::from_str(&std::fs::read_to_string(&cargo_toml).unwrap()).unwrap();
+        assert_eq!(manifest["dev-dependencies"]["proptest"].as_str(), Some("1"));
+        assert!(manifest["dev-dependencies"].get("tokio").is_none());
+        let features: Vec<&str> = manifest["dependencies"]["tokio"]["features"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|f| f.as_str())
+            .collect();
+        assert_eq!(features, ["rt", "macros", "time"]);
+
+        // A bare version requirement gains the features too
+        let mut dependency = toml_edit::value("1");
+        let enabled = enable_features(&mut dependency, &["macros".to_string()]);
+        assert_eq!(enabled, ["macros"]);
+        assert_eq!(
+            dependency.to_string(),
+            r#"{ version = "1", features = ["macros"] }"#
+        );
+    }
+
     #[tokio::test]
     async fn test_generate_test_scaffold() {
         let temp_dir = TempDir::new().unwrap();
@@ -301,6 +775,23 @@ mod tests {
         assert_eq!(report.total_lines, 1000);
     }
 
+    #[test]
+    fn test_parse_output_of_several_binaries() {
+        let output = "running 2 tests\n\
+                      test net::tests::test_retry ... FAILED\n\
+                      test net::tests::test_parse ... ok\n\
+                      test result: FAILED. 1 passed; 1 failed; 0 ignored\n\
+                      running 2 tests\n\
+                      test slow ... ignored\n\
+                      test api ... ok\n\
+                      test result: ok. 1 passed; 0 failed; 1 ignored\n";
+        let mut report = TestReport::new();
+        TestRunner::parse_test_output(output, &mut report);
+
+        assert_eq!((report.total, report.passed, report.failed), (3, 2, 1));
+        assert_eq!(report.failed_tests, ["net::tests::test_retry"]);
+    }
+
     #[test]
     fn test_parse_test_output() {
         let output = "test result: ok. 5 passed; 0 failed";
@@ -0,0 +1,244 @@
+//! Test fixtures and golden files
+//!
+//! A [`Fixture`] is a temporary directory that tests fill with what they
+//! need: a project generated from a template, `.env` files, a migrations
+//! directory with a seed script, or arbitrary files. The directory is
+//! deleted when the fixture is dropped, unless [`Fixture::keep`] persists it
+//! for debugging.
+//!
+//! [`assert_golden`] compares output against a file checked into the
+//! repository. Running the tests with `FORGEKIT_UPDATE_GOLDEN=1` writes the
+//! actual output to the golden files instead, so they can be reviewed and
+//! committed.
+
+use crate::error::ForgeKitError;
+use crate::project::{self, InitOptions, Vcs};
+use crate::templates::TemplateType;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// Environment variable that makes golden assertions rewrite their files
+pub const UPDATE_GOLDEN_VAR: &str = "FORGEKIT_UPDATE_GOLDEN";
+
+/// Name of the seed script written next to the migrations
+pub const SEED_FILE: &str = "seed.sql";
+
+/// Author recorded in the forgekit.toml of project fixtures
+const FIXTURE_AUTHOR: &str = "ForgeKit Tests <tests@forgekit.invalid>";
+
+/// A temporary directory holding test data, deleted on drop
+#[derive(Debug)]
+pub struct Fixture {
+    dir: TempDir,
+    root: PathBuf,
+}
+
+impl Fixture {
+    /// Create an empty fixture
+    pub fn new() -> Result<Self, ForgeKitError> {
+        let dir = TempDir::new()?;
+        let root = dir.path().to_path_buf();
+        Ok(Self { dir, root })
+    }
+
+    /// Create a fixture holding a project generated from `template`
+    ///
+    /// The project is created like `forgekit new` does, without git and
+    /// with a fixed author so generated files do not depend on the machine.
+    /// It lives in a directory named after it; [`Fixture::path`] points at it.
+    pub async fn project(name: &str, template: TemplateType) -> Result<Self, ForgeKitError> {
+        let dir = TempDir::new()?;
+        let root = dir.path().join(name);
+        let options = InitOptions {
+            template: Some(template),
+            vcs: Vcs::None,
+            authors: vec![FIXTURE_AUTHOR.to_string()],
+            ..Default::default()
+        };
+        project::init_with_options(name, &root, &options).await?;
+        Ok(Self { dir, root })
+    }
+
+    /// Root of the fixture, or of the project for project fixtures
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+
+    /// Path of `relative` inside the fixture
+    pub fn join(&self, relative: impl AsRef<Path>) -> PathBuf {
+        self.root.join(relative)
+    }
+
+    /// Write a file, creating its parent directories
+    pub fn file(
+        &self,
+        relative: impl AsRef<Path>,
+        contents: impl AsRef<[u8]>,
+    ) -> Result<PathBuf, ForgeKitError> {
+        let path = self.join(relative);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, contents)?;
+        Ok(path)
+    }
+
+    /// Write `.env`, or `.env.<env>` when an environment is given
+    pub fn env_file(
+        &self,
+        env: Option<&str>,
+        vars: &[(&str, &str)],
+    ) -> Result<PathBuf, ForgeKitError> {
+        let name = match env {
+            Some(env) => format!(".env.{}", env),
+            None => ".env".to_string(),
+        };
+        let contents: String = vars
+            .iter()
+            .map(|(key, value)| format!("{}={}\n", key, value))
+            .collect();
+        self.file(name, contents)
+    }
+
+    /// Write numbered migrations and an optional seed script
+    ///
+    /// Migrations are named like the ones `forgekit migrate create` makes,
+    /// with increasing stand-in timestamps so they apply in the given order.
+    /// Returns the migration files, without the seed script.
+    pub fn migrations(
+        &self,
+        migrations: &[(&str, &str)],
+        seed: Option<&str>,
+    ) -> Result<Vec<PathBuf>, ForgeKitError> {
+        let dir = Path::new("migrations");
+        let files = migrations
+            .iter()
+            .enumerate()
+            .map(|(index, (name, sql))| {
+                self.file(dir.join(format!("{:014}_{}.sql", index + 1, name)), sql)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(seed) = seed {
+            self.file(dir.join(SEED_FILE), seed)?;
+        }
+        Ok(files)
+    }
+
+    /// Persist the fixture instead of deleting it, returning its root
+    pub fn keep(self) -> PathBuf {
+        let _ = self.dir.keep();
+        self.root
+    }
+}
+
+/// Panic unless `actual` matches the golden file at `path`
+///
+/// Line endings are normalized before comparing. With
+/// `FORGEKIT_UPDATE_GOLDEN=1` the file is written instead.
+#[track_caller]
+pub fn assert_golden(path: impl AsRef<Path>, actual: &str) {
+    let update = std::env::var(UPDATE_GOLDEN_VAR).is_ok_and(|v| v == "1");
+    if let Err(message) = check_golden(path.as_ref(), actual, update) {
+        panic!("{}", message);
+    }
+}
+
+/// Panic unless `value`, as pretty JSON, matches the golden file at `path`
+#[track_caller]
+pub fn assert_golden_json<T: Serialize>(path: impl AsRef<Path>, value: &T) {
+    let json = serde_json::to_string_pretty(value).expect("value serializes to JSON");
+    assert_golden(path, &format!("{}\n", json));
+}
+
+fn check_golden(path: &Path, actual: &str, update: bool) -> Result<(), String> {
+    if update {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        return std::fs::write(path, actual).map_err(|e| e.to_string());
+    }
+
+    let expected = std::fs::read_to_string(path).map_err(|e| {
+        format!(
+            "cannot read golden file {:?} ({}); run with {}=1 to create it",
+            path, e, UPDATE_GOLDEN_VAR
+        )
+    })?;
+    let (expected, actual) = (expected.replace("\r\n", "\n"), actual.replace("\r\n", "\n"));
+    if expected == actual {
+        return Ok(());
+    }
+    Err(format!(
+        "output does not match golden file {:?}; run with {}=1 to update it\n{}",
+        path,
+        UPDATE_GOLDEN_VAR,
+        line_diff(&expected, &actual)
+    ))
+}
+
+/// Differing lines, `-` from the golden file and `+` from the output
+fn line_diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+    let mut diff = String::new();
+    for line in 0..expected.len().max(actual.len()) {
+        let (old, new) = (expected.get(line), actual.get(line));
+        if old == new {
+            continue;
+        }
+        diff.push_str(&format!("@@ line {}\n", line + 1));
+        if let Some(old) = old {
+            diff.push_str(&format!("-{}\n", old));
+        }
+        if let Some(new) = new {
+            diff.push_str(&format!("+{}\n", new));
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ProjectConfig;
+    use crate::env_manager::EnvManager;
+
+    #[tokio::test]
+    async fn test_fixtures_are_laid_out_and_golden_files_compared() {
+        let fixture = Fixture::project("demo", TemplateType::Cli).await.unwrap();
+        let config = ProjectConfig::load(fixture.join("forgekit.toml")).unwrap();
+        assert_eq!(config.name, "demo");
+
+        fixture.env_file(None, &[("PORT", "8080")]).unwrap();
+        fixture
+            .env_file(Some("dev"), &[("PORT", "3000"), ("DEBUG", "1")])
+            .unwrap();
+        let env = EnvManager::load_for_environment("dev", fixture.path()).unwrap();
+        assert_eq!(env.get("PORT"), Some("3000"));
+        assert_eq!(env.get("DEBUG"), Some("1"));
+
+        let files = fixture
+            .migrations(
+                &[
+                    ("users", "CREATE TABLE users;"),
+                    ("posts", "CREATE TABLE posts;"),
+                ],
+                Some("INSERT INTO users VALUES (1);"),
+            )
+            .unwrap();
+        assert!(files[0] < files[1]);
+        assert!(fixture.join("migrations").join(SEED_FILE).is_file());
+
+        let golden = fixture.join("golden/output.txt");
+        assert!(check_golden(&golden, "a\nb\n", false).is_err());
+        check_golden(&golden, "a\nb\n", true).unwrap();
+        check_golden(&golden, "a\r\nb\r\n", false).unwrap();
+        let mismatch = check_golden(&golden, "a\nc\n", false).unwrap_err();
+        assert!(mismatch.contains("@@ line 2\n-b\n+c\n"));
+
+        let root = fixture.path().parent().unwrap().to_path_buf();
+        drop(fixture);
+        assert!(!root.exists());
+    }
+}
@@ -0,0 +1,388 @@
+//! End-to-end harness for packaged apps
+//!
+//! [`IntegrationHarness`] takes a project through the same steps as a
+//! release: it builds and packages the project, installs the .mox into a
+//! temporary prefix with the local installer, and launches the installed
+//! binary from its data directory. The running app is driven through
+//! [`RunningApp`], which writes to its stdin, waits for output and sends
+//! HTTP requests to it; [`AppOutput`] carries the assertions on what the
+//! app printed and how it exited.
+
+use crate::builder::{self, BuildOptions};
+use crate::error::ForgeKitError;
+use crate::installer::{self, InstallOptions, InstalledApp};
+use crate::packager;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::task::JoinHandle;
+
+/// How often output and HTTP readiness are polled
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// An app built, packaged and installed into a temporary prefix
+///
+/// The prefix is deleted when the harness is dropped.
+pub struct IntegrationHarness {
+    prefix: TempDir,
+    app: InstalledApp,
+    env: Vec<(String, String)>,
+}
+
+impl IntegrationHarness {
+    /// Build, package and install the project at `project_path`
+    pub async fn new(project_path: impl AsRef<Path>) -> Result<Self, ForgeKitError> {
+        let project_path = project_path.as_ref();
+        builder::build_with_options(project_path, &BuildOptions::default()).await?;
+        let package = packager::package(project_path).await?;
+        Self::from_package(&package).await
+    }
+
+    /// Install an already packaged app
+    pub async fn from_package(package: impl AsRef<Path>) -> Result<Self, ForgeKitError> {
+        let prefix = TempDir::new()?;
+        let app =
+            installer::install(package.as_ref(), prefix.path(), &InstallOptions::default()).await?;
+        Ok(Self {
+            prefix,
+            app,
+            env: Vec::new(),
+        })
+    }
+
+    /// Set an environment variable for every launch
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    /// The installed app
+    pub fn app(&self) -> &InstalledApp {
+        &self.app
+    }
+
+    /// The prefix the app is installed into
+    pub fn prefix(&self) -> &Path {
+        self.prefix.path()
+    }
+
+    /// Path of the installed binary
+    pub fn binary(&self) -> PathBuf {
+        self.app.app_dir.join("bin").join(&self.app.name)
+    }
+
+    /// Start the app with `args`
+    pub async fn launch(&self, args: &[&str]) -> Result<RunningApp, ForgeKitError> {
+        let mut child = Command::new(self.binary())
+            .args(args)
+            .current_dir(&self.app.data_dir)
+            .env("FORGEKIT_APP_DIR", &self.app.app_dir)
+            .env("FORGEKIT_DATA_DIR", &self.app.data_dir)
+            .envs(self.env.iter().map(|(k, v)| (k, v)))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        let stdin = child.stdin.take();
+        let stdout = Arc::new(Mutex::new(String::new()));
+        let stderr = Arc::new(Mutex::new(String::new()));
+        let readers = [
+            child
+                .stdout
+                .take()
+                .map(|out| collect(out, Arc::clone(&stdout))),
+            child
+                .stderr
+                .take()
+                .map(|err| collect(err, Arc::clone(&stderr))),
+        ];
+        Ok(RunningApp {
+            child,
+            stdin,
+            stdout,
+            stderr,
+            readers: readers.into_iter().flatten().collect(),
+        })
+    }
+
+    /// Run the app to completion, feeding it `input` on stdin
+    pub async fn run(&self, args: &[&str], input: &str) -> Result<AppOutput, ForgeKitError> {
+        let mut app = self.launch(args).await?;
+        app.send(input).await?;
+        app.wait().await
+    }
+}
+
+/// A launched app
+pub struct RunningApp {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stdout: Arc<Mutex<String>>,
+    stderr: Arc<Mutex<String>>,
+    readers: Vec<JoinHandle<()>>,
+}
+
+impl RunningApp {
+    /// Write `input` to the app's stdin
+    pub async fn send(&mut self, input: &str) -> Result<(), ForgeKitError> {
+        if let Some(stdin) = self.stdin.as_mut() {
+            stdin.write_all(input.as_bytes()).await?;
+            stdin.flush().await?;
+        }
+        Ok(())
+    }
+
+    /// Write one line to the app's stdin
+    pub async fn send_line(&mut self, line: &str) -> Result<(), ForgeKitError> {
+        self.send(&format!("{}\n", line)).await
+    }
+
+    /// Stdout printed so far
+    pub fn stdout(&self) -> String {
+        self.stdout.lock().unwrap().clone()
+    }
+
+    /// Wait until stdout contains `needle`, returning stdout so far
+    pub async fn expect_output(
+        &self,
+        needle: &str,
+        timeout: Duration,
+    ) -> Result<String, ForgeKitError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let stdout = self.stdout();
+            if stdout.contains(needle) {
+                return Ok(stdout);
+            }
+            if Instant::now() >= deadline {
+                return Err(ForgeKitError::TestFailed(format!(
+                    "no {:?} in app output after {:?}; stdout was:\n{}",
+                    needle, timeout, stdout
+                )));
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// Send a GET request to the app on `port`, retrying until it listens
+    pub async fn get(
+        &self,
+        port: u16,
+        path: &str,
+        timeout: Duration,
+    ) -> Result<HttpResponse, ForgeKitError> {
+        self.request(reqwest::Method::GET, port, path, None, timeout)
+            .await
+    }
+
+    /// Send a POST request to the app on `port`, retrying until it listens
+    pub async fn post(
+        &self,
+        port: u16,
+        path: &str,
+        body: &str,
+        timeout: Duration,
+    ) -> Result<HttpResponse, ForgeKitError> {
+        self.request(reqwest::Method::POST, port, path, Some(body), timeout)
+            .await
+    }
+
+    async fn request(
+        &self,
+        method: reqwest::Method,
+        port: u16,
+        path: &str,
+        body: Option<&str>,
+        timeout: Duration,
+    ) -> Result<HttpResponse, ForgeKitError> {
+        let url = format!("http://127.0.0.1:{}/{}", port, path.trim_start_matches('/'));
+        let client = reqwest::Client::new();
+        let deadline = Instant::now() + timeout;
+        loop {
+            let mut request = client.request(method.clone(), &url);
+            if let Some(body) = body {
+                request = request.body(body.to_string());
+            }
+            match request.send().await {
+                Ok(response) => {
+                    return Ok(HttpResponse {
+                        status: response.status().as_u16(),
+                        body: response.text().await?,
+                    })
+                }
+                Err(e) if e.is_connect() && Instant::now() < deadline => {
+                    tokio::time::sleep(POLL_INTERVAL).await;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Close stdin and wait for the app to exit
+    pub async fn wait(mut self) -> Result<AppOutput, ForgeKitError> {
+        drop(self.stdin.take());
+        let status = self.child.wait().await?;
+        self.finish(status).await
+    }
+
+    /// Kill the app, e.g. a service that does not exit on its own
+    pub async fn stop(mut self) -> Result<AppOutput, ForgeKitError> {
+        self.child.start_kill()?;
+        let status = self.child.wait().await?;
+        self.finish(status).await
+    }
+
+    async fn finish(self, status: ExitStatus) -> Result<AppOutput, ForgeKitError> {
+        for reader in self.readers {
+            let _ = reader.await;
+        }
+        let stdout = self.stdout.lock().unwrap().clone();
+        let stderr = self.stderr.lock().unwrap().clone();
+        Ok(AppOutput {
+            status,
+            stdout,
+            stderr,
+        })
+    }
+}
+
+/// Response to a request sent to the app
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    /// HTTP status code
+    pub status: u16,
+    /// Response body
+    pub body: String,
+}
+
+/// How an app run ended
+#[derive(Debug, Clone)]
+pub struct AppOutput {
+    /// Exit status of the app
+    pub status: ExitStatus,
+    /// Everything the app printed to stdout
+    pub stdout: String,
+    /// Everything the app printed to stderr
+    pub stderr: String,
+}
+
+impl AppOutput {
+    /// Exit code, `None` when the app was killed by a signal
+    pub fn code(&self) -> Option<i32> {
+        self.status.code()
+    }
+
+    /// Panic unless the app exited successfully
+    #[track_caller]
+    pub fn assert_success(&self) -> &Self {
+        assert!(
+            self.status.success(),
+            "app exited with {}\nstderr:\n{}",
+            self.status,
+            self.stderr
+        );
+        self
+    }
+
+    /// Panic unless the app exited with `code`
+    #[track_caller]
+    pub fn assert_exit_code(&self, code: i32) -> &Self {
+        assert_eq!(
+            self.code(),
+            Some(code),
+            "unexpected exit status\nstderr:\n{}",
+            self.stderr
+        );
+        self
+    }
+
+    /// Panic unless stdout contains `needle`
+    #[track_caller]
+    pub fn assert_stdout_contains(&self, needle: &str) -> &Self {
+        assert!(
+            self.stdout.contains(needle),
+            "stdout does not contain {:?}:\n{}",
+            needle,
+            self.stdout
+        );
+        self
+    }
+
+    /// Panic unless stderr contains `needle`
+    #[track_caller]
+    pub fn assert_stderr_contains(&self, needle: &str) -> &Self {
+        assert!(
+            self.stderr.contains(needle),
+            "stderr does not contain {:?}:\n{}",
+            needle,
+            self.stderr
+        );
+        self
+    }
+}
+
+/// Append everything read from `source` to `sink` until it closes
+fn collect(
+    mut source: impl AsyncRead + Unpin + Send + 'static,
+    sink: Arc<Mutex<String>>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut buffer = [0u8; 8 * 1024];
+        while let Ok(read) = source.read(&mut buffer).await {
+            if read == 0 {
+                break;
+            }
+            sink.lock()
+                .unwrap()
+                .push_str(&String::from_utf8_lossy(&buffer[..read]));
+        }
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::config::ProjectConfig;
+    use crate::packager::BINARY_ENTRY;
+    use crate::permissions::PERMISSIONS_MANIFEST;
+    use std::io::Write;
+    use zip::{write::FileOptions, ZipWriter};
+
+    #[tokio::test]
+    async fn test_harness_runs_installed_app() {
+        let temp_dir = TempDir::new().unwrap();
+        let package = temp_dir.path().join("echo.mox");
+        let config = ProjectConfig {
+            name: "echo".to_string(),
+            ..Default::default()
+        };
+        let script = "#!/bin/sh\nread line\necho \"got $line in $PWD\"\nexit 3\n";
+        let mut zip = ZipWriter::new(std::fs::File::create(&package).unwrap());
+        for (name, contents) in [
+            (BINARY_ENTRY, script.to_string()),
+            ("forgekit.toml", toml::to_string(&config).unwrap()),
+            (PERMISSIONS_MANIFEST, String::new()),
+        ] {
+            zip.start_file(name, FileOptions::default()).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let harness = IntegrationHarness::from_package(&package).await.unwrap();
+        let mut app = harness.launch(&[]).await.unwrap();
+        app.send_line("ping").await.unwrap();
+        app.expect_output("got ping", Duration::from_secs(5))
+            .await
+            .unwrap();
+        let output = app.wait().await.unwrap();
+        output
+            .assert_exit_code(3)
+            .assert_stdout_contains(&harness.app().data_dir.display().to_string());
+    }
+}
@@ -0,0 +1,657 @@
+//! Toolchain module
+//!
+//! Stock Rust toolchains have no `ledokoz` target, so `cargo build --target
+//! ledokoz` fails on them. This module ships the target spec and writes it
+//! to `.forgekit/toolchain/ledokoz.json`, where builds point cargo at it.
+//! No prebuilt std exists for a custom target, so std is compiled from
+//! source with `-Z build-std`. That needs a nightly toolchain with the
+//! `rust-src` component; [`install`] sets it up through rustup, and
+//! [`status`] reports what is missing and where the toolchain's sysroot is.
+//!
+//! For local testing the builder can compile for a host-compatible triple
+//! instead: `[build.toolchain] fallback_target`, or the host's own triple.
+//!
+//! A project can pin its toolchain with `[toolchain] channel`. Ledokoz builds
+//! then run the pinned toolchain when it is a nightly, and [`verify_pin`] makes every build check
+//! that the rustc it runs is the pinned one. [`write_rust_toolchain_file`]
+//! writes the pin to rust-toolchain.toml so rustup selects it for plain
+//! `cargo` commands too.
+
+use crate::atomic;
+use crate::config::{ProjectConfig, ToolchainConfig};
+use crate::error::ForgeKitError;
+use crate::platform;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// Target spec of Ledokoz OS, shipped with ForgeKit
+pub const TARGET_SPEC: &str = r#"{
+  "llvm-target": "x86_64-unknown-linux-gnu",
+  "metadata": {
+    "description": "64-bit Ledokoz OS",
+    "host_tools": false,
+    "std": true
+  },
+  "data-layout": "e-m:e-p270:32:32-p271:32:32-p272:64:64-i64:64-i128:128-f80:128-n8:16:32:64-S128",
+  "arch": "x86_64",
+  "cpu": "x86-64",
+  "target-pointer-width": 64,
+  "max-atomic-width": 64,
+  "os": "linux",
+  "env": "gnu",
+  "vendor": "ledokoz",
+  "target-family": ["unix"],
+  "linker-flavor": "gnu-cc",
+  "pre-link-args": {
+    "gnu-cc": ["-m64"]
+  },
+  "dynamic-linking": true,
+  "has-rpath": true,
+  "has-thread-local": true,
+  "position-independent-executables": true,
+  "static-position-independent-executables": true,
+  "crt-static-respected": true,
+  "relro-level": "full",
+  "plt-by-default": false,
+  "default-uwtable": true,
+  "stack-probes": {
+    "kind": "inline"
+  },
+  "supported-split-debuginfo": ["packed", "unpacked", "off"]
+}
+"#;
+
+/// Toolchain components building for the ledokoz target needs
+pub const REQUIRED_COMPONENTS: &[&str] = &["rust-src"];
+
+/// File rustup reads the toolchain of a directory from
+pub const RUST_TOOLCHAIN_FILE: &str = "rust-toolchain.toml";
+
+/// Crates of std compiled for the ledokoz target
+const BUILD_STD: &str = "build-std=std,panic_abort";
+
+/// Where the shipped target spec is written in a project
+pub fn spec_path(project_path: &Path) -> PathBuf {
+    project_path
+        .join(".forgekit")
+        .join("toolchain")
+        .join(format!("{}.json", platform::TARGET))
+}
+
+/// Write the shipped target spec into the project, unless it is up to date
+pub fn write_spec(project_path: &Path) -> Result<PathBuf, ForgeKitError> {
+    let path = spec_path(project_path);
+    if std::fs::read_to_string(&path).ok().as_deref() != Some(TARGET_SPEC) {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        atomic::write(&path, TARGET_SPEC)?;
+    }
+    Ok(path)
+}
+
+/// What a project is compiled for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompileTarget {
+    /// The ledokoz target, with std built from source
+    Ledokoz {
+        /// Target spec passed to cargo
+        spec: PathBuf,
+        /// Toolchain building it
+        channel: String,
+    },
+    /// A host-compatible triple, for local testing
+    Host(String),
+}
+
+impl CompileTarget {
+    /// Target of a build, the host-compatible one when `host` is set
+    ///
+    /// The ledokoz target uses the project's own spec when
+    /// `[build.toolchain] target_spec` names one, and the shipped spec
+    /// otherwise.
+    pub async fn resolve(
+        project_path: &Path,
+        config: &ToolchainConfig,
+        host: bool,
+    ) -> Result<Self, ForgeKitError> {
+        if host {
+            let triple = match &config.fallback_target {
+                Some(triple) => triple.clone(),
+                None => host_triple().await?,
+            };
+            return Ok(CompileTarget::Host(triple));
+        }
+
+        let spec = match &config.target_spec {
+            Some(spec) => {
+                let spec = project_path.join(spec);
+                if !spec.is_file() {
+                    return Err(ForgeKitError::InvalidConfig(format!(
+                        "target spec {:?} from [build.toolchain] does not exist",
+                        spec
+                    )));
+                }
+                spec
+            }
+            None => write_spec(project_path)?,
+        };
+        Ok(CompileTarget::Ledokoz {
+            spec,
+            channel: config.channel.clone(),
+        })
+    }
+
+    /// Directory under the cargo target directory holding the artifacts
+    ///
+    /// Cargo names it after the triple, or after the file of a spec.
+    pub fn dir_name(&self) -> String {
+        match self {
+            CompileTarget::Ledokoz { spec, .. } => spec
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_else(|| platform::TARGET.to_string()),
+            CompileTarget::Host(triple) => triple.clone(),
+        }
+    }
+
+    /// Arguments selecting the target, added to `cargo build`
+    pub fn cargo_args(&self) -> Vec<String> {
+        match self {
+            CompileTarget::Ledokoz { spec, .. } => vec![
+                "--target".to_string(),
+                spec.to_string_lossy().to_string(),
+                "-Z".to_string(),
+                BUILD_STD.to_string(),
+                "-Z".to_string(),
+                "json-target-spec".to_string(),
+            ],
+            CompileTarget::Host(triple) => vec!["--target".to_string(), triple.clone()],
+        }
+    }
+
+    /// Environment variables cargo runs with
+    pub fn env(&self) -> Vec<(&'static str, String)> {
+        match self {
+            CompileTarget::Ledokoz { channel, .. } => vec![("RUSTUP_TOOLCHAIN", channel.clone())],
+            CompileTarget::Host(_) => Vec::new(),
+        }
+    }
+
+    /// Whether this is a host build rather than one for Ledokoz
+    pub fn is_host(&self) -> bool {
+        matches!(self, CompileTarget::Host(_))
+    }
+}
+
+/// State of the toolchain building the ledokoz target
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolchainStatus {
+    /// Toolchain channel from `[build.toolchain]`
+    pub channel: String,
+    /// Whether rustup has the toolchain installed
+    pub installed: bool,
+    /// Required components the toolchain lacks
+    pub missing_components: Vec<String>,
+    /// Sysroot of the toolchain
+    pub sysroot: Option<PathBuf>,
+    /// Standard library sources `-Z build-std` compiles
+    pub rust_src: Option<PathBuf>,
+    /// Triple of the host, the default fallback target
+    pub host: Option<String>,
+}
+
+impl ToolchainStatus {
+    /// Whether the ledokoz target can be built
+    pub fn is_ready(&self) -> bool {
+        self.installed && self.missing_components.is_empty()
+    }
+}
+
+/// Check the toolchain building the ledokoz target
+pub async fn status(config: &ToolchainConfig) -> ToolchainStatus {
+    let channel = &config.channel;
+    let components = tool_output(
+        "rustup",
+        &["component", "list", "--installed", "--toolchain", channel],
+        None,
+        None,
+    )
+    .await;
+    let sysroot = tool_output("rustc", &["--print", "sysroot"], Some(channel), None)
+        .await
+        .ok()
+        .map(|output| PathBuf::from(output.trim()));
+    let rust_src = sysroot
+        .as_deref()
+        .map(rust_src_dir)
+        .filter(|dir| dir.is_dir());
+
+    ToolchainStatus {
+        channel: channel.clone(),
+        installed: components.is_ok(),
+        missing_components: components
+            .map(|installed| missing_components(&installed))
+            .unwrap_or_else(|_| REQUIRED_COMPONENTS.iter().map(|c| c.to_string()).collect()),
+        sysroot,
+        rust_src,
+        host: host_triple().await.ok(),
+    }
+}
+
+/// Install the toolchain and components, and report the result
+pub async fn install(config: &ToolchainConfig) -> Result<ToolchainStatus, ForgeKitError> {
+    let mut args = vec![
+        "toolchain",
+        "install",
+        &config.channel,
+        "--profile",
+        "minimal",
+    ];
+    for component in REQUIRED_COMPONENTS {
+        args.extend(["--component", component]);
+    }
+    tool_output("rustup", &args, None, None).await?;
+
+    let status = status(config).await;
+    if !status.is_ready() {
+        return Err(ForgeKitError::Toolchain(format!(
+            "{} is missing {} after installing",
+            config.channel,
+            status.missing_components.join(", ")
+        )));
+    }
+    Ok(status)
+}
+
+/// Triple of the machine forgekit runs on
+pub async fn host_triple() -> Result<String, ForgeKitError> {
+    let output = tool_output("rustc", &["-vV"], None, None).await?;
+    output
+        .lines()
+        .find_map(|line| line.strip_prefix("host: "))
+        .map(|host| host.trim().to_string())
+        .ok_or_else(|| ForgeKitError::Toolchain("rustc -vV did not report a host".to_string()))
+}
+
+/// Version of a rustc, as reported by `rustc -vV`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RustcVersion {
+    /// Release, like `1.78.0` or `1.80.0-nightly`
+    pub release: String,
+    /// Date of the commit the compiler was built from
+    pub commit_date: Option<String>,
+}
+
+impl RustcVersion {
+    /// Parse the output of `rustc -vV`
+    pub fn parse(output: &str) -> Option<Self> {
+        let field = |name: &str| {
+            output
+                .lines()
+                .find_map(|line| line.strip_prefix(name))
+                .map(|value| value.trim().to_string())
+        };
+        Some(Self {
+            release: field("release: ")?,
+            commit_date: field("commit-date: ").filter(|date| date != "unknown"),
+        })
+    }
+
+    /// Whether this is a nightly compiler
+    pub fn is_nightly(&self) -> bool {
+        self.release.ends_with("-nightly") || self.release.ends_with("-dev")
+    }
+
+    /// Whether this compiler is what a pinned channel names
+    ///
+    /// Channels are the ones rustup accepts: `stable`, `beta`, `nightly`,
+    /// a version such as `1.78` or `1.78.0`, or a dated channel such as
+    /// `nightly-2024-05-01`, each optionally followed by a host triple as in
+    /// `1.78-x86_64-unknown-linux-gnu`. A nightly is dated the day after the
+    /// commit it is built from, so either day matches. Dates of beta and
+    /// stable releases, and the host, are not checked.
+    pub fn satisfies(&self, channel: &str) -> bool {
+        let (number, pre) = match self.release.split_once('-') {
+            Some((number, pre)) => (number, Some(pre)),
+            None => (self.release.as_str(), None),
+        };
+        let (name, date) = split_channel(channel);
+        match name {
+            "stable" => pre.is_none(),
+            "beta" => pre.is_some_and(|pre| pre.starts_with("beta")),
+            "nightly" => self.is_nightly() && date.is_none_or(|date| self.built_for(date)),
+            version => {
+                date.is_none()
+                    && pre.is_none()
+                    && (number == version || number.starts_with(&format!("{}.", version)))
+            }
+        }
+    }
+
+    /// Whether the commit date fits a nightly dated `date`
+    fn built_for(&self, date: &str) -> bool {
+        let parse = |date: &str| chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").ok();
+        match (parse(date), self.commit_date.as_deref().and_then(parse)) {
+            (Some(date), Some(commit)) => {
+                commit <= date && date - commit <= chrono::TimeDelta::days(1)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Name and date of a rustup channel, without its host triple
+fn split_channel(channel: &str) -> (&str, Option<&str>) {
+    let (name, rest) = channel.split_once('-').unwrap_or((channel, ""));
+    let date = rest.get(..10).filter(|date| {
+        chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok()
+            && (rest.len() == 10 || rest[10..].starts_with('-'))
+    });
+    (name, date)
+}
+
+/// Whether a rustup channel names a nightly toolchain
+pub fn is_nightly_channel(channel: &str) -> bool {
+    split_channel(channel).0 == "nightly"
+}
+
+/// Version of the rustc rustup selects in `dir`, or of `channel` when given
+pub async fn rustc_version(
+    dir: &Path,
+    channel: Option<&str>,
+) -> Result<RustcVersion, ForgeKitError> {
+    let output = tool_output("rustc", &["-vV"], channel, Some(dir)).await?;
+    RustcVersion::parse(&output)
+        .ok_or_else(|| ForgeKitError::Toolchain("rustc -vV did not report a release".to_string()))
+}
+
+/// Check that the rustc a build runs is the toolchain the project pins
+///
+/// Ledokoz builds need a nightly to build std, so they run a nightly pin
+/// itself and otherwise the `[build.toolchain]` channel; a stable pin then
+/// only applies to host builds. Host builds run whatever rustup selects in
+/// the project, so rust-toolchain.toml or an override can disagree with the
+/// pin.
+pub async fn verify_pin(
+    project_path: &Path,
+    channel: &str,
+    target: &CompileTarget,
+) -> Result<RustcVersion, ForgeKitError> {
+    if let CompileTarget::Ledokoz {
+        channel: build_channel,
+        ..
+    } = target
+    {
+        let setting = if build_channel == channel {
+            format!("the project pins Rust {} in forgekit.toml", channel)
+        } else {
+            format!("[build.toolchain] channel is {}", build_channel)
+        };
+        let version = rustc_version(project_path, Some(build_channel))
+            .await
+            .map_err(|e| {
+                ForgeKitError::Toolchain(format!(
+                    "{}, which is not usable ({}); run `forgekit toolchain install`",
+                    setting, e
+                ))
+            })?;
+        if !version.is_nightly() {
+            return Err(ForgeKitError::Toolchain(format!(
+                "{}, but building std for the ledokoz target needs a nightly; use one such as `nightly-2024-05-01`, or build with `--host`",
+                setting
+            )));
+        }
+        return Ok(version);
+    }
+
+    let version = rustc_version(project_path, None).await.map_err(|e| {
+        ForgeKitError::Toolchain(format!(
+            "the project pins Rust {} in forgekit.toml, but rustc cannot run ({}); run `rustup toolchain install {}`",
+            channel, e, channel
+        ))
+    })?;
+    if !version.satisfies(channel) {
+        return Err(ForgeKitError::Toolchain(format!(
+            "the project pins Rust {} in forgekit.toml, but the active rustc is {}; run `forgekit toolchain pin` to write {}, or `rustup override set {}`",
+            channel, version.release, RUST_TOOLCHAIN_FILE, channel
+        )));
+    }
+    Ok(version)
+}
+
+/// Pin the project to `channel`, or keep its current pin, and write the pin
+/// to rust-toolchain.toml
+///
+/// Returns the pinned channel.
+pub fn pin(project_path: &Path, channel: Option<&str>) -> Result<String, ForgeKitError> {
+    let config_path = project_path.join("forgekit.toml");
+    let mut config = ProjectConfig::load(&config_path)?;
+    let channel = match channel {
+        Some(channel) => {
+            if config.toolchain.channel.as_deref() != Some(channel) {
+                config.toolchain.channel = Some(channel.to_string());
+                config.save(&config_path)?;
+            }
+            channel.to_string()
+        }
+        None => config.toolchain.channel.ok_or_else(|| {
+            ForgeKitError::InvalidConfig(
+                "no toolchain is pinned in [toolchain]; name a channel such as `1.78`".to_string(),
+            )
+        })?,
+    };
+    write_rust_toolchain_file(project_path, &channel)?;
+    Ok(channel)
+}
+
+/// Set the channel in the project's rust-toolchain.toml, creating it if needed
+///
+/// Other settings in an existing file are kept; the components ledokoz
+/// builds need are added to its list.
+pub fn write_rust_toolchain_file(
+    project_path: &Path,
+    channel: &str,
+) -> Result<PathBuf, ForgeKitError> {
+    let path = project_path.join(RUST_TOOLCHAIN_FILE);
+    let existing = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let mut file: toml_edit::DocumentMut = existing.parse().map_err(|e| {
+        ForgeKitError::InvalidConfig(format!("Invalid {}: {}", RUST_TOOLCHAIN_FILE, e))
+    })?;
+    let toolchain = file
+        .entry("toolchain")
+        .or_insert_with(toml_edit::table)
+        .as_table_mut()
+        .ok_or_else(|| {
+            ForgeKitError::InvalidConfig(format!(
+                "`toolchain` in {} is not a table",
+                RUST_TOOLCHAIN_FILE
+            ))
+        })?;
+    toolchain["channel"] = toml_edit::value(channel);
+    let components = toolchain
+        .entry("components")
+        .or_insert_with(|| toml_edit::value(toml_edit::Array::new()))
+        .as_array_mut()
+        .ok_or_else(|| {
+            ForgeKitError::InvalidConfig(format!(
+                "`toolchain.components` in {} is not an array",
+                RUST_TOOLCHAIN_FILE
+            ))
+        })?;
+    for component in REQUIRED_COMPONENTS {
+        if !components.iter().any(|c| c.as_str() == Some(component)) {
+            components.push(*component);
+        }
+    }
+
+    let contents = file.to_string();
+    if contents != existing {
+        atomic::write(&path, contents)?;
+    }
+    Ok(path)
+}
+
+/// Standard library sources inside a sysroot
+pub fn rust_src_dir(sysroot: &Path) -> PathBuf {
+    sysroot
+        .join("lib")
+        .join("rustlib")
+        .join("src")
+        .join("rust")
+        .join("library")
+}
+
+/// Required components absent from `rustup component list --installed`
+fn missing_components(installed: &str) -> Vec<String> {
+    REQUIRED_COMPONENTS
+        .iter()
+        .filter(|required| {
+            !installed.lines().any(|line| {
+                let line = line.trim();
+                line == **required || line.starts_with(&format!("{}-", required))
+            })
+        })
+        .map(|component| component.to_string())
+        .collect()
+}
+
+/// Stdout of a tool, run with `channel` as the rustup toolchain and in
+/// `dir` when given
+async fn tool_output(
+    program: &str,
+    args: &[&str],
+    channel: Option<&str>,
+    dir: Option<&Path>,
+) -> Result<String, ForgeKitError> {
+    let mut command = Command::new(program);
+    command.args(args);
+    if let Some(channel) = channel {
+        command.env("RUSTUP_TOOLCHAIN", channel);
+    }
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    let output = command
+        .output()
+        .await
+        .map_err(|e| ForgeKitError::Toolchain(format!("cannot run {}: {}", program, e)))?;
+    if !output.status.success() {
+        return Err(ForgeKitError::Toolchain(format!(
+            "{} {} failed: {}",
+            program,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_resolve_writes_spec_and_honours_overrides() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path();
+        let config = ToolchainConfig::default();
+
+        let target = CompileTarget::resolve(project, &config, false)
+            .await
+            .unwrap();
+        assert_eq!(target.dir_name(), platform::TARGET);
+        assert_eq!(
+            std::fs::read_to_string(spec_path(project)).unwrap(),
+            TARGET_SPEC
+        );
+        assert!(target.cargo_args().contains(&BUILD_STD.to_string()));
+        assert_eq!(target.env(), [("RUSTUP_TOOLCHAIN", config.channel.clone())]);
+
+        let custom = ToolchainConfig {
+            target_spec: Some("specs/ledokoz-arm.json".to_string()),
+            fallback_target: Some("x86_64-unknown-linux-musl".to_string()),
+            ..Default::default()
+        };
+        assert!(CompileTarget::resolve(project, &custom, false)
+            .await
+            .is_err());
+        std::fs::create_dir_all(project.join("specs")).unwrap();
+        std::fs::write(project.join("specs/ledokoz-arm.json"), "{}").unwrap();
+        let target = CompileTarget::resolve(project, &custom, false)
+            .await
+            .unwrap();
+        assert_eq!(target.dir_name(), "ledokoz-arm");
+
+        let host = CompileTarget::resolve(project, &custom, true)
+            .await
+            .unwrap();
+        assert!(host.is_host());
+        assert_eq!(host.cargo_args(), ["--target", "x86_64-unknown-linux-musl"]);
+        assert!(host.env().is_empty());
+
+        let installed = "rust-src\nrustc-x86_64-unknown-linux-gnu\n";
+        assert!(missing_components(installed).is_empty());
+        assert_eq!(
+            missing_components("cargo-x86_64-unknown-linux-gnu\n"),
+            ["rust-src"]
+        );
+    }
+
+    #[test]
+    fn test_pins_match_rustc_versions_and_rust_toolchain_file() {
+        let stable = RustcVersion::parse(
+            "rustc 1.78.0 (9b00956e5 2024-04-29)\nbinary: rustc\nrelease: 1.78.0\ncommit-date: 2024-04-29\n",
+        )
+        .unwrap();
+        assert!(stable.satisfies("1.78"));
+        assert!(stable.satisfies("1.78.0"));
+        assert!(stable.satisfies("stable"));
+        assert!(!stable.satisfies("1.7"));
+        assert!(!stable.satisfies("1.79"));
+        assert!(!stable.satisfies("nightly"));
+        assert!(stable.satisfies("1.78-x86_64-unknown-linux-gnu"));
+        assert!(stable.satisfies("stable-aarch64-apple-darwin"));
+        assert!(!stable.satisfies("1.79.0-x86_64-unknown-linux-gnu"));
+
+        let nightly = RustcVersion {
+            release: "1.80.0-nightly".to_string(),
+            commit_date: Some("2024-04-30".to_string()),
+        };
+        assert!(nightly.is_nightly());
+        assert!(nightly.satisfies("nightly"));
+        assert!(nightly.satisfies("nightly-2024-05-01"));
+        assert!(nightly.satisfies("nightly-2024-04-30"));
+        assert!(!nightly.satisfies("nightly-2024-05-02"));
+        assert!(!nightly.satisfies("1.80"));
+        assert!(!nightly.satisfies("beta"));
+        assert!(nightly.satisfies("nightly-x86_64-unknown-linux-gnu"));
+        assert!(nightly.satisfies("nightly-2024-05-01-x86_64-unknown-linux-gnu"));
+        assert!(!nightly.satisfies("nightly-2024-05-02-x86_64-unknown-linux-gnu"));
+        assert!(is_nightly_channel("nightly-2024-05-01"));
+        assert!(!is_nightly_channel("1.78"));
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(RUST_TOOLCHAIN_FILE);
+        std::fs::write(
+            &path,
+            "# team toolchain\n[toolchain]\nchannel = \"1.77\"\ncomponents = [\"clippy\"]\n",
+        )
+        .unwrap();
+        write_rust_toolchain_file(temp_dir.path(), "nightly-2024-05-01").unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(written.starts_with("# team toolchain\n"));
+        let file: toml::Table = toml::from_str(&written).unwrap();
+        assert_eq!(
+            file["toolchain"]["channel"].as_str(),
+            Some("nightly-2024-05-01")
+        );
+        assert_eq!(file["toolchain"]["components"].as_array().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,416 @@
+//! UI layout module
+//!
+//! This module validates the XML layouts in a project's `ui/` directory
+//! against the known widget set and compiles them into the compact binary
+//! `.mxui` format shipped inside .mox packages.
+
+use crate::error::ForgeKitError;
+use std::path::{Path, PathBuf};
+
+/// Magic bytes at the start of a compiled layout
+pub const MXUI_MAGIC: &[u8; 4] = b"MXUI";
+
+/// Compiled layout format version
+const MXUI_VERSION: u8 = 1;
+
+/// Attributes accepted on every widget
+const COMMON_ATTRIBUTES: &[&str] = &["id", "visible", "style"];
+
+/// A widget known to the Ledokoz UI runtime
+#[derive(Debug, Clone, Copy)]
+pub struct WidgetSpec {
+    /// Element name
+    pub name: &'static str,
+    /// Widget specific attributes
+    pub attributes: &'static [&'static str],
+    /// Whether the widget may contain other widgets
+    pub container: bool,
+}
+
+/// Widgets supported in layouts
+pub const WIDGETS: &[WidgetSpec] = &[
+    WidgetSpec {
+        name: "window",
+        attributes: &["title", "width", "height", "resizable"],
+        container: true,
+    },
+    WidgetSpec {
+        name: "layout",
+        attributes: &["type", "spacing", "padding", "align"],
+        container: true,
+    },
+    WidgetSpec {
+        name: "label",
+        attributes: &["text", "size", "color"],
+        container: false,
+    },
+    WidgetSpec {
+        name: "button",
+        attributes: &["text", "onclick", "enabled"],
+        container: false,
+    },
+    WidgetSpec {
+        name: "input",
+        attributes: &["placeholder", "value", "onchange", "password"],
+        container: false,
+    },
+    WidgetSpec {
+        name: "checkbox",
+        attributes: &["text", "checked", "onchange"],
+        container: false,
+    },
+    WidgetSpec {
+        name: "image",
+        attributes: &["src", "width", "height"],
+        container: false,
+    },
+    WidgetSpec {
+        name: "list",
+        attributes: &["items", "onselect"],
+        container: true,
+    },
+];
+
+/// Severity of a layout diagnostic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The layout cannot be compiled
+    Error,
+    /// The layout compiles but something is probably wrong
+    Warning,
+}
+
+/// A problem found in a layout
+#[derive(Debug, Clone)]
+pub struct UiDiagnostic {
+    /// Diagnostic severity
+    pub severity: Severity,
+    /// 1-based line number
+    pub line: u32,
+    /// 1-based column number
+    pub column: u32,
+    /// Description of the problem
+    pub message: String,
+}
+
+/// A layout compiled from a project's `ui/` directory
+#[derive(Debug, Clone)]
+pub struct CompiledLayout {
+    /// Source XML file
+    pub source: PathBuf,
+    /// Compiled `.mxui` file
+    pub output: PathBuf,
+    /// Warnings reported for the layout
+    pub warnings: Vec<UiDiagnostic>,
+}
+
+/// Validate a layout, returning every diagnostic found
+pub fn validate(xml: &str) -> Vec<UiDiagnostic> {
+    let document = match roxmltree::Document::parse(xml) {
+        Ok(document) => document,
+        Err(e) => {
+            let pos = e.pos();
+            return vec![UiDiagnostic {
+                severity: Severity::Error,
+                line: pos.row,
+                column: pos.col,
+                message: e.to_string(),
+            }];
+        }
+    };
+
+    let mut diagnostics = Vec::new();
+    let root = document.root_element();
+    if root.tag_name().name() != "window" {
+        diagnostics.push(diagnostic(
+            &document,
+            root,
+            Severity::Error,
+            format!(
+                "root element must be <window>, found <{}>",
+                root.tag_name().name()
+            ),
+        ));
+    }
+
+    for node in root.descendants().filter(|n| n.is_element()) {
+        let name = node.tag_name().name();
+        let Some(spec) = WIDGETS.iter().find(|w| w.name == name) else {
+            diagnostics.push(diagnostic(
+                &document,
+                node,
+                Severity::Error,
+                format!("unknown widget <{}>", name),
+            ));
+            continue;
+        };
+
+        for attribute in node.attributes() {
+            let attr = attribute.name();
+            if !spec.attributes.contains(&attr) && !COMMON_ATTRIBUTES.contains(&attr) {
+                diagnostics.push(diagnostic(
+                    &document,
+                    node,
+                    Severity::Warning,
+                    format!("unknown attribute '{}' on <{}>", attr, name),
+                ));
+            }
+        }
+
+        if !spec.container && node.children().any(|c| c.is_element()) {
+            diagnostics.push(diagnostic(
+                &document,
+                node,
+                Severity::Error,
+                format!("<{}> cannot contain other widgets", name),
+            ));
+        }
+    }
+
+    diagnostics
+}
+
+/// Compile a layout into the binary `.mxui` format
+///
+/// The format is the magic bytes, a version byte, a string table
+/// (`u16` count followed by `u16`-length-prefixed UTF-8 strings) and the
+/// widget tree in pre-order. Each widget is a `u16` name index, a `u8`
+/// attribute count with `u16` key/value index pairs and a `u16` child count.
+/// All integers are little-endian.
+pub fn compile(xml: &str) -> Result<Vec<u8>, ForgeKitError> {
+    let errors: Vec<String> = validate(xml)
+        .into_iter()
+        .filter(|d| d.severity == Severity::Error)
+        .map(|d| format!("{}:{}: {}", d.line, d.column, d.message))
+        .collect();
+    if !errors.is_empty() {
+        return Err(ForgeKitError::InvalidUi(errors.join("; ")));
+    }
+
+    let document =
+        roxmltree::Document::parse(xml).map_err(|e| ForgeKitError::InvalidUi(e.to_string()))?;
+
+    let mut strings = StringTable::default();
+    let mut tree = Vec::new();
+    encode_node(document.root_element(), &mut strings, &mut tree)?;
+
+    let mut output = Vec::with_capacity(tree.len() + 64);
+    output.extend_from_slice(MXUI_MAGIC);
+    output.push(MXUI_VERSION);
+    let count = u16::try_from(strings.values.len())
+        .map_err(|_| ForgeKitError::InvalidUi("layout has too many strings".to_string()))?;
+    output.extend_from_slice(&count.to_le_bytes());
+    for value in &strings.values {
+        let length = u16::try_from(value.len()).map_err(|_| {
+            ForgeKitError::InvalidUi(format!("string of {} bytes is too long", value.len()))
+        })?;
+        output.extend_from_slice(&length.to_le_bytes());
+        output.extend_from_slice(value.as_bytes());
+    }
+    output.extend_from_slice(&tree);
+    Ok(output)
+}
+
+/// Validate and compile every layout in a project's `ui/` directory
+///
+/// Compiled layouts are written to `target/forgekit/ui/` with the same
+/// relative path and a `.mxui` extension. Warnings are logged and returned;
+/// any error aborts the build.
+pub fn compile_project(project_path: &Path) -> Result<Vec<CompiledLayout>, ForgeKitError> {
+    let ui_dir = project_path.join("ui");
+    if !ui_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut layouts = Vec::new();
+    for source in layout_files(&ui_dir) {
+        layouts.push(compile_layout(project_path, &source)?);
+    }
+
+    Ok(layouts)
+}
+
+/// Validate and compile a single layout file of a project
+pub fn compile_layout(project_path: &Path, source: &Path) -> Result<CompiledLayout, ForgeKitError> {
+    let ui_dir = project_path.join("ui");
+    let relative = source.strip_prefix(&ui_dir).unwrap_or(source);
+    let xml = std::fs::read_to_string(source)?;
+
+    let (errors, warnings): (Vec<UiDiagnostic>, Vec<UiDiagnostic>) = validate(&xml)
+        .into_iter()
+        .partition(|d| d.severity == Severity::Error);
+    if !errors.is_empty() {
+        let messages: Vec<String> = errors
+            .iter()
+            .map(|d| {
+                format!(
+                    "ui/{}:{}:{}: {}",
+                    relative.display(),
+                    d.line,
+                    d.column,
+                    d.message
+                )
+            })
+            .collect();
+        return Err(ForgeKitError::InvalidUi(messages.join("; ")));
+    }
+
+    let compiled = compile(&xml)?;
+    for warning in &warnings {
+        tracing::warn!(
+            "ui/{}:{}:{}: {}",
+            relative.display(),
+            warning.line,
+            warning.column,
+            warning.message
+        );
+    }
+
+    let output = compiled_dir(project_path)
+        .join(relative)
+        .with_extension("mxui");
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&output, compiled)?;
+
+    Ok(CompiledLayout {
+        source: source.to_path_buf(),
+        output,
+        warnings,
+    })
+}
+
+/// Directory holding compiled layouts
+pub fn compiled_dir(project_path: &Path) -> PathBuf {
+    project_path.join("target").join("forgekit").join("ui")
+}
+
+/// XML layout files under a directory, sorted
+pub fn layout_files(ui_dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = walkdir::WalkDir::new(ui_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "xml"))
+        .map(|e| e.path().to_path_buf())
+        .collect();
+    files.sort();
+    files
+}
+
+#[derive(Default)]
+struct StringTable {
+    values: Vec<String>,
+}
+
+impl StringTable {
+    fn index(&mut self, value: &str) -> Result<u16, ForgeKitError> {
+        let position = match self.values.iter().position(|v| v == value) {
+            Some(position) => position,
+            None => {
+                self.values.push(value.to_string());
+                self.values.len() - 1
+            }
+        };
+        u16::try_from(position)
+            .map_err(|_| ForgeKitError::InvalidUi("layout has too many strings".to_string()))
+    }
+}
+
+fn encode_node(
+    node: roxmltree::Node,
+    strings: &mut StringTable,
+    out: &mut Vec<u8>,
+) -> Result<(), ForgeKitError> {
+    out.extend_from_slice(&strings.index(node.tag_name().name())?.to_le_bytes());
+
+    let attributes: Vec<_> = node.attributes().collect();
+    let count = u8::try_from(attributes.len())
+        .map_err(|_| ForgeKitError::InvalidUi("widget has too many attributes".to_string()))?;
+    out.push(count);
+    for attribute in attributes {
+        out.extend_from_slice(&strings.index(attribute.name())?.to_le_bytes());
+        out.extend_from_slice(&strings.index(attribute.value())?.to_le_bytes());
+    }
+
+    let children: Vec<_> = node.children().filter(|c| c.is_element()).collect();
+    let count = u16::try_from(children.len())
+        .map_err(|_| ForgeKitError::InvalidUi("widget has too many children".to_string()))?;
+    out.extend_from_slice(&count.to_le_bytes());
+    for child in children {
+        encode_node(child, strings, out)?;
+    }
+
+    Ok(())
+}
+
+fn diagnostic(
+    document: &roxmltree::Document,
+    node: roxmltree::Node,
+    severity: Severity,
+    message: String,
+) -> UiDiagnostic {
+    let pos = document.text_pos_at(node.range().start);
+    UiDiagnostic {
+        severity,
+        line: pos.row,
+        column: pos.col,
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const LAYOUT: &str = r#"<window title="App" width="800" height="600">
+    <layout type="vertical">
+        <label text="Hello" />
+        <button text="Click" onclick="handle_click" />
+    </layout>
+</window>
+"#;
+
+    #[test]
+    fn test_validate_reports_unknown_widgets_and_attributes() {
+        let xml = r#"<window title="App">
+    <slider value="1" />
+    <label text="Hi" colour="red" />
+</window>"#;
+        let diagnostics = validate(xml);
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].line, 2);
+        assert!(diagnostics[0].message.contains("slider"));
+        assert_eq!(diagnostics[1].severity, Severity::Warning);
+        assert!(diagnostics[1].message.contains("colour"));
+    }
+
+    #[test]
+    fn test_compile_layout() {
+        let compiled = compile(LAYOUT).unwrap();
+        assert_eq!(&compiled[..4], MXUI_MAGIC);
+        assert_eq!(compiled[4], MXUI_VERSION);
+        assert!(compiled.len() < LAYOUT.len());
+
+        assert!(compile("<window><label><button/></label></window>").is_err());
+
+        // String lengths are stored in 16 bits
+        let long = format!(r#"<window title="{}" />"#, "a".repeat(70_000));
+        assert!(compile(&long).is_err());
+    }
+
+    #[test]
+    fn test_compile_project() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(temp_dir.path().join("ui")).unwrap();
+        std::fs::write(temp_dir.path().join("ui").join("main.xml"), LAYOUT).unwrap();
+
+        let layouts = compile_project(temp_dir.path()).unwrap();
+        assert_eq!(layouts.len(), 1);
+        assert!(layouts[0].output.ends_with("target/forgekit/ui/main.mxui"));
+        assert!(layouts[0].output.exists());
+    }
+}
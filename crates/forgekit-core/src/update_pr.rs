@@ -0,0 +1,840 @@
+//! Dependency update pull request module
+//!
+//! `forgekit update --create-pr` is a small Dependabot for ForgeKit
+//! registries. Every registry dependency with a newer release, or all of them
+//! at once with `--group`, gets its own branch off the current one: the bump
+//! is applied with the package manager, the project is built and tested, and
+//! the result is committed and pushed. A pull request is then opened through
+//! the API of the remote's host, GitHub or GitLab, describing the bumps with
+//! the release notes of the new versions and how the build and tests went.
+//!
+//! The API token is read from `GITHUB_TOKEN` or `GITLAB_TOKEN`, else from the
+//! credential store entry `forge.github.token` or `forge.gitlab.token`.
+
+use crate::builder;
+use crate::config::ProjectConfig;
+use crate::credentials;
+use crate::error::ForgeKitError;
+use crate::http_client::HttpClient;
+use crate::lock::LockOptions;
+use crate::lockfile::LOCKFILE_NAME;
+use crate::package_manager::PackageManager;
+use crate::registry::{RegistryClient, RegistryConfig};
+use crate::testing::TestRunner;
+use crate::version_manager::{compare_versions, VersionManager};
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::process::Command;
+
+/// Prefix of the branches updates are pushed to
+pub const BRANCH_PREFIX: &str = "forgekit/update-";
+
+/// A dependency with a newer release in the registry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OutdatedDependency {
+    /// Package name
+    pub name: String,
+    /// Version in forgekit.toml
+    pub current: String,
+    /// Latest version in the registry
+    pub latest: String,
+}
+
+/// Registry dependencies of `config` with a newer release
+///
+/// Dependencies from other sources, like git or a path, are left alone.
+pub async fn outdated(
+    config: &ProjectConfig,
+    registry: &RegistryClient,
+) -> Result<Vec<OutdatedDependency>, ForgeKitError> {
+    let mut outdated = Vec::new();
+    for dependency in &config.dependencies {
+        if dependency
+            .source
+            .as_deref()
+            .is_some_and(|source| source != "registry")
+        {
+            continue;
+        }
+        let Some(entry) = registry.fetch_entry(&dependency.name).await? else {
+            continue;
+        };
+        if compare_versions(&entry.latest, &dependency.version) == Ordering::Greater {
+            outdated.push(OutdatedDependency {
+                name: dependency.name.clone(),
+                current: dependency.version.clone(),
+                latest: entry.latest,
+            });
+        }
+    }
+    Ok(outdated)
+}
+
+/// Bumps made on one branch and proposed in one pull request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UpdateBranch {
+    /// Branch name
+    pub branch: String,
+    /// Commit message and pull request title
+    pub title: String,
+    /// Dependencies bumped
+    pub updates: Vec<OutdatedDependency>,
+}
+
+/// Branches for `outdated`: one per dependency, or a single one with all
+/// of them when `group` is set
+pub fn plan(outdated: Vec<OutdatedDependency>, group: bool) -> Vec<UpdateBranch> {
+    if group && outdated.len() > 1 {
+        return vec![UpdateBranch {
+            branch: format!("{}dependencies", BRANCH_PREFIX),
+            title: format!("Update {} dependencies", outdated.len()),
+            updates: outdated,
+        }];
+    }
+    outdated
+        .into_iter()
+        .map(|update| UpdateBranch {
+            branch: format!("{}{}-{}", BRANCH_PREFIX, update.name, update.latest),
+            title: format!(
+                "Update {} from {} to {}",
+                update.name, update.current, update.latest
+            ),
+            updates: vec![update],
+        })
+        .collect()
+}
+
+/// How building and testing the updated project went
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Checks {
+    /// Whether the project built
+    pub build: bool,
+    /// Whether the tests passed; `None` when they did not run
+    pub tests: Option<bool>,
+    /// Why a check failed
+    pub failure: Option<String>,
+}
+
+impl Checks {
+    /// Whether no check failed
+    pub fn passed(&self) -> bool {
+        self.build && self.tests != Some(false)
+    }
+
+    /// Build and test the project at `project_path`
+    ///
+    /// Tests run when the project has a Cargo.toml and the build succeeded.
+    pub async fn run(project_path: &Path) -> Self {
+        if let Err(e) = builder::build(project_path).await {
+            return Self {
+                build: false,
+                tests: None,
+                failure: Some(e.to_string()),
+            };
+        }
+        if !project_path.join("Cargo.toml").exists() {
+            return Self {
+                build: true,
+                tests: None,
+                failure: None,
+            };
+        }
+        match TestRunner::run_tests(project_path).await {
+            Ok(report) if report.all_passed() => Self {
+                build: true,
+                tests: Some(true),
+                failure: None,
+            },
+            Ok(report) => Self {
+                build: true,
+                tests: Some(false),
+                failure: Some(format!("failed tests: {}", report.failed_tests.join(", "))),
+            },
+            Err(e) => Self {
+                build: true,
+                tests: Some(false),
+                failure: Some(e.to_string()),
+            },
+        }
+    }
+}
+
+/// Pull request description of a branch
+///
+/// `notes` holds the release notes of the new versions by package name.
+pub fn description(
+    branch: &UpdateBranch,
+    notes: &HashMap<String, String>,
+    checks: &Checks,
+) -> String {
+    let mut body = String::from("Bumps ForgeKit dependencies to their latest release.\n\n");
+    body.push_str("| Package | From | To |\n|---|---|---|\n");
+    for update in &branch.updates {
+        body.push_str(&format!(
+            "| {} | {} | {} |\n",
+            update.name, update.current, update.latest
+        ));
+    }
+
+    for update in &branch.updates {
+        body.push_str(&format!("\n### {} {}\n\n", update.name, update.latest));
+        match notes.get(&update.name) {
+            Some(notes) => body.push_str(notes.trim()),
+            None => body.push_str("_No release notes were published for this version._"),
+        }
+        body.push('\n');
+    }
+
+    let mark = |passed: bool| if passed { "✅" } else { "❌" };
+    body.push_str("\n### Checks\n\n");
+    body.push_str(&format!("- {} `forgekit build`\n", mark(checks.build)));
+    match checks.tests {
+        Some(passed) => body.push_str(&format!("- {} `forgekit test`\n", mark(passed))),
+        None => body.push_str("- ⏭️ `forgekit test` did not run\n"),
+    }
+    if let Some(failure) = &checks.failure {
+        body.push_str(&format!("\n```\n{}\n```\n", failure.trim()));
+    }
+    body.push_str("\n---\nOpened by `forgekit update --create-pr`.\n");
+    body
+}
+
+/// Code host a git remote points at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Forge {
+    /// github.com
+    GitHub {
+        /// REST API base URL
+        api_url: String,
+        /// Repository as `owner/name`
+        repository: String,
+    },
+    /// gitlab.com or a self-hosted GitLab
+    GitLab {
+        /// REST API base URL
+        api_url: String,
+        /// Project path as `group/name`
+        project: String,
+    },
+}
+
+impl Forge {
+    /// Code host of a remote URL, like `git@github.com:owner/repo.git` or
+    /// `https://gitlab.example.com/group/repo`
+    pub fn from_remote(url: &str) -> Result<Self, ForgeKitError> {
+        let (host, path) = match reqwest::Url::parse(url) {
+            Ok(parsed) if parsed.host_str().is_some() => (
+                parsed.host_str().unwrap_or_default().to_string(),
+                parsed.path().to_string(),
+            ),
+            // scp-like syntax: [user@]host:path
+            _ => {
+                let (host, path) = url.split_once(':').ok_or_else(|| {
+                    ForgeKitError::PullRequest(format!("Cannot parse git remote '{}'", url))
+                })?;
+                let host = host.rsplit_once('@').map_or(host, |(_, host)| host);
+                (host.to_string(), path.to_string())
+            }
+        };
+        let path = path.trim_matches('/');
+        let path = path.strip_suffix(".git").unwrap_or(path).to_string();
+        if path.is_empty() {
+            return Err(ForgeKitError::PullRequest(format!(
+                "Git remote '{}' names no repository",
+                url
+            )));
+        }
+
+        if host == "github.com" {
+            Ok(Self::GitHub {
+                api_url: "https://api.github.com".to_string(),
+                repository: path,
+            })
+        } else if host.contains("gitlab") {
+            Ok(Self::GitLab {
+                api_url: format!("https://{}/api/v4", host),
+                project: path,
+            })
+        } else {
+            Err(ForgeKitError::PullRequest(format!(
+                "Cannot open pull requests on {}: only GitHub and GitLab remotes are supported",
+                host
+            )))
+        }
+    }
+
+    /// Name of the code host
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::GitHub { .. } => "GitHub",
+            Self::GitLab { .. } => "GitLab",
+        }
+    }
+
+    /// Environment variable holding the API token
+    pub fn token_var(&self) -> &'static str {
+        match self {
+            Self::GitHub { .. } => "GITHUB_TOKEN",
+            Self::GitLab { .. } => "GITLAB_TOKEN",
+        }
+    }
+
+    /// Credential store entry holding the API token
+    pub fn secret_name(&self) -> &'static str {
+        match self {
+            Self::GitHub { .. } => "forge.github.token",
+            Self::GitLab { .. } => "forge.gitlab.token",
+        }
+    }
+
+    /// API token from the environment or the credential store
+    pub fn token(&self) -> Result<String, ForgeKitError> {
+        if let Some(token) = std::env::var(self.token_var())
+            .ok()
+            .filter(|token| !token.is_empty())
+        {
+            return Ok(token);
+        }
+        credentials::default_store()?
+            .get(self.secret_name())?
+            .ok_or_else(|| {
+                ForgeKitError::PullRequest(format!(
+                    "No {} token: set {} or run `forgekit secret set {}`",
+                    self.name(),
+                    self.token_var(),
+                    self.secret_name()
+                ))
+            })
+    }
+
+    /// Open a pull request from `branch` into `base`, or update the one
+    /// already open for the branch, returning its web URL
+    pub async fn open(
+        &self,
+        http: &dyn HttpClient,
+        token: &str,
+        branch: &str,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> Result<String, ForgeKitError> {
+        let existing = self.find_open(http, token, branch).await?;
+        let (method, url, payload, link) = match (self, existing) {
+            (
+                Self::GitHub {
+                    api_url,
+                    repository,
+                },
+                Some(number),
+            ) => (
+                reqwest::Method::PATCH,
+                format!("{}/repos/{}/pulls/{}", api_url, repository, number),
+                serde_json::json!({ "title": title, "base": base, "body": body }),
+                "html_url",
+            ),
+            (
+                Self::GitHub {
+                    api_url,
+                    repository,
+                },
+                None,
+            ) => (
+                reqwest::Method::POST,
+                format!("{}/repos/{}/pulls", api_url, repository),
+                serde_json::json!({
+                    "title": title,
+                    "head": branch,
+                    "base": base,
+                    "body": body,
+                }),
+                "html_url",
+            ),
+            (Self::GitLab { api_url, project }, Some(iid)) => (
+                reqwest::Method::PUT,
+                format!(
+                    "{}/projects/{}/merge_requests/{}",
+                    api_url,
+                    project.replace('/', "%2F"),
+                    iid
+                ),
+                serde_json::json!({
+                    "title": title,
+                    "target_branch": base,
+                    "description": body,
+                }),
+                "web_url",
+            ),
+            (Self::GitLab { api_url, project }, None) => (
+                reqwest::Method::POST,
+                format!(
+                    "{}/projects/{}/merge_requests",
+                    api_url,
+                    project.replace('/', "%2F")
+                ),
+                serde_json::json!({
+                    "title": title,
+                    "source_branch": branch,
+                    "target_branch": base,
+                    "description": body,
+                    "remove_source_branch": true,
+                }),
+                "web_url",
+            ),
+        };
+
+        let answer = self
+            .request(http, token, method, &url, Some(payload))
+            .await?;
+        Ok(answer
+            .get(link)
+            .and_then(|link| link.as_str())
+            .unwrap_or_default()
+            .to_string())
+    }
+
+    /// Number of the pull request open for `branch`, if there is one
+    async fn find_open(
+        &self,
+        http: &dyn HttpClient,
+        token: &str,
+        branch: &str,
+    ) -> Result<Option<u64>, ForgeKitError> {
+        let (url, id) = match self {
+            Self::GitHub {
+                api_url,
+                repository,
+            } => {
+                let owner = repository.split('/').next().unwrap_or_default();
+                (
+                    format!(
+                        "{}/repos/{}/pulls?head={}:{}&state=open",
+                        api_url, repository, owner, branch
+                    ),
+                    "number",
+                )
+            }
+            Self::GitLab { api_url, project } => (
+                format!(
+                    "{}/projects/{}/merge_requests?source_branch={}&state=opened",
+                    api_url,
+                    project.replace('/', "%2F"),
+                    branch
+                ),
+                "iid",
+            ),
+        };
+        let answer = self
+            .request(http, token, reqwest::Method::GET, &url, None)
+            .await?;
+        Ok(answer
+            .as_array()
+            .and_then(|open| open.first())
+            .and_then(|pull_request| pull_request.get(id))
+            .and_then(|id| id.as_u64()))
+    }
+
+    /// Send an API request and return the JSON answer
+    async fn request(
+        &self,
+        http: &dyn HttpClient,
+        token: &str,
+        method: reqwest::Method,
+        url: &str,
+        payload: Option<serde_json::Value>,
+    ) -> Result<serde_json::Value, ForgeKitError> {
+        let auth = match self {
+            Self::GitHub { .. } => ("authorization", format!("Bearer {}", token)),
+            Self::GitLab { .. } => ("private-token", token.to_string()),
+        };
+        let parsed = reqwest::Url::parse(url)
+            .map_err(|e| ForgeKitError::PullRequest(format!("Invalid URL {}: {}", url, e)))?;
+        let mut request = reqwest::Request::new(method, parsed);
+        let headers = request.headers_mut();
+        let header = |value: &str| {
+            reqwest::header::HeaderValue::from_str(value)
+                .map_err(|_| ForgeKitError::PullRequest(format!("Invalid {} token", self.name())))
+        };
+        headers.insert(auth.0, header(&auth.1)?);
+        headers.insert(
+            reqwest::header::CONTENT_TYPE,
+            reqwest::header::HeaderValue::from_static("application/json"),
+        );
+        headers.insert(
+            reqwest::header::USER_AGENT,
+            reqwest::header::HeaderValue::from_static(concat!(
+                "forgekit/",
+                env!("CARGO_PKG_VERSION")
+            )),
+        );
+        if let Some(payload) = payload {
+            *request.body_mut() = Some(payload.to_string().into());
+        }
+
+        let response = http.send(request).await?;
+        let status = response.status();
+        let answer: serde_json::Value = response.json().await.unwrap_or_default();
+        if !status.is_success() {
+            let message = answer
+                .get("message")
+                .map(|message| match message {
+                    serde_json::Value::String(message) => message.clone(),
+                    other => other.to_string(),
+                })
+                .unwrap_or_default();
+            return Err(ForgeKitError::PullRequest(format!(
+                "{} answered {}: {}",
+                self.name(),
+                status,
+                message
+            )));
+        }
+        Ok(answer)
+    }
+}
+
+/// Pull request opened for a branch
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdatePullRequest {
+    /// Branch and the bumps made on it
+    #[serde(flatten)]
+    pub branch: UpdateBranch,
+    /// How building and testing went
+    pub checks: Checks,
+    /// Web URL of the pull request
+    pub url: String,
+}
+
+/// Opens a pull request per dependency update
+pub struct DependencyUpdater {
+    project_root: PathBuf,
+    registry_client: RegistryClient,
+    http: Arc<dyn HttpClient>,
+    remote: String,
+    group: bool,
+    lock: LockOptions,
+}
+
+impl DependencyUpdater {
+    /// Updater for the project at `project_root`, pushing to `origin`
+    pub fn new(project_root: PathBuf) -> Result<Self, ForgeKitError> {
+        Ok(Self {
+            project_root,
+            registry_client: RegistryClient::from_login(RegistryConfig::default())?,
+            http: Arc::new(reqwest::Client::new()),
+            remote: "origin".to_string(),
+            group: false,
+            lock: LockOptions::default(),
+        })
+    }
+
+    /// Look up and download packages through `registry_client`
+    pub fn with_registry_client(mut self, registry_client: RegistryClient) -> Self {
+        self.registry_client = registry_client;
+        self
+    }
+
+    /// Send code host API requests through `http`
+    pub fn with_http_client(mut self, http: Arc<dyn HttpClient>) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Push branches to `remote` and open pull requests on its host
+    pub fn with_remote(mut self, remote: &str) -> Self {
+        self.remote = remote.to_string();
+        self
+    }
+
+    /// Bump all outdated dependencies in a single pull request
+    pub fn with_group(mut self, group: bool) -> Self {
+        self.group = group;
+        self
+    }
+
+    /// Lock the project this way while changing its dependencies
+    pub fn with_lock(mut self, lock: LockOptions) -> Self {
+        self.lock = lock;
+        self
+    }
+
+    /// Open pull requests for the project's outdated dependencies
+    ///
+    /// The working tree must be clean. Each branch starts from the current
+    /// one, which is checked out again once the pull requests are open. A
+    /// pull request already open for a branch, from an earlier run, is
+    /// updated instead.
+    pub async fn create_pull_requests(&self) -> Result<Vec<UpdatePullRequest>, ForgeKitError> {
+        let root = &self.project_root;
+        if !git(root, &["status", "--porcelain"]).await?.is_empty() {
+            return Err(ForgeKitError::PullRequest(
+                "The working tree has uncommitted changes; commit or stash them first".to_string(),
+            ));
+        }
+        let base = git(root, &["rev-parse", "--abbrev-ref", "HEAD"]).await?;
+        let forge = Forge::from_remote(&git(root, &["remote", "get-url", &self.remote]).await?)?;
+        let token = forge.token()?;
+
+        let config = ProjectConfig::load(root.join("forgekit.toml"))?;
+        let outdated = outdated(&config, &self.registry_client).await?;
+
+        let mut opened = Vec::new();
+        for branch in plan(outdated, self.group) {
+            git(root, &["checkout", "-B", &branch.branch]).await?;
+            let result = self.propose(&forge, &token, &base, branch).await;
+            let restored = restore(root, &base, result.is_err()).await;
+            // A failed update is reported over a failed cleanup
+            let opened_pr = result?;
+            restored?;
+            opened.push(opened_pr);
+        }
+        Ok(opened)
+    }
+
+    /// Apply, check, push and open the pull request of the checked out branch
+    async fn propose(
+        &self,
+        forge: &Forge,
+        token: &str,
+        base: &str,
+        branch: UpdateBranch,
+    ) -> Result<UpdatePullRequest, ForgeKitError> {
+        let root = &self.project_root;
+        let package_manager = PackageManager::new(root.clone())?
+            .with_registry_client(self.registry_client.clone())
+            .with_lock(self.lock.clone());
+        let mut notes = HashMap::new();
+        for update in &branch.updates {
+            package_manager
+                .add_dependency(&update.name, &update.latest)
+                .await?;
+            if let Some(release_notes) = release_notes(root, &update.name, &update.latest)? {
+                notes.insert(update.name.clone(), release_notes);
+            }
+        }
+
+        let checks = Checks::run(root).await;
+        // Only the update itself is committed, not what building and testing
+        // left behind
+        let mut changed = vec!["add", "--"];
+        changed.extend(
+            ["forgekit.toml", LOCKFILE_NAME, "vendor"]
+                .into_iter()
+                .filter(|path| root.join(path).exists()),
+        );
+        git(root, &changed).await?;
+        git(root, &["commit", "--quiet", "-m", &branch.title]).await?;
+        git(
+            root,
+            &[
+                "push",
+                "--quiet",
+                "--force-with-lease",
+                "-u",
+                &self.remote,
+                &branch.branch,
+            ],
+        )
+        .await?;
+
+        let body = description(&branch, &notes, &checks);
+        let url = forge
+            .open(
+                self.http.as_ref(),
+                token,
+                &branch.branch,
+                base,
+                &branch.title,
+                &body,
+            )
+            .await?;
+        Ok(UpdatePullRequest {
+            branch,
+            checks,
+            url,
+        })
+    }
+}
+
+/// Check out `base` again after proposing an update, first discarding the
+/// changes of an update that failed half-way
+async fn restore(root: &Path, base: &str, discard: bool) -> Result<(), ForgeKitError> {
+    if discard {
+        git(root, &["reset", "--hard", "--quiet"]).await?;
+        git(root, &["clean", "-d", "--force", "--quiet"]).await?;
+    }
+    git(root, &["checkout", "--quiet", base]).await?;
+    Ok(())
+}
+
+/// Release notes of an installed dependency version
+///
+/// Library packages carry them in their packaged forgekit.toml; other
+/// packages may have a changelog.
+fn release_notes(
+    project_root: &Path,
+    name: &str,
+    version: &str,
+) -> Result<Option<String>, ForgeKitError> {
+    let installed = project_root
+        .join("vendor")
+        .join(format!("{}-{}", name, version));
+    let packaged = ProjectConfig::load(installed.join("forgekit.toml"))
+        .ok()
+        .and_then(|config| config.release_notes);
+    match packaged {
+        Some(notes) => Ok(Some(notes)),
+        None => VersionManager::notes_for(&installed, version),
+    }
+}
+
+/// Run a git command in `dir` and return its trimmed standard output
+async fn git(dir: &Path, args: &[&str]) -> Result<String, ForgeKitError> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(ForgeKitError::PullRequest(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_client::{MockHttpClient, MockResponse};
+
+    fn update(name: &str, current: &str, latest: &str) -> OutdatedDependency {
+        OutdatedDependency {
+            name: name.to_string(),
+            current: current.to_string(),
+            latest: latest.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_remotes_and_branches() {
+        assert_eq!(
+            Forge::from_remote("git@github.com:acme/app.git").unwrap(),
+            Forge::GitHub {
+                api_url: "https://api.github.com".to_string(),
+                repository: "acme/app".to_string(),
+            }
+        );
+        assert_eq!(
+            Forge::from_remote("https://gitlab.example.com/team/tools/app").unwrap(),
+            Forge::GitLab {
+                api_url: "https://gitlab.example.com/api/v4".to_string(),
+                project: "team/tools/app".to_string(),
+            }
+        );
+        assert!(Forge::from_remote("https://example.com/acme/app.git").is_err());
+
+        let outdated = vec![
+            update("forgekit-http", "0.1.0", "0.2.0"),
+            update("forgekit-json", "0.3.0", "0.3.1"),
+        ];
+        let single = plan(outdated.clone(), false);
+        assert_eq!(single.len(), 2);
+        assert_eq!(single[0].branch, "forgekit/update-forgekit-http-0.2.0");
+        assert_eq!(single[0].title, "Update forgekit-http from 0.1.0 to 0.2.0");
+        let grouped = plan(outdated, true);
+        assert_eq!(grouped.len(), 1);
+        assert_eq!(grouped[0].branch, "forgekit/update-dependencies");
+        assert_eq!(grouped[0].updates.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_pull_request_is_opened_with_release_notes() {
+        let branch = plan(vec![update("forgekit-http", "0.1.0", "0.2.0")], false).remove(0);
+        let notes = HashMap::from([(
+            "forgekit-http".to_string(),
+            "### Added\n- Retries".to_string(),
+        )]);
+        let checks = Checks {
+            build: true,
+            tests: Some(false),
+            failure: Some("failed tests: client::timeout".to_string()),
+        };
+        assert!(!checks.passed());
+        let body = description(&branch, &notes, &checks);
+        assert!(body.contains("| forgekit-http | 0.1.0 | 0.2.0 |"));
+        assert!(body.contains("- Retries"));
+        assert!(body.contains("❌ `forgekit test`"));
+
+        let forge = Forge::from_remote("https://github.com/acme/app").unwrap();
+        let lookup =
+            "https://api.github.com/repos/acme/app/pulls?head=acme:forgekit/update-forgekit-http-0.2.0&state=open";
+        let http = MockHttpClient::new()
+            .on("GET", lookup, MockResponse::json(&serde_json::json!([])))
+            .on(
+                "POST",
+                "https://api.github.com/repos/acme/app/pulls",
+                MockResponse::json(&serde_json::json!({
+                    "html_url": "https://github.com/acme/app/pull/7"
+                })),
+            );
+        let url = forge
+            .open(
+                &http,
+                "ghp_token",
+                &branch.branch,
+                "main",
+                &branch.title,
+                &body,
+            )
+            .await
+            .unwrap();
+        assert_eq!(url, "https://github.com/acme/app/pull/7");
+
+        let request = &http.requests()[1];
+        assert!(request
+            .headers
+            .contains(&("authorization".to_string(), "Bearer ghp_token".to_string())));
+        let sent: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(sent["head"], "forgekit/update-forgekit-http-0.2.0");
+        assert_eq!(sent["base"], "main");
+
+        // A rerun updates the pull request the first run opened
+        let rerun = MockHttpClient::new()
+            .on(
+                "GET",
+                lookup,
+                MockResponse::json(&serde_json::json!([{ "number": 7 }])),
+            )
+            .on(
+                "PATCH",
+                "https://api.github.com/repos/acme/app/pulls/7",
+                MockResponse::json(&serde_json::json!({
+                    "html_url": "https://github.com/acme/app/pull/7"
+                })),
+            );
+        let url = forge
+            .open(&rerun, "ghp_token", &branch.branch, "main", "t", "b")
+            .await
+            .unwrap();
+        assert_eq!(url, "https://github.com/acme/app/pull/7");
+        assert_eq!(rerun.requests().len(), 2);
+
+        let rejected = MockHttpClient::new()
+            .on("GET", lookup, MockResponse::json(&serde_json::json!([])))
+            .on(
+                "POST",
+                "https://api.github.com/repos/acme/app/pulls",
+                MockResponse::new(422).body(r#"{"message":"Validation Failed"}"#),
+            );
+        let err = forge
+            .open(&rejected, "ghp_token", &branch.branch, "main", "t", "b")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Validation Failed"));
+    }
+}
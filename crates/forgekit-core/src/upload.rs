@@ -0,0 +1,227 @@
+//! Resumable upload module
+//!
+//! Archives are published to a registry server in chunks instead of one
+//! request. The server keeps the bytes it received under an upload URL; the
+//! URL, size and checksum of the archive are saved next to it, so a later
+//! attempt asks the server how far it got and sends only the rest. Uploads
+//! can be rate-limited, and the server checks the size and SHA-256 of the
+//! assembled archive before publishing it.
+//!
+//! - `POST /api/v1/uploads` with `Upload-Length` and `Upload-Checksum`:
+//!   start an upload, answered with its `Location`
+//! - `HEAD <location>`: bytes received so far, as `Upload-Offset`
+//! - `PATCH <location>` with `Upload-Offset`: append a chunk
+//! - `POST <location>/publish`: verify and publish the assembled archive
+
+use crate::atomic;
+use crate::error::ForgeKitError;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Bytes sent per request by default
+pub const DEFAULT_CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+
+/// Smallest chunk sent, however low the rate limit
+const MIN_CHUNK_SIZE: u64 = 64 * 1024;
+
+/// Header with the total size of an upload
+pub const UPLOAD_LENGTH: &str = "upload-length";
+
+/// Header with the bytes of an upload received so far
+pub const UPLOAD_OFFSET: &str = "upload-offset";
+
+/// Header with the SHA-256 of the whole archive, as `sha256 <hex>`
+pub const UPLOAD_CHECKSUM: &str = "upload-checksum";
+
+/// How archives are uploaded
+#[derive(Debug, Clone)]
+pub struct UploadOptions {
+    /// Bytes sent per request
+    pub chunk_size: u64,
+    /// Largest average rate in bytes per second, unlimited when unset
+    pub rate_limit: Option<u64>,
+}
+
+impl Default for UploadOptions {
+    fn default() -> Self {
+        Self {
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            rate_limit: None,
+        }
+    }
+}
+
+impl UploadOptions {
+    /// Bytes to send per request
+    ///
+    /// With a rate limit, chunks hold at most a second's worth of bytes so
+    /// progress stays smooth.
+    pub fn effective_chunk_size(&self) -> u64 {
+        let chunk_size = match self.rate_limit {
+            Some(rate) => self.chunk_size.min(rate),
+            None => self.chunk_size,
+        };
+        chunk_size.max(MIN_CHUNK_SIZE)
+    }
+}
+
+/// Progress of an upload
+#[derive(Debug, Clone)]
+pub struct UploadProgress {
+    /// Archive being uploaded
+    pub package: String,
+    /// Bytes the server has received
+    pub uploaded: u64,
+    /// Size of the archive in bytes
+    pub total: u64,
+    /// Whether the archive has been published
+    pub finished: bool,
+}
+
+/// Callback invoked as upload progress is made
+pub type UploadProgressCallback = Arc<dyn Fn(&UploadProgress) + Send + Sync>;
+
+/// Upload of an archive that has not been published yet
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingUpload {
+    /// URL the server keeps the upload under
+    pub url: String,
+    /// Size of the archive in bytes
+    pub size: u64,
+    /// SHA-256 of the archive
+    pub checksum: String,
+}
+
+impl PendingUpload {
+    /// Location of the saved upload of an archive
+    pub fn path(archive: &Path) -> PathBuf {
+        let mut path = archive.as_os_str().to_os_string();
+        path.push(".upload.json");
+        PathBuf::from(path)
+    }
+
+    /// Upload of `archive` to resume, if one was saved for the same
+    /// contents on a server under `base_url`
+    pub fn load(archive: &Path, base_url: &str, size: u64, checksum: &str) -> Option<Self> {
+        let state = std::fs::read_to_string(Self::path(archive)).ok()?;
+        let pending: Self = serde_json::from_str(&state).ok()?;
+        (pending.url.starts_with(base_url) && pending.size == size && pending.checksum == checksum)
+            .then_some(pending)
+    }
+
+    /// Save the upload next to `archive`
+    pub fn save(&self, archive: &Path) -> Result<(), ForgeKitError> {
+        atomic::write(Self::path(archive), serde_json::to_string_pretty(self)?)
+    }
+
+    /// Forget the upload saved next to `archive`
+    pub fn clear(archive: &Path) -> Result<(), ForgeKitError> {
+        match std::fs::remove_file(Self::path(archive)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Keeps the average rate of sent bytes under a limit
+#[derive(Debug)]
+pub struct RateLimiter {
+    rate: Option<u64>,
+    started: Instant,
+    sent: u64,
+}
+
+impl RateLimiter {
+    /// Limiter allowing `rate` bytes per second, or any rate when unset
+    pub fn new(rate: Option<u64>) -> Self {
+        Self {
+            rate: rate.filter(|rate| *rate > 0),
+            started: Instant::now(),
+            sent: 0,
+        }
+    }
+
+    /// How long to wait after sending `bytes` more
+    pub fn delay(&mut self, bytes: u64) -> Duration {
+        self.sent += bytes;
+        let Some(rate) = self.rate else {
+            return Duration::ZERO;
+        };
+        let due = Duration::from_secs_f64(self.sent as f64 / rate as f64);
+        due.saturating_sub(self.started.elapsed())
+    }
+
+    /// Wait as long as needed after sending `bytes` more
+    pub async fn throttle(&mut self, bytes: u64) {
+        let delay = self.delay(bytes);
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// Value of the `Upload-Checksum` header for a SHA-256
+pub fn checksum_header(checksum: &str) -> String {
+    format!("sha256 {}", checksum)
+}
+
+/// SHA-256 from an `Upload-Checksum` header
+pub fn parse_checksum_header(header: &str) -> Option<&str> {
+    header
+        .strip_prefix("sha256 ")
+        .map(str::trim)
+        .filter(|hex| hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_pending_upload_matches_contents_and_server() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive = temp_dir.path().join("shared-0.1.0.moxlib");
+        let pending = PendingUpload {
+            url: "http://registry.example/api/v1/uploads/abc".to_string(),
+            size: 10,
+            checksum: "c0ffee".to_string(),
+        };
+        pending.save(&archive).unwrap();
+
+        let base = "http://registry.example";
+        assert_eq!(
+            PendingUpload::load(&archive, base, 10, "c0ffee"),
+            Some(pending)
+        );
+        assert!(PendingUpload::load(&archive, base, 11, "c0ffee").is_none());
+        assert!(PendingUpload::load(&archive, "http://other.example", 10, "c0ffee").is_none());
+        PendingUpload::clear(&archive).unwrap();
+        PendingUpload::clear(&archive).unwrap();
+        assert!(PendingUpload::load(&archive, base, 10, "c0ffee").is_none());
+    }
+
+    #[test]
+    fn test_rate_limit_bounds_chunks_and_delays() {
+        let options = UploadOptions {
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            rate_limit: Some(1024 * 1024),
+        };
+        assert_eq!(options.effective_chunk_size(), 1024 * 1024);
+        assert_eq!(
+            UploadOptions::default().effective_chunk_size(),
+            DEFAULT_CHUNK_SIZE
+        );
+
+        let mut limiter = RateLimiter::new(Some(1000));
+        assert!(limiter.delay(2000) > Duration::from_millis(1900));
+        assert_eq!(RateLimiter::new(None).delay(u64::MAX), Duration::ZERO);
+        assert_eq!(
+            parse_checksum_header(&checksum_header(&"a".repeat(64))),
+            Some("a".repeat(64).as_str())
+        );
+        assert!(parse_checksum_header("md5 abc").is_none());
+    }
+}
@@ -3,13 +3,20 @@
 //! This module provides functionality to validate ForgeKit projects,
 //! including configuration files, directory structure, and dependencies.
 
-use crate::config::ProjectConfig;
+use crate::appmeta::AppMeta;
+use crate::config::{ProjectConfig, Strictness};
+use crate::diagnostics::{Diagnostic, Severity};
 use crate::error::ForgeKitError;
-use std::path::Path;
+use crate::license_header::LicenseHeader;
+use crate::overrides::{self, Overrides};
+use crate::permissions;
+use crate::release_profile;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
 /// Validation report containing results of project validation
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ValidationReport {
     /// Whether the project is valid
     pub is_valid: bool,
@@ -39,6 +46,17 @@ impl ValidationReport {
     pub fn add_warning(&mut self, warning: String) {
         self.warnings.push(warning);
     }
+
+    /// Convert the report into diagnostics
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let errors = self.errors.iter().map(|m| (Severity::Error, m));
+        let warnings = self.warnings.iter().map(|m| (Severity::Warning, m));
+
+        errors
+            .chain(warnings)
+            .map(|(severity, message)| Diagnostic::new("validator", severity, message.clone()))
+            .collect()
+    }
 }
 
 impl Default for ValidationReport {
@@ -60,24 +78,47 @@ impl ProjectValidator {
     /// # Returns
     ///
     /// A `ValidationReport` containing validation results
+    ///
+    /// Validation scans the project's files, so it runs on a blocking thread
+    /// to keep the runtime responsive.
     pub async fn validate_project(path: &Path) -> Result<ValidationReport, ForgeKitError> {
+        Self::validate_project_with(path, Strictness::Warn).await
+    }
+
+    /// Validate a project, reporting unknown forgekit.toml keys as errors
+    /// rather than warnings under [`Strictness::Deny`]
+    pub async fn validate_project_with(
+        path: &Path,
+        strictness: Strictness,
+    ) -> Result<ValidationReport, ForgeKitError> {
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || Self::validate_blocking(&path, strictness))
+            .await
+            .map_err(std::io::Error::from)?
+    }
+
+    fn validate_blocking(
+        path: &Path,
+        strictness: Strictness,
+    ) -> Result<ValidationReport, ForgeKitError> {
         let mut report = ValidationReport::new();
 
         // Validate configuration file
-        Self::validate_config(path, &mut report).await?;
+        Self::validate_config(path, strictness, &mut report)?;
 
         // Validate directory structure
         Self::validate_structure(path, &mut report)?;
 
         // Validate dependencies
-        Self::validate_dependencies(path, &mut report).await?;
+        Self::validate_dependencies(path, &mut report)?;
 
         Ok(report)
     }
 
     /// Validate the forgekit.toml configuration file
-    async fn validate_config(
+    fn validate_config(
         path: &Path,
+        strictness: Strictness,
         report: &mut ValidationReport,
     ) -> Result<(), ForgeKitError> {
         let config_path = path.join("forgekit.toml");
@@ -87,8 +128,15 @@ impl ProjectValidator {
             return Ok(());
         }
 
-        match ProjectConfig::load(&config_path) {
-            Ok(config) => {
+        match ProjectConfig::load_reporting(&config_path) {
+            Ok((config, unknown)) => {
+                for key in unknown {
+                    match strictness {
+                        Strictness::Warn => report.add_warning(key),
+                        Strictness::Deny => report.add_error(key),
+                    }
+                }
+
                 // Validate required fields
                 if config.name.is_empty() {
                     report.add_error("Project name is required in forgekit.toml".to_string());
@@ -96,6 +144,52 @@ impl ProjectValidator {
                 if config.version.is_empty() {
                     report.add_error("Project version is required in forgekit.toml".to_string());
                 }
+
+                // Validate [patch] and [replace] against the dependency graph
+                match Overrides::from_config(&config) {
+                    Ok(overrides) => {
+                        let graph = overrides::resolve_graph(path, &config, &overrides);
+                        let checked = overrides.check(path, &graph);
+                        checked.errors.into_iter().for_each(|e| report.add_error(e));
+                        checked
+                            .warnings
+                            .into_iter()
+                            .for_each(|w| report.add_warning(w));
+                    }
+                    Err(e) => report.add_error(e.to_string()),
+                }
+
+                // Icons, launcher entry and splash must be generated from [appmeta]
+                AppMeta::validate(path, &config)
+                    .into_iter()
+                    .for_each(|e| report.add_error(e));
+
+                // Source files must start with the [license] header
+                match LicenseHeader::from_config(path, &config).and_then(|header| match header {
+                    Some(header) => header.missing(path),
+                    None => Ok(Vec::new()),
+                }) {
+                    Ok(missing) => {
+                        for file in missing {
+                            report.add_error(format!(
+                                "{} is missing the license header; run `forgekit validate --fix`",
+                                file.strip_prefix(path).unwrap_or(&file).display()
+                            ));
+                        }
+                    }
+                    Err(e) => report.add_error(e.to_string()),
+                }
+
+                // Linker and code generation settings must work together
+                release_profile::check(&config.build)
+                    .into_iter()
+                    .for_each(|e| report.add_error(e));
+
+                // Cross-check [permissions] against what the code appears to use;
+                // an unreadable Cargo.toml is reported by validate_dependencies
+                if let Ok(warnings) = permissions::check(path, &config.permissions) {
+                    warnings.into_iter().for_each(|w| report.add_warning(w));
+                }
             }
             Err(e) => {
                 report.add_error(format!("Invalid forgekit.toml: {}", e));
@@ -123,7 +217,7 @@ impl ProjectValidator {
     }
 
     /// Validate project dependencies
-    async fn validate_dependencies(
+    fn validate_dependencies(
         path: &Path,
         report: &mut ValidationReport,
     ) -> Result<(), ForgeKitError> {
@@ -149,6 +243,17 @@ impl ProjectValidator {
         Ok(())
     }
 
+    /// Apply the fixes validation knows about, returning the changed files
+    ///
+    /// This adds the `[license]` header to source files that lack it.
+    pub fn fix(path: &Path) -> Result<Vec<PathBuf>, ForgeKitError> {
+        let config = ProjectConfig::load(path.join("forgekit.toml"))?;
+        match LicenseHeader::from_config(path, &config)? {
+            Some(header) => header.apply(path),
+            None => Ok(Vec::new()),
+        }
+    }
+
     /// Validate only the configuration
     pub async fn validate_config_only(config: &ProjectConfig) -> Result<(), ForgeKitError> {
         if config.name.is_empty() {
@@ -2,34 +2,88 @@
 //!
 //! This module provides semantic versioning and release management.
 
+use crate::api_surface::ApiDiff;
+use crate::atomic;
+use crate::dry_run::{DryRun, PlannedAction};
 use crate::error::ForgeKitError;
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::fmt;
 use std::path::Path;
 
 /// Version bump type
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum BumpType {
     Major,
     Minor,
     Patch,
 }
 
+impl fmt::Display for BumpType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BumpType::Major => write!(f, "major"),
+            BumpType::Minor => write!(f, "minor"),
+            BumpType::Patch => write!(f, "patch"),
+        }
+    }
+}
+
 /// Version manager
 pub struct VersionManager;
 
 impl VersionManager {
-    /// Bump the version
+    /// Bump the version in Cargo.toml, returning the new version
     pub async fn bump_version(path: &Path, bump_type: BumpType) -> Result<String, ForgeKitError> {
-        if !path.join("Cargo.toml").exists() {
+        Self::bump_version_with(path, bump_type, &DryRun::disabled()).await
+    }
+
+    /// Bump the version, recording the change on `dry_run` instead of
+    /// making it when it is enabled
+    pub async fn bump_version_with(
+        path: &Path,
+        bump_type: BumpType,
+        dry_run: &DryRun,
+    ) -> Result<String, ForgeKitError> {
+        let cargo_toml = path.join("Cargo.toml");
+        if !cargo_toml.exists() {
             return Err(ForgeKitError::ProjectNotFound(
                 "Cargo.toml not found".to_string(),
             ));
         }
 
+        let mut manifest: toml_edit::DocumentMut = std::fs::read_to_string(&cargo_toml)?
+            .parse()
+            .map_err(|e| ForgeKitError::InvalidConfig(format!("Invalid Cargo.toml: {}", e)))?;
+        let current = manifest
+            .get("package")
+            .and_then(|p| p.get("version"))
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| {
+                ForgeKitError::InvalidConfig(
+                    "Cargo.toml has no [package] version to bump".to_string(),
+                )
+            })?
+            .to_string();
+        let [major, minor, patch] = numeric_parts(&current).ok_or_else(|| {
+            ForgeKitError::InvalidConfig(format!("Cannot bump version '{}'", current))
+        })?;
+
         let new_version = match bump_type {
-            BumpType::Major => "1.0.0".to_string(),
-            BumpType::Minor => "0.1.0".to_string(),
-            BumpType::Patch => "0.0.1".to_string(),
+            BumpType::Major => format!("{}.0.0", major + 1),
+            BumpType::Minor => format!("{}.{}.0", major, minor + 1),
+            BumpType::Patch => format!("{}.{}.{}", major, minor, patch + 1),
         };
+        if dry_run.is_enabled() {
+            dry_run.record(PlannedAction::UpdateFile {
+                path: cargo_toml,
+                change: format!("set version from {} to {}", current, new_version),
+            });
+        } else {
+            manifest["package"]["version"] = toml_edit::value(new_version.as_str());
+            atomic::write(&cargo_toml, manifest.to_string())?;
+        }
 
         Ok(new_version)
     }
@@ -45,6 +99,36 @@ impl VersionManager {
         Ok("# Changelog\n\n## [Unreleased]\n".to_string())
     }
 
+    /// Smallest bump from the `previous` release that keeps the API changes
+    /// in `diff` semver-compatible
+    ///
+    /// Before 1.0.0 the minor version is the breaking one, as in Cargo.
+    /// `previous` is a version like `v1.4.0`; see [`is_version`] for git
+    /// revisions that may not be one.
+    pub fn suggest_bump(previous: &str, diff: &ApiDiff) -> BumpType {
+        let unstable = numeric_parts(previous).is_some_and(|[major, ..]| major == 0);
+        match (diff.is_breaking(), diff.has_additions(), unstable) {
+            (true, _, false) => BumpType::Major,
+            (true, _, true) | (false, true, false) => BumpType::Minor,
+            _ => BumpType::Patch,
+        }
+    }
+
+    /// Release notes of `version`, from the project's changelog
+    ///
+    /// Returns `None` when the project has no changelog or the changelog has
+    /// no section for the version.
+    pub fn notes_for(project_path: &Path, version: &str) -> Result<Option<String>, ForgeKitError> {
+        for name in CHANGELOG_FILES {
+            match std::fs::read_to_string(project_path.join(name)) {
+                Ok(changelog) => return Ok(changelog_section(&changelog, version)),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(None)
+    }
+
     /// Tag a release
     pub async fn tag_release(version: &str) -> Result<(), ForgeKitError> {
         tracing::info!("Tagging release: {}", version);
@@ -52,14 +136,326 @@ impl VersionManager {
     }
 }
 
+/// Changelog file names, in the order they are looked for
+const CHANGELOG_FILES: &[&str] = &["CHANGELOG.md", "CHANGELOG", "CHANGES.md"];
+
+/// Body of the changelog section for `version`
+///
+/// Sections start with a heading naming the version, as in Keep a Changelog
+/// (`## [1.2.0] - 2024-05-01`) or plainly (`## v1.2.0`), and end at the next
+/// heading of the same or a higher level.
+pub fn changelog_section(changelog: &str, version: &str) -> Option<String> {
+    let version = version.trim_start_matches('v');
+    fn heading(line: &str) -> Option<(usize, &str)> {
+        let level = line.chars().take_while(|&c| c == '#').count();
+        (level > 0 && line[level..].starts_with(' ')).then(|| (level, line[level..].trim()))
+    }
+
+    let mut lines = changelog.lines();
+    let level = lines.by_ref().find_map(|line| {
+        let (level, title) = heading(line)?;
+        let named = title
+            .trim_start_matches('[')
+            .split(|c: char| c == ']' || c.is_whitespace())
+            .next()?
+            .trim_start_matches('v');
+        (named == version).then_some(level)
+    })?;
+    let body: Vec<&str> = lines
+        .take_while(|line| heading(line).is_none_or(|(other, _)| other > level))
+        .collect();
+    let body = body.join("\n").trim().to_string();
+    (!body.is_empty()).then_some(body)
+}
+
+/// Compare two dotted version strings numerically
+///
+/// Missing components count as zero and non-numeric components (such as
+/// pre-release tags) are compared as text.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let parts = |v: &str| {
+        v.trim_start_matches('v')
+            .split(['.', '-', '+'])
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+    let (a, b) = (parts(a), parts(b));
+
+    for i in 0..a.len().max(b.len()) {
+        let x = a.get(i).map(String::as_str).unwrap_or("0");
+        let y = b.get(i).map(String::as_str).unwrap_or("0");
+        let ordering = match (x.parse::<u64>(), y.parse::<u64>()) {
+            (Ok(x), Ok(y)) => x.cmp(&y),
+            _ => x.cmp(y),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    Ordering::Equal
+}
+
+/// Comparison operator in a version requirement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Exact,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+    Tilde,
+    Caret,
+}
+
+/// A Cargo-style version requirement such as `^1.2`, `~0.3.1` or `>=1, <2`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionReq {
+    comparators: Vec<(Op, Vec<u64>)>,
+}
+
+impl VersionReq {
+    /// Parse a requirement; bare versions are caret requirements and `*` matches anything
+    pub fn parse(req: &str) -> Result<Self, ForgeKitError> {
+        let invalid =
+            || ForgeKitError::InvalidConfig(format!("invalid version requirement '{}'", req));
+        let mut comparators = Vec::new();
+
+        for part in req.split(',').map(str::trim) {
+            if part == "*" {
+                continue;
+            }
+            if part.is_empty() {
+                return Err(invalid());
+            }
+
+            let (op, rest) = [
+                (">=", Op::GreaterEq),
+                ("<=", Op::LessEq),
+                (">", Op::Greater),
+                ("<", Op::Less),
+                ("=", Op::Exact),
+                ("~", Op::Tilde),
+                ("^", Op::Caret),
+            ]
+            .iter()
+            .find_map(|(prefix, op)| part.strip_prefix(prefix).map(|rest| (*op, rest.trim())))
+            .unwrap_or((Op::Caret, part));
+
+            // `1.*` and `1.2.*` behave like `~1` and `~1.2`
+            let (op, rest) = match rest.strip_suffix(".*") {
+                Some(rest) if op == Op::Caret => (Op::Tilde, rest),
+                Some(_) => return Err(invalid()),
+                None => (op, rest),
+            };
+
+            let numbers = rest.split(['-', '+']).next().unwrap_or_default();
+            let parts = numbers
+                .split('.')
+                .map(|n| n.parse::<u64>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| invalid())?;
+            if parts.is_empty() || parts.len() > 3 {
+                return Err(invalid());
+            }
+
+            comparators.push((op, parts));
+        }
+
+        Ok(Self { comparators })
+    }
+
+    /// Whether a version satisfies every comparator of the requirement
+    pub fn matches(&self, version: &str) -> bool {
+        let Some(version) = numeric_parts(version) else {
+            return false;
+        };
+
+        self.comparators.iter().all(|(op, parts)| {
+            let lower = padded(parts);
+            match op {
+                Op::Exact => version[..parts.len()] == parts[..],
+                Op::Greater => version > lower,
+                Op::GreaterEq => version >= lower,
+                Op::Less => version < lower,
+                Op::LessEq => version <= lower,
+                // A bound past the largest number leaves the range open above
+                Op::Tilde => {
+                    let upper = match parts.len() {
+                        1 => parts[0].checked_add(1).map(|major| vec![major]),
+                        _ => parts[1].checked_add(1).map(|minor| vec![parts[0], minor]),
+                    };
+                    version >= lower && upper.is_none_or(|upper| version < padded(&upper))
+                }
+                Op::Caret => {
+                    let upper = match parts.as_slice() {
+                        [major] => major.checked_add(1).map(|major| vec![major]),
+                        [0, minor] => minor.checked_add(1).map(|minor| vec![0, minor]),
+                        [0, 0, patch] => patch.checked_add(1).map(|patch| vec![0, 0, patch]),
+                        [0, minor, _] => minor.checked_add(1).map(|minor| vec![0, minor]),
+                        [major, ..] => major.checked_add(1).map(|major| vec![major]),
+                        [] => unreachable!("requirements have at least one component"),
+                    };
+                    version >= lower && upper.is_none_or(|upper| version < padded(&upper))
+                }
+            }
+        })
+    }
+}
+
+/// Whether `revision`, like a release tag, names a version such as `v1.4.0`
+pub fn is_version(revision: &str) -> bool {
+    numeric_parts(revision).is_some()
+}
+
+/// Major, minor and patch numbers of a version, ignoring pre-release and build tags
+fn numeric_parts(version: &str) -> Option<[u64; 3]> {
+    let numbers = version
+        .trim_start_matches('v')
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default();
+    let parts = numbers
+        .split('.')
+        .map(|n| n.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    (parts.len() <= 3).then(|| padded(&parts))
+}
+
+fn padded(parts: &[u64]) -> [u64; 3] {
+    let mut padded = [0; 3];
+    padded[..parts.len()].copy_from_slice(parts);
+    padded
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_compare_versions() {
+        assert_eq!(compare_versions("0.2.0", "0.10.0"), Ordering::Less);
+        assert_eq!(compare_versions("1.0", "1.0.0"), Ordering::Equal);
+        assert_eq!(compare_versions("v2.0.0", "1.9.9"), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_version_req() {
+        let req = VersionReq::parse("^1.2").unwrap();
+        assert!(req.matches("1.9.0"));
+        assert!(!req.matches("2.0.0"));
+        assert!(VersionReq::parse("0.3").unwrap().matches("0.3.7"));
+        assert!(!VersionReq::parse("0.3").unwrap().matches("0.4.0"));
+        assert!(VersionReq::parse(">=1, <2").unwrap().matches("1.5.0"));
+        assert!(VersionReq::parse("*").unwrap().matches("42.0.0"));
+        assert!(VersionReq::parse("~1.2.3").unwrap().matches("1.2.9"));
+        assert!(!VersionReq::parse("=1.2.3").unwrap().matches("1.2.4"));
+
+        // Bounds past the largest number do not overflow
+        let max = u64::MAX;
+        assert!(VersionReq::parse(&format!("^{}", max))
+            .unwrap()
+            .matches(&format!("{}.1.0", max)));
+        assert!(VersionReq::parse(&format!("~1.{}", max))
+            .unwrap()
+            .matches(&format!("1.{}.3", max)));
+        assert!(VersionReq::parse(&format!("^0.0.{}", max))
+            .unwrap()
+            .matches(&format!("0.0.{}", max)));
+
+        assert!(VersionReq::parse("latest").is_err());
+        assert!(VersionReq::parse(">=1,").is_err());
+    }
+
     #[test]
     fn test_bump_type() {
         let _major = BumpType::Major;
         let _minor = BumpType::Minor;
         let _patch = BumpType::Patch;
     }
+
+    #[tokio::test]
+    async fn test_bump_version_updates_cargo_toml() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let cargo_toml = temp_dir.path().join("Cargo.toml");
+        std::fs::write(
+            &cargo_toml,
+            "[package]\nname = \"demo\" # the app\nversion = \"1.4.2\"\n",
+        )
+        .unwrap();
+
+        let dry_run = DryRun::enabled();
+        let version = VersionManager::bump_version_with(temp_dir.path(), BumpType::Minor, &dry_run)
+            .await
+            .unwrap();
+        assert_eq!(version, "1.5.0");
+        assert!(std::fs::read_to_string(&cargo_toml)
+            .unwrap()
+            .contains("version = \"1.4.2\""));
+        assert_eq!(dry_run.plan().actions.len(), 1);
+
+        let version = VersionManager::bump_version(temp_dir.path(), BumpType::Patch)
+            .await
+            .unwrap();
+        assert_eq!(version, "1.4.3");
+        let manifest = std::fs::read_to_string(&cargo_toml).unwrap();
+        assert!(manifest.contains("version = \"1.4.3\""));
+        assert!(manifest.contains("# the app"));
+    }
+
+    #[test]
+    fn test_changelog_section() {
+        let changelog = "# Changelog\n\n## [Unreleased]\n\n- Wip\n\n## [1.2.0] - 2024-05-01\n\n### Added\n\n- Offline mode\n\n## v1.1.0\n\n- Fixes\n";
+        assert_eq!(
+            changelog_section(changelog, "1.2.0").as_deref(),
+            Some("### Added\n\n- Offline mode")
+        );
+        assert_eq!(
+            changelog_section(changelog, "v1.1.0").as_deref(),
+            Some("- Fixes")
+        );
+        assert_eq!(changelog_section(changelog, "1.0.0"), None);
+        assert_eq!(changelog_section(changelog, "1.2"), None);
+    }
+
+    #[test]
+    fn test_suggest_bump() {
+        use crate::api_surface::{ApiChange, ApiItem, ItemKind};
+
+        let item = ApiItem {
+            path: "crate::open".to_string(),
+            kind: ItemKind::Function,
+            signature: "fn open ()".to_string(),
+            documented: true,
+            file: "src/lib.rs".into(),
+        };
+        let added = ApiDiff {
+            changes: vec![ApiChange::Added { item: item.clone() }],
+        };
+        let removed = ApiDiff {
+            changes: vec![ApiChange::Removed { item }],
+        };
+        assert_eq!(
+            VersionManager::suggest_bump("v1.4.0", &removed),
+            BumpType::Major
+        );
+        assert_eq!(
+            VersionManager::suggest_bump("1.4.0", &added),
+            BumpType::Minor
+        );
+        assert_eq!(
+            VersionManager::suggest_bump("0.4.0", &removed),
+            BumpType::Minor
+        );
+        assert_eq!(
+            VersionManager::suggest_bump("0.4.0", &added),
+            BumpType::Patch
+        );
+        assert_eq!(
+            VersionManager::suggest_bump("1.4.0", &ApiDiff::default()),
+            BumpType::Patch
+        );
+        assert!(is_version("v1.4.0"));
+        assert!(!is_version("HEAD~3"));
+    }
 }
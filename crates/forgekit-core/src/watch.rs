@@ -0,0 +1,407 @@
+//! Build watch module
+//!
+//! This module provides `forgekit build --watch`: the project is rebuilt
+//! whenever a source, asset, layout or manifest changes, and optionally
+//! packaged after every successful build. Unlike the dev server it serves
+//! nothing; it only keeps build artifacts up to date. Changes limited to
+//! `assets/` and `ui/` can be hot-swapped instead: the changed layouts are
+//! recompiled and cargo does not run at all.
+
+use crate::builder::{self, BuildOptions};
+use crate::error::ForgeKitError;
+use crate::packager;
+use crate::ui;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Directories and files whose changes trigger a rebuild
+const WATCHED: &[&str] = &[
+    "src",
+    "assets",
+    "ui",
+    "locales",
+    "build.rs",
+    "Cargo.toml",
+    "forgekit.toml",
+];
+
+/// Directories whose changes are applied without cargo when hot-swapping
+pub const HOT_SWAPPABLE: &[&str] = &["assets", "ui"];
+
+/// Options for a build watch
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+    /// Options passed to every build
+    pub build: BuildOptions,
+    /// Package the project after each successful build
+    pub package: bool,
+    /// How often the project is checked for changes
+    pub interval: Duration,
+    /// Hot-swap changes limited to assets and layouts instead of building
+    pub hot_swap: bool,
+}
+
+impl WatchOptions {
+    /// Create options that only build, checking for changes every 500ms
+    pub fn new() -> Self {
+        Self {
+            build: BuildOptions::default(),
+            package: false,
+            interval: Duration::from_millis(500),
+            hot_swap: false,
+        }
+    }
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Outcome of one watch iteration
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchStatus {
+    /// Number of builds run so far
+    pub builds: usize,
+    /// Whether the last build succeeded
+    pub success: bool,
+    /// Errors reported by the last build
+    pub errors: usize,
+    /// Warnings reported by the last build
+    pub warnings: usize,
+    /// Duration of the last build
+    pub duration: Duration,
+    /// Package created after the last build, if any
+    pub package: Option<PathBuf>,
+    /// Files that triggered the build
+    pub changed: Vec<PathBuf>,
+    /// Error message of the last build or packaging step
+    pub message: Option<String>,
+    /// Files hot-swapped instead of building, as the app loads them;
+    /// `None` when cargo ran
+    pub swapped: Option<Vec<PathBuf>>,
+}
+
+impl WatchStatus {
+    /// One-line status for a terminal
+    pub fn status_line(&self) -> String {
+        let state = if self.success { "ok" } else { "failed" };
+        if let Some(swapped) = &self.swapped {
+            return format!(
+                "hot swap #{} {} in {}ms | {} file(s)",
+                self.builds,
+                state,
+                self.duration.as_millis(),
+                swapped.len()
+            );
+        }
+        let mut line = format!(
+            "build #{} {} in {:.1}s | {} error(s), {} warning(s)",
+            self.builds,
+            state,
+            self.duration.as_secs_f64(),
+            self.errors,
+            self.warnings
+        );
+        if let Some(package) = &self.package {
+            line.push_str(&format!(" | packaged {}", package.display()));
+        }
+        line
+    }
+}
+
+/// Modification times of the files under some of a project's entries
+#[derive(Debug, Default)]
+pub struct FileSnapshot {
+    files: HashMap<PathBuf, SystemTime>,
+}
+
+impl FileSnapshot {
+    /// Files under the `entries` of `root` created, modified or removed
+    /// since the last call
+    pub fn changes(&mut self, root: &Path, entries: &[&str]) -> Vec<PathBuf> {
+        let mut current = HashMap::new();
+        for entry in entries {
+            for file in walkdir::WalkDir::new(root.join(entry))
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file())
+            {
+                if let Ok(modified) = file
+                    .metadata()
+                    .map_err(std::io::Error::from)
+                    .and_then(|m| m.modified())
+                {
+                    current.insert(file.into_path(), modified);
+                }
+            }
+        }
+
+        let mut changed: Vec<PathBuf> = current
+            .iter()
+            .filter(|(path, modified)| self.files.get(*path) != Some(modified))
+            .map(|(path, _)| path.clone())
+            .chain(
+                self.files
+                    .keys()
+                    .filter(|path| !current.contains_key(*path))
+                    .cloned(),
+            )
+            .collect();
+        changed.sort();
+
+        self.files = current;
+        changed
+    }
+}
+
+/// Whether every changed file lies under `assets/` or `ui/`, so the change
+/// can be hot-swapped
+pub fn hot_swappable(project_path: &Path, changed: &[PathBuf]) -> bool {
+    !changed.is_empty()
+        && changed.iter().all(|file| {
+            HOT_SWAPPABLE
+                .iter()
+                .any(|dir| file.starts_with(project_path.join(dir)))
+        })
+}
+
+/// Re-run the asset pipeline for changed assets and layouts
+///
+/// Layouts are recompiled and assets are used as they are. Returns the
+/// changed files as the app loads them (the asset, or the compiled layout)
+/// together with the errors of layouts that failed to compile; those keep
+/// their previous compiled output. Removed layouts are skipped.
+pub fn hot_swap(project_path: &Path, changed: &[PathBuf]) -> (Vec<PathBuf>, Vec<String>) {
+    let ui_dir = project_path.join("ui");
+    let mut swapped = Vec::new();
+    let mut errors = Vec::new();
+    for file in changed {
+        if !file.starts_with(&ui_dir) {
+            swapped.push(file.clone());
+        } else if file.is_file() && file.extension().is_some_and(|ext| ext == "xml") {
+            match ui::compile_layout(project_path, file) {
+                Ok(layout) => swapped.push(layout.output),
+                Err(e) => errors.push(e.to_string()),
+            }
+        }
+    }
+    (swapped, errors)
+}
+
+/// Rebuilds a project whenever its files change
+pub struct BuildWatcher {
+    project_path: PathBuf,
+    options: WatchOptions,
+    snapshot: FileSnapshot,
+    builds: usize,
+}
+
+impl BuildWatcher {
+    /// Create a watcher for a project
+    pub fn new(project_path: &Path, options: WatchOptions) -> Self {
+        Self {
+            project_path: project_path.to_path_buf(),
+            options,
+            snapshot: FileSnapshot::default(),
+            builds: 0,
+        }
+    }
+
+    /// Watch until the build options' cancellation token is triggered,
+    /// reporting every build
+    ///
+    /// The project is built once on start. Changes arriving while a build
+    /// runs trigger one more build afterwards. A build interrupted by
+    /// cancellation is not reported.
+    pub async fn run<F>(self, on_build: F) -> Result<(), ForgeKitError>
+    where
+        F: FnMut(&WatchStatus),
+    {
+        self.run_with_start(|_| {}, on_build).await
+    }
+
+    /// Watch like [`run`](Self::run), also calling `on_start` with the
+    /// changed files before each build
+    pub async fn run_with_start<S, F>(
+        mut self,
+        mut on_start: S,
+        mut on_build: F,
+    ) -> Result<(), ForgeKitError>
+    where
+        S: FnMut(&[PathBuf]),
+        F: FnMut(&WatchStatus),
+    {
+        let cancel = self.options.build.cancel.clone();
+        let mut changed = self.changed_files();
+        loop {
+            on_start(&changed);
+            let status = self.build_once(changed).await;
+            if cancel.is_cancelled() {
+                return Ok(());
+            }
+            on_build(&status);
+
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep(self.options.interval) => {}
+                    _ = cancel.cancelled() => return Ok(()),
+                }
+                changed = self.changed_files();
+                if !changed.is_empty() {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Files created, modified or removed since the last call
+    pub fn changed_files(&mut self) -> Vec<PathBuf> {
+        self.snapshot.changes(&self.project_path, WATCHED)
+    }
+
+    /// Build, and package if requested, once
+    async fn build_once(&mut self, changed: Vec<PathBuf>) -> WatchStatus {
+        self.builds += 1;
+        let start = Instant::now();
+        let mut status = WatchStatus {
+            builds: self.builds,
+            success: false,
+            errors: 0,
+            warnings: 0,
+            duration: Duration::ZERO,
+            package: None,
+            changed,
+            message: None,
+            swapped: None,
+        };
+
+        // The first build always runs cargo: nothing is built yet
+        if self.options.hot_swap
+            && self.builds > 1
+            && hot_swappable(&self.project_path, &status.changed)
+        {
+            let (swapped, errors) = hot_swap(&self.project_path, &status.changed);
+            status.success = errors.is_empty();
+            status.errors = errors.len();
+            status.message = (!errors.is_empty()).then(|| errors.join("; "));
+            status.swapped = Some(swapped);
+            status.duration = start.elapsed();
+            return status;
+        }
+
+        match builder::build_with_options(&self.project_path, &self.options.build).await {
+            Ok(summary) => {
+                status.success = true;
+                status.warnings = summary.warnings;
+            }
+            Err(e) => {
+                if let ForgeKitError::BuildFailed(stderr) = &e {
+                    (status.errors, status.warnings) = builder::count_diagnostics(stderr);
+                }
+                status.errors = status.errors.max(1);
+                status.message = Some(e.to_string());
+            }
+        }
+        status.duration = start.elapsed();
+
+        if status.success && self.options.package {
+            match packager::package_with_cancel(&self.project_path, &self.options.build.cancel)
+                .await
+            {
+                Ok(package) => status.package = Some(package),
+                Err(e) => {
+                    status.success = false;
+                    status.message = Some(e.to_string());
+                }
+            }
+        }
+
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_changed_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path();
+        std::fs::create_dir_all(project.join("src")).unwrap();
+        std::fs::create_dir_all(project.join("target")).unwrap();
+        std::fs::write(project.join("src").join("main.rs"), "fn main() {}").unwrap();
+        std::fs::write(project.join("target").join("out"), "").unwrap();
+
+        let mut watcher = BuildWatcher::new(project, WatchOptions::default());
+        assert_eq!(
+            watcher.changed_files(),
+            vec![project.join("src").join("main.rs")]
+        );
+        assert!(watcher.changed_files().is_empty());
+
+        std::fs::write(project.join("forgekit.toml"), "").unwrap();
+        std::fs::remove_file(project.join("src").join("main.rs")).unwrap();
+        assert_eq!(watcher.changed_files().len(), 2);
+    }
+
+    #[test]
+    fn test_hot_swap_recompiles_changed_layouts() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path();
+        std::fs::create_dir_all(project.join("ui")).unwrap();
+        std::fs::create_dir_all(project.join("assets")).unwrap();
+        std::fs::write(project.join("ui").join("main.xml"), "<window />").unwrap();
+        std::fs::write(project.join("ui").join("broken.xml"), "<window>").unwrap();
+        std::fs::write(project.join("assets").join("app.css"), "").unwrap();
+
+        let changed = vec![
+            project.join("assets").join("app.css"),
+            project.join("ui").join("broken.xml"),
+            project.join("ui").join("main.xml"),
+        ];
+        assert!(hot_swappable(project, &changed));
+        assert!(!hot_swappable(
+            project,
+            &[project.join("src").join("main.rs")]
+        ));
+
+        let (swapped, errors) = hot_swap(project, &changed);
+        assert_eq!(
+            swapped,
+            vec![
+                project.join("assets").join("app.css"),
+                ui::compiled_dir(project).join("main.mxui"),
+            ]
+        );
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_count_diagnostics_and_status_line() {
+        let stderr = "warning: unused variable: `x`\nerror[E0308]: mismatched types\nerror: expected `;`\nwarning: `demo` (bin \"demo\") generated 1 warning\nerror: could not compile `demo`\n";
+        let (errors, warnings) = builder::count_diagnostics(stderr);
+        assert_eq!((errors, warnings), (2, 1));
+
+        let status = WatchStatus {
+            builds: 3,
+            success: false,
+            errors,
+            warnings,
+            duration: Duration::from_millis(1500),
+            package: None,
+            changed: Vec::new(),
+            message: None,
+            swapped: None,
+        };
+        assert_eq!(
+            status.status_line(),
+            "build #3 failed in 1.5s | 2 error(s), 1 warning(s)"
+        );
+    }
+}
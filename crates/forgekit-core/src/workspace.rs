@@ -0,0 +1,147 @@
+//! Workspace discovery module
+//!
+//! This module finds the member projects of a multi-crate workspace by
+//! reading the `[workspace] members` list of the root Cargo.toml.
+
+use crate::config::ProjectConfig;
+use crate::error::ForgeKitError;
+use std::path::{Path, PathBuf};
+
+/// A member project of a workspace
+#[derive(Debug, Clone)]
+pub struct WorkspaceMember {
+    /// Project name from forgekit.toml
+    pub name: String,
+    /// Path to the member directory
+    pub path: PathBuf,
+    /// Member project configuration
+    pub config: ProjectConfig,
+}
+
+/// A multi-crate workspace
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    /// Workspace root directory
+    pub root: PathBuf,
+    /// Members that are ForgeKit projects
+    pub members: Vec<WorkspaceMember>,
+}
+
+impl Workspace {
+    /// Load the workspace rooted at the given path
+    ///
+    /// Members without a forgekit.toml are skipped.
+    pub fn load(root: &Path) -> Result<Self, ForgeKitError> {
+        let mut members = Vec::new();
+
+        for path in Self::member_paths(root)? {
+            let config_path = path.join("forgekit.toml");
+            if !config_path.exists() {
+                continue;
+            }
+
+            let config = ProjectConfig::load(&config_path)?;
+            members.push(WorkspaceMember {
+                name: config.name.clone(),
+                path,
+                config,
+            });
+        }
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            members,
+        })
+    }
+
+    /// Resolve the member directories listed in the root Cargo.toml
+    ///
+    /// Supports plain paths and trailing `*` globs such as `crates/*`.
+    pub fn member_paths(root: &Path) -> Result<Vec<PathBuf>, ForgeKitError> {
+        let cargo_toml = root.join("Cargo.toml");
+        if !cargo_toml.exists() {
+            return Err(ForgeKitError::ProjectNotFound(
+                "Cargo.toml not found".to_string(),
+            ));
+        }
+
+        let manifest: toml::Value = toml::from_str(&std::fs::read_to_string(&cargo_toml)?)?;
+        let patterns = manifest
+            .get("workspace")
+            .and_then(|w| w.get("members"))
+            .and_then(|m| m.as_array())
+            .ok_or_else(|| {
+                ForgeKitError::InvalidConfig("Cargo.toml has no [workspace] members".to_string())
+            })?;
+
+        let mut paths = Vec::new();
+        for pattern in patterns.iter().filter_map(|p| p.as_str()) {
+            match pattern.strip_suffix("/*") {
+                Some(dir) => {
+                    let mut entries: Vec<PathBuf> = std::fs::read_dir(root.join(dir))?
+                        .flatten()
+                        .map(|e| e.path())
+                        .filter(|p| p.join("Cargo.toml").exists())
+                        .collect();
+                    entries.sort();
+                    paths.extend(entries);
+                }
+                None => paths.push(root.join(pattern)),
+            }
+        }
+
+        Ok(paths)
+    }
+
+    /// Find a member by name
+    pub fn member(&self, name: &str) -> Option<&WorkspaceMember> {
+        self.members.iter().find(|m| m.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_member(root: &Path, dir: &str, name: &str) {
+        let path = root.join(dir);
+        std::fs::create_dir_all(&path).unwrap();
+        std::fs::write(path.join("Cargo.toml"), "[package]\nname = \"x\"").unwrap();
+        let config = ProjectConfig {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        config.save(path.join("forgekit.toml")).unwrap();
+    }
+
+    #[test]
+    fn test_load_workspace_members() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::write(
+            root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"apps/*\", \"service\"]\n",
+        )
+        .unwrap();
+        write_member(root, "apps/gui", "gui");
+        write_member(root, "apps/cli", "cli");
+        write_member(root, "service", "service");
+
+        let workspace = Workspace::load(root).unwrap();
+        assert_eq!(workspace.members.len(), 3);
+        assert_eq!(workspace.members[0].name, "cli");
+        assert!(workspace.member("service").is_some());
+    }
+
+    #[test]
+    fn test_missing_workspace_section() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(
+            temp_dir.path().join("Cargo.toml"),
+            "[package]\nname = \"x\"",
+        )
+        .unwrap();
+        assert!(Workspace::load(temp_dir.path()).is_err());
+    }
+}